@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Benchmarks of descriptor parsing, address derivation, policy compilation,
+//! satisfaction and interpretation on Liquid-like scripts: the 11-of-15
+//! federation multisig and a covenant vault.
+//!
+//! Run with `cargo bench -p elements-miniscript-bench`.
+
+extern crate elements_miniscript as miniscript;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use elements::secp256k1_zkp::{self, Secp256k1};
+use elements::{AddressParams, EcdsaSigHashType, LockTime, Sequence};
+use miniscript::descriptor::XpubCache;
+use miniscript::{Descriptor, DescriptorPublicKey, ElementsSig, Interpreter};
+
+const XPUB: &str = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL";
+const XPUB2: &str = "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y";
+
+// 11-of-15 federation multisig with ranged keys
+fn federation() -> String {
+    let keys = (0..15)
+        .map(|i| format!("{}/{}/*", XPUB, i))
+        .collect::<Vec<_>>();
+    format!("elwsh(multi(11,{}))", keys.join(","))
+}
+
+// Vault whose key can only spend the full input value to the same script
+fn vault() -> String {
+    format!(
+        "eltr({}/0/*,and_v(v:pk({}/0/*),and_v(v:spk_eq(curr_inp_spk,out_spk(0)),value_eq(curr_inp_value,out_value(0)))))",
+        XPUB, XPUB2
+    )
+}
+
+// 11-of-15 federation multisig with 11 signatures
+fn signed_federation() -> (
+    Descriptor<bitcoin::PublicKey>,
+    HashMap<bitcoin::PublicKey, ElementsSig>,
+) {
+    let secp = Secp256k1::new();
+    let msg = secp256k1_zkp::Message::from_slice(&[1; 32]).unwrap();
+    let mut keys = vec![];
+    let mut sigs = HashMap::new();
+    for i in 1..=15 {
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[i; 32]).unwrap();
+        let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+        if i <= 11 {
+            sigs.insert(pk, (secp.sign_ecdsa(&msg, &sk), EcdsaSigHashType::All));
+        }
+        keys.push(pk.to_string());
+    }
+    let desc = Descriptor::from_str(&format!("elwsh(multi(11,{}))", keys.join(","))).unwrap();
+    (desc, sigs)
+}
+
+fn parse(c: &mut Criterion) {
+    let federation = federation();
+    c.bench_function("parse_federation", |b| {
+        b.iter(|| Descriptor::<DescriptorPublicKey>::from_str(black_box(&federation)).unwrap())
+    });
+    let vault = vault();
+    c.bench_function("parse_vault", |b| {
+        b.iter(|| Descriptor::<DescriptorPublicKey>::from_str(black_box(&vault)).unwrap())
+    });
+}
+
+fn derive_address(c: &mut Criterion) {
+    let secp = Secp256k1::verification_only();
+    let federation = Descriptor::<DescriptorPublicKey>::from_str(&federation()).unwrap();
+    c.bench_function("address_federation", |b| {
+        b.iter(|| {
+            let desc = federation.at_derivation_index(black_box(7)).unwrap();
+            desc.address(&AddressParams::LIQUID).unwrap()
+        })
+    });
+    c.bench_function("derive_federation", |b| {
+        b.iter(|| federation.derive_public(&secp, black_box(7)).unwrap())
+    });
+    let mut cache = XpubCache::new();
+    c.bench_function("derive_federation_cached", |b| {
+        b.iter(|| {
+            federation
+                .derive_public_cached(&secp, black_box(7), &mut cache)
+                .unwrap()
+        })
+    });
+    let vault = Descriptor::<DescriptorPublicKey>::from_str(&vault()).unwrap();
+    c.bench_function("address_vault", |b| {
+        b.iter(|| {
+            let desc = vault.at_derivation_index(black_box(7)).unwrap();
+            desc.address(&AddressParams::LIQUID).unwrap()
+        })
+    });
+}
+
+#[cfg(feature = "compiler")]
+fn compile(c: &mut Criterion) {
+    use miniscript::policy::Concrete;
+    use miniscript::Segwitv0;
+
+    // Federation with an emergency recovery after about four weeks
+    let members = (0..15).map(|i| format!("pk(F{})", i)).collect::<Vec<_>>();
+    let pol = Concrete::<String>::from_str(&format!(
+        "or(99@thresh(11,{}),1@and(thresh(2,pk(E0),pk(E1),pk(E2)),older(4032)))",
+        members.join(",")
+    ))
+    .unwrap();
+    c.bench_function("compile_federation", |b| {
+        b.iter(|| black_box(&pol).compile::<Segwitv0>().unwrap())
+    });
+}
+
+#[cfg(not(feature = "compiler"))]
+fn compile(_c: &mut Criterion) {}
+
+fn satisfy(c: &mut Criterion) {
+    let (desc, sigs) = signed_federation();
+    c.bench_function("satisfy_federation", |b| {
+        b.iter(|| black_box(&desc).get_satisfaction(&sigs).unwrap())
+    });
+}
+
+fn interpret(c: &mut Criterion) {
+    let (desc, sigs) = signed_federation();
+    let (witness, script_sig) = desc.get_satisfaction(&sigs).unwrap();
+    let spk = desc.script_pubkey();
+    c.bench_function("interpret_federation", |b| {
+        b.iter(|| {
+            let interpreter = Interpreter::from_txdata(
+                &spk,
+                &script_sig,
+                black_box(&witness),
+                Sequence::ZERO,
+                LockTime::ZERO,
+            )
+            .unwrap();
+            for sat in interpreter.iter_assume_sigs() {
+                sat.unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, parse, derive_address, compile, satisfy, interpret);
+criterion_main!(benches);
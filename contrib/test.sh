@@ -51,12 +51,14 @@ then
     cargo run --example xpub_descriptors
     cargo run --example taproot --features=compiler
     cargo run --example psbt_sign_finalize --features=base64
+    cargo run --example liquid_spend
 fi
 
 # Bench if told to (this only works with the nightly toolchain)
 if [ "$DO_BENCH" = true ]
 then
     RUSTFLAGS=--cfg=miniscript_bench cargo bench --features="compiler"
+    cargo bench -p elements-miniscript-bench
 fi
 
 # Build the docs if told to (this only works with the nightly toolchain)
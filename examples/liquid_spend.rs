@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Example: Spending a Liquid output with a PSET.
+//!
+//! Goes through every step of a confidential spend using descriptors only:
+//! the funding output is spent to a blinded output of another descriptor,
+//! then the PSET is blinded, signed, finalized and the extracted transaction
+//! is checked with the interpreter.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+use elements::hashes::Hash;
+use elements::pset::PartiallySignedTransaction as Pset;
+use elements::secp256k1_zkp::{self as secp256k1, Secp256k1};
+use elements::sighash::SigHashCache;
+use elements::{pset, AssetId, OutPoint, TxOut, TxOutSecrets, Txid};
+use miniscript::descriptor::DefiniteDescriptorKey;
+use miniscript::psbt::PsbtExt;
+use miniscript::{elementssig_to_rawsig, Descriptor, Interpreter, TxEnv};
+use {actual_rand as rand, elements_miniscript as miniscript};
+
+fn main() {
+    let secp = Secp256k1::new();
+    // Genesis hash is only used for taproot sighashes
+    let genesis_hash = elements::BlockHash::all_zeros();
+    let asset =
+        AssetId::from_str("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")
+            .unwrap();
+
+    // The key of the funding descriptor
+    let sk = secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+    let pk = bitcoin::PublicKey::new(secp256k1::PublicKey::from_secret_key(&secp, &sk));
+    let spender =
+        Descriptor::<DefiniteDescriptorKey>::from_str(&format!("elwpkh({})", pk)).unwrap();
+
+    // The receiver descriptor and the key to which its output is blinded
+    let receiver = Descriptor::<DefiniteDescriptorKey>::from_str(
+        "elwsh(multi(2,02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13,022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4))",
+    )
+    .unwrap();
+    let blinding_sk = secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+    let blinding_pk = secp256k1::PublicKey::from_secret_key(&secp, &blinding_sk);
+
+    // In practice the funding output and its outpoint come from the chain
+    let amount = 100_000;
+    let fee = 500;
+    let utxo = spender.txout(amount, asset, None);
+    let prevout = OutPoint::new(Txid::all_zeros(), 0);
+
+    // Build the PSET: one input, a blinded output and the fee
+    let mut pset = Pset::new_v2();
+    let index = pset
+        .add_descriptor_input(prevout, utxo.clone(), &spender)
+        .unwrap();
+    let mut output =
+        pset::Output::from_txout(receiver.txout(amount - fee, asset, Some(blinding_pk)));
+    output.blinder_index = Some(index as u32);
+    pset.add_output(output);
+    pset.add_output(pset::Output::from_txout(TxOut::new_fee(fee, asset)));
+
+    // Blind the receiver output, the funding output is explicit
+    let mut secrets = HashMap::new();
+    secrets.insert(
+        index,
+        TxOutSecrets::new(
+            asset,
+            AssetBlindingFactor::zero(),
+            amount,
+            ValueBlindingFactor::zero(),
+        ),
+    );
+    pset.blind_last(&mut rand::thread_rng(), &secp, &secrets)
+        .unwrap();
+    assert!(pset.outputs()[0].to_txout().value.is_confidential());
+
+    // Sign
+    let tx = pset.extract_tx().unwrap();
+    let mut cache = SigHashCache::new(&tx);
+    let msg = pset
+        .sighash_msg(index, &mut cache, None, genesis_hash)
+        .unwrap()
+        .to_secp_msg();
+    let sig = secp.sign_ecdsa(&msg, &sk);
+    let hash_ty = elements::EcdsaSigHashType::All;
+    pset.inputs_mut()[index]
+        .partial_sigs
+        .insert(pk, elementssig_to_rawsig(&(sig, hash_ty)));
+
+    // Finalize and extract, both of which run the interpreter
+    pset.finalize_mut(&secp, genesis_hash).unwrap();
+    let tx = pset.extract(&secp, genesis_hash).unwrap();
+    println!("{}", elements::encode::serialize_hex(&tx));
+
+    // Check the spend again, as a verifier of the transaction would
+    let txin = &tx.input[index];
+    let interpreter = Interpreter::from_txdata(
+        &utxo.script_pubkey,
+        &txin.script_sig,
+        &txin.witness.script_witness,
+        txin.sequence,
+        tx.lock_time,
+    )
+    .unwrap();
+    let utxos = [utxo];
+    let env = TxEnv::new(&tx, &utxos, index).unwrap();
+    for elem in interpreter.iter(&secp, &env, genesis_hash) {
+        elem.expect("valid spend");
+    }
+    println!("Spend of {} verified", spender);
+}
@@ -0,0 +1,240 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Arbitrary implementations
+//!
+//! Generation of structured fuzzing inputs with the `arbitrary` crate.
+//!
+//! Policies, miniscripts and descriptors are generated from a small grammar
+//! of fragments which always type check, with keys taken from a fixed set of
+//! valid public keys. Generated values are well typed, but they may not be
+//! sane (e.g. they may repeat keys or mix timelocks). Inputs for which the
+//! generated value cannot be parsed are rejected with
+//! [`arbitrary::Error::IncorrectFormat`].
+//!
+
+use std::str::FromStr;
+
+use arbitrary::{Arbitrary, Unstructured};
+use elements::hashes::{sha256, Hash};
+
+use crate::policy::Concrete;
+use crate::{Context, Descriptor, Miniscript, ScriptContext};
+
+/// Maximum depth of the generated fragments
+const MAX_DEPTH: usize = 4;
+
+/// Public keys of the secret keys 1 to 5
+const KEYS: [&str; 5] = [
+    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+    "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+    "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13",
+    "022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4",
+];
+
+fn key(u: &mut Unstructured<'_>) -> arbitrary::Result<&'static str> {
+    Ok(*u.choose(&KEYS)?)
+}
+
+// A non-empty subset of the keys, without repetitions
+fn keys(u: &mut Unstructured<'_>) -> arbitrary::Result<Vec<&'static str>> {
+    let mut keys = vec![];
+    for key in KEYS.iter() {
+        if u.arbitrary()? {
+            keys.push(*key);
+        }
+    }
+    if keys.is_empty() {
+        keys.push(KEYS[0]);
+    }
+    Ok(keys)
+}
+
+fn hash(u: &mut Unstructured<'_>) -> arbitrary::Result<sha256::Hash> {
+    let preimage: [u8; 32] = u.arbitrary()?;
+    Ok(sha256::Hash::hash(&preimage))
+}
+
+fn older(u: &mut Unstructured<'_>) -> arbitrary::Result<String> {
+    Ok(format!("older({})", u.int_in_range(1..=0xffffu32)?))
+}
+
+fn after(u: &mut Unstructured<'_>) -> arbitrary::Result<String> {
+    Ok(format!("after({})", u.int_in_range(1..=0x7fff_ffffu32)?))
+}
+
+// A miniscript of type B
+fn ms_leaf(u: &mut Unstructured<'_>, ctx: Context) -> arbitrary::Result<String> {
+    Ok(match u.int_in_range(0..=5u8)? {
+        0 => format!("pk({})", key(u)?),
+        1 => format!("pkh({})", key(u)?),
+        2 => older(u)?,
+        3 => after(u)?,
+        4 => format!("sha256({})", hash(u)?),
+        _ => {
+            let keys = keys(u)?;
+            let k = u.int_in_range(1..=keys.len())?;
+            let name = if ctx == Context::Tap {
+                "multi_a"
+            } else {
+                "multi"
+            };
+            format!("{}({},{})", name, k, keys.join(","))
+        }
+    })
+}
+
+// A miniscript of type B. The first child of `or_d` and `andor` must be
+// dissatisfiable and unit, which `pk` always is.
+fn ms_string(u: &mut Unstructured<'_>, ctx: Context, depth: usize) -> arbitrary::Result<String> {
+    if depth == 0 || u.is_empty() {
+        return ms_leaf(u, ctx);
+    }
+    Ok(match u.int_in_range(0..=4u8)? {
+        0 => ms_leaf(u, ctx)?,
+        1 => {
+            let left = ms_string(u, ctx, depth - 1)?;
+            let right = ms_string(u, ctx, depth - 1)?;
+            format!("and_v(v:{},{})", left, right)
+        }
+        2 => {
+            let left = ms_string(u, ctx, depth - 1)?;
+            let right = ms_string(u, ctx, depth - 1)?;
+            format!("or_i({},{})", left, right)
+        }
+        3 => {
+            let left = key(u)?;
+            let right = ms_string(u, ctx, depth - 1)?;
+            format!("or_d(pk({}),{})", left, right)
+        }
+        _ => {
+            let cond = key(u)?;
+            let left = ms_string(u, ctx, depth - 1)?;
+            let right = ms_string(u, ctx, depth - 1)?;
+            format!("andor(pk({}),{},{})", cond, left, right)
+        }
+    })
+}
+
+fn tap_tree(u: &mut Unstructured<'_>, depth: usize) -> arbitrary::Result<String> {
+    if depth == 0 || u.is_empty() || u.arbitrary()? {
+        ms_string(u, Context::Tap, MAX_DEPTH)
+    } else {
+        let left = tap_tree(u, depth - 1)?;
+        let right = tap_tree(u, depth - 1)?;
+        Ok(format!("{{{},{}}}", left, right))
+    }
+}
+
+fn policy_leaf(u: &mut Unstructured<'_>) -> arbitrary::Result<String> {
+    Ok(match u.int_in_range(0..=3u8)? {
+        0 => format!("pk({})", key(u)?),
+        1 => older(u)?,
+        2 => after(u)?,
+        _ => format!("sha256({})", hash(u)?),
+    })
+}
+
+fn policy_string(u: &mut Unstructured<'_>, depth: usize) -> arbitrary::Result<String> {
+    if depth == 0 || u.is_empty() {
+        return policy_leaf(u);
+    }
+    Ok(match u.int_in_range(0..=3u8)? {
+        0 => policy_leaf(u)?,
+        1 => {
+            let left = policy_string(u, depth - 1)?;
+            let right = policy_string(u, depth - 1)?;
+            format!("and({},{})", left, right)
+        }
+        2 => {
+            let left_weight = u.int_in_range(1..=9u8)?;
+            let left = policy_string(u, depth - 1)?;
+            let right_weight = u.int_in_range(1..=9u8)?;
+            let right = policy_string(u, depth - 1)?;
+            format!("or({}@{},{}@{})", left_weight, left, right_weight, right)
+        }
+        _ => {
+            let n = u.int_in_range(2..=4usize)?;
+            let subs = (0..n)
+                .map(|_| policy_string(u, depth - 1))
+                .collect::<arbitrary::Result<Vec<_>>>()?;
+            let k = u.int_in_range(1..=n)?;
+            format!("thresh({},{})", k, subs.join(","))
+        }
+    })
+}
+
+impl<'a> Arbitrary<'a> for Concrete<bitcoin::PublicKey> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let s = policy_string(u, MAX_DEPTH)?;
+        Concrete::from_str(&s).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+impl<'a, Ctx: ScriptContext> Arbitrary<'a> for Miniscript<bitcoin::PublicKey, Ctx> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let s = ms_string(u, Ctx::context(), MAX_DEPTH)?;
+        Miniscript::from_str_insane(&s).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Descriptor<bitcoin::PublicKey> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let s = match u.int_in_range(0..=7u8)? {
+            0 => format!("elpkh({})", key(u)?),
+            1 => format!("elwpkh({})", key(u)?),
+            2 => format!("elsh(wpkh({}))", key(u)?),
+            3 => format!("elsh({})", ms_string(u, Context::Legacy, MAX_DEPTH)?),
+            4 => format!("elwsh({})", ms_string(u, Context::Segwitv0, MAX_DEPTH)?),
+            5 => format!("elsh(wsh({}))", ms_string(u, Context::Segwitv0, MAX_DEPTH)?),
+            6 => format!("eltr({})", key(u)?),
+            _ => {
+                let internal_key = key(u)?;
+                format!("eltr({},{})", internal_key, tap_tree(u, 2)?)
+            }
+        };
+        Descriptor::from_str(&s).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Segwitv0;
+
+    // Deterministic pseudo random bytes
+    fn data(seed: u8) -> Vec<u8> {
+        (0..512u32)
+            .map(|i| (i as u8).wrapping_mul(seed | 1).wrapping_add(seed))
+            .collect()
+    }
+
+    #[test]
+    fn arbitrary_roundtrip() {
+        let mut n_desc = 0;
+        for seed in 0..=255 {
+            let data = data(seed);
+            if let Ok(desc) =
+                Descriptor::<bitcoin::PublicKey>::arbitrary(&mut Unstructured::new(&data))
+            {
+                let parsed = Descriptor::<bitcoin::PublicKey>::from_str(&desc.to_string());
+                assert_eq!(parsed.unwrap(), desc);
+                n_desc += 1;
+            }
+            if let Ok(ms) =
+                Miniscript::<bitcoin::PublicKey, Segwitv0>::arbitrary(&mut Unstructured::new(&data))
+            {
+                let parsed =
+                    Miniscript::<bitcoin::PublicKey, Segwitv0>::from_str_insane(&ms.to_string());
+                assert_eq!(parsed.unwrap(), ms);
+            }
+            if let Ok(pol) =
+                Concrete::<bitcoin::PublicKey>::arbitrary(&mut Unstructured::new(&data))
+            {
+                let parsed = Concrete::<bitcoin::PublicKey>::from_str(&pol.to_string());
+                assert_eq!(parsed.unwrap(), pol);
+            }
+        }
+        assert!(n_desc > 0);
+    }
+}
@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Command line interface to parse, compile, derive and analyze descriptors
+//! and policies, printing its results in JSON.
+//!
+//! Build it with `cargo install elements-miniscript --features cli`.
+
+extern crate elements_miniscript as miniscript;
+
+use std::process;
+use std::str::FromStr;
+
+use elements::secp256k1_zkp::{self, Secp256k1};
+use miniscript::policy::{Concrete, Liftable};
+use miniscript::{Descriptor, DescriptorPublicKey, Segwitv0};
+use serde_json::{json, Value};
+
+const USAGE: &str = "\
+Usage: elements-miniscript <command> [<args>]
+
+Commands:
+    parse <descriptor>
+        Parse a descriptor, which may contain secret keys
+    compile <policy>
+        Compile a policy into elwsh and eltr descriptors
+    derive <descriptor> <index> [--blinding-key <key>] [--network <network>]
+        Derive the address of a descriptor at an index, blinded with the
+        given public key. The network is one of liquid (default),
+        liquidtestnet or elements
+    analyze <descriptor>
+        Report the spending conditions, keys and satisfaction weight of a
+        descriptor";
+
+fn parse(secp: &Secp256k1<secp256k1_zkp::All>, s: &str) -> Result<Value, String> {
+    let (desc, keymap) =
+        Descriptor::<DescriptorPublicKey>::parse_descriptor(secp, s).map_err(|e| e.to_string())?;
+    Ok(json!({
+        "descriptor": desc.to_string(),
+        "type": desc.desc_type().to_string(),
+        "has_wildcard": desc.has_wildcard(),
+        "is_multipath": desc.is_multipath(),
+        "has_secret_keys": !keymap.is_empty(),
+        "sanity_check": desc.sanity_check().err().map(|e| e.to_string()),
+    }))
+}
+
+fn compile(s: &str) -> Result<Value, String> {
+    let policy = Concrete::<String>::from_str(s).map_err(|e| e.to_string())?;
+    let ms = policy.compile::<Segwitv0>().map_err(|e| e.to_string())?;
+    let wsh = Descriptor::<String>::new_wsh(ms.clone()).map_err(|e| e.to_string())?;
+    let tr = policy.compile_tr(None).map_err(|e| e.to_string())?;
+    Ok(json!({
+        "policy": policy.to_string(),
+        "miniscript": ms.to_string(),
+        "elwsh": wsh.to_string(),
+        "eltr": tr.to_string(),
+    }))
+}
+
+fn derive(secp: &Secp256k1<secp256k1_zkp::All>, args: &[String]) -> Result<Value, String> {
+    let (desc, index) = match args {
+        [desc, index, ..] => (desc, index),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let index = u32::from_str(index).map_err(|e| e.to_string())?;
+    let mut blinding_key = None;
+    let mut params = &elements::AddressParams::LIQUID;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or_else(|| USAGE.to_owned())?;
+        match option.as_str() {
+            "--blinding-key" => {
+                let key = secp256k1_zkp::PublicKey::from_str(value).map_err(|e| e.to_string())?;
+                blinding_key = Some(key);
+            }
+            "--network" => {
+                params = match value.as_str() {
+                    "liquid" => &elements::AddressParams::LIQUID,
+                    "liquidtestnet" => &elements::AddressParams::LIQUID_TESTNET,
+                    "elements" => &elements::AddressParams::ELEMENTS,
+                    _ => return Err(format!("Unknown network {}", value)),
+                }
+            }
+            _ => return Err(USAGE.to_owned()),
+        }
+    }
+
+    let (desc, _) = Descriptor::<DescriptorPublicKey>::parse_descriptor(secp, desc)
+        .map_err(|e| e.to_string())?;
+    let derived = desc
+        .at_derivation_index(index)
+        .and_then(|desc| desc.derived_descriptor(secp))
+        .map_err(|e| e.to_string())?;
+    let address = match blinding_key {
+        Some(key) => derived.blinded_address(key, params),
+        None => derived.address(params),
+    };
+    Ok(json!({
+        "descriptor": derived.to_string(),
+        "index": index,
+        "script_pubkey": format!("{:x}", derived.script_pubkey()),
+        "address": address.map_err(|e| e.to_string())?.to_string(),
+    }))
+}
+
+fn analyze(secp: &Secp256k1<secp256k1_zkp::All>, s: &str) -> Result<Value, String> {
+    let (desc, _) =
+        Descriptor::<DescriptorPublicKey>::parse_descriptor(secp, s).map_err(|e| e.to_string())?;
+    let keys = desc
+        .collect_keys()
+        .into_iter()
+        .map(|k| {
+            json!({
+                "key": k.key.to_string(),
+                "roles": k.roles.iter().map(|r| format!("{:?}", r)).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "descriptor": desc.to_string(),
+        "type": desc.desc_type().to_string(),
+        "sanity_check": desc.sanity_check().err().map(|e| e.to_string()),
+        "policy": desc.lift().map(|p| p.to_string()).map_err(|e| e.to_string())?,
+        "keys": keys,
+        "tree": desc.to_json_tree(),
+        "max_weight_to_satisfy": desc.max_weight_to_satisfy().ok(),
+    }))
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let secp = Secp256k1::new();
+    let res = match args.first().map(String::as_str) {
+        Some("parse") if args.len() == 2 => parse(&secp, &args[1]),
+        Some("compile") if args.len() == 2 => compile(&args[1]),
+        Some("derive") => derive(&secp, &args[1..]),
+        Some("analyze") if args.len() == 2 => analyze(&secp, &args[1]),
+        _ => Err(USAGE.to_owned()),
+    };
+    match res {
+        Ok(value) => println!(
+            "{}",
+            serde_json::to_string_pretty(&value).expect("JSON values serialize")
+        ),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
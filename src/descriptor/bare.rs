@@ -10,18 +10,18 @@
 
 use core::fmt;
 
-use elements::{self, script, secp256k1_zkp, Script};
+use elements::{self, secp256k1_zkp, Script};
 
 use super::checksum::verify_checksum;
-use super::ELMTS_STR;
+use super::{CustomAddressParams, WitnessBuilder, ELMTS_STR};
 use crate::descriptor::checksum;
 use crate::expression::{self, FromTree};
 use crate::miniscript::context::ScriptContext;
 use crate::policy::{semantic, Liftable};
-use crate::util::{varint_len, witness_to_scriptsig};
+use crate::util::varint_len;
 use crate::{
-    elementssig_to_rawsig, BareCtx, Error, ForEachKey, Miniscript, MiniscriptKey, Satisfier,
-    ToPublicKey, TranslatePk, Translator,
+    elementssig_to_rawsig, BareCtx, Error, ForEachKey, Legacy, Miniscript, MiniscriptKey,
+    Satisfier, ToPublicKey, TranslatePk, Translator,
 };
 
 /// Create a Bare Descriptor. That is descriptor that is
@@ -116,7 +116,7 @@ impl<Pk: MiniscriptKey + ToPublicKey> Bare<Pk> {
         S: Satisfier<Pk>,
     {
         let ms = self.ms.satisfy(satisfier)?;
-        let script_sig = witness_to_scriptsig(&ms);
+        let script_sig = WitnessBuilder::<BareCtx>::new(ms).into_script_sig()?;
         let witness = vec![];
         Ok((witness, script_sig))
     }
@@ -129,7 +129,7 @@ impl<Pk: MiniscriptKey + ToPublicKey> Bare<Pk> {
         S: Satisfier<Pk>,
     {
         let ms = self.ms.satisfy_malleable(satisfier)?;
-        let script_sig = witness_to_scriptsig(&ms);
+        let script_sig = WitnessBuilder::<BareCtx>::new(ms).into_script_sig()?;
         let witness = vec![];
         Ok((witness, script_sig))
     }
@@ -282,6 +282,25 @@ impl<Pk: MiniscriptKey + ToPublicKey> Pkh<Pk> {
         elements::Address::p2pkh(&self.pk.to_public_key(), blinder, params)
     }
 
+    /// Obtains the unconfidential address for this descriptor, see
+    /// [`Descriptor::unconfidential_address`](crate::Descriptor::unconfidential_address)
+    pub fn unconfidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        params: P,
+    ) -> elements::Address {
+        self.address(None, params.into().to_static())
+    }
+
+    /// Obtains the confidential address for this descriptor, blinded with
+    /// the `blinder` public key
+    pub fn confidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        blinder: secp256k1_zkp::PublicKey,
+        params: P,
+    ) -> elements::Address {
+        self.address(Some(blinder), params.into().to_static())
+    }
+
     /// Obtains the underlying miniscript for this descriptor.
     pub fn inner_script(&self) -> Script {
         self.script_pubkey()
@@ -301,10 +320,9 @@ impl<Pk: MiniscriptKey + ToPublicKey> Pkh<Pk> {
     {
         if let Some(sig) = satisfier.lookup_ecdsa_sig(&self.pk) {
             let sig_vec = elementssig_to_rawsig(&sig);
-            let script_sig = script::Builder::new()
-                .push_slice(&sig_vec[..])
-                .push_key(&self.pk.to_public_key())
-                .into_script();
+            let script_sig =
+                WitnessBuilder::<Legacy>::new(vec![sig_vec, self.pk.to_public_key().to_bytes()])
+                    .into_script_sig()?;
             let witness = vec![];
             Ok((witness, script_sig))
         } else {
@@ -18,12 +18,17 @@
 //! Also includes pk, and pkh descriptors
 //!
 
+use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
 
-use elements::{self, Script};
+use elements::{self, secp256k1_zkp, Script};
 
-use super::checksum::verify_checksum;
-use super::{Descriptor, TranslatePk};
+use super::checksum::{desc_checksum, verify_checksum};
+use super::{
+    Descriptor, DescriptorPublicKey, DescriptorSecretKey, DescriptorType, KeyMap, KeyMapWrapper,
+    TranslatePk,
+};
 use crate::descriptor::checksum;
 use crate::expression::{self, FromTree};
 use crate::extensions::{CovExtArgs, CovenantExt};
@@ -32,7 +37,7 @@ use crate::{Error, MiniscriptKey, Satisfier, ToPublicKey, Translator};
 
 /// Create a Bare Descriptor. That is descriptor that is
 /// not wrapped in sh or wsh. This covers the Pk descriptor
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct Blinded<Pk: MiniscriptKey> {
     /// The blinding key
     blinder: Pk,
@@ -64,6 +69,11 @@ impl<Pk: MiniscriptKey> Blinded<Pk> {
     pub fn into_unblinded(self) -> Descriptor<Pk, CovenantExt<CovExtArgs>> {
         self.desc
     }
+
+    /// Get the [`DescriptorType`] of the unblinded descriptor
+    pub fn desc_type(&self) -> DescriptorType {
+        self.desc.desc_type()
+    }
 }
 
 impl<Pk: MiniscriptKey> fmt::Debug for Blinded<Pk> {
@@ -201,6 +211,63 @@ impl<Pk: MiniscriptKey> Blinded<Pk> {
     }
 }
 
+impl Blinded<DescriptorPublicKey> {
+    /// Parse a blinded descriptor that may contain secret keys
+    ///
+    /// Like [`Descriptor::parse_descriptor`], every secret key is turned into the
+    /// corresponding public key and returned in a map from public to secret key.
+    /// This includes the blinding key.
+    pub fn parse_descriptor<C: secp256k1_zkp::Signing>(
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        s: &str,
+    ) -> Result<(Blinded<DescriptorPublicKey>, KeyMap), Error> {
+        let mut keymap_pk = KeyMapWrapper(HashMap::new(), secp);
+        let blinded = Blinded::<String>::from_str(s)?;
+        let blinded = blinded.translate_pk(&mut keymap_pk)?;
+        Ok((blinded, keymap_pk.0))
+    }
+
+    /// Serialize a view-only version of this descriptor
+    ///
+    /// The blinding key is written out with its secret (the "view key") from
+    /// `key_map`, while all other keys are written as public keys, regardless of
+    /// whether `key_map` contains their secrets. The result allows unblinding and
+    /// watching outputs but not spending them, and can be parsed back with
+    /// [`Blinded::parse_view_only`].
+    ///
+    /// # Errors
+    /// If `key_map` does not contain the secret of the blinding key.
+    pub fn to_view_only(&self, key_map: &KeyMap) -> Result<String, Error> {
+        let view_key = key_map
+            .get(&self.blinder)
+            .ok_or_else(|| Error::BadDescriptor("Missing secret for blinding key".to_string()))?;
+        let desc_str = format!("blinded({},{:#})", view_key, self.desc);
+        let checksum = desc_checksum(&desc_str)?;
+        Ok(format!("{}#{}", desc_str, checksum))
+    }
+
+    /// Parse a view-only blinded descriptor
+    ///
+    /// Returns the descriptor together with the secret blinding key. Fails if
+    /// the blinding key is not secret, or if any other key in the descriptor is,
+    /// so the result is guaranteed to carry no spending capability.
+    pub fn parse_view_only<C: secp256k1_zkp::Signing>(
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        s: &str,
+    ) -> Result<(Blinded<DescriptorPublicKey>, DescriptorSecretKey), Error> {
+        let (blinded, mut key_map) = Self::parse_descriptor(secp, s)?;
+        let view_key = key_map.remove(&blinded.blinder).ok_or_else(|| {
+            Error::BadDescriptor("View-only descriptor must have a secret blinding key".to_string())
+        })?;
+        if !key_map.is_empty() {
+            return Err(Error::BadDescriptor(
+                "View-only descriptor must not contain spending secret keys".to_string(),
+            ));
+        }
+        Ok((blinded, view_key))
+    }
+}
+
 impl<P: MiniscriptKey, Q: MiniscriptKey> TranslatePk<P, Q> for Blinded<P> {
     type Output = Blinded<Q>;
 
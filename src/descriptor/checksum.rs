@@ -46,20 +46,33 @@ pub fn desc_checksum(desc: &str) -> Result<String, Error> {
     Ok(eng.checksum())
 }
 
+/// Splits a descriptor string into the descriptor and its checksum, if it
+/// has one, i.e. at its first `#`.
+///
+/// Neither the descriptor nor the checksum are checked, see
+/// [`verify_checksum`].
+pub fn split_checksum(s: &str) -> (&str, Option<&str>) {
+    let mut parts = s.splitn(2, '#');
+    let desc_str = parts.next().expect("splitn returns at least one part");
+    (desc_str, parts.next())
+}
+
 /// Helper function for FromStr for various
-/// descriptor types. Checks and verifies the checksum
+/// descriptor types and policies. Checks and verifies the checksum
 /// if it is present and returns the descriptor string
 /// without the checksum
-pub(super) fn verify_checksum(s: &str) -> Result<&str, Error> {
+///
+/// The descriptor itself is not parsed, so that this can be used to validate
+/// descriptors of any type.
+pub fn verify_checksum(s: &str) -> Result<&str, Error> {
     for ch in s.as_bytes() {
         if *ch < 20 || *ch > 127 {
             return Err(Error::Unprintable(*ch));
         }
     }
 
-    let mut parts = s.splitn(2, '#');
-    let desc_str = parts.next().unwrap();
-    if let Some(checksum_str) = parts.next() {
+    let (desc_str, checksum_str) = split_checksum(s);
+    if let Some(checksum_str) = checksum_str {
         let expected_sum = desc_checksum(desc_str)?;
         if checksum_str != expected_sum {
             return Err(Error::BadDescriptor(format!(
@@ -211,6 +224,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_verify_checksum() {
+        let desc = "elwpkh(tprv8ZgxMBicQKsPdpkqS7Eair4YxjcuuvDPNYmKX3sCniCf16tHEVrjjiSXEkFRnUH77yXc6ZcwHHcLNfjdi5qUvw3VDfgYiH5mNsj5izuiu2N/1/2/*)";
+        let with_checksum = format!("{}#hkvr2vkj", desc);
+        assert_eq!(split_checksum(&with_checksum), (desc, Some("hkvr2vkj")));
+        assert_eq!(split_checksum(desc), (desc, None));
+        assert_eq!(verify_checksum(&with_checksum).unwrap(), desc);
+        assert_eq!(verify_checksum(desc).unwrap(), desc);
+        assert!(verify_checksum(&format!("{}#hkvr2vkk", desc)).is_err());
+        // Descriptors of unsupported types are not parsed
+        assert_eq!(
+            verify_checksum("unknown(A)#xxxxxxxx")
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Invalid descriptor: Invalid checksum 'xxxxxxxx', expected '{}'",
+                desc_checksum("unknown(A)").unwrap()
+            )
+        );
+    }
+
     #[test]
     fn test_desc_checksum_invalid_character() {
         let sparkle_heart = vec![240, 159, 146, 150];
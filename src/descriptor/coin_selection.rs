@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Coin Selection
+//!
+//! Selection of the outputs to spend to fund a transaction. The weight of
+//! each input is computed from the descriptor of the spent output, including
+//! the witness of covenant descriptors, so that the selection accounts for
+//! the fee paid to spend every input.
+//!
+
+use super::Descriptor;
+use crate::{Extension, MiniscriptKey};
+
+/// Weight of an unsigned input of a segwit transaction: the outpoint,
+/// sequence and scriptSig length, plus the empty issuance rangeproofs, script
+/// witness and pegin witness.
+pub const TXIN_BASE_WEIGHT: usize = 4 * (32 + 4 + 4 + 1) + 4;
+
+/// Result of [`select_coins`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CoinSelection {
+    /// Indexes of the selected candidates
+    pub selected: Vec<usize>,
+    /// Sum of the values of the selected candidates
+    pub total_value: u64,
+    /// Fee paid to spend the selected candidates
+    pub input_fee: u64,
+}
+
+impl CoinSelection {
+    /// Value of the selected candidates after paying for their inputs
+    pub fn effective_value(&self) -> u64 {
+        self.total_value - self.input_fee
+    }
+}
+
+/// Fee of `weight` weight units at `feerate` satoshis per kvB, rounded up
+pub(crate) fn fee(weight: usize, feerate: u64) -> u64 {
+    (weight as u64 * feerate + 3999) / 4000
+}
+
+/// Selects candidates whose value, after paying for their own inputs at
+/// `feerate` satoshis per kvB, is at least `target`.
+///
+/// Candidates are selected by decreasing effective value. Candidates that
+/// cannot be satisfied, or whose value does not cover the fee to spend them,
+/// are never selected. Returns `None` if the candidates are not enough to
+/// reach the target.
+///
+/// The target should include the fee of the rest of the transaction.
+pub fn select_coins<Pk: MiniscriptKey, Ext: Extension>(
+    candidates: &[(Descriptor<Pk, Ext>, u64)],
+    target: u64,
+    feerate: u64,
+) -> Option<CoinSelection> {
+    let mut effective = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (desc, value))| {
+            let weight = TXIN_BASE_WEIGHT + desc.max_weight_to_satisfy().ok()?;
+            let input_fee = fee(weight, feerate);
+            if input_fee < *value {
+                Some((i, *value, input_fee))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    effective.sort_by_key(|&(i, value, input_fee)| (std::cmp::Reverse(value - input_fee), i));
+
+    let mut selection = CoinSelection {
+        selected: vec![],
+        total_value: 0,
+        input_fee: 0,
+    };
+    for (i, value, input_fee) in effective {
+        if selection.effective_value() >= target {
+            break;
+        }
+        selection.selected.push(i);
+        selection.total_value += value;
+        selection.input_fee += input_fee;
+    }
+    if selection.effective_value() >= target {
+        Some(selection)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::DescriptorPublicKey;
+
+    #[test]
+    fn select_by_effective_value() {
+        let pk = "020000000000000000000000000000000000000000000000000000000000000002";
+        let pk2 = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let wpkh = Descriptor::<DescriptorPublicKey>::from_str(&format!("elwpkh({})", pk)).unwrap();
+        let sh =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elsh(multi(1,{},{}))", pk, pk2))
+                .unwrap();
+        let wpkh_fee = fee(
+            TXIN_BASE_WEIGHT + wpkh.max_weight_to_satisfy().unwrap(),
+            1000,
+        );
+
+        let candidates = vec![
+            (wpkh.clone(), 1_000),
+            (wpkh.clone(), 5_000),
+            (wpkh, wpkh_fee),
+            (sh, 2_000),
+        ];
+        let sel = select_coins(&candidates, 5_500, 1000).unwrap();
+        assert_eq!(sel.selected, vec![1, 3]);
+        assert_eq!(sel.total_value, 7_000);
+        assert!(sel.effective_value() >= 5_500);
+
+        // Candidates not covering their own fee are never selected
+        assert_eq!(select_coins(&candidates[2..3], 1, 1000), None);
+        assert_eq!(select_coins(&candidates, 10_000, 1000), None);
+        assert_eq!(select_coins(&candidates, 0, 1000).unwrap().selected, vec![]);
+    }
+}
@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Covenant Context Checks
+//!
+//! The miniscript of a covenant descriptor is parsed in the segwit v0
+//! context, but it is executed between the sighash items pushed by the
+//! satisfaction and the CHECKSIGFROMSTACK verification of the covenant.
+//! [`CovCheck`] gathers the additional rules this places on the descriptor,
+//! so that unsupported descriptors are rejected when parsed or constructed
+//! rather than when encoded or satisfied.
+//!
+
+use super::CovError;
+use crate::miniscript::context::ScriptContextError;
+use crate::miniscript::decode::Terminal;
+use crate::{Error, Extension, Miniscript, MiniscriptKey, ScriptContext, Segwitv0};
+
+/// The rules of the legacy CHECKSIGFROMSTACK covenant context, on top of the
+/// ones of the [`Segwitv0`] context:
+///
+/// - the covenant key is pushed as a 33-byte key, so it must be a compressed
+///   and non x-only key;
+/// - the keys of `pk_h` fragments (and their `pkh` alias) must be compressed
+///   and non x-only keys as well, like the keys of `pk` and `multi`;
+/// - extensions must be available in segwit v0 scripts, i.e. only the legacy
+///   `ver_eq` and `outputs_pref` covenant fragments can be used, as the other
+///   extensions need tapscript opcodes.
+///
+/// All the other segwit v0 fragments are supported.
+pub enum CovCheck {}
+
+impl CovCheck {
+    /// Checks that `pk` can be used as the key of a covenant
+    pub fn check_cov_pk<Pk: MiniscriptKey>(pk: &Pk) -> Result<(), CovError> {
+        if pk.is_uncompressed() {
+            Err(CovError::UncompressedCovKey)
+        } else if pk.is_x_only_key() {
+            Err(CovError::XOnlyCovKey)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that a single fragment can be used inside a covenant
+    pub fn check_fragment<Pk: MiniscriptKey, Ext: Extension>(
+        frag: &Terminal<Pk, Segwitv0, Ext>,
+    ) -> Result<(), ScriptContextError> {
+        match *frag {
+            Terminal::PkH(ref pk) => {
+                if pk.is_uncompressed() {
+                    Err(ScriptContextError::CompressedOnly(pk.to_string()))
+                } else if pk.is_x_only_key() {
+                    Err(ScriptContextError::XOnlyKeysNotAllowed(
+                        pk.to_string(),
+                        Segwitv0::name_str(),
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            Terminal::Ext(ref e) => e.segwit_ctx_checks(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the key and all the fragments of a covenant descriptor,
+    /// reporting the first unsupported one
+    pub fn check<Pk: MiniscriptKey, Ext: Extension>(
+        pk: &Pk,
+        ms: &Miniscript<Pk, Segwitv0, Ext>,
+    ) -> Result<(), Error> {
+        Self::check_cov_pk(pk)?;
+        for node in ms.iter() {
+            Self::check_fragment(&node.node)?;
+        }
+        Ok(())
+    }
+}
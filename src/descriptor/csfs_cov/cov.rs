@@ -40,16 +40,17 @@
 //! After all the miniscript fragments are evaluated, we concat
 //! all the items using OP_CAT to obtain a Sighash on which we
 //! which we verify using CHECKSIGFROMSTACK
+use std::collections::HashMap;
 use std::fmt;
 
 use bitcoin;
 use elements::encode::{serialize, Encodable};
-use elements::hashes::{sha256d, Hash};
+use elements::hashes::{hash160, sha256d, Hash};
 use elements::{self, script, secp256k1_zkp, Script};
 
 use super::super::checksum::{desc_checksum, verify_checksum};
-use super::super::ELMTS_STR;
-use super::{CovError, CovOperations};
+use super::super::{WitnessBuilder, ELMTS_STR};
+use super::{CovCheck, CovError, CovOperations};
 use crate::expression::{self, FromTree};
 use crate::extensions::ParseableExt;
 use crate::miniscript::lex::{lex, Token as Tk, TokenIter};
@@ -57,10 +58,12 @@ use crate::miniscript::limits::{
     MAX_OPS_PER_SCRIPT, MAX_SCRIPT_SIZE, MAX_STANDARD_P2WSH_SCRIPT_SIZE,
 };
 use crate::miniscript::{decode, types};
+use crate::policy::covenant::CovLiftable;
+use crate::policy::{semantic, Liftable};
 use crate::util::varint_len;
 use crate::{
     Error, ExtTranslator, Extension, ForEachKey, Miniscript, MiniscriptKey, Satisfier,
-    ScriptContext, Segwitv0, ToPublicKey, TranslateExt, TranslatePk, Translator,
+    ScriptContext, Segwitv0, Threshold, ToPublicKey, TranslateExt, TranslatePk, Translator,
 };
 
 // A simple utility function to serialize an array
@@ -75,7 +78,7 @@ fn hash256_arr<T: Encodable>(sl: &[T]) -> sha256d::Hash {
 pub(crate) const COV_SCRIPT_SIZE: usize = 120;
 pub(crate) const COV_SCRIPT_OPCODE_COST: usize = 74;
 /// The covenant descriptor
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LegacyCSFSCov<Pk: MiniscriptKey, Ext: Extension> {
     /// the pk constraining the Covenant
     /// The key over which we want CHECKSIGFROMSTACK
@@ -102,8 +105,24 @@ impl<Pk: MiniscriptKey, Ext: Extension> LegacyCSFSCov<Pk, Ext> {
         self.ms
     }
 
+    /// Substitutes the raw public key hashes of the `expr_raw_pkh` fragments
+    /// obtained when parsing the script of a covenant with `pkh` fragments,
+    /// see [`Miniscript::substitute_raw_pkh`].
+    pub fn substitute_raw_pkh(&self, pk_map: &HashMap<hash160::Hash, Pk>) -> Self {
+        LegacyCSFSCov {
+            pk: self.pk.clone(),
+            ms: self.ms.substitute_raw_pkh(pk_map),
+        }
+    }
+
     /// Create a new Self from components
+    ///
+    /// # Errors
+    ///
+    /// If the key or a fragment of `ms` is not supported in covenants, see
+    /// [`CovCheck`], or if the covenant script exceeds the consensus limits.
     pub fn new(pk: Pk, ms: Miniscript<Pk, Segwitv0, Ext>) -> Result<Self, Error> {
+        CovCheck::check(&pk, &ms)?;
         // // 1) Check the 201 opcode count here
         let ms_op_count = ms.ext.ops.op_count();
         // statically computed
@@ -296,6 +315,7 @@ impl_from_tree!(
             let top = &top.args[1];
             let sub = Miniscript::from_tree(top)?;
             Segwitv0::top_level_checks(&sub)?;
+            CovCheck::check(&pk, &sub)?;
             Ok(LegacyCSFSCov { pk, ms: sub })
         } else {
             Err(Error::Unexpected(format!(
@@ -406,10 +426,11 @@ where
         S: Satisfier<Pk>,
         Ext: ParseableExt,
     {
-        let mut witness = self.satisfy(satisfier, /*allow_mall*/ false)?;
-        witness.push(self.encode().into_bytes());
+        let mut witness =
+            WitnessBuilder::<Segwitv0>::new(self.satisfy(satisfier, /*allow_mall*/ false)?);
+        witness.script(&self.encode());
         let script_sig = Script::new();
-        Ok((witness, script_sig))
+        Ok((witness.into_witness()?, script_sig))
     }
 
     /// Computes an upper bound on the weight of a satisfying witness to the
@@ -448,10 +469,11 @@ where
         S: Satisfier<Pk>,
         Ext: ParseableExt,
     {
-        let mut witness = self.satisfy(satisfier, /*allow_mall*/ true)?;
-        witness.push(self.encode().into_bytes());
+        let mut witness =
+            WitnessBuilder::<Segwitv0>::new(self.satisfy(satisfier, /*allow_mall*/ true)?);
+        witness.script(&self.encode());
         let script_sig = Script::new();
-        Ok((witness, script_sig))
+        Ok((witness.into_witness()?, script_sig))
     }
 }
 
@@ -464,6 +486,20 @@ impl<Pk: MiniscriptKey, Ext: Extension> ForEachKey<Pk> for LegacyCSFSCov<Pk, Ext
     }
 }
 
+impl<Pk: MiniscriptKey, Ext: Extension> Liftable<Pk> for LegacyCSFSCov<Pk, Ext> {
+    fn lift(&self) -> Result<semantic::Policy<Pk>, Error> {
+        // The covenant key signs the transaction via CHECKSIGFROMSTACK. The
+        // constraints of the extensions are assumed to be met, the covenant
+        // node marking that they exist.
+        let inner = self.ms.lift_cov()?.to_semantic();
+        let pol = semantic::Policy::Threshold(Threshold::both(
+            semantic::Policy::Key(self.pk.clone()),
+            inner,
+        ));
+        Ok(semantic::Policy::Covenant(Box::new(pol.normalized())))
+    }
+}
+
 impl<P, Q, Ext> TranslatePk<P, Q> for LegacyCSFSCov<P, Ext>
 where
     P: MiniscriptKey,
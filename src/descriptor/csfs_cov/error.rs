@@ -40,6 +40,10 @@ pub enum CovError {
     /// The Covenant Sighash type and the satisfier sighash
     /// type must be the same
     CovenantSighashTypeMismatch,
+    /// The covenant key must be compressed
+    UncompressedCovKey,
+    /// The covenant key cannot be an x-only key
+    XOnlyCovKey,
 }
 
 impl fmt::Display for CovError {
@@ -58,6 +62,8 @@ impl fmt::Display for CovError {
                 "The sighash type provided in the witness must the same \
                 as the one used in signature"
             ),
+            CovError::UncompressedCovKey => write!(f, "Uncompressed covenant pk"),
+            CovError::XOnlyCovKey => write!(f, "X-only covenant pk"),
         }
     }
 }
@@ -42,10 +42,12 @@
 //! all the items using OP_CAT to obtain a Sighash on which we
 //! which we verify using CHECKSIGFROMSTACK
 
+mod check;
 mod cov;
 mod error;
 mod satisfy;
 mod script_internals;
+pub use self::check::CovCheck;
 pub use self::cov::LegacyCSFSCov;
 pub use self::error::CovError;
 pub use self::satisfy::LegacyCovSatisfier;
@@ -96,6 +98,55 @@ mod tests {
         string_rtt("elcovwsh(A,and_v(v:pk(B),pk(C)))");
         string_rtt("elcovwsh(A,thresh(2,l:ver_eq(1),s:pk(C),s:pk(B)))");
         string_rtt("elcovwsh(A,outputs_pref(01020304))");
+        string_rtt("elcovwsh(A,pkh(B))");
+        string_rtt("elcovwsh(A,and_v(v:pkh(B),ver_eq(2)))");
+    }
+
+    #[test]
+    fn cov_check() {
+        let (pks, _sks) = setup_keys(2);
+        let mut uncompressed = pks[1];
+        uncompressed.compressed = false;
+
+        let err = Descriptor::<bitcoin::PublicKey>::from_str(&format!(
+            "elcovwsh({},pk({}))",
+            uncompressed, pks[0]
+        ))
+        .unwrap_err();
+        assert!(matches!(err, Error::CovError(CovError::UncompressedCovKey)));
+        let err = Descriptor::<bitcoin::PublicKey>::from_str(&format!(
+            "elcovwsh({},pkh({}))",
+            pks[0], uncompressed
+        ))
+        .unwrap_err();
+        assert!(matches!(err, Error::ContextError(..)));
+        // Introspection opcodes are only available in taproot
+        assert!(
+            Descriptor::<String>::from_str("elcovwsh(A,and_v(v:pk(B),curr_idx_eq(1)))")
+                .unwrap_err()
+                .to_string()
+                .contains("Taproot")
+        );
+    }
+
+    #[test]
+    fn lift_cov() {
+        use crate::policy::{Liftable, Semantic};
+
+        let lift = |s: &str| Descriptor::<String>::from_str(s).unwrap().lift().unwrap();
+        assert_eq!(
+            lift("elcovwsh(A,pk(B))").to_string(),
+            "covenant(and(pk(A),pk(B)))"
+        );
+        let pol = lift("elcovwsh(A,thresh(2,l:ver_eq(1),s:pk(C),s:pk(B)))");
+        assert_eq!(pol.to_string(), "covenant(and(pk(A),or(pk(C),pk(B))))");
+        assert_eq!(pol, Semantic::from_str(&pol.to_string()).unwrap());
+        assert_eq!(pol.minimum_n_keys(), Some(2));
+
+        // The covenant is an additional requirement
+        let no_cov = Semantic::from_str("and(pk(A),or(pk(C),pk(B)))").unwrap();
+        assert!(pol.clone().entails(no_cov.clone()).unwrap());
+        assert!(!no_cov.entails(pol).unwrap());
     }
 
     fn script_rtt(desc_str: &str) {
@@ -134,6 +185,25 @@ mod tests {
             "elcovwsh({},and_v(v:outputs_pref(f2f233),pk({})))",
             pks[0], pks[1]
         ));
+
+        // Parsing the script loses the keys of the pkh fragments
+        let desc = Descriptor::<bitcoin::PublicKey>::from_str(&format!(
+            "elcovwsh({},and_v(v:pkh({}),ver_eq(2)))",
+            pks[0], pks[1]
+        ))
+        .unwrap();
+        let cov = desc.as_cov().unwrap();
+        let parsed = LegacyCSFSCov::<bitcoin::PublicKey, CovenantExt<CovExtArgs>>::parse_insane(
+            &cov.encode(),
+        )
+        .unwrap();
+        assert!(parsed.to_string().contains("expr_raw_pkh"));
+        let mut pk_map = std::collections::HashMap::new();
+        pk_map.insert(
+            crate::ToPublicKey::to_pubkeyhash(&pks[1], crate::SigType::Ecdsa),
+            pks[1],
+        );
+        assert_eq!(&parsed.substitute_raw_pkh(&pk_map), cov);
     }
 
     // Some deterministic keys for ease of testing
@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Descriptor Diff
+//!
+//! Structural comparison of two descriptors. The expressions of both
+//! descriptors are walked in parallel and every sub-expression which differs
+//! is reported, classified by the fragment it belongs to. This allows, for
+//! instance, checking that a wallet upgrade only rotated the keys of a
+//! descriptor without altering its spending conditions.
+//!
+
+use std::fmt;
+
+use super::tr::parse_tr_tree;
+use super::Descriptor;
+use crate::expression::Tree;
+use crate::{Error, Extension, MiniscriptKey};
+
+/// A difference between two descriptors
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DescriptorChange {
+    /// A key was replaced by another key
+    KeyChanged {
+        /// The key in the old descriptor
+        old: String,
+        /// The key in the new descriptor
+        new: String,
+    },
+    /// The threshold of a `thresh` or multisig fragment changed
+    ThresholdChanged {
+        /// The threshold in the old descriptor
+        old: String,
+        /// The threshold in the new descriptor
+        new: String,
+    },
+    /// The value of an `older` or `after` fragment changed
+    TimelockChanged {
+        /// The timelock fragment in the old descriptor
+        old: String,
+        /// The timelock fragment in the new descriptor
+        new: String,
+    },
+    /// The image of a hashlock fragment changed
+    HashChanged {
+        /// The hashlock fragment in the old descriptor
+        old: String,
+        /// The hashlock fragment in the new descriptor
+        new: String,
+    },
+    /// A sub-expression was added to the new descriptor
+    BranchAdded(String),
+    /// A sub-expression of the old descriptor was removed
+    BranchRemoved(String),
+    /// A sub-expression was replaced by a different one
+    FragmentChanged {
+        /// The sub-expression in the old descriptor
+        old: String,
+        /// The sub-expression in the new descriptor
+        new: String,
+    },
+}
+
+impl fmt::Display for DescriptorChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DescriptorChange::KeyChanged { ref old, ref new } => {
+                write!(f, "key {} changed to {}", old, new)
+            }
+            DescriptorChange::ThresholdChanged { ref old, ref new } => {
+                write!(f, "threshold {} changed to {}", old, new)
+            }
+            DescriptorChange::TimelockChanged { ref old, ref new } => {
+                write!(f, "timelock {} changed to {}", old, new)
+            }
+            DescriptorChange::HashChanged { ref old, ref new } => {
+                write!(f, "hashlock {} changed to {}", old, new)
+            }
+            DescriptorChange::BranchAdded(ref new) => write!(f, "added {}", new),
+            DescriptorChange::BranchRemoved(ref old) => write!(f, "removed {}", old),
+            DescriptorChange::FragmentChanged { ref old, ref new } => {
+                write!(f, "{} changed to {}", old, new)
+            }
+        }
+    }
+}
+
+/// Differences between two descriptors, as returned by [`Descriptor::diff`]
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct DescriptorDiff {
+    /// The differences, in the order they appear in the descriptors
+    pub changes: Vec<DescriptorChange>,
+}
+
+impl DescriptorDiff {
+    /// Whether the descriptors are identical
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether the descriptors only differ in their keys, i.e. they have
+    /// the same spending conditions
+    pub fn only_keys_changed(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|c| matches!(c, DescriptorChange::KeyChanged { .. }))
+    }
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// Reports the structural differences between `self` and `other`.
+    ///
+    /// Sub-expressions with the same fragment are compared argument by
+    /// argument, so that changed keys, thresholds, timelocks and hashlocks are
+    /// reported individually. Arguments present in only one of the
+    /// descriptors (e.g. the keys of a larger multisig, or the script tree of
+    /// a taproot descriptor) are reported as added or removed branches, and
+    /// any other mismatch is reported as a changed fragment.
+    ///
+    /// # Errors
+    ///
+    /// If the string representation of one of the descriptors cannot be
+    /// parsed back, e.g. because of a key whose display contains commas or
+    /// parentheses.
+    pub fn diff(&self, other: &Self) -> Result<DescriptorDiff, Error> {
+        let old_str = format!("{:#}", self);
+        let new_str = format!("{:#}", other);
+        let old = descriptor_tree(&old_str)?;
+        let new = descriptor_tree(&new_str)?;
+        let mut diff = DescriptorDiff::default();
+        diff_tree(&old, &new, None, &mut diff.changes);
+        Ok(diff)
+    }
+}
+
+// Parses the display of a descriptor back to an expression tree, with
+// the leaves of the taproot tree parsed as well
+pub(super) fn descriptor_tree(s: &str) -> Result<Tree<'_>, Error> {
+    let s = s.split('#').next().unwrap_or(s);
+    if s.starts_with("eltr(") {
+        let mut top = parse_tr_tree(s)?;
+        if top.args.len() == 2 {
+            top.args[1] = tap_tree(&top.args[1])?;
+        }
+        Ok(top)
+    } else {
+        Tree::from_str(s)
+    }
+}
+
+fn tap_tree<'a>(tree: &Tree<'a>) -> Result<Tree<'a>, Error> {
+    if tree.args.is_empty() {
+        Tree::from_str(tree.name)
+    } else {
+        Ok(Tree {
+            name: tree.name,
+            args: tree.args.iter().map(tap_tree).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+// Writes the tree back in descriptor form
+fn tree_string(tree: &Tree<'_>) -> String {
+    if tree.args.is_empty() {
+        return tree.name.to_owned();
+    }
+    let args = tree.args.iter().map(tree_string).collect::<Vec<_>>();
+    if tree.name.is_empty() {
+        format!("{{{}}}", args.join(","))
+    } else {
+        format!("{}({})", tree.name, args.join(","))
+    }
+}
+
+// Classifies a changed argument of a fragment
+fn leaf_change(parent: &Tree<'_>, idx: usize, old: String, new: String) -> DescriptorChange {
+    // Ignore the wrappers
+    let name = parent.name.rsplit(':').next().unwrap_or(parent.name);
+    match (name, idx) {
+        ("thresh", 0)
+        | ("multi", 0)
+        | ("sortedmulti", 0)
+        | ("multi_a", 0)
+        | ("sortedmulti_a", 0) => DescriptorChange::ThresholdChanged { old, new },
+        ("multi", _) | ("sortedmulti", _) | ("multi_a", _) | ("sortedmulti_a", _) => {
+            DescriptorChange::KeyChanged { old, new }
+        }
+        ("pk", _)
+        | ("pk_k", _)
+        | ("pk_h", _)
+        | ("pkh", _)
+        | ("wpkh", _)
+        | ("elpkh", _)
+        | ("elwpkh", _)
+        | ("eltr", 0)
+        | ("elcovwsh", 0) => DescriptorChange::KeyChanged { old, new },
+        ("older", _) | ("after", _) => DescriptorChange::TimelockChanged {
+            old: format!("{}({})", name, old),
+            new: format!("{}({})", name, new),
+        },
+        ("sha256", _) | ("hash256", _) | ("ripemd160", _) | ("hash160", _) => {
+            DescriptorChange::HashChanged {
+                old: format!("{}({})", name, old),
+                new: format!("{}({})", name, new),
+            }
+        }
+        _ => DescriptorChange::FragmentChanged { old, new },
+    }
+}
+
+fn diff_tree(
+    old: &Tree<'_>,
+    new: &Tree<'_>,
+    parent: Option<(&Tree<'_>, usize)>,
+    changes: &mut Vec<DescriptorChange>,
+) {
+    if old.name != new.name || old.args.is_empty() != new.args.is_empty() {
+        let (old, new) = (tree_string(old), tree_string(new));
+        changes.push(match parent {
+            Some((parent, idx)) if !old.contains('(') && !new.contains('(') => {
+                leaf_change(parent, idx, old, new)
+            }
+            _ => DescriptorChange::FragmentChanged { old, new },
+        });
+        return;
+    }
+    for (idx, (old_arg, new_arg)) in old.args.iter().zip(new.args.iter()).enumerate() {
+        diff_tree(old_arg, new_arg, Some((old, idx)), changes);
+    }
+    for arg in old.args.iter().skip(new.args.len()) {
+        changes.push(DescriptorChange::BranchRemoved(tree_string(arg)));
+    }
+    for arg in new.args.iter().skip(old.args.len()) {
+        changes.push(DescriptorChange::BranchAdded(tree_string(arg)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> DescriptorDiff {
+        let old = Descriptor::<String>::from_str(old).unwrap();
+        let new = Descriptor::<String>::from_str(new).unwrap();
+        old.diff(&new).unwrap()
+    }
+
+    fn key(old: &str, new: &str) -> DescriptorChange {
+        DescriptorChange::KeyChanged {
+            old: old.to_owned(),
+            new: new.to_owned(),
+        }
+    }
+
+    #[test]
+    fn descriptor_diff() {
+        let desc = "elwsh(or_d(multi(2,A,B,C),and_v(v:pk(D),older(144))))";
+        assert!(diff(desc, desc).is_empty());
+
+        // Key rotation
+        let d = diff(
+            desc,
+            "elwsh(or_d(multi(2,A,E,C),and_v(v:pk(F),older(144))))",
+        );
+        assert_eq!(d.changes, vec![key("B", "E"), key("D", "F")]);
+        assert!(d.only_keys_changed());
+
+        // Changed spending conditions
+        let d = diff(
+            desc,
+            "elwsh(or_d(multi(1,A,B,C,E),and_v(v:pk(D),older(288))))",
+        );
+        assert_eq!(
+            d.changes,
+            vec![
+                DescriptorChange::ThresholdChanged {
+                    old: "2".to_owned(),
+                    new: "1".to_owned()
+                },
+                DescriptorChange::BranchAdded("E".to_owned()),
+                DescriptorChange::TimelockChanged {
+                    old: "older(144)".to_owned(),
+                    new: "older(288)".to_owned()
+                },
+            ]
+        );
+        assert!(!d.only_keys_changed());
+
+        let d = diff(
+            desc,
+            "elsh(wsh(or_d(multi(2,A,B,C),and_v(v:pk(D),older(144)))))",
+        );
+        assert_eq!(d.changes.len(), 1);
+        assert!(matches!(
+            d.changes[0],
+            DescriptorChange::FragmentChanged { .. }
+        ));
+
+        // Taproot trees
+        let tr = "eltr(A,{pk(B),and_v(v:pk(C),after(100))})";
+        let d = diff(tr, "eltr(D,{pk(E),and_v(v:pk(C),after(100))})");
+        assert_eq!(d.changes, vec![key("A", "D"), key("B", "E")]);
+        let d = diff("eltr(A)", tr);
+        assert_eq!(
+            d.changes,
+            vec![DescriptorChange::BranchAdded(
+                "{pk(B),and_v(v:pk(C),after(100))}".to_owned()
+            )]
+        );
+        let d = diff(tr, "eltr(A,{pk(B),and_v(v:pk(C),sha256(H))})");
+        assert_eq!(
+            d.changes,
+            vec![DescriptorChange::FragmentChanged {
+                old: "after(100)".to_owned(),
+                new: "sha256(H)".to_owned()
+            }]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Descriptor Encryption
+//!
+//! Encryption at rest of descriptors, e.g. for backups, with a passphrase.
+//! The key is derived from the passphrase with Argon2id and the descriptor is
+//! encrypted with XChaCha20-Poly1305, in a versioned container:
+//!
+//! | Bytes   | Content                                        |
+//! |---------|------------------------------------------------|
+//! | 4       | magic `ELMD`                                   |
+//! | 1       | version, `1`                                   |
+//! | 4       | Argon2id memory cost in KiB, little endian     |
+//! | 4       | Argon2id number of iterations, little endian   |
+//! | 4       | Argon2id degree of parallelism, little endian  |
+//! | 16      | Argon2id salt                                  |
+//! | 24      | XChaCha20-Poly1305 nonce                       |
+//! | n + 16  | encrypted descriptor string and its tag        |
+//!
+//! The header, i.e. everything before the ciphertext, is authenticated as
+//! associated data. Since it is read before it can be authenticated, the
+//! key derivation parameters of a container are rejected above
+//! [`MAX_KDF_PARAMS`].
+//!
+
+use std::str::FromStr;
+use std::{error, fmt};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+use super::Descriptor;
+use crate::{Error, Extension, MiniscriptKey};
+
+/// The magic bytes starting every container
+pub const MAGIC: [u8; 4] = *b"ELMD";
+
+/// The version of the container format
+pub const VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = 4 + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// Parameters of the Argon2id key derivation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KdfParams {
+    /// Memory cost, in KiB
+    pub memory_kib: u32,
+    /// Number of iterations
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The parameters recommended by OWASP, 19 MiB of memory and 2
+    /// iterations
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// The most expensive key derivation parameters accepted when decrypting,
+/// a few times the [default ones](KdfParams::default)
+pub const MAX_KDF_PARAMS: KdfParams = KdfParams {
+    memory_kib: 128 * 1024,
+    iterations: 8,
+    parallelism: 8,
+};
+
+impl KdfParams {
+    // Whether all the costs are within the ones of `max`
+    fn within(&self, max: &KdfParams) -> bool {
+        self.memory_kib <= max.memory_kib
+            && self.iterations <= max.iterations
+            && self.parallelism <= max.parallelism
+    }
+}
+
+/// Error when decrypting a container
+#[derive(Debug)]
+pub enum EncryptionError {
+    /// The data does not start with [`MAGIC`] or is shorter than a container
+    NotAContainer,
+    /// The container has a version which is not supported
+    UnsupportedVersion(u8),
+    /// The key derivation parameters are invalid
+    InvalidKdfParams(KdfParams),
+    /// The key derivation parameters of the container exceed
+    /// [`MAX_KDF_PARAMS`]
+    KdfParamsTooExpensive(KdfParams),
+    /// Wrong passphrase, or tampered container
+    Decryption,
+    /// The decrypted data is not a descriptor
+    Descriptor(Error),
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EncryptionError::NotAContainer => f.write_str("not an encrypted descriptor"),
+            EncryptionError::UnsupportedVersion(v) => {
+                write!(f, "unsupported encrypted descriptor version {}", v)
+            }
+            EncryptionError::InvalidKdfParams(ref params) => {
+                write!(f, "invalid key derivation parameters {:?}", params)
+            }
+            EncryptionError::KdfParamsTooExpensive(ref params) => {
+                write!(
+                    f,
+                    "key derivation parameters {:?} exceed the maximum",
+                    params
+                )
+            }
+            EncryptionError::Decryption => {
+                f.write_str("decryption failed: wrong passphrase or corrupted data")
+            }
+            EncryptionError::Descriptor(ref e) => write!(f, "decrypted invalid descriptor: {}", e),
+        }
+    }
+}
+
+impl error::Error for EncryptionError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            EncryptionError::NotAContainer
+            | EncryptionError::UnsupportedVersion(_)
+            | EncryptionError::InvalidKdfParams(_)
+            | EncryptionError::KdfParamsTooExpensive(_)
+            | EncryptionError::Decryption => None,
+            EncryptionError::Descriptor(ref e) => Some(e),
+        }
+    }
+}
+
+fn cipher(
+    passphrase: &str,
+    params: KdfParams,
+    salt: &[u8],
+) -> Result<XChaCha20Poly1305, EncryptionError> {
+    let invalid = |_| EncryptionError::InvalidKdfParams(params);
+    let argon2_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(32),
+    )
+    .map_err(invalid)?;
+    let mut key = Zeroizing::new([0; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(invalid)?;
+    Ok(XChaCha20Poly1305::new(Key::from_slice(&*key)))
+}
+
+/// Encrypts `data` with a key derived from `passphrase`, with a random salt
+/// and nonce
+///
+/// # Errors
+///
+/// If the key derivation parameters are invalid.
+pub fn encrypt(
+    data: &[u8],
+    passphrase: &str,
+    params: KdfParams,
+) -> Result<Vec<u8>, EncryptionError> {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut container = Vec::with_capacity(HEADER_LEN + data.len() + 16);
+    container.extend_from_slice(&MAGIC);
+    container.push(VERSION);
+    container.extend_from_slice(&params.memory_kib.to_le_bytes());
+    container.extend_from_slice(&params.iterations.to_le_bytes());
+    container.extend_from_slice(&params.parallelism.to_le_bytes());
+    container.extend_from_slice(&salt);
+    container.extend_from_slice(&nonce);
+
+    let ciphertext = cipher(passphrase, params, &salt)?
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: data,
+                aad: &container,
+            },
+        )
+        .expect("encryption of in-memory data does not fail");
+    container.extend_from_slice(&ciphertext);
+    Ok(container)
+}
+
+/// Decrypts a container created by [`encrypt`]
+///
+/// The returned data is zeroized when dropped.
+///
+/// # Errors
+///
+/// If the data is not a container, or if its key derivation parameters
+/// exceed [`MAX_KDF_PARAMS`], before deriving any key.
+pub fn decrypt(container: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>, EncryptionError> {
+    if container.len() < HEADER_LEN || container[..4] != MAGIC {
+        return Err(EncryptionError::NotAContainer);
+    }
+    if container[4] != VERSION {
+        return Err(EncryptionError::UnsupportedVersion(container[4]));
+    }
+    let u32_at = |i: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&container[i..i + 4]);
+        u32::from_le_bytes(bytes)
+    };
+    let params = KdfParams {
+        memory_kib: u32_at(5),
+        iterations: u32_at(9),
+        parallelism: u32_at(13),
+    };
+    if !params.within(&MAX_KDF_PARAMS) {
+        return Err(EncryptionError::KdfParamsTooExpensive(params));
+    }
+    let (header, ciphertext) = container.split_at(HEADER_LEN);
+    let salt = &header[17..17 + SALT_LEN];
+    let nonce = XNonce::from_slice(&header[17 + SALT_LEN..]);
+    cipher(passphrase, params, salt)?
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| EncryptionError::Decryption)
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// Encrypts the descriptor, with its checksum, with a key derived from
+    /// `passphrase`, see the [module documentation](crate::descriptor::encryption)
+    /// for the format.
+    ///
+    /// Secret keys are not part of the string representation of a
+    /// [`Descriptor<DescriptorPublicKey>`](super::DescriptorPublicKey), to
+    /// encrypt them use [`encrypt`] with
+    /// [`Descriptor::to_string_with_secret`].
+    ///
+    /// # Errors
+    ///
+    /// If the key derivation parameters are invalid.
+    pub fn encrypt(&self, passphrase: &str, params: KdfParams) -> Result<Vec<u8>, EncryptionError> {
+        encrypt(self.to_string().as_bytes(), passphrase, params)
+    }
+
+    /// Decrypts a descriptor encrypted by [`Descriptor::encrypt`]
+    pub fn decrypt(container: &[u8], passphrase: &str) -> Result<Self, EncryptionError>
+    where
+        Self: FromStr<Err = Error>,
+    {
+        let data = decrypt(container, passphrase)?;
+        let s = std::str::from_utf8(&data)
+            .map_err(|_| EncryptionError::Descriptor(Error::Unexpected("non UTF-8".to_owned())))?;
+        Self::from_str(s).map_err(EncryptionError::Descriptor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters for the tests
+    const PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn encrypt_decrypt() {
+        let desc =
+            Descriptor::<String>::from_str("elwsh(or_d(pk(A),and_v(v:pk(B),older(144))))").unwrap();
+        let container = desc.encrypt("correct horse", PARAMS).unwrap();
+        assert_eq!(&container[..5], b"ELMD\x01");
+        assert_eq!(container.len(), HEADER_LEN + desc.to_string().len() + 16);
+        assert_eq!(
+            Descriptor::<String>::decrypt(&container, "correct horse").unwrap(),
+            desc
+        );
+        // Salts and nonces are random
+        assert_ne!(desc.encrypt("correct horse", PARAMS).unwrap(), container);
+
+        assert!(matches!(
+            Descriptor::<String>::decrypt(&container, "battery staple"),
+            Err(EncryptionError::Decryption)
+        ));
+        // The parameters cannot be tampered with
+        let mut tampered = container.clone();
+        tampered[9] = 2;
+        assert!(matches!(
+            decrypt(&tampered, "correct horse"),
+            Err(EncryptionError::Decryption)
+        ));
+        let mut tampered = container.clone();
+        tampered[4] = 2;
+        assert!(matches!(
+            decrypt(&tampered, "correct horse"),
+            Err(EncryptionError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            decrypt(&container[..HEADER_LEN - 1], "correct horse"),
+            Err(EncryptionError::NotAContainer)
+        ));
+        // Untrusted parameters are bounded before deriving the key
+        for i in [5, 9, 13].iter() {
+            let mut tampered = container.clone();
+            tampered[i + 3] = 0xff;
+            assert!(matches!(
+                decrypt(&tampered, "correct horse"),
+                Err(EncryptionError::KdfParamsTooExpensive(_))
+            ));
+        }
+
+        let data = encrypt(b"not a descriptor", "pass", PARAMS).unwrap();
+        assert!(matches!(
+            Descriptor::<String>::decrypt(&data, "pass"),
+            Err(EncryptionError::Descriptor(_))
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Hash Time Locked Contracts
+//!
+//! Descriptor templates for the HTLCs used by submarine swaps on Liquid.
+//! The output can be claimed by the claim key with the preimage of the
+//! payment hash, or by the refund key once the timeout has expired.
+//!
+//! Segwit v0 HTLCs use the script
+//! `andor(pk(claim),sha256(H),and_v(v:pk(refund),after(T)))` while taproot
+//! HTLCs put the claim and refund paths in separate leaves.
+//!
+
+use std::sync::Arc;
+
+use elements::hashes::{sha256, Hash};
+use elements::LockTime;
+
+use super::{Descriptor, TapTree, Tr};
+use crate::miniscript::satisfy::Preimage32;
+use crate::{
+    AbsLockTime, Error, Miniscript, MiniscriptKey, NoExt, Satisfier, ScriptContext, Segwitv0, Tap,
+    Terminal, ToPublicKey,
+};
+
+/// A hash time locked contract
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Htlc<Pk: MiniscriptKey> {
+    /// Key that can claim the output with the preimage
+    pub claim_key: Pk,
+    /// Key that can spend the output after the timeout
+    pub refund_key: Pk,
+    /// Hash of the preimage required by the claim path
+    pub payment_hash: Pk::Sha256,
+    /// Absolute locktime after which the refund path is available
+    pub timeout: AbsLockTime,
+}
+
+impl<Pk: MiniscriptKey> Htlc<Pk> {
+    /// Create a new HTLC
+    pub fn new(
+        claim_key: Pk,
+        refund_key: Pk,
+        payment_hash: Pk::Sha256,
+        timeout: AbsLockTime,
+    ) -> Self {
+        Htlc {
+            claim_key,
+            refund_key,
+            payment_hash,
+            timeout,
+        }
+    }
+
+    /// Miniscript of the claim path `and_v(v:pk(claim),sha256(H))`
+    pub fn claim_ms<Ctx: ScriptContext>(&self) -> Result<Miniscript<Pk, Ctx>, Error> {
+        let claim = verify(pk(self.claim_key.clone())?)?;
+        let hash = Miniscript::from_ast(Terminal::Sha256(self.payment_hash.clone()))?;
+        Miniscript::from_ast(Terminal::AndV(Arc::new(claim), Arc::new(hash)))
+    }
+
+    /// Miniscript of the refund path `and_v(v:pk(refund),after(T))`
+    pub fn refund_ms<Ctx: ScriptContext>(&self) -> Result<Miniscript<Pk, Ctx>, Error> {
+        let refund = verify(pk(self.refund_key.clone())?)?;
+        let after = Miniscript::from_ast(Terminal::After(self.timeout))?;
+        Miniscript::from_ast(Terminal::AndV(Arc::new(refund), Arc::new(after)))
+    }
+
+    /// Miniscript with both paths
+    /// `andor(pk(claim),sha256(H),and_v(v:pk(refund),after(T)))`
+    pub fn ms<Ctx: ScriptContext>(&self) -> Result<Miniscript<Pk, Ctx>, Error> {
+        let claim = pk(self.claim_key.clone())?;
+        let hash = Miniscript::from_ast(Terminal::Sha256(self.payment_hash.clone()))?;
+        Miniscript::from_ast(Terminal::AndOr(
+            Arc::new(claim),
+            Arc::new(hash),
+            Arc::new(self.refund_ms()?),
+        ))
+    }
+
+    /// Segwit v0 descriptor of the HTLC
+    pub fn wsh_descriptor(&self) -> Result<Descriptor<Pk>, Error> {
+        Descriptor::new_wsh(self.ms::<Segwitv0>()?)
+    }
+
+    /// Taproot descriptor of the HTLC with the claim and refund paths as leaves.
+    ///
+    /// The internal key is usually an aggregate of the claim and refund keys
+    /// so that the swap can be completed cooperatively with a key spend.
+    pub fn tr_descriptor(&self, internal_key: Pk) -> Result<Descriptor<Pk>, Error> {
+        let tree = TapTree::Tree(
+            Arc::new(TapTree::Leaf(Arc::new(self.claim_ms::<Tap>()?))),
+            Arc::new(TapTree::Leaf(Arc::new(self.refund_ms::<Tap>()?))),
+        );
+        Ok(Descriptor::Tr(Tr::<Pk, NoExt>::new(
+            internal_key,
+            Some(tree),
+        )?))
+    }
+}
+
+// `c:pk_k(key)`
+fn pk<Pk: MiniscriptKey, Ctx: ScriptContext>(key: Pk) -> Result<Miniscript<Pk, Ctx>, Error> {
+    let pk_k = Miniscript::from_ast(Terminal::PkK(key))?;
+    Miniscript::from_ast(Terminal::Check(Arc::new(pk_k)))
+}
+
+// `v:ms`
+fn verify<Pk: MiniscriptKey, Ctx: ScriptContext>(
+    ms: Miniscript<Pk, Ctx>,
+) -> Result<Miniscript<Pk, Ctx>, Error> {
+    Miniscript::from_ast(Terminal::Verify(Arc::new(ms)))
+}
+
+/// Satisfier for the claim path of an [`Htlc`], providing the preimage.
+///
+/// Combine it with a satisfier providing the signature of the claim key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HtlcClaim {
+    /// The preimage of the payment hash
+    pub preimage: Preimage32,
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for HtlcClaim {
+    fn lookup_sha256(&self, h: &Pk::Sha256) -> Option<Preimage32> {
+        if sha256::Hash::hash(&self.preimage) == Pk::to_sha256(h) {
+            Some(self.preimage)
+        } else {
+            None
+        }
+    }
+}
+
+/// Satisfier for the refund path of an [`Htlc`], providing the locktime of
+/// the spending transaction.
+///
+/// Combine it with a satisfier providing the signature of the refund key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HtlcRefund {
+    /// The locktime of the spending transaction
+    pub lock_time: LockTime,
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for HtlcRefund {
+    fn check_after(&self, n: LockTime) -> bool {
+        <LockTime as Satisfier<Pk>>::check_after(&self.lock_time, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    use elements::secp256k1_zkp::{self, Secp256k1};
+    use elements::EcdsaSigHashType;
+
+    use super::*;
+
+    #[test]
+    fn htlc_descriptors() {
+        let secp = Secp256k1::new();
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        let claim = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+        let sk2 = secp256k1_zkp::SecretKey::from_slice(&[2; 32]).unwrap();
+        let refund =
+            bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk2));
+        let preimage = [7; 32];
+        let htlc = Htlc::new(
+            claim,
+            refund,
+            sha256::Hash::hash(&preimage),
+            AbsLockTime::from_consensus(800_000),
+        );
+
+        let desc = htlc.wsh_descriptor().unwrap();
+        assert_eq!(
+            desc.to_string().split('#').next().unwrap(),
+            format!(
+                "elwsh(andor(pk({}),sha256({}),and_v(v:pk({}),after(800000))))",
+                claim, htlc.payment_hash, refund
+            )
+        );
+        let tr = htlc.tr_descriptor(claim).unwrap();
+        assert!(tr.to_string().starts_with(&format!(
+            "eltr({},{{and_v(v:pk({}),sha256({})),and_v(v:pk({}),after(800000))}})",
+            claim, claim, htlc.payment_hash, refund
+        )));
+
+        // Claim and refund paths
+        let msg = secp256k1_zkp::Message::from_slice(&[3; 32]).unwrap();
+        let mut sigs = HashMap::new();
+        sigs.insert(claim, (secp.sign_ecdsa(&msg, &sk), EcdsaSigHashType::All));
+        sigs.insert(refund, (secp.sign_ecdsa(&msg, &sk2), EcdsaSigHashType::All));
+        let ms = htlc.ms::<Segwitv0>().unwrap();
+        let wit = ms.satisfy((&sigs, HtlcClaim { preimage })).unwrap();
+        assert_eq!(wit[0], preimage.to_vec());
+        let refund_sat = HtlcRefund {
+            lock_time: LockTime::from_consensus(800_001),
+        };
+        let wit = ms.satisfy((&sigs, refund_sat)).unwrap();
+        assert_eq!(wit.len(), 2);
+        assert_eq!(wit[1], Vec::<u8>::new());
+        let early = HtlcRefund {
+            lock_time: LockTime::from_consensus(799_999),
+        };
+        assert!(ms.satisfy((&sigs, early)).is_err());
+
+        // Wrong preimages are not used
+        let wrong = HtlcClaim { preimage: [8; 32] };
+        assert!(ms.satisfy((&sigs, wrong)).is_err());
+        assert!(Descriptor::<bitcoin::PublicKey>::from_str(&desc.to_string()).is_ok());
+    }
+}
@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # JSON Export
+//!
+//! Structured JSON representation of a descriptor, see
+//! [`Descriptor::to_json_tree`], for consumption by tools rendering the
+//! spending conditions of a descriptor, e.g. graphically.
+//!
+
+use bitcoin::bip32;
+use bitcoin::key::XOnlyPublicKey;
+use elements::hex::ToHex;
+use serde_json::{json, Value};
+
+use super::{
+    Bare, DefiniteDescriptorKey, Descriptor, DescriptorPublicKey, DescriptorVisitor, LegacyCSFSCov,
+    OriginKey, Pkh, Sh, ShInner, SortedMultiVec, Tr, Wit, Wpkh, Wsh, WshInner,
+};
+use crate::miniscript::decode::Terminal;
+use crate::{Extension, Miniscript, NoExt, ScriptContext, Threshold};
+
+/// A key which can be exported by [`Descriptor::to_json_tree`]
+pub trait JsonKey: crate::MiniscriptKey {
+    /// The JSON representation of the key, an object with the key as a
+    /// string under `key` by default
+    fn to_json(&self) -> Value {
+        json!({ "key": self.to_string() })
+    }
+}
+
+// A key with its origin under `origin` if it has one
+fn key_json(key: String, origin: &Option<bip32::KeySource>) -> Value {
+    let mut value = json!({ "key": key });
+    if let Some((ref fingerprint, ref path)) = *origin {
+        value["origin"] = json!({
+            "fingerprint": fingerprint.to_string(),
+            "path": path.to_string(),
+        });
+    }
+    value
+}
+
+impl JsonKey for String {}
+
+impl JsonKey for bitcoin::PublicKey {}
+
+impl JsonKey for XOnlyPublicKey {}
+
+impl JsonKey for DescriptorPublicKey {
+    /// The key, with its origin under `origin` if it has one
+    fn to_json(&self) -> Value {
+        let origin = match *self {
+            DescriptorPublicKey::Single(ref single) => &single.origin,
+            DescriptorPublicKey::XPub(ref xpub) => &xpub.origin,
+            DescriptorPublicKey::MultiXPub(ref xpub) => &xpub.origin,
+        };
+        key_json(self.to_string(), origin)
+    }
+}
+
+impl JsonKey for OriginKey {
+    /// The name of the key, with its origin under `origin` if it has one
+    fn to_json(&self) -> Value {
+        key_json(self.name.clone(), &self.origin)
+    }
+}
+
+impl JsonKey for DefiniteDescriptorKey {
+    fn to_json(&self) -> Value {
+        self.as_descriptor_public_key().to_json()
+    }
+}
+
+impl<Pk: JsonKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// A JSON tree of the descriptor, e.g. for UIs rendering its spending
+    /// conditions.
+    ///
+    /// Every node is an object with its `type`: the descriptor types as
+    /// returned by [`Descriptor::desc_type`], or the name of a miniscript
+    /// fragment. Miniscript nodes have their subexpressions under `children`,
+    /// their keys under `key` or `keys`, as returned by [`JsonKey::to_json`],
+    /// and their threshold, timelock or hash under `k`, `value` or `hash`
+    /// respectively. Extension fragments have type `ext` and are exported as
+    /// a string under `value`.
+    pub fn to_json_tree(&self) -> Value {
+        let mut builder = JsonBuilder(Value::Null);
+        self.accept(&mut builder);
+        builder.0
+    }
+}
+
+struct JsonBuilder(Value);
+
+fn sortedmulti_json<Pk: JsonKey, Ctx: ScriptContext>(smv: &SortedMultiVec<Pk, Ctx>) -> Value {
+    json!({
+        "type": "sortedmulti",
+        "k": smv.threshold(),
+        "keys": smv.participants().iter().map(JsonKey::to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn ms_json<Pk: JsonKey, Ctx: ScriptContext, Ext: Extension>(
+    ms: &Miniscript<Pk, Ctx, Ext>,
+) -> Value {
+    let wrapper = |name: &str, sub: &Miniscript<Pk, Ctx, Ext>| {
+        json!({
+            "type": name,
+            "children": [ms_json(sub)],
+        })
+    };
+    let binary = |name: &str, l: &Miniscript<Pk, Ctx, Ext>, r: &Miniscript<Pk, Ctx, Ext>| {
+        json!({
+            "type": name,
+            "children": [ms_json(l), ms_json(r)],
+        })
+    };
+    let multi = |name: &str, thresh: &Threshold<Pk>| {
+        json!({
+            "type": name,
+            "k": thresh.k(),
+            "keys": thresh.iter().map(JsonKey::to_json).collect::<Vec<_>>(),
+        })
+    };
+    match ms.node {
+        Terminal::True => json!({ "type": "1" }),
+        Terminal::False => json!({ "type": "0" }),
+        Terminal::PkK(ref pk) => json!({ "type": "pk_k", "key": pk.to_json() }),
+        Terminal::PkH(ref pk) => json!({ "type": "pk_h", "key": pk.to_json() }),
+        Terminal::RawPkH(ref hash) => json!({ "type": "expr_raw_pkh", "hash": hash.to_string() }),
+        Terminal::After(t) => json!({ "type": "after", "value": t.to_consensus_u32() }),
+        Terminal::Older(t) => json!({ "type": "older", "value": t.to_consensus_u32() }),
+        Terminal::Sha256(ref h) => json!({ "type": "sha256", "hash": h.to_string() }),
+        Terminal::Hash256(ref h) => json!({ "type": "hash256", "hash": h.to_string() }),
+        Terminal::Ripemd160(ref h) => json!({ "type": "ripemd160", "hash": h.to_string() }),
+        Terminal::Hash160(ref h) => json!({ "type": "hash160", "hash": h.to_string() }),
+        Terminal::Alt(ref sub) => wrapper("a", sub),
+        Terminal::Swap(ref sub) => wrapper("s", sub),
+        Terminal::Check(ref sub) => wrapper("c", sub),
+        Terminal::DupIf(ref sub) => wrapper("d", sub),
+        Terminal::Verify(ref sub) => wrapper("v", sub),
+        Terminal::NonZero(ref sub) => wrapper("j", sub),
+        Terminal::ZeroNotEqual(ref sub) => wrapper("n", sub),
+        Terminal::AndV(ref l, ref r) => binary("and_v", l, r),
+        Terminal::AndB(ref l, ref r) => binary("and_b", l, r),
+        Terminal::AndOr(ref a, ref b, ref c) => json!({
+            "type": "andor",
+            "children": [ms_json(a), ms_json(b), ms_json(c)],
+        }),
+        Terminal::OrB(ref l, ref r) => binary("or_b", l, r),
+        Terminal::OrD(ref l, ref r) => binary("or_d", l, r),
+        Terminal::OrC(ref l, ref r) => binary("or_c", l, r),
+        Terminal::OrI(ref l, ref r) => binary("or_i", l, r),
+        Terminal::Thresh(ref thresh) => json!({
+            "type": "thresh",
+            "k": thresh.k(),
+            "children": thresh.iter().map(|sub| ms_json(sub)).collect::<Vec<_>>(),
+        }),
+        Terminal::Multi(ref thresh) => multi("multi", thresh),
+        Terminal::MultiA(ref thresh) => multi("multi_a", thresh),
+        Terminal::Ext(ref ext) => json!({ "type": "ext", "value": ext.to_string() }),
+    }
+}
+
+fn tr_json<Pk: JsonKey, Ext: Extension>(ty: &str, tr: &Tr<Pk, Ext>) -> Value {
+    let leaves = tr
+        .iter_scripts()
+        .map(|(depth, ms)| json!({ "depth": depth, "script": ms_json(ms) }))
+        .collect::<Vec<_>>();
+    json!({
+        "type": ty,
+        "internal_key": tr.internal_key().to_json(),
+        "leaves": leaves,
+    })
+}
+
+impl<Pk: JsonKey, Ext: Extension> DescriptorVisitor<Pk, Ext> for JsonBuilder {
+    fn visit_bare(&mut self, bare: &Bare<Pk>) {
+        self.0 = json!({ "type": "bare", "script": ms_json(bare.as_inner()) });
+    }
+
+    fn visit_pkh(&mut self, pkh: &Pkh<Pk>) {
+        self.0 = json!({ "type": "pkh", "key": pkh.as_inner().to_json() });
+    }
+
+    fn visit_wpkh(&mut self, wpkh: &Wpkh<Pk>) {
+        self.0 = json!({ "type": "wpkh", "key": wpkh.as_inner().to_json() });
+    }
+
+    fn visit_sh(&mut self, sh: &Sh<Pk>) {
+        let inner = match *sh.as_inner() {
+            ShInner::Wsh(ref wsh) => {
+                <Self as DescriptorVisitor<Pk, Ext>>::visit_wsh(self, wsh);
+                self.0.take()
+            }
+            ShInner::Wpkh(ref wpkh) => {
+                <Self as DescriptorVisitor<Pk, Ext>>::visit_wpkh(self, wpkh);
+                self.0.take()
+            }
+            ShInner::SortedMulti(ref smv) => sortedmulti_json(smv),
+            ShInner::Ms(ref ms) => ms_json(ms),
+        };
+        self.0 = json!({ "type": "sh", "inner": inner });
+    }
+
+    fn visit_wsh(&mut self, wsh: &Wsh<Pk>) {
+        let inner = match *wsh.as_inner() {
+            WshInner::SortedMulti(ref smv) => sortedmulti_json(smv),
+            WshInner::Ms(ref ms) => ms_json(ms),
+        };
+        self.0 = json!({ "type": "wsh", "inner": inner });
+    }
+
+    fn visit_tr(&mut self, tr: &Tr<Pk, NoExt>) {
+        self.0 = tr_json("tr", tr);
+    }
+
+    fn visit_tr_ext(&mut self, tr: &Tr<Pk, Ext>) {
+        self.0 = tr_json("tr_ext", tr);
+    }
+
+    fn visit_cov(&mut self, cov: &LegacyCSFSCov<Pk, Ext>) {
+        self.0 = json!({
+            "type": "elcovwsh",
+            "key": cov.pk().to_json(),
+            "inner": ms_json(cov.to_ms()),
+        });
+    }
+
+    fn visit_wit(&mut self, wit: &Wit) {
+        self.0 = json!({
+            "type": "wit",
+            "version": wit.version(),
+            "program": wit.program().to_hex(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn json_tree() {
+        let desc = Descriptor::<String>::from_str(
+            "elwsh(or_d(multi(2,A,B),and_v(v:pk(C),and_v(v:sha256(H),older(144)))))",
+        )
+        .unwrap();
+        assert_eq!(
+            desc.to_json_tree(),
+            json!({
+                "type": "wsh",
+                "inner": {
+                    "type": "or_d",
+                    "children": [
+                        { "type": "multi", "k": 2, "keys": [{ "key": "A" }, { "key": "B" }] },
+                        {
+                            "type": "and_v",
+                            "children": [
+                                {
+                                    "type": "v",
+                                    "children": [{
+                                        "type": "c",
+                                        "children": [{ "type": "pk_k", "key": { "key": "C" } }],
+                                    }],
+                                },
+                                {
+                                    "type": "and_v",
+                                    "children": [
+                                        {
+                                            "type": "v",
+                                            "children": [{ "type": "sha256", "hash": "H" }],
+                                        },
+                                        { "type": "older", "value": 144 },
+                                    ],
+                                },
+                            ],
+                        },
+                    ],
+                },
+            })
+        );
+
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(
+            "elsh(wpkh([d34db33f/44'/0'/0']03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd))",
+        )
+        .unwrap();
+        assert_eq!(
+            desc.to_json_tree(),
+            json!({
+                "type": "sh",
+                "inner": {
+                    "type": "wpkh",
+                    "key": {
+                        "key": "[d34db33f/44'/0'/0']03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+                        "origin": { "fingerprint": "d34db33f", "path": "m/44'/0'/0'" },
+                    },
+                },
+            })
+        );
+
+        let desc = Descriptor::<String>::from_str("eltr(A,{pk(B),curr_idx_eq(1)})").unwrap();
+        let tree = desc.to_json_tree();
+        assert_eq!(tree["type"], "tr_ext");
+        assert_eq!(tree["leaves"][1]["depth"], 1);
+        assert_eq!(
+            tree["leaves"][1]["script"],
+            json!({ "type": "ext", "value": "curr_idx_eq(1)" })
+        );
+    }
+}
@@ -9,6 +9,7 @@ use bitcoin::{self, bip32};
 use elements::hashes::{hash160, ripemd160, sha256, Hash, HashEngine};
 use elements::secp256k1_zkp::{Secp256k1, Signing, Verification};
 
+use super::XpubCache;
 #[cfg(feature = "serde")]
 use crate::serde::{Deserialize, Deserializer, Serialize, Serializer};
 use crate::{hash256, MiniscriptKey, ToPublicKey};
@@ -499,6 +500,8 @@ pub enum ConversionError {
     HardenedChild,
     /// Attempted to convert a key with multiple derivation paths to a bitcoin public key
     MultiKey,
+    /// Attempted to use an x-only key where a full public key is required
+    XOnlyKey,
 }
 
 impl fmt::Display for ConversionError {
@@ -506,6 +509,7 @@ impl fmt::Display for ConversionError {
         f.write_str(match *self {
             ConversionError::HardenedChild => "hardened child step in bip32 path",
             ConversionError::MultiKey => "multiple existing keys",
+            ConversionError::XOnlyKey => "x-only key where a full public key is required",
         })
     }
 }
@@ -515,7 +519,7 @@ impl error::Error for ConversionError {
         use self::ConversionError::*;
 
         match self {
-            HardenedChild | MultiKey => None,
+            HardenedChild | MultiKey | XOnlyKey => None,
         }
     }
 }
@@ -587,6 +591,72 @@ impl DescriptorPublicKey {
         }
     }
 
+    /// Full paths, from the master key, one per derivation path of the key
+    ///
+    /// This is [`Self::full_derivation_path`], also returning the paths of
+    /// multipath extended keys.
+    pub fn full_derivation_paths(&self) -> Vec<bip32::DerivationPath> {
+        match *self {
+            DescriptorPublicKey::MultiXPub(ref xpub) => {
+                let origin_path = if let Some((_, ref path)) = xpub.origin {
+                    path.clone()
+                } else {
+                    bip32::DerivationPath::from(vec![])
+                };
+                xpub.derivation_paths
+                    .paths()
+                    .iter()
+                    .map(|path| origin_path.extend(path))
+                    .collect()
+            }
+            _ => self.full_derivation_path().into_iter().collect(),
+        }
+    }
+
+    /// The origin information of the key, i.e. the fingerprint of its master
+    /// key and the path from it, if any
+    pub fn origin(&self) -> Option<&bip32::KeySource> {
+        match *self {
+            DescriptorPublicKey::Single(ref single) => single.origin.as_ref(),
+            DescriptorPublicKey::XPub(ref xpub) => xpub.origin.as_ref(),
+            DescriptorPublicKey::MultiXPub(ref xpub) => xpub.origin.as_ref(),
+        }
+    }
+
+    /// Returns the key with its origin information replaced by `origin`
+    pub fn with_origin(self, origin: Option<bip32::KeySource>) -> DescriptorPublicKey {
+        match self {
+            DescriptorPublicKey::Single(single) => {
+                DescriptorPublicKey::Single(SinglePub { origin, ..single })
+            }
+            DescriptorPublicKey::XPub(xpub) => {
+                DescriptorPublicKey::XPub(DescriptorXKey { origin, ..xpub })
+            }
+            DescriptorPublicKey::MultiXPub(xpub) => {
+                DescriptorPublicKey::MultiXPub(DescriptorMultiXKey { origin, ..xpub })
+            }
+        }
+    }
+
+    /// Returns the key re-rooted at the master key with fingerprint
+    /// `fingerprint`, from which the current master key is derived at
+    /// `path`, e.g. after importing the key on a signing device where it is
+    /// derived from another seed.
+    ///
+    /// The origin path of the key is appended to `path`. A key without
+    /// origin is considered to be its own master key.
+    pub fn rerooted(
+        self,
+        fingerprint: bip32::Fingerprint,
+        path: &bip32::DerivationPath,
+    ) -> DescriptorPublicKey {
+        let origin_path = match self.origin() {
+            Some((_, origin_path)) => path.extend(origin_path),
+            None => path.clone(),
+        };
+        self.with_origin(Some((fingerprint, origin_path)))
+    }
+
     /// Whether or not the key has a wildcard
     #[deprecated(note = "use has_wildcard instead")]
     pub fn is_deriveable(&self) -> bool {
@@ -1009,6 +1079,25 @@ impl DefiniteDescriptorKey {
     pub fn derive_public_key<C: Verification>(
         &self,
         secp: &Secp256k1<C>,
+    ) -> Result<bitcoin::PublicKey, ConversionError> {
+        self.derive_public_key_inner(secp, None)
+    }
+
+    /// Computes the public key corresponding to this descriptor key, as
+    /// [`Self::derive_public_key`] does, reusing the intermediate derivations
+    /// of `cache`
+    pub fn derive_public_key_cached<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        cache: &mut XpubCache,
+    ) -> Result<bitcoin::PublicKey, ConversionError> {
+        self.derive_public_key_inner(secp, Some(cache))
+    }
+
+    fn derive_public_key_inner<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        cache: Option<&mut XpubCache>,
     ) -> Result<bitcoin::PublicKey, ConversionError> {
         match self.0 {
             DescriptorPublicKey::Single(ref pk) => match pk.key {
@@ -1019,13 +1108,19 @@ impl DefiniteDescriptorKey {
                 Wildcard::Unhardened | Wildcard::Hardened => {
                     unreachable!("we've excluded this error case")
                 }
-                Wildcard::None => match xpk.xkey.derive_pub(secp, &xpk.derivation_path.as_ref()) {
-                    Ok(xpub) => Ok(bitcoin::PublicKey::new(xpub.public_key)),
-                    Err(bip32::Error::CannotDeriveFromHardenedKey) => {
-                        Err(ConversionError::HardenedChild)
+                Wildcard::None => {
+                    let derived = match cache {
+                        Some(cache) => cache.derive_pub(secp, &xpk.xkey, &xpk.derivation_path),
+                        None => xpk.xkey.derive_pub(secp, &xpk.derivation_path.as_ref()),
+                    };
+                    match derived {
+                        Ok(xpub) => Ok(bitcoin::PublicKey::new(xpub.public_key)),
+                        Err(bip32::Error::CannotDeriveFromHardenedKey) => {
+                            Err(ConversionError::HardenedChild)
+                        }
+                        Err(e) => unreachable!("cryptographically unreachable: {}", e),
                     }
-                    Err(e) => unreachable!("cryptographically unreachable: {}", e),
-                },
+                }
             },
             DescriptorPublicKey::MultiXPub(_) => {
                 unreachable!("A definite key cannot contain a multipath key.")
@@ -1136,6 +1231,62 @@ impl Borrow<DescriptorPublicKey> for DefiniteDescriptorKey {
     }
 }
 
+/// A placeholder for a key, with an optional origin, e.g.
+/// `[d34db33f/48'/1776'/0'/2']Alice/<0;1>/*`
+///
+/// The name of the key is kept as is, without being interpreted as an actual
+/// key, so that descriptors and policies using it can be designed, analyzed
+/// and displayed before the keys are generated, without losing their origin.
+/// Hashes are kept as strings too, as for `String` keys.
+#[derive(Debug, Eq, PartialEq, Clone, Ord, PartialOrd, Hash)]
+pub struct OriginKey {
+    /// Origin of the key, i.e. the fingerprint of its master key and the
+    /// derivation path from it
+    pub origin: Option<bip32::KeySource>,
+    /// Name of the key, with its derivation path if any
+    pub name: String,
+}
+
+impl OriginKey {
+    /// Creates a placeholder key named `name`, derived from `origin`
+    pub fn new(name: String, origin: Option<bip32::KeySource>) -> Self {
+        OriginKey { origin, name }
+    }
+}
+
+impl FromStr for OriginKey {
+    type Err = DescriptorKeyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, origin) = parse_key_origin(s)?;
+        if name.is_empty() {
+            return Err(DescriptorKeyParseError("No key after origin."));
+        }
+        Ok(OriginKey {
+            origin,
+            name: name.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for OriginKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        maybe_fmt_master_id(f, &self.origin)?;
+        f.write_str(&self.name)
+    }
+}
+
+impl MiniscriptKey for OriginKey {
+    type Sha256 = String;
+    type Hash256 = String;
+    type Ripemd160 = String;
+    type Hash160 = String;
+
+    fn num_der_paths(&self) -> usize {
+        0
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for DescriptorPublicKey {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -1354,6 +1505,48 @@ mod test {
         );
     }
 
+    #[test]
+    fn origin_rewriting() {
+        let key = DescriptorPublicKey::from_str(
+            "[78412e3a/44'/0'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/<0;1>/*",
+        )
+        .unwrap();
+        assert_eq!(key.full_derivation_path(), None);
+        assert_eq!(
+            key.full_derivation_paths(),
+            vec![
+                bip32::DerivationPath::from_str("m/44'/0'/0'/0").unwrap(),
+                bip32::DerivationPath::from_str("m/44'/0'/0'/1").unwrap(),
+            ]
+        );
+
+        let fingerprint = bip32::Fingerprint::from_str("deadbeef").unwrap();
+        let path = bip32::DerivationPath::from_str("m/1'").unwrap();
+        let rerooted = key.clone().rerooted(fingerprint, &path);
+        assert_eq!(rerooted.master_fingerprint(), fingerprint);
+        assert_eq!(
+            rerooted.full_derivation_paths()[1],
+            bip32::DerivationPath::from_str("m/1'/44'/0'/0'/1").unwrap()
+        );
+        assert_eq!(
+            rerooted.to_string(),
+            key.to_string().replace("[78412e3a/44'", "[deadbeef/1'/44'")
+        );
+        assert_eq!(rerooted.with_origin(None).origin(), None);
+
+        // A key without origin is its own master key
+        let single = DescriptorPublicKey::from_str(
+            "03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8",
+        )
+        .unwrap();
+        assert_eq!(
+            single.full_derivation_paths(),
+            vec![bip32::DerivationPath::master()]
+        );
+        let rerooted = single.rerooted(fingerprint, &path);
+        assert_eq!(rerooted.origin(), Some(&(fingerprint, path)));
+    }
+
     fn get_multipath_xpub(
         key_str: &str,
         num_paths: usize,
@@ -1543,6 +1736,30 @@ mod test {
         DescriptorPublicKey::from_str("tpubDBrgjcxBxnXyL575sHdkpKohWu5qHKoQ7TJXKNrYznh5fVEGBv89hA8ENW7A8MFVpFUSvgLqc4Nj1WZcpePX6rrxviVtPowvMuGF5rdT2Vi/2/4/<0;1;>").unwrap_err();
     }
 
+    #[test]
+    fn origin_key() {
+        let key = OriginKey::from_str("[d34db33f/48'/1776'/0'/2']Alice/<0;1>/*").unwrap();
+        assert_eq!(key.name, "Alice/<0;1>/*");
+        assert_eq!(
+            key.origin,
+            Some((
+                bip32::Fingerprint::from_str("d34db33f").unwrap(),
+                bip32::DerivationPath::from_str("m/48'/1776'/0'/2'").unwrap()
+            ))
+        );
+        assert_eq!(key.to_string(), "[d34db33f/48'/1776'/0'/2']Alice/<0;1>/*");
+        assert_eq!(OriginKey::from_str("Bob").unwrap().origin, None);
+        assert!(OriginKey::from_str("[d34db33f/48']").is_err());
+        assert!(OriginKey::from_str("[d34db3/48']Bob").is_err());
+
+        // Round trip through a descriptor
+        let desc = "elwsh(or_d(pk([d34db33f/48'/1776'/0'/2']Alice/<0;1>/*),\
+                    and_v(v:pk([cafebabe/48'/1776'/0'/2']Bob/<0;1>/*),older(144))))";
+        let parsed = crate::Descriptor::<OriginKey>::from_str(desc).unwrap();
+        parsed.sanity_check().unwrap();
+        assert_eq!(parsed.to_string().split('#').next().unwrap(), desc);
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn test_descriptor_public_key_serde() {
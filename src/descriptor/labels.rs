@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Wallet Labels
+//!
+//! Export and import of wallet labels in the BIP-329 format, i.e. one JSON
+//! object per line, so that labels can be moved between wallets. Every
+//! record references a transaction, an address, a key or an outpoint, and
+//! optionally the descriptor of the wallet it belongs to, as its `origin`.
+//!
+
+use std::str::FromStr;
+use std::{error, fmt};
+
+use bitcoin::bip32;
+use elements::{Address, OutPoint, Txid};
+use serde_json::{json, Map, Value};
+
+use super::{Descriptor, DescriptorPublicKey};
+use crate::{hash256, Extension, TranslatePk, Translator};
+
+/// The item referenced by a [`Label`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LabelRef {
+    /// A transaction
+    Tx(Txid),
+    /// An address
+    Addr(Address),
+    /// A public key
+    Pubkey(bitcoin::PublicKey),
+    /// A transaction input, by the outpoint it spends
+    Input(OutPoint),
+    /// A transaction output
+    Output(OutPoint),
+    /// An extended public key
+    Xpub(bip32::ExtendedPubKey),
+}
+
+impl LabelRef {
+    /// The `type` of the record
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            LabelRef::Tx(..) => "tx",
+            LabelRef::Addr(..) => "addr",
+            LabelRef::Pubkey(..) => "pubkey",
+            LabelRef::Input(..) => "input",
+            LabelRef::Output(..) => "output",
+            LabelRef::Xpub(..) => "xpub",
+        }
+    }
+
+    // The `ref` of the record
+    fn to_ref_string(&self) -> String {
+        match *self {
+            LabelRef::Tx(ref txid) => txid.to_string(),
+            LabelRef::Addr(ref addr) => addr.to_string(),
+            LabelRef::Pubkey(ref pk) => pk.to_string(),
+            // Without the `[elements]` prefix of their display
+            LabelRef::Input(ref op) | LabelRef::Output(ref op) => {
+                format!("{}:{}", op.txid, op.vout)
+            }
+            LabelRef::Xpub(ref xpub) => xpub.to_string(),
+        }
+    }
+
+    // Parses the `ref` of a record of the given type, `None` for an unknown
+    // type
+    fn from_type_and_ref(ty: &str, s: &str) -> Option<Result<Self, String>> {
+        fn parse<T: FromStr>(s: &str) -> Result<T, String>
+        where
+            T::Err: fmt::Display,
+        {
+            T::from_str(s).map_err(|e| e.to_string())
+        }
+        Some(match ty {
+            "tx" => parse(s).map(LabelRef::Tx),
+            "addr" => parse(s).map(LabelRef::Addr),
+            "pubkey" => parse(s).map(LabelRef::Pubkey),
+            "input" => parse(s).map(LabelRef::Input),
+            "output" => parse(s).map(LabelRef::Output),
+            "xpub" => parse(s).map(LabelRef::Xpub),
+            _ => return None,
+        })
+    }
+}
+
+/// A BIP-329 label record
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Label {
+    /// The labelled item
+    pub reference: LabelRef,
+    /// The label
+    pub label: String,
+    /// The wallet of the item, as an abbreviated descriptor, see [`origin`]
+    pub origin: Option<String>,
+    /// Whether an output can be spent, only for [`LabelRef::Output`]
+    pub spendable: Option<bool>,
+}
+
+impl Label {
+    /// A label for an item, without origin
+    pub fn new(reference: LabelRef, label: String) -> Self {
+        Label {
+            reference,
+            label,
+            origin: None,
+            spendable: None,
+        }
+    }
+
+    /// Sets the origin of the label to the wallet of the descriptor
+    pub fn with_origin<Ext: Extension>(
+        mut self,
+        desc: &Descriptor<DescriptorPublicKey, Ext>,
+    ) -> Self {
+        self.origin = Some(origin(desc));
+        self
+    }
+
+    /// The record as a JSON object
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "type": self.reference.type_name(),
+            "ref": self.reference.to_ref_string(),
+            "label": self.label,
+        });
+        if let Some(ref origin) = self.origin {
+            value["origin"] = json!(origin);
+        }
+        if let Some(spendable) = self.spendable {
+            value["spendable"] = json!(spendable);
+        }
+        value
+    }
+
+    // Parses a record, `None` for a record of unknown type
+    fn from_json(line: usize, value: &Map<String, Value>) -> Result<Option<Self>, LabelError> {
+        let field = |name: &'static str| -> Result<Option<&Value>, LabelError> {
+            match value.get(name) {
+                None | Some(Value::Null) => Ok(None),
+                Some(v) => Ok(Some(v)),
+            }
+        };
+        let string = |name: &'static str| -> Result<Option<String>, LabelError> {
+            match field(name)? {
+                None => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.clone())),
+                Some(_) => Err(LabelError::InvalidField(line, name)),
+            }
+        };
+
+        let ty = string("type")?.ok_or(LabelError::InvalidField(line, "type"))?;
+        let reference = string("ref")?.ok_or(LabelError::InvalidField(line, "ref"))?;
+        let reference = match LabelRef::from_type_and_ref(&ty, &reference) {
+            Some(reference) => reference.map_err(|e| LabelError::InvalidRef(line, e))?,
+            None => return Ok(None),
+        };
+        let spendable = match field("spendable")? {
+            None => None,
+            Some(&Value::Bool(b)) => Some(b),
+            Some(_) => return Err(LabelError::InvalidField(line, "spendable")),
+        };
+        Ok(Some(Label {
+            reference,
+            // Records may omit the label, e.g. to only set `spendable`
+            label: string("label")?.unwrap_or_default(),
+            origin: string("origin")?,
+            spendable,
+        }))
+    }
+}
+
+/// Error when importing labels
+#[derive(Debug)]
+pub enum LabelError {
+    /// A line, numbered from 1, is not a JSON object
+    Json(usize, Option<serde_json::Error>),
+    /// A record is missing a field, or a field has the wrong type
+    InvalidField(usize, &'static str),
+    /// The reference of a record cannot be parsed
+    InvalidRef(usize, String),
+}
+
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LabelError::Json(line, Some(ref e)) => write!(f, "line {}: {}", line, e),
+            LabelError::Json(line, None) => write!(f, "line {}: not a JSON object", line),
+            LabelError::InvalidField(line, field) => {
+                write!(f, "line {}: missing or invalid field {}", line, field)
+            }
+            LabelError::InvalidRef(line, ref e) => write!(f, "line {}: invalid ref: {}", line, e),
+        }
+    }
+}
+
+impl error::Error for LabelError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            LabelError::Json(_, Some(ref e)) => Some(e),
+            LabelError::Json(_, None)
+            | LabelError::InvalidField(..)
+            | LabelError::InvalidRef(..) => None,
+        }
+    }
+}
+
+/// The origin of labels in the wallet of `desc`: the descriptor without
+/// checksum, with every key replaced by its origin, e.g.
+/// `elwpkh([d34db33f/84'/1776'/0'])`
+///
+/// Keys without origin are replaced by their fingerprint, see
+/// [`DescriptorPublicKey::master_fingerprint`].
+pub fn origin<Ext: Extension>(desc: &Descriptor<DescriptorPublicKey, Ext>) -> String {
+    struct OriginTranslator;
+
+    impl Translator<DescriptorPublicKey, String, ()> for OriginTranslator {
+        fn pk(&mut self, pk: &DescriptorPublicKey) -> Result<String, ()> {
+            let origin = match *pk {
+                DescriptorPublicKey::Single(ref single) => &single.origin,
+                DescriptorPublicKey::XPub(ref xpub) => &xpub.origin,
+                DescriptorPublicKey::MultiXPub(ref xpub) => &xpub.origin,
+            };
+            Ok(match *origin {
+                // The path is displayed as `m/..`
+                Some((fingerprint, ref path)) if !path.is_empty() => {
+                    format!("[{}{}]", fingerprint, &path.to_string()[1..])
+                }
+                _ => format!("[{}]", pk.master_fingerprint()),
+            })
+        }
+
+        fn sha256(&mut self, sha256: &bitcoin::hashes::sha256::Hash) -> Result<String, ()> {
+            Ok(sha256.to_string())
+        }
+
+        fn hash256(&mut self, hash256: &hash256::Hash) -> Result<String, ()> {
+            Ok(hash256.to_string())
+        }
+
+        fn ripemd160(
+            &mut self,
+            ripemd160: &bitcoin::hashes::ripemd160::Hash,
+        ) -> Result<String, ()> {
+            Ok(ripemd160.to_string())
+        }
+
+        fn hash160(&mut self, hash160: &bitcoin::hashes::hash160::Hash) -> Result<String, ()> {
+            Ok(hash160.to_string())
+        }
+    }
+
+    let desc = desc
+        .translate_pk(&mut OriginTranslator)
+        .expect("translation to string cannot fail")
+        .to_string();
+    match desc.find('#') {
+        Some(i) => desc[..i].to_owned(),
+        None => desc,
+    }
+}
+
+/// Exports labels as BIP-329 JSON lines
+pub fn export<'a, I: IntoIterator<Item = &'a Label>>(labels: I) -> String {
+    let mut out = String::new();
+    for label in labels {
+        out.push_str(&label.to_json().to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// Imports labels from BIP-329 JSON lines
+///
+/// Empty lines and records of unknown types are skipped, as required by
+/// BIP-329, while malformed records are errors.
+pub fn import(s: &str) -> Result<Vec<Label>, LabelError> {
+    let mut labels = vec![];
+    for (i, line) in s.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value =
+            serde_json::from_str::<Value>(line).map_err(|e| LabelError::Json(i + 1, Some(e)))?;
+        let record = value.as_object().ok_or(LabelError::Json(i + 1, None))?;
+        labels.extend(Label::from_json(i + 1, record)?);
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_import() {
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(
+            "elwpkh([d34db33f/84'/1776'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*)",
+        )
+        .unwrap();
+        assert_eq!(origin(&desc), "elwpkh([d34db33f/84'/1776'/0'])");
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(
+            "elwsh(multi(1,[d34db33f]03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd,[ffffffff/1]03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8))",
+        )
+        .unwrap();
+        assert_eq!(origin(&desc), "elwsh(multi(1,[d34db33f],[ffffffff/1]))");
+
+        let txid = "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd";
+        let mut output = Label::new(
+            LabelRef::Output(OutPoint::from_str(&format!("{}:1", txid)).unwrap()),
+            "change".to_owned(),
+        )
+        .with_origin(&desc);
+        output.spendable = Some(false);
+        let labels = vec![
+            Label::new(
+                LabelRef::Tx(Txid::from_str(txid).unwrap()),
+                "Transaction".to_owned(),
+            ),
+            output,
+        ];
+        let exported = export(&labels);
+        assert_eq!(
+            exported.lines().nth(1).unwrap(),
+            format!(
+                "{{\"label\":\"change\",\"origin\":\"elwsh(multi(1,[d34db33f],[ffffffff/1]))\",\"ref\":\"{}:1\",\"spendable\":false,\"type\":\"output\"}}",
+                txid
+            )
+        );
+        assert_eq!(import(&exported).unwrap(), labels);
+
+        // Unknown types are skipped, malformed records are not
+        let unknown = "\n{\"type\": \"tapscript\", \"ref\": \"00\", \"label\": \"x\"}\n";
+        assert_eq!(import(unknown).unwrap(), vec![]);
+        assert!(matches!(import("[]"), Err(LabelError::Json(1, None))));
+        assert!(matches!(
+            import("{\"type\": \"tx\", \"ref\": \"00\"}"),
+            Err(LabelError::InvalidRef(1, _))
+        ));
+        assert!(matches!(
+            import("{\"type\": \"tx\"}"),
+            Err(LabelError::InvalidField(1, "ref"))
+        ));
+    }
+}
@@ -18,46 +18,88 @@ use std::ops::Range;
 use std::str::{self, FromStr};
 use std::sync::Arc;
 
+pub mod coin_selection;
+pub mod htlc;
 pub mod pegin;
+pub mod pegout;
 
 use bitcoin::address::WitnessVersion;
+use bitcoin::bip32;
 use elements::hashes::{hash160, ripemd160, sha256};
 use elements::{secp256k1_zkp as secp256k1, secp256k1_zkp, Script, TxIn};
 use {bitcoin, elements};
 
 use self::checksum::verify_checksum;
+use self::diff::descriptor_tree;
 use crate::extensions::{CovExtArgs, ExtParam, ParseableExt};
 use crate::miniscript::{Legacy, Miniscript, Segwitv0};
 use crate::{
-    expression, hash256, miniscript, BareCtx, CovenantExt, Error, ExtTranslator, Extension,
-    ForEachKey, MiniscriptKey, NoExt, Satisfier, ToPublicKey, TranslateExt, TranslatePk,
-    Translator,
+    expression, hash256, miniscript, BareCtx, CovenantExt, Error, ExtParams, ExtTranslator,
+    Extension, ForEachKey, MiniscriptKey, NoExt, Satisfier, Terminal, ToPublicKey, TranslateExt,
+    TranslatePk, Translator,
 };
 
 mod bare;
 mod blinded;
 mod csfs_cov;
+mod diff;
+#[cfg(feature = "json")]
+mod json;
+mod musig;
+#[cfg(feature = "rand")]
+mod random;
+mod roles;
 mod segwitv0;
 mod sh;
 mod sortedmulti;
+mod standardness;
 mod tr;
+mod visitor;
+mod wit;
+mod witness_builder;
+mod xpub_cache;
 
 // Descriptor Exports
 pub use self::bare::{Bare, Pkh};
 pub use self::blinded::Blinded;
+pub use self::diff::{DescriptorChange, DescriptorDiff};
+#[cfg(feature = "json")]
+pub use self::json::JsonKey;
+pub use self::musig::{key_agg, key_agg_with_secp, MusigKey};
+#[cfg(feature = "rand")]
+pub use self::random::RandomParams;
+pub use self::roles::{KeyRole, KeyRoles};
 pub use self::segwitv0::{Wpkh, Wsh, WshInner};
 pub use self::sh::{Sh, ShInner};
-pub use self::sortedmulti::SortedMultiVec;
+pub use self::sortedmulti::{SortedMultiBuilder, SortedMultiError, SortedMultiVec};
+pub use self::standardness::{
+    RelayPolicy, StandardnessViolation, DUST_RELAY_TX_FEE, MAX_OP_RETURN_RELAY,
+};
+pub use self::visitor::DescriptorVisitor;
+pub use self::wit::Wit;
+pub use self::witness_builder::WitnessBuilder;
+pub use self::xpub_cache::XpubCache;
 
 pub mod checksum;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 mod key;
-pub use self::csfs_cov::{CovError, CovOperations, LegacyCSFSCov, LegacyCovSatisfier};
+#[cfg(feature = "json")]
+pub mod labels;
+mod params;
+pub mod rpc;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+pub use self::csfs_cov::{CovCheck, CovError, CovOperations, LegacyCSFSCov, LegacyCovSatisfier};
 pub use self::key::{
     ConversionError, DefiniteDescriptorKey, DerivPaths, DescriptorKeyParseError,
     DescriptorMultiXKey, DescriptorPublicKey, DescriptorSecretKey, DescriptorXKey, InnerXKey,
-    SinglePriv, SinglePub, SinglePubKey, Wildcard,
+    OriginKey, SinglePriv, SinglePub, SinglePubKey, Wildcard,
+};
+pub use self::params::{AddressMismatch, CustomAddressParams};
+pub use self::tr::{
+    nums_point, unspendable_key, unspendable_key_with_secp, SpendPath, TapTree, Tr, NUMS_POINT,
 };
-pub use self::tr::{TapTree, Tr};
 /// Alias type for a map of public key to secret key
 ///
 /// This map is returned whenever a descriptor that contains secrets is parsed using
@@ -69,6 +111,16 @@ pub type KeyMap = HashMap<DescriptorPublicKey, DescriptorSecretKey>;
 /// Elements Descriptor String Prefix
 pub const ELMTS_STR: &str = "el";
 
+/// Converts an Elements descriptor string into a Bitcoin one by removing the
+/// Elements prefix and recomputing the checksum
+pub(super) fn strip_elements_prefix(s: &str) -> Result<String, Error> {
+    let desc_str = verify_checksum(s)?;
+    let btc_str = desc_str.strip_prefix(ELMTS_STR).ok_or_else(|| {
+        Error::BadDescriptor(format!("Descriptor must start with `{}`", ELMTS_STR))
+    })?;
+    Ok(format!("{}#{}", btc_str, checksum::desc_checksum(btc_str)?))
+}
+
 /// Descriptor Type of the descriptor
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum DescriptorType {
@@ -100,6 +152,10 @@ pub enum DescriptorType {
     Cov,
     /// Tr
     Tr,
+    /// Tr with Elements extensions, e.g. introspection covenants
+    TrExt,
+    /// Witness program of a version unknown to this crate
+    Wit,
 }
 
 impl fmt::Display for DescriptorType {
@@ -119,6 +175,8 @@ impl fmt::Display for DescriptorType {
             DescriptorType::Pegin => write!(f, "pegin"),
             DescriptorType::Cov => write!(f, "elcovwsh"),
             DescriptorType::Tr => write!(f, "tr"),
+            DescriptorType::TrExt => write!(f, "tr_ext"),
+            DescriptorType::Wit => write!(f, "wit"),
         }
     }
 }
@@ -152,6 +210,8 @@ impl FromStr for DescriptorType {
             Ok(DescriptorType::Wsh)
         } else if s.len() >= 6 && &s[0..6] == "covwsh" {
             Ok(DescriptorType::Cov)
+        } else if s.len() >= 3 && &s[0..3] == "wit" {
+            Ok(DescriptorType::Wit)
         } else {
             Ok(DescriptorType::Bare)
         }
@@ -211,7 +271,7 @@ impl DescriptorInfo {
 }
 
 /// Script descriptor
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Descriptor<Pk: MiniscriptKey, T: Extension = CovenantExt<CovExtArgs>> {
     /// A raw scriptpubkey (including pay-to-pubkey) under Legacy context
     Bare(Bare<Pk>),
@@ -230,6 +290,9 @@ pub enum Descriptor<Pk: MiniscriptKey, T: Extension = CovenantExt<CovExtArgs>> {
     /// Covenant descriptor with all known extensions
     /// Downstream implementations of extensions should implement directly use descriptor API
     LegacyCSFSCov(LegacyCSFSCov<Pk, T>),
+    /// Witness program of a future witness version, which can be tracked
+    /// but not spent
+    Wit(Wit),
 }
 
 impl<Pk: MiniscriptKey, Ext: Extension> From<Bare<Pk>> for Descriptor<Pk, Ext> {
@@ -274,6 +337,13 @@ impl<Pk: MiniscriptKey, Ext: Extension> From<Tr<Pk, NoExt>> for Descriptor<Pk, E
     }
 }
 
+impl<Pk: MiniscriptKey, Ext: Extension> From<Wit> for Descriptor<Pk, Ext> {
+    #[inline]
+    fn from(inner: Wit) -> Self {
+        Descriptor::Wit(inner)
+    }
+}
+
 impl<Pk: MiniscriptKey, Arg: ExtParam> From<LegacyCSFSCov<Pk, CovenantExt<Arg>>>
     for Descriptor<Pk, CovenantExt<Arg>>
 {
@@ -290,7 +360,7 @@ impl DescriptorType {
     pub fn segwit_version(&self) -> Option<WitnessVersion> {
         use self::DescriptorType::*;
         match self {
-            Tr => Some(WitnessVersion::V1),
+            Tr | TrExt => Some(WitnessVersion::V1),
             Wpkh | ShWpkh | Wsh | ShWsh | ShWshSortedMulti | WshSortedMulti => {
                 Some(WitnessVersion::V0)
             }
@@ -298,8 +368,14 @@ impl DescriptorType {
             LegacyPegin => Some(WitnessVersion::V1),
             Pegin => None, // Can have any witness version
             Cov => None,   // Can have any witness version
+            Wit => None,   // Version is only known from the descriptor
         }
     }
+
+    /// Whether the descriptor type enforces covenants on the spending transaction
+    pub fn is_covenant(&self) -> bool {
+        matches!(self, DescriptorType::Cov | DescriptorType::TrExt)
+    }
 }
 
 impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
@@ -431,7 +507,8 @@ impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
             },
             Descriptor::LegacyCSFSCov(ref _cov) => DescriptorType::Cov,
             Descriptor::Tr(ref _tr) => DescriptorType::Tr,
-            Descriptor::TrExt(ref _tr) => DescriptorType::Tr,
+            Descriptor::TrExt(ref _tr) => DescriptorType::TrExt,
+            Descriptor::Wit(ref _wit) => DescriptorType::Wit,
         }
     }
 
@@ -439,6 +516,79 @@ impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
     pub fn to_string_no_chksum(&self) -> String {
         format!("{:?}", self)
     }
+
+    /// Converts the descriptor into the equivalent Bitcoin descriptor string,
+    /// as understood by rust-miniscript and Bitcoin Core, by removing the
+    /// Elements prefix. The returned string includes the checksum.
+    ///
+    /// Errors if the descriptor uses Elements-only constructs such as covenants
+    /// or extensions, which have no Bitcoin equivalent.
+    pub fn to_bitcoin_descriptor(&self) -> Result<String, Error> {
+        self.check_bitcoin_compatible()?;
+        strip_elements_prefix(&format!("{:#}", self))
+    }
+
+    /// Parses a Bitcoin descriptor string, as understood by rust-miniscript
+    /// and Bitcoin Core, into the equivalent Elements descriptor.
+    ///
+    /// The checksum is verified if present. Errors for Bitcoin-only
+    /// descriptors like `rawtr`, `addr`, `raw` or `combo`.
+    pub fn from_bitcoin_descriptor(s: &str) -> Result<Self, Error>
+    where
+        Self: FromStr<Err = Error>,
+    {
+        let desc_str = verify_checksum(s)?;
+        if desc_str.starts_with(ELMTS_STR) {
+            return Err(Error::BadDescriptor(format!(
+                "`{}` is already an Elements descriptor",
+                desc_str
+            )));
+        }
+        for name in ["rawtr", "addr", "raw", "combo"].iter() {
+            if desc_str.starts_with(&format!("{}(", name)) {
+                return Err(Error::BadDescriptor(format!(
+                    "Bitcoin descriptor `{}` has no Elements equivalent",
+                    name
+                )));
+            }
+        }
+        Self::from_str(&format!("{}{}", ELMTS_STR, desc_str))
+    }
+
+    /// Checks that the descriptor does not use any Elements-only construct
+    pub(super) fn check_bitcoin_compatible(&self) -> Result<(), Error> {
+        match *self {
+            Descriptor::LegacyCSFSCov(..) => Err(Error::BadDescriptor(format!(
+                "Covenant descriptor `{}covwsh` has no Bitcoin equivalent",
+                ELMTS_STR
+            ))),
+            Descriptor::Wit(..) => Err(Error::BadDescriptor(format!(
+                "Witness program descriptor `{}wit` has no Bitcoin equivalent",
+                ELMTS_STR
+            ))),
+            Descriptor::TrExt(ref tr) => {
+                let ext = tr.iter_scripts().find_map(|(_, ms)| {
+                    ms.iter().find_map(|node| match node.node {
+                        Terminal::Ext(ref e) => Some(e.to_string()),
+                        _ => None,
+                    })
+                });
+                match ext {
+                    Some(ext) => Err(Error::BadDescriptor(format!(
+                        "Taproot descriptor uses Elements extension `{}` which has no Bitcoin equivalent",
+                        ext
+                    ))),
+                    None => Ok(()),
+                }
+            }
+            Descriptor::Bare(..)
+            | Descriptor::Pkh(..)
+            | Descriptor::Wpkh(..)
+            | Descriptor::Sh(..)
+            | Descriptor::Wsh(..)
+            | Descriptor::Tr(..) => Ok(()),
+        }
+    }
     /// Checks whether the descriptor is safe.
     ///
     /// Checks whether all the spend paths in the descriptor are possible on the
@@ -458,7 +608,48 @@ impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
             Descriptor::LegacyCSFSCov(ref cov) => cov.sanity_check(),
             Descriptor::Tr(ref tr) => tr.sanity_check(),
             Descriptor::TrExt(ref tr) => tr.sanity_check(),
+            Descriptor::Wit(_) => Ok(()),
+        }
+    }
+
+    /// Checks the miniscripts of the descriptor against `ext`, which lists
+    /// the non-sane properties they are allowed to have, see
+    /// [`Miniscript::ext_check`].
+    ///
+    /// Unlike [`Descriptor::sanity_check`], this lets deployments pick their
+    /// own strictness, e.g. banning timelock mixing and malleability while
+    /// allowing repeated keys.
+    pub fn ext_check(&self, ext: &ExtParams) -> Result<(), Error> {
+        match *self {
+            Descriptor::Bare(ref bare) => bare.as_inner().ext_check(ext)?,
+            Descriptor::Wsh(ref wsh) => {
+                if let WshInner::Ms(ref ms) = *wsh.as_inner() {
+                    ms.ext_check(ext)?;
+                }
+            }
+            Descriptor::Sh(ref sh) => match *sh.as_inner() {
+                ShInner::Wsh(ref wsh) => {
+                    if let WshInner::Ms(ref ms) = *wsh.as_inner() {
+                        ms.ext_check(ext)?;
+                    }
+                }
+                ShInner::Ms(ref ms) => ms.ext_check(ext)?,
+                ShInner::Wpkh(_) | ShInner::SortedMulti(_) => {}
+            },
+            Descriptor::LegacyCSFSCov(ref cov) => cov.to_ms().ext_check(ext)?,
+            Descriptor::Tr(ref tr) => {
+                for (_depth, ms) in tr.iter_scripts() {
+                    ms.ext_check(ext)?;
+                }
+            }
+            Descriptor::TrExt(ref tr) => {
+                for (_depth, ms) in tr.iter_scripts() {
+                    ms.ext_check(ext)?;
+                }
+            }
+            Descriptor::Pkh(_) | Descriptor::Wpkh(_) | Descriptor::Wit(_) => {}
         }
+        Ok(())
     }
 
     /// Computes an upper bound on the difference between a non-satisfied
@@ -509,10 +700,35 @@ impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
             Descriptor::Tr(ref tr) => tr.max_weight_to_satisfy()?,
             Descriptor::TrExt(ref tr) => tr.max_weight_to_satisfy()?,
             Descriptor::LegacyCSFSCov(ref csfs) => csfs.max_satisfaction_weight()?,
+            Descriptor::Wit(_) => return Err(Error::UnknownWitnessVersion),
         };
         Ok(weight)
     }
 
+    /// Length, in bytes, of the scriptPubKey of the descriptor, computed from
+    /// its shape alone without encoding the script.
+    ///
+    /// This does not require the keys to be convertible to public keys, and
+    /// together with [`Descriptor::desc_type`] allows sizing outputs, e.g. for
+    /// fee estimation, without building any [`Script`].
+    pub fn script_pubkey_len(&self) -> usize {
+        match *self {
+            Descriptor::Bare(ref bare) => bare.as_inner().script_size(),
+            // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+            Descriptor::Pkh(..) => 25,
+            // OP_0 <20 bytes>
+            Descriptor::Wpkh(..) => 22,
+            // OP_HASH160 <20 bytes> OP_EQUAL
+            Descriptor::Sh(..) => 23,
+            // OP_0 <32 bytes> or OP_1 <32 bytes>
+            Descriptor::Wsh(..)
+            | Descriptor::Tr(..)
+            | Descriptor::TrExt(..)
+            | Descriptor::LegacyCSFSCov(..) => 34,
+            Descriptor::Wit(ref wit) => wit.script_pubkey_len(),
+        }
+    }
+
     /// Computes an upper bound on the weight of a satisfying witness to the
     /// transaction.
     ///
@@ -534,6 +750,7 @@ impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
             Descriptor::LegacyCSFSCov(ref cov) => cov.max_satisfaction_weight()?,
             Descriptor::Tr(ref tr) => tr.max_satisfaction_weight()?,
             Descriptor::TrExt(ref tr) => tr.max_satisfaction_weight()?,
+            Descriptor::Wit(_) => return Err(Error::UnknownWitnessVersion),
         };
         Ok(weight)
     }
@@ -560,6 +777,24 @@ impl<Pk: MiniscriptKey, Arg: ExtParam> Descriptor<Pk, CovenantExt<Arg>> {
     }
 }
 
+/// The scripts involved in spending an output of a descriptor, as returned
+/// by [`Descriptor::related_scripts`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RelatedScripts {
+    /// The scriptPubKey of the output
+    pub script_pubkey: Script,
+    /// The redeemScript of p2sh descriptors, which is the segwit program for
+    /// nested segwit descriptors
+    pub redeem_script: Option<Script>,
+    /// The witnessScript of p2wsh descriptors, nested or not
+    pub witness_script: Option<Script>,
+    /// The scriptCode signed by ECDSA signatures, `None` for taproot
+    /// descriptors
+    pub script_code: Option<Script>,
+    /// The scriptSig of an unsigned input spending the output
+    pub unsigned_script_sig: Script,
+}
+
 impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<Pk, Ext> {
     ///
     /// Obtains the blinded address for this descriptor
@@ -585,6 +820,7 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(ref cov) => Ok(cov.address(Some(blinder), params)),
             Descriptor::Tr(ref tr) => Ok(tr.address(Some(blinder), params)),
             Descriptor::TrExt(ref tr) => Ok(tr.address(Some(blinder), params)),
+            Descriptor::Wit(ref wit) => Ok(wit.address(Some(blinder), params)),
         }
     }
 
@@ -605,9 +841,43 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(ref cov) => Ok(cov.address(None, params)),
             Descriptor::Tr(ref tr) => Ok(tr.address(None, params)),
             Descriptor::TrExt(ref tr) => Ok(tr.address(None, params)),
+            Descriptor::Wit(ref wit) => Ok(wit.address(None, params)),
         }
     }
 
+    /// Obtains the unconfidential address for this descriptor, same as
+    /// [`Descriptor::address`]
+    ///
+    /// Unlike [`Descriptor::address`], the `params` do not need to be
+    /// `'static`: they can be e.g. a [`CustomAddressParams`] of a network
+    /// only known at runtime, or a borrowed [`elements::AddressParams`]. The
+    /// params of networks other than Liquid, Liquid testnet and Elements are
+    /// interned as with [`CustomAddressParams::leak`].
+    ///
+    /// # Errors
+    /// For raw/bare descriptors that don't have an address.
+    pub fn unconfidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        params: P,
+    ) -> Result<elements::Address, Error> {
+        self.address(params.into().to_static())
+    }
+
+    /// Obtains the confidential address for this descriptor, blinded with
+    /// the `blinder` public key, same as [`Descriptor::blinded_address`]
+    ///
+    /// The `params` are taken as in [`Descriptor::unconfidential_address`].
+    ///
+    /// # Errors
+    /// For raw/bare descriptors that don't have an address.
+    pub fn confidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        blinder: secp256k1_zkp::PublicKey,
+        params: P,
+    ) -> Result<elements::Address, Error> {
+        self.blinded_address(blinder, params.into().to_static())
+    }
+
     /// Computes the scriptpubkey of the descriptor.
     pub fn script_pubkey(&self) -> Script {
         match *self {
@@ -619,6 +889,54 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(ref cov) => cov.script_pubkey(),
             Descriptor::Tr(ref tr) => tr.script_pubkey(),
             Descriptor::TrExt(ref tr) => tr.script_pubkey(),
+            Descriptor::Wit(ref wit) => wit.script_pubkey(),
+        }
+    }
+
+    /// Creates an unblinded output paying `value` of `asset` to the
+    /// descriptor.
+    ///
+    /// When a `blinder` public key is given it is stored in the nonce of the
+    /// output, which marks the output to be blinded to that key, e.g. by
+    /// [`elements::pset::PartiallySignedTransaction::blind_last`] once the
+    /// output is added to a PSET.
+    pub fn txout(
+        &self,
+        value: u64,
+        asset: elements::AssetId,
+        blinder: Option<secp256k1_zkp::PublicKey>,
+    ) -> elements::TxOut {
+        elements::TxOut {
+            asset: elements::confidential::Asset::Explicit(asset),
+            value: elements::confidential::Value::Explicit(value),
+            nonce: blinder.map_or(
+                elements::confidential::Nonce::Null,
+                elements::confidential::Nonce::Confidential,
+            ),
+            script_pubkey: self.script_pubkey(),
+            witness: elements::TxOutWitness::default(),
+        }
+    }
+
+    /// Whether `txout` pays to this descriptor.
+    ///
+    /// The script pubkeys are compared and, if a `blinder` public key is
+    /// given, the output must also be confidential: either already blinded,
+    /// or explicit with `blinder` in its nonce as done by [`Self::txout`].
+    /// The nonce of a blinded output holds the ephemeral key of the sender,
+    /// which cannot be matched against the blinder public key.
+    pub fn matches_txout(
+        &self,
+        txout: &elements::TxOut,
+        blinder: Option<secp256k1_zkp::PublicKey>,
+    ) -> bool {
+        if txout.script_pubkey != self.script_pubkey() {
+            return false;
+        }
+        match blinder {
+            None => true,
+            Some(_) if txout.is_partially_blinded() => true,
+            Some(pk) => txout.nonce == elements::confidential::Nonce::Confidential(pk),
         }
     }
 
@@ -639,6 +957,7 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(_) => Script::new(),
             Descriptor::Tr(_) => Script::new(),
             Descriptor::TrExt(_) => Script::new(),
+            Descriptor::Wit(_) => Script::new(),
         }
     }
 
@@ -658,6 +977,7 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::Tr(_) => Err(Error::TrNoScriptCode),
             Descriptor::TrExt(_) => Err(Error::TrNoScriptCode),
             Descriptor::LegacyCSFSCov(ref cov) => Ok(cov.inner_script()),
+            Descriptor::Wit(_) => Err(Error::UnknownWitnessVersion),
         }
     }
 
@@ -678,6 +998,35 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(ref cov) => Ok(cov.ecdsa_sighash_script_code()),
             Descriptor::Tr(_) => Err(Error::TrNoScriptCode),
             Descriptor::TrExt(_) => Err(Error::TrNoScriptCode),
+            Descriptor::Wit(_) => Err(Error::UnknownWitnessVersion),
+        }
+    }
+
+    /// Computes all the scripts involved in spending an output of this
+    /// descriptor, e.g. to import a p2sh-wrapped descriptor in a system which
+    /// expects its redeemScript and witnessScript.
+    pub fn related_scripts(&self) -> RelatedScripts {
+        let (redeem_script, witness_script) = match *self {
+            Descriptor::Sh(ref sh) => match *sh.as_inner() {
+                ShInner::Wsh(ref wsh) => (Some(wsh.script_pubkey()), Some(wsh.inner_script())),
+                ShInner::Wpkh(ref wpkh) => (Some(wpkh.script_pubkey()), None),
+                ShInner::SortedMulti(..) | ShInner::Ms(..) => (Some(sh.inner_script()), None),
+            },
+            Descriptor::Wsh(ref wsh) => (None, Some(wsh.inner_script())),
+            Descriptor::LegacyCSFSCov(ref cov) => (None, Some(cov.inner_script())),
+            Descriptor::Bare(_)
+            | Descriptor::Pkh(_)
+            | Descriptor::Wpkh(_)
+            | Descriptor::Tr(_)
+            | Descriptor::TrExt(_)
+            | Descriptor::Wit(_) => (None, None),
+        };
+        RelatedScripts {
+            script_pubkey: self.script_pubkey(),
+            redeem_script,
+            witness_script,
+            script_code: self.script_code().ok(),
+            unsigned_script_sig: self.unsigned_script_sig(),
         }
     }
 
@@ -697,6 +1046,7 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(ref cov) => cov.get_satisfaction(satisfier),
             Descriptor::Tr(ref tr) => tr.get_satisfaction(satisfier),
             Descriptor::TrExt(ref tr) => tr.get_satisfaction(satisfier),
+            Descriptor::Wit(_) => Err(Error::UnknownWitnessVersion),
         }
     }
 
@@ -716,13 +1066,27 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
             Descriptor::LegacyCSFSCov(ref cov) => cov.get_satisfaction_mall(satisfier),
             Descriptor::Tr(ref tr) => tr.get_satisfaction_mall(satisfier),
             Descriptor::TrExt(ref tr) => tr.get_satisfaction_mall(satisfier),
+            Descriptor::Wit(_) => Err(Error::UnknownWitnessVersion),
         }
     }
 
     /// Attempts to produce a non-malleable satisfying witness and scriptSig to spend an
     /// output controlled by the given descriptor; add the data to a given
-    /// `TxIn` output.
+    /// `TxIn` output. Same as [`Descriptor::fill_txin`].
     pub fn satisfy<S>(&self, txin: &mut TxIn, satisfier: S) -> Result<(), Error>
+    where
+        S: Satisfier<Pk>,
+    {
+        self.fill_txin(txin, satisfier)
+    }
+
+    /// Attempts to produce a non-malleable satisfying witness and scriptSig
+    /// to spend an output controlled by the given descriptor, and places them
+    /// in the `script_sig` and the `witness.script_witness` of `txin`.
+    ///
+    /// The pegin witness and the rangeproofs of `txin` are left untouched,
+    /// and `txin` is not modified if there is no satisfaction.
+    pub fn fill_txin<S>(&self, txin: &mut TxIn, satisfier: S) -> Result<(), Error>
     where
         S: Satisfier<Pk>,
     {
@@ -731,6 +1095,18 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<
         txin.script_sig = script_sig;
         Ok(())
     }
+
+    /// Same as [`Descriptor::fill_txin`], with a possibly malleable
+    /// satisfaction
+    pub fn fill_txin_mall<S>(&self, txin: &mut TxIn, satisfier: S) -> Result<(), Error>
+    where
+        S: Satisfier<Pk>,
+    {
+        let (witness, script_sig) = self.get_satisfaction_mall(satisfier)?;
+        txin.witness.script_witness = witness;
+        txin.script_sig = script_sig;
+        Ok(())
+    }
 }
 
 impl<P, Q, Ext> TranslatePk<P, Q> for Descriptor<P, Ext>
@@ -755,6 +1131,7 @@ where
             Descriptor::Tr(ref tr) => Descriptor::Tr(tr.translate_pk(t)?),
             Descriptor::TrExt(ref tr) => Descriptor::TrExt(tr.translate_pk(t)?),
             Descriptor::LegacyCSFSCov(ref cov) => Descriptor::LegacyCSFSCov(cov.translate_pk(t)?),
+            Descriptor::Wit(ref wit) => Descriptor::Wit(wit.clone()),
         };
         Ok(desc)
     }
@@ -789,6 +1166,7 @@ where
                     cov, t,
                 )?)
             }
+            Descriptor::Wit(ref wit) => Descriptor::Wit(wit.clone()),
         };
         Ok(desc)
     }
@@ -808,10 +1186,95 @@ impl<Pk: MiniscriptKey, T: Extension> ForEachKey<Pk> for Descriptor<Pk, T> {
             Descriptor::LegacyCSFSCov(ref cov) => cov.for_any_key(pred),
             Descriptor::Tr(ref tr) => tr.for_each_key(pred),
             Descriptor::TrExt(ref tr) => tr.for_each_key(pred),
+            Descriptor::Wit(_) => true,
         }
     }
 }
 
+/// Parses a key that may be secret, recording any secret key in `key_map`
+fn parse_key<C: secp256k1::Signing>(
+    s: &str,
+    key_map: &mut KeyMap,
+    secp: &secp256k1::Secp256k1<C>,
+) -> Result<DescriptorPublicKey, Error> {
+    let (public_key, secret_key) = match DescriptorSecretKey::from_str(s) {
+        Ok(sk) => (
+            sk.to_public(secp)
+                .map_err(|e| Error::Unexpected(e.to_string()))?,
+            Some(sk),
+        ),
+        Err(_) => (
+            DescriptorPublicKey::from_str(s).map_err(|e| Error::Unexpected(e.to_string()))?,
+            None,
+        ),
+    };
+
+    if let Some(secret_key) = secret_key {
+        key_map.insert(public_key.clone(), secret_key);
+    }
+
+    Ok(public_key)
+}
+
+/// Translator from string keys to [`DescriptorPublicKey`]s which collects
+/// the secret keys it encounters into a [`KeyMap`].
+struct KeyMapWrapper<'a, C: secp256k1::Signing>(KeyMap, &'a secp256k1::Secp256k1<C>);
+
+impl<'a, C: secp256k1::Signing> Translator<String, DescriptorPublicKey, Error>
+    for KeyMapWrapper<'a, C>
+{
+    fn pk(&mut self, pk: &String) -> Result<DescriptorPublicKey, Error> {
+        parse_key(pk, &mut self.0, self.1)
+    }
+
+    fn sha256(&mut self, sha256: &String) -> Result<sha256::Hash, Error> {
+        let hash = sha256::Hash::from_str(sha256).map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(hash)
+    }
+
+    fn hash256(&mut self, hash256: &String) -> Result<hash256::Hash, Error> {
+        let hash =
+            hash256::Hash::from_str(hash256).map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(hash)
+    }
+
+    fn ripemd160(&mut self, ripemd160: &String) -> Result<ripemd160::Hash, Error> {
+        let hash =
+            ripemd160::Hash::from_str(ripemd160).map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(hash)
+    }
+
+    fn hash160(&mut self, hash160: &String) -> Result<hash160::Hash, Error> {
+        let hash =
+            hash160::Hash::from_str(hash160).map_err(|e| Error::Unexpected(e.to_string()))?;
+        Ok(hash)
+    }
+}
+
+/// Derivation properties of a descriptor, as returned by
+/// [`Descriptor::range_info`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RangeInfo {
+    /// Number of keys with an unhardened wildcard, i.e. `/*`
+    pub unhardened_wildcards: usize,
+    /// Number of keys with a hardened wildcard, i.e. `/*h`
+    pub hardened_wildcards: usize,
+    /// Whether any key has multiple derivation paths
+    pub is_multipath: bool,
+    /// Number of indexes the descriptor can be derived at from its public
+    /// keys: `2^31` if it has wildcards, `1` if it has none (the index is
+    /// then ignored) and `0` if a key has a hardened wildcard, which
+    /// requires the private key.
+    pub max_derivation_count: u32,
+}
+
+impl RangeInfo {
+    /// Whether or not the descriptor has any wildcards
+    pub fn has_wildcard(&self) -> bool {
+        self.unhardened_wildcards + self.hardened_wildcards > 0
+    }
+}
+
 impl<Ext: Extension + ParseableExt> Descriptor<DescriptorPublicKey, Ext> {
     /// Whether or not the descriptor has any wildcards
     #[deprecated(note = "use has_wildcards instead")]
@@ -824,6 +1287,36 @@ impl<Ext: Extension + ParseableExt> Descriptor<DescriptorPublicKey, Ext> {
         self.for_any_key(|key| key.has_wildcard())
     }
 
+    /// The wildcards and multipath keys of the descriptor, and the number of
+    /// indexes it can be derived at.
+    pub fn range_info(&self) -> RangeInfo {
+        let mut info = RangeInfo::default();
+        self.for_each_key(|key| {
+            let wildcard = match *key {
+                DescriptorPublicKey::Single(..) => Wildcard::None,
+                DescriptorPublicKey::XPub(ref xpub) => xpub.wildcard,
+                DescriptorPublicKey::MultiXPub(ref xpub) => {
+                    info.is_multipath = true;
+                    xpub.wildcard
+                }
+            };
+            match wildcard {
+                Wildcard::None => {}
+                Wildcard::Unhardened => info.unhardened_wildcards += 1,
+                Wildcard::Hardened => info.hardened_wildcards += 1,
+            }
+            true
+        });
+        info.max_derivation_count = if info.hardened_wildcards > 0 {
+            0
+        } else if info.unhardened_wildcards > 0 {
+            1 << 31
+        } else {
+            1
+        };
+        info
+    }
+
     /// Replaces all wildcards (i.e. `/*`) in the descriptor with a particular derivation index,
     /// turning it into a *definite* descriptor.
     ///
@@ -891,74 +1384,98 @@ impl<Ext: Extension + ParseableExt> Descriptor<DescriptorPublicKey, Ext> {
         self.at_derivation_index(index)?.derived_descriptor(secp)
     }
 
-    /// Parse a descriptor that may contain secret keys
+    /// Derives the descriptor at `index` and converts all its keys to
+    /// [`bitcoin::PublicKey`] in a single translation, with the same `secp`
+    /// context used for every key.
     ///
-    /// Internally turns every secret key found into the corresponding public key and then returns a
-    /// a descriptor that only contains public keys and a map to lookup the secret key given a public key.
-    pub fn parse_descriptor<C: secp256k1_zkp::Signing>(
+    /// This gives the same result as [`Self::derived_descriptor`] without
+    /// building the intermediate definite descriptor. The x-only keys of
+    /// taproot descriptors get the default (0x02) y-coordinate.
+    ///
+    /// # Errors
+    ///
+    /// - If `index` is hardened, or a key has hardened derivation steps
+    /// - If a key has multiple derivation paths
+    /// - If an x-only key is used outside of a taproot descriptor, where a
+    ///   full key is needed
+    pub fn derive_public<C: secp256k1_zkp::Verification>(
+        &self,
         secp: &secp256k1_zkp::Secp256k1<C>,
-        s: &str,
-    ) -> Result<(Descriptor<DescriptorPublicKey, Ext>, KeyMap), Error> {
-        fn parse_key<C: secp256k1::Signing>(
-            s: &str,
-            key_map: &mut KeyMap,
-            secp: &secp256k1::Secp256k1<C>,
-        ) -> Result<DescriptorPublicKey, Error> {
-            let (public_key, secret_key) = match DescriptorSecretKey::from_str(s) {
-                Ok(sk) => (
-                    sk.to_public(secp)
-                        .map_err(|e| Error::Unexpected(e.to_string()))?,
-                    Some(sk),
-                ),
-                Err(_) => (
-                    DescriptorPublicKey::from_str(s)
-                        .map_err(|e| Error::Unexpected(e.to_string()))?,
-                    None,
-                ),
-            };
-
-            if let Some(secret_key) = secret_key {
-                key_map.insert(public_key.clone(), secret_key);
-            }
-
-            Ok(public_key)
-        }
+        index: u32,
+    ) -> Result<Descriptor<bitcoin::PublicKey, Ext>, ConversionError> {
+        self.derive_public_inner(secp, index, None)
+    }
 
-        let mut keymap_pk = KeyMapWrapper(HashMap::new(), secp);
+    /// Derives the descriptor at `index` and converts all its keys to
+    /// [`bitcoin::PublicKey`], as [`Self::derive_public`] does, reusing the
+    /// intermediate BIP-32 derivations of `cache`.
+    ///
+    /// The same cache should be reused to derive the descriptor at many
+    /// indices, e.g. when scanning for addresses.
+    pub fn derive_public_cached<C: secp256k1_zkp::Verification>(
+        &self,
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        index: u32,
+        cache: &mut XpubCache,
+    ) -> Result<Descriptor<bitcoin::PublicKey, Ext>, ConversionError> {
+        self.derive_public_inner(secp, index, Some(cache))
+    }
 
-        struct KeyMapWrapper<'a, C: secp256k1::Signing>(KeyMap, &'a secp256k1::Secp256k1<C>);
+    fn derive_public_inner<C: secp256k1_zkp::Verification>(
+        &self,
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        index: u32,
+        cache: Option<&mut XpubCache>,
+    ) -> Result<Descriptor<bitcoin::PublicKey, Ext>, ConversionError> {
+        struct Derivator<'a, 'b, C: secp256k1_zkp::Verification>(
+            &'a secp256k1_zkp::Secp256k1<C>,
+            u32,
+            Option<&'b mut XpubCache>,
+        );
 
-        impl<'a, C: secp256k1::Signing> Translator<String, DescriptorPublicKey, Error>
-            for KeyMapWrapper<'a, C>
+        impl<'a, 'b, C: secp256k1_zkp::Verification>
+            Translator<DescriptorPublicKey, bitcoin::PublicKey, ConversionError>
+            for Derivator<'a, 'b, C>
         {
-            fn pk(&mut self, pk: &String) -> Result<DescriptorPublicKey, Error> {
-                parse_key(pk, &mut self.0, self.1)
-            }
-
-            fn sha256(&mut self, sha256: &String) -> Result<sha256::Hash, Error> {
-                let hash =
-                    sha256::Hash::from_str(sha256).map_err(|e| Error::Unexpected(e.to_string()))?;
-                Ok(hash)
-            }
-
-            fn hash256(&mut self, hash256: &String) -> Result<hash256::Hash, Error> {
-                let hash = hash256::Hash::from_str(hash256)
-                    .map_err(|e| Error::Unexpected(e.to_string()))?;
-                Ok(hash)
+            fn pk(
+                &mut self,
+                pk: &DescriptorPublicKey,
+            ) -> Result<bitcoin::PublicKey, ConversionError> {
+                let definite = pk.clone().at_derivation_index(self.1)?;
+                match self.2 {
+                    Some(ref mut cache) => definite.derive_public_key_cached(self.0, cache),
+                    None => definite.derive_public_key(self.0),
+                }
             }
 
-            fn ripemd160(&mut self, ripemd160: &String) -> Result<ripemd160::Hash, Error> {
-                let hash = ripemd160::Hash::from_str(ripemd160)
-                    .map_err(|e| Error::Unexpected(e.to_string()))?;
-                Ok(hash)
-            }
+            translate_hash_clone!(DescriptorPublicKey, bitcoin::PublicKey, ConversionError);
+        }
 
-            fn hash160(&mut self, hash160: &String) -> Result<hash160::Hash, Error> {
-                let hash = hash160::Hash::from_str(hash160)
-                    .map_err(|e| Error::Unexpected(e.to_string()))?;
-                Ok(hash)
+        match *self {
+            Descriptor::Tr(..) | Descriptor::TrExt(..) => {}
+            _ => {
+                if self.for_any_key(|key| match *key {
+                    DescriptorPublicKey::Single(ref single) => {
+                        matches!(single.key, SinglePubKey::XOnly(..))
+                    }
+                    _ => false,
+                }) {
+                    return Err(ConversionError::XOnlyKey);
+                }
             }
         }
+        self.translate_pk(&mut Derivator(secp, index, cache))
+    }
+
+    /// Parse a descriptor that may contain secret keys
+    ///
+    /// Internally turns every secret key found into the corresponding public key and then returns a
+    /// a descriptor that only contains public keys and a map to lookup the secret key given a public key.
+    pub fn parse_descriptor<C: secp256k1_zkp::Signing>(
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        s: &str,
+    ) -> Result<(Descriptor<DescriptorPublicKey, Ext>, KeyMap), Error> {
+        let mut keymap_pk = KeyMapWrapper(HashMap::new(), secp);
 
         let descriptor = Descriptor::<String, Ext>::from_str(s)?;
         let descriptor = descriptor
@@ -1099,6 +1616,32 @@ impl<Ext: Extension + ParseableExt> Descriptor<DescriptorPublicKey, Ext> {
     }
 }
 
+impl<Ext: Extension> Descriptor<DescriptorPublicKey, Ext> {
+    /// Returns each key of the descriptor, in the order they appear, along
+    /// with its origin `[fingerprint/path]` if present.
+    pub fn key_origins(&self) -> Vec<(&DescriptorPublicKey, Option<&bip32::KeySource>)> {
+        let mut origins = vec![];
+        self.for_each_key(|key| {
+            let origin = match *key {
+                DescriptorPublicKey::Single(ref single) => single.origin.as_ref(),
+                DescriptorPublicKey::XPub(ref xpub) => xpub.origin.as_ref(),
+                DescriptorPublicKey::MultiXPub(ref xpub) => xpub.origin.as_ref(),
+            };
+            origins.push((key, origin));
+            true
+        });
+        origins
+    }
+
+    /// Whether any key of the descriptor has the master fingerprint `fp`.
+    ///
+    /// Keys without origin are identified by their own fingerprint, see
+    /// [`DescriptorPublicKey::master_fingerprint`].
+    pub fn requires_fingerprint(&self, fp: bip32::Fingerprint) -> bool {
+        self.for_any_key(|key| key.master_fingerprint() == fp)
+    }
+}
+
 impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
     /// Whether this descriptor is a multipath descriptor that contains any 2 multipath keys
     /// with a different number of derivation paths.
@@ -1196,6 +1739,7 @@ impl_from_tree!(
             ("elcovwsh", 2) => Descriptor::LegacyCSFSCov(LegacyCSFSCov::from_tree(top)?),
             ("elwsh", 1) => Descriptor::Wsh(Wsh::from_tree(top)?),
             ("eltr", _) => Descriptor::Tr(Tr::from_tree(top)?),
+            ("elwit", 2) => Descriptor::Wit(Wit::from_tree(top)?),
             _ => Descriptor::Bare(Bare::from_tree(top)?),
         })
     }
@@ -1238,6 +1782,143 @@ impl_from_str!(
     }
 );
 
+impl_block_str!(
+    Descriptor<Pk, T>,
+    => T ; Extension,
+    /// Parses a descriptor which may contain whitespace, such as the output
+    /// of [`Descriptor::to_string_pretty`]. The checksum, if any, must be the
+    /// one of the descriptor without whitespace.
+    pub fn from_str_pretty(s: &str,) -> Result<Descriptor<Pk, T>, Error>
+    {
+        Descriptor::from_str(&expression::strip_whitespace(s))
+    }
+);
+
+impl_block_str!(
+    Descriptor<Pk, T>,
+    => T ; Extension,
+    /// Parses a descriptor and checks its miniscripts against `ext`, see
+    /// [`Descriptor::ext_check`].
+    ///
+    /// [`Descriptor::from_str`] does not perform these checks, it accepts
+    /// any descriptor which typechecks and is valid in its context.
+    pub fn from_str_ext(s: &str, ext: &ExtParams,) -> Result<Descriptor<Pk, T>, Error>
+    {
+        let desc = Descriptor::from_str(s)?;
+        desc.ext_check(ext)?;
+        Ok(desc)
+    }
+);
+
+impl<Pk, T> Descriptor<Pk, T>
+where
+    Pk: MiniscriptKey,
+    T: Extension,
+    Self: FromStr<Err = Error>,
+{
+    /// Checks that the descriptor parses back from its string
+    /// representation to an identical descriptor.
+    ///
+    /// This is meant for the tests of downstream crates defining their own
+    /// extensions or key types, whose `Display` and `FromStr` implementations
+    /// must be consistent. The returned error shows both descriptors.
+    pub fn roundtrip_check(&self) -> Result<(), Error> {
+        let s = self.to_string();
+        let parsed = Descriptor::<Pk, T>::from_str(&s).map_err(|e| {
+            Error::BadDescriptor(format!(
+                "descriptor {} does not parse back from its string representation: {}",
+                s, e
+            ))
+        })?;
+        if parsed == *self {
+            Ok(())
+        } else {
+            Err(Error::BadDescriptor(format!(
+                "descriptor does not round trip through its string representation\n  \
+                 original: {:?}\n  parsed:   {:?}",
+                self, parsed
+            )))
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey, T: Extension> Descriptor<Pk, T> {
+    /// Formats the descriptor over multiple lines, with nested fragments
+    /// indented by two spaces, followed by its checksum.
+    ///
+    /// # Errors
+    ///
+    /// If the string representation of the descriptor cannot be parsed
+    /// back, e.g. because of a key whose display contains commas or
+    /// parentheses.
+    pub fn to_string_pretty(&self) -> Result<String, Error> {
+        let s = self.to_string();
+        let (desc, checksum) = s.split_at(s.find('#').unwrap_or(s.len()));
+        let tree = descriptor_tree(desc)?;
+        Ok(format!("{}{}", tree.to_string_pretty(2), checksum))
+    }
+
+    /// Replaces every occurrence of the key `old` by `new`, e.g. to rotate
+    /// the key of a cosigner, leaving the rest of the descriptor unchanged.
+    ///
+    /// Returns the new descriptor, whose string representation has the new
+    /// checksum, and the number of replaced occurrences.
+    ///
+    /// # Errors
+    /// When `old` is not a key of the descriptor, or `new` already is.
+    pub fn replace_key(&self, old: &Pk, new: Pk) -> Result<(Descriptor<Pk, T>, usize), Error> {
+        if self.for_any_key(|pk| *pk == new) {
+            return Err(Error::BadDescriptor(format!(
+                "key {} is already in the descriptor",
+                new
+            )));
+        }
+        let mut replacer = KeyReplacer { old, new, count: 0 };
+        let desc = self.translate_pk(&mut replacer)?;
+        if replacer.count == 0 {
+            return Err(Error::BadDescriptor(format!(
+                "key {} is not in the descriptor",
+                old
+            )));
+        }
+        Ok((desc, replacer.count))
+    }
+}
+
+// Replaces a key by another, see `Descriptor::replace_key`
+struct KeyReplacer<'a, Pk: MiniscriptKey> {
+    old: &'a Pk,
+    new: Pk,
+    count: usize,
+}
+
+impl<'a, Pk: MiniscriptKey> Translator<Pk, Pk, Error> for KeyReplacer<'a, Pk> {
+    fn pk(&mut self, pk: &Pk) -> Result<Pk, Error> {
+        if pk == self.old {
+            self.count += 1;
+            Ok(self.new.clone())
+        } else {
+            Ok(pk.clone())
+        }
+    }
+
+    fn sha256(&mut self, sha256: &Pk::Sha256) -> Result<Pk::Sha256, Error> {
+        Ok(sha256.clone())
+    }
+
+    fn hash256(&mut self, hash256: &Pk::Hash256) -> Result<Pk::Hash256, Error> {
+        Ok(hash256.clone())
+    }
+
+    fn ripemd160(&mut self, ripemd160: &Pk::Ripemd160) -> Result<Pk::Ripemd160, Error> {
+        Ok(ripemd160.clone())
+    }
+
+    fn hash160(&mut self, hash160: &Pk::Hash160) -> Result<Pk::Hash160, Error> {
+        Ok(hash160.clone())
+    }
+}
+
 impl<Pk: MiniscriptKey, T: Extension> fmt::Debug for Descriptor<Pk, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -1249,6 +1930,7 @@ impl<Pk: MiniscriptKey, T: Extension> fmt::Debug for Descriptor<Pk, T> {
             Descriptor::Tr(ref tr) => fmt::Debug::fmt(tr, f),
             Descriptor::TrExt(ref tr) => fmt::Debug::fmt(tr, f),
             Descriptor::LegacyCSFSCov(ref cov) => fmt::Debug::fmt(cov, f),
+            Descriptor::Wit(ref wit) => fmt::Debug::fmt(wit, f),
         }
     }
 }
@@ -1264,6 +1946,7 @@ impl<Pk: MiniscriptKey, T: Extension> fmt::Display for Descriptor<Pk, T> {
             Descriptor::Tr(ref tr) => fmt::Display::fmt(tr, f),
             Descriptor::TrExt(ref tr) => fmt::Display::fmt(tr, f),
             Descriptor::LegacyCSFSCov(ref cov) => fmt::Display::fmt(cov, f),
+            Descriptor::Wit(ref wit) => fmt::Display::fmt(wit, f),
         }
     }
 }
@@ -1288,10 +1971,11 @@ mod tests {
     use super::*;
     use crate::descriptor::key::Wildcard;
     use crate::descriptor::{DescriptorPublicKey, DescriptorXKey};
+    use crate::extensions::LegacyVerEq;
     use crate::miniscript::satisfy::ElementsSig;
     #[cfg(feature = "compiler")]
     use crate::policy;
-    use crate::{hex_script, Descriptor, Error, Miniscript, NoExt, Satisfier};
+    use crate::{hex_script, AnalysisError, Descriptor, Error, Miniscript, NoExt, Satisfier};
 
     type StdDescriptor = Descriptor<PublicKey, CovenantExt<CovExtArgs>>;
     const TEST_PK: &str =
@@ -1688,6 +2372,37 @@ mod tests {
         assert_eq!(shwsh.unsigned_script_sig(), expected_ssig);
     }
 
+    #[test]
+    fn fill_txin() {
+        let desc = Descriptor::<bitcoin::PublicKey>::from_str("elwsh(older(100))").unwrap();
+        let pegin_witness = vec![vec![1; 8], vec![2; 32]];
+        let mut txin = elements::TxIn {
+            previous_output: elements::OutPoint::default(),
+            script_sig: Script::new(),
+            sequence: Sequence::from_height(10),
+            is_pegin: true,
+            asset_issuance: elements::AssetIssuance::default(),
+            witness: elements::TxInWitness {
+                pegin_witness: pegin_witness.clone(),
+                ..Default::default()
+            },
+        };
+
+        // No satisfaction leaves the input untouched
+        let unsatisfied = txin.clone();
+        assert!(desc.fill_txin(&mut txin, unsatisfied.sequence).is_err());
+        assert_eq!(txin, unsatisfied);
+
+        desc.fill_txin(&mut txin, Sequence::from_height(100))
+            .unwrap();
+        assert_eq!(txin.script_sig, Script::new());
+        assert_eq!(
+            txin.witness.script_witness,
+            vec![desc.explicit_script().unwrap().into_bytes()]
+        );
+        assert_eq!(txin.witness.pegin_witness, pegin_witness);
+    }
+
     #[test]
     fn after_is_cltv() {
         let descriptor = Descriptor::<bitcoin::PublicKey>::from_str("elwsh(after(1000))").unwrap();
@@ -2143,6 +2858,69 @@ pk(03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8))";
             .unwrap_err();
     }
 
+    #[test]
+    fn matches_txout() {
+        let secp = secp256k1_zkp::Secp256k1::new();
+        let asset = elements::AssetId::default();
+        let desc = StdDescriptor::from_str(
+            "elwpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap();
+        let other = StdDescriptor::from_str(TEST_PK).unwrap();
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        let blinder = secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk);
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[2; 32]).unwrap();
+        let other_blinder = secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk);
+
+        let explicit = desc.txout(1000, asset, None);
+        assert!(desc.matches_txout(&explicit, None));
+        assert!(!other.matches_txout(&explicit, None));
+        assert!(!desc.matches_txout(&explicit, Some(blinder)));
+
+        let to_blind = desc.txout(1000, asset, Some(blinder));
+        assert!(desc.matches_txout(&to_blind, None));
+        assert!(desc.matches_txout(&to_blind, Some(blinder)));
+        assert!(!desc.matches_txout(&to_blind, Some(other_blinder)));
+
+        let mut blinded = to_blind.clone();
+        let abf = elements::confidential::AssetBlindingFactor::from_slice(&[3; 32]).unwrap();
+        blinded.asset = elements::confidential::Asset::new_confidential(&secp, asset, abf);
+        blinded.nonce = elements::confidential::Nonce::Confidential(other_blinder);
+        assert!(desc.matches_txout(&blinded, Some(blinder)));
+        assert!(!other.matches_txout(&blinded, Some(blinder)));
+    }
+
+    #[test]
+    fn derive_public() {
+        let secp = secp256k1_zkp::Secp256k1::verification_only();
+        let descs = [
+            "eltr(xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/0/*)",
+            "elwsh(multi(1,xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/1/*,0308c0fcf8895f4361b4fc77afe2ad53b0bd27dcebfd863421b2b246dc283d4103))",
+            "eltr(08c0fcf8895f4361b4fc77afe2ad53b0bd27dcebfd863421b2b246dc283d4103)",
+        ];
+        for desc in descs.iter() {
+            let desc = Descriptor::<DescriptorPublicKey>::from_str(desc).unwrap();
+            assert_eq!(
+                desc.derive_public(&secp, 7).unwrap(),
+                desc.derived_descriptor(&secp, 7).unwrap()
+            );
+        }
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(descs[0]).unwrap();
+        assert_eq!(
+            desc.derive_public(&secp, 1 << 31),
+            Err(ConversionError::HardenedChild)
+        );
+
+        // X-only keys are not parsed outside of taproot, but can be used
+        // to construct descriptors
+        let xonly = DescriptorPublicKey::from_str(
+            "08c0fcf8895f4361b4fc77afe2ad53b0bd27dcebfd863421b2b246dc283d4103",
+        )
+        .unwrap();
+        let desc = Descriptor::<_, CovenantExt<CovExtArgs>>::new_pkh(xonly);
+        assert_eq!(desc.derive_public(&secp, 0), Err(ConversionError::XOnlyKey));
+    }
+
     #[test]
     fn test_find_derivation_index_for_spk() {
         let secp = secp256k1_zkp::Secp256k1::verification_only();
@@ -2255,6 +3033,35 @@ pk(03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8))";
         let _ = Descriptor::<String>::from_str("eltr(,thresh(1,spk_eq(,00)))");
     }
 
+    #[test]
+    fn range_info() {
+        let xpub = "tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV";
+        let info = |s: &str| {
+            Descriptor::<DescriptorPublicKey, NoExt>::from_str(s)
+                .unwrap()
+                .range_info()
+        };
+
+        let single = info(&format!("elwpkh({}/0)", xpub));
+        assert!(!single.has_wildcard());
+        assert!(!single.is_multipath);
+        assert_eq!(single.max_derivation_count, 1);
+
+        let ranged = info(&format!("elwsh(multi(1,{}/0/*,{}/<0;1>/*))", xpub, xpub));
+        assert!(ranged.has_wildcard());
+        assert!(ranged.is_multipath);
+        assert_eq!(ranged.unhardened_wildcards, 2);
+        assert_eq!(ranged.hardened_wildcards, 0);
+        assert_eq!(ranged.max_derivation_count, 1 << 31);
+
+        let hardened = info(&format!("elwsh(multi(1,{}/0/*,{}/1/*h))", xpub, xpub));
+        assert!(hardened.has_wildcard());
+        assert!(!hardened.is_multipath);
+        assert_eq!(hardened.unhardened_wildcards, 1);
+        assert_eq!(hardened.hardened_wildcards, 1);
+        assert_eq!(hardened.max_derivation_count, 0);
+    }
+
     #[test]
     fn multipath_descriptors() {
         // We can parse a multipath descriptors, and make it into separate single-path descriptors.
@@ -2289,4 +3096,442 @@ pk(03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8))";
         Descriptor::<DescriptorPublicKey>::from_str("elwsh(andor(pk(tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/0'/<0;1>/*),older(10000),pk(tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/8/<0;1;2;3;4>/*)))").unwrap_err();
         Descriptor::<DescriptorPublicKey>::from_str("elwsh(andor(pk(tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/0'/<0;1;2;3>/*),older(10000),pk(tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/8/<0;1;2>/*)))").unwrap_err();
     }
+
+    #[test]
+    fn blinded_view_only() {
+        let secp = secp256k1_zkp::Secp256k1::new();
+        let view_xprv = "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L";
+        let spend_xprv = "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc";
+        let spend_xpub = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL";
+
+        let full = format!("blinded({}/0,elwpkh({}/*))", view_xprv, spend_xprv);
+        let (blinded, key_map) = Blinded::parse_descriptor(&secp, &full).unwrap();
+        assert_eq!(key_map.len(), 2);
+
+        let view_only = blinded.to_view_only(&key_map).unwrap();
+        let expected = format!("blinded({}/0,elwpkh({}/*))", view_xprv, spend_xpub);
+        assert_eq!(
+            view_only,
+            format!("{}#{}", expected, desc_checksum(&expected).unwrap())
+        );
+
+        let (parsed, view_key) = Blinded::parse_view_only(&secp, &view_only).unwrap();
+        assert_eq!(parsed, blinded);
+        assert_eq!(view_key.to_string(), format!("{}/0", view_xprv));
+
+        // Spending keys are rejected, and so is a missing view key
+        Blinded::parse_view_only(&secp, &full).unwrap_err();
+        let view_xpub = "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y";
+        let public = format!("blinded({}/0,elwpkh({}/*))", view_xpub, spend_xpub);
+        let (public, key_map) = Blinded::parse_descriptor(&secp, &public).unwrap();
+        assert!(key_map.is_empty());
+        public.to_view_only(&key_map).unwrap_err();
+    }
+
+    #[test]
+    fn bitcoin_descriptor_interop() {
+        let btc = "wsh(multi(2,020000000000000000000000000000000000000000000000000000000000000002,030000000000000000000000000000000000000000000000000000000000000003))";
+        let desc = Descriptor::<DescriptorPublicKey>::from_bitcoin_descriptor(btc).unwrap();
+        assert_eq!(desc.desc_type(), DescriptorType::Wsh);
+        assert_eq!(
+            desc.to_bitcoin_descriptor().unwrap(),
+            format!("{}#{}", btc, desc_checksum(btc).unwrap())
+        );
+        // Checksums are verified
+        Descriptor::<DescriptorPublicKey>::from_bitcoin_descriptor(&format!("{}#aaaaaaaa", btc))
+            .unwrap_err();
+        // Elements and Bitcoin-only descriptors are rejected
+        Descriptor::<DescriptorPublicKey>::from_bitcoin_descriptor(&format!("el{}", btc))
+            .unwrap_err();
+        Descriptor::<DescriptorPublicKey>::from_bitcoin_descriptor(
+            "rawtr(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap_err();
+
+        // Covenants have no Bitcoin equivalent
+        let cov = Descriptor::<String>::from_str("elcovwsh(A,pk(B))").unwrap();
+        cov.to_bitcoin_descriptor().unwrap_err();
+        let tr_ext =
+            Descriptor::<String>::from_str("eltr(A,and_v(v:pk(B),curr_idx_eq(1)))").unwrap();
+        assert!(tr_ext
+            .to_bitcoin_descriptor()
+            .unwrap_err()
+            .to_string()
+            .contains("curr_idx_eq(1)"));
+        // Unless no extension is actually used
+        let tr_ext = Descriptor::<String>::new_tr_ext(
+            "A".to_owned(),
+            Some(tr::TapTree::Leaf(Arc::new(
+                Miniscript::from_str("pk(B)").unwrap(),
+            ))),
+        )
+        .unwrap();
+        assert_eq!(tr_ext.desc_type(), DescriptorType::TrExt);
+        assert!(tr_ext
+            .to_bitcoin_descriptor()
+            .unwrap()
+            .starts_with("tr(A,pk(B))#"));
+    }
+
+    #[test]
+    fn key_origins() {
+        let origin_xpub = "[d34db33f/48'/0'/0'/2']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*";
+        let xpub = "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y/0/*";
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(&format!(
+            "elwsh(multi(1,{},{}))",
+            origin_xpub, xpub
+        ))
+        .unwrap();
+
+        let origins = desc.key_origins();
+        assert_eq!(origins.len(), 2);
+        let fp = bip32::Fingerprint::from([0xd3, 0x4d, 0xb3, 0x3f]);
+        let path = bip32::DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        assert_eq!(origins[0].1, Some(&(fp, path)));
+        assert_eq!(origins[1].1, None);
+
+        assert!(desc.requires_fingerprint(fp));
+        assert!(desc.requires_fingerprint(origins[1].0.master_fingerprint()));
+        assert!(!desc.requires_fingerprint(bip32::Fingerprint::from([0; 4])));
+    }
+
+    #[test]
+    fn script_pubkey_len() {
+        let pk = "020000000000000000000000000000000000000000000000000000000000000002";
+        let pk2 = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        for desc in [
+            format!("elpk({})", pk),
+            format!("elpkh({})", pk),
+            format!("elwpkh({})", pk),
+            format!("elsh(wpkh({}))", pk),
+            format!("elsh(multi(1,{},{}))", pk, pk2),
+            format!("elwsh(multi(1,{},{}))", pk, pk2),
+            format!("elsh(wsh(pk({})))", pk),
+            format!("eltr({})", pk2),
+            format!("eltr({},and_v(v:pk({}),curr_idx_eq(1)))", pk, pk2),
+            format!("elcovwsh({},pk({}))", pk, pk2),
+        ]
+        .iter()
+        {
+            let desc = Descriptor::<bitcoin::PublicKey>::from_str(desc).unwrap();
+            assert_eq!(desc.script_pubkey_len(), desc.script_pubkey().len());
+        }
+    }
+
+    #[test]
+    fn descriptors_as_map_keys() {
+        use std::collections::{BTreeMap, HashSet};
+
+        use crate::policy::{Concrete, Liftable, Semantic};
+
+        let strs = [
+            "elwsh(multi(2,A,B,C))",
+            "eltr(A,and_v(v:pk(B),curr_idx_eq(1)))",
+            "elcovwsh(A,pk(B))",
+            "elwpkh(A)",
+        ];
+        let mut index = BTreeMap::new();
+        let mut set = HashSet::new();
+        let mut policies = HashSet::new();
+        for (i, s) in strs.iter().enumerate() {
+            let desc = Descriptor::<String>::from_str(s).unwrap();
+            index.insert(desc.clone(), i);
+            // Taproot extensions cannot be lifted
+            if let Ok(pol) = desc.lift() {
+                policies.insert(pol);
+            }
+            set.insert(desc);
+        }
+        assert_eq!(index.len(), strs.len());
+        assert_eq!(set.len(), strs.len());
+        assert!(policies.contains(&Semantic::Key("A".to_string())));
+        let desc = Descriptor::<String>::from_str("elwpkh(A)").unwrap();
+        assert_eq!(index[&desc], 3);
+
+        let blinded = Blinded::<String>::from_str("blinded(K,elwpkh(A))").unwrap();
+        let blinded_set = vec![blinded.clone()].into_iter().collect::<HashSet<_>>();
+        assert!(blinded_set.contains(&blinded));
+        let pol = Concrete::<String>::from_str("or(pk(A),pk(B))").unwrap();
+        let pol_index = vec![(pol.clone(), 0)]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        assert!(pol_index.contains_key(&pol));
+    }
+
+    #[test]
+    fn pretty_descriptors() {
+        let desc =
+            Descriptor::<String>::from_str("elwsh(or_d(multi(2,A,B,C),and_v(v:pk(D),older(144))))")
+                .unwrap();
+        let pretty = desc.to_string_pretty().unwrap();
+        let checksum = desc.to_string().split('#').nth(1).unwrap().to_owned();
+        assert_eq!(
+            pretty,
+            format!(
+                "elwsh(\n  or_d(\n    multi(2,A,B,C),\n    and_v(\n      v:pk(D),\n      older(144)\n    )\n  )\n)#{}",
+                checksum
+            )
+        );
+        assert_eq!(
+            Descriptor::<String>::from_str_pretty(&pretty).unwrap(),
+            desc
+        );
+        assert!(Descriptor::<String>::from_str(&pretty).is_err());
+        // A key whose display is not a valid expression
+        assert!(Descriptor::new_pk("A)".to_owned())
+            .to_string_pretty()
+            .is_err());
+
+        let tr =
+            Descriptor::<String>::from_str("eltr(A,{pk(B),and_v(v:pk(C),after(100))})").unwrap();
+        assert_eq!(
+            Descriptor::<String>::from_str_pretty(&tr.to_string_pretty().unwrap()).unwrap(),
+            tr
+        );
+        let edited = "eltr(A,\n\t{\n\t\tpk(B),\n\t\tand_v(v:pk(C), after(100))\n\t}\n)\n";
+        assert_eq!(Descriptor::<String>::from_str_pretty(edited).unwrap(), tr);
+    }
+
+    #[test]
+    fn roundtrip_check() {
+        for desc in [
+            "elwsh(or_d(multi(2,A,B,C),and_v(v:pk(D),older(144))))",
+            "eltr(A,{pk(B),and_v(v:pk(C),num64_eq(inp_v(0),out_v(1)))})",
+            "elcovwsh(A,and_v(v:pkh(B),ver_eq(2)))",
+        ]
+        .iter()
+        {
+            let desc = Descriptor::<String>::from_str(desc).unwrap();
+            desc.roundtrip_check().unwrap();
+        }
+
+        // A key whose string representation is not a valid key
+        let desc = Descriptor::<String>::new_pkh("A)B".to_owned());
+        let err = desc.roundtrip_check().unwrap_err();
+        assert!(err.to_string().contains("elpkh(A)B)"));
+    }
+
+    #[test]
+    fn from_str_ext() {
+        let mixed = "elwsh(and_v(v:pk(A),and_v(v:after(100),after(1000000000))))";
+        let repeated = "eltr(A,{pk(B),or_d(pk(C),pk(C))})";
+        // Parsing alone does not check the sanity of the miniscripts
+        Descriptor::<String>::from_str(mixed).unwrap();
+        Descriptor::<String>::from_str(repeated).unwrap();
+
+        assert_eq!(
+            Descriptor::<String>::from_str_ext(mixed, &ExtParams::sane()),
+            Err(Error::AnalysisError(
+                AnalysisError::HeightTimelockCombination
+            ))
+        );
+        Descriptor::<String>::from_str_ext(mixed, &ExtParams::sane().timelock_mixing()).unwrap();
+        assert_eq!(
+            Descriptor::<String>::from_str_ext(repeated, &ExtParams::sane().timelock_mixing()),
+            Err(Error::AnalysisError(AnalysisError::RepeatedPubkeys))
+        );
+        Descriptor::<String>::from_str_ext(repeated, &ExtParams::sane().repeated_pk()).unwrap();
+    }
+
+    #[test]
+    fn replace_key() {
+        let old = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*";
+        let other = "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB/0/*";
+        let new = "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH/0/*";
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(&format!(
+            "elwsh(or_d(multi(2,{0},{1}),and_v(v:pk({0}),older(144))))",
+            old, other
+        ))
+        .unwrap();
+        let old = DescriptorPublicKey::from_str(old).unwrap();
+        let new = DescriptorPublicKey::from_str(new).unwrap();
+
+        let (rotated, count) = desc.replace_key(&old, new.clone()).unwrap();
+        assert_eq!(count, 2);
+        assert!(!rotated.for_any_key(|pk| *pk == old));
+        let expected = desc
+            .to_string()
+            .split('#')
+            .next()
+            .unwrap()
+            .replace(&old.to_string(), &new.to_string());
+        assert_eq!(rotated, Descriptor::from_str(&expected).unwrap());
+        // The checksum is the one of the new descriptor
+        assert_eq!(rotated.to_string().split('#').next().unwrap(), expected);
+        rotated.roundtrip_check().unwrap();
+
+        assert!(rotated.replace_key(&old, new.clone()).is_err());
+        let other = DescriptorPublicKey::from_str(other).unwrap();
+        assert!(rotated.replace_key(&other, new).is_err());
+    }
+
+    #[test]
+    fn desc_type_elements() {
+        let tr = Descriptor::<String>::from_str("eltr(A,pk(B))").unwrap();
+        assert_eq!(tr.desc_type(), DescriptorType::Tr);
+        assert!(!tr.desc_type().is_covenant());
+
+        let tr_ext =
+            Descriptor::<String>::from_str("eltr(A,and_v(v:pk(B),curr_idx_eq(1)))").unwrap();
+        assert_eq!(tr_ext.desc_type(), DescriptorType::TrExt);
+        assert!(tr_ext.desc_type().is_covenant());
+        assert_eq!(
+            tr_ext.desc_type().segwit_version(),
+            Some(WitnessVersion::V1)
+        );
+
+        let blinded = Blinded::<String>::from_str("blinded(K,elwpkh(A))").unwrap();
+        assert_eq!(blinded.desc_type(), DescriptorType::Wpkh);
+    }
+
+    #[test]
+    fn descriptor_macro() {
+        let key = |name: &str| name.to_owned();
+        let desc: Descriptor<String> = descriptor!(elwsh(or_d(
+            multi(2, key("A"), key("B")),
+            and_v(v:pk(key("C")), older(Sequence::from_height(144)))
+        )))
+        .unwrap();
+        assert_eq!(
+            desc,
+            Descriptor::from_str("elwsh(or_d(multi(2,A,B),and_v(v:pk(C),older(144))))").unwrap()
+        );
+
+        let desc: Descriptor<String> =
+            descriptor!(elsh(wsh(sortedmulti(1, key("A"), key("B"))))).unwrap();
+        assert_eq!(
+            desc,
+            Descriptor::from_str("elsh(wsh(sortedmulti(1,A,B)))").unwrap()
+        );
+
+        let desc: Descriptor<String> = descriptor!(elsh(wpkh(key("A")))).unwrap();
+        assert_eq!(desc, Descriptor::from_str("elsh(wpkh(A))").unwrap());
+
+        let ver_eq = CovenantExt::LegacyVerEq(LegacyVerEq { n: 2 });
+        let desc: Descriptor<String> =
+            descriptor!(elcovwsh(key("A"), and_v(v:pk(key("B")), ext(ver_eq)))).unwrap();
+        assert_eq!(
+            desc,
+            Descriptor::from_str("elcovwsh(A,and_v(v:pk(B),ver_eq(2)))").unwrap()
+        );
+
+        // The fragments are type checked when the macro is evaluated
+        let res: Result<Descriptor<String>, _> =
+            descriptor!(elwsh(and_v(pk(key("A")), pk(key("B")))));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn confidential_address() {
+        let blinder = secp256k1_zkp::PublicKey::from_str(
+            "0231c7d3fc85c148717848033ce276ae2b464a4e2c367ed33886cc428b8af48ff8",
+        )
+        .unwrap();
+        let params = &elements::AddressParams::LIQUID;
+        for desc in &[
+            "elpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+            "elwpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+            "elsh(wpkh(020000000000000000000000000000000000000000000000000000000000000002))",
+            "eltr(020000000000000000000000000000000000000000000000000000000000000002)",
+        ] {
+            let desc = StdDescriptor::from_str(desc).unwrap();
+            let unconf = desc.unconfidential_address(params).unwrap();
+            assert_eq!(unconf, desc.address(params).unwrap());
+            assert!(!unconf.is_blinded());
+            let conf = desc.confidential_address(blinder, params).unwrap();
+            assert_eq!(conf, desc.blinded_address(blinder, params).unwrap());
+            assert_eq!(conf.blinding_pubkey, Some(blinder));
+            assert_eq!(conf.script_pubkey(), unconf.script_pubkey());
+        }
+
+        let wsh = Wsh::<PublicKey>::new(
+            Miniscript::from_str(
+                "pk(020000000000000000000000000000000000000000000000000000000000000002)",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            wsh.confidential_address(blinder, params),
+            wsh.address(Some(blinder), params)
+        );
+        // The params do not need to be 'static
+        let local = params.clone();
+        assert_eq!(
+            wsh.unconfidential_address(&local),
+            wsh.address(None, params)
+        );
+        assert!(StdDescriptor::from_str(TEST_PK)
+            .unwrap()
+            .unconfidential_address(params)
+            .is_err());
+    }
+
+    #[test]
+    fn related_scripts() {
+        let pk = "020000000000000000000000000000000000000000000000000000000000000002";
+        let sh_wsh = StdDescriptor::from_str(&format!("elsh(wsh(pk({})))", pk)).unwrap();
+        let scripts = sh_wsh.related_scripts();
+        let witness_script = sh_wsh.explicit_script().unwrap();
+        assert_eq!(scripts.script_pubkey, sh_wsh.script_pubkey());
+        assert_eq!(scripts.redeem_script, Some(witness_script.to_v0_p2wsh()));
+        assert_eq!(scripts.witness_script, Some(witness_script.clone()));
+        assert_eq!(scripts.script_code, Some(witness_script));
+        assert_eq!(scripts.unsigned_script_sig, sh_wsh.unsigned_script_sig());
+
+        let sh_wpkh = StdDescriptor::from_str(&format!("elsh(wpkh({}))", pk)).unwrap();
+        let scripts = sh_wpkh.related_scripts();
+        assert_eq!(
+            scripts.redeem_script,
+            Some(sh_wpkh.explicit_script().unwrap())
+        );
+        assert_eq!(scripts.witness_script, None);
+        assert_eq!(scripts.script_code, Some(sh_wpkh.script_code().unwrap()));
+
+        let tr = StdDescriptor::from_str(&format!("eltr({})", pk)).unwrap();
+        let scripts = tr.related_scripts();
+        assert_eq!(scripts.redeem_script, None);
+        assert_eq!(scripts.script_code, None);
+        assert!(scripts.unsigned_script_sig.is_empty());
+    }
+
+    #[test]
+    fn wsh_construction_limits() {
+        use crate::miniscript::context::ScriptContextError;
+        use crate::Threshold;
+
+        // thresh(1,pk(K0),s:pk(K1),...) with `n` keys
+        let thresh = |n: usize| -> Miniscript<String, Segwitv0> {
+            let subs = (0..n)
+                .map(|i| {
+                    let pk = Miniscript::from_str(&format!("pk(K{})", i)).unwrap();
+                    if i == 0 {
+                        Arc::new(pk)
+                    } else {
+                        Arc::new(Miniscript::from_ast(Terminal::Swap(Arc::new(pk))).unwrap())
+                    }
+                })
+                .collect();
+            Miniscript::from_ast(Terminal::Thresh(Threshold::new(1, subs).unwrap())).unwrap()
+        };
+        let context_err = |res: Result<Wsh<String>, Error>| match res {
+            Err(Error::ContextError(e)) => e,
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert!(Wsh::new(thresh(60)).is_ok());
+        // 2590 bytes, but 209 opcodes
+        let ms = thresh(70);
+        assert_eq!(ms.script_size(), 2590);
+        assert_eq!(
+            context_err(Wsh::new(ms.clone())),
+            ScriptContextError::MaxOpCountExceeded
+        );
+        assert!(Descriptor::new_sh_wsh(ms).is_err());
+        // 4070 bytes
+        assert_eq!(
+            context_err(Wsh::new(thresh(110))),
+            ScriptContextError::MaxWitnessScriptSizeExceeded
+        );
+    }
 }
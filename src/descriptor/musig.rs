@@ -0,0 +1,328 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # MuSig2 Keys
+//!
+//! Keys of the form `musig(A,B,..)`, standing for the BIP-327 aggregate of
+//! their participants, so that e.g. the two parties of a channel can use a
+//! single `pk()` in a tapscript leaf, which does not reveal on chain that it
+//! is a multisignature.
+//!
+//! Only key aggregation is done here: the MuSig2 signing sessions, i.e. the
+//! exchange of nonces and the aggregation of partial signatures, happen
+//! outside of this library. The aggregated signature is then provided to the
+//! satisfier like any other signature, for the aggregate key, see
+//! [`MusigKey`].
+//!
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::constants::CURVE_ORDER;
+use bitcoin::secp256k1::{PublicKey, Scalar};
+use elements::secp256k1_zkp;
+
+use crate::{Error, MiniscriptKey, ToPublicKey};
+
+/// A key which is either a single key, or the MuSig2 aggregate of keys,
+/// written `musig(A,B,..)`
+///
+/// As a [`ToPublicKey`], a `musig()` key is its BIP-327 aggregate, in the
+/// order of its participants, so that satisfiers look up the aggregated
+/// signature under the aggregate key. E.g. for a PSET, the aggregated
+/// signature goes in `tap_script_sigs` under the x-only aggregate key and
+/// the leaf hash, as returned by [`MusigKey::aggregate`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum MusigKey<Pk> {
+    /// A single key
+    Single(Pk),
+    /// The aggregate of the participants, in their order
+    ///
+    /// As in BIP-390, the participants are single keys: aggregates cannot be
+    /// nested.
+    Musig(Vec<Pk>),
+}
+
+impl<Pk> MusigKey<Pk> {
+    /// Iterates over the single keys, i.e. the key itself or the
+    /// participants of the aggregate
+    pub fn iter_single_keys(&self) -> std::slice::Iter<'_, Pk> {
+        match *self {
+            MusigKey::Single(ref pk) => std::slice::from_ref(pk).iter(),
+            MusigKey::Musig(ref keys) => keys.iter(),
+        }
+    }
+}
+
+impl<Pk: ToPublicKey> MusigKey<Pk> {
+    /// The key, i.e. the BIP-327 aggregate of the participants for a
+    /// `musig()` key
+    pub fn aggregate(&self) -> Result<bitcoin::PublicKey, Error> {
+        match *self {
+            MusigKey::Single(ref pk) => Ok(pk.to_public_key()),
+            MusigKey::Musig(ref keys) => {
+                let keys = keys
+                    .iter()
+                    .map(ToPublicKey::to_public_key)
+                    .collect::<Vec<_>>();
+                key_agg(&keys)
+            }
+        }
+    }
+}
+
+// The tagged hash of BIP-340
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    for data in data {
+        engine.input(data);
+    }
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+// The hash reduced modulo the curve order, which it exceeds with negligible
+// probability
+fn hash_to_scalar(mut hash: [u8; 32]) -> Scalar {
+    if hash >= CURVE_ORDER {
+        let mut borrow = 0;
+        for i in (0..32).rev() {
+            let diff = i16::from(hash[i]) - i16::from(CURVE_ORDER[i]) - borrow;
+            borrow = i16::from(diff < 0);
+            hash[i] = diff.rem_euclid(256) as u8;
+        }
+    }
+    Scalar::from_be_bytes(hash).expect("reduced modulo the curve order")
+}
+
+/// Aggregates the keys with the `KeyAgg` algorithm of BIP-327, in their
+/// order
+pub fn key_agg(keys: &[bitcoin::PublicKey]) -> Result<bitcoin::PublicKey, Error> {
+    crate::with_secp(|secp| key_agg_with_secp(secp, keys))
+}
+
+/// Aggregates the keys as [`key_agg`] does, with the given context
+pub fn key_agg_with_secp<C: secp256k1_zkp::Verification>(
+    secp: &secp256k1_zkp::Secp256k1<C>,
+    keys: &[bitcoin::PublicKey],
+) -> Result<bitcoin::PublicKey, Error> {
+    let keys = keys
+        .iter()
+        .map(|pk| pk.inner.serialize())
+        .collect::<Vec<_>>();
+    let list = keys
+        .iter()
+        .flat_map(|pk| pk.iter().copied())
+        .collect::<Vec<_>>();
+    let list_hash = tagged_hash("KeyAgg list", &[&list[..]]);
+    // The first key different from the first one has a coefficient of 1
+    let second_key = keys.iter().find(|&pk| *pk != keys[0]);
+
+    let invalid = |_| Error::BadDescriptor("invalid musig key aggregation".to_string());
+    let mut points = Vec::with_capacity(keys.len());
+    for key in &keys {
+        let point = PublicKey::from_slice(key).map_err(invalid)?;
+        if Some(key) == second_key {
+            points.push(point);
+        } else {
+            let coefficient = tagged_hash("KeyAgg coefficient", &[&list_hash[..], &key[..]]);
+            points.push(
+                point
+                    .mul_tweak(secp, &hash_to_scalar(coefficient))
+                    .map_err(invalid)?,
+            );
+        }
+    }
+    let points = points.iter().collect::<Vec<_>>();
+    let inner = PublicKey::combine_keys(&points).map_err(invalid)?;
+    Ok(bitcoin::PublicKey::new(inner))
+}
+
+impl<Pk: fmt::Display> fmt::Display for MusigKey<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MusigKey::Single(ref pk) => pk.fmt(f),
+            MusigKey::Musig(ref keys) => {
+                f.write_str("musig(")?;
+                for (i, key) in keys.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    key.fmt(f)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl<Pk: FromStr> FromStr for MusigKey<Pk>
+where
+    <Pk as FromStr>::Err: ToString,
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner = match s.strip_prefix("musig(").and_then(|s| s.strip_suffix(')')) {
+            Some(inner) => inner,
+            None => {
+                return Pk::from_str(s)
+                    .map(MusigKey::Single)
+                    .map_err(|e| Error::Unexpected(e.to_string()))
+            }
+        };
+        // BIP-390 does not allow nested aggregates
+        if inner.contains("musig(") {
+            return Err(Error::Unexpected(format!("nested musig() in {}", s)));
+        }
+        let keys = inner
+            .split(',')
+            .map(|pk| Pk::from_str(pk).map_err(|e| Error::Unexpected(e.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(MusigKey::Musig(keys))
+    }
+}
+
+impl<Pk: MiniscriptKey> MiniscriptKey for MusigKey<Pk> {
+    type Sha256 = Pk::Sha256;
+    type Hash256 = Pk::Hash256;
+    type Ripemd160 = Pk::Ripemd160;
+    type Hash160 = Pk::Hash160;
+
+    fn is_uncompressed(&self) -> bool {
+        match *self {
+            MusigKey::Single(ref pk) => pk.is_uncompressed(),
+            MusigKey::Musig(..) => false,
+        }
+    }
+
+    fn is_x_only_key(&self) -> bool {
+        match *self {
+            MusigKey::Single(ref pk) => pk.is_x_only_key(),
+            MusigKey::Musig(..) => false,
+        }
+    }
+
+    fn num_der_paths(&self) -> usize {
+        self.iter_single_keys()
+            .map(MiniscriptKey::num_der_paths)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey> ToPublicKey for MusigKey<Pk> {
+    fn to_public_key(&self) -> bitcoin::PublicKey {
+        // Only fails if the aggregate is the point at infinity, which cannot
+        // be reached without breaking the hash function
+        self.aggregate().expect("musig keys aggregate")
+    }
+
+    fn to_sha256(hash: &Pk::Sha256) -> sha256::Hash {
+        Pk::to_sha256(hash)
+    }
+
+    fn to_hash256(hash: &Pk::Hash256) -> crate::hash256::Hash {
+        Pk::to_hash256(hash)
+    }
+
+    fn to_ripemd160(hash: &Pk::Ripemd160) -> bitcoin::hashes::ripemd160::Hash {
+        Pk::to_ripemd160(hash)
+    }
+
+    fn to_hash160(hash: &Pk::Hash160) -> bitcoin::hashes::hash160::Hash {
+        Pk::to_hash160(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use elements::taproot::{LeafVersion, TapLeafHash};
+
+    use super::*;
+    use crate::{Miniscript, Satisfier, Tap};
+
+    fn key(s: &str) -> MusigKey<bitcoin::PublicKey> {
+        MusigKey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn bip327_key_agg() {
+        let x1 = "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9";
+        let x2 = "03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659";
+        let x3 = "023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66";
+        let agg = |keys: &[&str]| {
+            key(&format!("musig({})", keys.join(",")))
+                .to_x_only_pubkey()
+                .to_string()
+                .to_uppercase()
+        };
+        assert_eq!(
+            agg(&[x1, x2, x3]),
+            "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C"
+        );
+        assert_eq!(
+            agg(&[x3, x2, x1]),
+            "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B"
+        );
+        assert_eq!(
+            agg(&[x1, x1, x1]),
+            "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935"
+        );
+        assert_eq!(
+            agg(&[x1, x1, x2, x2]),
+            "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E"
+        );
+    }
+
+    #[test]
+    fn musig_in_tapscript() {
+        let x1 = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+        let x2 = "03dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659";
+        let s = format!("pk(musig({},{}))", x1, x2);
+        let ms = Miniscript::<MusigKey<bitcoin::PublicKey>, Tap>::from_str(&s).unwrap();
+        assert_eq!(ms.to_string(), s);
+        let agg = key(&format!("musig({},{})", x1, x2));
+        assert_eq!(
+            agg.iter_single_keys().collect::<Vec<_>>(),
+            vec![&key(x1).aggregate().unwrap(), &key(x2).aggregate().unwrap()]
+        );
+        // The leaf pays to the aggregate key
+        let agg_key = agg.to_x_only_pubkey();
+        assert_eq!(ms.encode().as_bytes()[1..33], agg_key.serialize()[..]);
+
+        // The aggregated signature is looked up under the aggregate key
+        let leaf_hash = TapLeafHash::from_script(&ms.encode(), LeafVersion::default());
+        let sig = elements::SchnorrSig {
+            sig: secp256k1_zkp::schnorr::Signature::from_slice(&[1; 64]).unwrap(),
+            hash_ty: elements::SchnorrSigHashType::Default,
+        };
+        let mut sigs = HashMap::new();
+        sigs.insert((agg.clone(), leaf_hash), sig);
+        assert_eq!(sigs.lookup_tap_leaf_script_sig(&agg, &leaf_hash), Some(sig));
+        let witness = ms.satisfy(&sigs).unwrap();
+        assert_eq!(witness, vec![sig.to_vec()]);
+
+        assert!(MusigKey::<bitcoin::PublicKey>::from_str("musig(").is_err());
+        assert!(MusigKey::<bitcoin::PublicKey>::from_str(&format!("musig({}))", x1)).is_err());
+    }
+
+    #[test]
+    fn musig_not_nested() {
+        let x1 = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+        let x2 = "03dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659";
+        let x3 = "023590a94e768f8e1815c2f24b4d80a8e3149316c3518ce7b7ad338368d038ca66";
+        let nested = format!("musig(musig({},{}),{})", x1, x2, x3);
+        assert!(MusigKey::<bitcoin::PublicKey>::from_str(&nested).is_err());
+        let nested = format!("musig({},musig({},{}))", x1, x2, x3);
+        assert!(MusigKey::<bitcoin::PublicKey>::from_str(&nested).is_err());
+        assert!(
+            Miniscript::<MusigKey<bitcoin::PublicKey>, Tap>::from_str(&format!("pk({})", nested))
+                .is_err()
+        );
+    }
+}
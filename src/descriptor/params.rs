@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Custom Address Parameters
+//!
+//! Address parameters of networks only known at runtime, e.g. of a custom
+//! Elements sidechain whose prefixes are loaded from a configuration file.
+//!
+//! The address APIs take `&'static` [`AddressParams`], as they are
+//! referenced by the [`elements::Address`] they create. A
+//! [`CustomAddressParams`] is turned into such parameters with
+//! [`CustomAddressParams::leak`], which can then be used with every address
+//! API, including the confidential addresses and covenant descriptors.
+//! [`Descriptor::unconfidential_address`] and
+//! [`Descriptor::confidential_address`] also take a [`CustomAddressParams`]
+//! directly.
+//!
+//! This module also checks that an address, e.g. from a payment request,
+//! corresponds to a descriptor and was encoded for the expected network, see
+//! [`Descriptor::address_matches`].
+//!
+
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use std::{error, fmt, ptr};
+
+use elements::{secp256k1_zkp, AddressParams};
+
+use super::{Blinded, Descriptor};
+use crate::extensions::ParseableExt;
+use crate::{Extension, MiniscriptKey, ToPublicKey};
+
+/// Owned address parameters of a network
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CustomAddressParams {
+    /// The base58 prefix for p2pkh addresses
+    pub p2pkh_prefix: u8,
+    /// The base58 prefix for p2sh addresses
+    pub p2sh_prefix: u8,
+    /// The base58 prefix for blinded addresses
+    pub blinded_prefix: u8,
+    /// The bech32 HRP for unblinded segwit addresses
+    pub bech_hrp: String,
+    /// The bech32 HRP for blinded segwit addresses
+    pub blech_hrp: String,
+}
+
+impl CustomAddressParams {
+    /// Leaks the parameters, to obtain the `&'static` [`AddressParams`]
+    /// taken by the address APIs.
+    ///
+    /// The parameters are never freed. They are interned, so each distinct
+    /// value is leaked only once and calling this repeatedly with the same
+    /// parameters returns the same reference.
+    pub fn leak(&self) -> &'static AddressParams {
+        let mut interned = interned_params()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(params) = interned.get(self) {
+            return params;
+        }
+        let leak_str = |s: &str| -> &'static str { Box::leak(s.to_owned().into_boxed_str()) };
+        let params: &'static AddressParams = Box::leak(Box::new(AddressParams {
+            p2pkh_prefix: self.p2pkh_prefix,
+            p2sh_prefix: self.p2sh_prefix,
+            blinded_prefix: self.blinded_prefix,
+            bech_hrp: leak_str(&self.bech_hrp),
+            blech_hrp: leak_str(&self.blech_hrp),
+        }));
+        interned.insert(self.clone(), params);
+        params
+    }
+
+    // The `&'static` params of a known network, which are not leaked, or the
+    // interned params otherwise
+    pub(crate) fn to_static(&self) -> &'static AddressParams {
+        [
+            &AddressParams::LIQUID,
+            &AddressParams::LIQUID_TESTNET,
+            &AddressParams::ELEMENTS,
+        ]
+        .iter()
+        .find(|params| *self == ***params)
+        .copied()
+        .unwrap_or_else(|| self.leak())
+    }
+}
+
+type InternedParams = Mutex<HashMap<CustomAddressParams, &'static AddressParams>>;
+
+// The parameters leaked by `CustomAddressParams::leak`. `Mutex::new` is not
+// `const` on our MSRV, so the map is allocated on first use.
+fn interned_params() -> &'static InternedParams {
+    static INIT: Once = Once::new();
+    static mut INTERNED: *const InternedParams = ptr::null();
+    // SAFETY: `INTERNED` is only written once, inside `call_once`, which
+    // synchronizes with every later read.
+    unsafe {
+        INIT.call_once(|| INTERNED = Box::into_raw(Box::new(Mutex::new(HashMap::new()))));
+        &*INTERNED
+    }
+}
+
+impl<'a> From<&'a AddressParams> for CustomAddressParams {
+    fn from(params: &'a AddressParams) -> Self {
+        CustomAddressParams {
+            p2pkh_prefix: params.p2pkh_prefix,
+            p2sh_prefix: params.p2sh_prefix,
+            blinded_prefix: params.blinded_prefix,
+            bech_hrp: params.bech_hrp.to_owned(),
+            blech_hrp: params.blech_hrp.to_owned(),
+        }
+    }
+}
+
+impl PartialEq<AddressParams> for CustomAddressParams {
+    fn eq(&self, other: &AddressParams) -> bool {
+        *self == CustomAddressParams::from(other)
+    }
+}
+
+/// The reason an address does not correspond to a descriptor
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AddressMismatch {
+    /// The address pays to another scriptPubKey
+    ScriptPubkey,
+    /// The address is not blinded with the blinding key of the descriptor
+    Blinder {
+        /// The blinding key of the descriptor
+        expected: secp256k1_zkp::PublicKey,
+        /// The blinding key of the address, if it is confidential
+        found: Option<secp256k1_zkp::PublicKey>,
+    },
+    /// The address was encoded for another network
+    Network {
+        /// The parameters of the expected network
+        expected: &'static AddressParams,
+        /// The parameters the address was encoded with
+        found: &'static AddressParams,
+    },
+}
+
+impl fmt::Display for AddressMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            AddressMismatch::ScriptPubkey => {
+                f.write_str("address does not pay to the descriptor scriptPubKey")
+            }
+            AddressMismatch::Blinder {
+                ref expected,
+                found: Some(ref found),
+            } => write!(f, "address blinded with {}, expected {}", found, expected),
+            AddressMismatch::Blinder {
+                ref expected,
+                found: None,
+            } => write!(
+                f,
+                "unconfidential address, expected blinding with {}",
+                expected
+            ),
+            AddressMismatch::Network { expected, found } => write!(
+                f,
+                "address for network with bech32 prefix {}, expected {}",
+                found.bech_hrp, expected.bech_hrp
+            ),
+        }
+    }
+}
+
+impl error::Error for AddressMismatch {}
+
+impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<Pk, Ext> {
+    /// Checks that `addr` pays to this descriptor, and returns the network
+    /// parameters it was encoded with.
+    ///
+    /// The address may be confidential or not, use
+    /// [`Blinded::address_matches`] to also check its blinding key.
+    pub fn address_matches(
+        &self,
+        addr: &elements::Address,
+    ) -> Result<&'static AddressParams, AddressMismatch> {
+        if addr.script_pubkey() != self.script_pubkey() {
+            return Err(AddressMismatch::ScriptPubkey);
+        }
+        Ok(addr.params)
+    }
+
+    /// Checks that `addr` pays to this descriptor and was encoded for the
+    /// network with the given parameters, e.g. to catch mainnet addresses
+    /// used on a testnet.
+    pub fn address_matches_network(
+        &self,
+        addr: &elements::Address,
+        params: &'static AddressParams,
+    ) -> Result<(), AddressMismatch> {
+        check_network(self.address_matches(addr)?, params)
+    }
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey> Blinded<Pk> {
+    /// Checks that `addr` pays to this descriptor, and is blinded with its
+    /// blinding key, and returns the network parameters it was encoded
+    /// with.
+    pub fn address_matches(
+        &self,
+        addr: &elements::Address,
+    ) -> Result<&'static AddressParams, AddressMismatch> {
+        if addr.script_pubkey() != self.script_pubkey() {
+            return Err(AddressMismatch::ScriptPubkey);
+        }
+        let expected = self.blinder().to_public_key().inner;
+        if addr.blinding_pubkey != Some(expected) {
+            return Err(AddressMismatch::Blinder {
+                expected,
+                found: addr.blinding_pubkey,
+            });
+        }
+        Ok(addr.params)
+    }
+
+    /// Checks that `addr` pays to this descriptor, is blinded with its
+    /// blinding key and was encoded for the network with the given
+    /// parameters.
+    pub fn address_matches_network(
+        &self,
+        addr: &elements::Address,
+        params: &'static AddressParams,
+    ) -> Result<(), AddressMismatch> {
+        check_network(self.address_matches(addr)?, params)
+    }
+}
+
+fn check_network(
+    found: &'static AddressParams,
+    expected: &'static AddressParams,
+) -> Result<(), AddressMismatch> {
+    if found == expected {
+        Ok(())
+    } else {
+        Err(AddressMismatch::Network { expected, found })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::Descriptor;
+
+    #[test]
+    fn custom_params() {
+        let custom = CustomAddressParams {
+            p2pkh_prefix: 60,
+            p2sh_prefix: 40,
+            blinded_prefix: 13,
+            bech_hrp: "cx".to_owned(),
+            blech_hrp: "cl".to_owned(),
+        };
+        let params = custom.leak();
+        assert_eq!(custom, *params);
+        assert!(std::ptr::eq(params, custom.clone().leak()));
+        let other = CustomAddressParams {
+            p2pkh_prefix: 61,
+            ..custom.clone()
+        };
+        assert!(!std::ptr::eq(params, other.leak()));
+        assert_eq!(
+            CustomAddressParams::from(&AddressParams::LIQUID),
+            AddressParams::LIQUID
+        );
+
+        let blinder = elements::secp256k1_zkp::PublicKey::from_str(
+            "0231c7d3fc85c148717848033ce276ae2b464a4e2c367ed33886cc428b8af48ff8",
+        )
+        .unwrap();
+        let desc = Descriptor::<bitcoin::PublicKey>::from_str(
+            "elwpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap();
+        let addr = desc.unconfidential_address(params).unwrap();
+        assert!(addr.to_string().starts_with("cx1"));
+        assert_eq!(desc.unconfidential_address(custom.clone()).unwrap(), addr);
+        let conf = desc.confidential_address(blinder, params).unwrap();
+        assert!(conf.to_string().starts_with("cl1"));
+        assert_eq!(
+            elements::Address::parse_with_params(&conf.to_string(), params).unwrap(),
+            conf
+        );
+    }
+
+    #[test]
+    fn address_matches() {
+        let liquid = &AddressParams::LIQUID;
+        let testnet = &AddressParams::LIQUID_TESTNET;
+        let blinder = "0231c7d3fc85c148717848033ce276ae2b464a4e2c367ed33886cc428b8af48ff8";
+        let desc = Descriptor::<bitcoin::PublicKey>::from_str(
+            "elwpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap();
+        let other = Descriptor::<bitcoin::PublicKey>::from_str(
+            "elpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap();
+
+        let addr = desc.address(testnet).unwrap();
+        assert_eq!(desc.address_matches(&addr), Ok(testnet));
+        assert!(desc.address_matches_network(&addr, testnet).is_ok());
+        assert_eq!(
+            desc.address_matches_network(&addr, liquid),
+            Err(AddressMismatch::Network {
+                expected: liquid,
+                found: testnet
+            })
+        );
+        assert_eq!(
+            other.address_matches(&addr),
+            Err(AddressMismatch::ScriptPubkey)
+        );
+
+        let blinded = Blinded::new(
+            bitcoin::PublicKey::from_str(blinder).unwrap(),
+            Descriptor::from_str(&desc.to_string()).unwrap(),
+        );
+        let blinder = blinded.blinder().inner;
+        let conf = blinded.address(liquid).unwrap();
+        assert_eq!(blinded.address_matches(&conf), Ok(liquid));
+        assert_eq!(desc.address_matches(&conf), Ok(liquid));
+        assert_eq!(
+            blinded.address_matches(&addr),
+            Err(AddressMismatch::Blinder {
+                expected: blinder,
+                found: None
+            })
+        );
+    }
+}
@@ -39,7 +39,7 @@ use crate::{
 
 /// New Pegin Descriptor with Miniscript support
 /// Useful with dynamic federations
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct Pegin<Pk: MiniscriptKey> {
     /// The untweaked pegin bitcoin descriptor
     pub fed_desc: BtcDescriptor<Pk>,
@@ -112,7 +112,7 @@ impl MiniscriptKey for LegacyPeginKey {
 }
 
 /// Legacy Pegin Descriptor
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct LegacyPegin<Pk: MiniscriptKey> {
     /// The federation pks
     pub fed_pks: Vec<LegacyPeginKey>,
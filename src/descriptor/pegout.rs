@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Pegout Support
+//!
+//! Construction and recognition of pegout outputs. A pegout is an elements
+//! output whose script pubkey is
+//! `OP_RETURN <genesis_hash> <mainchain_spk> [<extra_data>...]` and whose value
+//! is released on the mainchain to `mainchain_spk`.
+//!
+//! The mainchain script pubkey is described by a bitcoin descriptor, so that
+//! wallets and monitoring tools can check that pegouts go to the expected
+//! destination.
+//!
+
+use std::ops::Range;
+
+use bitcoin::hashes::Hash;
+use bitcoin::BlockHash;
+use bitcoin_miniscript::DescriptorPublicKey as BtcDescriptorPublicKey;
+use elements::{confidential, opcodes, script, AssetId, TxOut, TxOutWitness};
+
+use crate::{BtcDescriptor, ToPublicKey};
+
+/// Script pubkey of a pegout to the mainchain with the given genesis hash,
+/// paying to `mainchain_desc`.
+///
+/// `extra_data` is appended as additional pushes, as used by some forks of
+/// Elements.
+pub fn pegout_script_pubkey<Pk: ToPublicKey>(
+    genesis_hash: BlockHash,
+    mainchain_desc: &BtcDescriptor<Pk>,
+    extra_data: &[&[u8]],
+) -> elements::Script {
+    let builder = script::Builder::new()
+        .push_opcode(opcodes::all::OP_RETURN)
+        .push_slice(&genesis_hash.to_byte_array())
+        .push_slice(mainchain_desc.script_pubkey().as_bytes());
+    extra_data
+        .iter()
+        .fold(builder, |builder, data| builder.push_slice(data))
+        .into_script()
+}
+
+/// Explicit pegout output of `value` units of `asset` to `mainchain_desc`.
+pub fn pegout_txout<Pk: ToPublicKey>(
+    asset: AssetId,
+    value: u64,
+    genesis_hash: BlockHash,
+    mainchain_desc: &BtcDescriptor<Pk>,
+) -> TxOut {
+    TxOut {
+        asset: confidential::Asset::Explicit(asset),
+        value: confidential::Value::Explicit(value),
+        nonce: confidential::Nonce::Null,
+        script_pubkey: pegout_script_pubkey(genesis_hash, mainchain_desc, &[]),
+        witness: TxOutWitness::default(),
+    }
+}
+
+/// Checks whether `txout` is a pegout to the mainchain with the given
+/// genesis hash paying to `mainchain_desc`.
+///
+/// Extra data after the mainchain script pubkey is ignored.
+pub fn is_pegout_to<Pk: ToPublicKey>(
+    txout: &TxOut,
+    genesis_hash: BlockHash,
+    mainchain_desc: &BtcDescriptor<Pk>,
+) -> bool {
+    match txout.pegout_data() {
+        Some(data) => {
+            data.genesis_hash == genesis_hash
+                && data.script_pubkey == mainchain_desc.script_pubkey()
+        }
+        None => false,
+    }
+}
+
+/// Returns the first derivation index in `range` of the ranged
+/// `mainchain_desc` to which `txout` is a pegout, if any.
+///
+/// Indexes for which the descriptor cannot be derived (hardened indexes) are
+/// skipped.
+pub fn find_pegout_index(
+    txout: &TxOut,
+    genesis_hash: BlockHash,
+    mainchain_desc: &BtcDescriptor<BtcDescriptorPublicKey>,
+    mut range: Range<u32>,
+) -> Option<u32> {
+    let data = txout.pegout_data()?;
+    if data.genesis_hash != genesis_hash {
+        return None;
+    }
+    range.find(|i| match mainchain_desc.at_derivation_index(*i) {
+        Ok(desc) => desc.script_pubkey() == data.script_pubkey,
+        Err(_) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::Network;
+
+    use super::*;
+
+    #[test]
+    fn pegout_to_descriptor() {
+        let genesis_hash = genesis_block(Network::Bitcoin).block_hash();
+        let asset =
+            AssetId::from_str("6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d")
+                .unwrap();
+        let desc = BtcDescriptor::<bitcoin::PublicKey>::from_str(
+            "wpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap();
+        let other = BtcDescriptor::<bitcoin::PublicKey>::from_str(
+            "pkh(020000000000000000000000000000000000000000000000000000000000000002)",
+        )
+        .unwrap();
+
+        let txout = pegout_txout(asset, 100_000, genesis_hash, &desc);
+        assert!(txout.is_pegout());
+        assert!(is_pegout_to(&txout, genesis_hash, &desc));
+        assert!(!is_pegout_to(&txout, genesis_hash, &other));
+        let testnet = genesis_block(Network::Testnet).block_hash();
+        assert!(!is_pegout_to(&txout, testnet, &desc));
+
+        // Extra data does not change the destination
+        let mut with_extra = txout.clone();
+        with_extra.script_pubkey = pegout_script_pubkey(genesis_hash, &desc, &[&[1, 2, 3]]);
+        assert!(is_pegout_to(&with_extra, genesis_hash, &desc));
+
+        // Ranged descriptors
+        let ranged = BtcDescriptor::<BtcDescriptorPublicKey>::from_str("wpkh(xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*)").unwrap();
+        let derived = ranged.at_derivation_index(7).unwrap();
+        let txout = pegout_txout(asset, 100_000, genesis_hash, &derived);
+        assert_eq!(
+            find_pegout_index(&txout, genesis_hash, &ranged, 0..10),
+            Some(7)
+        );
+        assert_eq!(find_pegout_index(&txout, genesis_hash, &ranged, 0..5), None);
+    }
+}
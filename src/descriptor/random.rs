@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Random Descriptors
+//!
+//! Generation of random descriptors for the property tests of downstream
+//! wallets, see [`Descriptor::generate_random`].
+//!
+//! Descriptors are generated from a small grammar of fragments which always
+//! type check, with fresh random keys, so that keys are never repeated.
+//! Generated descriptors are valid in their context, but they may not be
+//! sane, e.g. they may mix timelocks.
+//!
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::rand::Rng;
+use bitcoin::secp256k1::{Secp256k1, SecretKey, SignOnly};
+use elements::hashes::{sha256, Hash};
+
+use super::Descriptor;
+use crate::{Context, Error, Miniscript};
+
+/// How many times a descriptor is generated again when it exceeds the
+/// resource limits of its context
+const MAX_ATTEMPTS: usize = 32;
+
+/// Parameters of [`Descriptor::generate_random`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RandomParams {
+    /// Maximum depth of the generated miniscripts
+    pub max_depth: usize,
+    /// Maximum depth of the leaves of the generated taproot trees, which
+    /// always have at least one leaf
+    pub max_tap_depth: usize,
+    /// Whether taproot leaves may contain the covenant extensions, which
+    /// are only available in tapscript
+    pub extensions: bool,
+}
+
+impl Default for RandomParams {
+    fn default() -> Self {
+        RandomParams {
+            max_depth: 4,
+            max_tap_depth: 2,
+            extensions: false,
+        }
+    }
+}
+
+struct Generator<'a, R: Rng + ?Sized> {
+    rng: &'a mut R,
+    secp: Secp256k1<SignOnly>,
+    params: &'a RandomParams,
+}
+
+impl<'a, R: Rng + ?Sized> Generator<'a, R> {
+    fn key(&mut self) -> bitcoin::PublicKey {
+        let sk = SecretKey::new(&mut *self.rng);
+        bitcoin::PublicKey::new(sk.public_key(&self.secp))
+    }
+
+    fn keys(&mut self) -> Vec<String> {
+        let n = self.rng.gen_range(1..=5);
+        (0..n).map(|_| self.key().to_string()).collect()
+    }
+
+    // A miniscript of type B
+    fn ms_leaf(&mut self, ctx: Context) -> String {
+        let n = if ctx == Context::Tap && self.params.extensions {
+            8
+        } else {
+            5
+        };
+        match self.rng.gen_range(0..=n) {
+            0 => format!("pk({})", self.key()),
+            1 => format!("pkh({})", self.key()),
+            2 => format!("older({})", self.rng.gen_range(1..=0xffffu32)),
+            3 => format!("after({})", self.rng.gen_range(1..=0x7fff_ffffu32)),
+            4 => format!(
+                "sha256({})",
+                sha256::Hash::hash(&self.rng.gen::<[u8; 32]>())
+            ),
+            5 => {
+                let keys = self.keys();
+                let k = self.rng.gen_range(1..=keys.len());
+                let name = if ctx == Context::Tap {
+                    "multi_a"
+                } else {
+                    "multi"
+                };
+                format!("{}({},{})", name, k, keys.join(","))
+            }
+            6 => format!(
+                "num64_eq(inp_v({}),out_v({}))",
+                self.rng.gen_range(0..4u8),
+                self.rng.gen_range(0..4u8)
+            ),
+            7 => format!(
+                "num64_geq(curr_inp_v,out_v({}))",
+                self.rng.gen_range(0..4u8)
+            ),
+            _ => format!("curr_idx_eq({})", self.rng.gen_range(0..4u8)),
+        }
+    }
+
+    // A miniscript of type B. The first child of `or_d` and `andor` must be
+    // dissatisfiable and unit, which `pk` always is.
+    fn ms_string(&mut self, ctx: Context, depth: usize) -> String {
+        if depth == 0 {
+            return self.ms_leaf(ctx);
+        }
+        match self.rng.gen_range(0..=4u8) {
+            0 => self.ms_leaf(ctx),
+            1 => {
+                let left = self.ms_string(ctx, depth - 1);
+                let right = self.ms_string(ctx, depth - 1);
+                format!("and_v(v:{},{})", left, right)
+            }
+            2 => {
+                let left = self.ms_string(ctx, depth - 1);
+                let right = self.ms_string(ctx, depth - 1);
+                format!("or_i({},{})", left, right)
+            }
+            3 => {
+                let left = self.key();
+                let right = self.ms_string(ctx, depth - 1);
+                format!("or_d(pk({}),{})", left, right)
+            }
+            _ => {
+                let cond = self.key();
+                let left = self.ms_string(ctx, depth - 1);
+                let right = self.ms_string(ctx, depth - 1);
+                format!("andor(pk({}),{},{})", cond, left, right)
+            }
+        }
+    }
+
+    fn tap_tree(&mut self, depth: usize) -> String {
+        if depth == 0 || self.rng.gen() {
+            self.ms_string(Context::Tap, self.params.max_depth)
+        } else {
+            let left = self.tap_tree(depth - 1);
+            let right = self.tap_tree(depth - 1);
+            format!("{{{},{}}}", left, right)
+        }
+    }
+
+    fn descriptor(&mut self, ctx: Context) -> Result<Descriptor<bitcoin::PublicKey>, Error> {
+        let depth = self.params.max_depth;
+        match ctx {
+            Context::BareCtx => {
+                // A bare `pkh` miniscript is displayed as a pkh descriptor
+                let mut ms = self.ms_string(ctx, depth);
+                while ms.starts_with("pkh(") {
+                    ms = self.ms_string(ctx, depth);
+                }
+                Descriptor::new_bare(Miniscript::from_str_insane(&ms)?)
+            }
+            Context::Legacy => {
+                Descriptor::new_sh(Miniscript::from_str_insane(&self.ms_string(ctx, depth))?)
+            }
+            Context::Segwitv0 => {
+                Descriptor::new_wsh(Miniscript::from_str_insane(&self.ms_string(ctx, depth))?)
+            }
+            Context::Tap => {
+                let internal_key = self.key();
+                let tree = self.tap_tree(self.params.max_tap_depth);
+                Descriptor::from_str(&format!("eltr({},{})", internal_key, tree))
+            }
+            Context::NoChecks => unreachable!("checked by generate_random"),
+        }
+    }
+}
+
+impl Descriptor<bitcoin::PublicKey> {
+    /// Generates a random descriptor of the context `ctx`: a bare, a sh, a
+    /// wsh or a taproot descriptor, with miniscripts of depth at most
+    /// `params.max_depth`.
+    ///
+    /// Keys are fresh random keys, so that they are never repeated, and all
+    /// the fragments type check. The descriptors may not be sane though,
+    /// e.g. they may mix timelocks. Descriptors exceeding the resource
+    /// limits of the context are generated again, a few times.
+    ///
+    /// # Errors
+    ///
+    /// If `ctx` is [`Context::NoChecks`], or if no generated descriptor is
+    /// within the resource limits of the context.
+    pub fn generate_random<R: Rng + ?Sized>(
+        ctx: Context,
+        rng: &mut R,
+        params: &RandomParams,
+    ) -> Result<Self, Error> {
+        if ctx == Context::NoChecks {
+            return Err(Error::BadDescriptor(String::from(
+                "no descriptor has the NoChecks context",
+            )));
+        }
+        let mut gen = Generator {
+            rng,
+            secp: Secp256k1::signing_only(),
+            params,
+        };
+        let mut res = gen.descriptor(ctx);
+        for _ in 1..MAX_ATTEMPTS {
+            if res.is_ok() {
+                break;
+            }
+            res = gen.descriptor(ctx);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actual_rand::rngs::StdRng;
+    use actual_rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn generate_random() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let params = RandomParams {
+            extensions: true,
+            ..Default::default()
+        };
+        for ctx in [
+            Context::BareCtx,
+            Context::Legacy,
+            Context::Segwitv0,
+            Context::Tap,
+        ]
+        .iter()
+        {
+            for _ in 0..20 {
+                let desc =
+                    Descriptor::<bitcoin::PublicKey>::generate_random(*ctx, &mut rng, &params)
+                        .unwrap();
+                assert_eq!(Descriptor::from_str(&desc.to_string()), Ok(desc));
+            }
+        }
+        assert!(Descriptor::<bitcoin::PublicKey>::generate_random(
+            Context::NoChecks,
+            &mut rng,
+            &params
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Key Roles
+//!
+//! Listing of the keys of a descriptor along with the role they play in its
+//! spending conditions, e.g. to display who controls a wallet. A key used in
+//! several places of a descriptor is reported once, with all its roles.
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    Bare, Descriptor, DescriptorVisitor, LegacyCSFSCov, Pkh, Sh, ShInner, Tr, Wpkh, Wsh, WshInner,
+};
+use crate::miniscript::decode::Terminal;
+use crate::{Extension, Miniscript, MiniscriptKey, NoExt, ScriptContext};
+
+/// The role of a key in a descriptor
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum KeyRole {
+    /// The internal key of a taproot descriptor, which can spend alone using
+    /// the key path
+    TaprootInternal,
+    /// A participant of a multisig
+    Multisig,
+    /// A key which can only sign along with a timelock, e.g. a recovery key
+    Timelocked,
+    /// A key in any other position, e.g. a single-key descriptor
+    Signer,
+}
+
+/// A key of a descriptor with its roles, as returned by
+/// [`Descriptor::collect_keys`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyRoles<Pk: MiniscriptKey> {
+    /// The key
+    pub key: Pk,
+    /// The roles of the key, in all the places it is used
+    pub roles: BTreeSet<KeyRole>,
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// All the keys of the descriptor with their roles, in the order they
+    /// first appear in the descriptor.
+    ///
+    /// Keys combined with a timelock, i.e. in a conjunction with a branch
+    /// containing an `older` or `after` fragment, are tagged as
+    /// [`KeyRole::Timelocked`], in addition to [`KeyRole::Multisig`] for the
+    /// participants of a multisig. Keys of the form of a hash, as used in
+    /// `expr_raw_pkh`, are not reported.
+    pub fn collect_keys(&self) -> Vec<KeyRoles<Pk>> {
+        let mut collector = RoleCollector {
+            keys: vec![],
+            index: BTreeMap::new(),
+        };
+        self.accept(&mut collector);
+        collector.keys
+    }
+}
+
+struct RoleCollector<Pk: MiniscriptKey> {
+    keys: Vec<KeyRoles<Pk>>,
+    // The position of every key in `keys`
+    index: BTreeMap<Pk, usize>,
+}
+
+impl<Pk: MiniscriptKey> RoleCollector<Pk> {
+    fn add(&mut self, key: &Pk, role: KeyRole) {
+        let keys = &mut self.keys;
+        let idx = *self.index.entry(key.clone()).or_insert_with(|| {
+            keys.push(KeyRoles {
+                key: key.clone(),
+                roles: BTreeSet::new(),
+            });
+            keys.len() - 1
+        });
+        self.keys[idx].roles.insert(role);
+    }
+
+    fn add_multi(&mut self, keys: &[Pk], timelocked: bool) {
+        for key in keys {
+            self.add(key, KeyRole::Multisig);
+            if timelocked {
+                self.add(key, KeyRole::Timelocked);
+            }
+        }
+    }
+
+    fn add_tr<Ext: Extension>(&mut self, tr: &Tr<Pk, Ext>) {
+        self.add(tr.internal_key(), KeyRole::TaprootInternal);
+        for (_, ms) in tr.iter_scripts() {
+            self.add_ms(ms, false);
+        }
+    }
+
+    // Adds the keys of `ms`, `timelocked` telling whether a timelock is
+    // required along with `ms`
+    fn add_ms<Ctx: ScriptContext, Ext: Extension>(
+        &mut self,
+        ms: &Miniscript<Pk, Ctx, Ext>,
+        timelocked: bool,
+    ) {
+        match ms.node {
+            Terminal::PkK(ref pk) | Terminal::PkH(ref pk) => {
+                let role = if timelocked {
+                    KeyRole::Timelocked
+                } else {
+                    KeyRole::Signer
+                };
+                self.add(pk, role);
+            }
+            Terminal::Multi(ref thresh) | Terminal::MultiA(ref thresh) => {
+                self.add_multi(thresh.data(), timelocked)
+            }
+            Terminal::AndV(ref left, ref right) | Terminal::AndB(ref left, ref right) => {
+                self.add_ms(left, timelocked || has_timelock(right));
+                self.add_ms(right, timelocked || has_timelock(left));
+            }
+            Terminal::AndOr(ref a, ref b, ref c) => {
+                self.add_ms(a, timelocked || has_timelock(b));
+                self.add_ms(b, timelocked || has_timelock(a));
+                self.add_ms(c, timelocked);
+            }
+            Terminal::Thresh(ref thresh) => {
+                for (i, sub) in thresh.iter().enumerate() {
+                    let others_timelocked = thresh.k() > 1
+                        && thresh
+                            .iter()
+                            .enumerate()
+                            .any(|(j, other)| i != j && has_timelock(other));
+                    self.add_ms(sub, timelocked || others_timelocked);
+                }
+            }
+            _ => {
+                for branch in ms.branches() {
+                    self.add_ms(branch, timelocked);
+                }
+            }
+        }
+    }
+}
+
+// Whether `ms` contains an absolute or relative timelock
+fn has_timelock<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension>(
+    ms: &Miniscript<Pk, Ctx, Ext>,
+) -> bool {
+    ms.iter()
+        .any(|node| matches!(node.node, Terminal::After(..) | Terminal::Older(..)))
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> DescriptorVisitor<Pk, Ext> for RoleCollector<Pk> {
+    fn visit_bare(&mut self, bare: &Bare<Pk>) {
+        self.add_ms(bare.as_inner(), false);
+    }
+
+    fn visit_pkh(&mut self, pkh: &Pkh<Pk>) {
+        self.add(pkh.as_inner(), KeyRole::Signer);
+    }
+
+    fn visit_wpkh(&mut self, wpkh: &Wpkh<Pk>) {
+        self.add(wpkh.as_inner(), KeyRole::Signer);
+    }
+
+    fn visit_sh(&mut self, sh: &Sh<Pk>) {
+        match *sh.as_inner() {
+            ShInner::Wsh(ref wsh) => self.visit_wsh(wsh),
+            ShInner::Wpkh(ref wpkh) => self.visit_wpkh(wpkh),
+            ShInner::SortedMulti(ref smv) => self.add_multi(smv.participants(), false),
+            ShInner::Ms(ref ms) => self.add_ms(ms, false),
+        }
+    }
+
+    fn visit_wsh(&mut self, wsh: &Wsh<Pk>) {
+        match *wsh.as_inner() {
+            WshInner::SortedMulti(ref smv) => self.add_multi(smv.participants(), false),
+            WshInner::Ms(ref ms) => self.add_ms(ms, false),
+        }
+    }
+
+    fn visit_tr(&mut self, tr: &Tr<Pk, NoExt>) {
+        self.add_tr(tr);
+    }
+
+    fn visit_tr_ext(&mut self, tr: &Tr<Pk, Ext>) {
+        self.add_tr(tr);
+    }
+
+    fn visit_cov(&mut self, cov: &LegacyCSFSCov<Pk, Ext>) {
+        self.add(cov.pk(), KeyRole::Signer);
+        self.add_ms(cov.to_ms(), false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn roles(desc: &str) -> Vec<(String, Vec<KeyRole>)> {
+        Descriptor::<String>::from_str(desc)
+            .unwrap()
+            .collect_keys()
+            .into_iter()
+            .map(|k| (k.key, k.roles.into_iter().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn collect_keys() {
+        use KeyRole::*;
+
+        assert_eq!(roles("elwpkh(A)"), vec![("A".to_owned(), vec![Signer])]);
+        assert_eq!(
+            roles("elwsh(or_d(multi(2,A,B,C),and_v(v:pk(D),older(144))))"),
+            vec![
+                ("A".to_owned(), vec![Multisig]),
+                ("B".to_owned(), vec![Multisig]),
+                ("C".to_owned(), vec![Multisig]),
+                ("D".to_owned(), vec![Timelocked]),
+            ]
+        );
+        assert_eq!(
+            roles("eltr(A,{multi_a(2,A,B),and_v(v:multi_a(1,B,C),after(100))})"),
+            vec![
+                ("A".to_owned(), vec![TaprootInternal, Multisig]),
+                ("B".to_owned(), vec![Multisig, Timelocked]),
+                ("C".to_owned(), vec![Multisig, Timelocked]),
+            ]
+        );
+        assert_eq!(
+            roles("elsh(wsh(sortedmulti(1,B,A)))"),
+            vec![
+                ("B".to_owned(), vec![Multisig]),
+                ("A".to_owned(), vec![Multisig]),
+            ]
+        );
+    }
+}
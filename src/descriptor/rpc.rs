@@ -0,0 +1,513 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Elements Core wallet RPC support
+//!
+//! Conversion between descriptors of this crate and the JSON objects used by
+//! the `importdescriptors` and `listdescriptors` RPCs of Elements Core.
+//!
+//! Elements Core does not use the `el` prefix for its descriptors, so the
+//! descriptors are converted the same way as with
+//! [`Descriptor::to_bitcoin_descriptor`] and [`Descriptor::from_bitcoin_descriptor`].
+//! Covenant descriptors cannot be represented in Elements Core.
+//!
+//! The Elements Core releases able to watch and sign for a descriptor are
+//! reported by [`Descriptor::core_compatibility`].
+//!
+
+use std::{cmp, fmt};
+
+use super::{
+    strip_elements_prefix, Descriptor, DescriptorPublicKey, KeyMap, ShInner, Wsh, WshInner,
+};
+use crate::extensions::ParseableExt;
+#[cfg(feature = "serde")]
+use crate::serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use crate::{
+    Error, Extension, ForEachKey, Miniscript, MiniscriptKey, ScriptContext, Tap, Terminal,
+};
+
+/// Timestamp from which the wallet rescans for transactions to an
+/// imported descriptor
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ImportTimestamp {
+    /// Only transactions after the time of import, `"now"`
+    Now,
+    /// Unix time in seconds
+    Time(u64),
+}
+
+/// A single request for the `importdescriptors` RPC.
+///
+/// With the `serde` feature, this serializes into the JSON object expected
+/// by Elements Core.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ImportDescriptorRequest {
+    /// The descriptor string as understood by Elements Core, including the checksum
+    pub desc: String,
+    /// Whether the descriptor should be used to generate new addresses
+    pub active: bool,
+    /// Inclusive range of derivation indexes to import for ranged descriptors
+    pub range: Option<(u32, u32)>,
+    /// Next derivation index from which to generate addresses
+    pub next_index: Option<u32>,
+    /// Time from which to rescan the chain for transactions
+    pub timestamp: ImportTimestamp,
+    /// Whether the descriptor is used for change addresses
+    pub internal: bool,
+    /// Label of the addresses, only allowed for non-internal descriptors
+    pub label: Option<String>,
+}
+
+impl ImportDescriptorRequest {
+    /// Create a new request to import a watch-only descriptor.
+    ///
+    /// The request is inactive, non-internal, rescans from now on and, for
+    /// ranged descriptors, uses Elements Core default range.
+    pub fn new<Ext: Extension + ParseableExt>(
+        desc: &Descriptor<DescriptorPublicKey, Ext>,
+    ) -> Result<Self, Error> {
+        Ok(Self::from_desc_string(desc.to_bitcoin_descriptor()?))
+    }
+
+    /// Create a new request to import a descriptor along with its secret keys.
+    ///
+    /// See [`ImportDescriptorRequest::new`] for the defaults.
+    pub fn with_secret<Ext: Extension + ParseableExt>(
+        desc: &Descriptor<DescriptorPublicKey, Ext>,
+        key_map: &KeyMap,
+    ) -> Result<Self, Error> {
+        desc.check_bitcoin_compatible()?;
+        let desc_str = desc.to_string_with_secret(key_map);
+        Ok(Self::from_desc_string(strip_elements_prefix(&desc_str)?))
+    }
+
+    fn from_desc_string(desc: String) -> Self {
+        ImportDescriptorRequest {
+            desc,
+            active: false,
+            range: None,
+            next_index: None,
+            timestamp: ImportTimestamp::Now,
+            internal: false,
+            label: None,
+        }
+    }
+}
+
+/// A single entry of the `descriptors` array returned by the
+/// `listdescriptors` RPC.
+///
+/// With the `serde` feature, this can be deserialized from the JSON object
+/// returned by Elements Core. Only public descriptors are supported.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ListedDescriptor {
+    /// The descriptor
+    pub desc: Descriptor<DescriptorPublicKey>,
+    /// Time from which the wallet scanned for transactions
+    pub timestamp: u64,
+    /// Whether the descriptor is used to generate new addresses
+    pub active: bool,
+    /// Whether the descriptor is used for change addresses, only present
+    /// for active descriptors
+    pub internal: Option<bool>,
+    /// Inclusive range of derivation indexes, only present for ranged descriptors
+    pub range: Option<(u32, u32)>,
+    /// Next derivation index used to generate addresses, only present for
+    /// ranged descriptors
+    pub next: Option<u32>,
+}
+
+/// Parse a descriptor string as output by Elements Core.
+///
+/// The checksum is verified if present.
+pub fn from_core_descriptor(s: &str) -> Result<Descriptor<DescriptorPublicKey>, Error> {
+    Descriptor::from_bitcoin_descriptor(s)
+}
+
+/// A release of Elements Core
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CoreVersion {
+    /// The major version
+    pub major: u32,
+    /// The minor version
+    pub minor: u32,
+}
+
+impl CoreVersion {
+    /// Elements Core 0.21, with descriptor wallets and the
+    /// `importdescriptors` RPC
+    pub const V0_21: CoreVersion = CoreVersion {
+        major: 0,
+        minor: 21,
+    };
+    /// Elements Core 22.0, with Taproot and the new tapscript opcodes
+    pub const V22_0: CoreVersion = CoreVersion {
+        major: 22,
+        minor: 0,
+    };
+}
+
+impl fmt::Display for CoreVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// A feature of Elements Core required by a descriptor, see
+/// [`Descriptor::core_compatibility`]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum CoreFeature {
+    /// Descriptor wallets, to watch and sign for any descriptor
+    DescriptorWallet,
+    /// Taproot outputs, i.e. `tr()` descriptors with `pk()` leaves
+    Taproot,
+    /// Miniscript other than the `pk()`, `pkh()` and `multi()` script
+    /// templates
+    Miniscript,
+    /// Keys with multiple derivation paths, e.g. `xpub/<0;1>/*`
+    MultipathKeys,
+    /// Elements-only descriptors, with no Elements Core equivalent:
+    /// covenant descriptors, extensions and witness programs
+    ElementsOnly,
+}
+
+impl CoreFeature {
+    /// The first release of Elements Core supporting the feature, `None` if
+    /// no release supports it
+    pub fn min_version(self) -> Option<CoreVersion> {
+        match self {
+            CoreFeature::DescriptorWallet => Some(CoreVersion::V0_21),
+            CoreFeature::Taproot => Some(CoreVersion::V22_0),
+            CoreFeature::Miniscript | CoreFeature::MultipathKeys | CoreFeature::ElementsOnly => {
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Display for CoreFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            CoreFeature::DescriptorWallet => "descriptor wallets",
+            CoreFeature::Taproot => "taproot",
+            CoreFeature::Miniscript => "miniscript",
+            CoreFeature::MultipathKeys => "multipath keys",
+            CoreFeature::ElementsOnly => "elements-only descriptors",
+        })
+    }
+}
+
+/// The Elements Core features required to watch and sign for a descriptor,
+/// see [`Descriptor::core_compatibility`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CoreCompatibility {
+    /// The required features, sorted and without duplicates
+    pub features: Vec<CoreFeature>,
+}
+
+impl CoreCompatibility {
+    /// The first release of Elements Core supporting the descriptor, `None`
+    /// if no release supports one of its features
+    pub fn min_version(&self) -> Option<CoreVersion> {
+        self.features
+            .iter()
+            .map(|feature| feature.min_version())
+            .try_fold(CoreVersion::V0_21, |max, version| {
+                Some(cmp::max(max, version?))
+            })
+    }
+
+    /// Whether the given release of Elements Core supports the descriptor
+    pub fn is_supported_by(&self, version: CoreVersion) -> bool {
+        self.min_version().map_or(false, |min| min <= version)
+    }
+
+    /// The features not supported by the given release of Elements Core
+    pub fn unsupported_features(&self, version: CoreVersion) -> Vec<CoreFeature> {
+        self.features
+            .iter()
+            .copied()
+            .filter(|feature| feature.min_version().map_or(true, |min| min > version))
+            .collect()
+    }
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// The Elements Core features required to watch and sign for the
+    /// descriptor, once converted with [`ImportDescriptorRequest::new`], e.g.
+    /// to check before deployment that the nodes of a pipeline support the
+    /// descriptors of its wallets.
+    ///
+    /// The miniscript support of Elements Core is not assumed, only the
+    /// script templates supported by every descriptor wallet.
+    pub fn core_compatibility(&self) -> CoreCompatibility {
+        let mut features = vec![CoreFeature::DescriptorWallet];
+        if self.check_bitcoin_compatible().is_err() {
+            features.push(CoreFeature::ElementsOnly);
+        }
+        if self.for_any_key(|pk| pk.num_der_paths() > 1) {
+            features.push(CoreFeature::MultipathKeys);
+        }
+        let templates = match *self {
+            Descriptor::Bare(ref bare) => is_core_template(bare.as_inner()),
+            Descriptor::Sh(ref sh) => match *sh.as_inner() {
+                ShInner::Wsh(ref wsh) => is_core_wsh(wsh),
+                ShInner::Ms(ref ms) => is_core_template(ms),
+                ShInner::Wpkh(..) | ShInner::SortedMulti(..) => true,
+            },
+            Descriptor::Wsh(ref wsh) => is_core_wsh(wsh),
+            Descriptor::Tr(ref tr) => {
+                features.push(CoreFeature::Taproot);
+                tr.iter_scripts().all(|(_, ms)| is_core_tap_leaf(ms))
+            }
+            Descriptor::TrExt(ref tr) => {
+                features.push(CoreFeature::Taproot);
+                tr.iter_scripts().all(|(_, ms)| is_core_tap_leaf(ms))
+            }
+            Descriptor::Pkh(..)
+            | Descriptor::Wpkh(..)
+            | Descriptor::LegacyCSFSCov(..)
+            | Descriptor::Wit(..) => true,
+        };
+        if !templates {
+            features.push(CoreFeature::Miniscript);
+        }
+        features.sort();
+        features.dedup();
+        CoreCompatibility { features }
+    }
+}
+
+fn is_core_wsh<Pk: MiniscriptKey>(wsh: &Wsh<Pk>) -> bool {
+    match *wsh.as_inner() {
+        WshInner::SortedMulti(..) => true,
+        WshInner::Ms(ref ms) => is_core_template(ms),
+    }
+}
+
+// Whether the miniscript is one of the script templates of descriptor
+// wallets: `pk()`, `pkh()` or `multi()`
+fn is_core_template<Pk: MiniscriptKey, Ctx: ScriptContext>(ms: &Miniscript<Pk, Ctx>) -> bool {
+    match ms.node {
+        Terminal::Multi(..) => true,
+        Terminal::Check(ref inner) => matches!(inner.node, Terminal::PkK(..) | Terminal::PkH(..)),
+        _ => false,
+    }
+}
+
+// Whether the tapscript is a `pk()` leaf
+fn is_core_tap_leaf<Pk: MiniscriptKey, Ext: Extension>(ms: &Miniscript<Pk, Tap, Ext>) -> bool {
+    match ms.node {
+        Terminal::Check(ref inner) => matches!(inner.node, Terminal::PkK(..)),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ImportTimestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            ImportTimestamp::Now => serializer.serialize_str("now"),
+            ImportTimestamp::Time(t) => serializer.serialize_u64(t),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ImportDescriptorRequest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use ser::SerializeStruct;
+
+        let mut st = serializer.serialize_struct("ImportDescriptorRequest", 7)?;
+        st.serialize_field("desc", &self.desc)?;
+        st.serialize_field("active", &self.active)?;
+        match self.range {
+            Some((start, end)) => st.serialize_field("range", &[start, end])?,
+            None => st.skip_field("range")?,
+        }
+        match self.next_index {
+            Some(next_index) => st.serialize_field("next_index", &next_index)?,
+            None => st.skip_field("next_index")?,
+        }
+        st.serialize_field("timestamp", &self.timestamp)?;
+        st.serialize_field("internal", &self.internal)?;
+        match self.label {
+            Some(ref label) => st.serialize_field("label", label)?,
+            None => st.skip_field("label")?,
+        }
+        st.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ListedDescriptor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ListedDescriptor;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str("a listdescriptors entry")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let mut desc = None;
+                let mut timestamp = None;
+                let mut active = None;
+                let mut internal = None;
+                let mut range = None;
+                let mut next = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "desc" => {
+                            let s: String = map.next_value()?;
+                            desc = Some(from_core_descriptor(&s).map_err(de::Error::custom)?);
+                        }
+                        "timestamp" => timestamp = Some(map.next_value()?),
+                        "active" => active = Some(map.next_value()?),
+                        "internal" => internal = Some(map.next_value()?),
+                        "range" => {
+                            let r: [u32; 2] = map.next_value()?;
+                            range = Some((r[0], r[1]));
+                        }
+                        "next" | "next_index" => next = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(ListedDescriptor {
+                    desc: desc.ok_or_else(|| de::Error::missing_field("desc"))?,
+                    timestamp: timestamp.ok_or_else(|| de::Error::missing_field("timestamp"))?,
+                    active: active.ok_or_else(|| de::Error::missing_field("active"))?,
+                    internal,
+                    range,
+                    next,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::descriptor::checksum::desc_checksum;
+
+    const XPUB: &str = "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL";
+    const XPUB2: &str = "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y";
+
+    #[test]
+    fn core_descriptor_roundtrip() {
+        let desc =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elwpkh({}/0/*)", XPUB)).unwrap();
+        let req = ImportDescriptorRequest::new(&desc).unwrap();
+        let core_str = format!("wpkh({}/0/*)", XPUB);
+        assert_eq!(
+            req.desc,
+            format!("{}#{}", core_str, desc_checksum(&core_str).unwrap())
+        );
+        assert_eq!(from_core_descriptor(&req.desc).unwrap(), desc);
+
+        // Covenants are not supported by Elements Core
+        let cov = Descriptor::<DescriptorPublicKey>::from_str(&format!(
+            "elcovwsh({},pk({}))",
+            XPUB, XPUB2
+        ))
+        .unwrap();
+        assert!(ImportDescriptorRequest::new(&cov).is_err());
+    }
+
+    #[test]
+    fn core_compatibility() {
+        let desc =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elwpkh({}/0/*)", XPUB)).unwrap();
+        let compat = desc.core_compatibility();
+        assert_eq!(compat.features, vec![CoreFeature::DescriptorWallet]);
+        assert_eq!(compat.min_version(), Some(CoreVersion::V0_21));
+
+        let desc =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("eltr({},pk({}))", XPUB, XPUB2))
+                .unwrap();
+        let compat = desc.core_compatibility();
+        assert_eq!(compat.min_version(), Some(CoreVersion::V22_0));
+        assert!(!compat.is_supported_by(CoreVersion::V0_21));
+        assert_eq!(
+            compat.unsupported_features(CoreVersion::V0_21),
+            vec![CoreFeature::Taproot]
+        );
+
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(&format!(
+            "elwsh(and_v(v:pk({}/<0;1>/*),older(144)))",
+            XPUB
+        ))
+        .unwrap();
+        let compat = desc.core_compatibility();
+        assert_eq!(compat.min_version(), None);
+        assert_eq!(
+            compat.unsupported_features(CoreVersion::V22_0),
+            vec![CoreFeature::Miniscript, CoreFeature::MultipathKeys]
+        );
+
+        let cov = Descriptor::<DescriptorPublicKey>::from_str(&format!(
+            "elcovwsh({},pk({}))",
+            XPUB, XPUB2
+        ))
+        .unwrap();
+        assert!(cov
+            .core_compatibility()
+            .features
+            .contains(&CoreFeature::ElementsOnly));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn rpc_json() {
+        let desc =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elwpkh({}/0/*)", XPUB)).unwrap();
+        let mut req = ImportDescriptorRequest::new(&desc).unwrap();
+        req.range = Some((0, 100));
+        req.timestamp = ImportTimestamp::Time(1_600_000_000);
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "desc": req.desc,
+                "active": false,
+                "range": [0, 100],
+                "timestamp": 1_600_000_000u64,
+                "internal": false,
+            })
+        );
+
+        let listed: ListedDescriptor = serde_json::from_value(serde_json::json!({
+            "desc": req.desc,
+            "timestamp": 1_600_000_000u64,
+            "active": true,
+            "internal": false,
+            "range": [0, 999],
+            "next": 10,
+        }))
+        .unwrap();
+        assert_eq!(listed.desc, desc);
+        assert_eq!(listed.range, Some((0, 999)));
+        assert_eq!(listed.next, Some(10));
+    }
+}
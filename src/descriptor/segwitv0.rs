@@ -13,10 +13,11 @@ use std::str::FromStr;
 use elements::{self, secp256k1_zkp, Address, Script};
 
 use super::checksum::verify_checksum;
-use super::{SortedMultiVec, ELMTS_STR};
+use super::{CustomAddressParams, SortedMultiVec, WitnessBuilder, ELMTS_STR};
 use crate::descriptor::checksum;
 use crate::expression::{self, FromTree};
 use crate::miniscript::context::{ScriptContext, ScriptContextError};
+use crate::miniscript::limits::MAX_OPS_PER_SCRIPT;
 use crate::policy::{semantic, Liftable};
 use crate::util::varint_len;
 use crate::{
@@ -42,9 +43,18 @@ impl<Pk: MiniscriptKey> Wsh<Pk> {
     }
 
     /// Create a new wsh descriptor
+    ///
+    /// The witness script is checked against the limits which do not depend
+    /// on its satisfaction, as when parsing a descriptor: its size, at most
+    /// 3600 bytes to be standard, and its number of non-push opcodes, at
+    /// most 201, which are counted even in branches which are not executed.
     pub fn new(ms: Miniscript<Pk, Segwitv0>) -> Result<Self, Error> {
         // do the top-level checks
         Segwitv0::top_level_checks(&ms)?;
+        Segwitv0::check_global_validity(&ms)?;
+        if ms.ext.ops.count > MAX_OPS_PER_SCRIPT {
+            return Err(ScriptContextError::MaxOpCountExceeded.into());
+        }
         Ok(Self {
             inner: WshInner::Ms(ms),
         })
@@ -183,6 +193,25 @@ impl<Pk: MiniscriptKey + ToPublicKey> Wsh<Pk> {
         }
     }
 
+    /// Obtains the unconfidential address for this descriptor, see
+    /// [`Descriptor::unconfidential_address`](crate::Descriptor::unconfidential_address)
+    pub fn unconfidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        params: P,
+    ) -> elements::Address {
+        self.address(None, params.into().to_static())
+    }
+
+    /// Obtains the confidential address for this descriptor, blinded with
+    /// the `blinder` public key
+    pub fn confidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        blinder: secp256k1_zkp::PublicKey,
+        params: P,
+    ) -> elements::Address {
+        self.address(Some(blinder), params.into().to_static())
+    }
+
     /// Obtains the underlying miniscript for this descriptor.
     pub fn inner_script(&self) -> Script {
         match self.inner {
@@ -203,14 +232,14 @@ impl<Pk: MiniscriptKey + ToPublicKey> Wsh<Pk> {
     where
         S: Satisfier<Pk>,
     {
-        let mut witness = match self.inner {
+        let satisfaction = match self.inner {
             WshInner::SortedMulti(ref smv) => smv.satisfy(satisfier)?,
             WshInner::Ms(ref ms) => ms.satisfy(satisfier)?,
         };
-        let witness_script = self.inner_script();
-        witness.push(witness_script.into_bytes());
+        let mut witness = WitnessBuilder::<Segwitv0>::new(satisfaction);
+        witness.script(&self.inner_script());
         let script_sig = Script::new();
-        Ok((witness, script_sig))
+        Ok((witness.into_witness()?, script_sig))
     }
 
     /// Returns satisfying, possibly malleable, witness and scriptSig with
@@ -220,13 +249,14 @@ impl<Pk: MiniscriptKey + ToPublicKey> Wsh<Pk> {
     where
         S: Satisfier<Pk>,
     {
-        let mut witness = match self.inner {
+        let satisfaction = match self.inner {
             WshInner::SortedMulti(ref smv) => smv.satisfy(satisfier)?,
             WshInner::Ms(ref ms) => ms.satisfy_malleable(satisfier)?,
         };
-        witness.push(self.inner_script().into_bytes());
+        let mut witness = WitnessBuilder::<Segwitv0>::new(satisfaction);
+        witness.script(&self.inner_script());
         let script_sig = Script::new();
-        Ok((witness, script_sig))
+        Ok((witness.into_witness()?, script_sig))
     }
 }
 
@@ -437,6 +467,25 @@ impl<Pk: MiniscriptKey + ToPublicKey> Wpkh<Pk> {
         Address::p2wpkh(&self.pk.to_public_key(), blinder, params)
     }
 
+    /// Obtains the unconfidential address for this descriptor, see
+    /// [`Descriptor::unconfidential_address`](crate::Descriptor::unconfidential_address)
+    pub fn unconfidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        params: P,
+    ) -> elements::Address {
+        self.address(None, params.into().to_static())
+    }
+
+    /// Obtains the confidential address for this descriptor, blinded with
+    /// the `blinder` public key
+    pub fn confidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        blinder: secp256k1_zkp::PublicKey,
+        params: P,
+    ) -> elements::Address {
+        self.address(Some(blinder), params.into().to_static())
+    }
+
     /// Obtains the underlying miniscript for this descriptor.
     pub fn inner_script(&self) -> Script {
         self.script_pubkey()
@@ -468,8 +517,9 @@ impl<Pk: MiniscriptKey + ToPublicKey> Wpkh<Pk> {
         if let Some(sig) = satisfier.lookup_ecdsa_sig(&self.pk) {
             let sig_vec = elementssig_to_rawsig(&sig);
             let script_sig = Script::new();
-            let witness = vec![sig_vec, self.pk.to_public_key().to_bytes()];
-            Ok((witness, script_sig))
+            let witness =
+                WitnessBuilder::<Segwitv0>::new(vec![sig_vec, self.pk.to_public_key().to_bytes()]);
+            Ok((witness.into_witness()?, script_sig))
         } else {
             Err(Error::MissingSig(self.pk.to_public_key()))
         }
@@ -13,12 +13,13 @@ use core::fmt;
 use elements::{self, script, secp256k1_zkp, Script};
 
 use super::checksum::verify_checksum;
-use super::{SortedMultiVec, Wpkh, Wsh, ELMTS_STR};
+use super::{CustomAddressParams, SortedMultiVec, WitnessBuilder, Wpkh, Wsh, ELMTS_STR};
 use crate::descriptor::checksum;
 use crate::expression::{self, FromTree};
-use crate::miniscript::context::ScriptContext;
+use crate::miniscript::context::{ScriptContext, ScriptContextError};
+use crate::miniscript::limits::MAX_OPS_PER_SCRIPT;
 use crate::policy::{semantic, Liftable};
-use crate::util::{varint_len, witness_to_scriptsig};
+use crate::util::varint_len;
 use crate::{
     push_opcode_size, Error, ForEachKey, Legacy, Miniscript, MiniscriptKey, Satisfier, Segwitv0,
     ToPublicKey, TranslatePk, Translator,
@@ -137,9 +138,16 @@ impl<Pk: MiniscriptKey> Sh<Pk> {
     }
 
     /// Create a new p2sh descriptor with the raw miniscript
+    ///
+    /// As for [`Wsh::new`], the redeemScript is checked against its size
+    /// limit, 520 bytes, and the limit of 201 non-push opcodes.
     pub fn new(ms: Miniscript<Pk, Legacy>) -> Result<Self, Error> {
         // do the top-level checks
         Legacy::top_level_checks(&ms)?;
+        Legacy::check_global_validity(&ms)?;
+        if ms.ext.ops.count > MAX_OPS_PER_SCRIPT {
+            return Err(ScriptContextError::MaxOpCountExceeded.into());
+        }
         Ok(Self {
             inner: ShInner::Ms(ms),
         })
@@ -315,6 +323,25 @@ impl<Pk: MiniscriptKey + ToPublicKey> Sh<Pk> {
         }
     }
 
+    /// Obtains the unconfidential address for this descriptor, see
+    /// [`Descriptor::unconfidential_address`](crate::Descriptor::unconfidential_address)
+    pub fn unconfidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        params: P,
+    ) -> elements::Address {
+        self.address(None, params.into().to_static())
+    }
+
+    /// Obtains the confidential address for this descriptor, blinded with
+    /// the `blinder` public key
+    pub fn confidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        blinder: secp256k1_zkp::PublicKey,
+        params: P,
+    ) -> elements::Address {
+        self.address(Some(blinder), params.into().to_static())
+    }
+
     /// Obtain the underlying miniscript for this descriptor
     pub fn inner_script(&self) -> Script {
         match self.inner {
@@ -382,18 +409,16 @@ impl<Pk: MiniscriptKey + ToPublicKey> Sh<Pk> {
                 Ok((witness, script_sig))
             }
             ShInner::SortedMulti(ref smv) => {
-                let mut script_witness = smv.satisfy(satisfier)?;
-                script_witness.push(smv.encode().into_bytes());
-                let script_sig = witness_to_scriptsig(&script_witness);
+                let mut script_witness = WitnessBuilder::<Legacy>::new(smv.satisfy(satisfier)?);
+                script_witness.script(&smv.encode());
                 let witness = vec![];
-                Ok((witness, script_sig))
+                Ok((witness, script_witness.into_script_sig()?))
             }
             ShInner::Ms(ref ms) => {
-                let mut script_witness = ms.satisfy(satisfier)?;
-                script_witness.push(ms.encode().into_bytes());
-                let script_sig = witness_to_scriptsig(&script_witness);
+                let mut script_witness = WitnessBuilder::<Legacy>::new(ms.satisfy(satisfier)?);
+                script_witness.script(&ms.encode());
                 let witness = vec![];
-                Ok((witness, script_sig))
+                Ok((witness, script_witness.into_script_sig()?))
             }
         }
     }
@@ -412,11 +437,11 @@ impl<Pk: MiniscriptKey + ToPublicKey> Sh<Pk> {
                 Ok((witness, script_sig))
             }
             ShInner::Ms(ref ms) => {
-                let mut script_witness = ms.satisfy_malleable(satisfier)?;
-                script_witness.push(ms.encode().into_bytes());
-                let script_sig = witness_to_scriptsig(&script_witness);
+                let mut script_witness =
+                    WitnessBuilder::<Legacy>::new(ms.satisfy_malleable(satisfier)?);
+                script_witness.script(&ms.encode());
                 let witness = vec![];
-                Ok((witness, script_sig))
+                Ok((witness, script_witness.into_script_sig()?))
             }
             _ => self.get_satisfaction(satisfier),
         }
@@ -6,33 +6,184 @@
 //! Implementation of sorted multi primitive for descriptors
 //!
 
-use std::fmt;
+use std::collections::BTreeSet;
 use std::marker::PhantomData;
 use std::str::FromStr;
+use std::{error, fmt};
 
 use elements::script;
 
-use crate::miniscript::context::ScriptContext;
+use crate::miniscript::context::{Context, ScriptContext};
 use crate::miniscript::decode::Terminal;
-use crate::miniscript::limits::MAX_PUBKEYS_PER_MULTISIG;
+use crate::miniscript::limits::{MAX_PUBKEYS_PER_MULTISIG, MAX_SCRIPT_ELEMENT_SIZE};
 use crate::miniscript::{self};
 use crate::{
     errstr, expression, policy, script_num_size, Error, ForEachKey, Miniscript, MiniscriptKey,
-    Satisfier, ToPublicKey, Translator,
+    Satisfier, Threshold, ToPublicKey, Translator,
 };
 
 /// Contents of a "sortedmulti" descriptor
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SortedMultiVec<Pk: MiniscriptKey, Ctx: ScriptContext> {
-    /// signatures required
-    pub k: usize,
-    /// public keys inside sorted Multi
-    pub pks: Vec<Pk>,
+    /// The threshold of signatures required over the public keys
+    inner: Threshold<Pk>,
     /// The current ScriptContext for sortedmulti
     pub(crate) phantom: PhantomData<Ctx>,
 }
 
+/// Errors detected by [`SortedMultiBuilder`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SortedMultiError {
+    /// The threshold is zero
+    ZeroThreshold,
+    /// The threshold is higher than the number of keys
+    ThresholdTooHigh {
+        /// The threshold
+        k: usize,
+        /// The number of keys
+        n: usize,
+    },
+    /// More keys than allowed under the script context
+    TooManyKeys {
+        /// The number of keys
+        n: usize,
+        /// The maximum number of keys under the script context
+        max: usize,
+    },
+    /// The same key appears more than once
+    DuplicateKey(String),
+    /// The p2sh redeemScript is larger than the 520 bytes which can be
+    /// pushed in a scriptSig, e.g. with more than 15 compressed keys
+    RedeemScriptTooLarge {
+        /// The size of the redeemScript
+        size: usize,
+        /// The maximum size of a redeemScript
+        max: usize,
+    },
+}
+
+impl fmt::Display for SortedMultiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SortedMultiError::ZeroThreshold => f.write_str("sortedmulti threshold is zero"),
+            SortedMultiError::ThresholdTooHigh { k, n } => write!(
+                f,
+                "sortedmulti threshold {} is higher than the number of keys {}",
+                k, n
+            ),
+            SortedMultiError::TooManyKeys { n, max } => {
+                write!(f, "sortedmulti has {} keys, at most {} are allowed", n, max)
+            }
+            SortedMultiError::DuplicateKey(ref pk) => {
+                write!(f, "sortedmulti key {} is repeated", pk)
+            }
+            SortedMultiError::RedeemScriptTooLarge { size, max } => write!(
+                f,
+                "sortedmulti redeemScript has {} bytes, at most {} are allowed",
+                size, max
+            ),
+        }
+    }
+}
+
+impl error::Error for SortedMultiError {}
+
+#[doc(hidden)]
+impl From<SortedMultiError> for Error {
+    fn from(e: SortedMultiError) -> Error {
+        Error::SortedMultiError(e)
+    }
+}
+
+/// Builder of a [`SortedMultiVec`] which validates the threshold and keys
+///
+/// Unlike [`SortedMultiVec::new`], the threshold and the number of keys are
+/// checked against the limit of CHECKMULTISIG (20 keys, as sortedmulti is
+/// not available in tapscript) and repeated keys are rejected, with a
+/// [`SortedMultiError`].
+/// Under p2sh, the redeemScript must also fit in a single push, which limits
+/// the number of keys to 15 compressed keys, or 7 uncompressed keys.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SortedMultiBuilder<Pk: MiniscriptKey, Ctx: ScriptContext> {
+    k: usize,
+    pks: Vec<Pk>,
+    phantom: PhantomData<Ctx>,
+}
+
+impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiBuilder<Pk, Ctx> {
+    /// Create a builder for a sortedmulti with threshold `k` and no keys
+    pub fn new(k: usize) -> Self {
+        SortedMultiBuilder {
+            k,
+            pks: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Add a key
+    pub fn key(mut self, pk: Pk) -> Self {
+        self.pks.push(pk);
+        self
+    }
+
+    /// Add several keys
+    pub fn keys<I: IntoIterator<Item = Pk>>(mut self, pks: I) -> Self {
+        self.pks.extend(pks);
+        self
+    }
+
+    /// Validate the threshold and the keys and build the [`SortedMultiVec`]
+    ///
+    /// Errors of the script context other than the ones of
+    /// [`SortedMultiError`] are returned as by [`SortedMultiVec::new`].
+    pub fn build(self) -> Result<SortedMultiVec<Pk, Ctx>, Error> {
+        let n = self.pks.len();
+        if self.k == 0 {
+            return Err(SortedMultiError::ZeroThreshold.into());
+        }
+        if self.k > n {
+            return Err(SortedMultiError::ThresholdTooHigh { k: self.k, n }.into());
+        }
+        let max = MAX_PUBKEYS_PER_MULTISIG;
+        if n > max {
+            return Err(SortedMultiError::TooManyKeys { n, max }.into());
+        }
+        if Ctx::context() == Context::Legacy {
+            let size = script_num_size(self.k)
+                + 1
+                + script_num_size(n)
+                + self.pks.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>();
+            if size > MAX_SCRIPT_ELEMENT_SIZE {
+                let max = MAX_SCRIPT_ELEMENT_SIZE;
+                return Err(SortedMultiError::RedeemScriptTooLarge { size, max }.into());
+            }
+        }
+        let mut seen = BTreeSet::new();
+        for pk in &self.pks {
+            if !seen.insert(pk) {
+                return Err(SortedMultiError::DuplicateKey(pk.to_string()).into());
+            }
+        }
+        SortedMultiVec::new(self.k, self.pks)
+    }
+}
+
 impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
+    /// Create a [`SortedMultiBuilder`] with threshold `k`
+    pub fn builder(k: usize) -> SortedMultiBuilder<Pk, Ctx> {
+        SortedMultiBuilder::new(k)
+    }
+
+    /// The keys of the participants, in the order they appear in the descriptor
+    pub fn participants(&self) -> &[Pk] {
+        self.inner.data()
+    }
+
+    /// The number of signatures required
+    pub fn threshold(&self) -> usize {
+        self.inner.k()
+    }
+
     /// Create a new instance of `SortedMultiVec` given a list of keys and the threshold
     ///
     /// Internally checks all the applicable size limits and pubkey types limitations according to the current `Ctx`.
@@ -46,7 +197,8 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
         // For example, under p2sh context the scriptlen can only be
         // upto 520 bytes.
         // sorted_multi has no extensions enabled
-        let term: miniscript::decode::Terminal<Pk, Ctx> = Terminal::Multi(k, pks.clone());
+        let inner = Threshold::new(k, pks)?;
+        let term: miniscript::decode::Terminal<Pk, Ctx> = Terminal::Multi(inner.clone());
         let ms = Miniscript::from_ast(term)?;
 
         // This would check all the consensus rules for p2sh/p2wsh and
@@ -54,8 +206,7 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
         Ctx::check_local_validity(&ms)?;
 
         Ok(Self {
-            k,
-            pks,
+            inner,
             phantom: PhantomData,
         })
     }
@@ -93,10 +244,8 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
         T: Translator<Pk, Q, FuncError>,
         Q: MiniscriptKey,
     {
-        let pks: Result<Vec<Q>, _> = self.pks.iter().map(|pk| t.pk(pk)).collect();
         Ok(SortedMultiVec {
-            k: self.k,
-            pks: pks?,
+            inner: self.inner.translate_ref(|pk| t.pk(pk))?,
             phantom: PhantomData,
         })
     }
@@ -107,7 +256,7 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> ForEachKey<Pk> for SortedMultiVec<Pk
     where
         Pk: 'a,
     {
-        self.pks.iter().all(pred)
+        self.inner.iter().all(pred)
     }
 }
 
@@ -115,8 +264,7 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
     /// utility function to sanity a sorted multi vec
     pub fn sanity_check(&self) -> Result<(), Error> {
         let ms: Miniscript<Pk, Ctx> =
-            Miniscript::from_ast(Terminal::Multi(self.k, self.pks.clone()))
-                .expect("Must typecheck");
+            Miniscript::from_ast(Terminal::Multi(self.inner.clone())).expect("Must typecheck");
         // '?' for doing From conversion
         ms.sanity_check()?;
         Ok(())
@@ -129,7 +277,7 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
     where
         Pk: ToPublicKey,
     {
-        let mut pks = self.pks.clone();
+        let mut pks = self.inner.data().to_vec();
         // Sort pubkeys lexicographically according to BIP 67
         pks.sort_by(|a, b| {
             a.to_public_key()
@@ -138,7 +286,8 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
                 .partial_cmp(&b.to_public_key().inner.serialize())
                 .unwrap()
         });
-        Terminal::Multi(self.k, pks)
+        let thresh = Threshold::new(self.inner.k(), pks).expect("same k and n as the keys");
+        Terminal::Multi(thresh)
     }
 
     /// Encode as a Bitcoin script
@@ -170,10 +319,10 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
     /// to instead call the corresponding function on a `Descriptor`, which
     /// will handle the segwit/non-segwit technicalities for you.
     pub fn script_size(&self) -> usize {
-        script_num_size(self.k)
+        script_num_size(self.inner.k())
             + 1
-            + script_num_size(self.pks.len())
-            + self.pks.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>()
+            + script_num_size(self.inner.n())
+            + self.inner.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>()
     }
 
     /// Maximum number of witness elements used to satisfy the Miniscript
@@ -185,7 +334,7 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
     /// not correspond to semantically sane Scripts. (Such scripts should be
     /// rejected at parse time. Any exceptions are bugs.)
     pub fn max_satisfaction_witness_elements(&self) -> usize {
-        2 + self.k
+        2 + self.inner.k()
     }
 
     /// Maximum size, in bytes, of a satisfying witness.
@@ -197,18 +346,15 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> SortedMultiVec<Pk, Ctx> {
     /// length prefix (segwit) or push opcode (pre-segwit) and sighash
     /// postfix.
     pub fn max_satisfaction_size(&self) -> usize {
-        1 + 73 * self.k
+        1 + 73 * self.inner.k()
     }
 }
 
 impl<Pk: MiniscriptKey, Ctx: ScriptContext> policy::Liftable<Pk> for SortedMultiVec<Pk, Ctx> {
     fn lift(&self) -> Result<policy::semantic::Policy<Pk>, Error> {
         let ret = policy::semantic::Policy::Threshold(
-            self.k,
-            self.pks
-                .iter()
-                .map(|k| policy::semantic::Policy::Key(k.clone()))
-                .collect(),
+            self.inner
+                .map_ref(|k| policy::semantic::Policy::Key(k.clone())),
         );
         Ok(ret)
     }
@@ -222,8 +368,8 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext> fmt::Debug for SortedMultiVec<Pk, Ct
 
 impl<Pk: MiniscriptKey, Ctx: ScriptContext> fmt::Display for SortedMultiVec<Pk, Ctx> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "sortedmulti({}", self.k)?;
-        for k in &self.pks {
+        write!(f, "sortedmulti({}", self.inner.k())?;
+        for k in &self.inner {
             write!(f, ",{}", k)?;
         }
         f.write_str(")")
@@ -235,7 +381,7 @@ mod tests {
     use bitcoin::secp256k1::PublicKey;
 
     use super::*;
-    use crate::miniscript::context::Legacy;
+    use crate::miniscript::context::{Legacy, Segwitv0};
 
     #[test]
     fn too_many_pubkeys() {
@@ -260,4 +406,118 @@ mod tests {
             other => panic!("unexpected error: {:?}", other),
         }
     }
+
+    #[test]
+    fn builder() {
+        let pks = [
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443",
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+        ]
+        .iter()
+        .map(|s| PublicKey::from_str(s).unwrap())
+        .collect::<Vec<_>>();
+        type Builder = SortedMultiBuilder<PublicKey, Segwitv0>;
+
+        let smv = Builder::new(2).keys(pks.clone()).build().unwrap();
+        assert_eq!(smv.threshold(), 2);
+        assert_eq!(smv.participants(), &pks[..]);
+
+        let err = |b: Builder| match b.build() {
+            Err(Error::SortedMultiError(e)) => e,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(
+            err(Builder::new(0).keys(pks.clone())),
+            SortedMultiError::ZeroThreshold
+        );
+        assert_eq!(
+            err(Builder::new(4).keys(pks.clone())),
+            SortedMultiError::ThresholdTooHigh { k: 4, n: 3 }
+        );
+        assert_eq!(
+            err(Builder::new(1).keys(vec![pks[0]; 21])),
+            SortedMultiError::TooManyKeys { n: 21, max: 20 }
+        );
+        assert_eq!(
+            err(Builder::new(1).keys(pks.clone()).key(pks[1])),
+            SortedMultiError::DuplicateKey(pks[1].to_string())
+        );
+    }
+
+    #[test]
+    fn p2sh_federation() {
+        use std::collections::HashMap;
+
+        use elements::secp256k1_zkp;
+
+        let secp = secp256k1_zkp::Secp256k1::signing_only();
+        let msg = secp256k1_zkp::Message::from_slice(&[1; 32]).unwrap();
+        let mut pks = vec![];
+        let mut uncompressed = vec![];
+        let mut sigs = HashMap::new();
+        for i in 1..17 {
+            let sk = secp256k1_zkp::SecretKey::from_slice(&[i; 32]).unwrap();
+            let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+            let sig = (secp.sign_ecdsa(&msg, &sk), elements::EcdsaSigHashType::All);
+            sigs.insert(pk, sig);
+            pks.push(pk);
+            uncompressed.push(bitcoin::PublicKey::new_uncompressed(pk.inner));
+        }
+        type Builder = SortedMultiBuilder<bitcoin::PublicKey, Legacy>;
+        let err = |b: Builder| match b.build() {
+            Err(Error::SortedMultiError(e)) => e,
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        // A 15-of-15 redeemScript is the largest fitting in a push
+        let smv = Builder::new(15).keys(pks[..15].to_vec()).build().unwrap();
+        assert_eq!(smv.script_size(), 513);
+        assert_eq!(
+            err(Builder::new(16).keys(pks.clone())),
+            SortedMultiError::RedeemScriptTooLarge {
+                size: 547,
+                max: 520
+            }
+        );
+        assert!(Builder::new(7)
+            .keys(uncompressed[..7].to_vec())
+            .build()
+            .is_ok());
+        assert_eq!(
+            err(Builder::new(8).keys(uncompressed[..8].to_vec())),
+            SortedMultiError::RedeemScriptTooLarge {
+                size: 531,
+                max: 520
+            }
+        );
+        // Segwit scripts have no such limit
+        assert!(SortedMultiBuilder::<_, Segwitv0>::new(16)
+            .keys(pks.clone())
+            .build()
+            .is_ok());
+        assert!(
+            crate::Descriptor::<bitcoin::PublicKey>::new_sh_sortedmulti(16, pks.clone()).is_err()
+        );
+
+        // The satisfaction of a p2sh descriptor is in its scriptSig
+        let desc =
+            crate::Descriptor::<bitcoin::PublicKey>::new_sh_sortedmulti(15, pks[..15].to_vec())
+                .unwrap();
+        let (witness, script_sig) = desc.get_satisfaction(&sigs).unwrap();
+        assert!(witness.is_empty());
+        assert!(script_sig.len() <= crate::miniscript::limits::MAX_SCRIPTSIG_SIZE);
+        assert!(4 * script_sig.len() < desc.max_weight_to_satisfy().unwrap());
+        let pushes = script_sig
+            .instructions()
+            .map(|ins| match ins.unwrap() {
+                script::Instruction::PushBytes(push) => push.to_vec(),
+                script::Instruction::Op(op) => panic!("unexpected {:?}", op),
+            })
+            .collect::<Vec<_>>();
+        // The dummy element of CHECKMULTISIG, the signatures and the
+        // redeemScript
+        assert_eq!(pushes.len(), 17);
+        assert_eq!(pushes[16], smv.encode().into_bytes());
+    }
 }
@@ -0,0 +1,483 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Standardness checks
+//!
+//! Checks of descriptors against the relay policy of Elements Core. These
+//! rules are stricter than consensus: a transaction spending a non-standard
+//! output is valid, but it will not be relayed by default nodes.
+//!
+
+use std::fmt;
+
+use elements::script::Instruction;
+use elements::{opcodes, Script, TxOut};
+
+use super::{Descriptor, ShInner, WshInner};
+use crate::extensions::ParseableExt;
+use crate::miniscript::context::ScriptContext;
+use crate::miniscript::limits::{
+    MAX_SCRIPTSIG_SIZE, MAX_SCRIPT_ELEMENT_SIZE, MAX_STANDARD_P2WSH_SCRIPT_SIZE,
+    MAX_STANDARD_P2WSH_STACK_ITEMS,
+};
+use crate::util::varint_len;
+use crate::{push_opcode_size, BareCtx, Extension, MiniscriptKey, Terminal, ToPublicKey};
+
+/// Default dust relay fee of Elements Core, in satoshis per kvB
+pub const DUST_RELAY_TX_FEE: u64 = 3000;
+
+/// Default maximum size of the script pubkey of an OP_RETURN output
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+
+/// Size of an output with an explicit asset, an explicit value and no nonce,
+/// excluding its script pubkey
+const EXPLICIT_TXOUT_SIZE: usize = 33 + 9 + 1;
+
+/// Relay policy limits checked by [`Descriptor::standardness_check`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RelayPolicy {
+    /// Maximum size of a P2WSH witness script
+    pub max_witness_script_size: usize,
+    /// Maximum number of P2WSH witness stack elements, excluding the witness script
+    pub max_witness_stack_items: usize,
+    /// Maximum size of a P2SH redeem script
+    pub max_redeem_script_size: usize,
+    /// Maximum size of a scriptSig
+    pub max_script_sig_size: usize,
+    /// Whether bare multisig outputs are relayed (`-permitbaremultisig`)
+    pub permit_bare_multisig: bool,
+    /// Fee rate defining dust outputs, in satoshis per kvB (`-dustrelayfee`)
+    pub dust_relay_fee: u64,
+    /// Maximum size of the script pubkey of an OP_RETURN output, `None` if
+    /// OP_RETURN outputs are not relayed (`-datacarriersize`)
+    pub max_data_carrier_size: Option<usize>,
+}
+
+impl Default for RelayPolicy {
+    /// The default relay policy of Elements Core
+    fn default() -> Self {
+        RelayPolicy {
+            max_witness_script_size: MAX_STANDARD_P2WSH_SCRIPT_SIZE,
+            max_witness_stack_items: MAX_STANDARD_P2WSH_STACK_ITEMS,
+            max_redeem_script_size: MAX_SCRIPT_ELEMENT_SIZE,
+            max_script_sig_size: MAX_SCRIPTSIG_SIZE,
+            permit_bare_multisig: true,
+            dust_relay_fee: DUST_RELAY_TX_FEE,
+            max_data_carrier_size: Some(MAX_OP_RETURN_RELAY),
+        }
+    }
+}
+
+impl RelayPolicy {
+    /// The minimum explicit value of an output with the script pubkey
+    /// `script_pubkey` not to be dust, i.e. the fee to spend it at
+    /// `dust_relay_fee`
+    ///
+    /// Unspendable outputs, including OP_RETURN and fee outputs, are never
+    /// dust.
+    pub fn dust_threshold(&self, script_pubkey: &Script) -> u64 {
+        if script_pubkey.is_provably_unspendable() {
+            return 0;
+        }
+        // The size of the output and of an input spending it
+        let spend_size = if script_pubkey.is_witness_program() {
+            32 + 4 + 1 + 107 / 4 + 4
+        } else {
+            32 + 4 + 1 + 107 + 4
+        };
+        let size = EXPLICIT_TXOUT_SIZE
+            + varint_len(script_pubkey.len())
+            + script_pubkey.len()
+            + spend_size;
+        size as u64 * self.dust_relay_fee / 1000
+    }
+
+    /// Checks an output against the dust and OP_RETURN rules of the policy
+    ///
+    /// Only explicit values can be checked for dust, blinded values are
+    /// always accepted.
+    pub fn check_output(&self, txout: &TxOut) -> Result<(), StandardnessViolation> {
+        if txout.script_pubkey.is_op_return() {
+            let size = txout.script_pubkey.len();
+            return match self.max_data_carrier_size {
+                Some(limit) if size > limit => Err(StandardnessViolation::DataCarrierSize {
+                    actual: size,
+                    limit,
+                }),
+                Some(_) if is_push_only(&txout.script_pubkey) => Ok(()),
+                _ => Err(StandardnessViolation::NonStandardOpReturn),
+            };
+        }
+        let threshold = self.dust_threshold(&txout.script_pubkey);
+        match txout.value.explicit() {
+            Some(value) if value < threshold && !txout.is_fee() => {
+                Err(StandardnessViolation::Dust { value, threshold })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Whether the script after the OP_RETURN only pushes data
+fn is_push_only(script: &Script) -> bool {
+    script.instructions().skip(1).all(|ins| match ins {
+        Ok(Instruction::PushBytes(_)) => true,
+        Ok(Instruction::Op(op)) => op.into_u8() <= opcodes::all::OP_PUSHNUM_16.into_u8(),
+        Err(_) => false,
+    })
+}
+
+/// A violation of the relay policy by a descriptor
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StandardnessViolation {
+    /// The witness script is larger than the policy limit
+    WitnessScriptSize {
+        /// Size of the witness script
+        actual: usize,
+        /// Policy limit
+        limit: usize,
+    },
+    /// A satisfaction may need more witness elements than the policy limit
+    WitnessStackItems {
+        /// Maximum number of elements of a satisfaction
+        actual: usize,
+        /// Policy limit
+        limit: usize,
+    },
+    /// The P2SH redeem script is larger than the policy limit
+    RedeemScriptSize {
+        /// Size of the redeem script
+        actual: usize,
+        /// Policy limit
+        limit: usize,
+    },
+    /// A satisfying scriptSig may be larger than the policy limit
+    ScriptSigSize {
+        /// Maximum size of a satisfying scriptSig
+        actual: usize,
+        /// Policy limit
+        limit: usize,
+    },
+    /// Bare multisig outputs are not relayed by the policy
+    BareMultisig,
+    /// The bare script is not one of the standard output types
+    NonStandardBareScript,
+    /// The satisfaction size could not be computed
+    Unsatisfiable(String),
+    /// The explicit value of the output is below the dust threshold
+    Dust {
+        /// Value of the output
+        value: u64,
+        /// Dust threshold of the output
+        threshold: u64,
+    },
+    /// The OP_RETURN script pubkey is larger than the policy limit
+    DataCarrierSize {
+        /// Size of the script pubkey
+        actual: usize,
+        /// Policy limit
+        limit: usize,
+    },
+    /// The OP_RETURN output pushes more than data, or OP_RETURN outputs are
+    /// not relayed by the policy
+    NonStandardOpReturn,
+}
+
+impl fmt::Display for StandardnessViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            StandardnessViolation::WitnessScriptSize { actual, limit } => write!(
+                f,
+                "witness script size {} exceeds standard limit {}",
+                actual, limit
+            ),
+            StandardnessViolation::WitnessStackItems { actual, limit } => write!(
+                f,
+                "witness stack elements {} exceed standard limit {}",
+                actual, limit
+            ),
+            StandardnessViolation::RedeemScriptSize { actual, limit } => write!(
+                f,
+                "redeem script size {} exceeds standard limit {}",
+                actual, limit
+            ),
+            StandardnessViolation::ScriptSigSize { actual, limit } => write!(
+                f,
+                "scriptSig size {} exceeds standard limit {}",
+                actual, limit
+            ),
+            StandardnessViolation::BareMultisig => f.write_str("bare multisig is not standard"),
+            StandardnessViolation::NonStandardBareScript => {
+                f.write_str("bare script is not a standard output type")
+            }
+            StandardnessViolation::Unsatisfiable(ref e) => write!(f, "unsatisfiable: {}", e),
+            StandardnessViolation::Dust { value, threshold } => {
+                write!(
+                    f,
+                    "value {} is below the dust threshold {}",
+                    value, threshold
+                )
+            }
+            StandardnessViolation::DataCarrierSize { actual, limit } => write!(
+                f,
+                "OP_RETURN script size {} exceeds standard limit {}",
+                actual, limit
+            ),
+            StandardnessViolation::NonStandardOpReturn => {
+                f.write_str("OP_RETURN output is not standard")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StandardnessViolation {}
+
+impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// Checks the descriptor against the standardness rules of `policy`,
+    /// returning all the violations found.
+    ///
+    /// These are distinct from the consensus rules checked by
+    /// [`Descriptor::sanity_check`]. Taproot descriptors have no additional
+    /// standardness limits.
+    pub fn standardness_check(
+        &self,
+        policy: &RelayPolicy,
+    ) -> Result<(), Vec<StandardnessViolation>> {
+        let mut violations = vec![];
+        match *self {
+            Descriptor::Bare(ref bare) => {
+                let ms = bare.as_inner();
+                if let Terminal::Multi(..) = ms.node {
+                    if !policy.permit_bare_multisig {
+                        violations.push(StandardnessViolation::BareMultisig);
+                    }
+                }
+                if BareCtx::other_top_level_checks(ms).is_err() {
+                    violations.push(StandardnessViolation::NonStandardBareScript);
+                }
+            }
+            Descriptor::Pkh(..) | Descriptor::Wpkh(..) => {}
+            Descriptor::Sh(ref sh) => match *sh.as_inner() {
+                ShInner::Wsh(ref wsh) => check_wsh(wsh.as_inner(), policy, &mut violations),
+                ShInner::Wpkh(..) => {}
+                ShInner::SortedMulti(ref smv) => check_sh(
+                    smv.script_size(),
+                    Ok(smv.max_satisfaction_size()),
+                    policy,
+                    &mut violations,
+                ),
+                ShInner::Ms(ref ms) => check_sh(
+                    ms.script_size(),
+                    ms.max_satisfaction_size(),
+                    policy,
+                    &mut violations,
+                ),
+            },
+            Descriptor::Wsh(ref wsh) => check_wsh(wsh.as_inner(), policy, &mut violations),
+            Descriptor::Tr(..) | Descriptor::TrExt(..) | Descriptor::Wit(..) => {}
+            Descriptor::LegacyCSFSCov(ref cov) => {
+                // The covenant prefix adds 58 bytes of script and 12 witness elements
+                let ms = cov.to_ms();
+                let script_size =
+                    ms.script_size() + 58 - if ms.ext.has_free_verify { 1 } else { 0 };
+                check_segwit(
+                    script_size,
+                    ms.max_satisfaction_witness_elements().map(|n| n + 12),
+                    policy,
+                    &mut violations,
+                );
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey + ToPublicKey, Ext: Extension + ParseableExt> Descriptor<Pk, Ext> {
+    /// The minimum explicit value of an output of the descriptor not to be
+    /// dust under `policy`, see [`RelayPolicy::dust_threshold`]
+    pub fn dust_threshold(&self, policy: &RelayPolicy) -> u64 {
+        policy.dust_threshold(&self.script_pubkey())
+    }
+}
+
+fn check_wsh<Pk: MiniscriptKey>(
+    inner: &WshInner<Pk>,
+    policy: &RelayPolicy,
+    violations: &mut Vec<StandardnessViolation>,
+) {
+    match *inner {
+        WshInner::SortedMulti(ref smv) => check_segwit(
+            smv.script_size(),
+            Ok(smv.max_satisfaction_witness_elements()),
+            policy,
+            violations,
+        ),
+        WshInner::Ms(ref ms) => check_segwit(
+            ms.script_size(),
+            ms.max_satisfaction_witness_elements(),
+            policy,
+            violations,
+        ),
+    }
+}
+
+fn check_segwit(
+    script_size: usize,
+    max_sat_elems: Result<usize, crate::Error>,
+    policy: &RelayPolicy,
+    violations: &mut Vec<StandardnessViolation>,
+) {
+    if script_size > policy.max_witness_script_size {
+        violations.push(StandardnessViolation::WitnessScriptSize {
+            actual: script_size,
+            limit: policy.max_witness_script_size,
+        });
+    }
+    match max_sat_elems {
+        Ok(n) if n > policy.max_witness_stack_items => {
+            violations.push(StandardnessViolation::WitnessStackItems {
+                actual: n,
+                limit: policy.max_witness_stack_items,
+            })
+        }
+        Ok(_) => {}
+        Err(e) => violations.push(StandardnessViolation::Unsatisfiable(e.to_string())),
+    }
+}
+
+fn check_sh(
+    script_size: usize,
+    max_sat_size: Result<usize, crate::Error>,
+    policy: &RelayPolicy,
+    violations: &mut Vec<StandardnessViolation>,
+) {
+    if script_size > policy.max_redeem_script_size {
+        violations.push(StandardnessViolation::RedeemScriptSize {
+            actual: script_size,
+            limit: policy.max_redeem_script_size,
+        });
+    }
+    match max_sat_size {
+        // The redeem script is pushed after the satisfaction
+        Ok(n) => {
+            let script_sig_size = n + push_opcode_size(script_size) + script_size;
+            if script_sig_size > policy.max_script_sig_size {
+                violations.push(StandardnessViolation::ScriptSigSize {
+                    actual: script_sig_size,
+                    limit: policy.max_script_sig_size,
+                });
+            }
+        }
+        Err(e) => violations.push(StandardnessViolation::Unsatisfiable(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::DescriptorPublicKey;
+
+    #[test]
+    fn standardness() {
+        let policy = RelayPolicy::default();
+        let pk = "020000000000000000000000000000000000000000000000000000000000000002";
+        let pk2 = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let desc =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elwsh(pk({}))", pk)).unwrap();
+        assert_eq!(desc.standardness_check(&policy), Ok(()));
+
+        // Bare multisig is only standard if permitted by the policy
+        let bare =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elmulti(1,{})", pk)).unwrap();
+        assert_eq!(bare.standardness_check(&policy), Ok(()));
+        let no_bare_multisig = RelayPolicy {
+            permit_bare_multisig: false,
+            ..policy
+        };
+        assert_eq!(
+            bare.standardness_check(&no_bare_multisig),
+            Err(vec![StandardnessViolation::BareMultisig])
+        );
+
+        // Tighter policies
+        let sh =
+            Descriptor::<DescriptorPublicKey>::from_str(&format!("elsh(multi(2,{},{}))", pk, pk2))
+                .unwrap();
+        assert_eq!(sh.standardness_check(&policy), Ok(()));
+        let tight = RelayPolicy {
+            max_witness_script_size: 10,
+            max_script_sig_size: 100,
+            ..policy
+        };
+        match sh.standardness_check(&tight) {
+            Err(v) => assert!(matches!(
+                v[..],
+                [StandardnessViolation::ScriptSigSize { limit: 100, .. }]
+            )),
+            Ok(()) => panic!("scriptSig limit must be violated"),
+        }
+        assert_eq!(
+            desc.standardness_check(&tight),
+            Err(vec![StandardnessViolation::WitnessScriptSize {
+                actual: 35,
+                limit: 10
+            }])
+        );
+    }
+
+    #[test]
+    fn relay_outputs() {
+        let policy = RelayPolicy::default();
+        let pk = "020000000000000000000000000000000000000000000000000000000000000002";
+        let asset = elements::AssetId::from_slice(&[1; 32]).unwrap();
+        let wpkh = Descriptor::<bitcoin::PublicKey>::from_str(&format!("elwpkh({})", pk)).unwrap();
+        let pkh = Descriptor::<bitcoin::PublicKey>::from_str(&format!("elpkh({})", pk)).unwrap();
+        let bare =
+            Descriptor::<bitcoin::PublicKey>::from_str(&format!("elmulti(1,{})", pk)).unwrap();
+        assert_eq!(wpkh.dust_threshold(&policy), 399);
+        assert_eq!(pkh.dust_threshold(&policy), 651);
+        assert_eq!(bare.dust_threshold(&policy), 687);
+
+        assert_eq!(policy.check_output(&bare.txout(687, asset, None)), Ok(()));
+        assert_eq!(
+            policy.check_output(&bare.txout(686, asset, None)),
+            Err(StandardnessViolation::Dust {
+                value: 686,
+                threshold: 687
+            })
+        );
+        // Fee outputs are never dust
+        let fee = TxOut::new_fee(1, asset);
+        assert_eq!(policy.check_output(&fee), Ok(()));
+
+        // OP_RETURN outputs may only push data, up to the policy limit
+        let op_return = |data: &[u8]| TxOut {
+            script_pubkey: elements::script::Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_slice(data)
+                .into_script(),
+            ..fee.clone()
+        };
+        assert_eq!(policy.check_output(&op_return(&[7; 80])), Ok(()));
+        assert_eq!(
+            policy.check_output(&op_return(&[7; 81])),
+            Err(StandardnessViolation::DataCarrierSize {
+                actual: 84,
+                limit: 83
+            })
+        );
+        let no_data_carrier = RelayPolicy {
+            max_data_carrier_size: None,
+            ..policy
+        };
+        assert_eq!(
+            no_data_carrier.check_output(&op_return(&[7; 10])),
+            Err(StandardnessViolation::NonStandardOpReturn)
+        );
+    }
+}
@@ -0,0 +1,465 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Test Vectors
+//!
+//! Generation of test vectors for descriptors: a transaction spending an
+//! output of the descriptor, with its witness, which is checked to be
+//! accepted by the [`crate::interpreter`]. The keys and hash preimages of the
+//! descriptor are derived from a seed, so that the vectors are
+//! deterministic and can be used as regression tests, e.g. by wallets
+//! integrating new descriptor types.
+//!
+//! Covenant extensions constraining the spending transaction are only
+//! satisfied if the outputs of the transaction satisfy them, see
+//! [`TestVectorGenerator::outputs`].
+//!
+
+use std::collections::BTreeMap;
+use std::{error, fmt};
+
+use bitcoin::hashes::{hash160, ripemd160, sha256, Hash};
+use elements::schnorr::TapTweak;
+use elements::secp256k1_zkp::{self, Secp256k1, SecretKey};
+use elements::sighash::{Prevouts, SigHashCache};
+use elements::taproot::{TapBranchHash, TapLeafHash};
+use elements::{
+    confidential, AssetId, BlockHash, EcdsaSigHashType, LockTime, OutPoint, SchnorrSigHashType,
+    Script, Sequence, Transaction, TxIn, TxOut, Txid,
+};
+
+use super::{Descriptor, DescriptorType, LegacyCovSatisfier};
+use crate::extensions::{ParseableExt, TxEnv};
+use crate::interpreter::{self, Interpreter};
+use crate::miniscript::satisfy::{ElementsSig, Preimage32};
+use crate::policy::Liftable;
+use crate::{hash256, Error, Extension, Satisfier, ToPublicKey, TranslatePk, Translator};
+
+/// The value of the spent output
+const PREVOUT_VALUE: u64 = 100_000_000;
+
+/// A spend of a descriptor accepted by the interpreter
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TestVector<Ext: Extension> {
+    /// The descriptor, with the generated keys and hashes
+    pub descriptor: Descriptor<bitcoin::PublicKey, Ext>,
+    /// The secret keys, by the name of their key in the descriptor
+    pub secret_keys: BTreeMap<String, SecretKey>,
+    /// The preimages, by the name of their hash in the descriptor
+    pub preimages: BTreeMap<String, Preimage32>,
+    /// The spent outputs, i.e. a single output of the descriptor
+    pub prevouts: Vec<TxOut>,
+    /// The spending transaction, with its witness and scriptSig
+    pub tx: Transaction,
+    /// The genesis hash committed to by taproot signatures
+    pub genesis_hash: BlockHash,
+}
+
+/// Error when generating a test vector
+#[derive(Debug)]
+pub enum TestVectorError {
+    /// The descriptor cannot be satisfied
+    Satisfaction(Error),
+    /// The interpreter rejected the satisfaction
+    Interpreter(interpreter::Error),
+}
+
+impl fmt::Display for TestVectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TestVectorError::Satisfaction(ref e) => write!(f, "cannot satisfy descriptor: {}", e),
+            TestVectorError::Interpreter(ref e) => write!(f, "interpreter rejected spend: {}", e),
+        }
+    }
+}
+
+impl error::Error for TestVectorError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            TestVectorError::Satisfaction(ref e) => Some(e),
+            TestVectorError::Interpreter(ref e) => Some(e),
+        }
+    }
+}
+
+/// Generator of [`TestVector`]s
+#[derive(Clone, Debug)]
+pub struct TestVectorGenerator {
+    seed: [u8; 32],
+    outputs: Option<Vec<TxOut>>,
+    tap_key_spend: bool,
+}
+
+impl TestVectorGenerator {
+    /// A generator deriving keys, preimages and transaction data from `seed`
+    pub fn new(seed: [u8; 32]) -> Self {
+        TestVectorGenerator {
+            seed,
+            outputs: None,
+            tap_key_spend: true,
+        }
+    }
+
+    /// Sets the outputs of the spending transaction, by default a single
+    /// output with the value of the spent output minus a fee
+    pub fn outputs(mut self, outputs: Vec<TxOut>) -> Self {
+        self.outputs = Some(outputs);
+        self
+    }
+
+    /// Spends taproot descriptors with their script paths, by not signing
+    /// with their internal key
+    pub fn tap_script_spend(mut self) -> Self {
+        self.tap_key_spend = false;
+        self
+    }
+
+    // Derives 32 bytes for the given purpose and name
+    fn derive(&self, purpose: &str, name: &str) -> [u8; 32] {
+        let mut engine = sha256::Hash::engine();
+        bitcoin::hashes::HashEngine::input(&mut engine, &self.seed);
+        bitcoin::hashes::HashEngine::input(&mut engine, purpose.as_bytes());
+        bitcoin::hashes::HashEngine::input(&mut engine, &[0]);
+        bitcoin::hashes::HashEngine::input(&mut engine, name.as_bytes());
+        sha256::Hash::from_engine(engine).to_byte_array()
+    }
+
+    /// Generates a spend of the descriptor, with keys and hashes named by
+    /// strings
+    pub fn generate<Ext>(
+        &self,
+        desc: &Descriptor<String, Ext>,
+    ) -> Result<TestVector<Ext>, TestVectorError>
+    where
+        Ext: Extension + ParseableExt,
+    {
+        let secp = Secp256k1::new();
+        let mut keygen = KeyGen {
+            generator: self,
+            secp: &secp,
+            secret_keys: BTreeMap::new(),
+            preimages: BTreeMap::new(),
+        };
+        let descriptor = desc
+            .translate_pk(&mut keygen)
+            .map_err(TestVectorError::Satisfaction)?;
+        let (secret_keys, preimages) = (keygen.secret_keys, keygen.preimages);
+
+        // Timelocks are set to satisfy the longest ones of the descriptor
+        let (after, older) = match descriptor.lift() {
+            Ok(policy) => (
+                policy.absolute_timelocks().into_iter().max(),
+                policy.relative_timelocks().into_iter().max(),
+            ),
+            Err(_) => (None, None),
+        };
+
+        let asset = AssetId::from_slice(&self.derive("asset", "")).expect("32 bytes");
+        let prevouts = vec![TxOut {
+            asset: confidential::Asset::Explicit(asset),
+            value: confidential::Value::Explicit(PREVOUT_VALUE),
+            nonce: confidential::Nonce::Null,
+            script_pubkey: descriptor.script_pubkey(),
+            witness: Default::default(),
+        }];
+        let outputs = self.outputs.clone().unwrap_or_else(|| {
+            vec![TxOut {
+                asset: confidential::Asset::Explicit(asset),
+                value: confidential::Value::Explicit(PREVOUT_VALUE - 1_000),
+                nonce: confidential::Nonce::Null,
+                script_pubkey: Script::new(),
+                witness: Default::default(),
+            }]
+        });
+        let mut tx = Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(after.unwrap_or(0)),
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_byte_array(self.derive("txid", "")), 0),
+                sequence: older
+                    .map(Sequence::from_consensus)
+                    .unwrap_or(Sequence::ENABLE_RBF_NO_LOCKTIME),
+                ..Default::default()
+            }],
+            output: outputs,
+        };
+        let genesis_hash = BlockHash::from_byte_array(self.derive("genesis", ""));
+
+        let (witness, script_sig) = {
+            let keys = secret_keys
+                .values()
+                .map(|sk| {
+                    let pk = secp256k1_zkp::PublicKey::from_secret_key(&secp, sk);
+                    (bitcoin::PublicKey::new(pk), *sk)
+                })
+                .collect();
+            let tap_key_spend = match descriptor {
+                Descriptor::Tr(ref tr) if self.tap_key_spend => {
+                    Some((*tr.internal_key(), tr.spend_info().merkle_root()))
+                }
+                Descriptor::TrExt(ref tr) if self.tap_key_spend => {
+                    Some((*tr.internal_key(), tr.spend_info().merkle_root()))
+                }
+                _ => None,
+            };
+            let legacy = matches!(
+                descriptor.desc_type(),
+                DescriptorType::Bare
+                    | DescriptorType::Sh
+                    | DescriptorType::Pkh
+                    | DescriptorType::ShSortedMulti
+            );
+            let script_code = descriptor.script_code().ok();
+            let satisfier = SigningSatisfier {
+                secp: &secp,
+                keys,
+                preimages: preimages.values().copied().collect(),
+                tx: &tx,
+                prevouts: &prevouts,
+                script_code: script_code.clone(),
+                legacy,
+                tap_key_spend,
+                genesis_hash,
+            };
+            let txenv = TxEnv::new(&tx, &prevouts, 0).expect("one prevout per input");
+            // Only used by covenant descriptors, which have a script code
+            let script_code = script_code.unwrap_or_default();
+            let cov = LegacyCovSatisfier::new_segwitv0(
+                &tx,
+                0,
+                prevouts[0].value,
+                &script_code,
+                EcdsaSigHashType::All,
+            );
+            descriptor
+                .get_satisfaction((satisfier, txenv, tx.lock_time, tx.input[0].sequence, cov))
+                .map_err(TestVectorError::Satisfaction)?
+        };
+        tx.input[0].witness.script_witness = witness;
+        tx.input[0].script_sig = script_sig;
+
+        {
+            let txin = &tx.input[0];
+            let interpreter = Interpreter::<Ext>::from_txdata_ext(
+                &prevouts[0].script_pubkey,
+                &txin.script_sig,
+                &txin.witness.script_witness,
+                txin.sequence,
+                tx.lock_time,
+            )
+            .map_err(TestVectorError::Interpreter)?;
+            let txenv = TxEnv::new(&tx, &prevouts, 0).expect("one prevout per input");
+            for res in interpreter.iter(&secp, &txenv, genesis_hash) {
+                res.map_err(TestVectorError::Interpreter)?;
+            }
+        }
+
+        Ok(TestVector {
+            descriptor,
+            secret_keys,
+            preimages,
+            prevouts,
+            tx,
+            genesis_hash,
+        })
+    }
+}
+
+// Translator replacing named keys and hashes by generated ones
+struct KeyGen<'a> {
+    generator: &'a TestVectorGenerator,
+    secp: &'a Secp256k1<secp256k1_zkp::All>,
+    secret_keys: BTreeMap<String, SecretKey>,
+    preimages: BTreeMap<String, Preimage32>,
+}
+
+impl<'a> KeyGen<'a> {
+    fn preimage(&mut self, name: &str) -> Preimage32 {
+        let preimage = self.generator.derive("preimage", name);
+        self.preimages.insert(name.to_owned(), preimage);
+        preimage
+    }
+}
+
+impl<'a> Translator<String, bitcoin::PublicKey, Error> for KeyGen<'a> {
+    fn pk(&mut self, pk: &String) -> Result<bitcoin::PublicKey, Error> {
+        let sk = SecretKey::from_slice(&self.generator.derive("key", pk))?;
+        self.secret_keys.insert(pk.clone(), sk);
+        Ok(bitcoin::PublicKey::new(
+            secp256k1_zkp::PublicKey::from_secret_key(self.secp, &sk),
+        ))
+    }
+
+    fn sha256(&mut self, sha256: &String) -> Result<sha256::Hash, Error> {
+        Ok(sha256::Hash::hash(&self.preimage(sha256)))
+    }
+
+    fn hash256(&mut self, hash256: &String) -> Result<hash256::Hash, Error> {
+        Ok(hash256::Hash::hash(&self.preimage(hash256)))
+    }
+
+    fn ripemd160(&mut self, ripemd160: &String) -> Result<ripemd160::Hash, Error> {
+        Ok(ripemd160::Hash::hash(&self.preimage(ripemd160)))
+    }
+
+    fn hash160(&mut self, hash160: &String) -> Result<hash160::Hash, Error> {
+        Ok(hash160::Hash::hash(&self.preimage(hash160)))
+    }
+}
+
+// Satisfier signing the spending transaction with the generated keys
+struct SigningSatisfier<'a> {
+    secp: &'a Secp256k1<secp256k1_zkp::All>,
+    keys: BTreeMap<bitcoin::PublicKey, SecretKey>,
+    preimages: Vec<Preimage32>,
+    tx: &'a Transaction,
+    prevouts: &'a [TxOut],
+    script_code: Option<Script>,
+    legacy: bool,
+    tap_key_spend: Option<(bitcoin::PublicKey, Option<TapBranchHash>)>,
+    genesis_hash: BlockHash,
+}
+
+impl<'a> SigningSatisfier<'a> {
+    fn schnorr_sign(&self, keypair: &secp256k1_zkp::KeyPair, msg: &[u8]) -> elements::SchnorrSig {
+        let msg = secp256k1_zkp::Message::from_slice(msg).expect("32 byte sighash");
+        elements::SchnorrSig {
+            sig: self
+                .secp
+                .sign_schnorr_with_aux_rand(&msg, keypair, &[0; 32]),
+            hash_ty: SchnorrSigHashType::Default,
+        }
+    }
+
+    fn preimage<F: Fn(&Preimage32) -> bool>(&self, matches: F) -> Option<Preimage32> {
+        self.preimages.iter().copied().find(matches)
+    }
+}
+
+impl<'a> Satisfier<bitcoin::PublicKey> for SigningSatisfier<'a> {
+    fn lookup_ecdsa_sig(&self, pk: &bitcoin::PublicKey) -> Option<ElementsSig> {
+        let sk = self.keys.get(pk)?;
+        let script_code = self.script_code.as_ref()?;
+        let mut cache = SigHashCache::new(self.tx);
+        let sighash = if self.legacy {
+            cache.legacy_sighash(0, script_code, EcdsaSigHashType::All)
+        } else {
+            cache.segwitv0_sighash(
+                0,
+                script_code,
+                self.prevouts[0].value,
+                EcdsaSigHashType::All,
+            )
+        };
+        let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).expect("32 byte sighash");
+        Some((self.secp.sign_ecdsa(&msg, sk), EcdsaSigHashType::All))
+    }
+
+    fn lookup_tap_key_spend_sig(&self) -> Option<elements::SchnorrSig> {
+        let (internal_key, merkle_root) = self.tap_key_spend?;
+        let sk = self.keys.get(&internal_key)?;
+        let keypair = secp256k1_zkp::KeyPair::from_secret_key(self.secp, sk)
+            .tap_tweak(self.secp, merkle_root)
+            .to_inner();
+        let sighash = SigHashCache::new(self.tx)
+            .taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(self.prevouts),
+                SchnorrSigHashType::Default,
+                self.genesis_hash,
+            )
+            .ok()?;
+        Some(self.schnorr_sign(&keypair, &sighash[..]))
+    }
+
+    fn lookup_tap_leaf_script_sig(
+        &self,
+        pk: &bitcoin::PublicKey,
+        leaf_hash: &TapLeafHash,
+    ) -> Option<elements::SchnorrSig> {
+        let sk = self.keys.get(pk)?;
+        let sighash = SigHashCache::new(self.tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(self.prevouts),
+                *leaf_hash,
+                SchnorrSigHashType::Default,
+                self.genesis_hash,
+            )
+            .ok()?;
+        let keypair = secp256k1_zkp::KeyPair::from_secret_key(self.secp, sk);
+        Some(self.schnorr_sign(&keypair, &sighash[..]))
+    }
+
+    fn lookup_sha256(&self, h: &sha256::Hash) -> Option<Preimage32> {
+        self.preimage(|p| sha256::Hash::hash(p) == *h)
+    }
+
+    fn lookup_hash256(&self, h: &hash256::Hash) -> Option<Preimage32> {
+        self.preimage(|p| hash256::Hash::hash(p) == *h)
+    }
+
+    fn lookup_ripemd160(&self, h: &ripemd160::Hash) -> Option<Preimage32> {
+        self.preimage(|p| ripemd160::Hash::hash(p) == *h)
+    }
+
+    fn lookup_hash160(&self, h: &hash160::Hash) -> Option<Preimage32> {
+        self.preimage(|p| hash160::Hash::hash(p) == *h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::extensions::CovExtArgs;
+    use crate::{CovenantExt, NoExt};
+
+    #[test]
+    fn generate() {
+        let generator = TestVectorGenerator::new([7; 32]);
+        for desc in &[
+            "elpkh(A)",
+            "elsh(wpkh(A))",
+            "elwsh(or_d(multi(2,A,B,C),and_v(v:pk(D),and_v(v:sha256(H),older(144)))))",
+            "elsh(and_v(v:pk(A),after(500000)))",
+            "eltr(A,{pk(B),and_v(v:pk(C),hash160(H))})",
+        ] {
+            let desc = Descriptor::<String>::from_str(desc).unwrap();
+            let vector = generator.generate(&desc).unwrap();
+            assert_eq!(vector, generator.generate(&desc).unwrap());
+            assert!(!vector.secret_keys.is_empty());
+        }
+
+        // Script paths of taproot descriptors
+        let desc =
+            Descriptor::<String, NoExt>::from_str("eltr(A,and_v(v:pk(B),older(10)))").unwrap();
+        let vector = generator
+            .clone()
+            .tap_script_spend()
+            .generate(&desc)
+            .unwrap();
+        assert_eq!(vector.tx.input[0].witness.script_witness.len(), 3);
+        assert_eq!(vector.tx.input[0].sequence, Sequence::from_consensus(10));
+
+        // Extensions and covenants
+        for desc in &[
+            "eltr(A,{pk(B),and_v(v:pk(C),curr_idx_eq(0))})",
+            "elcovwsh(A,and_v(v:pk(B),ver_eq(2)))",
+        ] {
+            let desc = Descriptor::<String, CovenantExt<CovExtArgs>>::from_str(desc).unwrap();
+            let generator = generator.clone().tap_script_spend();
+            assert!(generator.generate(&desc).is_ok());
+        }
+
+        // The version of the spending transaction is 2
+        let desc = Descriptor::<String, CovenantExt<CovExtArgs>>::from_str(
+            "elwsh(and_v(v:pk(A),ver_eq(3)))",
+        )
+        .unwrap();
+        assert!(matches!(
+            generator.generate(&desc),
+            Err(TestVectorError::Satisfaction(_))
+        ));
+    }
+}
@@ -4,24 +4,32 @@ use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::{fmt, hash};
 
+use bitcoin::key::XOnlyPublicKey;
+use bitcoin::secp256k1::{Parity, Scalar};
+use bitcoin::taproot::TAPROOT_ANNEX_PREFIX;
 use elements::taproot::{
-    LeafVersion, TaprootBuilder, TaprootSpendInfo, TAPROOT_CONTROL_BASE_SIZE,
+    LeafVersion, TapLeafHash, TaprootBuilder, TaprootSpendInfo, TAPROOT_CONTROL_BASE_SIZE,
     TAPROOT_CONTROL_MAX_NODE_COUNT, TAPROOT_CONTROL_NODE_SIZE,
 };
 use elements::{self, opcodes, secp256k1_zkp, Script};
 
 use super::checksum::verify_checksum;
-use super::ELMTS_STR;
+use super::{
+    CustomAddressParams, DefiniteDescriptorKey, DescriptorPublicKey, SinglePub, SinglePubKey,
+    WitnessBuilder, ELMTS_STR,
+};
 use crate::descriptor::checksum;
 use crate::expression::{self, FromTree};
 use crate::extensions::ParseableExt;
+use crate::miniscript::dot::DotWriter;
+use crate::miniscript::satisfy::{check_preimages, Missing};
 use crate::miniscript::Miniscript;
 use crate::policy::semantic::Policy;
 use crate::policy::Liftable;
 use crate::util::{varint_len, witness_size};
 use crate::{
-    errstr, Error, Extension, ForEachKey, MiniscriptKey, NoExt, Satisfier, Tap, ToPublicKey,
-    TranslateExt, TranslatePk, Translator,
+    errstr, Error, Extension, ForEachKey, MiniscriptKey, NoExt, Satisfier, Tap, Threshold,
+    ToPublicKey, TranslateExt, TranslatePk, Translator,
 };
 
 /// A Taproot Tree representation.
@@ -182,6 +190,97 @@ impl<Pk: MiniscriptKey, Ext: Extension> fmt::Debug for TapTree<Pk, Ext> {
     }
 }
 
+/// The x-coordinate of the BIP-341 NUMS point `H`, obtained by hashing the
+/// standard uncompressed encoding of the secp256k1 generator. Nobody knows
+/// its discrete logarithm, so it can be used as an internal key which
+/// disables the key spend path.
+pub const NUMS_POINT: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// The BIP-341 NUMS point, see [`NUMS_POINT`]
+pub fn nums_point() -> XOnlyPublicKey {
+    XOnlyPublicKey::from_str(NUMS_POINT).expect("valid NUMS point")
+}
+
+/// The provably unspendable key `H + rG` suggested by BIP-341, where `H` is
+/// the [`NUMS_POINT`] and `r` is `entropy`.
+///
+/// Unlike the NUMS point itself, the resulting key does not reveal to
+/// observers that the key spend path is disabled, while revealing `r` proves
+/// it. `entropy` should be derived deterministically, e.g. from the hash of
+/// the wallet keys, so that the descriptor can be recovered.
+pub fn unspendable_key(entropy: [u8; 32]) -> Result<XOnlyPublicKey, Error> {
+    crate::with_secp(|secp| unspendable_key_with_secp(secp, entropy))
+}
+
+/// The provably unspendable key of [`unspendable_key`], computed with the
+/// given context
+pub fn unspendable_key_with_secp<C: secp256k1_zkp::Verification>(
+    secp: &secp256k1_zkp::Secp256k1<C>,
+    entropy: [u8; 32],
+) -> Result<XOnlyPublicKey, Error> {
+    let r = Scalar::from_be_bytes(entropy)
+        .map_err(|_| Error::BadDescriptor("unspendable key entropy out of range".to_string()))?;
+    let key = nums_point()
+        .public_key(Parity::Even)
+        .add_exp_tweak(secp, &r)
+        .map_err(|_| Error::BadDescriptor("invalid unspendable key entropy".to_string()))?;
+    Ok(key.x_only_public_key().0)
+}
+
+// The constructors with a provably unspendable internal key, for the key
+// types which can be built directly from the x-only key `$key`
+macro_rules! impl_unspendable_internal_key {
+    ($pk:ty, |$key:ident| $to_pk:expr) => {
+        impl<Ext: Extension> Tr<$pk, Ext> {
+            /// Create a new [`Tr`] descriptor with the script paths of `tree`
+            /// only, using the [`NUMS_POINT`] as internal key.
+            pub fn new_with_nums_point(tree: TapTree<$pk, Ext>) -> Result<Self, Error> {
+                let $key = nums_point();
+                Tr::new($to_pk, Some(tree))
+            }
+
+            /// Create a new [`Tr`] descriptor with the script paths of `tree`
+            /// only, using the [`unspendable_key`] obtained from `entropy` as
+            /// internal key.
+            pub fn new_with_unspendable_key(
+                tree: TapTree<$pk, Ext>,
+                entropy: [u8; 32],
+            ) -> Result<Self, Error> {
+                let $key = unspendable_key(entropy)?;
+                Tr::new($to_pk, Some(tree))
+            }
+        }
+    };
+}
+
+impl_unspendable_internal_key!(XOnlyPublicKey, |key| key);
+impl_unspendable_internal_key!(bitcoin::PublicKey, |key| bitcoin::PublicKey::new(
+    key.public_key(Parity::Even)
+));
+impl_unspendable_internal_key!(DescriptorPublicKey, |key| single_x_only_key(key));
+impl_unspendable_internal_key!(DefiniteDescriptorKey, |key| single_x_only_key(key)
+    .at_derivation_index(0)
+    .expect("single keys are definite"));
+
+// The descriptor key of an x-only key, without origin
+fn single_x_only_key(key: XOnlyPublicKey) -> DescriptorPublicKey {
+    DescriptorPublicKey::Single(SinglePub {
+        origin: None,
+        key: SinglePubKey::XOnly(key),
+    })
+}
+
+impl<Pk: ToPublicKey, Ext: Extension> Tr<Pk, Ext> {
+    /// Whether the internal key is the [`NUMS_POINT`], so that the
+    /// descriptor can only be spent through its script paths.
+    ///
+    /// Keys obtained from [`unspendable_key`] are not detected, as that
+    /// requires knowing the entropy.
+    pub fn is_key_spend_disabled(&self) -> bool {
+        self.internal_key.to_x_only_pubkey() == nums_point()
+    }
+}
+
 impl<Pk: MiniscriptKey, Ext: Extension> Tr<Pk, Ext> {
     /// Create a new [`Tr`] descriptor from internal key and [`TapTree`]
     pub fn new(internal_key: Pk, tree: Option<TapTree<Pk, Ext>>) -> Result<Self, Error> {
@@ -217,12 +316,55 @@ impl<Pk: MiniscriptKey, Ext: Extension> Tr<Pk, Ext> {
         }
     }
 
+    /// Graphviz DOT source of the script tree, with a node for the internal
+    /// key, each branch of the tree and each fragment of the leaves, see
+    /// [`Miniscript::to_dot`] for the labels of the fragments
+    pub fn to_dot(&self) -> String {
+        fn add_tree<Pk: MiniscriptKey, Ext: Extension>(
+            writer: &mut DotWriter,
+            tree: &TapTree<Pk, Ext>,
+        ) -> usize {
+            match *tree {
+                TapTree::Tree(ref left, ref right) => {
+                    let id = writer.node("branch");
+                    let left = add_tree(writer, left);
+                    writer.edge(id, left);
+                    let right = add_tree(writer, right);
+                    writer.edge(id, right);
+                    id
+                }
+                TapTree::Leaf(ref ms) => writer.miniscript(ms),
+            }
+        }
+
+        let mut writer = DotWriter::new("taproot");
+        let root = writer.node(&format!("internal key: {}", self.internal_key));
+        if let Some(ref tree) = self.tree {
+            let tree = add_tree(&mut writer, tree);
+            writer.edge(root, tree);
+        }
+        writer.finish()
+    }
+
     /// Compute the [`TaprootSpendInfo`] associated with this descriptor if spend data is `None`.
     ///
     /// If spend data is already computed (i.e it is not `None`), this does not recompute it.
     ///
     /// [`TaprootSpendInfo`] is only required for spending via the script paths.
     pub fn spend_info(&self) -> Arc<TaprootSpendInfo>
+    where
+        Pk: ToPublicKey,
+        Ext: ParseableExt,
+    {
+        crate::with_secp(|secp| self.spend_info_with_secp(secp))
+    }
+
+    /// Compute the [`TaprootSpendInfo`] associated with this descriptor, as
+    /// [`Self::spend_info`] does, with the given context.
+    pub fn spend_info_with_secp<C: secp256k1_zkp::Verification>(
+        &self,
+        secp: &secp256k1_zkp::Secp256k1<C>,
+    ) -> Arc<TaprootSpendInfo>
     where
         Pk: ToPublicKey,
         Ext: ParseableExt,
@@ -235,12 +377,9 @@ impl<Pk: MiniscriptKey, Ext: Extension> Tr<Pk, Ext> {
         }
         drop(read_lock);
 
-        // Get a new secp context
-        // This would be cheap operation after static context support from upstream
-        let secp = secp256k1_zkp::Secp256k1::verification_only();
         // Key spend path with no merkle root
         let data = if self.tree.is_none() {
-            TaprootSpendInfo::new_key_spend(&secp, self.internal_key.to_x_only_pubkey(), None)
+            TaprootSpendInfo::new_key_spend(secp, self.internal_key.to_x_only_pubkey(), None)
         } else {
             let mut builder = TaprootBuilder::new();
             for (depth, ms) in self.iter_scripts() {
@@ -250,7 +389,7 @@ impl<Pk: MiniscriptKey, Ext: Extension> Tr<Pk, Ext> {
                     .expect("Computing spend data on a valid Tree should always succeed");
             }
             // Assert builder cannot error here because we have a well formed descriptor
-            match builder.finalize(&secp, self.internal_key.to_x_only_pubkey()) {
+            match builder.finalize(secp, self.internal_key.to_x_only_pubkey()) {
                 Ok(data) => data,
                 Err(_) => unreachable!("We know the builder can be finalized"),
             }
@@ -277,15 +416,34 @@ impl<Pk: MiniscriptKey, Ext: Extension> Tr<Pk, Ext> {
     /// # Errors
     /// When the descriptor is impossible to safisfy (ex: sh(OP_FALSE)).
     pub fn max_weight_to_satisfy(&self) -> Result<usize, Error> {
+        self.max_weight_with_annex(None)
+    }
+
+    /// Computes an upper bound on the difference between a non-satisfied
+    /// `TxIn`'s `segwit_weight` and a satisfied `TxIn`'s `segwit_weight`,
+    /// when the witness ends with an annex of `annex_len` bytes, including
+    /// its `0x50` prefix, as returned by [`Satisfier::lookup_tap_annex`].
+    ///
+    /// See [`Tr::max_weight_to_satisfy`].
+    ///
+    /// # Errors
+    /// When the descriptor is impossible to safisfy (ex: sh(OP_FALSE)).
+    pub fn max_weight_to_satisfy_with_annex(&self, annex_len: usize) -> Result<usize, Error> {
+        self.max_weight_with_annex(Some(annex_len))
+    }
+
+    fn max_weight_with_annex(&self, annex_len: Option<usize>) -> Result<usize, Error> {
+        // annex: 1 stack item of varint(annex) + <annex>
+        let (annex_elems, annex_size) = annex_len.map_or((0, 0), |len| (1, varint_len(len) + len));
         let tree = match self.taptree() {
             None => {
                 // key spend path
                 // item: varint(sig+sigHash) + <sig(64)+sigHash(1)>
                 let item_sig_size = 1 + 65;
                 // 1 stack item
-                let stack_varint_diff = varint_len(1) - varint_len(0);
+                let stack_varint_diff = varint_len(1 + annex_elems) - varint_len(0);
 
-                return Ok(stack_varint_diff + item_sig_size);
+                return Ok(stack_varint_diff + item_sig_size + annex_size);
             }
             // script path spend..
             Some(tree) => tree,
@@ -299,10 +457,12 @@ impl<Pk: MiniscriptKey, Ext: Extension> Tr<Pk, Ext> {
                 let control_block_size = control_block_len(depth);
 
                 // stack varint difference (+1 for ctrl block, witness script already included)
-                let stack_varint_diff = varint_len(max_sat_elems + 1) - varint_len(0);
+                let stack_varint_diff = varint_len(max_sat_elems + 1 + annex_elems) - varint_len(0);
 
                 Some(
                     stack_varint_diff +
+                    // optional last element: annex
+                    annex_size +
                     // size of elements to satisfy script
                     max_sat_size +
                     // second to last element: script
@@ -383,6 +543,25 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: ParseableExt> Tr<Pk, Ext> {
         elements::Address::p2tr_tweaked(spend_info.output_key(), blinder, params)
     }
 
+    /// Obtains the unconfidential address for this descriptor, see
+    /// [`Descriptor::unconfidential_address`](crate::Descriptor::unconfidential_address)
+    pub fn unconfidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        params: P,
+    ) -> elements::Address {
+        self.address(None, params.into().to_static())
+    }
+
+    /// Obtains the confidential address for this descriptor, blinded with
+    /// the `blinder` public key
+    pub fn confidential_address<P: Into<CustomAddressParams>>(
+        &self,
+        blinder: secp256k1_zkp::PublicKey,
+        params: P,
+    ) -> elements::Address {
+        self.address(Some(blinder), params.into().to_static())
+    }
+
     /// Returns satisfying non-malleable witness and scriptSig with minimum
     /// weight to spend an output controlled by the given descriptor if it is
     /// possible to construct one using the `satisfier`.
@@ -390,7 +569,7 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: ParseableExt> Tr<Pk, Ext> {
     where
         S: Satisfier<Pk>,
     {
-        best_tap_spend(self, satisfier, false /* allow_mall */)
+        best_tap_spend(self, satisfier, false /* allow_mall */, SpendPath::Any)
     }
 
     /// Returns satisfying, possibly malleable, witness and scriptSig with
@@ -400,7 +579,66 @@ impl<Pk: MiniscriptKey + ToPublicKey, Ext: ParseableExt> Tr<Pk, Ext> {
     where
         S: Satisfier<Pk>,
     {
-        best_tap_spend(self, satisfier, true /* allow_mall */)
+        best_tap_spend(self, satisfier, true /* allow_mall */, SpendPath::Any)
+    }
+
+    /// Returns satisfying non-malleable witness and scriptSig with minimum
+    /// weight to spend an output controlled by the given descriptor along
+    /// `path`, if it is possible to construct one using the `satisfier`.
+    ///
+    /// This allows spending through a leaf even if the key spend is
+    /// available, e.g. when the spending transaction relies on the
+    /// introspection performed by a leaf.
+    ///
+    /// # Errors
+    ///
+    /// If there is no satisfaction along `path`, or if `path` is a leaf which
+    /// is not in the descriptor.
+    pub fn get_satisfaction_with<S>(
+        &self,
+        satisfier: S,
+        path: SpendPath,
+    ) -> Result<(Vec<Vec<u8>>, Script), Error>
+    where
+        S: Satisfier<Pk>,
+    {
+        best_tap_spend(self, satisfier, false /* allow_mall */, path)
+    }
+
+    /// Returns satisfying, possibly malleable, witness and scriptSig with
+    /// minimum weight to spend an output controlled by the given descriptor
+    /// along `path`, if it is possible to construct one using the
+    /// `satisfier`. See [`Tr::get_satisfaction_with`].
+    pub fn get_satisfaction_mall_with<S>(
+        &self,
+        satisfier: S,
+        path: SpendPath,
+    ) -> Result<(Vec<Vec<u8>>, Script), Error>
+    where
+        S: Satisfier<Pk>,
+    {
+        best_tap_spend(self, satisfier, true /* allow_mall */, path)
+    }
+}
+
+/// The spending path used to satisfy a [`Tr`] descriptor, see
+/// [`Tr::get_satisfaction_with`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum SpendPath {
+    /// The key spend if its signature is available, otherwise the script
+    /// spend with the smallest witness
+    Any,
+    /// The key spend only
+    KeySpend,
+    /// The script spend with the smallest witness, ignoring the key spend
+    ScriptSpend,
+    /// The script spend through the given leaf only
+    Leaf(TapLeafHash),
+}
+
+impl Default for SpendPath {
+    fn default() -> Self {
+        SpendPath::Any
     }
 }
 
@@ -546,7 +784,7 @@ impl<Pk: MiniscriptKey, Ext: Extension> fmt::Display for Tr<Pk, Ext> {
 }
 
 // Helper function to parse string into miniscript tree form
-fn parse_tr_tree(s: &str) -> Result<expression::Tree<'_>, Error> {
+pub(super) fn parse_tr_tree(s: &str) -> Result<expression::Tree<'_>, Error> {
     for ch in s.bytes() {
         if !ch.is_ascii() {
             return Err(Error::Unprintable(ch));
@@ -619,9 +857,10 @@ impl<Pk: MiniscriptKey, Ext: Extension> Liftable<Pk> for TapTree<Pk, Ext> {
             s: &TapTree<Pk, Ext>,
         ) -> Result<Policy<Pk>, Error> {
             match s {
-                TapTree::Tree(ref l, ref r) => {
-                    Ok(Policy::Threshold(1, vec![lift_helper(l)?, lift_helper(r)?]))
-                }
+                TapTree::Tree(ref l, ref r) => Ok(Policy::Threshold(Threshold::either(
+                    lift_helper(l)?,
+                    lift_helper(r)?,
+                ))),
                 TapTree::Leaf(ref leaf) => leaf.lift(),
             }
         }
@@ -634,10 +873,10 @@ impl<Pk: MiniscriptKey, Ext: Extension> Liftable<Pk> for TapTree<Pk, Ext> {
 impl<Pk: MiniscriptKey, Ext: Extension> Liftable<Pk> for Tr<Pk, Ext> {
     fn lift(&self) -> Result<Policy<Pk>, Error> {
         match &self.tree {
-            Some(root) => Ok(Policy::Threshold(
-                1,
-                vec![Policy::Key(self.internal_key.clone()), root.lift()?],
-            )),
+            Some(root) => Ok(Policy::Threshold(Threshold::either(
+                Policy::Key(self.internal_key.clone()),
+                root.lift()?,
+            ))),
             None => Ok(Policy::Key(self.internal_key.clone())),
         }
     }
@@ -714,6 +953,7 @@ fn best_tap_spend<Pk, S, Ext>(
     desc: &Tr<Pk, Ext>,
     satisfier: S,
     allow_mall: bool,
+    path: SpendPath,
 ) -> Result<(Vec<Vec<u8>>, Script), Error>
 where
     Pk: ToPublicKey,
@@ -721,15 +961,53 @@ where
     Ext: ParseableExt,
 {
     let spend_info = desc.spend_info();
+    let annex = satisfier.lookup_tap_annex();
+    if let Some(ref annex) = annex {
+        if annex.first() != Some(&TAPROOT_ANNEX_PREFIX) {
+            return Err(Error::InvalidTapAnnex);
+        }
+    }
+    let with_annex = |mut wit: WitnessBuilder<Tap>| -> Result<_, Error> {
+        if let Some(ref annex) = annex {
+            wit.annex(annex.clone())?;
+        }
+        Ok((wit.into_witness()?, Script::new()))
+    };
+    let key_sig = match path {
+        SpendPath::Any | SpendPath::KeySpend => satisfier.lookup_tap_key_spend_sig(),
+        SpendPath::ScriptSpend | SpendPath::Leaf(..) => None,
+    };
+    // Only the leaves along `path` are considered for the script spend
+    let in_path = |ms: &Miniscript<Pk, Tap, Ext>| match path {
+        SpendPath::Any | SpendPath::ScriptSpend => true,
+        SpendPath::KeySpend => false,
+        SpendPath::Leaf(hash) => {
+            TapLeafHash::from_script(&ms.encode(), LeafVersion::default()) == hash
+        }
+    };
+    if let SpendPath::Leaf(hash) = path {
+        if !desc.iter_scripts().any(|(_, ms)| in_path(ms)) {
+            return Err(Error::BadDescriptor(format!(
+                "No leaf with hash {} in taproot descriptor",
+                hash
+            )));
+        }
+    }
     // First try the key spend path
-    if let Some(sig) = satisfier.lookup_tap_key_spend_sig() {
-        Ok((vec![sig.to_vec()], Script::new()))
+    if let Some(sig) = key_sig {
+        with_annex(WitnessBuilder::new(vec![sig.to_vec()]))
+    } else if path == SpendPath::KeySpend {
+        Err(Error::MissingSig(desc.internal_key().to_public_key()))
     } else {
+        // A wrong preimage is an error, even if another leaf can be satisfied
+        for (_, ms) in desc.iter_scripts().filter(|&(_, ms)| in_path(ms)) {
+            check_preimages(ms, &satisfier)?;
+        }
         // Since we have the complete descriptor we can ignore the satisfier. We don't use the control block
         // map (lookup_control_block) from the satisfier here.
         let (mut min_wit, mut min_wit_len) = (None, None);
-        for (depth, ms) in desc.iter_scripts() {
-            let mut wit = if allow_mall {
+        for (depth, ms) in desc.iter_scripts().filter(|&(_, ms)| in_path(ms)) {
+            let wit = if allow_mall {
                 match ms.satisfy_malleable(&satisfier) {
                     Ok(wit) => wit,
                     Err(..) => continue, // No witness for this script in tr descriptor, look for next one
@@ -751,21 +1029,31 @@ where
                 continue;
             } else {
                 let leaf_script = (ms.encode(), LeafVersion::default());
+                // There can be multiple control blocks for a (script, ver) pair
+                // Find the smallest one amongst those
                 let control_block = spend_info
                     .control_block(&leaf_script)
                     .expect("Control block must exist in script map for every known leaf");
-                wit.push(leaf_script.0.into_bytes()); // Push the leaf script
-                                                      // There can be multiple control blocks for a (script, ver) pair
-                                                      // Find the smallest one amongst those
-                wit.push(control_block.serialize());
+                let mut wit = WitnessBuilder::new(wit);
+                wit.script(&leaf_script.0).control_block(&control_block);
                 // Finally, save the minimum
                 min_wit = Some(wit);
                 min_wit_len = Some(wit_size);
             }
         }
         match min_wit {
-            Some(wit) => Ok((wit, Script::new())),
-            None => Err(Error::CouldNotSatisfy), // Could not satisfy all miniscripts inside Tr
+            Some(wit) => with_annex(wit),
+            // Could not satisfy any miniscript inside Tr, report the items
+            // missing from the cheapest one
+            None => Err(desc
+                .iter_scripts()
+                .filter(|&(_, ms)| in_path(ms))
+                .filter_map(|(_, ms)| ms.missing_items(&satisfier))
+                .min_by_key(Missing::len)
+                .filter(|missing| !missing.is_empty())
+                .map_or(Error::CouldNotSatisfy, |missing| {
+                    Error::MissingItems(missing.into_public_keys())
+                })),
         }
     }
 }
@@ -794,4 +1082,201 @@ mod tests {
         // Note the last ac12 only has ac and fails the predicate
         assert!(!tr.for_each_key(|k| k.starts_with("acc")));
     }
+
+    #[test]
+    fn nums_internal_key() {
+        let pk = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let ms = Miniscript::<bitcoin::PublicKey, Tap>::from_str(&format!("pk({})", pk)).unwrap();
+        let tree = TapTree::Leaf(Arc::new(ms));
+        let ms = Miniscript::<XOnlyPublicKey, Tap>::from_str(&format!("pk({})", &pk[2..])).unwrap();
+        let x_only_tree = TapTree::Leaf(Arc::new(ms));
+
+        let tr = Tr::<bitcoin::PublicKey, NoExt>::new_with_nums_point(tree.clone()).unwrap();
+        assert_eq!(tr.internal_key().to_string(), format!("02{}", NUMS_POINT));
+        assert!(tr.is_key_spend_disabled());
+        let tr = Tr::<XOnlyPublicKey, NoExt>::new_with_nums_point(x_only_tree).unwrap();
+        assert_eq!(tr.internal_key().to_string(), NUMS_POINT);
+        assert!(tr.is_key_spend_disabled());
+        let ms =
+            Miniscript::<DescriptorPublicKey, Tap>::from_str(&format!("pk({})", &pk[2..])).unwrap();
+        let tr = Tr::<DescriptorPublicKey, NoExt>::new_with_nums_point(TapTree::Leaf(Arc::new(ms)))
+            .unwrap();
+        assert_eq!(tr.internal_key().to_string(), NUMS_POINT);
+
+        // H + 1G
+        let mut one = [0; 32];
+        one[31] = 1;
+        let tr = Tr::<bitcoin::PublicKey, NoExt>::new_with_unspendable_key(tree, one).unwrap();
+        assert!(!tr.is_key_spend_disabled());
+        assert!(unspendable_key([0xff; 32]).is_err());
+        let secp = secp256k1_zkp::Secp256k1::verification_only();
+        assert_eq!(
+            unspendable_key_with_secp(&secp, one).unwrap(),
+            unspendable_key(one).unwrap()
+        );
+        let fresh =
+            Tr::<bitcoin::PublicKey, NoExt>::new(*tr.internal_key(), tr.taptree().clone()).unwrap();
+        assert_eq!(
+            fresh.spend_info_with_secp(&secp).output_key(),
+            tr.spend_info().output_key()
+        );
+        let tr = Tr::<bitcoin::PublicKey, NoExt>::new(pk.parse().unwrap(), None).unwrap();
+        assert!(!tr.is_key_spend_disabled());
+    }
+
+    #[test]
+    fn satisfy_with_spend_path() {
+        // a satisfier with signatures for the key spend and all the leaves
+        struct AllSigs(elements::SchnorrSig);
+
+        impl<Pk: ToPublicKey> Satisfier<Pk> for AllSigs {
+            fn lookup_tap_key_spend_sig(&self) -> Option<elements::SchnorrSig> {
+                Some(self.0)
+            }
+
+            fn lookup_tap_leaf_script_sig(
+                &self,
+                _pk: &Pk,
+                _h: &TapLeafHash,
+            ) -> Option<elements::SchnorrSig> {
+                Some(self.0)
+            }
+        }
+
+        let tr = Tr::<bitcoin::PublicKey, NoExt>::from_str(
+            "eltr(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798,{\
+             pk(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5),\
+             and_v(v:pk(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9),older(10))})",
+        )
+        .unwrap();
+        let sig = elements::SchnorrSig {
+            sig: secp256k1_zkp::schnorr::Signature::from_slice(&[1; 64]).unwrap(),
+            hash_ty: elements::SchnorrSigHashType::Default,
+        };
+        let leaf_hash = |i: usize| {
+            let ms = tr.iter_scripts().nth(i).unwrap().1;
+            TapLeafHash::from_script(&ms.encode(), LeafVersion::default())
+        };
+
+        let (wit, _) = tr.get_satisfaction(AllSigs(sig)).unwrap();
+        assert_eq!(wit.len(), 1);
+        let (wit, _) = tr
+            .get_satisfaction_with(AllSigs(sig), SpendPath::KeySpend)
+            .unwrap();
+        assert_eq!(wit.len(), 1);
+        assert!(matches!(
+            tr.get_satisfaction_with((), SpendPath::KeySpend),
+            Err(Error::MissingSig(..))
+        ));
+
+        // the cheapest leaf is the first one
+        let (wit, _) = tr
+            .get_satisfaction_with(AllSigs(sig), SpendPath::ScriptSpend)
+            .unwrap();
+        assert_eq!(wit.len(), 3);
+        assert_eq!(
+            wit[1],
+            tr.iter_scripts().next().unwrap().1.encode().into_bytes()
+        );
+
+        // the second leaf needs the relative timelock, which is not met
+        assert!(tr
+            .get_satisfaction_with(AllSigs(sig), SpendPath::Leaf(leaf_hash(1)))
+            .is_err());
+        let (wit, _) = tr
+            .get_satisfaction_with(
+                (AllSigs(sig), elements::Sequence::from_height(10)),
+                SpendPath::Leaf(leaf_hash(1)),
+            )
+            .unwrap();
+        assert_eq!(
+            wit[1],
+            tr.iter_scripts().nth(1).unwrap().1.encode().into_bytes()
+        );
+
+        let unknown = TapLeafHash::from_script(&Script::new(), LeafVersion::default());
+        assert!(matches!(
+            tr.get_satisfaction_with(AllSigs(sig), SpendPath::Leaf(unknown)),
+            Err(Error::BadDescriptor(..))
+        ));
+    }
+
+    #[test]
+    fn satisfy_with_annex() {
+        struct KeySpend(elements::SchnorrSig, Vec<u8>);
+
+        impl<Pk: ToPublicKey> Satisfier<Pk> for KeySpend {
+            fn lookup_tap_key_spend_sig(&self) -> Option<elements::SchnorrSig> {
+                Some(self.0)
+            }
+
+            fn lookup_tap_annex(&self) -> Option<Vec<u8>> {
+                Some(self.1.clone())
+            }
+        }
+
+        let pk = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let tr = Tr::<bitcoin::PublicKey, NoExt>::new(pk.parse().unwrap(), None).unwrap();
+        let sig = elements::SchnorrSig {
+            sig: secp256k1_zkp::schnorr::Signature::from_slice(&[1; 64]).unwrap(),
+            hash_ty: elements::SchnorrSigHashType::Default,
+        };
+
+        let (wit, _) = tr
+            .get_satisfaction(KeySpend(sig, vec![0x50, 1, 2]))
+            .unwrap();
+        assert_eq!(wit, vec![sig.to_vec(), vec![0x50, 1, 2]]);
+        // varint(annex) + <annex>
+        assert_eq!(
+            tr.max_weight_to_satisfy_with_annex(3).unwrap(),
+            tr.max_weight_to_satisfy().unwrap() + 4
+        );
+        assert!(matches!(
+            tr.get_satisfaction(KeySpend(sig, vec![1, 2])),
+            Err(Error::InvalidTapAnnex)
+        ));
+    }
+
+    #[test]
+    fn tr_preimage_mismatch() {
+        use bitcoin::hashes::{sha256, Hash};
+
+        struct PreimageSatisfier([u8; 32]);
+
+        impl Satisfier<bitcoin::PublicKey> for PreimageSatisfier {
+            fn lookup_sha256(&self, _h: &sha256::Hash) -> Option<[u8; 32]> {
+                Some(self.0)
+            }
+        }
+
+        let pk = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let hash = sha256::Hash::hash(&[0x42; 32]);
+        let tr = Tr::<bitcoin::PublicKey, NoExt>::from_str(&format!(
+            "eltr({},and_v(v:pk({}),sha256({})))",
+            pk, pk, hash
+        ))
+        .unwrap();
+        assert!(matches!(
+            tr.get_satisfaction(PreimageSatisfier([0x43; 32])),
+            Err(Error::PreimageMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn tr_to_dot() {
+        let tr = Tr::<String, NoExt>::from_str("eltr(A,{pk(B),pk(C)})").unwrap();
+        let dot = tr.to_dot();
+        assert!(dot.starts_with("digraph taproot {\n"));
+        assert!(dot.contains("n0 [label=\"internal key: A\"];"));
+        assert!(dot.contains("n1 [label=\"branch\"];"));
+        assert!(dot.contains("n3 [label=\"pk_k(B)\\nscript: 33 B\\nsat: 66 B\\ndissat: 1 B\"];"));
+        // branch -> leaves, c: -> pk_k and the internal key -> branch
+        assert_eq!(dot.matches(" -> ").count(), 5);
+
+        let tr = Tr::<String, NoExt>::from_str("eltr(A)").unwrap();
+        assert_eq!(
+            tr.to_dot(),
+            "digraph taproot {\n    node [shape=box];\n    n0 [label=\"internal key: A\"];\n}\n"
+        );
+    }
 }
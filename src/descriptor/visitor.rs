@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Descriptor Visitor
+//!
+//! Dispatch on the type of a descriptor without matching on the variants of
+//! [`Descriptor`]. Every method of [`DescriptorVisitor`] has an empty default
+//! implementation, so that visitors keep compiling when new descriptor types
+//! are added.
+//!
+
+use super::{Bare, Descriptor, LegacyCSFSCov, Pkh, Sh, Tr, Wit, Wpkh, Wsh};
+use crate::{Extension, MiniscriptKey, NoExt};
+
+/// A visitor of the different types of descriptors, see [`Descriptor::accept`]
+///
+/// Only the methods of the descriptor types of interest need to be
+/// implemented, the other ones do nothing by default.
+pub trait DescriptorVisitor<Pk: MiniscriptKey, Ext: Extension> {
+    /// Visit a bare descriptor
+    fn visit_bare(&mut self, _bare: &Bare<Pk>) {}
+
+    /// Visit a pkh descriptor
+    fn visit_pkh(&mut self, _pkh: &Pkh<Pk>) {}
+
+    /// Visit a wpkh descriptor
+    fn visit_wpkh(&mut self, _wpkh: &Wpkh<Pk>) {}
+
+    /// Visit a sh descriptor, including the nested wsh and wpkh ones
+    fn visit_sh(&mut self, _sh: &Sh<Pk>) {}
+
+    /// Visit a wsh descriptor
+    fn visit_wsh(&mut self, _wsh: &Wsh<Pk>) {}
+
+    /// Visit a taproot descriptor without extensions
+    fn visit_tr(&mut self, _tr: &Tr<Pk, NoExt>) {}
+
+    /// Visit a taproot descriptor with extensions
+    fn visit_tr_ext(&mut self, _tr: &Tr<Pk, Ext>) {}
+
+    /// Visit a legacy covenant descriptor
+    fn visit_cov(&mut self, _cov: &LegacyCSFSCov<Pk, Ext>) {}
+
+    /// Visit a witness program descriptor of an unknown version
+    fn visit_wit(&mut self, _wit: &Wit) {}
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> Descriptor<Pk, Ext> {
+    /// Calls the method of `visitor` corresponding to the type of the
+    /// descriptor
+    pub fn accept<V: DescriptorVisitor<Pk, Ext>>(&self, visitor: &mut V) {
+        match *self {
+            Descriptor::Bare(ref bare) => visitor.visit_bare(bare),
+            Descriptor::Pkh(ref pkh) => visitor.visit_pkh(pkh),
+            Descriptor::Wpkh(ref wpkh) => visitor.visit_wpkh(wpkh),
+            Descriptor::Sh(ref sh) => visitor.visit_sh(sh),
+            Descriptor::Wsh(ref wsh) => visitor.visit_wsh(wsh),
+            Descriptor::Tr(ref tr) => visitor.visit_tr(tr),
+            Descriptor::TrExt(ref tr) => visitor.visit_tr_ext(tr),
+            Descriptor::LegacyCSFSCov(ref cov) => visitor.visit_cov(cov),
+            Descriptor::Wit(ref wit) => visitor.visit_wit(wit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::ForEachKey;
+
+    // Counts the keys of segwit v0 and covenant descriptors only
+    #[derive(Default)]
+    struct SegwitKeys(usize);
+
+    impl<Ext: Extension> DescriptorVisitor<String, Ext> for SegwitKeys {
+        fn visit_wpkh(&mut self, _wpkh: &Wpkh<String>) {
+            self.0 += 1;
+        }
+
+        fn visit_wsh(&mut self, wsh: &Wsh<String>) {
+            wsh.for_each_key(|_| {
+                self.0 += 1;
+                true
+            });
+        }
+
+        fn visit_cov(&mut self, cov: &LegacyCSFSCov<String, Ext>) {
+            cov.for_each_key(|_| {
+                self.0 += 1;
+                true
+            });
+        }
+    }
+
+    #[test]
+    fn visit_descriptors() {
+        let descs = [
+            "elwpkh(A)",
+            "elwsh(multi(2,B,C,D))",
+            "elcovwsh(E,pk(F))",
+            "elpkh(G)",
+            "eltr(H,pk(I))",
+            "elsh(wpkh(J))",
+        ];
+        let mut visitor = SegwitKeys::default();
+        for desc in descs.iter() {
+            let desc = Descriptor::<String>::from_str(desc).unwrap();
+            desc.accept(&mut visitor);
+        }
+        assert_eq!(visitor.0, 6);
+    }
+}
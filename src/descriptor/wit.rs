@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Witness Program Descriptors
+//!
+//! Implementation of the `elwit(VERSION,PROGRAM)` descriptor, describing a
+//! witness program of a version without any spending rules known to this
+//! crate, e.g. one introduced by a future Elements soft fork. Such outputs
+//! can be tracked by wallets, but not spent.
+//!
+
+use core::fmt;
+use core::str::FromStr;
+
+use elements::bech32::u5;
+use elements::hex::{FromHex, ToHex};
+use elements::{secp256k1_zkp, Script};
+
+use super::checksum::{self, verify_checksum};
+use super::ELMTS_STR;
+use crate::expression::{self, FromTree};
+use crate::Error;
+
+/// A witness program of version 1 to 16 which is not a taproot output
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Wit {
+    /// The witness version
+    version: u8,
+    /// The witness program
+    program: Vec<u8>,
+}
+
+impl Wit {
+    /// Create a new witness program descriptor
+    ///
+    /// # Errors
+    ///
+    /// If the version is not between 1 and 16, if the program is not between
+    /// 2 and 40 bytes long, or if it is a taproot output, i.e. a version 1
+    /// program of 32 bytes, which should be described with `eltr` instead.
+    pub fn new(version: u8, program: Vec<u8>) -> Result<Self, Error> {
+        if version == 0 || version > 16 {
+            return Err(Error::BadDescriptor(format!(
+                "Invalid witness version {}, segwit v0 outputs must use wpkh or wsh",
+                version
+            )));
+        }
+        if program.len() < 2 || program.len() > 40 {
+            return Err(Error::BadDescriptor(format!(
+                "Invalid witness program length {}",
+                program.len()
+            )));
+        }
+        if version == 1 && program.len() == 32 {
+            return Err(Error::BadDescriptor(
+                "Taproot witness programs must use tr".to_owned(),
+            ));
+        }
+        Ok(Self { version, program })
+    }
+
+    /// Get the witness version
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Get a reference to the witness program
+    pub fn program(&self) -> &[u8] {
+        &self.program
+    }
+
+    /// Obtains the corresponding script pubkey for this descriptor.
+    pub fn script_pubkey(&self) -> Script {
+        let version = u5::try_from_u8(self.version).expect("Version checked in constructor");
+        Script::new_witness_program(version, &self.program)
+    }
+
+    /// Obtains the corresponding address for this descriptor.
+    pub fn address(
+        &self,
+        blinder: Option<secp256k1_zkp::PublicKey>,
+        params: &'static elements::AddressParams,
+    ) -> elements::Address {
+        elements::Address::from_script(&self.script_pubkey(), blinder, params)
+            .expect("Witness programs have an address")
+    }
+
+    /// Length, in bytes, of the scriptPubKey
+    pub fn script_pubkey_len(&self) -> usize {
+        // <version> <push> <program>
+        2 + self.program.len()
+    }
+}
+
+impl fmt::Debug for Wit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}wit({},{})",
+            ELMTS_STR,
+            self.version,
+            self.program.to_hex()
+        )
+    }
+}
+
+impl fmt::Display for Wit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        let mut wrapped_f = checksum::Formatter::new(f);
+        write!(
+            wrapped_f,
+            "{}wit({},{})",
+            ELMTS_STR,
+            self.version,
+            self.program.to_hex()
+        )?;
+        wrapped_f.write_checksum_if_not_alt()
+    }
+}
+
+impl FromTree for Wit {
+    fn from_tree(top: &expression::Tree<'_>) -> Result<Self, Error> {
+        if top.name == "elwit" && top.args.len() == 2 {
+            let version = expression::terminal(&top.args[0], u8::from_str)?;
+            let program = expression::terminal(&top.args[1], Vec::<u8>::from_hex)?;
+            Wit::new(version, program)
+        } else {
+            Err(Error::Unexpected(format!(
+                "{}({} args) while parsing wit descriptor",
+                top.name,
+                top.args.len(),
+            )))
+        }
+    }
+}
+
+impl FromStr for Wit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let desc_str = verify_checksum(s)?;
+        let top = expression::Tree::from_str(desc_str)?;
+        Self::from_tree(&top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Descriptor;
+
+    #[test]
+    fn parse_wit() {
+        let desc = Descriptor::<String>::from_str("elwit(2,0001020304050607)").unwrap();
+        let wit = match desc {
+            Descriptor::Wit(ref wit) => wit.clone(),
+            _ => panic!("expected a witness program descriptor"),
+        };
+        assert_eq!(wit.version(), 2);
+        assert_eq!(
+            desc.to_string(),
+            Descriptor::<String>::from_str(&desc.to_string())
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            wit.script_pubkey().to_hex(),
+            "52080001020304050607".to_owned()
+        );
+        assert_eq!(desc.script_pubkey_len(), wit.script_pubkey().len());
+        assert!(desc.max_weight_to_satisfy().is_err());
+
+        let addr = wit.address(None, &elements::AddressParams::ELEMENTS);
+        assert_eq!(addr.script_pubkey(), wit.script_pubkey());
+
+        // segwit v0, taproot and invalid program lengths
+        assert!(Wit::from_str("elwit(0,0001020304050607)").is_err());
+        assert!(Wit::from_str("elwit(17,0001020304050607)").is_err());
+        assert!(Wit::new(1, vec![0; 32]).is_err());
+        assert!(Wit::new(1, vec![0; 33]).is_ok());
+        assert!(Wit::new(3, vec![0; 1]).is_err());
+        assert!(Wit::new(3, vec![0; 41]).is_err());
+    }
+}
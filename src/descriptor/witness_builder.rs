@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Witness Builder
+//!
+//! Assembly of the witness and the scriptSig of a satisfaction, shared by
+//! the `get_satisfaction` implementations of all the descriptors, see
+//! [`WitnessBuilder`].
+//!
+
+use std::marker::PhantomData;
+
+use bitcoin::taproot::TAPROOT_ANNEX_PREFIX;
+use elements::taproot::ControlBlock;
+use elements::{Script, TxInWitness};
+
+use crate::util::{witness_size, witness_to_scriptsig};
+use crate::{Error, NoExt, ScriptContext};
+
+/// Builds the witness of an input from the satisfaction of a script.
+///
+/// The elements of a witness always come in the same order: the
+/// satisfaction, then the witness script or the leaf script, then the
+/// control block for taproot script spends and finally the annex. The
+/// builder keeps them apart and emits them in this order, whatever the order
+/// in which they were added.
+///
+/// The satisfaction is checked against the limits of the script context
+/// `Ctx`, e.g. the number of standard P2WSH stack items or the size of a
+/// legacy scriptSig.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WitnessBuilder<Ctx: ScriptContext> {
+    stack: Vec<Vec<u8>>,
+    script: Option<Vec<u8>>,
+    control_block: Option<Vec<u8>>,
+    annex: Option<Vec<u8>>,
+    phantom: PhantomData<Ctx>,
+}
+
+impl<Ctx: ScriptContext> WitnessBuilder<Ctx> {
+    /// Creates a builder starting with the elements of `satisfaction`
+    pub fn new(satisfaction: Vec<Vec<u8>>) -> Self {
+        WitnessBuilder {
+            stack: satisfaction,
+            script: None,
+            control_block: None,
+            annex: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Pushes an element on top of the satisfaction
+    pub fn push(&mut self, elem: Vec<u8>) -> &mut Self {
+        self.stack.push(elem);
+        self
+    }
+
+    /// Sets the witness script, the redeem script or the tapscript leaf,
+    /// which follows the satisfaction
+    pub fn script(&mut self, script: &Script) -> &mut Self {
+        self.script = Some(script.to_bytes());
+        self
+    }
+
+    /// Sets the control block of a taproot script spend, which follows the
+    /// leaf script
+    pub fn control_block(&mut self, control_block: &ControlBlock) -> &mut Self {
+        self.control_block = Some(control_block.serialize());
+        self
+    }
+
+    /// Sets the taproot annex, which is the last element of the witness
+    ///
+    /// # Errors
+    ///
+    /// If the annex does not start with `0x50`.
+    pub fn annex(&mut self, annex: Vec<u8>) -> Result<&mut Self, Error> {
+        if annex.first() != Some(&TAPROOT_ANNEX_PREFIX) {
+            return Err(Error::InvalidTapAnnex);
+        }
+        self.annex = Some(annex);
+        Ok(self)
+    }
+
+    /// The size of the witness, including the varint of its length
+    pub fn witness_size(&self) -> usize {
+        witness_size(&self.elements())
+    }
+
+    fn elements(&self) -> Vec<Vec<u8>> {
+        let mut elements = self.stack.clone();
+        elements.extend(self.script.iter().cloned());
+        elements.extend(self.control_block.iter().cloned());
+        elements.extend(self.annex.iter().cloned());
+        elements
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.control_block.is_some() && self.script.is_none() {
+            return Err(Error::Unexpected(String::from(
+                "control block without a leaf script",
+            )));
+        }
+        Ctx::check_witness::<bitcoin::PublicKey, NoExt>(&self.stack)?;
+        Ok(())
+    }
+
+    /// Emits the witness stack, after checking the satisfaction against the
+    /// limits of its context
+    pub fn into_witness(self) -> Result<Vec<Vec<u8>>, Error> {
+        self.check()?;
+        Ok(self.elements())
+    }
+
+    /// Emits the witness of a transaction input, with no rangeproofs nor
+    /// pegin witness
+    pub fn into_txin_witness(self) -> Result<TxInWitness, Error> {
+        Ok(TxInWitness {
+            script_witness: self.into_witness()?,
+            ..Default::default()
+        })
+    }
+
+    /// Emits the elements as the pushes of a scriptSig, for bare and legacy
+    /// spends. The whole scriptSig is checked against the limits of the
+    /// context.
+    ///
+    /// # Errors
+    ///
+    /// If a control block or an annex was set, or if a limit is exceeded.
+    pub fn into_script_sig(self) -> Result<Script, Error> {
+        if self.control_block.is_some() || self.annex.is_some() {
+            return Err(Error::Unexpected(String::from(
+                "taproot witness elements in a scriptSig",
+            )));
+        }
+        let elements = self.elements();
+        Ctx::check_witness::<bitcoin::PublicKey, NoExt>(&elements)?;
+        Ok(witness_to_scriptsig(&elements))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miniscript::context::ScriptContextError;
+    use crate::{Legacy, Segwitv0, Tap};
+
+    #[test]
+    fn witness_order() {
+        let script = Script::from(vec![0x51]);
+        let control_block = ControlBlock::from_slice(&control_block_bytes()).unwrap();
+        let mut builder = WitnessBuilder::<Tap>::new(vec![vec![1]]);
+        builder
+            .annex(vec![TAPROOT_ANNEX_PREFIX, 2])
+            .unwrap()
+            .control_block(&control_block)
+            .script(&script)
+            .push(vec![3]);
+        assert_eq!(
+            builder.clone().into_witness().unwrap(),
+            vec![
+                vec![1],
+                vec![3],
+                vec![0x51],
+                control_block.serialize(),
+                vec![TAPROOT_ANNEX_PREFIX, 2]
+            ]
+        );
+        assert_eq!(
+            builder.clone().into_txin_witness().unwrap().script_witness,
+            builder.clone().into_witness().unwrap()
+        );
+        assert!(builder.into_script_sig().is_err());
+        assert!(matches!(
+            WitnessBuilder::<Tap>::new(vec![]).annex(vec![1]),
+            Err(Error::InvalidTapAnnex)
+        ));
+
+        // Context limits, and the redeem script of a legacy scriptSig is
+        // pushed like any other element
+        let mut builder = WitnessBuilder::<Segwitv0>::new(vec![vec![]; 101]);
+        builder.script(&script);
+        assert!(matches!(
+            builder.into_witness(),
+            Err(Error::ContextError(
+                ScriptContextError::MaxWitnessItemssExceeded { actual: 101, .. }
+            ))
+        ));
+        let mut builder = WitnessBuilder::<Legacy>::new(vec![vec![]]);
+        builder.script(&script);
+        assert_eq!(
+            builder.into_script_sig().unwrap(),
+            Script::from(vec![0x00, 0x01, 0x51])
+        );
+    }
+
+    // A control block for a leaf at depth 1
+    fn control_block_bytes() -> Vec<u8> {
+        let mut cb = vec![0xc4];
+        cb.extend_from_slice(&[
+            0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87,
+            0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b,
+            0x16, 0xf8, 0x17, 0x98,
+        ]);
+        cb.extend_from_slice(&[7; 32]);
+        cb
+    }
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Extended Public Key Cache
+//!
+//! Memoization of the intermediate BIP-32 derivations of extended public
+//! keys. The keys of a descriptor are typically derived at many indices from
+//! the same path, e.g. `xpub/0/*`, and the keys of large multisig
+//! descriptors often share prefixes, which this cache derives only once.
+//!
+
+use std::collections::HashMap;
+
+use bitcoin::bip32;
+use elements::secp256k1_zkp::{Secp256k1, Verification};
+
+/// Cache of the intermediate derivations of extended public keys, see
+/// [`crate::Descriptor::derive_public_cached`]
+///
+/// Every strict prefix of a derived path is cached, the last derivation
+/// step, usually the varying index of a wildcard, is not. The cache is
+/// never pruned: use one cache per descriptor, or [`XpubCache::clear`] it.
+#[derive(Clone, Debug, Default)]
+pub struct XpubCache {
+    derived: HashMap<(bip32::ExtendedPubKey, bip32::DerivationPath), bip32::ExtendedPubKey>,
+}
+
+impl XpubCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        XpubCache::default()
+    }
+
+    /// The number of cached derivations
+    pub fn len(&self) -> usize {
+        self.derived.len()
+    }
+
+    /// Whether no derivation is cached
+    pub fn is_empty(&self) -> bool {
+        self.derived.is_empty()
+    }
+
+    /// Removes all cached derivations
+    pub fn clear(&mut self) {
+        self.derived.clear()
+    }
+
+    /// Derives `xpub` at `path`, as [`bip32::ExtendedPubKey::derive_pub`]
+    /// does, reusing and caching the derivations of the prefixes of `path`
+    pub fn derive_pub<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        xpub: &bip32::ExtendedPubKey,
+        path: &bip32::DerivationPath,
+    ) -> Result<bip32::ExtendedPubKey, bip32::Error> {
+        let steps: &[bip32::ChildNumber] = path.as_ref();
+        let (last, prefix) = match steps.split_last() {
+            Some(split) => split,
+            None => return Ok(*xpub),
+        };
+
+        // The longest cached prefix
+        let mut cached = 0;
+        let mut parent = *xpub;
+        for len in (1..=prefix.len()).rev() {
+            let key = (*xpub, bip32::DerivationPath::from(&prefix[..len]));
+            if let Some(derived) = self.derived.get(&key) {
+                cached = len;
+                parent = *derived;
+                break;
+            }
+        }
+        for len in cached + 1..=prefix.len() {
+            parent = parent.ckd_pub(secp, prefix[len - 1])?;
+            self.derived
+                .insert((*xpub, bip32::DerivationPath::from(&prefix[..len])), parent);
+        }
+        parent.ckd_pub(secp, *last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn xpub_cache() {
+        let secp = Secp256k1::verification_only();
+        let xpub = bip32::ExtendedPubKey::from_str("xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL").unwrap();
+        let mut cache = XpubCache::new();
+        for path in ["m/0/1/2", "m/0/1/3", "m/0/4", "m", "m/5"].iter() {
+            let path = bip32::DerivationPath::from_str(path).unwrap();
+            assert_eq!(
+                cache.derive_pub(&secp, &xpub, &path).unwrap(),
+                xpub.derive_pub(&secp, &path).unwrap()
+            );
+        }
+        // m/0 and m/0/1
+        assert_eq!(cache.len(), 2);
+
+        let hardened = bip32::DerivationPath::from_str("m/0/1'/2").unwrap();
+        assert!(cache.derive_pub(&secp, &xpub, &hardened).is_err());
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let desc = crate::Descriptor::<crate::DescriptorPublicKey>::from_str(&format!(
+            "elwsh(multi(2,{0}/0/1/*,{0}/0/2/*))",
+            xpub
+        ))
+        .unwrap();
+        for index in 0..3 {
+            assert_eq!(
+                desc.derive_public_cached(&secp, index, &mut cache).unwrap(),
+                desc.derive_public(&secp, index).unwrap()
+            );
+        }
+        // m/0, m/0/1 and m/0/2
+        assert_eq!(cache.len(), 3);
+    }
+}
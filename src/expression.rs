@@ -217,6 +217,58 @@ impl<'a> Tree<'a> {
             Err(errstr(rem))
         }
     }
+
+    /// Formats the tree over multiple lines, indenting the arguments of every
+    /// expression by `indent` spaces. Expressions whose arguments are all
+    /// terminals are kept on a single line, and expressions without a name
+    /// are written with curly brackets, like the branches of a taproot tree.
+    ///
+    /// The whitespace must be removed with [`strip_whitespace`] before
+    /// parsing the result.
+    pub fn to_string_pretty(&self, indent: usize) -> String {
+        let mut s = String::new();
+        self.write_pretty(&mut s, indent, 0);
+        s
+    }
+
+    fn write_pretty(&self, s: &mut String, indent: usize, level: usize) {
+        s.push_str(self.name);
+        if self.args.is_empty() {
+            return;
+        }
+        let (open, close) = if self.name.is_empty() {
+            ('{', '}')
+        } else {
+            ('(', ')')
+        };
+        s.push(open);
+        if self.args.iter().all(|arg| arg.args.is_empty()) {
+            let names = self.args.iter().map(|arg| arg.name).collect::<Vec<_>>();
+            s.push_str(&names.join(","));
+        } else {
+            for (i, arg) in self.args.iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                s.push('\n');
+                s.push_str(&" ".repeat((level + 1) * indent));
+                arg.write_pretty(s, indent, level + 1);
+            }
+            s.push('\n');
+            s.push_str(&" ".repeat(level * indent));
+        }
+        s.push(close);
+    }
+}
+
+/// Removes the whitespace of an expression, such as the newlines and the
+/// indentation of [`Tree::to_string_pretty`].
+///
+/// Whitespace is never significant in an expression, but trees borrow the
+/// string they are parsed from, so that it has to be stripped before
+/// parsing.
+pub fn strip_whitespace(s: &str) -> String {
+    s.chars().filter(|ch| !ch.is_ascii_whitespace()).collect()
 }
 
 /// Parse a string as a u32, for timelocks or thresholds
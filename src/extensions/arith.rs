@@ -20,6 +20,7 @@ use crate::miniscript::limits::MAX_STANDARD_P2WSH_STACK_ITEM_SIZE;
 use crate::miniscript::satisfy::{Satisfaction, Witness};
 use crate::miniscript::types::extra_props::{OpLimits, TimelockInfo};
 use crate::miniscript::types::{Base, Correctness, Dissat, ExtData, Input, Malleability};
+use crate::policy::covenant::{Constraint, OutputConstraint};
 use crate::{
     expression, interpreter, miniscript, script_num_size, Error, Extension, Satisfier, ToPublicKey,
     TranslateExt,
@@ -435,14 +436,14 @@ impl Expr<CovExtArgs> {
                 let schnorr_sig_sl = sig.try_push().map_err(|_| EvalError::MalformedSig)?;
                 let schnorr_sig = secp256k1::schnorr::Signature::from_slice(schnorr_sig_sl)
                     .map_err(|_| EvalError::MalformedSig)?;
-                let secp = secp256k1::Secp256k1::verification_only();
-
                 if *timestamp < time_signed_u64 {
                     return Err(EvalError::TimestampInFuture);
                 }
 
-                if check_sig_price_oracle_1(&secp, &schnorr_sig, &x_only_pk, *timestamp, price_u64)
-                {
+                let valid = crate::with_secp(|secp| {
+                    check_sig_price_oracle_1(secp, &schnorr_sig, &x_only_pk, *timestamp, price_u64)
+                });
+                if valid {
                     let price_i64 =
                         u64::try_into(price_u64).map_err(|_| EvalError::PriceOverflow)?;
                     Ok(price_i64)
@@ -984,6 +985,27 @@ impl<T: ExtParam> Arith<T> {
             | ArithInner::Geq(x, y) => x.script_size + y.script_size + 1,
         }
     }
+
+    /// Number of price oracle terminals in the expression, each of which is
+    /// satisfied by a signature, a timestamp and a price from the witness
+    fn price_oracle_count(&self) -> usize {
+        let (x, y) = match &self.expr {
+            ArithInner::Eq(x, y)
+            | ArithInner::Lt(x, y)
+            | ArithInner::Leq(x, y)
+            | ArithInner::Gt(x, y)
+            | ArithInner::Geq(x, y) => (x, y),
+        };
+        x.iter_terminals()
+            .chain(y.iter_terminals())
+            .filter(|t| {
+                matches!(
+                    t,
+                    ExprInner::PriceOracle1(..) | ExprInner::PriceOracle1W(..)
+                )
+            })
+            .count()
+    }
 }
 
 impl Arith<CovExtArgs> {
@@ -1318,12 +1340,16 @@ impl<T: ExtParam> Extension for Arith<T> {
     }
 
     fn extra_prop(&self) -> ExtData {
+        // Each price oracle is satisfied by a 64 byte signature, an 8 byte
+        // timestamp and an 8 byte price, with their length prefixes
+        let oracles = self.price_oracle_count();
+        let oracle_sat_size = oracles * ((1 + 64) + (1 + 8) + (1 + 8));
         ExtData {
             pk_cost: self.script_size(), // 1 opcodes, 1 key push, msg, 1 msg push
             has_free_verify: false,
-            stack_elem_count_sat: Some(0),
+            stack_elem_count_sat: Some(3 * oracles),
             stack_elem_count_dissat: Some(0),
-            max_sat_size: Some((0, 0)),
+            max_sat_size: Some((oracle_sat_size, oracle_sat_size)),
             max_dissat_size: Some((0, 0)),
             timelock_info: TimelockInfo::default(),
             exec_stack_elem_count_sat: Some(self.depth()),
@@ -1361,6 +1387,38 @@ impl<T: ExtParam> Extension for Arith<T> {
         let inner = ArithInner::from_tree(&tree).map_err(|_| FromTokenIterError)?;
         Arith::new(inner).map_err(|_e| FromTokenIterError)
     }
+
+    fn cov_constraint(&self) -> Constraint {
+        // Only comparisons of an explicit output amount with a constant are
+        // understood. Normalize to `out_v(i) <op> c`, flipping the comparison
+        // when the constant is on the left.
+        let (i, min, max) = match self.inner() {
+            ArithInner::Eq(x, y) => match (x.as_inner(), y.as_inner()) {
+                (ExprInner::Output(i), ExprInner::Const(c))
+                | (ExprInner::Const(c), ExprInner::Output(i)) => (i, Some(*c), Some(*c)),
+                _ => return Constraint::Other(self.to_string()),
+            },
+            ArithInner::Lt(x, y) | ArithInner::Gt(y, x) => match (x.as_inner(), y.as_inner()) {
+                (ExprInner::Output(i), ExprInner::Const(c)) => (i, None, c.checked_sub(1)),
+                (ExprInner::Const(c), ExprInner::Output(i)) => (i, c.checked_add(1), None),
+                _ => return Constraint::Other(self.to_string()),
+            },
+            ArithInner::Leq(x, y) | ArithInner::Geq(y, x) => match (x.as_inner(), y.as_inner()) {
+                (ExprInner::Output(i), ExprInner::Const(c)) => (i, None, Some(*c)),
+                (ExprInner::Const(c), ExprInner::Output(i)) => (i, Some(*c), None),
+                _ => return Constraint::Other(self.to_string()),
+            },
+        };
+        let mut out = OutputConstraint::new(i.clone());
+        out.min_amount = min;
+        out.max_amount = max;
+        Constraint::OutputsTo(out)
+    }
+
+    fn sig_ops(&self) -> usize {
+        // Each price oracle checks the signature of the oracle
+        self.price_oracle_count()
+    }
 }
 
 impl ParseableExt for Arith<CovExtArgs> {
@@ -1451,6 +1509,8 @@ pub enum EvalError {
     NonExplicitInputIssuance(usize),
     /// Input reissuance at index must be explicit
     NonExplicitInputReIssuance(usize),
+    /// Input does not have an asset issuance
+    MissingInputIssuance(usize),
     /// Addition overflow
     AddOverflow(i64, i64),
     /// Addition overflow
@@ -1481,6 +1541,8 @@ pub enum EvalError {
     InvalidSignature,
     /// Price overflow
     PriceOverflow,
+    /// The secrets of the utxo at index do not open its commitments
+    InvalidUtxoSecrets(usize),
 }
 
 impl error::Error for EvalError {}
@@ -1507,6 +1569,9 @@ impl fmt::Display for EvalError {
             EvalError::NonExplicitInputReIssuance(i) => {
                 write!(f, "Non explicit input reissuance amount at index {}", i)
             }
+            EvalError::MissingInputIssuance(i) => {
+                write!(f, "Input at index {} has no asset issuance", i)
+            }
             EvalError::AddOverflow(x, y) => write!(f, "Add overflow {} {}", x, y),
             EvalError::SubOverflow(x, y) => write!(f, "Sub overflow {} {}", x, y),
             EvalError::MulOverflow(x, y) => write!(f, "Mul overflow {} {}", x, y),
@@ -1526,6 +1591,9 @@ impl fmt::Display for EvalError {
             EvalError::TimestampInFuture => write!(f, "Oracle Timestamp in future"),
             EvalError::InvalidSignature => write!(f, "Invalid price oracle signature"),
             EvalError::PriceOverflow => write!(f, "Price overflow (must be 64 bit integer)"),
+            EvalError::InvalidUtxoSecrets(i) => {
+                write!(f, "Secrets do not open the commitments of utxo {}", i)
+            }
         }
     }
 }
@@ -1720,6 +1788,35 @@ mod tests {
         assert_eq!(ms, MsExt::parse_insane(&ms.encode()).unwrap());
     }
 
+    #[test]
+    fn price_oracle_sat_weight() {
+        type MsExtStr = Miniscript<String, Tap, CovenantExt<String>>;
+        // Signature, timestamp and price, with their length prefixes
+        let oracle_sat_size = 65 + 9 + 9;
+
+        let ms = MsExtStr::from_str_insane("num64_eq(inp_v(0),out_v(0))").unwrap();
+        assert_eq!(ms.max_satisfaction_size().unwrap(), 0);
+        let ms = MsExtStr::from_str_insane(
+            "num64_eq(price_oracle1(K,123213),price_oracle1_w(K,4318743))",
+        )
+        .unwrap();
+        assert_eq!(ms.max_satisfaction_size().unwrap(), 2 * oracle_sat_size);
+        assert_eq!(ms.max_satisfaction_witness_elements().unwrap(), 2 * 3 + 1);
+
+        // The oracle data is accounted for by the script spends of `tr`,
+        // along with the 41 bytes of script the oracle adds over a constant
+        let leaf = |ms: &str| {
+            crate::Descriptor::<String, CovenantExt<String>>::from_str(&format!("eltr(A,{})", ms))
+                .unwrap()
+                .max_weight_to_satisfy()
+                .unwrap()
+        };
+        assert_eq!(
+            leaf("and_v(v:pk(B),num64_eq(price_oracle1(K,123213),28004))"),
+            leaf("and_v(v:pk(B),num64_eq(28004,28004))") + oracle_sat_size + 41
+        );
+    }
+
     #[test]
     fn test_fuji_fixed_signs() {
         // Test Vector obtained from curl queries
@@ -73,8 +73,8 @@ impl<T: ExtParam> Extension for CheckSigFromStack<T> {
             has_free_verify: true,        // free verify form. Checksigfromstack verify
             stack_elem_count_sat: Some(1),
             stack_elem_count_dissat: Some(1), // supply empty signature for dissatisfaction
-            max_sat_size: Some((64, 64)),
-            max_dissat_size: Some((1, 1)), // empty sig
+            max_sat_size: Some((1 + 64, 1 + 64)), // length prefix and signature
+            max_dissat_size: Some((1, 1)),    // empty sig
             timelock_info: TimelockInfo::default(),
             exec_stack_elem_count_sat: Some(1),
             exec_stack_elem_count_dissat: Some(1),
@@ -98,6 +98,10 @@ impl<T: ExtParam> Extension for CheckSigFromStack<T> {
         ))
     }
 
+    fn sig_ops(&self) -> usize {
+        1
+    }
+
     fn from_name_tree(
         name: &str,
         children: &[expression::Tree<'_>],
@@ -322,9 +326,7 @@ impl ParseableExt for CheckSigFromStack<CovExtArgs> {
         // The interpreter will error on non 32 byte messages till it is fixed.
         let msg = secp256k1_zkp::Message::from_slice(&self.as_msg().0)?;
 
-        let secp = secp256k1_zkp::Secp256k1::verification_only();
-
-        secp.verify_schnorr(&sig, &msg, self.as_pk())?;
+        crate::with_secp(|secp| secp.verify_schnorr(&sig, &msg, self.as_pk()))?;
         Ok(true)
     }
 }
@@ -20,6 +20,7 @@ use crate::miniscript::lex::{Token as Tk, TokenIter};
 use crate::miniscript::satisfy::{Satisfaction, Witness};
 use crate::miniscript::types::extra_props::{OpLimits, TimelockInfo};
 use crate::miniscript::types::{Base, Correctness, Dissat, ExtData, Input, Malleability};
+use crate::policy::covenant::{Constraint, OutputConstraint};
 use crate::{
     expression, interpreter, script_num_size, Error, ExtTranslator, Extension, Satisfier,
     ToPublicKey, TranslateExt,
@@ -122,6 +123,12 @@ pub enum CovOps<T: ExtParam> {
     /// Index equality
     /// `[X] [Y] EQUAL`
     IdxEq(IdxExpr, IdxExpr),
+    /// Checks that the issuance amount of the current input equals the value (maybe confidential)
+    /// `PUSHCURRENTINPUTINDEX INSPECTINPUTISSUANCE DROP DROP TOALTSTACK NIP NIP [Y] FROMALTSTACK EQUAL TOALTSTACK EQUAL FROMALTSTACK BOOLAND`
+    IssuanceEq(ValueExpr<T>),
+    /// Checks that the reissuance token amount of the current input equals the value (maybe confidential)
+    /// `PUSHCURRENTINPUTINDEX INSPECTINPUTISSUANCE DROP DROP DROP DROP TOALTSTACK [Y] FROMALTSTACK EQUAL TOALTSTACK EQUAL FROMALTSTACK BOOLAND`
+    ReissuanceTokenEq(ValueExpr<T>),
 }
 
 impl<T: ExtParam> AssetExpr<T> {
@@ -350,6 +357,8 @@ impl<T: ExtParam> fmt::Display for CovOps<T> {
             CovOps::SpkEq(a, b) => write!(f, "spk_eq({},{})", a, b),
             CovOps::CurrIndEq(i) => write!(f, "curr_idx_eq({})", i),
             CovOps::IdxEq(a, b) => write!(f, "idx_eq({},{})", a, b),
+            CovOps::IssuanceEq(v) => write!(f, "issuance_eq({})", v),
+            CovOps::ReissuanceTokenEq(v) => write!(f, "reissuance_token_eq({})", v),
         }
     }
 }
@@ -364,6 +373,8 @@ impl<T: ExtParam> fmt::Debug for CovOps<T> {
             CovOps::SpkEq(a, b) => write!(f, "spk_eq({:?},{:?})", a, b),
             CovOps::CurrIndEq(i) => write!(f, "curr_idx_eq({:?})", i),
             CovOps::IdxEq(a, b) => write!(f, "idx_eq({},{})", a, b),
+            CovOps::IssuanceEq(v) => write!(f, "issuance_eq({:?})", v),
+            CovOps::ReissuanceTokenEq(v) => write!(f, "reissuance_token_eq({:?})", v),
         }
     }
 }
@@ -410,6 +421,11 @@ impl<T: ExtParam> FromTree for CovOps<T> {
                 let r = IdxExpr::from_tree(&top.args[1])?;
                 Ok(CovOps::IdxEq(l, r))
             }
+            ("issuance_eq", 1) => {
+                ValueExpr::from_tree_parent(&top.args[0], top.name, 0).map(CovOps::IssuanceEq)
+            }
+            ("reissuance_token_eq", 1) => ValueExpr::from_tree_parent(&top.args[0], top.name, 0)
+                .map(CovOps::ReissuanceTokenEq),
             _ => Err(Error::Unexpected(format!(
                 "{}({} args) while parsing Extension",
                 top.name,
@@ -419,6 +435,16 @@ impl<T: ExtParam> FromTree for CovOps<T> {
     }
 }
 
+impl<T: ExtParam> CovOps<T> {
+    // The maximum number of elements on the stack during the execution
+    fn exec_stack_elem_count(&self) -> usize {
+        match self {
+            CovOps::IssuanceEq(..) | CovOps::ReissuanceTokenEq(..) => 6,
+            _ => 4,
+        }
+    }
+}
+
 impl<T: ExtParam> Extension for CovOps<T> {
     fn corr_prop(&self) -> Correctness {
         Correctness {
@@ -446,8 +472,10 @@ impl<T: ExtParam> Extension for CovOps<T> {
             max_sat_size: Some((0, 0)),
             max_dissat_size: Some((0, 0)),
             timelock_info: TimelockInfo::default(),
-            exec_stack_elem_count_sat: Some(4), // There is composition in asset/value/spk expressions. Only max 4 depth with asset expressions
-            exec_stack_elem_count_dissat: Some(4),
+            // There is composition in asset/value/spk expressions. Only max 4 depth with asset
+            // expressions, but INSPECTINPUTISSUANCE pushes 6 elements
+            exec_stack_elem_count_sat: Some(self.exec_stack_elem_count()),
+            exec_stack_elem_count_dissat: Some(self.exec_stack_elem_count()),
             ops: OpLimits {
                 // Opcodes are really not relevant in tapscript as BIP342 removes all rules on them
                 // So, don't make any effort in trying to compute and cache them.
@@ -467,6 +495,8 @@ impl<T: ExtParam> Extension for CovOps<T> {
             CovOps::SpkEq(a, b) => a.script_size() + b.script_size() + 7,
             CovOps::CurrIndEq(i) => script_num_size(*i) + 2,
             CovOps::IdxEq(a, b) => a.script_size() + b.script_size() + 1,
+            CovOps::IssuanceEq(v) => v.script_size() + 13,
+            CovOps::ReissuanceTokenEq(v) => v.script_size() + 13,
         }
     }
 
@@ -485,6 +515,33 @@ impl<T: ExtParam> Extension for CovOps<T> {
             "Introspection opcodes only available in Taproot".to_string(),
         ))
     }
+
+    fn cov_constraint(&self) -> Constraint {
+        // Only equality of an output with a constant is understood, the
+        // constant may be on either side of the comparison.
+        let out = match self {
+            CovOps::AssetEq(AssetExpr::Output(i), AssetExpr::Const(c))
+            | CovOps::AssetEq(AssetExpr::Const(c), AssetExpr::Output(i)) => {
+                let mut out = OutputConstraint::new(i.clone());
+                out.asset = Some(c.to_string());
+                out
+            }
+            CovOps::ValueEq(ValueExpr::Output(i), ValueExpr::Const(c))
+            | CovOps::ValueEq(ValueExpr::Const(c), ValueExpr::Output(i)) => {
+                let mut out = OutputConstraint::new(i.clone());
+                out.value = Some(c.to_string());
+                out
+            }
+            CovOps::SpkEq(SpkExpr::Output(i), SpkExpr::Const(c))
+            | CovOps::SpkEq(SpkExpr::Const(c), SpkExpr::Output(i)) => {
+                let mut out = OutputConstraint::new(i.clone());
+                out.spk = Some(c.to_string());
+                out
+            }
+            _ => return Constraint::Other(self.to_string()),
+        };
+        Constraint::OutputsTo(out)
+    }
 }
 
 impl<PArg, QArg> TranslateExt<CovOps<PArg>, CovOps<QArg>> for CovOps<PArg>
@@ -567,19 +624,38 @@ impl ArgFromStr for confidential::Asset {
             ));
         }
         let asset_hex = Vec::<u8>::from_hex(s).map_err(|e| Error::Unexpected(e.to_string()))?;
-        elements::encode::deserialize(&asset_hex).map_err(|e| Error::Unexpected(e.to_string()))
+        match elements::encode::deserialize(&asset_hex) {
+            // Null assets cannot be pushed on the stack
+            Ok(confidential::Asset::Null) => Err(Error::Unexpected(
+                "null asset, expected an explicit asset or a commitment".to_string(),
+            )),
+            Ok(asset) => Ok(asset),
+            Err(e) => Err(Error::Unexpected(e.to_string())),
+        }
     }
 }
 
 impl ArgFromStr for confidential::Value {
     fn arg_from_str(s: &str, parent: &str, _pos: usize) -> Result<Self, Error> {
-        if parent != "value_eq" && parent != "is_exp_value" {
+        if parent != "value_eq"
+            && parent != "is_exp_value"
+            && parent != "issuance_eq"
+            && parent != "reissuance_token_eq"
+        {
             return Err(Error::Unexpected(
-                "value expressions only allowed inside value_eq and is_exp_value".to_string(),
+                "value expressions only allowed inside value_eq, is_exp_value, issuance_eq and reissuance_token_eq".to_string(),
             ));
         }
-        let asset_hex = Vec::<u8>::from_hex(s).map_err(|e| Error::Unexpected(e.to_string()))?;
-        elements::encode::deserialize(&asset_hex).map_err(|e| Error::Unexpected(e.to_string()))
+        let value_hex = Vec::<u8>::from_hex(s).map_err(|e| Error::Unexpected(e.to_string()))?;
+        match elements::encode::deserialize(&value_hex) {
+            // Null values are pushed as explicit zero values, which would not
+            // survive a script round trip
+            Ok(confidential::Value::Null) => Err(Error::Unexpected(
+                "null value, expected an explicit value or a commitment".to_string(),
+            )),
+            Ok(value) => Ok(value),
+            Err(e) => Err(Error::Unexpected(e.to_string())),
+        }
     }
 }
 
@@ -652,6 +728,29 @@ fn spk_to_components(s: &elements::Script) -> (i8, Vec<u8>) {
     }
 }
 
+// Internal helper function to get the asset issuance of the current input
+fn curr_inp_issuance(env: &TxEnv) -> Result<&elements::AssetIssuance, EvalError> {
+    let txin = env
+        .tx()
+        .input
+        .get(env.idx())
+        .ok_or_else(|| EvalError::InputIndexOutOfBounds(env.idx(), env.tx().input.len()))?;
+    if txin.has_issuance() {
+        Ok(&txin.asset_issuance)
+    } else {
+        Err(EvalError::MissingInputIssuance(env.idx()))
+    }
+}
+
+// Internal helper function to treat null amounts as explicit 0 values, the
+// way they are pushed on the stack by the introspection opcodes
+fn null_as_zero(v: confidential::Value) -> confidential::Value {
+    match v {
+        confidential::Value::Null => confidential::Value::Explicit(0),
+        v => v,
+    }
+}
+
 impl AssetExpr<CovExtArgs> {
     /// Push this script to builder
     /// Panics when trying to push a Null asset. This never occur in honest use-cases
@@ -987,6 +1086,32 @@ impl CovOps<CovExtArgs> {
                 let builder = y.push_to_builder(builder);
                 builder.push_opcode(OP_EQUAL)
             }
+            CovOps::IssuanceEq(y) => {
+                // INSPECTINPUTISSUANCE pushes [token] [pref] [amount] [pref] [entropy] [nonce],
+                // with the nonce on top. Keep the amount prefix on the altstack, same as ValueEq.
+                let builder = builder
+                    .push_opcode(OP_PUSHCURRENTINPUTINDEX)
+                    .push_opcode(OP_INSPECTINPUTISSUANCE)
+                    .push_opcode(OP_DROP)
+                    .push_opcode(OP_DROP)
+                    .push_opcode(OP_TOALTSTACK)
+                    .push_opcode(OP_NIP)
+                    .push_opcode(OP_NIP);
+                Self::push_value_eq_tail(y, builder)
+            }
+            CovOps::ReissuanceTokenEq(y) => {
+                // The token amount is at the bottom of the pushed elements,
+                // below its prefix.
+                let builder = builder
+                    .push_opcode(OP_PUSHCURRENTINPUTINDEX)
+                    .push_opcode(OP_INSPECTINPUTISSUANCE)
+                    .push_opcode(OP_DROP)
+                    .push_opcode(OP_DROP)
+                    .push_opcode(OP_DROP)
+                    .push_opcode(OP_DROP)
+                    .push_opcode(OP_TOALTSTACK);
+                Self::push_value_eq_tail(y, builder)
+            }
         }
     }
 
@@ -1000,9 +1125,64 @@ impl CovOps<CovExtArgs> {
             CovOps::SpkEq(x, y) => Ok(x.eval(env)? == y.eval(env)?),
             CovOps::CurrIndEq(i) => Ok(*i == env.idx()),
             CovOps::IdxEq(x, y) => Ok(x.eval(env)? == y.eval(env)?),
+            CovOps::IssuanceEq(y) => {
+                let issuance = curr_inp_issuance(env)?;
+                Ok(null_as_zero(issuance.amount) == null_as_zero(y.eval(env)?))
+            }
+            CovOps::ReissuanceTokenEq(y) => {
+                let issuance = curr_inp_issuance(env)?;
+                Ok(null_as_zero(issuance.inflation_keys) == null_as_zero(y.eval(env)?))
+            }
+        }
+    }
+
+    // Whether the operands of `asset_eq` or `value_eq` only differ by their
+    // blinding, according to the blinding factors known to the satisfier.
+    // The introspection opcodes compare the commitments, so that no witness
+    // can satisfy the fragment in that case.
+    fn differ_by_blinding<Pk, S>(&self, env: &TxEnv, sat: &S) -> bool
+    where
+        Pk: ToPublicKey,
+        S: Satisfier<Pk>,
+    {
+        let open_asset = |a: confidential::Asset| match a {
+            Asset::Explicit(id) => Some(id),
+            Asset::Confidential(_) => sat.lookup_asset_blinding(&a).map(|(id, _)| id),
+            Asset::Null => None,
+        };
+        let open_value = |v: confidential::Value| match null_as_zero(v) {
+            confidential::Value::Explicit(v) => Some(v),
+            v => sat.lookup_value_blinding(&v).map(|(v, _)| v),
+        };
+        match self {
+            CovOps::AssetEq(x, y) => match (x.eval(env), y.eval(env)) {
+                (Ok(x), Ok(y)) => {
+                    x != y && open_asset(x).is_some() && open_asset(x) == open_asset(y)
+                }
+                _ => false,
+            },
+            CovOps::ValueEq(x, y) => match (x.eval(env), y.eval(env)) {
+                (Ok(x), Ok(y)) => {
+                    x != y && open_value(x).is_some() && open_value(x) == open_value(y)
+                }
+                _ => false,
+            },
+            _ => false,
         }
     }
 
+    // Pushes the value `y` and compares it with the value on the altstack
+    // and the commitment below the stack top.
+    fn push_value_eq_tail(y: &ValueExpr<CovExtArgs>, builder: script::Builder) -> script::Builder {
+        y.push_to_builder(builder)
+            .push_opcode(OP_FROMALTSTACK)
+            .push_opcode(OP_EQUAL)
+            .push_opcode(OP_TOALTSTACK)
+            .push_opcode(OP_EQUAL)
+            .push_opcode(OP_FROMALTSTACK)
+            .push_opcode(OP_BOOLAND)
+    }
+
     /// Returns (self, start_pos) parsed reversed form tokens starting with index end_pos
     /// Expression is parsed from tokens`[start:end_pos]`
     pub fn from_tokens(tks: &[Tk]) -> Option<(Self, usize)> {
@@ -1041,6 +1221,18 @@ impl CovOps<CovExtArgs> {
                 return res;
             }
             let res = if let Some((y, e)) = ValueExpr::from_tokens(tks, e - 6) {
+                let issuance = e.checked_sub(7).and_then(|s| tks.get(s..e));
+                if let Some(
+                    &[Tk::CurrInp, Tk::InpIssue, Tk::Drop, Tk::Drop, Tk::ToAltStack, Tk::Nip, Tk::Nip],
+                ) = issuance
+                {
+                    return Some((CovOps::IssuanceEq(y), e - 7));
+                } else if let Some(
+                    &[Tk::CurrInp, Tk::InpIssue, Tk::Drop, Tk::Drop, Tk::Drop, Tk::Drop, Tk::ToAltStack],
+                ) = issuance
+                {
+                    return Some((CovOps::ReissuanceTokenEq(y), e - 7));
+                }
                 if tks.get(e - 1) != Some(&Tk::ToAltStack) {
                     return None;
                 }
@@ -1101,6 +1293,7 @@ impl ParseableExt for CovOps<CovExtArgs> {
             }
         };
         let wit = match self.eval(&env) {
+            Ok(false) if self.differ_by_blinding::<Pk, _>(&env, sat) => Witness::Impossible,
             Ok(false) => Witness::Unavailable,
             Ok(true) => Witness::empty(),
             Err(_e) => Witness::Impossible,
@@ -1209,6 +1402,8 @@ where
             CovOps::SpkEq(x, y) => Ok(CovOps::SpkEq(x._translate_ext(t)?, y._translate_ext(t)?)),
             CovOps::CurrIndEq(i) => Ok(CovOps::CurrIndEq(*i)),
             CovOps::IdxEq(x, y) => Ok(CovOps::IdxEq(x.clone(), y.clone())),
+            CovOps::IssuanceEq(v) => Ok(CovOps::IssuanceEq(v._translate_ext(t)?)),
+            CovOps::ReissuanceTokenEq(v) => Ok(CovOps::ReissuanceTokenEq(v._translate_ext(t)?)),
         }
     }
 }
@@ -1280,6 +1475,13 @@ mod tests {
         );
         _test_parse("and_v(v:pk(K),and_v(v:value_eq(ConfVal,ConfVal),spk_eq(V1Spk,V1Spk)))");
         _test_parse("and_v(v:pk(K),and_v(v:value_eq(ConfVal,ConfVal),and_v(v:spk_eq(V1Spk,V1Spk),curr_idx_eq(1))))");
+
+        // issuance of the current input
+        _test_parse("issuance_eq(ExpVal)");
+        _test_parse("issuance_eq(ConfVal)");
+        _test_parse("reissuance_token_eq(ExpVal)");
+        _test_parse("reissuance_token_eq(out_value(1))");
+        _test_parse("and_v(v:issuance_eq(curr_inp_value),reissuance_token_eq(inp_value(2)))");
     }
 
     #[test]
@@ -1290,6 +1492,260 @@ mod tests {
         MsExt::from_str_insane("asset_eq(out_asset(0),0179d51a47e4ac8e32306486dd0926a88678c392f2ed5f213e3ff2ad461c7c25e1)").unwrap();
         // 32 bytes explicit asset without prefix fails
         MsExt::from_str_insane("asset_eq(out_asset(0),79d51a47e4ac8e32306486dd0926a88678c392f2ed5f213e3ff2ad461c7c25e1)").unwrap_err();
+
+        // Commitments round trip, null assets and values fail
+        for s in [
+            "asset_eq(out_asset(0),0adef814ab021498562ab4717287305d3f7abb5686832fe6183e1db495abef7cc7)",
+            "value_eq(out_value(0),09def814ab021498562ab4717287305d3f7abb5686832fe6183e1db495abef7cc7)",
+            "value_eq(out_value(0),010000000011110000)",
+        ].iter() {
+            let ms = MsExt::from_str_insane(s).unwrap();
+            assert_eq!(ms.to_string(), *s);
+            assert_eq!(MsExt::parse_insane(&ms.encode()).unwrap(), ms);
+        }
+        MsExt::from_str_insane("asset_eq(out_asset(0),00)").unwrap_err();
+        MsExt::from_str_insane("value_eq(out_value(0),00)").unwrap_err();
+        // Invalid commitment
+        MsExt::from_str_insane("value_eq(out_value(0),090000000000000000000000000000000000000000000000000000000000000000)").unwrap_err();
+    }
+
+    // Knows the openings of a single asset commitment
+    struct AssetOpening(confidential::Asset, elements::AssetId);
+
+    impl Satisfier<XOnlyPublicKey> for AssetOpening {
+        fn lookup_asset_blinding(
+            &self,
+            asset: &confidential::Asset,
+        ) -> Option<(elements::AssetId, confidential::AssetBlindingFactor)> {
+            if *asset == self.0 {
+                Some((self.1, confidential::AssetBlindingFactor::zero()))
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn satisfy_blinded_asset_eq() {
+        let secp = elements::secp256k1_zkp::Secp256k1::new();
+        let id = elements::AssetId::from_slice(&[7; 32]).unwrap();
+        let abf = confidential::AssetBlindingFactor::from_slice(&[1; 32]).unwrap();
+        let blinded = confidential::Asset::new_confidential(&secp, id, abf);
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: elements::LockTime::ZERO,
+            input: vec![elements::TxIn::default()],
+            output: vec![elements::TxOut {
+                asset: blinded,
+                ..Default::default()
+            }],
+        };
+        let utxos = [elements::TxOut {
+            asset: confidential::Asset::Explicit(id),
+            ..Default::default()
+        }];
+        let env = TxEnv::new(&tx, &utxos, 0).unwrap();
+        let cov = CovOps::AssetEq(
+            AssetExpr::CurrInputAsset,
+            AssetExpr::Output(IdxExpr::Const(0)),
+        );
+
+        // Without the blinding factors the asset may differ
+        let sat = ParseableExt::satisfy::<XOnlyPublicKey, _>(&cov, &env);
+        assert_eq!(sat.stack, Witness::Unavailable);
+        // The same asset with a different blinding can never be equal
+        let sat =
+            ParseableExt::satisfy::<XOnlyPublicKey, _>(&cov, &(&env, AssetOpening(blinded, id)));
+        assert_eq!(sat.stack, Witness::Impossible);
+        let other = elements::AssetId::from_slice(&[8; 32]).unwrap();
+        let sat =
+            ParseableExt::satisfy::<XOnlyPublicKey, _>(&cov, &(&env, AssetOpening(blinded, other)));
+        assert_eq!(sat.stack, Witness::Unavailable);
+    }
+
+    #[test]
+    fn satisfy_with_utxo_secrets() {
+        let secp = elements::secp256k1_zkp::Secp256k1::new();
+        let id = elements::AssetId::from_slice(&[7; 32]).unwrap();
+        let abf = confidential::AssetBlindingFactor::from_slice(&[1; 32]).unwrap();
+        let vbf = confidential::ValueBlindingFactor::from_slice(&[2; 32]).unwrap();
+        let blinded = confidential::Asset::new_confidential(&secp, id, abf);
+        let value =
+            confidential::Value::new_confidential(&secp, 1_000, blinded.commitment().unwrap(), vbf);
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: elements::LockTime::ZERO,
+            input: vec![elements::TxIn::default()],
+            output: vec![elements::TxOut {
+                asset: confidential::Asset::Explicit(id),
+                value: confidential::Value::Explicit(1_000),
+                ..Default::default()
+            }],
+        };
+        let utxos = [elements::TxOut {
+            asset: blinded,
+            value,
+            ..Default::default()
+        }];
+        let secrets = elements::TxOutSecrets::new(id, abf, 1_000, vbf);
+        let env = TxEnv::new(&tx, &utxos, 0).unwrap();
+
+        let wrong = elements::TxOutSecrets::new(id, abf, 999, vbf);
+        assert!(matches!(
+            env.clone().with_spent_utxo_secrets(0, wrong),
+            Err(EvalError::InvalidUtxoSecrets(0))
+        ));
+        assert!(matches!(
+            env.clone().with_spent_utxo_secrets(1, secrets),
+            Err(EvalError::UtxoIndexOutOfBounds(1, 1))
+        ));
+
+        // The blinded input can never be equal to the explicit output
+        let asset_eq = CovOps::AssetEq(
+            AssetExpr::CurrInputAsset,
+            AssetExpr::Output(IdxExpr::Const(0)),
+        );
+        let value_eq = CovOps::ValueEq(
+            ValueExpr::CurrInputValue,
+            ValueExpr::Output(IdxExpr::Const(0)),
+        );
+        let covs = [asset_eq, value_eq];
+        for cov in covs.iter() {
+            let sat = ParseableExt::satisfy::<XOnlyPublicKey, _>(cov, &env);
+            assert_eq!(sat.stack, Witness::Unavailable);
+        }
+        let env = env.with_spent_utxo_secrets(0, secrets).unwrap();
+        assert_eq!(env.spent_utxo_secrets(0), Some(&secrets));
+        for cov in covs.iter() {
+            let sat = ParseableExt::satisfy::<XOnlyPublicKey, _>(cov, &env);
+            assert_eq!(sat.stack, Witness::Impossible);
+        }
+    }
+
+    #[test]
+    fn issuance_script_matches_eval() {
+        use elements::script::Instruction;
+        use elements::secp256k1_zkp::Tweak;
+        use elements::taproot::LeafVersion;
+
+        use crate::descriptor::Tr;
+        use crate::Interpreter;
+
+        // Runs the script of a fragment on the stack pushed by the
+        // introspection opcodes: the token amount, its prefix, the amount,
+        // its prefix, the entropy and the nonce on top.
+        fn exec(script: &elements::Script, env: &TxEnv) -> bool {
+            let push_value =
+                |stack: &mut Vec<Vec<u8>>, v: confidential::Value| match null_as_zero(v) {
+                    confidential::Value::Explicit(v) => {
+                        stack.push(v.to_le_bytes().to_vec());
+                        stack.push(vec![1]);
+                    }
+                    v => {
+                        let ser = encode::serialize(&v);
+                        stack.push(ser[1..].to_vec());
+                        stack.push(vec![ser[0]]);
+                    }
+                };
+            let (mut stack, mut alt) = (Vec::<Vec<u8>>::new(), Vec::new());
+            for ins in script.instructions_minimal() {
+                match ins.unwrap() {
+                    Instruction::PushBytes(b) => stack.push(b.to_vec()),
+                    Instruction::Op(op) => match op.into_u8() {
+                        n @ 0x51..=0x60 => stack.push(vec![n - 0x50]),
+                        _ if op == OP_PUSHCURRENTINPUTINDEX => stack.push(vec![env.idx() as u8]),
+                        _ if op == OP_INSPECTINPUTISSUANCE => {
+                            stack.pop().unwrap();
+                            let issuance = env.tx().input[env.idx()].asset_issuance;
+                            push_value(&mut stack, issuance.inflation_keys);
+                            push_value(&mut stack, issuance.amount);
+                            stack.push(issuance.asset_entropy.to_vec());
+                            stack.push(issuance.asset_blinding_nonce.as_ref().to_vec());
+                        }
+                        _ if op == OP_DROP => {
+                            stack.pop().unwrap();
+                        }
+                        _ if op == OP_NIP => {
+                            let top = stack.pop().unwrap();
+                            *stack.last_mut().unwrap() = top;
+                        }
+                        _ if op == OP_TOALTSTACK => alt.push(stack.pop().unwrap()),
+                        _ if op == OP_FROMALTSTACK => stack.push(alt.pop().unwrap()),
+                        _ if op == OP_EQUAL => {
+                            let (x, y) = (stack.pop().unwrap(), stack.pop().unwrap());
+                            stack.push(if x == y { vec![1] } else { vec![] });
+                        }
+                        _ if op == OP_BOOLAND => {
+                            let (x, y) = (stack.pop().unwrap(), stack.pop().unwrap());
+                            let both = !x.is_empty() && !y.is_empty();
+                            stack.push(if both { vec![1] } else { vec![] });
+                        }
+                        _ => panic!("unexpected opcode {}", op),
+                    },
+                }
+            }
+            assert!(alt.is_empty());
+            stack == vec![vec![1]]
+        }
+
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: elements::LockTime::ZERO,
+            input: vec![elements::TxIn {
+                asset_issuance: elements::AssetIssuance {
+                    asset_blinding_nonce: Tweak::from_slice(&[3; 32]).unwrap(),
+                    asset_entropy: [4; 32],
+                    amount: confidential::Value::Explicit(1_000),
+                    inflation_keys: confidential::Value::Explicit(100),
+                },
+                ..Default::default()
+            }],
+            output: vec![],
+        };
+        let key = "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115";
+        let values = [1_000, 100, 7];
+        for &v in values.iter() {
+            let y = ValueExpr::Const(CovExtArgs::Value(confidential::Value::Explicit(v)));
+            let utxos = [elements::TxOut::default()];
+            let env = TxEnv::new(&tx, &utxos, 0).unwrap();
+            for cov in [CovOps::IssuanceEq(y.clone()), CovOps::ReissuanceTokenEq(y)].iter() {
+                let script = cov.push_to_builder(script::Builder::new()).into_script();
+                assert_eq!(exec(&script, &env), cov.eval(&env).unwrap(), "{}", cov);
+            }
+
+            // The same through the interpreter, for a taproot leaf of a spent output
+            let desc = format!(
+                "eltr({},and_v(v:pk({}),reissuance_token_eq({})))",
+                key,
+                key,
+                encode::serialize_hex(&confidential::Value::Explicit(v))
+            );
+            let tr = Tr::<XOnlyPublicKey, CovOps<CovExtArgs>>::from_str(&desc).unwrap();
+            let leaf = tr.iter_scripts().next().unwrap().1.encode();
+            let control_block = tr
+                .spend_info()
+                .control_block(&(leaf.clone(), LeafVersion::default()))
+                .unwrap();
+            let witness = vec![vec![1; 64], leaf.into_bytes(), control_block.serialize()];
+            let utxos = [elements::TxOut {
+                script_pubkey: tr.script_pubkey(),
+                ..Default::default()
+            }];
+            let env = TxEnv::new(&tx, &utxos, 0).unwrap();
+            let script_sig = elements::Script::new();
+            let interpreter = Interpreter::<CovOps<CovExtArgs>>::from_txdata_ext(
+                &utxos[0].script_pubkey,
+                &script_sig,
+                &witness,
+                elements::Sequence::MAX,
+                elements::LockTime::ZERO,
+            )
+            .unwrap();
+            let spent = interpreter
+                .iter_custom(Box::new(|_| true), Some(&env))
+                .all(|res| res.is_ok());
+            assert_eq!(spent, v == 100);
+        }
     }
 
     #[rustfmt::skip]
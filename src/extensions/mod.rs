@@ -1,19 +1,43 @@
 //! Extensions to elements-miniscript
 //! Users should implement the [`Extension`] trait to extend miniscript to have newer leaf nodes
 //! Look at examples for implementation of ver_eq fragment
-
+//!
+//! # Custom fragments
+//!
+//! Downstream crates can define their own fragments, for instance for
+//! opcodes only available on a fork of Elements, by implementing
+//!
+//! * [`Extension`] for the abstract properties of the fragment: its type
+//!   ([`Extension::corr_prop`], [`Extension::mall_prop`]), its satisfaction
+//!   cost and resource limits ([`Extension::extra_prop`],
+//!   [`Extension::script_size`]), the context checks and parsing from the
+//!   string representation. [`fmt::Display`] must be the inverse of
+//!   [`Extension::from_name_tree`].
+//! * [`ParseableExt`] for the script encoding, the decoding from script, the
+//!   satisfaction and the interpreter evaluation. Satisfaction and evaluation
+//!   have defaults which never satisfy the fragment, so that fragments can be
+//!   encoded and decoded before they are fully supported.
+//! * [`Liftable`] and [`TranslateExt`] if the fragment is used with policies
+//!   or key translation.
+//!
+//! The fragments of this module, e.g. [`LegacyVerEq`], are implemented the
+//! same way and serve as examples.
+
+use std::collections::BTreeMap;
 use std::{fmt, hash};
 
 use bitcoin::hashes::Hash;
+use elements::confidential::{self, AssetBlindingFactor, ValueBlindingFactor};
 use elements::script::Builder;
-use elements::{secp256k1_zkp, Transaction, TxOut};
+use elements::{secp256k1_zkp, AssetId, Transaction, TxOut, TxOutSecrets};
 
 use crate::expression::Tree;
 use crate::interpreter::{self, Stack};
 use crate::miniscript::context::ScriptContextError;
 use crate::miniscript::lex::TokenIter;
-use crate::miniscript::satisfy::Satisfaction;
+use crate::miniscript::satisfy::{Satisfaction, Witness};
 use crate::miniscript::types::{Correctness, ExtData, Malleability};
+use crate::policy::covenant::Constraint;
 use crate::policy::Liftable;
 use crate::{policy, Error, ExtTranslator, MiniscriptKey, Satisfier, ToPublicKey, TranslateExt};
 
@@ -23,6 +47,7 @@ mod csfs;
 mod index_ops;
 mod introspect_ops;
 mod outputs_pref;
+mod pair;
 pub mod param;
 mod tx_ver;
 
@@ -32,12 +57,22 @@ pub use index_ops::IdxExpr;
 pub use introspect_ops::{AssetExpr, CovOps, Spk, SpkExpr, ValueExpr};
 
 pub use self::outputs_pref::LegacyOutputsPref;
+pub use self::pair::ExtPair;
 pub use self::param::{ArgFromStr, CovExtArgs, ExtParam, NoExtParam};
 pub use self::tx_ver::LegacyVerEq;
 
 /// Failed to extract a token from an iterator of tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FromTokenIterError;
 
+impl fmt::Display for FromTokenIterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("could not parse extension fragment")
+    }
+}
+
+impl std::error::Error for FromTokenIterError {}
+
 /// Extensions to elements-miniscript.
 /// Refer to implementations(unimplemented!) for example and tutorials
 pub trait Extension: Clone + Eq + Ord + fmt::Debug + fmt::Display + hash::Hash {
@@ -69,6 +104,20 @@ pub trait Extension: Clone + Eq + Ord + fmt::Debug + fmt::Display + hash::Hash {
     // Ideally, we would want a FromTree implementation here, but that is not possible
     // as we would need to create a new Tree by removing wrappers from root.
     fn from_name_tree(_name: &str, children: &[Tree<'_>]) -> Result<Self, FromTokenIterError>;
+
+    /// The constraint this fragment places on the spending transaction when
+    /// lifted into a [`policy::covenant::Policy`].
+    /// By default, the constraint is opaque and described by the fragment itself.
+    fn cov_constraint(&self) -> Constraint {
+        Constraint::Other(self.to_string())
+    }
+
+    /// The number of signatures checked by the fragment when satisfied,
+    /// which use the tapscript sigops budget, e.g. with
+    /// `OP_CHECKSIGFROMSTACK`. By default, the fragment checks none.
+    fn sig_ops(&self) -> usize {
+        0
+    }
 }
 
 /// Support for parsing/serializing/satisfaction of extensions.
@@ -93,11 +142,15 @@ pub trait ParseableExt:
     /// Output Ok(true) when the ext fragment is satisfied.
     /// Output Ok(false) when the ext fragment is dissatisfied,
     /// Output Some(Err) when there is an error in interpreter value.
+    ///
+    /// By default, the fragment cannot be evaluated.
     fn evaluate(
         &self,
-        stack: &mut Stack,
-        txenv: Option<&TxEnv>,
-    ) -> Result<bool, interpreter::Error>;
+        _stack: &mut Stack,
+        _txenv: Option<&TxEnv>,
+    ) -> Result<bool, interpreter::Error> {
+        Err(interpreter::Error::CouldNotEvaluate)
+    }
 
     /// Encoding of the current fragment
     fn push_to_builder(&self, builder: Builder) -> Builder;
@@ -106,19 +159,35 @@ pub trait ParseableExt:
     /// See satisfaction code in satisfy.rs for example
     /// Note that the [`Satisfaction`] struct also covers the case when
     /// satisfaction is impossible/unavailable
+    ///
+    /// By default, the satisfaction is unavailable.
     fn satisfy<Pk, S>(&self, _sat: &S) -> Satisfaction
     where
         Pk: ToPublicKey,
-        S: Satisfier<Pk>;
+        S: Satisfier<Pk>,
+    {
+        Satisfaction {
+            stack: Witness::Unavailable,
+            has_sig: false,
+        }
+    }
 
     /// Produce a satisfaction for this from satisfier.
     /// See satisfaction code in satisfy.rs for example
     /// Note that the [`Satisfaction`] struct also covers the case when
     /// dissatisfaction is impossible/unavailable
+    ///
+    /// By default, the dissatisfaction is unavailable.
     fn dissatisfy<Pk, S>(&self, _sat: &S) -> Satisfaction
     where
         Pk: ToPublicKey,
-        S: Satisfier<Pk>;
+        S: Satisfier<Pk>,
+    {
+        Satisfaction {
+            stack: Witness::Unavailable,
+            has_sig: false,
+        }
+    }
 }
 
 /// No Extensions for elements-miniscript
@@ -287,6 +356,14 @@ impl<T: ExtParam> Extension for CovenantExt<T> {
     fn segwit_ctx_checks(&self) -> Result<(), ScriptContextError> {
         all_arms_fn!(self, Extension, segwit_ctx_checks,)
     }
+
+    fn cov_constraint(&self) -> Constraint {
+        all_arms_fn!(self, Extension, cov_constraint,)
+    }
+
+    fn sig_ops(&self) -> usize {
+        all_arms_fn!(self, Extension, sig_ops,)
+    }
 }
 
 impl ParseableExt for CovenantExt<CovExtArgs> {
@@ -357,6 +434,11 @@ where
 /// 'tx denotes the lifetime of the transaction
 /// being satisfied and 'ptx denotes the lifetime
 /// of the previous transaction inputs
+///
+/// The secrets of confidential spent utxos can be added with
+/// [`TxEnv::with_spent_utxo_secrets`], so that the covenants comparing their
+/// values or assets are known to be impossible to satisfy when they only
+/// differ by their blinding.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TxEnv<'tx, 'ptx> {
     /// The transaction being spent
@@ -365,6 +447,8 @@ pub struct TxEnv<'tx, 'ptx> {
     spent_utxos: &'ptx [TxOut],
     /// The input index being spent
     idx: usize,
+    /// The secrets of the spent utxos, by input index
+    spent_utxo_secrets: BTreeMap<usize, TxOutSecrets>,
 }
 
 impl<'tx, 'ptx> TxEnv<'tx, 'ptx> {
@@ -377,10 +461,61 @@ impl<'tx, 'ptx> TxEnv<'tx, 'ptx> {
                 tx,
                 spent_utxos,
                 idx,
+                spent_utxo_secrets: BTreeMap::new(),
             })
         }
     }
 
+    /// Adds the secrets of the utxo spent by the input at `idx`, after
+    /// checking that they open its asset and value, explicit or confidential
+    pub fn with_spent_utxo_secrets(
+        mut self,
+        idx: usize,
+        secrets: TxOutSecrets,
+    ) -> Result<Self, EvalError> {
+        let utxo = self
+            .spent_utxos
+            .get(idx)
+            .ok_or_else(|| EvalError::UtxoIndexOutOfBounds(idx, self.spent_utxos.len()))?;
+        let opens = crate::with_secp(|secp| {
+            let (asset, gen) = match utxo.asset {
+                confidential::Asset::Explicit(id) => (
+                    id == secrets.asset,
+                    secp256k1_zkp::Generator::new_unblinded(secp, secrets.asset.into_tag()),
+                ),
+                confidential::Asset::Confidential(gen) => (
+                    confidential::Asset::new_confidential(secp, secrets.asset, secrets.asset_bf)
+                        == utxo.asset,
+                    gen,
+                ),
+                confidential::Asset::Null => return false,
+            };
+            let value = match utxo.value {
+                confidential::Value::Explicit(v) => v == secrets.value,
+                confidential::Value::Confidential(_) => {
+                    confidential::Value::new_confidential(
+                        secp,
+                        secrets.value,
+                        gen,
+                        secrets.value_bf,
+                    ) == utxo.value
+                }
+                confidential::Value::Null => false,
+            };
+            asset && value
+        });
+        if !opens {
+            return Err(EvalError::InvalidUtxoSecrets(idx));
+        }
+        self.spent_utxo_secrets.insert(idx, secrets);
+        Ok(self)
+    }
+
+    /// Obtains the secrets of the utxo spent by the input at `idx`, if known
+    pub fn spent_utxo_secrets(&self, idx: usize) -> Option<&TxOutSecrets> {
+        self.spent_utxo_secrets.get(&idx)
+    }
+
     /// Obtains the tx
     pub fn tx(&self) -> &Transaction {
         self.tx
@@ -409,6 +544,26 @@ impl<'tx, 'ptx, Pk: ToPublicKey> Satisfier<Pk> for TxEnv<'tx, 'ptx> {
     fn lookup_curr_inp(&self) -> Option<usize> {
         Some(self.idx)
     }
+
+    fn lookup_asset_blinding(
+        &self,
+        asset: &confidential::Asset,
+    ) -> Option<(AssetId, AssetBlindingFactor)> {
+        self.spent_utxo_secrets
+            .iter()
+            .find(|(idx, _)| self.spent_utxos[**idx].asset == *asset)
+            .map(|(_, secrets)| (secrets.asset, secrets.asset_bf))
+    }
+
+    fn lookup_value_blinding(
+        &self,
+        value: &confidential::Value,
+    ) -> Option<(u64, ValueBlindingFactor)> {
+        self.spent_utxo_secrets
+            .iter()
+            .find(|(idx, _)| self.spent_utxos[**idx].value == *value)
+            .map(|(_, secrets)| (secrets.value, secrets.value_bf))
+    }
 }
 
 /// API to check sig from fragment `price_oracle_1`
@@ -85,8 +85,11 @@ impl Extension for LegacyOutputsPref {
             has_free_verify: true,
             stack_elem_count_sat: Some(7),
             stack_elem_count_dissat: Some(7),
-            max_sat_size: Some((max_wit_sz, max_wit_sz)),
-            max_dissat_size: Some((0, 0)), // all empty should dissatisfy
+            // Plus the length prefixes of the 7 elements
+            max_sat_size: Some((max_wit_sz + 7, max_wit_sz + 7)),
+            // All empty should dissatisfy, except one `1` when the outputs
+            // are exactly the prefix
+            max_dissat_size: Some((7, 7)),
             timelock_info: TimelockInfo::default(),
             exec_stack_elem_count_sat: Some(3), // sha2 context, byte slice, target hash
             exec_stack_elem_count_dissat: Some(3),
@@ -0,0 +1,165 @@
+//! Combination of two extensions
+//! Allows using the fragments of two extensions in the same miniscript
+//! without writing an enum with all the fragments like [`super::CovenantExt`]
+
+use std::fmt;
+
+use elements::script::Builder;
+
+use super::{FromTokenIterError, ParseableExt, TxEnv};
+use crate::expression::Tree;
+use crate::interpreter::{self, Stack};
+use crate::miniscript::context::ScriptContextError;
+use crate::miniscript::lex::TokenIter;
+use crate::miniscript::satisfy::Satisfaction;
+use crate::miniscript::types::{Correctness, ExtData, Malleability};
+use crate::policy::covenant::Constraint;
+use crate::{ExtTranslator, Extension, Satisfier, ToPublicKey, TranslateExt};
+
+/// A fragment of either of the extensions `A` or `B`
+///
+/// When parsing, the fragments of `A` take precedence over the ones of `B`.
+/// Pairs can be nested to combine more than two extensions.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum ExtPair<A, B> {
+    /// Fragment of the first extension
+    First(A),
+    /// Fragment of the second extension
+    Second(B),
+}
+
+// Apply the function on both arms
+macro_rules! both_arms_fn {
+    ($slf: ident, $f: ident, $($args:ident, )* ) => {
+        match $slf {
+            ExtPair::First(a) => a.$f($($args, )*),
+            ExtPair::Second(b) => b.$f($($args, )*),
+        }
+    };
+}
+
+impl<A: Extension, B: Extension> Extension for ExtPair<A, B> {
+    fn corr_prop(&self) -> Correctness {
+        both_arms_fn!(self, corr_prop,)
+    }
+
+    fn mall_prop(&self) -> Malleability {
+        both_arms_fn!(self, mall_prop,)
+    }
+
+    fn extra_prop(&self) -> ExtData {
+        both_arms_fn!(self, extra_prop,)
+    }
+
+    fn script_size(&self) -> usize {
+        both_arms_fn!(self, script_size,)
+    }
+
+    fn segwit_ctx_checks(&self) -> Result<(), ScriptContextError> {
+        both_arms_fn!(self, segwit_ctx_checks,)
+    }
+
+    fn tap_ctx_checks(&self) -> Result<(), ScriptContextError> {
+        both_arms_fn!(self, tap_ctx_checks,)
+    }
+
+    fn from_name_tree(name: &str, children: &[Tree<'_>]) -> Result<Self, FromTokenIterError> {
+        A::from_name_tree(name, children)
+            .map(ExtPair::First)
+            .or_else(|_| B::from_name_tree(name, children).map(ExtPair::Second))
+    }
+
+    fn cov_constraint(&self) -> Constraint {
+        both_arms_fn!(self, cov_constraint,)
+    }
+
+    fn sig_ops(&self) -> usize {
+        both_arms_fn!(self, sig_ops,)
+    }
+}
+
+impl<A: ParseableExt, B: ParseableExt> ParseableExt for ExtPair<A, B> {
+    fn satisfy<Pk, S>(&self, sat: &S) -> Satisfaction
+    where
+        Pk: ToPublicKey,
+        S: Satisfier<Pk>,
+    {
+        both_arms_fn!(self, satisfy, sat,)
+    }
+
+    fn dissatisfy<Pk, S>(&self, sat: &S) -> Satisfaction
+    where
+        Pk: ToPublicKey,
+        S: Satisfier<Pk>,
+    {
+        both_arms_fn!(self, dissatisfy, sat,)
+    }
+
+    fn evaluate(
+        &self,
+        stack: &mut Stack,
+        txenv: Option<&TxEnv>,
+    ) -> Result<bool, interpreter::Error> {
+        both_arms_fn!(self, evaluate, stack, txenv,)
+    }
+
+    fn push_to_builder(&self, builder: Builder) -> Builder {
+        both_arms_fn!(self, push_to_builder, builder,)
+    }
+
+    fn from_token_iter(tokens: &mut TokenIter<'_>) -> Result<Self, FromTokenIterError> {
+        A::from_token_iter(tokens)
+            .map(ExtPair::First)
+            .or_else(|_| B::from_token_iter(tokens).map(ExtPair::Second))
+    }
+}
+
+impl<A: fmt::Display, B: fmt::Display> fmt::Display for ExtPair<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtPair::First(a) => a.fmt(f),
+            ExtPair::Second(b) => b.fmt(f),
+        }
+    }
+}
+
+impl<A, B, QExt> TranslateExt<ExtPair<A, B>, QExt> for ExtPair<A, B>
+where
+    A: Extension,
+    B: Extension,
+    QExt: Extension,
+{
+    type Output = QExt;
+
+    fn translate_ext<T, E>(&self, t: &mut T) -> Result<Self::Output, E>
+    where
+        T: ExtTranslator<ExtPair<A, B>, QExt, E>,
+    {
+        t.ext(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::key::XOnlyPublicKey;
+
+    use super::*;
+    use crate::extensions::{Arith, CovExtArgs, CovOps, LegacyVerEq};
+    use crate::{Miniscript, Segwitv0, Tap};
+
+    #[test]
+    fn parse_pair() {
+        type Ext = ExtPair<Arith<CovExtArgs>, CovOps<CovExtArgs>>;
+        type Ms = Miniscript<XOnlyPublicKey, Tap, Ext>;
+
+        let s = "and_v(v:is_exp_value(out_value(1)),num64_eq(inp_v(0),out_v(0)))";
+        let ms = Ms::from_str_insane(s).unwrap();
+        assert_eq!(ms.to_string(), s);
+        assert_eq!(ms, Ms::parse_insane(&ms.encode()).unwrap());
+
+        // Fragments of neither extension are rejected
+        assert!(Ms::from_str_insane("ver_eq(1)").is_err());
+        type VerMs = Miniscript<bitcoin::PublicKey, Segwitv0, ExtPair<LegacyVerEq, Ext>>;
+        assert!(VerMs::from_str_insane("ver_eq(1)").is_ok());
+    }
+}
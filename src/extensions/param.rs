@@ -65,14 +65,22 @@ pub enum CovExtArgs {
     XOnlyKey(CsfsKey),
     /// Message
     CsfsMsg(CsfsMsg),
-    /// Asset
+    /// Asset, explicit or a commitment, written as the hex of its consensus
+    /// encoding
     Asset(confidential::Asset),
-    /// Value
+    /// Value, explicit or a commitment, written as the hex of its consensus
+    /// encoding
     Value(confidential::Value),
     /// Script
     Script(Spk),
 }
 
+impl From<NoExtParam> for CovExtArgs {
+    fn from(v: NoExtParam) -> Self {
+        match v {}
+    }
+}
+
 impl From<CsfsMsg> for CovExtArgs {
     fn from(v: CsfsMsg) -> Self {
         Self::CsfsMsg(v)
@@ -166,7 +174,11 @@ impl ArgFromStr for CovExtArgs {
             ("asset_eq", 0) | ("asset_eq", 1) | ("is_exp_asset", 0) => {
                 CovExtArgs::Asset(confidential::Asset::arg_from_str(s, parent, pos)?)
             }
-            ("value_eq", 0) | ("value_eq", 1) | ("is_exp_value", 0) => {
+            ("value_eq", 0)
+            | ("value_eq", 1)
+            | ("is_exp_value", 0)
+            | ("issuance_eq", 0)
+            | ("reissuance_token_eq", 0) => {
                 CovExtArgs::Value(confidential::Value::arg_from_str(s, parent, pos)?)
             }
             ("spk_eq", 0) | ("spk_eq", 1) => CovExtArgs::Script(Spk::arg_from_str(s, parent, pos)?),
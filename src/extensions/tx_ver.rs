@@ -13,6 +13,7 @@ use crate::miniscript::lex::{Token as Tk, TokenIter};
 use crate::miniscript::satisfy::{Satisfaction, Witness};
 use crate::miniscript::types::extra_props::{OpLimits, TimelockInfo};
 use crate::miniscript::types::{Base, Correctness, Dissat, ExtData, Input, Malleability};
+use crate::policy::covenant::Constraint;
 use crate::policy::{self, Liftable};
 use crate::{
     expression, interpreter, miniscript, util, Error, Extension, MiniscriptKey, Satisfier,
@@ -97,6 +98,10 @@ impl Extension for LegacyVerEq {
             Err(FromTokenIterError)
         }
     }
+
+    fn cov_constraint(&self) -> Constraint {
+        Constraint::TxVersion(self.n)
+    }
 }
 
 impl ParseableExt for LegacyVerEq {
@@ -55,6 +55,9 @@ pub enum Error {
     NonStandardSigHash(Vec<u8>),
     /// Miniscript error
     Miniscript(crate::Error),
+    /// A pre-adapted signature, i.e. an adaptor signature which was not
+    /// decrypted into a signature, see [`crate::AdaptorPoint`]
+    PreAdaptedSignature(bitcoin::PublicKey),
     /// MultiSig requires 1 extra zero element apart from the `k` signatures
     MissingExtraZeroMultiSig,
     /// Script abortion because of incorrect dissatisfaction for multisig.
@@ -62,6 +65,9 @@ pub enum Error {
     /// this error. This is network standardness assumption and miniscript only
     /// supports standard scripts
     MultiSigEvaluationError,
+    /// A stack element is not a number of the expected encoding, e.g. a
+    /// non-minimally encoded script number
+    InvalidStackNumber(Vec<u8>),
     ///Witness must be empty for pre-segwit transactions
     NonEmptyWitness,
     ///ScriptSig must be empty for pure segwit transactions
@@ -91,7 +97,8 @@ pub enum Error {
     SchnorrSig(elements::SchnorrSigError),
     /// Errors in signature hash calculations
     SighashError(elements::sighash::Error),
-    /// Taproot Annex Unsupported
+    /// Taproot annex encountered while not allowed by the
+    /// [`super::InterpreterOptions`]
     TapAnnexUnsupported,
     /// An uncompressed public key was encountered in a context where it is
     /// disallowed (e.g. in a Segwit script or p2wpkh output)
@@ -123,6 +130,19 @@ pub enum Error {
     },
     /// Errors related to extensions.
     ArithError(EvalError),
+    /// The witness stack has more elements than allowed by the
+    /// [`super::InterpreterOptions`]
+    TooManyStackElements(usize),
+    /// The number of spent utxos does not match the number of inputs of the
+    /// transaction
+    SpentUtxosLenMismatch {
+        /// Number of inputs of the transaction
+        inputs: usize,
+        /// Number of spent utxos
+        spent_utxos: usize,
+    },
+    /// The transaction has no input at this index
+    InputIdxOutOfBounds(usize),
 }
 
 impl fmt::Display for Error {
@@ -161,6 +181,9 @@ impl fmt::Display for Error {
             ),
             Error::InvalidEcdsaSignature(pk) => write!(f, "bad ecdsa signature with pk {}", pk),
             Error::InvalidSchnorrSignature(pk) => write!(f, "bad schnorr signature with pk {}", pk),
+            Error::InvalidStackNumber(ref elem) => {
+                write!(f, "invalid number on the stack '{}'", elem.to_hex())
+            }
             Error::NonStandardSigHash(ref sig) => write!(
                 f,
                 "Non standard sighash type for signature '{}'",
@@ -169,6 +192,9 @@ impl fmt::Display for Error {
             Error::NonEmptyWitness => f.write_str("legacy spend had nonempty witness"),
             Error::NonEmptyScriptSig => f.write_str("segwit spend had nonempty scriptsig"),
             Error::Miniscript(ref e) => write!(f, "parse error: {}", e),
+            Error::PreAdaptedSignature(pk) => {
+                write!(f, "adaptor signature with pk {} was not adapted", pk)
+            }
             Error::MissingExtraZeroMultiSig => f.write_str("CMS missing extra zero"),
             Error::MultiSigEvaluationError => {
                 f.write_str("CMS script aborted, incorrect satisfaction/dissatisfaction")
@@ -210,6 +236,16 @@ impl fmt::Display for Error {
                 pos, expected, actual
             ),
             Error::ArithError(ref e) => write!(f, "{}", e),
+            Error::TooManyStackElements(n) => write!(f, "Too many stack elements: {}", n),
+            Error::SpentUtxosLenMismatch {
+                inputs,
+                spent_utxos,
+            } => write!(
+                f,
+                "Transaction has {} inputs, but {} spent utxos were given",
+                inputs, spent_utxos
+            ),
+            Error::InputIdxOutOfBounds(idx) => write!(f, "Transaction has no input {}", idx),
         }
     }
 }
@@ -237,12 +273,14 @@ impl error::Error for Error {
             | NonStandardSigHash(_)
             | MissingExtraZeroMultiSig
             | MultiSigEvaluationError
+            | InvalidStackNumber(_)
             | NonEmptyWitness
             | NonEmptyScriptSig
             | PubkeyParseError
             | XOnlyPublicKeyParseError
             | PkEvaluationError(_)
             | PkHashVerifyFail(_)
+            | PreAdaptedSignature(_)
             | RelativeLocktimeNotMet(_)
             | ScriptSatisfactionError
             | TapAnnexUnsupported
@@ -260,6 +298,8 @@ impl error::Error for Error {
             IncorrectCovenantWitness => None,
             CovWitnessSizeErr { .. } => None,
             ArithError(..) => None,
+            TooManyStackElements(..) => None,
+            SpentUtxosLenMismatch { .. } | InputIdxOutOfBounds(..) => None,
         }
     }
 }
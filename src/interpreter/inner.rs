@@ -125,6 +125,24 @@ pub enum Inner<Ext: Extension> {
     // todo: add extensions support as explicit enum
 }
 
+/// The annex of a taproot spend, i.e. the last element of a witness with at
+/// least two elements if it starts with `0x50`
+pub(super) fn tap_annex<'txin>(
+    spk: &elements::Script,
+    witness: &'txin [Vec<u8>],
+) -> Option<&'txin [u8]> {
+    match witness.split_last() {
+        Some((last, rest))
+            if is_v1_p2tr(spk)
+                && !rest.is_empty()
+                && last.first() == Some(&TAPROOT_ANNEX_PREFIX) =>
+        {
+            Some(&last[..])
+        }
+        _ => None,
+    }
+}
+
 // The `Script` returned by this method is always generated/cloned ... when
 // rust-bitcoin is updated to use a copy-on-write internal representation we
 // should revisit this and return references to the actual txdata wherever
@@ -244,17 +262,10 @@ pub fn from_txdata<'txin, Ext: ParseableExt>(
         } else {
             let output_key = bitcoin::key::XOnlyPublicKey::from_slice(&spk[2..])
                 .map_err(|_| Error::XOnlyPublicKeyParseError)?;
-            let has_annex = wit_stack
-                .last()
-                .and_then(|x| x.as_push().ok())
-                .map(|x| !x.is_empty() && x[0] == TAPROOT_ANNEX_PREFIX)
-                .unwrap_or(false);
-            let has_annex = has_annex && (wit_stack.len() >= 2);
-            if has_annex {
-                // Annex is non-standard, bitcoin consensus rules ignore it.
-                // Our sighash structure and signature verification
-                // does not support annex, return error
-                return Err(Error::TapAnnexUnsupported);
+            if tap_annex(spk, witness).is_some() {
+                // The annex is not used by the script, only committed to by
+                // the signatures, see `Interpreter::verify_sig`
+                wit_stack.pop();
             }
             match wit_stack.len() {
                 0 => Err(Error::UnexpectedStackEnd),
@@ -272,17 +283,18 @@ pub fn from_txdata<'txin, Ext: ParseableExt>(
                         ControlBlock::from_slice(ctrl_blk).map_err(Error::ControlBlockParse)?;
                     let tap_script = script_from_stack_elem::<Tap, Ext>(&tap_script)?;
                     let ms = tap_script.to_no_checks_ms();
-                    // Creating new contexts is cheap
-                    let secp = bitcoin::secp256k1::Secp256k1::verification_only();
                     let tap_script = tap_script.encode();
                     // Should not really need to call dangerous assumed tweaked here.
                     // Should be fixed after RC
                     // This is fixed in rust-bitcoin. Should also be fixed in rust-elements
-                    if ctrl_blk.verify_taproot_commitment(
-                        &secp,
-                        &TweakedPublicKey::new(output_key),
-                        &tap_script,
-                    ) {
+                    let valid = crate::with_secp(|secp| {
+                        ctrl_blk.verify_taproot_commitment(
+                            secp,
+                            &TweakedPublicKey::new(output_key),
+                            &tap_script,
+                        )
+                    });
+                    if valid {
                         Ok((
                             Inner::Script(ms, ScriptType::Tr),
                             wit_stack,
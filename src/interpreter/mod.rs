@@ -10,6 +10,7 @@
 
 use std::borrow::Borrow;
 use std::fmt;
+use std::ops::Deref;
 use std::str::FromStr;
 
 use bitcoin;
@@ -18,16 +19,19 @@ use elements::{self, secp256k1_zkp, sighash, EcdsaSigHashType, LockTime, Sequenc
 
 use crate::extensions::{CovExtArgs, ParseableExt, TxEnv};
 use crate::miniscript::context::{NoChecks, SigType};
+use crate::miniscript::limits::{MAX_STACK_SIZE, MAX_STANDARD_P2WSH_STACK_ITEMS};
 use crate::miniscript::ScriptContext;
 use crate::{hash256, util, Descriptor, ElementsSig, Miniscript, Terminal, ToPublicKey};
 
 mod error;
 mod inner;
 mod stack;
+mod tx;
 
 pub use self::error::Error;
 use self::error::PkEvalErrInner;
 pub use self::stack::{Element, Stack};
+pub use self::tx::TransactionInterpreter;
 use crate::{elementssig_from_rawsig, CovenantExt, Extension, MiniscriptKey};
 
 /// An iterable Miniscript-structured representation of the spending of a coin
@@ -37,8 +41,64 @@ pub struct Interpreter<'txin, Ext: Extension> {
     /// For non-Taproot spends, the scriptCode; for Taproot script-spends, this
     /// is the leaf script; for key-spends it is `None`.
     script_code: Option<elements::Script>,
+    /// The annex of Taproot spends, committed to by their signatures
+    annex: Option<&'txin [u8]>,
     age: Sequence,
     lock_time: LockTime,
+    options: InterpreterOptions,
+}
+
+/// The rules checked by the [`Interpreter`] on top of the Miniscript
+/// semantics
+///
+/// The default options enforce the standardness rules of the network, as
+/// expected by wallets. Disabling some of them allows evaluating witnesses
+/// which are only valid by consensus.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct InterpreterOptions {
+    /// Whether the argument of `IF` and `NOTIF` must be empty or exactly `1`,
+    /// which is a standardness rule for segwit v0 and a consensus rule for
+    /// tapscript. When disabled, any other value is cast to a boolean like
+    /// the script interpreter does.
+    pub require_minimal_if: bool,
+    /// Whether taproot spends can have an annex, which is non-standard
+    pub allow_annex: bool,
+    /// The maximum number of elements of the witness stack of segwit v0
+    /// script spends, not counting the witness script
+    pub max_stack: usize,
+    /// The maximum number of elements of the witness stack of tapscript
+    /// spends, not counting the leaf script, the control block and the annex
+    pub max_tap_stack: usize,
+    /// Whether the `after` and `older` fragments are checked against the lock
+    /// time and the sequence of the spending transaction. When disabled, all
+    /// the timelocks are assumed to be met.
+    pub verify_cltv_csv: bool,
+}
+
+impl Default for InterpreterOptions {
+    fn default() -> Self {
+        InterpreterOptions {
+            require_minimal_if: true,
+            allow_annex: false,
+            max_stack: MAX_STANDARD_P2WSH_STACK_ITEMS,
+            // There is no standardness limit on tapscript stacks
+            max_tap_stack: MAX_STACK_SIZE,
+            verify_cltv_csv: true,
+        }
+    }
+}
+
+impl InterpreterOptions {
+    /// Options checking the consensus rules only
+    pub fn consensus() -> Self {
+        InterpreterOptions {
+            require_minimal_if: false,
+            allow_annex: true,
+            max_stack: MAX_STACK_SIZE,
+            max_tap_stack: MAX_STACK_SIZE,
+            verify_cltv_csv: true,
+        }
+    }
 }
 
 // A type representing functions for checking signatures that accept both
@@ -172,13 +232,49 @@ where
         age: Sequence,       // CSV, relative lock time.
         lock_time: LockTime, // CLTV, absolute lock time.
     ) -> Result<Self, Error> {
+        Interpreter::from_txdata_with_options(
+            spk,
+            script_sig,
+            witness,
+            age,
+            lock_time,
+            InterpreterOptions::default(),
+        )
+    }
+
+    /// Same as [`Interpreter::from_txdata_ext`], but checks the rules given
+    /// by `options` instead of the standardness ones
+    pub fn from_txdata_with_options(
+        spk: &elements::Script,
+        script_sig: &'txin elements::Script,
+        witness: &'txin [Vec<u8>],
+        age: Sequence,       // CSV, relative lock time.
+        lock_time: LockTime, // CLTV, absolute lock time.
+        options: InterpreterOptions,
+    ) -> Result<Self, Error> {
+        let annex = inner::tap_annex(spk, witness);
+        if annex.is_some() && !options.allow_annex {
+            return Err(Error::TapAnnexUnsupported);
+        }
         let (inner, stack, script_code) = inner::from_txdata(spk, script_sig, witness)?;
+        let max_stack = match inner {
+            inner::Inner::Script(_, inner::ScriptType::Wsh)
+            | inner::Inner::Script(_, inner::ScriptType::ShWsh)
+            | inner::Inner::CovScript(..) => options.max_stack,
+            inner::Inner::Script(_, inner::ScriptType::Tr) => options.max_tap_stack,
+            _ => MAX_STACK_SIZE,
+        };
+        if stack.len() > max_stack {
+            return Err(Error::TooManyStackElements(stack.len()));
+        }
         Ok(Interpreter {
             inner,
             stack,
             script_code,
+            annex,
             age,
             lock_time,
+            options,
         })
     }
 
@@ -192,6 +288,7 @@ where
     ) -> Iter<'txin, 'iter, Ext> {
         Iter {
             verify_sig,
+            hash_oracle: None,
             public_key: if let inner::Inner::PublicKey(ref pk, _) = self.inner {
                 Some(pk)
             } else {
@@ -215,6 +312,7 @@ where
             stack: self.stack.clone(),
             age: self.age,
             lock_time: self.lock_time,
+            options: self.options,
             cov: if let inner::Inner::CovScript(ref pk, ref _ms) = self.inner {
                 Some(pk)
             } else {
@@ -244,6 +342,29 @@ where
         genesis_hash: elements::BlockHash,
         sig: &KeySigPair,
     ) -> bool {
+        let mut cache = sighash::SigHashCache::new(tx);
+        self.verify_sig_with_cache(secp, &mut cache, input_idx, prevouts, genesis_hash, sig)
+    }
+
+    /// Same as [`Interpreter::verify_sig`], but reads and updates the provided
+    /// [`sighash::SigHashCache`] instead of creating a new one. Reusing the cache
+    /// across signatures and inputs of the same transaction avoids recomputing the
+    /// hashes of the prevouts, sequences, issuances and outputs for every signature.
+    /// The cache must have been created for the transaction being verified.
+    pub fn verify_sig_with_cache<C, R, T>(
+        &self,
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        cache: &mut sighash::SigHashCache<R>,
+        input_idx: usize,
+        prevouts: &sighash::Prevouts<'_, T>,
+        genesis_hash: elements::BlockHash,
+        sig: &KeySigPair,
+    ) -> bool
+    where
+        C: secp256k1_zkp::Verification,
+        R: Deref<Target = elements::Transaction>,
+        T: Borrow<elements::TxOut>,
+    {
         fn get_prevout<'u, T: Borrow<elements::TxOut>>(
             prevouts: &'u sighash::Prevouts<'u, T>,
             input_index: usize,
@@ -259,16 +380,17 @@ where
                 sighash::Prevouts::All(prevouts) => prevouts.get(input_index),
             }
         }
-        let mut cache = elements::sighash::SigHashCache::new(tx);
         match sig {
             KeySigPair::Ecdsa(key, ecdsa_sig) => {
                 let script_pubkey = self.script_code.as_ref().expect("Legacy have script code");
                 let sighash = if self.is_legacy() {
                     cache.legacy_sighash(input_idx, script_pubkey, ecdsa_sig.1)
                 } else if self.is_segwit_v0() {
+                    // The value is signed as it appears in the spent output,
+                    // either explicit or as a confidential commitment
                     let amt = match get_prevout(prevouts, input_idx) {
-                        Some(txout) => txout.borrow().value,
-                        None => return false,
+                        Some(txout) if !txout.borrow().value.is_null() => txout.borrow().value,
+                        _ => return false,
                     };
                     cache.segwitv0_sighash(input_idx, script_pubkey, amt, ecdsa_sig.1)
                 } else {
@@ -279,10 +401,17 @@ where
                 secp.verify_ecdsa(&msg, &ecdsa_sig.0, &key.inner).is_ok()
             }
             KeySigPair::Schnorr(xpk, schnorr_sig) => {
+                let annex = match self.annex.map(sighash::Annex::new) {
+                    Some(Ok(annex)) => Some(annex),
+                    Some(Err(..)) => return false,
+                    None => None,
+                };
                 let sighash_msg = if self.is_taproot_v1_key_spend() {
-                    cache.taproot_key_spend_signature_hash(
+                    cache.taproot_sighash(
                         input_idx,
                         prevouts,
+                        annex,
+                        None,
                         schnorr_sig.hash_ty,
                         genesis_hash,
                     )
@@ -293,10 +422,11 @@ where
                     );
                     let leaf_hash =
                         elements::sighash::ScriptPath::with_defaults(tap_script).leaf_hash();
-                    cache.taproot_script_spend_signature_hash(
+                    cache.taproot_sighash(
                         input_idx,
                         prevouts,
-                        leaf_hash,
+                        annex,
+                        Some((leaf_hash, 0xFFFFFFFF)),
                         schnorr_sig.hash_ty,
                         genesis_hash,
                     )
@@ -331,17 +461,52 @@ where
     /// - For legacy outputs, no information about prevouts is required
     /// - For segwitv0 outputs, prevout at corresponding index with correct amount must be provided
     /// - For taproot outputs, information about all prevouts must be supplied and genesis_hash must be supplied
+    ///
+    /// The amounts of the prevouts are used as they appear in the spent outputs, so
+    /// confidential prevouts must be provided with their value commitments, without
+    /// unblinding them.
     pub fn iter<'iter, C: secp256k1_zkp::Verification>(
         &'iter self,
         secp: &'iter secp256k1_zkp::Secp256k1<C>,
         txenv: &'txin TxEnv, // actually a 'prevouts, but 'prevouts: 'iter
         genesis_hash: elements::BlockHash, // required for sighash computation in BIP341
     ) -> Iter<'txin, 'iter, Ext> {
+        let mut cache = sighash::SigHashCache::new(txenv.tx());
+        self.iter_custom(
+            Box::new(move |sig| {
+                self.verify_sig_with_cache(
+                    secp,
+                    &mut cache,
+                    txenv.idx(),
+                    &sighash::Prevouts::All(txenv.spent_utxos()),
+                    genesis_hash,
+                    sig,
+                )
+            }),
+            Some(txenv),
+        )
+    }
+
+    /// Same as [`Interpreter::iter`], but signatures are verified using the
+    /// provided [`sighash::SigHashCache`] so that sighash computations can be
+    /// shared between all the inputs of a transaction.
+    /// The cache must have been created for the transaction in `txenv`.
+    pub fn iter_with_cache<'iter, C, R>(
+        &'iter self,
+        secp: &'iter secp256k1_zkp::Secp256k1<C>,
+        txenv: &'txin TxEnv,
+        cache: &'iter mut sighash::SigHashCache<R>,
+        genesis_hash: elements::BlockHash,
+    ) -> Iter<'txin, 'iter, Ext>
+    where
+        C: secp256k1_zkp::Verification,
+        R: Deref<Target = elements::Transaction> + 'iter,
+    {
         self.iter_custom(
             Box::new(move |sig| {
-                self.verify_sig(
+                self.verify_sig_with_cache(
                     secp,
-                    txenv.tx(),
+                    cache,
                     txenv.idx(),
                     &sighash::Prevouts::All(txenv.spent_utxos()),
                     genesis_hash,
@@ -352,6 +517,35 @@ where
         )
     }
 
+    /// Same as [`Interpreter::iter`], but signatures are verified against the given
+    /// [`sighash::Prevouts`] instead of the spent utxos of a [`TxEnv`].
+    ///
+    /// This allows verifying an input knowing only its own prevout, by passing
+    /// [`sighash::Prevouts::One`], which is enough for legacy and segwitv0 inputs
+    /// and for taproot signatures with `ANYONECANPAY`. The prevout values may be
+    /// explicit or confidential commitments. Since no [`TxEnv`] is available,
+    /// covenant fragments which inspect the transaction fail to evaluate.
+    pub fn iter_with_prevouts<'iter, C, T>(
+        &'iter self,
+        secp: &'iter secp256k1_zkp::Secp256k1<C>,
+        tx: &'iter elements::Transaction,
+        input_idx: usize,
+        prevouts: &'iter sighash::Prevouts<'iter, T>,
+        genesis_hash: elements::BlockHash,
+    ) -> Iter<'txin, 'iter, Ext>
+    where
+        C: secp256k1_zkp::Verification,
+        T: Borrow<elements::TxOut>,
+    {
+        let mut cache = sighash::SigHashCache::new(tx);
+        self.iter_custom(
+            Box::new(move |sig| {
+                self.verify_sig_with_cache(secp, &mut cache, input_idx, prevouts, genesis_hash, sig)
+            }),
+            None,
+        )
+    }
+
     /// Creates an iterator over the satisfied spending conditions without checking signatures
     pub fn iter_assume_sigs<'iter>(&'iter self) -> Iter<'txin, 'iter, Ext> {
         self.iter_custom(Box::new(|_| true), None)
@@ -510,6 +704,23 @@ pub enum HashLockType {
     Ripemd160(ripemd160::Hash),
 }
 
+/// Checks the preimages of the hashlocks evaluated by an [`Iter`], instead
+/// of hashing them, see [`Iter::with_insane_hash_oracle`]
+///
+/// This is meant for research and testing, e.g. to accept preimages whose
+/// knowledge is proven off-script: a spend accepted with an oracle may be
+/// invalid by consensus.
+pub trait HashOracle {
+    /// Whether the 32 byte `preimage` of the witness satisfies `hash`
+    fn check_preimage(&mut self, hash: &HashLockType, preimage: &[u8; 32]) -> bool;
+}
+
+impl<F: FnMut(&HashLockType, &[u8; 32]) -> bool> HashOracle for F {
+    fn check_preimage(&mut self, hash: &HashLockType, preimage: &[u8; 32]) -> bool {
+        self(hash, preimage)
+    }
+}
+
 /// A satisfied Miniscript condition (Signature, Hashlock, Timelock)
 /// 'intp represents the lifetime of descriptor and `stack represents
 /// the lifetime of witness
@@ -566,6 +777,47 @@ pub enum SatisfiedConstraint<Ext: Extension> {
     },
 }
 
+/// The progress of the satisfaction of a spend whose witness may be
+/// incomplete, e.g. a half-finalized multisig witness, see
+/// [`Iter::partial_spend`]
+#[derive(Debug)]
+pub struct PartialSpend<Ext: Extension> {
+    /// The constraints satisfied by the witness before the evaluation stopped
+    pub satisfied: Vec<SatisfiedConstraint<Ext>>,
+    /// The keys of the script without a valid signature in the witness
+    pub missing_keys: Vec<BitcoinKey>,
+    /// The hashes of the keys of the `expr_raw_pkh` fragments without a
+    /// valid signature in the witness, whose keys are not in the script
+    pub missing_key_hashes: Vec<hash160::Hash>,
+    /// The hashlocks of the script without a preimage in the witness
+    pub missing_preimages: Vec<HashLockType>,
+    /// The error which stopped the evaluation, e.g.
+    /// [`Error::UnexpectedStackEnd`], or `None` if the witness satisfies
+    /// the script
+    pub error: Option<Error>,
+}
+
+impl<Ext: Extension> PartialSpend<Ext> {
+    /// Whether the witness satisfies the script
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The number of valid signatures in the witness
+    pub fn n_signatures(&self) -> usize {
+        self.satisfied
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c,
+                    SatisfiedConstraint::PublicKey { .. }
+                        | SatisfiedConstraint::PublicKeyHash { .. }
+                )
+            })
+            .count()
+    }
+}
+
 ///This is used by the interpreter to know which evaluation state a AstemElem is.
 ///This is required because whenever a same node(for eg. OrB) appears on the stack, we don't
 ///know if the left child has been evaluated or not. And based on the result on
@@ -600,12 +852,14 @@ where
     Ext: Extension,
 {
     verify_sig: Box<dyn FnMut(&KeySigPair) -> bool + 'intp>,
+    hash_oracle: Option<Box<dyn HashOracle + 'intp>>,
     public_key: Option<&'intp BitcoinKey>,
     state: Vec<NodeEvaluationState<'intp, Ext>>,
     stack: Stack<'txin>,
     txenv: Option<&'txin TxEnv<'txin, 'txin>>,
     age: Sequence,
     lock_time: LockTime,
+    options: InterpreterOptions,
     cov: Option<&'intp BitcoinKey>,
     has_errored: bool,
     sig_type: SigType,
@@ -633,11 +887,94 @@ where
     }
 }
 
+// The key of a signature checked by the interpreter
+fn key_sig_key(key_sig: &KeySigPair) -> BitcoinKey {
+    match *key_sig {
+        KeySigPair::Ecdsa(pk, _) => BitcoinKey::Fullkey(pk),
+        KeySigPair::Schnorr(xpk, _) => BitcoinKey::XOnlyPublicKey(xpk),
+    }
+}
+
 impl<'intp, 'txin: 'intp, Ext> Iter<'intp, 'txin, Ext>
 where
     NoChecks: ScriptContext,
     Ext: ParseableExt,
 {
+    /// Checks the preimages of the hashlocks with `oracle` instead of hashing
+    /// them.
+    ///
+    /// **This is unsafe for consensus**: the iterator accepts any preimage
+    /// accepted by the oracle, which Elements nodes would reject if it does
+    /// not hash to the hashlock. Preimages must still be 32 bytes long.
+    pub fn with_insane_hash_oracle(mut self, oracle: Box<dyn HashOracle + 'intp>) -> Self {
+        self.hash_oracle = Some(oracle);
+        self
+    }
+
+    /// Runs the iterator to completion, i.e. until the end of the witness or
+    /// its first error, and reports the constraints satisfied until then
+    /// along with the signatures and preimages missing from the witness,
+    /// instead of only the error.
+    ///
+    /// Signatures are verified as they are by this iterator, so a witness
+    /// with placeholders, e.g. empty signatures, may be inspected with any
+    /// of the `iter` methods of the [`Interpreter`].
+    pub fn partial_spend(mut self) -> PartialSpend<Ext> {
+        let mut missing_keys = vec![];
+        let mut missing_key_hashes = vec![];
+        let mut missing_preimages = vec![];
+        missing_keys.extend(self.public_key.into_iter().chain(self.cov).cloned());
+        for node_state in &self.state {
+            missing_keys.extend(node_state.node.iter_pk());
+            for node in node_state.node.iter() {
+                match node.node {
+                    Terminal::RawPkH(h) => missing_key_hashes.push(h),
+                    Terminal::Sha256(h) => missing_preimages.push(HashLockType::Sha256(h)),
+                    Terminal::Hash256(h) => missing_preimages.push(HashLockType::Hash256(h)),
+                    Terminal::Hash160(h) => missing_preimages.push(HashLockType::Hash160(h)),
+                    Terminal::Ripemd160(h) => missing_preimages.push(HashLockType::Ripemd160(h)),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut satisfied = vec![];
+        let mut error = None;
+        for res in &mut self {
+            match res {
+                Ok(constraint) => satisfied.push(constraint),
+                Err(e) => error = Some(e),
+            }
+        }
+        for constraint in &satisfied {
+            match *constraint {
+                SatisfiedConstraint::PublicKey { ref key_sig } => {
+                    let key = key_sig_key(key_sig);
+                    missing_keys.retain(|pk| *pk != key);
+                }
+                SatisfiedConstraint::PublicKeyHash {
+                    keyhash,
+                    ref key_sig,
+                } => {
+                    let key = key_sig_key(key_sig);
+                    missing_keys.retain(|pk| *pk != key);
+                    missing_key_hashes.retain(|h| *h != keyhash);
+                }
+                SatisfiedConstraint::HashLock { hash, .. } => {
+                    missing_preimages.retain(|h| *h != hash);
+                }
+                _ => {}
+            }
+        }
+        PartialSpend {
+            satisfied,
+            missing_keys,
+            missing_key_hashes,
+            missing_preimages,
+            error,
+        }
+    }
+
     /// Helper function to push a NodeEvaluationState on state stack
     fn push_evaluation_state(
         &mut self,
@@ -700,9 +1037,13 @@ where
                 Terminal::After(ref n) => {
                     debug_assert_eq!(node_state.n_evaluated, 0);
                     debug_assert_eq!(node_state.n_satisfied, 0);
-                    let res = self
-                        .stack
-                        .evaluate_after(&LockTime::from(*n), self.lock_time);
+                    let n = LockTime::from(*n);
+                    let lock_time = if self.options.verify_cltv_csv {
+                        self.lock_time
+                    } else {
+                        n
+                    };
+                    let res = self.stack.evaluate_after(&n, lock_time);
                     if res.is_some() {
                         return res;
                     }
@@ -710,7 +1051,12 @@ where
                 Terminal::Older(ref n) => {
                     debug_assert_eq!(node_state.n_evaluated, 0);
                     debug_assert_eq!(node_state.n_satisfied, 0);
-                    let res = self.stack.evaluate_older(n, self.age);
+                    let age = if self.options.verify_cltv_csv {
+                        self.age
+                    } else {
+                        (*n).into()
+                    };
+                    let res = self.stack.evaluate_older(n, age);
                     if res.is_some() {
                         return res;
                     }
@@ -718,7 +1064,12 @@ where
                 Terminal::Sha256(ref hash) => {
                     debug_assert_eq!(node_state.n_evaluated, 0);
                     debug_assert_eq!(node_state.n_satisfied, 0);
-                    let res = self.stack.evaluate_sha256(hash);
+                    let res = match self.hash_oracle {
+                        Some(ref mut oracle) => self
+                            .stack
+                            .evaluate_hash_oracle(HashLockType::Sha256(*hash), &mut **oracle),
+                        None => self.stack.evaluate_sha256(hash),
+                    };
                     if res.is_some() {
                         return res;
                     }
@@ -726,7 +1077,12 @@ where
                 Terminal::Hash256(ref hash) => {
                     debug_assert_eq!(node_state.n_evaluated, 0);
                     debug_assert_eq!(node_state.n_satisfied, 0);
-                    let res = self.stack.evaluate_hash256(hash);
+                    let res = match self.hash_oracle {
+                        Some(ref mut oracle) => self
+                            .stack
+                            .evaluate_hash_oracle(HashLockType::Hash256(*hash), &mut **oracle),
+                        None => self.stack.evaluate_hash256(hash),
+                    };
                     if res.is_some() {
                         return res;
                     }
@@ -734,7 +1090,12 @@ where
                 Terminal::Hash160(ref hash) => {
                     debug_assert_eq!(node_state.n_evaluated, 0);
                     debug_assert_eq!(node_state.n_satisfied, 0);
-                    let res = self.stack.evaluate_hash160(hash);
+                    let res = match self.hash_oracle {
+                        Some(ref mut oracle) => self
+                            .stack
+                            .evaluate_hash_oracle(HashLockType::Hash160(*hash), &mut **oracle),
+                        None => self.stack.evaluate_hash160(hash),
+                    };
                     if res.is_some() {
                         return res;
                     }
@@ -742,7 +1103,12 @@ where
                 Terminal::Ripemd160(ref hash) => {
                     debug_assert_eq!(node_state.n_evaluated, 0);
                     debug_assert_eq!(node_state.n_satisfied, 0);
-                    let res = self.stack.evaluate_ripemd160(hash);
+                    let res = match self.hash_oracle {
+                        Some(ref mut oracle) => self
+                            .stack
+                            .evaluate_hash_oracle(HashLockType::Ripemd160(*hash), &mut **oracle),
+                        None => self.stack.evaluate_ripemd160(hash),
+                    };
                     if res.is_some() {
                         return res;
                     }
@@ -772,6 +1138,14 @@ where
                         self.push_evaluation_state(node_state.node, 1, 1);
                         self.push_evaluation_state(sub, 0, 0);
                     }
+                    Some(stack::Element::Push(v)) if !self.options.require_minimal_if => {
+                        if cast_to_bool(v) {
+                            self.push_evaluation_state(node_state.node, 1, 1);
+                            self.push_evaluation_state(sub, 0, 0);
+                        } else {
+                            self.stack.push(stack::Element::Dissatisfied);
+                        }
+                    }
                     Some(stack::Element::Push(_v)) => {
                         return Some(Err(Error::UnexpectedStackElementPush))
                     }
@@ -901,17 +1275,25 @@ where
                         Some(stack::Element::Dissatisfied) => {
                             self.push_evaluation_state(right, 0, 0)
                         }
+                        Some(stack::Element::Push(v)) if !self.options.require_minimal_if => {
+                            if cast_to_bool(v) {
+                                self.push_evaluation_state(left, 0, 0)
+                            } else {
+                                self.push_evaluation_state(right, 0, 0)
+                            }
+                        }
                         Some(stack::Element::Push(_v)) => {
                             return Some(Err(Error::UnexpectedStackElementPush))
                         }
                         None => return Some(Err(Error::UnexpectedStackEnd)),
                     }
                 }
-                Terminal::Thresh(ref _k, ref subs) if node_state.n_evaluated == 0 => {
+                Terminal::Thresh(ref thresh) if node_state.n_evaluated == 0 => {
                     self.push_evaluation_state(node_state.node, 1, 0);
-                    self.push_evaluation_state(&subs[0], 0, 0);
+                    self.push_evaluation_state(&thresh.data()[0], 0, 0);
                 }
-                Terminal::Thresh(k, ref subs) if node_state.n_evaluated == subs.len() => {
+                Terminal::Thresh(ref thresh) if node_state.n_evaluated == thresh.n() => {
+                    let k = thresh.k();
                     match self.stack.pop() {
                         Some(stack::Element::Dissatisfied) if node_state.n_satisfied == k => {
                             self.stack.push(stack::Element::Satisfied)
@@ -928,7 +1310,8 @@ where
                         None => return Some(Err(Error::UnexpectedStackEnd)),
                     }
                 }
-                Terminal::Thresh(ref _k, ref subs) if node_state.n_evaluated != 0 => {
+                Terminal::Thresh(ref thresh) if node_state.n_evaluated != 0 => {
+                    let subs = thresh.data();
                     match self.stack.pop() {
                         Some(stack::Element::Dissatisfied) => {
                             self.push_evaluation_state(
@@ -952,9 +1335,10 @@ where
                         None => return Some(Err(Error::UnexpectedStackEnd)),
                     }
                 }
-                Terminal::MultiA(k, ref subs) => {
-                    if node_state.n_evaluated == subs.len() {
-                        if node_state.n_satisfied == k {
+                Terminal::MultiA(ref thresh) => {
+                    let subs = thresh.data();
+                    if node_state.n_evaluated == thresh.n() {
+                        if node_state.n_satisfied == thresh.k() {
                             self.stack.push(stack::Element::Satisfied);
                         } else {
                             self.stack.push(stack::Element::Dissatisfied);
@@ -993,7 +1377,8 @@ where
                         }
                     }
                 }
-                Terminal::Multi(ref k, ref subs) if node_state.n_evaluated == 0 => {
+                Terminal::Multi(ref thresh) if node_state.n_evaluated == 0 => {
+                    let (k, subs) = (thresh.k(), thresh.data());
                     let len = self.stack.len();
                     if len < k + 1 {
                         return Some(Err(Error::InsufficientSignaturesMultiSig));
@@ -1009,7 +1394,7 @@ where
                                     .map(|sig| *sig == stack::Element::Dissatisfied)
                                     .filter(|empty| *empty)
                                     .count();
-                                if nonsat == *k + 1 {
+                                if nonsat == k + 1 {
                                     self.stack.push(stack::Element::Dissatisfied);
                                 } else {
                                     return Some(Err(Error::MissingExtraZeroMultiSig));
@@ -1040,8 +1425,9 @@ where
                         }
                     }
                 }
-                Terminal::Multi(k, ref subs) => {
-                    if node_state.n_satisfied == k {
+                Terminal::Multi(ref thresh) => {
+                    let subs = thresh.data();
+                    if node_state.n_satisfied == thresh.k() {
                         //multi-sig bug: Pop extra 0
                         if let Some(stack::Element::Dissatisfied) = self.stack.pop() {
                             self.stack.push(stack::Element::Satisfied);
@@ -1129,9 +1515,10 @@ where
                         unreachable!("Internal error: Legacy cov check in schnorr sigs")
                     }
                 };
-                // Creating a context is no-longer expensive
-                let secp = secp256k1_zkp::Secp256k1::verification_only();
-                if secp.verify_ecdsa(&msg, &ecdsa_sig, &ec_pk.inner).is_err() {
+                let valid = crate::with_secp(|secp| {
+                    secp.verify_ecdsa(&msg, &ecdsa_sig, &ec_pk.inner).is_ok()
+                });
+                if !valid {
                     return Some(Err(Error::PkEvaluationError(PkEvalErrInner::from(*pk))));
                 }
                 self.stack.0.clear();
@@ -1167,6 +1554,18 @@ where
     }
 }
 
+/// Casts a stack element to a boolean like `IF` does without the `MINIMALIF`
+/// rule: any value other than zero or negative zero is true
+fn cast_to_bool(v: &[u8]) -> bool {
+    match v.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&b| b != 0) || (last != 0 && last != 0x80),
+        None => false,
+    }
+}
+
+// The length of a serialized ECDSA adaptor signature
+const ADAPTOR_SIG_LEN: usize = 162;
+
 /// Helper function to verify serialized signature
 fn verify_sersig<'txin>(
     verify_sig: &mut Box<dyn FnMut(&KeySigPair) -> bool + 'txin>,
@@ -1175,6 +1574,14 @@ fn verify_sersig<'txin>(
 ) -> Result<KeySigPair, Error> {
     match pk {
         BitcoinKey::Fullkey(pk) => {
+            // Adaptor signatures followed by their hashtype byte, which are
+            // longer than any DER signature
+            if sigser.len() == ADAPTOR_SIG_LEN + 1
+                && secp256k1_zkp::EcdsaAdaptorSignature::from_slice(&sigser[..ADAPTOR_SIG_LEN])
+                    .is_ok()
+            {
+                return Err(Error::PreAdaptedSignature(*pk));
+            }
             let ecdsa_sig = elementssig_from_rawsig(sigser)?;
             let key_sig_pair = KeySigPair::Ecdsa(*pk, ecdsa_sig);
             if verify_sig(&key_sig_pair) {
@@ -1292,6 +1699,7 @@ mod tests {
         ) -> Iter<'elem, 'txin, NoExt> {
             Iter {
                 verify_sig: verify_fn,
+                hash_oracle: None,
                 stack,
                 public_key: None,
                 state: vec![NodeEvaluationState {
@@ -1301,6 +1709,7 @@ mod tests {
                 }],
                 age: Sequence::from_height(1002),
                 lock_time: LockTime::from_height(1002).unwrap(),
+                options: InterpreterOptions::default(),
                 cov: None,
                 has_errored: false,
                 txenv: None,
@@ -1393,6 +1802,30 @@ mod tests {
             }]
         );
 
+        //Check Sha256 with a hash oracle accepting another preimage
+        let other_preimage = [0xcd; 32];
+        let stack = Stack::from(vec![stack::Element::Push(&other_preimage)]);
+        let oracle = |hash: &HashLockType, preimage: &[u8; 32]| {
+            *hash == HashLockType::Sha256(sha256_hash) && *preimage == [0xcd; 32]
+        };
+        let constraints =
+            from_stack(Box::new(vfyfn), stack, &sha256).with_insane_hash_oracle(Box::new(oracle));
+        let oracle_satisfied: Result<Vec<SatisfiedConstraint<NoExt>>, Error> =
+            constraints.collect();
+        assert_eq!(
+            oracle_satisfied.unwrap(),
+            vec![SatisfiedConstraint::HashLock {
+                hash: HashLockType::Sha256(sha256_hash),
+                preimage: other_preimage,
+            }]
+        );
+        let stack = Stack::from(vec![stack::Element::Push(&preimage)]);
+        let constraints =
+            from_stack(Box::new(vfyfn), stack, &sha256).with_insane_hash_oracle(Box::new(oracle));
+        assert!(constraints
+            .collect::<Result<Vec<SatisfiedConstraint<NoExt>>, Error>>()
+            .is_err());
+
         //Check Shad256
         let stack = Stack::from(vec![stack::Element::Push(&preimage)]);
         let constraints = from_stack(Box::new(vfyfn), stack, &hash256);
@@ -1748,6 +2181,408 @@ mod tests {
         assert!(multi_a_error.is_err());
     }
 
+    #[test]
+    fn verify_confidential_prevout() {
+        use std::collections::HashMap;
+
+        use elements::confidential::{self, AssetBlindingFactor, ValueBlindingFactor};
+
+        let secp = Secp256k1::new();
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+        let desc = Descriptor::<bitcoin::PublicKey>::new_wpkh(pk).unwrap();
+
+        let value = confidential::Value::new_confidential_from_assetid(
+            &secp,
+            1000,
+            elements::AssetId::from_slice(&[3; 32]).unwrap(),
+            ValueBlindingFactor::from_slice(&[1; 32]).unwrap(),
+            AssetBlindingFactor::from_slice(&[2; 32]).unwrap(),
+        );
+        let mut prevout = elements::TxOut {
+            value,
+            script_pubkey: desc.script_pubkey(),
+            ..Default::default()
+        };
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![elements::TxIn::default()],
+            output: vec![],
+        };
+
+        let sighash = sighash::SigHashCache::new(&tx).segwitv0_sighash(
+            0,
+            &desc.script_code().unwrap(),
+            value,
+            EcdsaSigHashType::All,
+        );
+        let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).unwrap();
+        let mut sigs = HashMap::new();
+        sigs.insert(pk, (secp.sign_ecdsa(&msg, &sk), EcdsaSigHashType::All));
+        let (witness, script_sig) = desc.get_satisfaction(&sigs).unwrap();
+        let interpreter = Interpreter::from_txdata(
+            &desc.script_pubkey(),
+            &script_sig,
+            &witness,
+            Sequence::ZERO,
+            LockTime::ZERO,
+        )
+        .unwrap();
+        let genesis_hash = elements::BlockHash::all_zeros();
+
+        let prevouts = sighash::Prevouts::One(0, prevout.clone());
+        let res = interpreter
+            .iter_with_prevouts(&secp, &tx, 0, &prevouts, genesis_hash)
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(res.unwrap().len(), 1);
+
+        // The signature does not commit to the unblinded value
+        prevout.value = confidential::Value::Explicit(1000);
+        let prevouts = sighash::Prevouts::One(0, prevout);
+        let res = interpreter
+            .iter_with_prevouts(&secp, &tx, 0, &prevouts, genesis_hash)
+            .collect::<Result<Vec<_>, _>>();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn verify_with_shared_cache() {
+        use std::collections::HashMap;
+
+        use elements::schnorr::TapTweak;
+
+        let secp = Secp256k1::new();
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+        let desc = Descriptor::<bitcoin::PublicKey>::new_wpkh(pk).unwrap();
+        let keypair = secp256k1_zkp::KeyPair::from_secret_key(&secp, &sk);
+        let (internal_key, _parity) = keypair.x_only_public_key();
+        let tr_spk = elements::Script::new_v1_p2tr(&secp, internal_key, None);
+
+        // Two segwit v0 inputs and a taproot key spend
+        let spks = [desc.script_pubkey(), desc.script_pubkey(), tr_spk];
+        let spent_utxos: Vec<_> = spks
+            .iter()
+            .map(|spk| elements::TxOut {
+                value: elements::confidential::Value::Explicit(1000),
+                script_pubkey: spk.clone(),
+                ..Default::default()
+            })
+            .collect();
+        let prevouts = sighash::Prevouts::All(&spent_utxos[..]);
+        let mut tx = elements::Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![elements::TxIn::default(); 3],
+            output: vec![],
+        };
+        let genesis_hash = elements::BlockHash::all_zeros();
+
+        let mut witnesses = vec![];
+        let mut cache = sighash::SigHashCache::new(&tx);
+        for (idx, utxo) in spent_utxos.iter().enumerate().take(2) {
+            let sighash = cache.segwitv0_sighash(
+                idx,
+                &desc.script_code().unwrap(),
+                utxo.value,
+                EcdsaSigHashType::All,
+            );
+            let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).unwrap();
+            let mut sigs = HashMap::new();
+            sigs.insert(pk, (secp.sign_ecdsa(&msg, &sk), EcdsaSigHashType::All));
+            witnesses.push(desc.get_satisfaction(&sigs).unwrap().0);
+        }
+        let sighash = cache
+            .taproot_key_spend_signature_hash(
+                2,
+                &prevouts,
+                elements::SchnorrSigHashType::Default,
+                genesis_hash,
+            )
+            .unwrap();
+        let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).unwrap();
+        let tweaked = keypair.tap_tweak(&secp, None).to_inner();
+        let sig = elements::SchnorrSig {
+            sig: secp.sign_schnorr_with_aux_rand(&msg, &tweaked, &[0u8; 32]),
+            hash_ty: elements::SchnorrSigHashType::Default,
+        };
+        witnesses.push(vec![sig.to_vec()]);
+        for (txin, witness) in tx.input.iter_mut().zip(witnesses) {
+            txin.witness.script_witness = witness;
+        }
+
+        let interpreters: Vec<_> = tx
+            .input
+            .iter()
+            .zip(&spent_utxos)
+            .map(|(txin, utxo)| {
+                Interpreter::<NoExt>::from_txdata(
+                    &utxo.script_pubkey,
+                    &txin.script_sig,
+                    &txin.witness.script_witness,
+                    txin.sequence,
+                    tx.lock_time,
+                )
+                .unwrap()
+            })
+            .collect();
+        let key_sigs: Vec<_> = interpreters
+            .iter()
+            .map(
+                |interpreter| match interpreter.iter_assume_sigs().next().unwrap().unwrap() {
+                    SatisfiedConstraint::PublicKey { key_sig }
+                    | SatisfiedConstraint::PublicKeyHash { key_sig, .. } => key_sig,
+                    c => panic!("expected a signature, got {:?}", c),
+                },
+            )
+            .collect();
+
+        // All the inputs are verified with the same cache, with the same
+        // results as with a cache per signature, including for a signature
+        // checked against the wrong input
+        let mut cache = sighash::SigHashCache::new(&tx);
+        for (idx, interpreter) in interpreters.iter().enumerate() {
+            for (sig_idx, key_sig) in key_sigs.iter().enumerate() {
+                // ECDSA signatures cannot be checked for taproot spends
+                if interpreter.is_taproot_v1_key_spend() && sig_idx != idx {
+                    continue;
+                }
+                let expected =
+                    interpreter.verify_sig(&secp, &tx, idx, &prevouts, genesis_hash, key_sig);
+                assert_eq!(expected, idx == sig_idx);
+                assert_eq!(
+                    interpreter.verify_sig_with_cache(
+                        &secp,
+                        &mut cache,
+                        idx,
+                        &prevouts,
+                        genesis_hash,
+                        key_sig
+                    ),
+                    expected
+                );
+            }
+        }
+        for (idx, interpreter) in interpreters.iter().enumerate() {
+            let txenv = TxEnv::new(&tx, &spent_utxos, idx).unwrap();
+            let res = interpreter
+                .iter_with_cache(&secp, &txenv, &mut cache, genesis_hash)
+                .collect::<Result<Vec<_>, _>>();
+            assert_eq!(res.unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn interpreter_options() {
+        let pk = bitcoin::PublicKey::from_str(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        let ms = Miniscript::<bitcoin::PublicKey, crate::Segwitv0>::from_str(&format!(
+            "or_i(older(2000),pk({}))",
+            pk
+        ))
+        .unwrap();
+        let script = ms.encode();
+        let spk = script.to_v0_p2wsh();
+        let script_sig = elements::Script::new();
+        // Non-minimal true argument of IF
+        let witness = vec![vec![2], script.into_bytes()];
+        let from_txdata = |options| {
+            Interpreter::<NoExt>::from_txdata_with_options(
+                &spk,
+                &script_sig,
+                &witness,
+                Sequence::ZERO,
+                LockTime::ZERO,
+                options,
+            )
+        };
+
+        let interpreter = from_txdata(InterpreterOptions::default()).unwrap();
+        let res = interpreter
+            .iter_assume_sigs()
+            .collect::<Result<Vec<_>, _>>();
+        assert!(matches!(res, Err(Error::UnexpectedStackElementPush)));
+
+        let interpreter = from_txdata(InterpreterOptions::consensus()).unwrap();
+        let res = interpreter
+            .iter_assume_sigs()
+            .collect::<Result<Vec<_>, _>>();
+        assert!(matches!(res, Err(Error::RelativeLocktimeNotMet(2000))));
+
+        let interpreter = from_txdata(InterpreterOptions {
+            verify_cltv_csv: false,
+            ..InterpreterOptions::consensus()
+        })
+        .unwrap();
+        let res = interpreter
+            .iter_assume_sigs()
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(res.unwrap().len(), 1);
+
+        let res = from_txdata(InterpreterOptions {
+            max_stack: 0,
+            ..InterpreterOptions::default()
+        });
+        assert!(matches!(res, Err(Error::TooManyStackElements(1))));
+        // The tapscript limit does not apply to segwit v0 spends
+        let res = from_txdata(InterpreterOptions {
+            max_tap_stack: 0,
+            ..InterpreterOptions::default()
+        });
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn pre_adapted_signatures() {
+        use std::collections::HashMap;
+
+        use crate::{AdaptorPoint, ElementsAdaptorSig, Segwitv0};
+
+        let (pks, der_sigs, ecdsa_sigs, msg, secp, ..) = setup_keys_sigs(2);
+        let mut sk = [0; 32];
+        sk[0] = 2;
+        let sk = secp256k1_zkp::SecretKey::from_slice(&sk).unwrap();
+        let adaptor_secret = secp256k1_zkp::SecretKey::from_slice(&[3; 32]).unwrap();
+        let adaptor_point = secp256k1_zkp::PublicKey::from_secret_key(&secp, &adaptor_secret);
+        let adaptor_sig = secp256k1_zkp::EcdsaAdaptorSignature::encrypt_no_aux_rand(
+            &secp,
+            &msg,
+            &sk,
+            &adaptor_point,
+        );
+
+        let ms = Miniscript::<bitcoin::PublicKey, Segwitv0>::from_str(&format!(
+            "and_v(v:pk({}),pk({}))",
+            pks[0], pks[1]
+        ))
+        .unwrap();
+        let mut sigs = HashMap::new();
+        sigs.insert(pks[0], ecdsa_sigs[0]);
+        let mut adaptor_sigs = HashMap::<_, ElementsAdaptorSig>::new();
+        adaptor_sigs.insert(
+            (pks[1], adaptor_point),
+            (adaptor_sig, elements::EcdsaSigHashType::All),
+        );
+        // Adaptor signatures are only used for a satisfaction with an adaptor point
+        assert!(ms.satisfy((&sigs, &adaptor_sigs)).is_err());
+        let witness = ms
+            .satisfy((&sigs, &adaptor_sigs, AdaptorPoint(adaptor_point)))
+            .unwrap();
+        assert_eq!(witness[0].len(), ADAPTOR_SIG_LEN + 1);
+        assert_eq!(witness[1], der_sigs[0]);
+
+        // The interpreter rejects the witness until it is adapted
+        let secp_ref = &secp;
+        let mut vfyfn: Box<dyn FnMut(&KeySigPair) -> bool> =
+            Box::new(move |pksig: &KeySigPair| match pksig {
+                KeySigPair::Ecdsa(pk, ecdsa_sig) => {
+                    secp_ref.verify_ecdsa(&msg, &ecdsa_sig.0, &pk.inner).is_ok()
+                }
+                KeySigPair::Schnorr(..) => false,
+            });
+        let key = BitcoinKey::Fullkey(pks[1]);
+        assert!(matches!(
+            verify_sersig(&mut vfyfn, &key, &witness[0]),
+            Err(Error::PreAdaptedSignature(pk)) if pk == pks[1]
+        ));
+        let sig = adaptor_sig.decrypt(&adaptor_secret).unwrap();
+        let mut sigser = sig.serialize_der().to_vec();
+        sigser.push(0x01);
+        assert!(verify_sersig(&mut vfyfn, &key, &sigser).is_ok());
+    }
+
+    #[test]
+    fn partial_spend() {
+        let (_, _, _, sighash, secp, xpks, schnorr_sigs, ser_schnorr_sigs) = setup_keys_sigs(3);
+        let secp_ref = &secp;
+        let ms = x_only_no_checks_ms(&format!(
+            "and_v(v:multi_a(2,{},{},{}),sha256({}))",
+            xpks[0],
+            xpks[1],
+            xpks[2],
+            sha256::Hash::hash(&[0xab; 32])
+        ));
+        // A witness with the first signature only, and placeholders for the
+        // other signatures and the preimage
+        let stack = Stack::from(vec![
+            stack::Element::Dissatisfied,
+            stack::Element::Dissatisfied,
+            stack::Element::Dissatisfied,
+            stack::Element::Push(&ser_schnorr_sigs[0]),
+        ]);
+        let iter: Iter<'_, '_, NoExt> = Iter {
+            verify_sig: Box::new(move |pksig: &KeySigPair| match pksig {
+                KeySigPair::Schnorr(xpk, schnorr_sig) => secp_ref
+                    .verify_schnorr(&schnorr_sig.sig, &sighash, xpk)
+                    .is_ok(),
+                KeySigPair::Ecdsa(..) => false,
+            }),
+            hash_oracle: None,
+            stack,
+            public_key: None,
+            state: vec![NodeEvaluationState {
+                node: &ms,
+                n_evaluated: 0,
+                n_satisfied: 0,
+            }],
+            age: Sequence::ZERO,
+            lock_time: LockTime::ZERO,
+            options: InterpreterOptions::default(),
+            cov: None,
+            has_errored: false,
+            txenv: None,
+            sig_type: SigType::Schnorr,
+        };
+        let partial = iter.partial_spend();
+        assert!(!partial.is_complete());
+        assert!(matches!(partial.error, Some(Error::VerifyFailed)));
+        assert_eq!(
+            partial.satisfied,
+            vec![SatisfiedConstraint::PublicKey {
+                key_sig: KeySigPair::Schnorr(xpks[0], schnorr_sigs[0])
+            }]
+        );
+        assert_eq!(partial.n_signatures(), 1);
+        assert_eq!(
+            partial.missing_keys,
+            vec![
+                BitcoinKey::XOnlyPublicKey(xpks[1]),
+                BitcoinKey::XOnlyPublicKey(xpks[2])
+            ]
+        );
+        assert_eq!(partial.missing_preimages.len(), 1);
+        assert!(partial.missing_key_hashes.is_empty());
+
+        // The keys of raw key hashes are unknown, their hashes are reported
+        let keyhash = hash160::Hash::hash(&[0xcd; 33]);
+        let ms = no_checks_ms(&format!("c:expr_raw_pkh({})", keyhash));
+        let iter: Iter<'_, '_, NoExt> = Iter {
+            verify_sig: Box::new(|_: &KeySigPair| false),
+            hash_oracle: None,
+            stack: Stack::from(vec![]),
+            public_key: None,
+            state: vec![NodeEvaluationState {
+                node: &ms,
+                n_evaluated: 0,
+                n_satisfied: 0,
+            }],
+            age: Sequence::ZERO,
+            lock_time: LockTime::ZERO,
+            options: InterpreterOptions::default(),
+            cov: None,
+            has_errored: false,
+            txenv: None,
+            sig_type: SigType::Ecdsa,
+        };
+        let partial = iter.partial_spend();
+        assert!(matches!(partial.error, Some(Error::UnexpectedStackEnd)));
+        assert!(partial.missing_keys.is_empty());
+        assert_eq!(partial.missing_key_hashes, vec![keyhash]);
+    }
+
     // By design there is no support for parse a miniscript with BitcoinKey
     // because it does not implement FromStr
     fn no_checks_ms(ms: &str) -> Miniscript<BitcoinKey, NoChecks> {
@@ -2,6 +2,11 @@
 // SPDX-License-Identifier: CC0-1.0
 
 //! Interpreter stack
+//!
+//! The stack elements are interpreted with the typed accessors of
+//! [`Element`], e.g. by the [`crate::extensions::ParseableExt::evaluate`]
+//! implementations of extensions, which return an error for elements of
+//! another type or encoding.
 
 use std::ops::Index;
 
@@ -9,9 +14,11 @@ use elements::hashes::{hash160, ripemd160, sha256, Hash};
 use elements::{self, opcodes, script, LockTime, Sequence};
 
 use super::error::PkEvalErrInner;
-use super::{verify_sersig, BitcoinKey, Error, HashLockType, KeySigPair, SatisfiedConstraint};
+use super::{
+    verify_sersig, BitcoinKey, Error, HashLockType, HashOracle, KeySigPair, SatisfiedConstraint,
+};
 use crate::miniscript::context::SigType;
-use crate::{hash256, Extension};
+use crate::{hash256, Extension, RelLockTime};
 
 /// Definition of Stack Element of the Stack used for interpretation of Miniscript.
 ///
@@ -48,6 +55,16 @@ impl<'txin> From<&'txin [u8]> for Element<'txin> {
 }
 
 impl<'txin> Element<'txin> {
+    /// The element of a boolean, as pushed by a satisfied or dissatisfied
+    /// fragment
+    pub fn from_bool(b: bool) -> Element<'static> {
+        if b {
+            Element::Satisfied
+        } else {
+            Element::Dissatisfied
+        }
+    }
+
     /// Converts a Bitcoin `script::Instruction` to a stack element
     ///
     /// Supports `OP_1` but no other numbers since these are not used by Miniscript
@@ -62,16 +79,57 @@ impl<'txin> Element<'txin> {
         }
     }
 
-    /// Errs when the element is not a push
-    pub(crate) fn try_push(&self) -> Result<&[u8], Error> {
-        match self {
+    /// The bytes of a push, other than the empty push and the push of `1`
+    /// which are booleans. Errs when the element is a boolean.
+    pub fn try_push(&self) -> Result<&'txin [u8], Error> {
+        match *self {
             Element::Push(x) => Ok(x),
             _ => Err(Error::ExpectedPush),
         }
     }
 
+    /// The boolean of a satisfied or dissatisfied fragment. Errs when the
+    /// element is a push.
+    pub fn as_bool(&self) -> Result<bool, Error> {
+        match *self {
+            Element::Satisfied => Ok(true),
+            Element::Dissatisfied => Ok(false),
+            Element::Push(_) => Err(Error::UnexpectedStackElementPush),
+        }
+    }
+
+    /// The element as a script number, i.e. a minimally encoded number of up
+    /// to 4 bytes, as used by the arithmetic opcodes of Bitcoin Script.
+    pub fn as_num(&self) -> Result<i64, Error> {
+        let v = self.into_slice();
+        let minimal = match v.split_last() {
+            None => true,
+            // The last byte may only be 0x00 or 0x80 for its sign bit, when
+            // the previous byte uses the bit
+            Some((last, rest)) => {
+                last & 0x7f != 0 || rest.last().map_or(false, |prev| prev & 0x80 != 0)
+            }
+        };
+        if v.len() > 4 || !minimal {
+            return Err(Error::InvalidStackNumber(v.to_vec()));
+        }
+        script::read_scriptint(v).map_err(|_| Error::InvalidStackNumber(v.to_vec()))
+    }
+
+    /// The element as an 8 byte little-endian signed number, as used by the
+    /// 64-bit arithmetic opcodes of Elements.
+    pub fn as_le64(&self) -> Result<i64, Error> {
+        let v = self.into_slice();
+        let mut bytes = [0; 8];
+        if v.len() != bytes.len() {
+            return Err(Error::InvalidStackNumber(v.to_vec()));
+        }
+        bytes.copy_from_slice(v);
+        Ok(i64::from_le_bytes(bytes))
+    }
+
     /// Convert element into slice
-    pub(crate) fn into_slice(self) -> &'txin [u8] {
+    pub fn into_slice(self) -> &'txin [u8] {
         match self {
             Element::Satisfied => &[1],
             Element::Dissatisfied => &[],
@@ -277,12 +335,12 @@ impl<'txin> Stack<'txin> {
     /// booleans
     pub(super) fn evaluate_older<Ext: Extension>(
         &mut self,
-        n: &Sequence,
+        n: &RelLockTime,
         age: Sequence,
     ) -> Option<Result<SatisfiedConstraint<Ext>, Error>> {
-        if age >= *n {
+        if n.is_satisfied_by(age) {
             self.push(Element::Satisfied);
-            Some(Ok(SatisfiedConstraint::RelativeTimelock { n: *n }))
+            Some(Ok(SatisfiedConstraint::RelativeTimelock { n: (*n).into() }))
         } else {
             Some(Err(Error::RelativeLocktimeNotMet(n.to_consensus_u32())))
         }
@@ -313,6 +371,30 @@ impl<'txin> Stack<'txin> {
         }
     }
 
+    /// Helper function to evaluate a hashlock with a [`HashOracle`] instead
+    /// of hashing the preimage
+    pub(super) fn evaluate_hash_oracle<Ext: Extension>(
+        &mut self,
+        hash: HashLockType,
+        oracle: &mut dyn HashOracle,
+    ) -> Option<Result<SatisfiedConstraint<Ext>, Error>> {
+        if let Some(Element::Push(preimage)) = self.pop() {
+            if preimage.len() != 32 {
+                return Some(Err(Error::HashPreimageLengthMismatch));
+            }
+            let preimage = preimage_from_sl(preimage);
+            if oracle.check_preimage(&hash, &preimage) {
+                self.push(Element::Satisfied);
+                Some(Ok(SatisfiedConstraint::HashLock { hash, preimage }))
+            } else {
+                self.push(Element::Dissatisfied);
+                None
+            }
+        } else {
+            Some(Err(Error::UnexpectedStackEnd))
+        }
+    }
+
     /// Helper function to evaluate a Hash256 Node.
     /// `SIZE 32 EQUALVERIFY HASH256 h EQUAL`
     pub(super) fn evaluate_hash256<Ext: Extension>(
@@ -428,3 +510,44 @@ fn preimage_from_sl(sl: &[u8]) -> [u8; 32] {
         preimage
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_elements() {
+        assert_eq!(Element::from_bool(true), Element::Satisfied);
+        assert!(!Element::from(&[][..]).as_bool().unwrap());
+        assert!(Element::from(&[1][..]).as_bool().unwrap());
+        assert!(Element::Push(&[2]).as_bool().is_err());
+        assert!(Element::Satisfied.try_push().is_err());
+
+        assert_eq!(Element::Dissatisfied.as_num().unwrap(), 0);
+        assert_eq!(Element::Satisfied.as_num().unwrap(), 1);
+        assert_eq!(Element::Push(&[0x81]).as_num().unwrap(), -1);
+        assert_eq!(Element::Push(&[0xff, 0x00]).as_num().unwrap(), 255);
+        assert_eq!(Element::Push(&[0xff, 0x80]).as_num().unwrap(), -255);
+        let max = Element::Push(&[0xff, 0xff, 0xff, 0x7f]);
+        assert_eq!(max.as_num().unwrap(), 0x7fffffff);
+        // Non-minimal and overflowing encodings
+        for v in [
+            &[0x00][..],
+            &[0x80],
+            &[0x01, 0x00],
+            &[0x01, 0x80],
+            &[0, 0, 0, 0, 1],
+        ]
+        .iter()
+        {
+            match Element::Push(v).as_num() {
+                Err(Error::InvalidStackNumber(ref elem)) => assert_eq!(elem[..], v[..]),
+                res => panic!("unexpected {:?}", res),
+            }
+        }
+
+        let le = (-2i64).to_le_bytes();
+        assert_eq!(Element::Push(&le).as_le64().unwrap(), -2);
+        assert!(Element::Push(&le[..4]).as_le64().is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Interpreter of all the inputs of a transaction
+
+use elements::{self, secp256k1_zkp, sighash};
+
+use super::{Error, Interpreter, InterpreterOptions, SatisfiedConstraint};
+use crate::extensions::{CovExtArgs, ParseableExt, TxEnv};
+use crate::CovenantExt;
+
+/// Interprets the inputs of a transaction, sharing the work which does not
+/// depend on the input between all of them
+///
+/// The [`Interpreter`] of every input is inferred once, the first time the
+/// input is accessed, and all the signatures are verified with the same
+/// [`sighash::SigHashCache`], so that the hashes of the prevouts, sequences,
+/// issuances and outputs of the transaction are computed only once.
+pub struct TransactionInterpreter<'txin, Ext: ParseableExt> {
+    tx: &'txin elements::Transaction,
+    spent_utxos: &'txin [elements::TxOut],
+    genesis_hash: elements::BlockHash,
+    options: InterpreterOptions,
+    cache: sighash::SigHashCache<&'txin elements::Transaction>,
+    // The interpreters of the inputs accessed so far
+    interpreters: Vec<Option<Interpreter<'txin, Ext>>>,
+}
+
+impl<'txin> TransactionInterpreter<'txin, CovenantExt<CovExtArgs>> {
+    /// Creates an interpreter for the inputs of `tx`, which spend
+    /// `spent_utxos`
+    ///
+    /// For downstream custom implementations of [`crate::Extension`], use
+    /// [`TransactionInterpreter::new_ext`].
+    ///
+    /// # Errors
+    ///
+    /// If the number of spent utxos does not match the number of inputs.
+    pub fn new(
+        tx: &'txin elements::Transaction,
+        spent_utxos: &'txin [elements::TxOut],
+        genesis_hash: elements::BlockHash,
+    ) -> Result<Self, Error> {
+        TransactionInterpreter::new_ext(tx, spent_utxos, genesis_hash)
+    }
+}
+
+impl<'txin, Ext: ParseableExt> TransactionInterpreter<'txin, Ext> {
+    /// Creates an interpreter for the inputs of `tx`, which spend
+    /// `spent_utxos`
+    ///
+    /// # Errors
+    ///
+    /// If the number of spent utxos does not match the number of inputs.
+    pub fn new_ext(
+        tx: &'txin elements::Transaction,
+        spent_utxos: &'txin [elements::TxOut],
+        genesis_hash: elements::BlockHash,
+    ) -> Result<Self, Error> {
+        if tx.input.len() != spent_utxos.len() {
+            return Err(Error::SpentUtxosLenMismatch {
+                inputs: tx.input.len(),
+                spent_utxos: spent_utxos.len(),
+            });
+        }
+        Ok(TransactionInterpreter {
+            tx,
+            spent_utxos,
+            genesis_hash,
+            options: InterpreterOptions::default(),
+            cache: sighash::SigHashCache::new(tx),
+            interpreters: (0..tx.input.len()).map(|_| None).collect(),
+        })
+    }
+
+    /// Checks the rules given by `options` instead of the standardness ones
+    /// when inferring the interpreters of the inputs
+    pub fn with_options(mut self, options: InterpreterOptions) -> Self {
+        self.options = options;
+        self.interpreters.iter_mut().for_each(|i| *i = None);
+        self
+    }
+
+    /// The transaction being interpreted
+    pub fn tx(&self) -> &'txin elements::Transaction {
+        self.tx
+    }
+
+    /// The [`Interpreter`] of the input at `idx`, inferred from the spent
+    /// utxo and the input the first time it is accessed
+    ///
+    /// # Errors
+    ///
+    /// If the transaction has no input at `idx`, or if the interpreter cannot
+    /// be inferred, see [`Interpreter::from_txdata_with_options`].
+    pub fn input(&mut self, idx: usize) -> Result<&Interpreter<'txin, Ext>, Error> {
+        let txin = self
+            .tx
+            .input
+            .get(idx)
+            .ok_or(Error::InputIdxOutOfBounds(idx))?;
+        if self.interpreters[idx].is_none() {
+            let interpreter = Interpreter::from_txdata_with_options(
+                &self.spent_utxos[idx].script_pubkey,
+                &txin.script_sig,
+                &txin.witness.script_witness,
+                txin.sequence,
+                self.tx.lock_time,
+                self.options,
+            )?;
+            self.interpreters[idx] = Some(interpreter);
+        }
+        Ok(self.interpreters[idx]
+            .as_ref()
+            .expect("Interpreter inferred above"))
+    }
+
+    /// Verifies the input at `idx`, returning all the constraints it
+    /// satisfies, as yielded by [`Interpreter::iter`]
+    ///
+    /// # Errors
+    ///
+    /// If the interpreter of the input cannot be inferred, see
+    /// [`TransactionInterpreter::input`], or if the input is not satisfied.
+    pub fn verify_input<C: secp256k1_zkp::Verification>(
+        &mut self,
+        secp: &secp256k1_zkp::Secp256k1<C>,
+        idx: usize,
+    ) -> Result<Vec<SatisfiedConstraint<Ext>>, Error> {
+        self.input(idx)?;
+        let interpreter = self.interpreters[idx]
+            .as_ref()
+            .expect("Interpreter inferred above");
+        let txenv =
+            TxEnv::new(self.tx, self.spent_utxos, idx).expect("Lengths checked in constructor");
+        let res = interpreter
+            .iter_with_cache(secp, &txenv, &mut self.cache, self.genesis_hash)
+            .collect();
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use elements::hashes::Hash;
+    use elements::secp256k1_zkp::Secp256k1;
+    use elements::{EcdsaSigHashType, LockTime};
+
+    use super::*;
+    use crate::Descriptor;
+
+    #[test]
+    fn transaction_interpreter() {
+        let secp = Secp256k1::new();
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+        let desc = Descriptor::<bitcoin::PublicKey>::new_wpkh(pk).unwrap();
+
+        let spent_utxos = vec![
+            elements::TxOut {
+                value: elements::confidential::Value::Explicit(1000),
+                script_pubkey: desc.script_pubkey(),
+                ..Default::default()
+            };
+            2
+        ];
+        let mut tx = elements::Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![elements::TxIn::default(); 2],
+            output: vec![],
+        };
+        let genesis_hash = elements::BlockHash::all_zeros();
+
+        let mut cache = sighash::SigHashCache::new(&tx);
+        let mut witnesses = vec![];
+        for (idx, utxo) in spent_utxos.iter().enumerate() {
+            let sighash = cache.segwitv0_sighash(
+                idx,
+                &desc.script_code().unwrap(),
+                utxo.value,
+                EcdsaSigHashType::All,
+            );
+            let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).unwrap();
+            let mut sigs = HashMap::new();
+            sigs.insert(pk, (secp.sign_ecdsa(&msg, &sk), EcdsaSigHashType::All));
+            witnesses.push(desc.get_satisfaction(&sigs).unwrap().0);
+        }
+        for (txin, witness) in tx.input.iter_mut().zip(witnesses) {
+            txin.witness.script_witness = witness;
+        }
+
+        assert!(matches!(
+            TransactionInterpreter::new(&tx, &spent_utxos[..1], genesis_hash),
+            Err(Error::SpentUtxosLenMismatch {
+                inputs: 2,
+                spent_utxos: 1
+            })
+        ));
+        let mut interpreter = TransactionInterpreter::new(&tx, &spent_utxos, genesis_hash).unwrap();
+        assert!(interpreter.input(1).unwrap().is_segwit_v0());
+        for idx in 0..2 {
+            assert_eq!(interpreter.verify_input(&secp, idx).unwrap().len(), 1);
+        }
+        assert!(matches!(
+            interpreter.verify_input(&secp, 2),
+            Err(Error::InputIdxOutOfBounds(2))
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Key Type Bridging
+//!
+//! The public keys of the `bitcoin` crate and of the `secp256k1_zkp` crate
+//! used by `elements` can all be used as the keys of descriptors, miniscripts
+//! and policies, without wrapper types: [`bitcoin::PublicKey`],
+//! [`secp256k1_zkp::PublicKey`] and [`secp256k1_zkp::XOnlyPublicKey`], the
+//! latter two being re-exports of the keys of `secp256k1`, as are the ones of
+//! `bitcoin::secp256k1`.
+//!
+//! [`KeyBridge`] translates the keys of a descriptor, a miniscript or a
+//! policy from one of these types to another. The translated keys are the
+//! ones their [`ToPublicKey`] implementation would give, so that key hashes,
+//! e.g. of `pkh` fragments, and thus scripts and addresses, are unchanged.
+//!
+//! ```
+//! use std::str::FromStr;
+//!
+//! use elements_miniscript::key_bridge::KeyBridge;
+//! use elements_miniscript::{Descriptor, TranslatePk};
+//! use elements_miniscript::elements::secp256k1_zkp;
+//!
+//! let desc = Descriptor::<bitcoin::PublicKey>::from_str(
+//!     "elpkh(020000000000000000000000000000000000000000000000000000000000000002)",
+//! )?;
+//! let secp_desc: Descriptor<secp256k1_zkp::PublicKey> = desc.translate_pk(&mut KeyBridge)?;
+//! assert_eq!(secp_desc.script_pubkey(), desc.script_pubkey());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+
+use std::{error, fmt};
+
+use elements::secp256k1_zkp;
+
+use crate::{ToPublicKey, Translator};
+
+/// A [`Translator`] between the public key types of `bitcoin` and
+/// `secp256k1_zkp`, see the [module documentation](self)
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct KeyBridge;
+
+/// An error translating keys with a [`KeyBridge`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyBridgeError {
+    /// An uncompressed key, which has no equivalent `secp256k1_zkp` key with
+    /// the same hash
+    Uncompressed(bitcoin::PublicKey),
+}
+
+impl fmt::Display for KeyBridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            KeyBridgeError::Uncompressed(ref pk) => {
+                write!(f, "uncompressed key {} has no compressed equivalent", pk)
+            }
+        }
+    }
+}
+
+impl error::Error for KeyBridgeError {}
+
+fn compressed(pk: &bitcoin::PublicKey) -> Result<secp256k1_zkp::PublicKey, KeyBridgeError> {
+    if pk.compressed {
+        Ok(pk.inner)
+    } else {
+        Err(KeyBridgeError::Uncompressed(*pk))
+    }
+}
+
+macro_rules! impl_key_bridge {
+    ($source:ty, $target:ty, |$pk:ident| $convert:expr) => {
+        impl Translator<$source, $target, KeyBridgeError> for KeyBridge {
+            fn pk(&mut self, $pk: &$source) -> Result<$target, KeyBridgeError> {
+                $convert
+            }
+
+            translate_hash_clone!($source, $target, KeyBridgeError);
+        }
+    };
+}
+
+impl_key_bridge!(secp256k1_zkp::PublicKey, bitcoin::PublicKey, |pk| Ok(
+    pk.to_public_key()
+));
+impl_key_bridge!(bitcoin::PublicKey, secp256k1_zkp::PublicKey, |pk| {
+    compressed(pk)
+});
+// The parity of the key is dropped, as by taproot
+impl_key_bridge!(bitcoin::PublicKey, secp256k1_zkp::XOnlyPublicKey, |pk| {
+    compressed(pk).map(secp256k1_zkp::XOnlyPublicKey::from)
+});
+impl_key_bridge!(
+    secp256k1_zkp::PublicKey,
+    secp256k1_zkp::XOnlyPublicKey,
+    |pk| { Ok(secp256k1_zkp::XOnlyPublicKey::from(*pk)) }
+);
+// The key with an even y coordinate, as by taproot
+impl_key_bridge!(secp256k1_zkp::XOnlyPublicKey, bitcoin::PublicKey, |pk| Ok(
+    pk.to_public_key()
+));
+impl_key_bridge!(
+    secp256k1_zkp::XOnlyPublicKey,
+    secp256k1_zkp::PublicKey,
+    |pk| { Ok(pk.to_public_key().inner) }
+);
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{Descriptor, TranslatePk};
+
+    #[test]
+    fn key_bridge() {
+        let pk = bitcoin::PublicKey::from_str(
+            "0331c7d3fc85c148717848033ce276ae2b464a4e2c367ed33886cc428b8af48ff8",
+        )
+        .unwrap();
+        let desc = Descriptor::<bitcoin::PublicKey>::from_str(&format!(
+            "elwsh(or_d(pk({0}),and_v(v:pkh({0}),older(144))))",
+            pk
+        ))
+        .unwrap();
+        let secp_desc: Descriptor<secp256k1_zkp::PublicKey> =
+            desc.translate_pk(&mut KeyBridge).unwrap();
+        assert_eq!(secp_desc.script_pubkey(), desc.script_pubkey());
+        let back: Descriptor<bitcoin::PublicKey> = secp_desc.translate_pk(&mut KeyBridge).unwrap();
+        assert_eq!(back, desc);
+
+        // The x-only key of an odd key is its negation
+        let tr = Descriptor::<bitcoin::PublicKey>::from_str(&format!("eltr({})", pk)).unwrap();
+        let xonly_tr: Descriptor<secp256k1_zkp::XOnlyPublicKey> =
+            tr.translate_pk(&mut KeyBridge).unwrap();
+        assert_eq!(xonly_tr.script_pubkey(), tr.script_pubkey());
+        let even: Descriptor<bitcoin::PublicKey> = xonly_tr.translate_pk(&mut KeyBridge).unwrap();
+        assert_ne!(even, tr);
+        assert_eq!(even.script_pubkey(), tr.script_pubkey());
+
+        let uncompressed = bitcoin::PublicKey::new_uncompressed(pk.inner);
+        let desc = Descriptor::<bitcoin::PublicKey>::new_pkh(uncompressed);
+        let res: Result<Descriptor<secp256k1_zkp::PublicKey>, _> =
+            desc.translate_pk(&mut KeyBridge);
+        assert_eq!(res, Err(KeyBridgeError::Uncompressed(uncompressed)));
+    }
+}
@@ -131,15 +131,21 @@ pub mod descriptor;
 pub mod expression;
 pub mod extensions;
 pub mod interpreter;
+pub mod key_bridge;
 pub mod miniscript;
 pub mod policy;
 pub mod psbt;
+pub mod sighash;
 
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
 #[cfg(test)]
 mod test_utils;
+mod threshold;
 mod util;
 
-use std::{cmp, error, fmt, str};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::{cmp, error, fmt, ptr, str};
 
 use elements::hashes::sha256;
 use elements::secp256k1_zkp::Secp256k1;
@@ -149,12 +155,15 @@ pub use crate::descriptor::{DefiniteDescriptorKey, Descriptor, DescriptorPublicK
 pub use crate::extensions::{CovenantExt, Extension, NoExt, TxEnv};
 pub use crate::interpreter::Interpreter;
 pub use crate::miniscript::analyzable::{AnalysisError, ExtParams};
-pub use crate::miniscript::context::{BareCtx, Legacy, ScriptContext, Segwitv0, Tap};
+pub use crate::miniscript::builder::MiniscriptBuilder;
+pub use crate::miniscript::context::{BareCtx, Context, Legacy, ScriptContext, Segwitv0, Tap};
 pub use crate::miniscript::decode::Terminal;
 pub use crate::miniscript::satisfy::{
-    elementssig_from_rawsig, elementssig_to_rawsig, ElementsSig, Preimage32, Satisfier,
+    adaptorsig_to_rawsig, elementssig_from_rawsig, elementssig_to_rawsig, AdaptorPoint,
+    AtHeightAndTime, ElementsAdaptorSig, ElementsSig, Preimage32, Satisfier,
 };
 pub use crate::miniscript::Miniscript;
+pub use crate::threshold::{Threshold, ThresholdError};
 // minimal implementation of contract hash module
 mod contracthash {
     use bitcoin::secp256k1::Scalar;
@@ -275,6 +284,63 @@ where
         T: ExtTranslator<PExt, QExt, E>;
 }
 
+/// Early-exit queries over the keys of a descriptor, a miniscript or a policy,
+/// implemented for every type implementing [`ForEachKey`].
+pub trait ForEachKeyExt<Pk: MiniscriptKey>: ForEachKey<Pk> {
+    /// Whether `pred` returns true for any key, stopping at the first one.
+    fn any_key<'a, F: FnMut(&'a Pk) -> bool>(&'a self, pred: F) -> bool
+    where
+        Pk: 'a,
+    {
+        self.for_any_key(pred)
+    }
+
+    /// Whether `pred` returns true for every key, stopping at the first key
+    /// for which it returns false.
+    fn all_keys<'a, F: FnMut(&'a Pk) -> bool>(&'a self, pred: F) -> bool
+    where
+        Pk: 'a,
+    {
+        self.for_each_key(pred)
+    }
+
+    /// The first key, in the order [`ForEachKey::for_each_key`] visits them,
+    /// for which `pred` returns true.
+    fn find_key<'a, F: FnMut(&'a Pk) -> bool>(&'a self, mut pred: F) -> Option<&'a Pk>
+    where
+        Pk: 'a,
+    {
+        let mut found = None;
+        self.for_each_key(|key| {
+            if pred(key) {
+                found = Some(key);
+                false
+            } else {
+                true
+            }
+        });
+        found
+    }
+
+    /// All the keys for which `pred` returns true, in the order
+    /// [`ForEachKey::for_each_key`] visits them, with repetitions.
+    fn filter_keys<'a, F: FnMut(&'a Pk) -> bool>(&'a self, mut pred: F) -> Vec<&'a Pk>
+    where
+        Pk: 'a,
+    {
+        let mut keys = vec![];
+        self.for_each_key(|key| {
+            if pred(key) {
+                keys.push(key);
+            }
+            true
+        });
+        keys
+    }
+}
+
+impl<Pk: MiniscriptKey, T: ForEachKey<Pk> + ?Sized> ForEachKeyExt<Pk> for T {}
+
 /// Miniscript Error
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -321,12 +387,16 @@ pub enum Error {
     MissingHash(sha256::Hash),
     /// Could not satisfy a script (fragment) because of a missing signature
     MissingSig(bitcoin::PublicKey),
+    /// The preimage provided by the satisfier does not hash to the hash of the fragment
+    PreimageMismatch(String),
     /// Could not satisfy, relative locktime not met
     RelativeLocktimeNotMet(u32),
     /// Could not satisfy, absolute locktime not met
     AbsoluteLocktimeNotMet(u32),
     /// General failure to satisfy
     CouldNotSatisfy,
+    /// Could not satisfy, the satisfier lacks the given items
+    MissingItems(miniscript::satisfy::Missing),
     /// Typechecking failed
     TypeCheck(String),
     /// General error in creating descriptor
@@ -359,6 +429,8 @@ pub enum Error {
     BtcError(bitcoin_miniscript::Error),
     /// Covenant Error
     CovError(descriptor::CovError),
+    /// Invalid sortedmulti keys or threshold
+    SortedMultiError(descriptor::SortedMultiError),
     /// PubKey invalid under current context
     PubKeyCtxError(miniscript::decode::KeyParseError, &'static str),
     /// Attempted to call function that requires PreComputed taproot info
@@ -370,6 +442,12 @@ pub enum Error {
     /// At least two BIP389 key expressions in the descriptor contain tuples of
     /// derivation indexes of different lengths.
     MultipathDescLenMismatch,
+    /// Outputs of witness programs with an unknown version cannot be spent
+    UnknownWitnessVersion,
+    /// The taproot annex does not start with `0x50`
+    InvalidTapAnnex,
+    /// Invalid threshold of a `thresh`, `multi` or `multi_a` fragment
+    Threshold(ThresholdError),
 }
 
 #[doc(hidden)]
@@ -391,6 +469,13 @@ impl From<bitcoin_miniscript::Error> for Error {
     }
 }
 
+#[doc(hidden)]
+impl From<ThresholdError> for Error {
+    fn from(e: ThresholdError) -> Error {
+        Error::Threshold(e)
+    }
+}
+
 #[doc(hidden)]
 impl From<policy::LiftError> for Error {
     fn from(e: policy::LiftError) -> Error {
@@ -473,6 +558,7 @@ impl fmt::Display for Error {
             Error::Trailing(ref s) => write!(f, "trailing tokens: {}", s),
             Error::MissingHash(ref h) => write!(f, "missing preimage of hash {}", h),
             Error::MissingSig(ref pk) => write!(f, "missing signature for key {:?}", pk),
+            Error::PreimageMismatch(ref s) => write!(f, "preimage does not match {}", s),
             Error::RelativeLocktimeNotMet(n) => {
                 write!(f, "required relative locktime CSV of {} blocks, not met", n)
             }
@@ -482,6 +568,7 @@ impl fmt::Display for Error {
                 n
             ),
             Error::CouldNotSatisfy => f.write_str("could not satisfy"),
+            Error::MissingItems(ref m) => write!(f, "could not satisfy, missing {}", m),
             Error::BadPubkey(ref e) => fmt::Display::fmt(e, f),
             Error::TypeCheck(ref e) => write!(f, "typecheck: {}", e),
             Error::BadDescriptor(ref e) => write!(f, "Invalid descriptor: {}", e),
@@ -512,6 +599,7 @@ impl fmt::Display for Error {
             Error::BareDescriptorAddr => write!(f, "Bare descriptors don't have address"),
             Error::BtcError(ref e) => write!(f, " Bitcoin Miniscript Error {}", e),
             Error::CovError(ref e) => write!(f, "Covenant Error: {}", e),
+            Error::SortedMultiError(ref e) => fmt::Display::fmt(e, f),
             Error::PubKeyCtxError(ref pk, ref ctx) => {
                 write!(f, "Pubkey error: {} under {} scriptcontext", pk, ctx)
             }
@@ -520,6 +608,11 @@ impl fmt::Display for Error {
             Error::TrNoScriptCode => write!(f, "No script code for Tr descriptors"),
             Error::TrNoExplicitScript => write!(f, "No script code for Tr descriptors"),
             Error::MultipathDescLenMismatch => write!(f, "At least two BIP389 key expressions in the descriptor contain tuples of derivation indexes of different lengths"),
+            Error::UnknownWitnessVersion => {
+                write!(f, "Witness programs of unknown versions cannot be spent")
+            }
+            Error::InvalidTapAnnex => write!(f, "Taproot annex must start with 0x50"),
+            Error::Threshold(ref e) => fmt::Display::fmt(e, f),
         }
     }
 }
@@ -547,9 +640,11 @@ impl error::Error for Error {
             | Trailing(_)
             | MissingHash(_)
             | MissingSig(_)
+            | PreimageMismatch(_)
             | RelativeLocktimeNotMet(_)
             | AbsoluteLocktimeNotMet(_)
             | CouldNotSatisfy
+            | MissingItems(_)
             | TypeCheck(_)
             | BadDescriptor(_)
             | MaxRecursiveDepthExceeded
@@ -560,9 +655,11 @@ impl error::Error for Error {
             | TaprootSpendInfoUnavialable
             | TrNoScriptCode
             | TrNoExplicitScript => None,
-            MultipathDescLenMismatch => None,
+            MultipathDescLenMismatch | UnknownWitnessVersion | InvalidTapAnnex => None,
             BtcError(e) => Some(e),
             CovError(e) => Some(e),
+            SortedMultiError(e) => Some(e),
+            Threshold(e) => Some(e),
             Script(_e) => None, // should be Some(e), but requires changes upstream
             AddrError(e) => Some(e),
             BadPubkey(e) => Some(e),
@@ -593,6 +690,41 @@ impl From<policy::concrete::PolicyError> for Error {
     }
 }
 
+// The context set with `set_secp`, null until then
+static GLOBAL_SECP: AtomicPtr<Secp256k1<secp256k1_zkp::All>> = AtomicPtr::new(ptr::null_mut());
+
+/// Calls `f` with the secp256k1 context used by the APIs which do not take
+/// one, e.g. [`descriptor::Tr::spend_info`] or the interpreter.
+///
+/// This is the context given to [`set_secp`], or by default the global
+/// context of `secp256k1-zkp`, which is randomized once and never
+/// reallocated, so applications can use it instead of creating their own
+/// contexts in hot paths. Most APIs creating keys or signatures also have a
+/// variant taking an explicit context, e.g. a preallocated one.
+pub fn with_secp<R, F>(f: F) -> R
+where
+    F: FnOnce(&Secp256k1<secp256k1_zkp::All>) -> R,
+{
+    let secp = GLOBAL_SECP.load(Ordering::Acquire);
+    if secp.is_null() {
+        f(secp256k1_zkp::SECP256K1)
+    } else {
+        // SAFETY: the pointer comes from the `'static` reference given to
+        // `set_secp`, and the context is never mutated through it.
+        f(unsafe { &*secp })
+    }
+}
+
+/// Sets the secp256k1 context passed by [`with_secp`] to the APIs which do
+/// not take one, for all the threads of the application, e.g. to use a
+/// preallocated context or one randomized by the application.
+///
+/// The APIs which are already running keep the context they were called
+/// with.
+pub fn set_secp(secp: &'static Secp256k1<secp256k1_zkp::All>) {
+    GLOBAL_SECP.store(secp as *const _ as *mut _, Ordering::Release);
+}
+
 /// The size of an encoding of a number in Script
 pub fn script_num_size(n: usize) -> usize {
     match n {
@@ -647,6 +779,30 @@ impl AbsLockTime {
     pub fn to_u32(self) -> u32 {
         self.to_consensus_u32()
     }
+
+    /// Whether both locktimes are block heights, or both are timestamps
+    pub fn is_same_unit(self, other: AbsLockTime) -> bool {
+        self.0.is_same_unit(other.0)
+    }
+
+    /// Whether a transaction with the given `nLockTime` satisfies
+    /// `self OP_CHECKLOCKTIMEVERIFY`, i.e. both have the same unit and the
+    /// `nLockTime` is at least `self`
+    pub fn is_satisfied_by(self, lock_time: locktime::LockTime) -> bool {
+        use locktime::LockTime::*;
+
+        match (self.0, lock_time) {
+            (Blocks(n), Blocks(lock_time)) => n <= lock_time,
+            (Seconds(n), Seconds(lock_time)) => n <= lock_time,
+            _ => false,
+        }
+    }
+
+    /// Whether satisfying `other` implies satisfying `self`, i.e. both have
+    /// the same unit and `other` is at least `self`
+    pub fn is_implied_by(self, other: AbsLockTime) -> bool {
+        self.is_satisfied_by(other.0)
+    }
 }
 
 impl From<locktime::LockTime> for AbsLockTime {
@@ -681,6 +837,132 @@ impl fmt::Display for AbsLockTime {
     }
 }
 
+/// A relative locktime, the argument to `OP_CHECKSEQUENCEVERIFY`, that
+/// implements `Ord`.
+///
+/// Locktimes are ordered by their unit first, block heights before
+/// intervals of 512 seconds, so that the comparison of locktimes of
+/// different units never depends on their raw consensus encoding. Whether a
+/// locktime is satisfied should be checked with
+/// [`RelLockTime::is_satisfied_by`] or [`RelLockTime::is_implied_by`], which
+/// fail for locktimes of different units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RelLockTime(elements::Sequence);
+
+impl RelLockTime {
+    /// Masks the value of the locktime in its consensus encoding
+    const VALUE_MASK: u32 = 0x0000ffff;
+
+    /// Constructs a `RelLockTime` from an nSequence value or the argument to
+    /// OP_CHECKSEQUENCEVERIFY.
+    ///
+    /// The value is not checked to encode a relative locktime, see
+    /// [`RelLockTime::is_relative_lock_time`].
+    pub fn from_consensus(n: u32) -> Self {
+        Self(elements::Sequence::from_consensus(n))
+    }
+
+    /// A relative locktime of `height` blocks
+    pub fn from_height(height: u16) -> Self {
+        Self(elements::Sequence::from_height(height))
+    }
+
+    /// A relative locktime of `intervals` times 512 seconds
+    pub fn from_512_second_intervals(intervals: u16) -> Self {
+        Self(elements::Sequence::from_512_second_intervals(intervals))
+    }
+
+    /// Returns the inner `u32` value, as used in `n OP_CHECKSEQUENCEVERIFY`
+    /// or nSequence.
+    pub fn to_consensus_u32(self) -> u32 {
+        self.0.to_consensus_u32()
+    }
+
+    /// Returns the locktime as an nSequence
+    pub fn to_sequence(self) -> elements::Sequence {
+        self.0
+    }
+
+    /// Whether the locktime encodes a relative locktime, i.e. whether its
+    /// disable flag is not set. `older()` fragments must also be non-zero.
+    pub fn is_relative_lock_time(self) -> bool {
+        self.0.is_relative_lock_time()
+    }
+
+    /// Whether the locktime is a number of blocks
+    pub fn is_height_locked(self) -> bool {
+        self.0.is_height_locked()
+    }
+
+    /// Whether the locktime is a number of intervals of 512 seconds
+    pub fn is_time_locked(self) -> bool {
+        self.0.is_time_locked()
+    }
+
+    /// The number of blocks, or of intervals of 512 seconds, of the locktime
+    pub fn value(self) -> u16 {
+        (self.0.to_consensus_u32() & Self::VALUE_MASK) as u16
+    }
+
+    /// Whether both locktimes are relative locktimes of the same unit
+    pub fn is_same_unit(self, other: RelLockTime) -> bool {
+        self.is_relative_lock_time()
+            && other.is_relative_lock_time()
+            && self.is_time_locked() == other.is_time_locked()
+    }
+
+    /// Whether an input with the given `nSequence` satisfies
+    /// `self OP_CHECKSEQUENCEVERIFY`, i.e. both have the same unit and the
+    /// `nSequence` is at least `self`
+    pub fn is_satisfied_by(self, sequence: elements::Sequence) -> bool {
+        self.is_implied_by(RelLockTime(sequence))
+    }
+
+    /// Whether satisfying `other` implies satisfying `self`, i.e. both have
+    /// the same unit and `other` is at least `self`
+    pub fn is_implied_by(self, other: RelLockTime) -> bool {
+        self.is_same_unit(other) && self.value() <= other.value()
+    }
+}
+
+impl From<elements::Sequence> for RelLockTime {
+    fn from(sequence: elements::Sequence) -> Self {
+        Self(sequence)
+    }
+}
+
+impl From<RelLockTime> for elements::Sequence {
+    fn from(lock_time: RelLockTime) -> elements::Sequence {
+        lock_time.0
+    }
+}
+
+impl cmp::PartialOrd for RelLockTime {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl cmp::Ord for RelLockTime {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        let key = |t: &Self| {
+            (
+                !t.is_relative_lock_time(),
+                t.is_time_locked(),
+                t.value(),
+                t.to_consensus_u32(),
+            )
+        };
+        key(self).cmp(&key(other))
+    }
+}
+
+impl fmt::Display for RelLockTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
 /// Helper function used by tests
 #[cfg(test)]
 fn hex_script(s: &str) -> elements::Script {
@@ -738,4 +1020,66 @@ mod tests {
         let got = pk.to_pubkeyhash(SigType::Schnorr);
         assert_eq!(got, want)
     }
+
+    #[test]
+    fn lock_time_units() {
+        use elements::{LockTime, Sequence};
+
+        let blocks = RelLockTime::from_height(1000);
+        let time = RelLockTime::from_512_second_intervals(10);
+        assert!(blocks.is_height_locked() && time.is_time_locked());
+        assert_eq!(time.value(), 10);
+        // Heights come before times, whatever their values
+        assert!(blocks < time);
+        assert!(RelLockTime::from_height(u16::MAX) < time);
+        assert!(time < RelLockTime::from_consensus(0x8000_0000));
+
+        assert!(blocks.is_satisfied_by(Sequence::from_height(1000)));
+        assert!(!blocks.is_satisfied_by(Sequence::from_height(999)));
+        // The raw nSequence of a time is larger than any height
+        assert!(!blocks.is_satisfied_by(Sequence::from_512_second_intervals(2000)));
+        assert!(!blocks.is_satisfied_by(Sequence::MAX));
+        assert!(time.is_implied_by(RelLockTime::from_512_second_intervals(11)));
+        assert!(!time.is_implied_by(blocks));
+
+        let height = AbsLockTime::from_consensus(800_000);
+        let timestamp = AbsLockTime::from_consensus(1_700_000_000);
+        assert!(height < timestamp);
+        assert!(!height.is_same_unit(timestamp));
+        assert!(height.is_satisfied_by(LockTime::from_consensus(800_001)));
+        assert!(!height.is_satisfied_by(LockTime::from_consensus(1_700_000_000)));
+        assert!(timestamp.is_implied_by(AbsLockTime::from_consensus(1_700_000_001)));
+    }
+
+    #[test]
+    fn global_secp() {
+        let secp: &'static Secp256k1<secp256k1_zkp::All> = Box::leak(Box::new(Secp256k1::new()));
+        set_secp(secp);
+        assert!(with_secp(|global| ptr::eq(global, secp)));
+        // The contexts are interchangeable for the other tests
+        let sk = secp256k1_zkp::SecretKey::from_slice(&[1; 32]).unwrap();
+        assert_eq!(
+            with_secp(|global| secp256k1_zkp::PublicKey::from_secret_key(global, &sk)),
+            secp256k1_zkp::PublicKey::from_secret_key(secp256k1_zkp::SECP256K1, &sk)
+        );
+    }
+
+    #[test]
+    fn for_each_key_ext() {
+        let desc = Descriptor::<DescriptorPublicKey>::from_str(
+            "elwsh(or_d(pk([01020304/48h/1h]xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*),and_v(v:pk([01020304/48h/1h]xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB/0/*),older(144))))",
+        )
+        .unwrap();
+        let fp = bitcoin::bip32::Fingerprint::from_str("01020304").unwrap();
+        assert!(desc.any_key(|key| key.master_fingerprint() == fp));
+        assert!(desc.all_keys(|key| key.master_fingerprint() == fp));
+        assert!(!desc.all_keys(|key| key.has_wildcard() && key.is_multipath()));
+
+        let second = desc
+            .find_key(|key| key.to_string().contains("xpub661My"))
+            .unwrap();
+        assert_eq!(desc.filter_keys(|key| key == second), vec![second]);
+        assert_eq!(desc.filter_keys(|key| key.has_wildcard()).len(), 2);
+        assert!(desc.find_key(|key| !key.has_wildcard()).is_none());
+    }
 }
@@ -12,7 +12,9 @@ use std::fmt;
 use crate::{error, Extension, Miniscript, MiniscriptKey, ScriptContext, Terminal};
 
 /// Params for parsing miniscripts that either non-sane or non-specified(experimental) in the spec.
-/// Used as a parameter [`Miniscript::from_str_ext`] and [`Miniscript::parse_with_ext`].
+/// Used as a parameter [`Miniscript::from_str_ext`] and [`Miniscript::parse_with_ext`],
+/// and to check every miniscript of a descriptor with
+/// [`Descriptor::from_str_ext`](crate::Descriptor::from_str_ext).
 ///
 /// This allows parsing miniscripts if
 /// 1. It is unsafe(does not require a digital signature to spend it)
@@ -13,7 +13,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use bitcoin::hashes::hash160;
-use elements::{opcodes, script, Sequence};
+use elements::{opcodes, script};
 
 use super::limits::{MAX_SCRIPT_ELEMENT_SIZE, MAX_STANDARD_P2WSH_STACK_ITEM_SIZE};
 use crate::extensions::ParseableExt;
@@ -23,7 +23,8 @@ use crate::miniscript::ScriptContext;
 use crate::util::MsKeyBuilder;
 use crate::{
     errstr, expression, script_num_size, AbsLockTime, Error, ExtTranslator, Extension, ForEachKey,
-    Miniscript, MiniscriptKey, Terminal, ToPublicKey, TranslateExt, TranslatePk, Translator,
+    Miniscript, MiniscriptKey, RelLockTime, Terminal, Threshold, ToPublicKey, TranslateExt,
+    TranslatePk, Translator,
 };
 
 impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ext> {
@@ -120,8 +121,8 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ex
                     && b.real_for_each_key(&mut *pred)
                     && c.real_for_each_key(pred)
             }
-            Terminal::Thresh(_, ref subs) => subs.iter().all(|sub| sub.real_for_each_key(pred)),
-            Terminal::Multi(_, ref keys) | Terminal::MultiA(_, ref keys) => keys.iter().all(pred),
+            Terminal::Thresh(ref thresh) => thresh.iter().all(|sub| sub.real_for_each_key(pred)),
+            Terminal::Multi(ref thresh) | Terminal::MultiA(ref thresh) => thresh.iter().all(pred),
             Terminal::Ext(ref _e) => true,
         }
     }
@@ -185,21 +186,11 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ex
                 Arc::new(left.real_translate_pk(t)?),
                 Arc::new(right.real_translate_pk(t)?),
             ),
-            Terminal::Thresh(k, ref subs) => {
-                let subs: Result<Vec<Arc<Miniscript<Q, _, _>>>, _> = subs
-                    .iter()
-                    .map(|s| s.real_translate_pk(t).map(Arc::new))
-                    .collect();
-                Terminal::Thresh(k, subs?)
-            }
-            Terminal::Multi(k, ref keys) => {
-                let keys: Result<Vec<Q>, _> = keys.iter().map(|k| t.pk(k)).collect();
-                Terminal::Multi(k, keys?)
-            }
-            Terminal::MultiA(k, ref keys) => {
-                let keys: Result<Vec<Q>, _> = keys.iter().map(|k| t.pk(k)).collect();
-                Terminal::MultiA(k, keys?)
+            Terminal::Thresh(ref thresh) => {
+                Terminal::Thresh(thresh.translate_ref(|s| s.real_translate_pk(t).map(Arc::new))?)
             }
+            Terminal::Multi(ref thresh) => Terminal::Multi(thresh.translate_ref(|k| t.pk(k))?),
+            Terminal::MultiA(ref thresh) => Terminal::MultiA(thresh.translate_ref(|k| t.pk(k))?),
             // Translate Pk does not translate extensions
             // use TranslateExt
             Terminal::Ext(ref e) => Terminal::Ext(e.clone()),
@@ -266,15 +257,11 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ex
                 Arc::new(left.real_translate_ext(t)?),
                 Arc::new(right.real_translate_ext(t)?),
             ),
-            Terminal::Thresh(k, ref subs) => {
-                let subs: Result<Vec<Arc<Miniscript<Pk, _, _>>>, _> = subs
-                    .iter()
-                    .map(|s| s.real_translate_ext(t).map(Arc::new))
-                    .collect();
-                Terminal::Thresh(k, subs?)
+            Terminal::Thresh(ref thresh) => {
+                Terminal::Thresh(thresh.translate_ref(|s| s.real_translate_ext(t).map(Arc::new))?)
             }
-            Terminal::Multi(k, ref keys) => Terminal::Multi(k, keys.clone()),
-            Terminal::MultiA(k, ref keys) => Terminal::MultiA(k, keys.clone()),
+            Terminal::Multi(ref thresh) => Terminal::Multi(thresh.clone()),
+            Terminal::MultiA(ref thresh) => Terminal::MultiA(thresh.clone()),
             Terminal::Ext(ref e) => Terminal::Ext(e.translate_ext(t)?),
         };
         Ok(frag)
@@ -304,37 +291,7 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("[")?;
         if let Ok(type_map) = types::Type::type_check(self, |_| None) {
-            f.write_str(match type_map.corr.base {
-                types::Base::B => "B",
-                types::Base::K => "K",
-                types::Base::V => "V",
-                types::Base::W => "W",
-            })?;
-            fmt::Write::write_char(f, '/')?;
-            f.write_str(match type_map.corr.input {
-                types::Input::Zero => "z",
-                types::Input::One => "o",
-                types::Input::OneNonZero => "on",
-                types::Input::Any => "",
-                types::Input::AnyNonZero => "n",
-            })?;
-            if type_map.corr.dissatisfiable {
-                fmt::Write::write_char(f, 'd')?;
-            }
-            if type_map.corr.unit {
-                fmt::Write::write_char(f, 'u')?;
-            }
-            f.write_str(match type_map.mall.dissat {
-                types::Dissat::None => "f",
-                types::Dissat::Unique => "e",
-                types::Dissat::Unknown => "",
-            })?;
-            if type_map.mall.safe {
-                fmt::Write::write_char(f, 's')?;
-            }
-            if type_map.mall.non_malleable {
-                fmt::Write::write_char(f, 'm')?;
-            }
+            write!(f, "{}", type_map)?;
         } else {
             f.write_str("TYPECHECK FAILED")?;
         }
@@ -372,23 +329,23 @@ where
                 Terminal::OrD(ref l, ref r) => write!(f, "or_d({:?},{:?})", l, r),
                 Terminal::OrC(ref l, ref r) => write!(f, "or_c({:?},{:?})", l, r),
                 Terminal::OrI(ref l, ref r) => write!(f, "or_i({:?},{:?})", l, r),
-                Terminal::Thresh(k, ref subs) => {
-                    write!(f, "thresh({}", k)?;
-                    for s in subs {
+                Terminal::Thresh(ref thresh) => {
+                    write!(f, "thresh({}", thresh.k())?;
+                    for s in thresh {
                         write!(f, ",{:?}", s)?;
                     }
                     f.write_str(")")
                 }
-                Terminal::Multi(k, ref keys) => {
-                    write!(f, "multi({}", k)?;
-                    for k in keys {
+                Terminal::Multi(ref thresh) => {
+                    write!(f, "multi({}", thresh.k())?;
+                    for k in thresh {
                         write!(f, ",{:?}", k)?;
                     }
                     f.write_str(")")
                 }
-                Terminal::MultiA(k, ref keys) => {
-                    write!(f, "multi_a({}", k)?;
-                    for k in keys {
+                Terminal::MultiA(ref thresh) => {
+                    write!(f, "multi_a({}", thresh.k())?;
+                    for k in thresh {
                         write!(f, ",{}", k)?;
                     }
                     f.write_str(")")
@@ -438,23 +395,23 @@ where
             {
                 write!(f, "or_i({},{})", l, r)
             }
-            Terminal::Thresh(k, ref subs) => {
-                write!(f, "thresh({}", k)?;
-                for s in subs {
+            Terminal::Thresh(ref thresh) => {
+                write!(f, "thresh({}", thresh.k())?;
+                for s in thresh {
                     write!(f, ",{}", s)?;
                 }
                 f.write_str(")")
             }
-            Terminal::Multi(k, ref keys) => {
-                write!(f, "multi({}", k)?;
-                for k in keys {
+            Terminal::Multi(ref thresh) => {
+                write!(f, "multi({}", thresh.k())?;
+                for k in thresh {
                     write!(f, ",{}", k)?;
                 }
                 f.write_str(")")
             }
-            Terminal::MultiA(k, ref keys) => {
-                write!(f, "multi_a({}", k)?;
-                for k in keys {
+            Terminal::MultiA(ref thresh) => {
+                write!(f, "multi_a({}", thresh.k())?;
+                for k in thresh {
                     write!(f, ",{}", k)?;
                 }
                 f.write_str(")")
@@ -574,7 +531,7 @@ impl_from_tree!(
                 expression::parse_num::<u32>(x).map(|x| Terminal::After(AbsLockTime::from_consensus(x)))
             }),
             ("older", 1) => expression::terminal(&top.args[0], |x| {
-                expression::parse_num::<u32>(x).map(|x| Terminal::Older(Sequence::from_consensus(x)))
+                expression::parse_num::<u32>(x).map(|x| Terminal::Older(RelLockTime::from_consensus(x)))
             }),
             ("sha256", 1) => expression::terminal(&top.args[0], |x| {
                 Pk::Sha256::from_str(x).map(Terminal::Sha256)
@@ -623,7 +580,7 @@ impl_from_tree!(
                     .map(expression::FromTree::from_tree)
                     .collect();
 
-                Ok(Terminal::Thresh(k, subs?))
+                Ok(Terminal::Thresh(Threshold::new(k, subs?)?))
             }
             ("multi", n) | ("multi_a", n) => {
                 if n == 0 {
@@ -639,11 +596,12 @@ impl_from_tree!(
                     .map(|sub| expression::terminal(sub, Pk::from_str))
                     .collect();
 
+                let thresh = Threshold::new(k, pks?)?;
                 if frag_name == "multi" {
-                    pks.map(|pks| Terminal::Multi(k, pks))
+                    Ok(Terminal::Multi(thresh))
                 } else {
                     // must be multi_a
-                    pks.map(|pks| Terminal::MultiA(k, pks))
+                    Ok(Terminal::MultiA(thresh))
                 }
             }
             (name, _num_child) => {
@@ -884,28 +842,30 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ex
                 .push_opcode(opcodes::all::OP_ELSE)
                 .push_astelem(right)
                 .push_opcode(opcodes::all::OP_ENDIF),
-            Terminal::Thresh(k, ref subs) => {
+            Terminal::Thresh(ref thresh) => {
+                let subs = thresh.data();
                 builder = builder.push_astelem(&subs[0]);
                 for sub in &subs[1..] {
                     builder = builder.push_astelem(sub).push_opcode(opcodes::all::OP_ADD);
                 }
                 builder
-                    .push_int(k as i64)
+                    .push_int(thresh.k() as i64)
                     .push_opcode(opcodes::all::OP_EQUAL)
             }
-            Terminal::Multi(k, ref keys) => {
+            Terminal::Multi(ref thresh) => {
                 debug_assert!(Ctx::sig_type() == SigType::Ecdsa);
-                builder = builder.push_int(k as i64);
-                for pk in keys {
+                builder = builder.push_int(thresh.k() as i64);
+                for pk in thresh {
                     builder = builder.push_key(&pk.to_public_key());
                 }
                 builder
-                    .push_int(keys.len() as i64)
+                    .push_int(thresh.n() as i64)
                     .push_opcode(opcodes::all::OP_CHECKMULTISIG)
             }
-            Terminal::MultiA(k, ref keys) => {
+            Terminal::MultiA(ref thresh) => {
                 debug_assert!(Ctx::sig_type() == SigType::Schnorr);
-                // keys must be atleast len 1 here, guaranteed by typing rules
+                // keys are at least 1, guaranteed by the threshold
+                let keys = thresh.data();
                 builder = builder.push_ms_key::<_, Ctx>(&keys[0]);
                 builder = builder.push_opcode(opcodes::all::OP_CHECKSIG);
                 for pk in keys.iter().skip(1) {
@@ -913,7 +873,7 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ex
                     builder = builder.push_opcode(opcodes::all::OP_CHECKSIGADD);
                 }
                 builder
-                    .push_int(k as i64)
+                    .push_int(thresh.k() as i64)
                     .push_opcode(opcodes::all::OP_NUMEQUAL)
             }
             Terminal::Ext(ref e) => e.push_to_builder(builder),
@@ -957,25 +917,24 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Terminal<Pk, Ctx, Ex
             Terminal::OrD(ref l, ref r) => l.node.script_size() + r.node.script_size() + 3,
             Terminal::OrC(ref l, ref r) => l.node.script_size() + r.node.script_size() + 2,
             Terminal::OrI(ref l, ref r) => l.node.script_size() + r.node.script_size() + 3,
-            Terminal::Thresh(k, ref subs) => {
-                assert!(!subs.is_empty(), "threshold must be nonempty");
-                script_num_size(k) // k
+            Terminal::Thresh(ref thresh) => {
+                script_num_size(thresh.k()) // k
                     + 1 // EQUAL
-                    + subs.iter().map(|s| s.node.script_size()).sum::<usize>()
-                    + subs.len() // ADD
+                    + thresh.iter().map(|s| s.node.script_size()).sum::<usize>()
+                    + thresh.n() // ADD
                     - 1 // no ADD on first element
             }
-            Terminal::Multi(k, ref pks) => {
-                script_num_size(k)
+            Terminal::Multi(ref thresh) => {
+                script_num_size(thresh.k())
                     + 1
-                    + script_num_size(pks.len())
-                    + pks.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>()
+                    + script_num_size(thresh.n())
+                    + thresh.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>()
             }
-            Terminal::MultiA(k, ref pks) => {
-                script_num_size(k)
+            Terminal::MultiA(ref thresh) => {
+                script_num_size(thresh.k())
                     + 1 // NUMEQUAL
-                    + pks.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>() // n keys
-                    + pks.len() // n times CHECKSIGADD
+                    + thresh.iter().map(|pk| Ctx::pk_len(pk)).sum::<usize>() // n keys
+                    + thresh.n() // n times CHECKSIGADD
             }
             Terminal::Ext(ref e) => e.script_size(),
         }
@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Script Size and Sigops Budget
+//!
+//! Per-fragment estimates of the script size and of the signature checks of
+//! a Miniscript, to plan how much logic fits in a tapleaf.
+//!
+//! Tapscript has no limit on the number of opcodes, but each executed
+//! signature check with a non-empty signature consumes 50 units of a budget
+//! of 50 plus the serialized size of the witness of the input, including the
+//! script and the control block, see BIP-342. Elements applies the same rule
+//! to `OP_CHECKSIGFROMSTACK`.
+//!
+
+use std::cmp;
+
+use super::decode::Terminal;
+use super::Miniscript;
+use crate::{Extension, MiniscriptKey, ScriptContext};
+
+/// The budget consumed by each executed signature check in tapscript
+pub const SIGOPS_BUDGET_PER_SIG: usize = 50;
+
+/// Size estimates of a fragment of a Miniscript, see
+/// [`Miniscript::binary_size_estimates`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SizeEstimate<'a, Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> {
+    /// The fragment
+    pub node: &'a Miniscript<Pk, Ctx, Ext>,
+    /// The size, in bytes, of the script of the fragment and its children
+    pub script_size: usize,
+    /// The number of signature checking opcodes in the script of the
+    /// fragment, counting `n` for a `multi` fragment
+    pub sig_ops: usize,
+    /// The maximum number of signatures checked by a satisfaction of the
+    /// fragment
+    pub max_sat_sigs: usize,
+    /// The maximum tapscript sigops budget used by a satisfaction of the
+    /// fragment, i.e. [`SIGOPS_BUDGET_PER_SIG`] per checked signature
+    pub budget_used: usize,
+}
+
+impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Miniscript<Pk, Ctx, Ext> {
+    /// Size estimates of every fragment of the Miniscript, in pre-order,
+    /// starting with the Miniscript itself.
+    ///
+    /// The signatures of a satisfaction are counted for the satisfaction
+    /// checking the most signatures, and dissatisfactions are assumed to use
+    /// empty signatures, which do not use any budget.
+    pub fn binary_size_estimates(&self) -> Vec<SizeEstimate<'_, Pk, Ctx, Ext>> {
+        let mut estimates = vec![];
+        estimate(self, &mut estimates);
+        estimates
+    }
+}
+
+// Pushes the estimates of `ms` and its children, and returns its number of
+// sig ops and the maximum number of signatures of its satisfaction and
+// dissatisfaction
+fn estimate<'a, Pk, Ctx, Ext>(
+    ms: &'a Miniscript<Pk, Ctx, Ext>,
+    estimates: &mut Vec<SizeEstimate<'a, Pk, Ctx, Ext>>,
+) -> (usize, usize, usize)
+where
+    Pk: MiniscriptKey,
+    Ctx: ScriptContext,
+    Ext: Extension,
+{
+    let idx = estimates.len();
+    estimates.push(SizeEstimate {
+        node: ms,
+        script_size: ms.script_size(),
+        sig_ops: 0,
+        max_sat_sigs: 0,
+        budget_used: 0,
+    });
+    let children = ms
+        .branches()
+        .into_iter()
+        .map(|child| estimate(child, estimates))
+        .collect::<Vec<_>>();
+    let (sig_ops, sat, dissat) = combine(&ms.node, &children);
+    let est = &mut estimates[idx];
+    est.sig_ops = sig_ops;
+    est.max_sat_sigs = sat;
+    est.budget_used = SIGOPS_BUDGET_PER_SIG * sat;
+    (sig_ops, sat, dissat)
+}
+
+// The sig ops and signatures of a fragment, from the ones of its children
+fn combine<Pk, Ctx, Ext>(
+    node: &Terminal<Pk, Ctx, Ext>,
+    children: &[(usize, usize, usize)],
+) -> (usize, usize, usize)
+where
+    Pk: MiniscriptKey,
+    Ctx: ScriptContext,
+    Ext: Extension,
+{
+    let sig_ops = children.iter().map(|c| c.0).sum::<usize>();
+    match *node {
+        Terminal::True
+        | Terminal::False
+        | Terminal::After(..)
+        | Terminal::Older(..)
+        | Terminal::Sha256(..)
+        | Terminal::Hash256(..)
+        | Terminal::Ripemd160(..)
+        | Terminal::Hash160(..) => (0, 0, 0),
+        Terminal::PkK(..) | Terminal::PkH(..) | Terminal::RawPkH(..) => (0, 1, 0),
+        Terminal::Multi(ref thresh) => (thresh.n(), thresh.k(), 0),
+        Terminal::MultiA(ref thresh) => (thresh.n(), thresh.k(), 0),
+        Terminal::Ext(ref ext) => (ext.sig_ops(), ext.sig_ops(), 0),
+        Terminal::Check(..) => (sig_ops + 1, children[0].1, children[0].2),
+        Terminal::Alt(..)
+        | Terminal::Swap(..)
+        | Terminal::Verify(..)
+        | Terminal::NonZero(..)
+        | Terminal::ZeroNotEqual(..) => (sig_ops, children[0].1, children[0].2),
+        Terminal::DupIf(..) => (sig_ops, children[0].1, 0),
+        Terminal::AndV(..) => (sig_ops, children[0].1 + children[1].1, 0),
+        Terminal::AndB(..) => (
+            sig_ops,
+            children[0].1 + children[1].1,
+            children[0].2 + children[1].2,
+        ),
+        Terminal::AndOr(..) => {
+            let (a, b, c) = (children[0], children[1], children[2]);
+            (sig_ops, cmp::max(a.1 + b.1, a.2 + c.1), a.2 + c.2)
+        }
+        Terminal::OrB(..) => {
+            let (a, b) = (children[0], children[1]);
+            (sig_ops, cmp::max(a.1 + b.2, a.2 + b.1), a.2 + b.2)
+        }
+        Terminal::OrD(..) | Terminal::OrC(..) => {
+            let (a, b) = (children[0], children[1]);
+            (sig_ops, cmp::max(a.1, a.2 + b.1), a.2 + b.2)
+        }
+        Terminal::OrI(..) => {
+            let (a, b) = (children[0], children[1]);
+            (sig_ops, cmp::max(a.1, b.1), cmp::max(a.2, b.2))
+        }
+        Terminal::Thresh(ref thresh) => {
+            // All the children are dissatisfied, except the `k` ones adding
+            // the most signatures when satisfied
+            let dissat = children.iter().map(|c| c.2).sum::<usize>();
+            let mut extra = children
+                .iter()
+                .map(|c| c.1.saturating_sub(c.2))
+                .collect::<Vec<_>>();
+            extra.sort_unstable_by(|a, b| b.cmp(a));
+            let sat = dissat + extra.iter().take(thresh.k()).sum::<usize>();
+            (sig_ops, sat, dissat)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{CovenantExt, Tap};
+
+    #[test]
+    fn binary_size_estimates() {
+        let ms =
+            Miniscript::<String, Tap>::from_str("or_d(multi_a(2,A,B,C),and_v(v:pk(D),older(144)))")
+                .unwrap();
+        let estimates = ms.binary_size_estimates();
+        assert_eq!(estimates.len(), ms.node_count());
+        assert_eq!(estimates[0].script_size, ms.script_size());
+        assert_eq!(estimates[0].sig_ops, 4);
+        // The two signatures of `multi_a`, the second branch only checks one
+        assert_eq!(estimates[0].max_sat_sigs, 2);
+        assert_eq!(estimates[0].budget_used, 100);
+        assert_eq!(estimates[1].node.to_string(), "multi_a(2,A,B,C)");
+        assert_eq!(estimates[1].sig_ops, 3);
+
+        let ms =
+            Miniscript::<String, Tap>::from_str("thresh(2,pk(A),s:pk(B),s:pk(C),sln:older(144))")
+                .unwrap();
+        assert_eq!(ms.binary_size_estimates()[0].max_sat_sigs, 2);
+
+        // The signature of `csfs` is checked when satisfied
+        let ms = Miniscript::<String, Tap, CovenantExt<String>>::from_str_insane(
+            "and_v(v:pk(A),csfs(B,M))",
+        )
+        .unwrap();
+        let estimates = ms.binary_size_estimates();
+        assert_eq!(estimates[0].sig_ops, 2);
+        assert_eq!(estimates[0].budget_used, 100);
+    }
+}
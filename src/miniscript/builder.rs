@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Miniscript Builder
+//!
+//! Construction of miniscripts from their fragments, without going through
+//! their string representation. Every fragment is type checked, and checked
+//! against the limits of the script context, as soon as it is built.
+//!
+
+use std::sync::Arc;
+
+use super::decode::Terminal;
+use super::{Miniscript, ScriptContext};
+use crate::{AbsLockTime, Error, Extension, MiniscriptKey, NoExt, RelLockTime, Threshold};
+
+/// Builder of a [`Miniscript`] from its fragments
+///
+/// Unlike a [`Terminal`] assembled by hand, every fragment is typed from the
+/// types of its subexpressions and checked against the script context as
+/// soon as it is built, as when parsing a miniscript, so that an invalid
+/// composition is rejected with an error about the fragment at fault.
+///
+/// ```
+/// use elements_miniscript::{MiniscriptBuilder, RelLockTime, Segwitv0};
+///
+/// type Builder = MiniscriptBuilder<String, Segwitv0>;
+/// let older = Builder::older(RelLockTime::from_height(144))?;
+/// let ms = Builder::pk("A".to_string())?
+///     .or_d(Builder::pk("B".to_string())?.v()?.and_v(older)?)?
+///     .build()?;
+/// assert_eq!(ms.to_string(), "or_d(pk(A),and_v(v:pk(B),older(144)))");
+///
+/// // `or_d` requires its left subexpression to be dissatisfiable
+/// assert!(Builder::pk("A".to_string())?.v()?.or_d(Builder::pk("B".to_string())?).is_err());
+/// # Ok::<(), elements_miniscript::Error>(())
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MiniscriptBuilder<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension = NoExt> {
+    ms: Arc<Miniscript<Pk, Ctx, Ext>>,
+}
+
+impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> MiniscriptBuilder<Pk, Ctx, Ext> {
+    /// Builds the given fragment, checking its type and the limits of the
+    /// script context
+    pub fn terminal(term: Terminal<Pk, Ctx, Ext>) -> Result<Self, Error> {
+        let ms = Miniscript::from_ast(term)?;
+        Ctx::check_global_validity(&ms)?;
+        Ok(MiniscriptBuilder { ms: Arc::new(ms) })
+    }
+
+    /// `pk_k(pk)`
+    pub fn pk_k(pk: Pk) -> Result<Self, Error> {
+        Self::terminal(Terminal::PkK(pk))
+    }
+
+    /// `pk_h(pk)`
+    pub fn pk_h(pk: Pk) -> Result<Self, Error> {
+        Self::terminal(Terminal::PkH(pk))
+    }
+
+    /// `pk(pk)`, i.e. `c:pk_k(pk)`
+    pub fn pk(pk: Pk) -> Result<Self, Error> {
+        Self::pk_k(pk)?.c()
+    }
+
+    /// `pkh(pk)`, i.e. `c:pk_h(pk)`
+    pub fn pkh(pk: Pk) -> Result<Self, Error> {
+        Self::pk_h(pk)?.c()
+    }
+
+    /// `after(t)`
+    pub fn after(t: AbsLockTime) -> Result<Self, Error> {
+        Self::terminal(Terminal::After(t))
+    }
+
+    /// `older(t)`
+    pub fn older(t: RelLockTime) -> Result<Self, Error> {
+        Self::terminal(Terminal::Older(t))
+    }
+
+    /// `multi(k,keys)`
+    pub fn multi(k: usize, keys: Vec<Pk>) -> Result<Self, Error> {
+        Self::terminal(Terminal::Multi(Threshold::new(k, keys)?))
+    }
+
+    /// `multi_a(k,keys)`
+    pub fn multi_a(k: usize, keys: Vec<Pk>) -> Result<Self, Error> {
+        Self::terminal(Terminal::MultiA(Threshold::new(k, keys)?))
+    }
+
+    /// An extension fragment
+    pub fn ext(ext: Ext) -> Result<Self, Error> {
+        Self::terminal(Terminal::Ext(ext))
+    }
+
+    /// `a:self`
+    pub fn a(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::Alt(self.ms))
+    }
+
+    /// `s:self`
+    pub fn s(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::Swap(self.ms))
+    }
+
+    /// `c:self`
+    pub fn c(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::Check(self.ms))
+    }
+
+    /// `d:self`
+    pub fn d(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::DupIf(self.ms))
+    }
+
+    /// `v:self`
+    pub fn v(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::Verify(self.ms))
+    }
+
+    /// `j:self`
+    pub fn j(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::NonZero(self.ms))
+    }
+
+    /// `n:self`
+    pub fn n(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::ZeroNotEqual(self.ms))
+    }
+
+    /// `t:self`, i.e. `and_v(self,1)`
+    pub fn t(self) -> Result<Self, Error> {
+        self.and_v(Self::terminal(Terminal::True)?)
+    }
+
+    /// `l:self`, i.e. `or_i(0,self)`
+    pub fn l(self) -> Result<Self, Error> {
+        Self::terminal(Terminal::False)?.or_i(self)
+    }
+
+    /// `u:self`, i.e. `or_i(self,0)`
+    pub fn u(self) -> Result<Self, Error> {
+        self.or_i(Self::terminal(Terminal::False)?)
+    }
+
+    /// `and_v(self,right)`
+    pub fn and_v(self, right: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::AndV(self.ms, right.ms))
+    }
+
+    /// `and_b(self,right)`
+    pub fn and_b(self, right: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::AndB(self.ms, right.ms))
+    }
+
+    /// `andor(self,then,otherwise)`
+    pub fn andor(self, then: Self, otherwise: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::AndOr(self.ms, then.ms, otherwise.ms))
+    }
+
+    /// `or_b(self,right)`
+    pub fn or_b(self, right: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::OrB(self.ms, right.ms))
+    }
+
+    /// `or_d(self,right)`
+    pub fn or_d(self, right: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::OrD(self.ms, right.ms))
+    }
+
+    /// `or_c(self,right)`
+    pub fn or_c(self, right: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::OrC(self.ms, right.ms))
+    }
+
+    /// `or_i(self,right)`
+    pub fn or_i(self, right: Self) -> Result<Self, Error> {
+        Self::terminal(Terminal::OrI(self.ms, right.ms))
+    }
+
+    /// `thresh(k,subs)`
+    pub fn thresh(k: usize, subs: Vec<Self>) -> Result<Self, Error> {
+        let subs = subs.into_iter().map(|sub| sub.ms).collect();
+        Self::terminal(Terminal::Thresh(Threshold::new(k, subs)?))
+    }
+
+    /// The miniscript built so far, which may not be valid at the top level
+    pub fn as_miniscript(&self) -> &Miniscript<Pk, Ctx, Ext> {
+        &self.ms
+    }
+
+    /// Returns the miniscript, checking that it is valid at the top level,
+    /// i.e. that it has type `B` and passes the top level checks of the
+    /// script context.
+    ///
+    /// As for [`Miniscript::from_str_insane`], the miniscript is not checked
+    /// to be sane, see [`Miniscript::sanity_check`].
+    pub fn build(self) -> Result<Miniscript<Pk, Ctx, Ext>, Error> {
+        Ctx::top_level_checks(&self.ms)?;
+        Ok(Arc::try_unwrap(self.ms).unwrap_or_else(|ms| (*ms).clone()))
+    }
+}
+
+impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> From<Miniscript<Pk, Ctx, Ext>>
+    for MiniscriptBuilder<Pk, Ctx, Ext>
+{
+    fn from(ms: Miniscript<Pk, Ctx, Ext>) -> Self {
+        MiniscriptBuilder { ms: Arc::new(ms) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::{CovExtArgs, LegacyVerEq};
+    use crate::{CovenantExt, Segwitv0, Tap};
+
+    type Builder = MiniscriptBuilder<String, Segwitv0>;
+
+    fn pk(name: &str) -> Builder {
+        Builder::pk(name.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn build() {
+        let ms = Builder::thresh(
+            2,
+            vec![
+                pk("A"),
+                pk("B").s().unwrap(),
+                Builder::terminal(Terminal::False)
+                    .unwrap()
+                    .or_i(
+                        Builder::older(RelLockTime::from_height(144))
+                            .unwrap()
+                            .n()
+                            .unwrap(),
+                    )
+                    .unwrap()
+                    .s()
+                    .unwrap(),
+            ],
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(
+            ms,
+            Miniscript::from_str_insane("thresh(2,pk(A),s:pk(B),sln:older(144))").unwrap()
+        );
+
+        // Errors are reported on the fragment at fault
+        assert!(Builder::thresh(2, vec![pk("A"), pk("B")]).is_err());
+        assert!(Builder::thresh(3, vec![pk("A"), pk("B").s().unwrap()]).is_err());
+        assert!(pk("A").and_v(pk("B")).is_err());
+        // Not valid at the top level
+        assert!(pk("A").v().unwrap().build().is_err());
+        // Not valid in the script context
+        assert!(Builder::multi_a(1, vec!["A".to_owned()]).is_err());
+        assert!(MiniscriptBuilder::<String, Tap>::multi(1, vec!["A".to_owned()]).is_err());
+
+        let ext = CovenantExt::LegacyVerEq(LegacyVerEq { n: 1 });
+        let ms = MiniscriptBuilder::<String, Segwitv0, CovenantExt<CovExtArgs>>::ext(ext)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(ms.to_string(), "ver_eq(1)");
+    }
+}
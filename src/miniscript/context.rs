@@ -356,6 +356,105 @@ where
 
     /// Local helper function to display error messages with context
     fn name_str() -> &'static str;
+
+    /// The runtime value of this context
+    fn context() -> Context;
+}
+
+/// Runtime representation of a [`ScriptContext`].
+///
+/// Allows writing context generic code without being generic over the
+/// context type.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Context {
+    /// [`BareCtx`]
+    BareCtx,
+    /// [`Legacy`]
+    Legacy,
+    /// [`Segwitv0`]
+    Segwitv0,
+    /// [`Tap`]
+    Tap,
+    /// [`NoChecks`]
+    NoChecks,
+}
+
+impl Context {
+    /// Name of the context, as used in error messages
+    pub fn name_str(self) -> &'static str {
+        match self {
+            Context::BareCtx => BareCtx::name_str(),
+            Context::Legacy => Legacy::name_str(),
+            Context::Segwitv0 => Segwitv0::name_str(),
+            Context::Tap => Tap::name_str(),
+            Context::NoChecks => NoChecks::name_str(),
+        }
+    }
+
+    /// The type of signature required for satisfaction
+    pub fn sig_type(self) -> SigType {
+        match self {
+            Context::BareCtx => BareCtx::sig_type(),
+            Context::Legacy => Legacy::sig_type(),
+            Context::Segwitv0 => Segwitv0::sig_type(),
+            Context::Tap => Tap::sig_type(),
+            Context::NoChecks => NoChecks::sig_type(),
+        }
+    }
+
+    /// Maximum script size allowed by consensus rules
+    pub fn max_script_size(self) -> Option<usize> {
+        match self {
+            Context::BareCtx | Context::Segwitv0 => Some(MAX_SCRIPT_SIZE),
+            Context::Legacy => Some(MAX_SCRIPT_ELEMENT_SIZE),
+            Context::Tap => Some(MAX_BLOCK_WEIGHT as usize),
+            Context::NoChecks => None,
+        }
+    }
+
+    /// Maximum script size allowed by standardness rules
+    pub fn max_standard_script_size(self) -> Option<usize> {
+        match self {
+            Context::Segwitv0 => Some(MAX_STANDARD_P2WSH_SCRIPT_SIZE),
+            _ => self.max_script_size(),
+        }
+    }
+
+    /// Maximum number of non-push opcodes per script, `None` if the
+    /// context has no such limit. Taproot replaces it with a sigops budget
+    /// which all miniscripts satisfy.
+    pub fn max_ops_per_script(self) -> Option<usize> {
+        match self {
+            Context::BareCtx | Context::Legacy | Context::Segwitv0 => Some(MAX_OPS_PER_SCRIPT),
+            Context::Tap | Context::NoChecks => None,
+        }
+    }
+
+    /// Maximum number of witness stack elements allowed by standardness
+    /// rules, `None` for contexts without witness.
+    pub fn max_standard_witness_items(self) -> Option<usize> {
+        match self {
+            Context::Segwitv0 => Some(MAX_STANDARD_P2WSH_STACK_ITEMS),
+            Context::Tap => Some(MAX_STACK_SIZE),
+            Context::BareCtx | Context::Legacy | Context::NoChecks => None,
+        }
+    }
+
+    /// Whether uncompressed public keys are allowed
+    pub fn allows_uncompressed_keys(self) -> bool {
+        matches!(self, Context::BareCtx | Context::Legacy | Context::NoChecks)
+    }
+
+    /// Whether x-only public keys are allowed
+    pub fn allows_x_only_keys(self) -> bool {
+        matches!(self, Context::Tap)
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name_str())
+    }
 }
 
 /// Legacy ScriptContext
@@ -405,11 +504,11 @@ impl ScriptContext for Legacy {
                     Self::name_str(),
                 ))
             }
-            Terminal::Multi(_k, ref pks) => {
-                if pks.len() > MAX_PUBKEYS_PER_MULTISIG {
+            Terminal::Multi(ref thresh) => {
+                if thresh.n() > MAX_PUBKEYS_PER_MULTISIG {
                     return Err(ScriptContextError::CheckMultiSigLimitExceeded);
                 }
-                for pk in pks.iter() {
+                for pk in thresh.iter() {
                     if pk.is_x_only_key() {
                         return Err(ScriptContextError::XOnlyKeysNotAllowed(
                             pk.to_string(),
@@ -480,6 +579,10 @@ impl ScriptContext for Legacy {
     fn sig_type() -> SigType {
         SigType::Ecdsa
     }
+
+    fn context() -> Context {
+        Context::Legacy
+    }
 }
 
 /// Segwitv0 ScriptContext
@@ -526,11 +629,11 @@ impl ScriptContext for Segwitv0 {
                 }
                 Ok(())
             }
-            Terminal::Multi(_k, ref pks) => {
-                if pks.len() > MAX_PUBKEYS_PER_MULTISIG {
+            Terminal::Multi(ref thresh) => {
+                if thresh.n() > MAX_PUBKEYS_PER_MULTISIG {
                     return Err(ScriptContextError::CheckMultiSigLimitExceeded);
                 }
-                for pk in pks.iter() {
+                for pk in thresh.iter() {
                     if pk.is_uncompressed() {
                         return Err(ScriptContextError::CompressedOnly(pk.to_string()));
                     } else if pk.is_x_only_key() {
@@ -609,6 +712,10 @@ impl ScriptContext for Segwitv0 {
     fn sig_type() -> SigType {
         SigType::Ecdsa
     }
+
+    fn context() -> Context {
+        Context::Segwitv0
+    }
 }
 
 /// Tap ScriptContext
@@ -723,6 +830,10 @@ impl ScriptContext for Tap {
     fn name_str() -> &'static str {
         "TapscriptCtx"
     }
+
+    fn context() -> Context {
+        Context::Tap
+    }
 }
 
 /// Bare ScriptContext
@@ -763,11 +874,11 @@ impl ScriptContext for BareCtx {
                     Self::name_str(),
                 ))
             }
-            Terminal::Multi(_k, ref pks) => {
-                if pks.len() > MAX_PUBKEYS_PER_MULTISIG {
+            Terminal::Multi(ref thresh) => {
+                if thresh.n() > MAX_PUBKEYS_PER_MULTISIG {
                     return Err(ScriptContextError::CheckMultiSigLimitExceeded);
                 }
-                for pk in pks.iter() {
+                for pk in thresh.iter() {
                     if pk.is_x_only_key() {
                         return Err(ScriptContextError::XOnlyKeysNotAllowed(
                             pk.to_string(),
@@ -803,7 +914,7 @@ impl ScriptContext for BareCtx {
                 Terminal::PkK(_pk) | Terminal::PkH(_pk) => Ok(()),
                 _ => Err(Error::NonStandardBareScript),
             },
-            Terminal::Multi(_k, subs) if subs.len() <= 3 => Ok(()),
+            Terminal::Multi(thresh) if thresh.n() <= 3 => Ok(()),
             _ => Err(Error::NonStandardBareScript),
         }
     }
@@ -830,6 +941,10 @@ impl ScriptContext for BareCtx {
     fn sig_type() -> SigType {
         SigType::Ecdsa
     }
+
+    fn context() -> Context {
+        Context::BareCtx
+    }
 }
 
 /// "No Checks Ecdsa" Context
@@ -938,6 +1053,10 @@ impl ScriptContext for NoChecks {
     fn sig_type() -> SigType {
         SigType::Ecdsa
     }
+
+    fn context() -> Context {
+        Context::NoChecks
+    }
 }
 
 /// Private Mod to prevent downstream from implementing this public trait
@@ -12,7 +12,6 @@ use std::{error, fmt};
 
 use elements::hashes::{hash160, ripemd160, sha256, Hash};
 
-use crate::elements::Sequence;
 use crate::extensions::ParseableExt;
 use crate::miniscript::lex::{Token as Tk, TokenIter};
 use crate::miniscript::limits::{MAX_BLOCK_WEIGHT, MAX_PUBKEYS_PER_MULTISIG};
@@ -22,7 +21,8 @@ use crate::miniscript::ScriptContext;
 #[cfg(doc)]
 use crate::Descriptor;
 use crate::{
-    bitcoin, hash256, AbsLockTime, Error, Extension, Miniscript, MiniscriptKey, NoExt, ToPublicKey,
+    bitcoin, hash256, AbsLockTime, Error, Extension, Miniscript, MiniscriptKey, NoExt, RelLockTime,
+    Threshold, ToPublicKey,
 };
 
 fn return_none<T>(_: usize) -> Option<T> {
@@ -140,7 +140,7 @@ pub enum Terminal<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension = NoExt>
     /// `n CHECKLOCKTIMEVERIFY`
     After(AbsLockTime),
     /// `n CHECKSEQUENCEVERIFY`
-    Older(Sequence),
+    Older(RelLockTime),
     // hashlocks
     /// `SIZE 32 EQUALVERIFY SHA256 <hash> EQUAL`
     Sha256(Pk::Sha256),
@@ -187,11 +187,11 @@ pub enum Terminal<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension = NoExt>
     OrI(Arc<Miniscript<Pk, Ctx, Ext>>, Arc<Miniscript<Pk, Ctx, Ext>>),
     // Thresholds
     /// `[E] ([W] ADD)* k EQUAL`
-    Thresh(usize, Vec<Arc<Miniscript<Pk, Ctx, Ext>>>),
+    Thresh(Threshold<Arc<Miniscript<Pk, Ctx, Ext>>>),
     /// `k (<key>)* n CHECKMULTISIG`
-    Multi(usize, Vec<Pk>),
+    Multi(Threshold<Pk>),
     /// `<key> CHECKSIG (<key> CHECKSIGADD)*(n-1) k NUMEQUAL`
-    MultiA(usize, Vec<Pk>),
+    MultiA(Threshold<Pk>),
     /// Extensions
     Ext(Ext),
 }
@@ -398,7 +398,7 @@ pub fn parse<Ctx: ScriptContext, Ext: ParseableExt>(
                     },
                     // timelocks
                     Tk::CheckSequenceVerify, Tk::Num(n)
-                        => term.reduce0(Terminal::Older(Sequence::from_consensus(n)))?,
+                        => term.reduce0(Terminal::Older(RelLockTime::from_consensus(n)))?,
                     Tk::CheckLockTimeVerify, Tk::Num(n)
                         => term.reduce0(Terminal::After(AbsLockTime::from_consensus(n)))?,
                     // hashlocks
@@ -488,7 +488,7 @@ pub fn parse<Ctx: ScriptContext, Ext: ParseableExt>(
                             Tk::Num(k) => k,
                         );
                         keys.reverse();
-                        term.reduce0(Terminal::Multi(k as usize, keys))?;
+                        term.reduce0(Terminal::Multi(Threshold::new(k as usize, keys)?))?;
                     },
                     // MultiA
                     Tk::NumEqual, Tk::Num(k) => {
@@ -511,7 +511,7 @@ pub fn parse<Ctx: ScriptContext, Ext: ParseableExt>(
                                 .map_err(|e| Error::PubKeyCtxError(e, Ctx::name_str()))?),
                         );
                         keys.reverse();
-                        term.reduce0(Terminal::MultiA(k as usize, keys))?;
+                        term.reduce0(Terminal::MultiA(Threshold::new(k as usize, keys)?))?;
                     },
                 );
             }
@@ -590,7 +590,7 @@ pub fn parse<Ctx: ScriptContext, Ext: ParseableExt>(
                 for _ in 0..n {
                     subs.push(Arc::new(term.pop().unwrap()));
                 }
-                term.reduce0(Terminal::Thresh(k, subs))?;
+                term.reduce0(Terminal::Thresh(Threshold::new(k, subs)?))?;
             }
             Some(NonTerm::EndIf) => {
                 match_token!(
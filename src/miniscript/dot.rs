@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Graphviz Rendering
+//!
+//! Rendering of miniscripts, and of the script trees of taproot descriptors,
+//! as Graphviz DOT graphs, e.g. to review large covenants. Every fragment is
+//! labelled with its script size and the maximum size of its satisfaction and
+//! dissatisfaction, i.e. of the witness or of the scriptSig in pre-segwit
+//! contexts.
+//!
+
+use super::{Context, Miniscript, ScriptContext};
+use crate::{Extension, MiniscriptKey};
+
+/// Writer of a DOT graph, handing out the identifiers of the nodes
+pub(crate) struct DotWriter {
+    out: String,
+    next_id: usize,
+}
+
+impl DotWriter {
+    /// Starts a directed graph named `name`
+    pub(crate) fn new(name: &str) -> Self {
+        DotWriter {
+            out: format!("digraph {} {{\n    node [shape=box];\n", name),
+            next_id: 0,
+        }
+    }
+
+    /// Adds a node with the given label, returning its identifier
+    pub(crate) fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.out.push_str(&format!(
+            "    n{} [label=\"{}\"];\n",
+            id,
+            label.replace('\n', "\\n")
+        ));
+        id
+    }
+
+    /// Adds an edge between two nodes
+    pub(crate) fn edge(&mut self, from: usize, to: usize) {
+        self.out.push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+
+    /// Adds the nodes of `ms` and its subexpressions, returning the
+    /// identifier of its root
+    pub(crate) fn miniscript<Pk, Ctx, Ext>(&mut self, ms: &Miniscript<Pk, Ctx, Ext>) -> usize
+    where
+        Pk: MiniscriptKey,
+        Ctx: ScriptContext,
+        Ext: Extension,
+    {
+        // Only the scriptSig is used in pre-segwit contexts
+        let legacy = matches!(Ctx::context(), Context::Legacy | Context::BareCtx);
+        let cost = |size: Option<(usize, usize)>| match size {
+            Some((_, script_sig)) if legacy => script_sig.to_string(),
+            Some((witness, _)) => witness.to_string(),
+            None => "-".to_owned(),
+        };
+        let label = format!(
+            "{}\nscript: {} B\nsat: {} B\ndissat: {} B",
+            ms.fragment_name(),
+            ms.ext.pk_cost,
+            cost(ms.ext.max_sat_size),
+            cost(ms.ext.max_dissat_size),
+        );
+        let id = self.node(&label);
+        for branch in ms.branches() {
+            let child = self.miniscript(branch);
+            self.edge(id, child);
+        }
+        id
+    }
+
+    /// Closes the graph, returning its DOT source
+    pub(crate) fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+}
+
+impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Miniscript<Pk, Ctx, Ext> {
+    /// Graphviz DOT source of the miniscript, with a node per fragment,
+    /// labelled with its script size and the maximum size of its satisfaction
+    /// and dissatisfaction witnesses, in bytes.
+    ///
+    /// The graph can be rendered with e.g. `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut writer = DotWriter::new("miniscript");
+        writer.miniscript(self);
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::Segwitv0;
+
+    #[test]
+    fn miniscript_to_dot() {
+        let ms = Miniscript::<String, Segwitv0>::from_str("or_d(pk(A),and_v(v:pk(B),older(144)))")
+            .unwrap();
+        let dot = ms.to_dot();
+        assert!(dot.starts_with("digraph miniscript {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("n0 [label=\"or_d\\nscript: 77 B\\nsat: "));
+        assert!(dot.contains("n2 [label=\"pk_k(A)\\nscript: 34 B\\nsat: 73 B\\ndissat: 1 B\"];"));
+        assert!(dot.contains("n7 [label=\"older(144)\\nscript: 4 B\\nsat: 0 B\\ndissat: - B\"];"));
+        // One edge per subexpression
+        assert_eq!(dot.matches(" -> ").count(), 7);
+        assert!(dot.contains("n0 -> n3;"));
+    }
+}
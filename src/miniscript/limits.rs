@@ -36,3 +36,7 @@ pub const MAX_BLOCK_WEIGHT: usize = 4000000;
 /// Maximum pubkeys as arguments to CHECKMULTISIG
 // https://github.com/bitcoin/bitcoin/blob/6acda4b00b3fc1bfac02f5de590e1a5386cbc779/src/script/script.h#L30
 pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+/// Maximum pubkeys in a CHECKSIGADD multisig, bounded by the tapscript stack
+/// size limit
+pub const MAX_PUBKEYS_IN_CHECKSIGADD: usize = 999;
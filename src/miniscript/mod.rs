@@ -13,19 +13,24 @@
 //! components of the AST.
 //!
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::{fmt, str};
 
+use elements::hashes::hash160;
 use elements::script;
 use elements::taproot::{LeafVersion, TapLeafHash};
 
 use self::analyzable::ExtParams;
-pub use self::context::{BareCtx, Legacy, Segwitv0, Tap};
+pub use self::context::{BareCtx, Context, Legacy, Segwitv0, Tap};
 
 pub mod analyzable;
 pub mod astelem;
+pub mod budget;
+pub mod builder;
 pub(crate) mod context;
 pub mod decode;
+pub(crate) mod dot;
 pub mod iter;
 pub mod lex;
 pub mod limits;
@@ -115,6 +120,12 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Miniscript<Pk, Ctx,
     /// Add type information(Type and Extdata) to Miniscript based on
     /// `AstElem` fragment. Dependent on display and clone because of Error
     /// Display code of type_check.
+    ///
+    /// The fragment is typed from the types of its subexpressions, and an
+    /// error is returned if they cannot be composed, but the fragment is not
+    /// checked against the limits of the script context. Use
+    /// [`crate::MiniscriptBuilder`] to also check them while building a
+    /// miniscript fragment by fragment.
     pub fn from_ast(t: Terminal<Pk, Ctx, Ext>) -> Result<Miniscript<Pk, Ctx, Ext>, Error> {
         Ok(Miniscript {
             ty: Type::type_check(&t, |_| None)?,
@@ -285,6 +296,143 @@ impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> Miniscript<Pk, Ctx,
     pub fn max_satisfaction_size(&self) -> Result<usize, Error> {
         Ctx::max_satisfaction_size::<Pk, Ext>(self).ok_or(Error::ImpossibleSatisfaction)
     }
+
+    /// Number of fragments of the Miniscript, including wrappers
+    pub fn node_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Depth of the Miniscript AST, where a fragment without children has
+    /// depth 1. Wrappers count as one level each.
+    pub fn depth(&self) -> usize {
+        1 + self
+            .branches()
+            .iter()
+            .map(|ms| ms.depth())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Score of the resources needed by the worst case satisfaction of the
+    /// Miniscript, to enforce limits on untrusted scripts.
+    ///
+    /// The score is the sum of the number of executed opcodes, the number of
+    /// witness elements and the maximum number of elements pushed on the
+    /// stack during execution.
+    ///
+    /// This function may returns Error when the Miniscript is
+    /// impossible to satisfy
+    pub fn complexity_score(&self) -> Result<usize, Error> {
+        match (
+            self.ext.ops.op_count(),
+            self.ext.stack_elem_count_sat,
+            self.ext.exec_stack_elem_count_sat,
+        ) {
+            (Some(ops), Some(wit), Some(exec)) => Ok(ops + wit + exec),
+            _ => Err(Error::ImpossibleSatisfaction),
+        }
+    }
+
+    /// Substitutes the raw public key hashes of `expr_raw_pkh` fragments, as
+    /// obtained when parsing a script, with the public keys provided by
+    /// `pk_map`. Hashes which are not in the map are left untouched.
+    ///
+    /// The map is trusted to be correct: the hash of each key is not
+    /// checked against the hash it replaces.
+    pub fn substitute_raw_pkh(&self, pk_map: &HashMap<hash160::Hash, Pk>) -> Self {
+        let sub = |ms: &Arc<Self>| Arc::new(ms.substitute_raw_pkh(pk_map));
+        let node = match self.node {
+            Terminal::RawPkH(ref hash) => match pk_map.get(hash) {
+                Some(pk) => Terminal::PkH(pk.clone()),
+                None => return self.clone(),
+            },
+            Terminal::Alt(ref s) => Terminal::Alt(sub(s)),
+            Terminal::Swap(ref s) => Terminal::Swap(sub(s)),
+            Terminal::Check(ref s) => Terminal::Check(sub(s)),
+            Terminal::DupIf(ref s) => Terminal::DupIf(sub(s)),
+            Terminal::Verify(ref s) => Terminal::Verify(sub(s)),
+            Terminal::NonZero(ref s) => Terminal::NonZero(sub(s)),
+            Terminal::ZeroNotEqual(ref s) => Terminal::ZeroNotEqual(sub(s)),
+            Terminal::AndV(ref l, ref r) => Terminal::AndV(sub(l), sub(r)),
+            Terminal::AndB(ref l, ref r) => Terminal::AndB(sub(l), sub(r)),
+            Terminal::AndOr(ref a, ref b, ref c) => Terminal::AndOr(sub(a), sub(b), sub(c)),
+            Terminal::OrB(ref l, ref r) => Terminal::OrB(sub(l), sub(r)),
+            Terminal::OrD(ref l, ref r) => Terminal::OrD(sub(l), sub(r)),
+            Terminal::OrC(ref l, ref r) => Terminal::OrC(sub(l), sub(r)),
+            Terminal::OrI(ref l, ref r) => Terminal::OrI(sub(l), sub(r)),
+            Terminal::Thresh(ref thresh) => Terminal::Thresh(thresh.map_ref(sub)),
+            _ => return self.clone(),
+        };
+        Miniscript {
+            // pk_h and expr_raw_pkh have the same type and properties
+            ty: self.ty,
+            ext: self.ext,
+            node,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Formats the Miniscript with one fragment per line, children indented
+    /// under their parent, each annotated with its type (e.g. `B/onduesm`,
+    /// see the miniscript spec) and its costs: the script size, the maximum
+    /// satisfaction and dissatisfaction sizes when put in the witness and in
+    /// the scriptSig, as `witness/scriptSig` bytes, and the number of
+    /// executed opcodes. Unavailable costs are written as `-`.
+    ///
+    /// Useful to find out why a fragment does not have the expected type.
+    pub fn fmt_verbose(&self) -> String {
+        let mut s = String::new();
+        self.write_verbose(&mut s, 0);
+        s
+    }
+
+    /// The name of the fragment, with its arguments other than
+    /// subexpressions, as written by [`Miniscript::fmt_verbose`] and
+    /// [`Miniscript::to_dot`]
+    pub(crate) fn fragment_name(&self) -> String {
+        match self.node {
+            Terminal::Alt(..) => "a:".to_owned(),
+            Terminal::Swap(..) => "s:".to_owned(),
+            Terminal::Check(..) => "c:".to_owned(),
+            Terminal::DupIf(..) => "d:".to_owned(),
+            Terminal::Verify(..) => "v:".to_owned(),
+            Terminal::NonZero(..) => "j:".to_owned(),
+            Terminal::ZeroNotEqual(..) => "n:".to_owned(),
+            Terminal::AndV(..) => "and_v".to_owned(),
+            Terminal::AndB(..) => "and_b".to_owned(),
+            Terminal::AndOr(..) => "andor".to_owned(),
+            Terminal::OrB(..) => "or_b".to_owned(),
+            Terminal::OrD(..) => "or_d".to_owned(),
+            Terminal::OrC(..) => "or_c".to_owned(),
+            Terminal::OrI(..) => "or_i".to_owned(),
+            Terminal::Thresh(ref thresh) => format!("thresh({} of {})", thresh.k(), thresh.n()),
+            // Leaves are written as in the miniscript string
+            ref leaf => leaf.to_string(),
+        }
+    }
+
+    fn write_verbose(&self, s: &mut String, level: usize) {
+        let size = |size: Option<(usize, usize)>| match size {
+            Some((wit, sig)) => format!("{}/{}", wit, sig),
+            None => "-".to_owned(),
+        };
+        s.push_str(&format!(
+            "{}{} [{}] script: {}, sat: {}, dissat: {}, ops: {}\n",
+            "  ".repeat(level),
+            self.fragment_name(),
+            self.ty,
+            self.ext.pk_cost,
+            size(self.ext.max_sat_size),
+            size(self.ext.max_dissat_size),
+            self.ext
+                .ops
+                .op_count()
+                .map_or("-".to_owned(), |ops| ops.to_string()),
+        ));
+        for sub in self.branches() {
+            sub.write_verbose(s, level + 1);
+        }
+    }
 }
 
 impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> ForEachKey<Pk>
@@ -417,7 +565,13 @@ impl_block_str!(
     {
         // This checks for invalid ASCII chars
         let top = expression::Tree::from_str(s)?;
-        let ms: Miniscript<Pk, Ctx, Ext> = expression::FromTree::from_tree(&top)?;
+        let ms: Miniscript<Pk, Ctx, Ext> =
+            expression::FromTree::from_tree(&top).map_err(|e| match e {
+                Error::TypeCheck(msg) => {
+                    Error::TypeCheck(Self::explain_type_error(&top).unwrap_or(msg))
+                }
+                e => e,
+            })?;
         ms.ext_check(ext)?;
 
         if ms.ty.corr.base != types::Base::B {
@@ -428,11 +582,68 @@ impl_block_str!(
     }
 );
 
+impl_block_str!(
+    ;Ctx; ScriptContext,
+    Miniscript<Pk, Ctx, Ext>,
+    => Ext ; Extension,
+    // Walks down the tree to the fragment which failed typechecking and
+    // describes the error along with the path to the fragment and, when a
+    // single child is at fault, its type and the type required for it.
+    fn explain_type_error(top: &expression::Tree<'_>,) -> Option<String>
+    {
+        let mut path = vec![];
+        let mut node = top;
+        loop {
+            path.push(node.name);
+            let child = node.args.iter().find(|arg| {
+                matches!(
+                    <Self as expression::FromTree>::from_tree(arg),
+                    Err(Error::TypeCheck(_))
+                )
+            });
+            match child {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+        let inner: Terminal<Pk, Ctx, Ext> = expression::FromTree::from_tree(node).ok()?;
+        let err = match Type::type_check(&inner, |_| None) {
+            Err(e) => e,
+            Ok(_) => ExtData::type_check(&inner, |_| None).err()?,
+        };
+        let mut ret = format!("{} (at {}", err, path.join(" → "));
+        if let Some((idx, child, req)) = err.offending_child() {
+            ret.push_str(&format!(
+                " → [{} arg]: «{}» has type {}, but {} is required",
+                ordinal(idx + 1),
+                child,
+                child.ty,
+                req,
+            ));
+        }
+        ret.push(')');
+        Some(ret)
+    }
+);
+
+// Writes a position as `1st`, `2nd`, `3rd`, `4th` etc.
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
 impl<Pk: ToPublicKey, Ctx: ScriptContext, Ext: ParseableExt> Miniscript<Pk, Ctx, Ext> {
     /// Attempt to produce non-malleable satisfying witness for the
     /// witness script represented by the parse tree
     pub fn satisfy<S: satisfy::Satisfier<Pk>>(&self, satisfier: S) -> Result<Vec<Vec<u8>>, Error> {
         // Only satisfactions for default versions (0xc0) are allowed.
+        satisfy::check_preimages(self, &satisfier)?;
         let leaf_hash = TapLeafHash::from_script(&self.encode(), LeafVersion::default());
         match satisfy::Satisfaction::satisfy(&self.node, &satisfier, self.ty.mall.safe, &leaf_hash)
             .stack
@@ -442,7 +653,7 @@ impl<Pk: ToPublicKey, Ctx: ScriptContext, Ext: ParseableExt> Miniscript<Pk, Ctx,
                 Ok(stack)
             }
             satisfy::Witness::Unavailable | satisfy::Witness::Impossible => {
-                Err(Error::CouldNotSatisfy)
+                Err(self.unsatisfied_error(&satisfier))
             }
         }
     }
@@ -453,6 +664,7 @@ impl<Pk: ToPublicKey, Ctx: ScriptContext, Ext: ParseableExt> Miniscript<Pk, Ctx,
         &self,
         satisfier: S,
     ) -> Result<Vec<Vec<u8>>, Error> {
+        satisfy::check_preimages(self, &satisfier)?;
         let leaf_hash = TapLeafHash::from_script(&self.encode(), LeafVersion::default());
         match satisfy::Satisfaction::satisfy_mall(
             &self.node,
@@ -467,10 +679,30 @@ impl<Pk: ToPublicKey, Ctx: ScriptContext, Ext: ParseableExt> Miniscript<Pk, Ctx,
                 Ok(stack)
             }
             satisfy::Witness::Unavailable | satisfy::Witness::Impossible => {
-                Err(Error::CouldNotSatisfy)
+                Err(self.unsatisfied_error(&satisfier))
             }
         }
     }
+
+    /// The signatures, hash preimages and timelocks `satisfier` lacks to
+    /// satisfy the miniscript, along the spending path which needs the
+    /// fewest of them. Returns `None` if the miniscript cannot be satisfied
+    /// whatever is added to the satisfier.
+    pub fn missing_items<S: satisfy::Satisfier<Pk>>(
+        &self,
+        satisfier: S,
+    ) -> Option<satisfy::Missing<Pk>> {
+        let leaf_hash = TapLeafHash::from_script(&self.encode(), LeafVersion::default());
+        satisfy::Missing::of_terminal(&self.node, &satisfier, &leaf_hash)
+    }
+
+    // Reports the missing items of a failed satisfaction, if any
+    fn unsatisfied_error<S: satisfy::Satisfier<Pk>>(&self, satisfier: S) -> Error {
+        match self.missing_items(satisfier) {
+            Some(missing) if !missing.is_empty() => Error::MissingItems(missing.into_public_keys()),
+            _ => Error::CouldNotSatisfy,
+        }
+    }
 }
 
 impl_from_tree!(
@@ -537,17 +769,20 @@ mod tests {
 
     use bitcoin::key::XOnlyPublicKey;
     use bitcoin::{self};
-    use elements::hashes::{hash160, sha256, Hash};
+    use elements::hashes::{hash160, ripemd160, sha256, Hash};
     use elements::taproot::TapLeafHash;
     use elements::{self, secp256k1_zkp, Sequence};
 
-    use super::{Miniscript, ScriptContext, Segwitv0, Tap};
+    use super::{Context, Miniscript, ScriptContext, Segwitv0, Tap};
     use crate::extensions::CovExtArgs;
     use crate::miniscript::types::{self, ExtData, Property, Type};
     use crate::miniscript::Terminal;
     use crate::policy::Liftable;
     use crate::test_utils::{StrKeyTranslator, StrXOnlyKeyTranslator};
-    use crate::{hex_script, CovenantExt, ExtParams, NoExt, Satisfier, ToPublicKey, TranslatePk};
+    use crate::{
+        hex_script, AtHeightAndTime, CovenantExt, DefiniteDescriptorKey, Error, ExtParams, NoExt,
+        RelLockTime, Satisfier, SigType, ToPublicKey, TranslatePk,
+    };
 
     type Tapscript = Miniscript<XOnlyPublicKey, Tap, NoExt>;
     type Segwitv0Script = Miniscript<bitcoin::PublicKey, Segwitv0, CovenantExt<CovExtArgs>>;
@@ -1193,6 +1428,63 @@ mod tests {
         assert_eq!(wit, vec![schnorr_sig.as_ref().to_vec(), vec![], vec![]]);
     }
 
+    #[test]
+    fn missing_items() {
+        use std::collections::HashMap;
+
+        use crate::miniscript::satisfy::{ElementsSig, Missing, MissingTimelock};
+
+        let keys = pubkeys(4);
+        let ms = Segwitv0Script::from_str(&format!(
+            "or_d(multi(2,{},{},{}),and_v(v:pk({}),older(144)))",
+            keys[0], keys[1], keys[2], keys[3],
+        ))
+        .unwrap();
+        let sig = (
+            secp256k1_zkp::ecdsa::Signature::from_compact(&[1; 64]).unwrap(),
+            elements::EcdsaSigHashType::All,
+        );
+
+        // The multisig is missing a single signature
+        let mut sigs = HashMap::<bitcoin::PublicKey, ElementsSig>::new();
+        sigs.insert(keys[0], sig);
+        let missing = Missing {
+            sigs: vec![keys[1]],
+            ..Default::default()
+        };
+        assert_eq!(ms.missing_items(&sigs), Some(missing.clone()));
+        assert_eq!(ms.satisfy(&sigs), Err(crate::Error::MissingItems(missing)));
+
+        // The timelocked branch is only missing its timelock
+        let mut sigs = HashMap::<bitcoin::PublicKey, ElementsSig>::new();
+        sigs.insert(keys[3], sig);
+        let missing = Missing {
+            timelocks: vec![MissingTimelock::Older(RelLockTime::from_height(144))],
+            ..Default::default()
+        };
+        assert_eq!(ms.missing_items(&sigs), Some(missing));
+        assert!(ms.satisfy((&sigs, Sequence::from_consensus(144))).is_ok());
+
+        // Ties are broken in favor of the first branch
+        assert_eq!(ms.missing_items(()).unwrap().sigs, vec![keys[0], keys[1]]);
+
+        // The keys are the ones of the miniscript
+        let ms = Miniscript::<DefiniteDescriptorKey, Segwitv0>::from_str(&format!(
+            "and_v(v:pk({}),pk({}))",
+            keys[0], keys[1]
+        ))
+        .unwrap();
+        let missing = ms.missing_items(()).unwrap();
+        assert_eq!(missing.sigs[0].to_public_key(), keys[0]);
+        assert_eq!(missing.into_public_keys().sigs, vec![keys[0], keys[1]]);
+        assert_eq!(
+            Segwitv0Script::from_str_insane("0")
+                .unwrap()
+                .missing_items(()),
+            None
+        );
+    }
+
     #[test]
     fn decode_bug_cpp_review() {
         let ms = Miniscript::<String, Segwitv0>::from_str_insane(
@@ -1224,6 +1516,74 @@ mod tests {
         SegwitMs::parse_with_ext(&script, &ExtParams::allow_all()).unwrap();
     }
 
+    #[test]
+    fn substitute_raw_pkh() {
+        type SegwitMs = Miniscript<bitcoin::PublicKey, Segwitv0>;
+        let keys = pubkeys(2);
+        let ms = SegwitMs::from_str(&format!(
+            "or_d(pk({}),and_v(v:pkh({}),older(10)))",
+            keys[0], keys[1]
+        ))
+        .unwrap();
+
+        // Parsing the script loses the key of the pkh fragment
+        let parsed = SegwitMs::parse_with_ext(&ms.encode(), &ExtParams::allow_all()).unwrap();
+        assert_ne!(parsed, ms);
+        assert!(parsed.to_string().contains("expr_raw_pkh"));
+        assert_eq!(
+            parsed.substitute_raw_pkh(&std::collections::HashMap::new()),
+            parsed
+        );
+
+        let mut pk_map = std::collections::HashMap::new();
+        pk_map.insert(keys[1].to_pubkeyhash(SigType::Ecdsa), keys[1]);
+        let substituted = parsed.substitute_raw_pkh(&pk_map);
+        assert_eq!(substituted, ms);
+        assert_eq!(substituted.to_string(), ms.to_string());
+        assert_eq!(substituted.encode(), ms.encode());
+    }
+
+    #[test]
+    fn fmt_verbose() {
+        let ms = Miniscript::<String, Segwitv0>::from_str("or_d(pk(A),older(10))").unwrap();
+        let verbose = ms.fmt_verbose();
+        let lines = verbose.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with(&format!("or_d [{}] script: {},", ms.ty, ms.ext.pk_cost)));
+        assert!(lines[1].starts_with("  c: [B/onduesm]"));
+        assert_eq!(
+            lines[2],
+            "    pk_k(A) [K/onduesm] script: 34, sat: 73/73, dissat: 1/1, ops: 0"
+        );
+        assert!(lines[3].starts_with("  older(10) [B/z"));
+    }
+
+    #[test]
+    fn type_error_path() {
+        let err = Miniscript::<String, Segwitv0>::from_str("or_b(pk(A),a:and_v(v:pk(B),pk(C)))")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("(at or_b → [2nd arg]: «a:and_v(v:pk(B),pk(C))» has type W/"));
+        assert!(err.ends_with(", but d is required)"));
+
+        let err = Miniscript::<String, Segwitv0>::from_str(
+            "or_d(pk(A),or_b(pk(B),a:and_v(v:pk(C),after(10))))",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains("(at or_d → or_b → [2nd arg]: «a:and_v(v:pk(C),after(10))» has type W/")
+        );
+        assert!(err.ends_with(", but d is required)"));
+
+        let err = Miniscript::<String, Segwitv0>::from_str("thresh(2,pk(A),pk(B))")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("(at thresh → [2nd arg]: «pk(B)» has type B/onduesm, but W is required)")
+        );
+    }
+
     #[test]
     fn tr_multi_a_j_wrapper() {
         // Reported by darosior
@@ -1233,4 +1593,93 @@ mod tests {
         let ms_str = TapMs::from_str_insane("j:multi_a(1,A,B,C)");
         assert!(ms_str.is_err());
     }
+
+    #[test]
+    fn runtime_context() {
+        fn check_limits<Ctx: ScriptContext>(ms: &str) -> bool {
+            let ms = Miniscript::<String, Ctx>::from_str_insane(ms).unwrap();
+            let ctx = Ctx::context();
+            ctx.max_standard_script_size()
+                .map_or(true, |max| ms.script_size() <= max)
+        }
+
+        assert_eq!(Segwitv0::context(), Context::Segwitv0);
+        assert_eq!(Tap::context().to_string(), "TapscriptCtx");
+        assert_eq!(Context::Tap.sig_type(), SigType::Schnorr);
+        assert_eq!(Context::Segwitv0.max_standard_script_size(), Some(3600));
+        assert_eq!(Context::Tap.max_ops_per_script(), None);
+        assert!(Context::Legacy.allows_uncompressed_keys());
+        assert!(!Context::Segwitv0.allows_x_only_keys());
+        assert!(check_limits::<Segwitv0>("and_v(v:pk(A),pk(B))"));
+        assert!(check_limits::<Tap>("and_v(v:pk(A),pk(B))"));
+    }
+
+    #[test]
+    fn preimage_mismatch() {
+        struct PreimageSatisfier([u8; 32]);
+
+        impl<Pk: ToPublicKey> Satisfier<Pk> for PreimageSatisfier {
+            fn lookup_ripemd160(&self, _h: &Pk::Ripemd160) -> Option<[u8; 32]> {
+                Some(self.0)
+            }
+        }
+
+        let preimage = [0x42; 32];
+        let hash = ripemd160::Hash::hash(&preimage);
+        let ms = Miniscript::<bitcoin::PublicKey, Segwitv0>::from_str_insane(&format!(
+            "ripemd160({})",
+            hash
+        ))
+        .unwrap();
+        assert_eq!(
+            ms.satisfy(PreimageSatisfier(preimage)).unwrap(),
+            vec![preimage.to_vec()]
+        );
+        // A wrong preimage is an error instead of an invalid witness
+        match ms.satisfy(PreimageSatisfier([0x43; 32])) {
+            Err(Error::PreimageMismatch(_)) => {}
+            res => panic!("expected preimage mismatch, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn complexity() {
+        type Ms = Miniscript<String, Segwitv0>;
+        let pk = Ms::from_str_insane("pk(A)").unwrap();
+        assert_eq!(pk.node_count(), 2);
+        assert_eq!(pk.depth(), 2);
+        // CHECKSIG with one witness element pushing the key
+        assert_eq!(pk.complexity_score().unwrap(), 1 + 1 + 1);
+
+        let ms = Ms::from_str_insane("and_v(v:pk(A),or_d(pk(B),older(10)))").unwrap();
+        assert_eq!(ms.node_count(), 8);
+        assert_eq!(ms.depth(), 4);
+        assert!(ms.complexity_score().unwrap() > pk.complexity_score().unwrap());
+
+        let impossible = Ms::from_str_insane("and_v(v:0,pk(A))").unwrap();
+        assert!(impossible.complexity_score().is_err());
+    }
+
+    #[test]
+    fn at_height_and_time() {
+        let tip = AtHeightAndTime {
+            height: 1000,
+            mtp: 1_600_000_000,
+            confirmation_height: 991,
+            confirmation_mtp: 1_600_000_000 - 512 * 10,
+        };
+        let satisfied = |s: &str| {
+            let ms = Miniscript::<bitcoin::PublicKey, Segwitv0>::from_str_insane(s).unwrap();
+            ms.satisfy(tip).is_ok()
+        };
+        // Included at height 1001, ten blocks after the confirmation
+        assert!(satisfied("older(10)"));
+        assert!(!satisfied("older(11)"));
+        assert!(satisfied("older(4194314)"));
+        assert!(!satisfied("older(4194315)"));
+        assert!(satisfied("after(1000)"));
+        assert!(!satisfied("after(1001)"));
+        assert!(satisfied("after(1599999999)"));
+        assert!(!satisfied("after(1600000000)"));
+    }
 }
@@ -9,42 +9,100 @@
 
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use std::{cmp, i64, mem};
+use std::{cmp, fmt, i64, mem};
 
-use bitcoin::hashes::hash160;
+use bitcoin::hashes::{hash160, ripemd160, sha256, Hash};
 use bitcoin::secp256k1::XOnlyPublicKey;
+use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
 use elements::hashes::sha256d;
 use elements::secp256k1_zkp::schnorr;
 use elements::taproot::{ControlBlock, LeafVersion, TapLeafHash};
-use elements::{self, confidential, secp256k1_zkp, LockTime, OutPoint, Script, Sequence};
+use elements::{self, confidential, secp256k1_zkp, AssetId, LockTime, OutPoint, Script, Sequence};
 
 use super::context::SigType;
 use crate::extensions::{CsfsMsg, ParseableExt};
+use crate::sighash::EcdsaSig;
 use crate::util::witness_size;
-use crate::{Miniscript, MiniscriptKey, ScriptContext, Terminal, ToPublicKey};
+use crate::{
+    hash256, AbsLockTime, Error, Miniscript, MiniscriptKey, RelLockTime, ScriptContext, Terminal,
+    ToPublicKey,
+};
 
 /// Type alias for a signature/hashtype pair
 pub type ElementsSig = (secp256k1_zkp::ecdsa::Signature, elements::EcdsaSigHashType);
 /// Type alias for 32 byte Preimage.
 pub type Preimage32 = [u8; 32];
-
-/// Convert to raw sig
+/// Type alias for an ECDSA adaptor signature, i.e. a pre-signature encrypted
+/// to an adaptor point, and its hashtype
+pub type ElementsAdaptorSig = (
+    secp256k1_zkp::EcdsaAdaptorSignature,
+    elements::EcdsaSigHashType,
+);
+
+/// Serialize a signature as it is placed in a witness, i.e. the DER
+/// signature followed by its hashtype byte, see [`EcdsaSig::to_vec`]
 pub fn elementssig_to_rawsig(sig: &ElementsSig) -> Vec<u8> {
-    let ser_sig = sig.0.serialize_der();
-    let mut raw_sig = Vec::from(&ser_sig[..]);
+    EcdsaSig::from(*sig).to_vec()
+}
+
+/// Serialize a pre-adapted signature as it is placed in a witness, i.e. the
+/// adaptor signature followed by its hashtype byte
+pub fn adaptorsig_to_rawsig(sig: &ElementsAdaptorSig) -> Vec<u8> {
+    let mut raw_sig = Vec::from(sig.0.as_ref());
     raw_sig.push(sig.1 as u8);
     raw_sig
 }
 
 /// Helper function to create ElementsSig from Rawsig
 /// Useful for downstream when implementing Satisfier.
-/// Returns underlying secp if the Signature is not of correct format
+/// Returns underlying secp if the Signature is not of correct format.
+///
+/// The signature must be strict DER, and the hashtype byte is interpreted
+/// as consensus does, see [`EcdsaSig::from_slice`] for other modes.
 pub fn elementssig_from_rawsig(rawsig: &[u8]) -> Result<ElementsSig, crate::interpreter::Error> {
-    let (flag, sig) = rawsig.split_last().unwrap();
+    let (flag, sig) = rawsig
+        .split_last()
+        .ok_or(secp256k1_zkp::UpstreamError::InvalidSignature)?;
     let flag = elements::EcdsaSigHashType::from_u32(*flag as u32);
     let sig = secp256k1_zkp::ecdsa::Signature::from_der(sig)?;
     Ok((sig, flag))
 }
+
+// Checks that the preimage returned by a satisfier hashes to `hash`
+fn check_preimage<H: Hash>(
+    name: &str,
+    hash: H,
+    preimage: Option<Preimage32>,
+) -> Result<Option<Preimage32>, Error> {
+    match preimage {
+        Some(pre) if H::hash(&pre) != hash => {
+            Err(Error::PreimageMismatch(format!("{}({})", name, hash)))
+        }
+        pre => Ok(pre),
+    }
+}
+
+/// Checks that all the hash preimages provided by the satisfier for the
+/// hash fragments of `ms` hash to the expected values.
+pub fn check_preimages<Pk, Ctx, Ext, S>(ms: &Miniscript<Pk, Ctx, Ext>, sat: &S) -> Result<(), Error>
+where
+    Pk: MiniscriptKey + ToPublicKey,
+    Ctx: ScriptContext,
+    Ext: ParseableExt,
+    S: Satisfier<Pk>,
+{
+    for node in ms.iter() {
+        match node.node {
+            Terminal::Sha256(ref h) => sat.lookup_sha256_checked(h).map(|_| ())?,
+            Terminal::Hash256(ref h) => sat.lookup_hash256_checked(h).map(|_| ())?,
+            Terminal::Ripemd160(ref h) => sat.lookup_ripemd160_checked(h).map(|_| ())?,
+            Terminal::Hash160(ref h) => sat.lookup_hash160_checked(h).map(|_| ())?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 /// Trait describing a lookup table for signatures, hash preimages, etc.
 /// Every method has a default implementation that simply returns `None`
 /// on every query. Users are expected to override the methods that they
@@ -55,11 +113,33 @@ pub trait Satisfier<Pk: MiniscriptKey + ToPublicKey> {
         None
     }
 
+    /// Lookup the adaptor point to which the ECDSA signatures are encrypted,
+    /// for a satisfaction with pre-adapted signatures, see [`AdaptorPoint`]
+    fn lookup_adaptor_point(&self) -> Option<secp256k1_zkp::PublicKey> {
+        None
+    }
+
+    /// Given a public key and an adaptor point, look up an ECDSA adaptor
+    /// signature with that key, encrypted to the adaptor point
+    fn lookup_adaptor_sig(
+        &self,
+        _: &Pk,
+        _: &secp256k1_zkp::PublicKey,
+    ) -> Option<ElementsAdaptorSig> {
+        None
+    }
+
     /// Lookup the tap key spend sig
     fn lookup_tap_key_spend_sig(&self) -> Option<elements::SchnorrSig> {
         None
     }
 
+    /// Lookup the annex to append to the witness of a taproot spend,
+    /// including its `0x50` prefix
+    fn lookup_tap_annex(&self) -> Option<Vec<u8>> {
+        None
+    }
+
     /// Given a public key and a associated leaf hash, look up an schnorr signature with that key
     fn lookup_tap_leaf_script_sig(&self, _: &Pk, _: &TapLeafHash) -> Option<elements::SchnorrSig> {
         None
@@ -124,6 +204,30 @@ pub trait Satisfier<Pk: MiniscriptKey + ToPublicKey> {
         None
     }
 
+    /// Given a SHA256 hash, look up its preimage and check that it hashes
+    /// to the given hash.
+    fn lookup_sha256_checked(&self, h: &Pk::Sha256) -> Result<Option<Preimage32>, Error> {
+        check_preimage("sha256", Pk::to_sha256(h), self.lookup_sha256(h))
+    }
+
+    /// Given a HASH256 hash, look up its preimage and check that it hashes
+    /// to the given hash.
+    fn lookup_hash256_checked(&self, h: &Pk::Hash256) -> Result<Option<Preimage32>, Error> {
+        check_preimage("hash256", Pk::to_hash256(h), self.lookup_hash256(h))
+    }
+
+    /// Given a RIPEMD160 hash, look up its preimage and check that it hashes
+    /// to the given hash.
+    fn lookup_ripemd160_checked(&self, h: &Pk::Ripemd160) -> Result<Option<Preimage32>, Error> {
+        check_preimage("ripemd160", Pk::to_ripemd160(h), self.lookup_ripemd160(h))
+    }
+
+    /// Given a HASH160 hash, look up its preimage and check that it hashes
+    /// to the given hash.
+    fn lookup_hash160_checked(&self, h: &Pk::Hash160) -> Result<Option<Preimage32>, Error> {
+        check_preimage("hash160", Pk::to_hash160(h), self.lookup_hash160(h))
+    }
+
     /// Assert whether an relative locktime is satisfied
     fn check_older(&self, _: Sequence) -> bool {
         false
@@ -219,6 +323,24 @@ pub trait Satisfier<Pk: MiniscriptKey + ToPublicKey> {
     ) -> Option<(schnorr::Signature, i64, u64)> {
         None
     }
+
+    /// Lookup the asset id and the asset blinding factor of a confidential
+    /// asset. Required to satisfy covenants comparing blinded assets
+    fn lookup_asset_blinding(
+        &self,
+        _asset: &confidential::Asset,
+    ) -> Option<(AssetId, AssetBlindingFactor)> {
+        None
+    }
+
+    /// Lookup the amount and the value blinding factor of a confidential
+    /// value. Required to satisfy covenants comparing blinded values
+    fn lookup_value_blinding(
+        &self,
+        _value: &confidential::Value,
+    ) -> Option<(u64, ValueBlindingFactor)> {
+        None
+    }
 }
 
 // Allow use of `()` as a "no conditions available" satisfier
@@ -226,36 +348,60 @@ impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for () {}
 
 impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for Sequence {
     fn check_older(&self, n: Sequence) -> bool {
-        if !self.is_relative_lock_time() {
-            return false;
-        }
+        RelLockTime::from(n).is_satisfied_by(*self)
+    }
+}
 
-        // We need a relative lock time type in rust-bitcoin to clean this up.
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for LockTime {
+    fn check_after(&self, n: LockTime) -> bool {
+        AbsLockTime::from(n).is_satisfied_by(*self)
+    }
+}
+
+/// Satisfier answering `older` and `after` for a transaction to be included
+/// in the block after the given chain tip.
+///
+/// Combine it with other satisfiers using a tuple. The spending transaction
+/// must then use a `nLockTime` and `nSequence` at least as large as the ones
+/// required by the chosen satisfaction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AtHeightAndTime {
+    /// Height of the chain tip
+    pub height: u32,
+    /// Median time past of the chain tip
+    pub mtp: u32,
+    /// Height of the block which confirmed the spent input
+    pub confirmation_height: u32,
+    /// Median time past of the block before the one which confirmed the
+    /// spent input, as used by BIP-68 for time based relative locktimes
+    pub confirmation_mtp: u32,
+}
 
-        /* If nSequence encodes a relative lock-time, this mask is
-         * applied to extract that lock-time from the sequence field. */
-        const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
-        const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 0x00400000;
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for AtHeightAndTime {
+    fn check_older(&self, n: Sequence) -> bool {
+        const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
 
-        let mask = SEQUENCE_LOCKTIME_MASK | SEQUENCE_LOCKTIME_TYPE_FLAG;
-        let masked_n = n.to_consensus_u32() & mask;
-        let masked_seq = self.to_consensus_u32() & mask;
-        if masked_n < SEQUENCE_LOCKTIME_TYPE_FLAG && masked_seq >= SEQUENCE_LOCKTIME_TYPE_FLAG {
-            false
+        let n = RelLockTime::from(n);
+        if !n.is_relative_lock_time() {
+            return false;
+        }
+        let value = u32::from(n.value());
+        if n.is_time_locked() {
+            let elapsed = self.mtp.saturating_sub(self.confirmation_mtp);
+            elapsed >= value << SEQUENCE_LOCKTIME_GRANULARITY
         } else {
-            masked_n <= masked_seq
+            // The transaction is included at `height + 1`
+            let depth = (self.height.saturating_add(1)).saturating_sub(self.confirmation_height);
+            depth >= value
         }
     }
-}
 
-impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for LockTime {
     fn check_after(&self, n: LockTime) -> bool {
-        use LockTime::*;
-
-        match (n, *self) {
-            (Blocks(n), Blocks(lock_time)) => n <= lock_time,
-            (Seconds(n), Seconds(lock_time)) => n <= lock_time,
-            _ => false, // Not the same units.
+        match n {
+            // The transaction is included at `height + 1`, which must be
+            // strictly larger than its locktime
+            LockTime::Blocks(n) => n.to_consensus_u32() <= self.height,
+            LockTime::Seconds(n) => n.to_consensus_u32() < self.mtp,
         }
     }
 }
@@ -282,6 +428,39 @@ impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk>
     }
 }
 
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk>
+    for HashMap<(Pk, secp256k1_zkp::PublicKey), ElementsAdaptorSig>
+{
+    fn lookup_adaptor_sig(
+        &self,
+        key: &Pk,
+        adaptor_point: &secp256k1_zkp::PublicKey,
+    ) -> Option<ElementsAdaptorSig> {
+        self.get(&(key.clone(), *adaptor_point)).cloned()
+    }
+}
+
+/// Satisfier producing witnesses with pre-adapted signatures, e.g. for atomic
+/// swaps
+///
+/// Combined with other satisfiers, e.g. `(sigs, AdaptorPoint(point))`, the
+/// ECDSA signature of every key with an adaptor signature encrypted to the
+/// point, see [`Satisfier::lookup_adaptor_sig`], is replaced by the adaptor
+/// signature in the witness. Such a witness is invalid until the adaptor
+/// signatures are decrypted with the discrete logarithm of the point, and is
+/// rejected as such by the [`crate::interpreter`].
+///
+/// There is no adaptor signature for schnorr signatures, so that taproot
+/// satisfactions are unaffected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AdaptorPoint(pub secp256k1_zkp::PublicKey);
+
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for AdaptorPoint {
+    fn lookup_adaptor_point(&self) -> Option<secp256k1_zkp::PublicKey> {
+        Some(self.0)
+    }
+}
+
 impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for HashMap<hash160::Hash, (Pk, ElementsSig)>
 where
     Pk: MiniscriptKey + ToPublicKey,
@@ -331,6 +510,18 @@ impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'
         (**self).lookup_ecdsa_sig(p)
     }
 
+    fn lookup_adaptor_point(&self) -> Option<secp256k1_zkp::PublicKey> {
+        (**self).lookup_adaptor_point()
+    }
+
+    fn lookup_adaptor_sig(
+        &self,
+        p: &Pk,
+        adaptor_point: &secp256k1_zkp::PublicKey,
+    ) -> Option<ElementsAdaptorSig> {
+        (**self).lookup_adaptor_sig(p, adaptor_point)
+    }
+
     fn lookup_tap_leaf_script_sig(&self, p: &Pk, h: &TapLeafHash) -> Option<elements::SchnorrSig> {
         (**self).lookup_tap_leaf_script_sig(p, h)
     }
@@ -354,6 +545,10 @@ impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'
         (**self).lookup_tap_key_spend_sig()
     }
 
+    fn lookup_tap_annex(&self) -> Option<Vec<u8>> {
+        (**self).lookup_tap_annex()
+    }
+
     fn lookup_raw_pkh_tap_leaf_script_sig(
         &self,
         pkh: &(hash160::Hash, TapLeafHash),
@@ -458,6 +653,20 @@ impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'
     ) -> Option<(schnorr::Signature, i64, u64)> {
         (**self).lookup_price_oracle_sig(pk, time)
     }
+
+    fn lookup_asset_blinding(
+        &self,
+        asset: &confidential::Asset,
+    ) -> Option<(AssetId, AssetBlindingFactor)> {
+        (**self).lookup_asset_blinding(asset)
+    }
+
+    fn lookup_value_blinding(
+        &self,
+        value: &confidential::Value,
+    ) -> Option<(u64, ValueBlindingFactor)> {
+        (**self).lookup_value_blinding(value)
+    }
 }
 
 impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'a mut S {
@@ -465,6 +674,18 @@ impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'
         (**self).lookup_ecdsa_sig(p)
     }
 
+    fn lookup_adaptor_point(&self) -> Option<secp256k1_zkp::PublicKey> {
+        (**self).lookup_adaptor_point()
+    }
+
+    fn lookup_adaptor_sig(
+        &self,
+        p: &Pk,
+        adaptor_point: &secp256k1_zkp::PublicKey,
+    ) -> Option<ElementsAdaptorSig> {
+        (**self).lookup_adaptor_sig(p, adaptor_point)
+    }
+
     fn lookup_tap_leaf_script_sig(&self, p: &Pk, h: &TapLeafHash) -> Option<elements::SchnorrSig> {
         (**self).lookup_tap_leaf_script_sig(p, h)
     }
@@ -473,6 +694,10 @@ impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'
         (**self).lookup_tap_key_spend_sig()
     }
 
+    fn lookup_tap_annex(&self) -> Option<Vec<u8>> {
+        (**self).lookup_tap_annex()
+    }
+
     fn lookup_raw_pkh_pk(&self, pkh: &hash160::Hash) -> Option<bitcoin::PublicKey> {
         (**self).lookup_raw_pkh_pk(pkh)
     }
@@ -592,6 +817,20 @@ impl<'a, Pk: MiniscriptKey + ToPublicKey, S: Satisfier<Pk>> Satisfier<Pk> for &'
     ) -> Option<(schnorr::Signature, i64, u64)> {
         (**self).lookup_price_oracle_sig(pk, time)
     }
+
+    fn lookup_asset_blinding(
+        &self,
+        asset: &confidential::Asset,
+    ) -> Option<(AssetId, AssetBlindingFactor)> {
+        (**self).lookup_asset_blinding(asset)
+    }
+
+    fn lookup_value_blinding(
+        &self,
+        value: &confidential::Value,
+    ) -> Option<(u64, ValueBlindingFactor)> {
+        (**self).lookup_value_blinding(value)
+    }
 }
 
 macro_rules! impl_tuple_satisfier {
@@ -612,6 +851,30 @@ macro_rules! impl_tuple_satisfier {
                 None
             }
 
+            fn lookup_adaptor_point(&self) -> Option<secp256k1_zkp::PublicKey> {
+                let &($(ref $ty,)*) = self;
+                $(
+                    if let Some(result) = $ty.lookup_adaptor_point() {
+                        return Some(result);
+                    }
+                )*
+                None
+            }
+
+            fn lookup_adaptor_sig(
+                &self,
+                key: &Pk,
+                adaptor_point: &secp256k1_zkp::PublicKey,
+            ) -> Option<ElementsAdaptorSig> {
+                let &($(ref $ty,)*) = self;
+                $(
+                    if let Some(result) = $ty.lookup_adaptor_sig(key, adaptor_point) {
+                        return Some(result);
+                    }
+                )*
+                None
+            }
+
             fn lookup_tap_key_spend_sig(&self) -> Option<elements::SchnorrSig> {
                 let &($(ref $ty,)*) = self;
                 $(
@@ -622,6 +885,16 @@ macro_rules! impl_tuple_satisfier {
                 None
             }
 
+            fn lookup_tap_annex(&self) -> Option<Vec<u8>> {
+                let &($(ref $ty,)*) = self;
+                $(
+                    if let Some(result) = $ty.lookup_tap_annex() {
+                        return Some(result);
+                    }
+                )*
+                None
+            }
+
             fn lookup_tap_leaf_script_sig(&self, key: &Pk, h: &TapLeafHash) -> Option<elements::SchnorrSig> {
                 let &($(ref $ty,)*) = self;
                 $(
@@ -915,6 +1188,26 @@ macro_rules! impl_tuple_satisfier {
                 )*
                 None
             }
+
+            fn lookup_asset_blinding(&self, asset: &confidential::Asset) -> Option<(AssetId, AssetBlindingFactor)> {
+                let &($(ref $ty,)*) = self;
+                $(
+                    if let Some(result) = $ty.lookup_asset_blinding(asset) {
+                        return Some(result);
+                    }
+                )*
+                None
+            }
+
+            fn lookup_value_blinding(&self, value: &confidential::Value) -> Option<(u64, ValueBlindingFactor)> {
+                let &($(ref $ty,)*) = self;
+                $(
+                    if let Some(result) = $ty.lookup_value_blinding(value) {
+                        return Some(result);
+                    }
+                )*
+                None
+            }
         }
     }
 }
@@ -972,6 +1265,12 @@ impl Witness {
         pk: &Pk,
         leaf_hash: &TapLeafHash,
     ) -> Self {
+        // Pre-adapted signatures take the place of the signatures
+        if let (SigType::Ecdsa, Some(point)) = (Ctx::sig_type(), sat.lookup_adaptor_point()) {
+            if let Some(sig) = sat.lookup_adaptor_sig(pk, &point) {
+                return Witness::Stack(vec![adaptorsig_to_rawsig(&sig)]);
+            }
+        }
         match Ctx::sig_type() {
             super::context::SigType::Ecdsa => match sat.lookup_ecdsa_sig(pk) {
                 Some(sig) => Witness::Stack(vec![elementssig_to_rawsig(&sig)]),
@@ -1035,37 +1334,45 @@ impl Witness {
         sat: S,
         h: &Pk::Ripemd160,
     ) -> Self {
-        match sat.lookup_ripemd160(h) {
-            Some(pre) => Witness::Stack(vec![pre.to_vec()]),
+        match sat.lookup_ripemd160_checked(h) {
+            Ok(Some(pre)) => Witness::Stack(vec![pre.to_vec()]),
             // Note hash preimages are unavailable instead of impossible
-            None => Witness::Unavailable,
+            Ok(None) => Witness::Unavailable,
+            // Never produce a witness with a wrong preimage
+            Err(_) => Witness::Impossible,
         }
     }
 
     /// Turn a hash preimage into (part of) a satisfaction
     pub fn hash160_preimage<Pk: ToPublicKey, S: Satisfier<Pk>>(sat: S, h: &Pk::Hash160) -> Self {
-        match sat.lookup_hash160(h) {
-            Some(pre) => Witness::Stack(vec![pre.to_vec()]),
+        match sat.lookup_hash160_checked(h) {
+            Ok(Some(pre)) => Witness::Stack(vec![pre.to_vec()]),
             // Note hash preimages are unavailable instead of impossible
-            None => Witness::Unavailable,
+            Ok(None) => Witness::Unavailable,
+            // Never produce a witness with a wrong preimage
+            Err(_) => Witness::Impossible,
         }
     }
 
     /// Turn a hash preimage into (part of) a satisfaction
     pub fn sha256_preimage<Pk: ToPublicKey, S: Satisfier<Pk>>(sat: S, h: &Pk::Sha256) -> Self {
-        match sat.lookup_sha256(h) {
-            Some(pre) => Witness::Stack(vec![pre.to_vec()]),
+        match sat.lookup_sha256_checked(h) {
+            Ok(Some(pre)) => Witness::Stack(vec![pre.to_vec()]),
             // Note hash preimages are unavailable instead of impossible
-            None => Witness::Unavailable,
+            Ok(None) => Witness::Unavailable,
+            // Never produce a witness with a wrong preimage
+            Err(_) => Witness::Impossible,
         }
     }
 
     /// Turn a hash preimage into (part of) a satisfaction
     pub fn hash256_preimage<Pk: ToPublicKey, S: Satisfier<Pk>>(sat: S, h: &Pk::Hash256) -> Self {
-        match sat.lookup_hash256(h) {
-            Some(pre) => Witness::Stack(vec![pre.to_vec()]),
+        match sat.lookup_hash256_checked(h) {
+            Ok(Some(pre)) => Witness::Stack(vec![pre.to_vec()]),
             // Note hash preimages are unavailable instead of impossible
-            None => Witness::Unavailable,
+            Ok(None) => Witness::Unavailable,
+            // Never produce a witness with a wrong preimage
+            Err(_) => Witness::Impossible,
         }
     }
 }
@@ -1447,7 +1754,7 @@ impl Satisfaction {
                 has_sig: false,
             },
             Terminal::Older(t) => Satisfaction {
-                stack: if stfr.check_older(t) {
+                stack: if stfr.check_older(t.into()) {
                     Witness::empty()
                 } else if root_has_sig {
                     // If the root terminal has signature, the
@@ -1621,14 +1928,20 @@ impl Satisfaction {
                     },
                 )
             }
-            Terminal::Thresh(k, ref subs) => {
-                thresh_fn(k, subs, stfr, root_has_sig, leaf_hash, min_fn)
-            }
-            Terminal::Multi(k, ref keys) => {
+            Terminal::Thresh(ref thresh) => thresh_fn(
+                thresh.k(),
+                thresh.data(),
+                stfr,
+                root_has_sig,
+                leaf_hash,
+                min_fn,
+            ),
+            Terminal::Multi(ref thresh) => {
+                let k = thresh.k();
                 // Collect all available signatures
                 let mut sig_count = 0;
                 let mut sigs = Vec::with_capacity(k);
-                for pk in keys {
+                for pk in thresh {
                     match Witness::signature::<_, _, Ctx>(stfr, pk, leaf_hash) {
                         Witness::Stack(sig) => {
                             sigs.push(sig);
@@ -1666,11 +1979,12 @@ impl Satisfaction {
                     }
                 }
             }
-            Terminal::MultiA(k, ref keys) => {
+            Terminal::MultiA(ref thresh) => {
+                let k = thresh.k();
                 // Collect all available signatures
                 let mut sig_count = 0;
-                let mut sigs = vec![vec![vec![]]; keys.len()];
-                for (i, pk) in keys.iter().rev().enumerate() {
+                let mut sigs = vec![vec![vec![]]; thresh.n()];
+                for (i, pk) in thresh.iter().rev().enumerate() {
                     match Witness::signature::<_, _, Ctx>(stfr, pk, leaf_hash) {
                         Witness::Stack(sig) => {
                             sigs[i] = sig;
@@ -1866,8 +2180,8 @@ impl Satisfaction {
                 // Dissatisfactions don't need to non-malleable. Use minimum_mall always
                 Satisfaction::minimum_mall(dissat_1, dissat_2)
             }
-            Terminal::Thresh(_, ref subs) => Satisfaction {
-                stack: subs.iter().fold(Witness::empty(), |acc, sub| {
+            Terminal::Thresh(ref thresh) => Satisfaction {
+                stack: thresh.iter().fold(Witness::empty(), |acc, sub| {
                     let nsat = Self::dissatisfy_helper(
                         &sub.node,
                         stfr,
@@ -1881,12 +2195,12 @@ impl Satisfaction {
                 }),
                 has_sig: false,
             },
-            Terminal::Multi(k, _) => Satisfaction {
-                stack: Witness::Stack(vec![vec![]; k + 1]),
+            Terminal::Multi(ref thresh) => Satisfaction {
+                stack: Witness::Stack(vec![vec![]; thresh.k() + 1]),
                 has_sig: false,
             },
-            Terminal::MultiA(_, ref pks) => Satisfaction {
-                stack: Witness::Stack(vec![vec![]; pks.len()]),
+            Terminal::MultiA(ref thresh) => Satisfaction {
+                stack: Witness::Stack(vec![vec![]; thresh.n()]),
                 has_sig: false,
             },
             Terminal::Ext(ref e) => e.dissatisfy(stfr),
@@ -1938,3 +2252,285 @@ impl Satisfaction {
         )
     }
 }
+
+/// A hash preimage which a satisfier does not have
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MissingPreimage {
+    /// Preimage of a `sha256` fragment
+    Sha256(sha256::Hash),
+    /// Preimage of a `hash256` fragment
+    Hash256(hash256::Hash),
+    /// Preimage of a `ripemd160` fragment
+    Ripemd160(ripemd160::Hash),
+    /// Preimage of a `hash160` fragment
+    Hash160(hash160::Hash),
+}
+
+impl fmt::Display for MissingPreimage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MissingPreimage::Sha256(ref h) => write!(f, "sha256({})", h),
+            MissingPreimage::Hash256(ref h) => write!(f, "hash256({})", h),
+            MissingPreimage::Ripemd160(ref h) => write!(f, "ripemd160({})", h),
+            MissingPreimage::Hash160(ref h) => write!(f, "hash160({})", h),
+        }
+    }
+}
+
+/// A timelock which a satisfier does not meet
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MissingTimelock {
+    /// Relative timelock of an `older` fragment
+    Older(RelLockTime),
+    /// Absolute timelock of an `after` fragment
+    After(AbsLockTime),
+}
+
+impl fmt::Display for MissingTimelock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MissingTimelock::Older(n) => write!(f, "older({})", n.to_consensus_u32()),
+            MissingTimelock::After(n) => write!(f, "after({})", n.to_consensus_u32()),
+        }
+    }
+}
+
+/// The items a satisfier lacks to satisfy a Miniscript, along the spending
+/// path needing the fewest of them. Returned in [`Error::MissingItems`] when
+/// a satisfaction fails, so that they can be requested from the other
+/// parties before trying again.
+///
+/// Dissatisfactions are assumed to be available, which holds for the
+/// standard fragments. Extensions are only considered if they can be
+/// satisfied already.
+///
+/// The keys are the ones of the Miniscript, [`Error::MissingItems`] holds
+/// their [`bitcoin::PublicKey`]s, see [`Missing::into_public_keys`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Missing<Pk: MiniscriptKey = bitcoin::PublicKey> {
+    /// Keys whose signatures are missing
+    pub sigs: Vec<Pk>,
+    /// Keys of the `expr_raw_pkh` fragments whose signatures are missing, as
+    /// found by [`Satisfier::lookup_raw_pkh_pk`]
+    pub raw_pkh_sigs: Vec<bitcoin::PublicKey>,
+    /// Missing hash preimages
+    pub preimages: Vec<MissingPreimage>,
+    /// Timelocks which are not met
+    pub timelocks: Vec<MissingTimelock>,
+}
+
+impl<Pk: MiniscriptKey> Default for Missing<Pk> {
+    fn default() -> Self {
+        Missing {
+            sigs: vec![],
+            raw_pkh_sigs: vec![],
+            preimages: vec![],
+            timelocks: vec![],
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey> fmt::Display for Missing<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = self
+            .sigs
+            .iter()
+            .map(|pk| format!("sig({})", pk))
+            .chain(self.raw_pkh_sigs.iter().map(|pk| format!("sig({})", pk)))
+            .chain(self.preimages.iter().map(|p| p.to_string()))
+            .chain(self.timelocks.iter().map(|t| t.to_string()))
+            .collect::<Vec<_>>();
+        f.write_str(&items.join(", "))
+    }
+}
+
+impl<Pk: MiniscriptKey> Missing<Pk> {
+    /// The number of missing items
+    pub fn len(&self) -> usize {
+        self.sigs.len() + self.raw_pkh_sigs.len() + self.preimages.len() + self.timelocks.len()
+    }
+
+    /// Whether nothing is missing
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Adds the items of `other` which are not already missing
+    fn merge(mut self, other: Self) -> Self {
+        for pk in other.sigs {
+            if !self.sigs.contains(&pk) {
+                self.sigs.push(pk);
+            }
+        }
+        for pk in other.raw_pkh_sigs {
+            if !self.raw_pkh_sigs.contains(&pk) {
+                self.raw_pkh_sigs.push(pk);
+            }
+        }
+        for preimage in other.preimages {
+            if !self.preimages.contains(&preimage) {
+                self.preimages.push(preimage);
+            }
+        }
+        for timelock in other.timelocks {
+            if !self.timelocks.contains(&timelock) {
+                self.timelocks.push(timelock);
+            }
+        }
+        self
+    }
+
+    // Both paths are needed
+    fn and(one: Option<Self>, two: Option<Self>) -> Option<Self> {
+        Some(one?.merge(two?))
+    }
+
+    // Either path is enough, the one missing fewer items is kept
+    fn or(one: Option<Self>, two: Option<Self>) -> Option<Self> {
+        match (one, two) {
+            (Some(one), Some(two)) if two.len() < one.len() => Some(two),
+            (Some(one), _) => Some(one),
+            (None, two) => two,
+        }
+    }
+
+    /// Computes the items `stfr` lacks to satisfy `term`, or `None` if
+    /// `term` cannot be satisfied whatever items are added.
+    pub(super) fn of_terminal<Ctx, Sat, Ext>(
+        term: &Terminal<Pk, Ctx, Ext>,
+        stfr: &Sat,
+        leaf_hash: &TapLeafHash,
+    ) -> Option<Self>
+    where
+        Pk: ToPublicKey,
+        Ctx: ScriptContext,
+        Sat: Satisfier<Pk>,
+        Ext: ParseableExt,
+    {
+        let sig = |pk: &Pk| match Witness::signature::<_, _, Ctx>(stfr, pk, leaf_hash) {
+            Witness::Stack(_) => None,
+            _ => Some(pk.clone()),
+        };
+        let preimage = |wit: Witness, missing: MissingPreimage| match wit {
+            Witness::Stack(_) => Some(Missing::default()),
+            Witness::Unavailable => Some(Missing {
+                preimages: vec![missing],
+                ..Default::default()
+            }),
+            // A wrong preimage was provided
+            Witness::Impossible => None,
+        };
+        let timelock = |met: bool, missing: MissingTimelock| Missing {
+            timelocks: if met { vec![] } else { vec![missing] },
+            ..Default::default()
+        };
+
+        match *term {
+            Terminal::True => Some(Missing::default()),
+            Terminal::False => None,
+            Terminal::PkK(ref pk) | Terminal::PkH(ref pk) => Some(Missing {
+                sigs: sig(pk).into_iter().collect(),
+                ..Default::default()
+            }),
+            Terminal::RawPkH(ref pkh) => {
+                match Witness::pkh_signature::<_, _, Ctx>(stfr, pkh, leaf_hash) {
+                    Witness::Stack(_) => Some(Missing::default()),
+                    _ => stfr.lookup_raw_pkh_pk(pkh).map(|pk| Missing {
+                        raw_pkh_sigs: vec![pk],
+                        ..Default::default()
+                    }),
+                }
+            }
+            Terminal::After(t) => Some(timelock(
+                stfr.check_after(t.into()),
+                MissingTimelock::After(t),
+            )),
+            Terminal::Older(t) => Some(timelock(
+                stfr.check_older(t.into()),
+                MissingTimelock::Older(t),
+            )),
+            Terminal::Sha256(ref h) => preimage(
+                Witness::sha256_preimage(stfr, h),
+                MissingPreimage::Sha256(Pk::to_sha256(h)),
+            ),
+            Terminal::Hash256(ref h) => preimage(
+                Witness::hash256_preimage(stfr, h),
+                MissingPreimage::Hash256(Pk::to_hash256(h)),
+            ),
+            Terminal::Ripemd160(ref h) => preimage(
+                Witness::ripemd160_preimage(stfr, h),
+                MissingPreimage::Ripemd160(Pk::to_ripemd160(h)),
+            ),
+            Terminal::Hash160(ref h) => preimage(
+                Witness::hash160_preimage(stfr, h),
+                MissingPreimage::Hash160(Pk::to_hash160(h)),
+            ),
+            Terminal::Alt(ref sub)
+            | Terminal::Swap(ref sub)
+            | Terminal::Check(ref sub)
+            | Terminal::DupIf(ref sub)
+            | Terminal::Verify(ref sub)
+            | Terminal::NonZero(ref sub)
+            | Terminal::ZeroNotEqual(ref sub) => Self::of_terminal(&sub.node, stfr, leaf_hash),
+            Terminal::AndV(ref l, ref r) | Terminal::AndB(ref l, ref r) => Self::and(
+                Self::of_terminal(&l.node, stfr, leaf_hash),
+                Self::of_terminal(&r.node, stfr, leaf_hash),
+            ),
+            Terminal::AndOr(ref a, ref b, ref c) => Self::or(
+                Self::and(
+                    Self::of_terminal(&a.node, stfr, leaf_hash),
+                    Self::of_terminal(&b.node, stfr, leaf_hash),
+                ),
+                Self::of_terminal(&c.node, stfr, leaf_hash),
+            ),
+            Terminal::OrB(ref l, ref r)
+            | Terminal::OrD(ref l, ref r)
+            | Terminal::OrC(ref l, ref r)
+            | Terminal::OrI(ref l, ref r) => Self::or(
+                Self::of_terminal(&l.node, stfr, leaf_hash),
+                Self::of_terminal(&r.node, stfr, leaf_hash),
+            ),
+            Terminal::Thresh(ref thresh) => {
+                let mut subs = thresh
+                    .iter()
+                    .filter_map(|sub| Self::of_terminal(&sub.node, stfr, leaf_hash))
+                    .collect::<Vec<_>>();
+                if subs.len() < thresh.k() {
+                    return None;
+                }
+                subs.sort_by_key(Missing::len);
+                Some(
+                    subs.into_iter()
+                        .take(thresh.k())
+                        .fold(Missing::default(), Missing::merge),
+                )
+            }
+            Terminal::Multi(ref thresh) | Terminal::MultiA(ref thresh) => {
+                let missing = thresh.iter().filter_map(sig).collect::<Vec<_>>();
+                let needed = (thresh.k() + missing.len()).saturating_sub(thresh.n());
+                Some(Missing {
+                    sigs: missing.into_iter().take(needed).collect(),
+                    ..Default::default()
+                })
+            }
+            Terminal::Ext(ref e) => match e.satisfy(stfr).stack {
+                Witness::Stack(_) => Some(Missing::default()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Replaces the keys of the missing signatures by their
+    /// [`bitcoin::PublicKey`]s
+    pub fn into_public_keys(self) -> Missing<bitcoin::PublicKey>
+    where
+        Pk: ToPublicKey,
+    {
+        Missing {
+            sigs: self.sigs.iter().map(ToPublicKey::to_public_key).collect(),
+            raw_pkh_sigs: self.raw_pkh_sigs,
+            preimages: self.preimages,
+            timelocks: self.timelocks,
+        }
+    }
+}
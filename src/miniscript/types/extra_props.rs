@@ -915,25 +915,8 @@ impl Property for ExtData {
             Terminal::False => Ok(Self::from_false()),
             Terminal::PkK(..) => Ok(Self::from_pk_k::<Ctx>()),
             Terminal::PkH(..) | Terminal::RawPkH(..) => Ok(Self::from_pk_h::<Ctx>()),
-            Terminal::Multi(k, ref pks) | Terminal::MultiA(k, ref pks) => {
-                if k == 0 {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::ZeroThreshold,
-                    });
-                }
-                if k > pks.len() {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::OverThreshold(k, pks.len()),
-                    });
-                }
-                match *fragment {
-                    Terminal::Multi(..) => Ok(Self::from_multi(k, pks.len())),
-                    Terminal::MultiA(..) => Ok(Self::from_multi_a(k, pks.len())),
-                    _ => unreachable!(),
-                }
-            }
+            Terminal::Multi(ref thresh) => Ok(Self::from_multi(thresh.k(), thresh.n())),
+            Terminal::MultiA(ref thresh) => Ok(Self::from_multi_a(thresh.k(), thresh.n())),
             Terminal::After(t) => {
                 // Note that for CLTV this is a limitation not of Bitcoin but Miniscript. The
                 // number on the stack would be a 5 bytes signed integer but Miniscript's B type
@@ -947,13 +930,13 @@ impl Property for ExtData {
                 Ok(Self::from_after(t.into()))
             }
             Terminal::Older(t) => {
-                if t == Sequence::ZERO || !t.is_relative_lock_time() {
+                if t.to_consensus_u32() == 0 || !t.is_relative_lock_time() {
                     return Err(Error {
                         fragment: fragment.clone(),
                         error: ErrorKind::InvalidTime,
                     });
                 }
-                Ok(Self::from_older(t))
+                Ok(Self::from_older(t.into()))
             }
             Terminal::Sha256(..) => Ok(Self::from_sha256()),
             Terminal::Hash256(..) => Ok(Self::from_hash256()),
@@ -1002,21 +985,10 @@ impl Property for ExtData {
                 let ctype = c.ext;
                 wrap_err(Self::and_or(atype, btype, ctype))
             }
-            Terminal::Thresh(k, ref subs) => {
-                if k == 0 {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::ZeroThreshold,
-                    });
-                }
-                if k > subs.len() {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::OverThreshold(k, subs.len()),
-                    });
-                }
+            Terminal::Thresh(ref thresh) => {
+                let subs = thresh.data();
 
-                let res = Self::threshold(k, subs.len(), |n| Ok(subs[n].ext));
+                let res = Self::threshold(thresh.k(), thresh.n(), |n| Ok(subs[n].ext));
 
                 res.map_err(|kind| Error {
                     fragment: fragment.clone(),
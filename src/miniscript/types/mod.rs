@@ -17,7 +17,7 @@ pub use self::correctness::{Base, Correctness, Input};
 pub use self::extra_props::ExtData;
 pub use self::malleability::{Dissat, Malleability};
 use super::ScriptContext;
-use crate::{Extension, MiniscriptKey, NoExt, Terminal};
+use crate::{Extension, Miniscript, MiniscriptKey, NoExt, Terminal};
 
 /// None-returning function to help type inference when we need a
 /// closure that simply returns `None`
@@ -32,11 +32,6 @@ pub enum ErrorKind {
     InvalidTime,
     /// Passed a `z` argument to a `d` wrapper when `z` was expected
     NonZeroDupIf,
-    /// Multisignature or threshold policy had a `k` value of 0
-    ZeroThreshold,
-    /// Multisignature or threshold policy has a `k` value in
-    /// excess of the number of subfragments
-    OverThreshold(usize, usize),
     /// Attempted to construct a disjunction (or `andor`) for which
     /// none of the child nodes were strong. This means that a 3rd
     /// party could produce a satisfaction for any branch, meaning
@@ -94,6 +89,72 @@ pub struct Error<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension = NoExt>
     pub error: ErrorKind,
 }
 
+impl<Pk, Ctx, Ext> Error<Pk, Ctx, Ext>
+where
+    Pk: MiniscriptKey,
+    Ctx: ScriptContext,
+    Ext: Extension,
+{
+    /// The child of the fragment which violates the typing rule, along with
+    /// its position and the type the fragment requires it to have, in the
+    /// notation of the miniscript spec. Returns `None` if the error is not
+    /// caused by a single child.
+    pub fn offending_child(&self) -> Option<(usize, &Miniscript<Pk, Ctx, Ext>, &'static str)> {
+        let (idx, req) = match (self.error, &self.fragment) {
+            (ErrorKind::NonZeroDupIf, _) => (0, "Vz"),
+            (ErrorKind::SwapNonOne, _) => (0, "Bo"),
+            (ErrorKind::NonZeroZero, _) => (0, "Bn"),
+            (ErrorKind::LeftNotUnit, _) => (0, "u"),
+            (ErrorKind::LeftNotDissatisfiable, _) => (0, "d"),
+            (ErrorKind::RightNotDissatisfiable, _) => (1, "d"),
+            (ErrorKind::ChildBase1(_), Terminal::Swap(_)) => (0, "Bo"),
+            (ErrorKind::ChildBase1(_), Terminal::Check(_)) => (0, "K"),
+            (ErrorKind::ChildBase1(_), Terminal::DupIf(_)) => (0, "Vz"),
+            (ErrorKind::ChildBase1(_), Terminal::NonZero(_)) => (0, "Bn"),
+            (ErrorKind::ChildBase1(_), _) => (0, "B"),
+            (ErrorKind::ChildBase2(Base::V, _), Terminal::AndV(..)) => (1, "B, K or V"),
+            (ErrorKind::ChildBase2(_, _), Terminal::AndV(..)) => (0, "V"),
+            (ErrorKind::ChildBase2(Base::B, _), Terminal::AndB(..))
+            | (ErrorKind::ChildBase2(Base::B, _), Terminal::OrB(..)) => (1, "W"),
+            (ErrorKind::ChildBase2(Base::B, _), Terminal::OrC(..)) => (1, "V"),
+            (ErrorKind::ChildBase2(Base::B, _), Terminal::OrD(..)) => (1, "B"),
+            (ErrorKind::ChildBase2(_, _), Terminal::AndB(..))
+            | (ErrorKind::ChildBase2(_, _), Terminal::OrB(..))
+            | (ErrorKind::ChildBase2(_, _), Terminal::OrC(..))
+            | (ErrorKind::ChildBase2(_, _), Terminal::OrD(..)) => (0, "B"),
+            (ErrorKind::ChildBase3(Base::B, _, _), _) => return None,
+            (ErrorKind::ChildBase3(_, _, _), _) => (0, "B"),
+            (ErrorKind::ThresholdBase(idx, _), _) => (idx, if idx == 0 { "B" } else { "W" }),
+            (ErrorKind::ThresholdDissat(idx), _) => (idx, "d"),
+            (ErrorKind::ThresholdNonUnit(idx), _) => (idx, "u"),
+            _ => return None,
+        };
+        let child = match (idx, &self.fragment) {
+            (0, Terminal::Alt(sub))
+            | (0, Terminal::Swap(sub))
+            | (0, Terminal::Check(sub))
+            | (0, Terminal::DupIf(sub))
+            | (0, Terminal::Verify(sub))
+            | (0, Terminal::NonZero(sub))
+            | (0, Terminal::ZeroNotEqual(sub))
+            | (0, Terminal::AndV(sub, _))
+            | (0, Terminal::AndB(sub, _))
+            | (0, Terminal::OrB(sub, _))
+            | (0, Terminal::OrD(sub, _))
+            | (0, Terminal::OrC(sub, _))
+            | (0, Terminal::AndOr(sub, _, _))
+            | (1, Terminal::AndV(_, sub))
+            | (1, Terminal::AndB(_, sub))
+            | (1, Terminal::OrB(_, sub))
+            | (1, Terminal::OrD(_, sub))
+            | (1, Terminal::OrC(_, sub)) => sub,
+            (idx, Terminal::Thresh(thresh)) => thresh.data().get(idx)?,
+            _ => return None,
+        };
+        Some((idx, &**child, req))
+    }
+}
+
 impl<Pk, Ctx, Ext> fmt::Display for Error<Pk, Ctx, Ext>
 where
     Pk: MiniscriptKey,
@@ -112,17 +173,6 @@ where
                 "fragment «{}» represents needs to be `z`, needs to consume zero elements from the stack",
                 self.fragment,
             ),
-            ErrorKind::ZeroThreshold => write!(
-                f,
-                "fragment «{}» has a threshold value of 0",
-                self.fragment,
-            ),
-            ErrorKind::OverThreshold(k, n) => write!(
-                f,
-                "fragment «{}» is a {}-of-{} threshold, which does not
-                 make sense",
-                self.fragment, k, n,
-            ),
             ErrorKind::NoStrongChild => write!(
                 f,
                 "fragment «{}» requires at least one strong child \
@@ -233,6 +283,46 @@ impl Type {
         self.corr.is_subtype(other.corr) && self.mall.is_subtype(other.mall)
     }
 }
+
+/// Writes the type in the notation of the miniscript spec, e.g. `B/onduesm`:
+/// the base type, followed by the properties `z`, `o`, `n`, `d` and `u` of
+/// the correctness and `f`, `e`, `s` and `m` of the malleability.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.corr.base {
+            Base::B => "B",
+            Base::K => "K",
+            Base::V => "V",
+            Base::W => "W",
+        })?;
+        f.write_str("/")?;
+        f.write_str(match self.corr.input {
+            Input::Zero => "z",
+            Input::One => "o",
+            Input::OneNonZero => "on",
+            Input::Any => "",
+            Input::AnyNonZero => "n",
+        })?;
+        if self.corr.dissatisfiable {
+            f.write_str("d")?;
+        }
+        if self.corr.unit {
+            f.write_str("u")?;
+        }
+        f.write_str(match self.mall.dissat {
+            Dissat::None => "f",
+            Dissat::Unique => "e",
+            Dissat::Unknown => "",
+        })?;
+        if self.mall.safe {
+            f.write_str("s")?;
+        }
+        if self.mall.non_malleable {
+            f.write_str("m")?;
+        }
+        Ok(())
+    }
+}
 /// Trait representing a type property, which defines how the property
 /// propagates from terminals to the root of a Miniscript
 pub trait Property: Sized {
@@ -409,25 +499,8 @@ pub trait Property: Sized {
             Terminal::False => Ok(Self::from_false()),
             Terminal::PkK(..) => Ok(Self::from_pk_k::<Ctx>()),
             Terminal::PkH(..) | Terminal::RawPkH(..) => Ok(Self::from_pk_h::<Ctx>()),
-            Terminal::Multi(k, ref pks) | Terminal::MultiA(k, ref pks) => {
-                if k == 0 {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::ZeroThreshold,
-                    });
-                }
-                if k > pks.len() {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::OverThreshold(k, pks.len()),
-                    });
-                }
-                match *fragment {
-                    Terminal::Multi(..) => Ok(Self::from_multi(k, pks.len())),
-                    Terminal::MultiA(..) => Ok(Self::from_multi_a(k, pks.len())),
-                    _ => unreachable!(),
-                }
-            }
+            Terminal::Multi(ref thresh) => Ok(Self::from_multi(thresh.k(), thresh.n())),
+            Terminal::MultiA(ref thresh) => Ok(Self::from_multi_a(thresh.k(), thresh.n())),
             Terminal::After(t) => {
                 // Note that for CLTV this is a limitation not of Bitcoin but Miniscript. The
                 // number on the stack would be a 5 bytes signed integer but Miniscript's B type
@@ -441,13 +514,13 @@ pub trait Property: Sized {
                 Ok(Self::from_after(t.into()))
             }
             Terminal::Older(t) => {
-                if t == Sequence::ZERO || !t.is_relative_lock_time() {
+                if t.to_consensus_u32() == 0 || !t.is_relative_lock_time() {
                     return Err(Error {
                         fragment: fragment.clone(),
                         error: ErrorKind::InvalidTime,
                     });
                 }
-                Ok(Self::from_older(t))
+                Ok(Self::from_older(t.into()))
             }
             Terminal::Sha256(..) => Ok(Self::from_sha256()),
             Terminal::Hash256(..) => Ok(Self::from_hash256()),
@@ -498,26 +571,17 @@ pub trait Property: Sized {
                 let ctype = get_child(&c.node, 2)?;
                 wrap_err(Self::and_or(atype, btype, ctype))
             }
-            Terminal::Thresh(k, ref subs) => {
-                if k == 0 {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::ZeroThreshold,
-                    });
-                }
-                if k > subs.len() {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::OverThreshold(k, subs.len()),
-                    });
-                }
+            Terminal::Thresh(ref thresh) => {
+                let subs = thresh.data();
 
                 let mut last_err_frag = None;
-                let res = Self::threshold(k, subs.len(), |n| match get_child(&subs[n].node, n) {
-                    Ok(x) => Ok(x),
-                    Err(e) => {
-                        last_err_frag = Some(e.fragment);
-                        Err(e.error)
+                let res = Self::threshold(thresh.k(), thresh.n(), |n| {
+                    match get_child(&subs[n].node, n) {
+                        Ok(x) => Ok(x),
+                        Err(e) => {
+                            last_err_frag = Some(e.fragment);
+                            Err(e.error)
+                        }
                     }
                 });
 
@@ -808,25 +872,8 @@ impl Property for Type {
             Terminal::False => Ok(Self::from_false()),
             Terminal::PkK(..) => Ok(Self::from_pk_k::<Ctx>()),
             Terminal::PkH(..) | Terminal::RawPkH(..) => Ok(Self::from_pk_h::<Ctx>()),
-            Terminal::Multi(k, ref pks) | Terminal::MultiA(k, ref pks) => {
-                if k == 0 {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::ZeroThreshold,
-                    });
-                }
-                if k > pks.len() {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::OverThreshold(k, pks.len()),
-                    });
-                }
-                match *fragment {
-                    Terminal::Multi(..) => Ok(Self::from_multi(k, pks.len())),
-                    Terminal::MultiA(..) => Ok(Self::from_multi_a(k, pks.len())),
-                    _ => unreachable!(),
-                }
-            }
+            Terminal::Multi(ref thresh) => Ok(Self::from_multi(thresh.k(), thresh.n())),
+            Terminal::MultiA(ref thresh) => Ok(Self::from_multi_a(thresh.k(), thresh.n())),
             Terminal::After(t) => {
                 // Note that for CLTV this is a limitation not of Bitcoin but Miniscript. The
                 // number on the stack would be a 5 bytes signed integer but Miniscript's B type
@@ -840,13 +887,13 @@ impl Property for Type {
                 Ok(Self::from_after(t.into()))
             }
             Terminal::Older(t) => {
-                if t == Sequence::ZERO || !t.is_relative_lock_time() {
+                if t.to_consensus_u32() == 0 || !t.is_relative_lock_time() {
                     return Err(Error {
                         fragment: fragment.clone(),
                         error: ErrorKind::InvalidTime,
                     });
                 }
-                Ok(Self::from_older(t))
+                Ok(Self::from_older(t.into()))
             }
             Terminal::Sha256(..) => Ok(Self::from_sha256()),
             Terminal::Hash256(..) => Ok(Self::from_hash256()),
@@ -895,21 +942,10 @@ impl Property for Type {
                 let ctype = c.ty;
                 wrap_err(Self::and_or(atype, btype, ctype))
             }
-            Terminal::Thresh(k, ref subs) => {
-                if k == 0 {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::ZeroThreshold,
-                    });
-                }
-                if k > subs.len() {
-                    return Err(Error {
-                        fragment: fragment.clone(),
-                        error: ErrorKind::OverThreshold(k, subs.len()),
-                    });
-                }
+            Terminal::Thresh(ref thresh) => {
+                let subs = thresh.data();
 
-                let res = Self::threshold(k, subs.len(), |n| Ok(subs[n].ty));
+                let res = Self::threshold(thresh.k(), thresh.n(), |n| Ok(subs[n].ty));
 
                 res.map_err(|kind| Error {
                     fragment: fragment.clone(),
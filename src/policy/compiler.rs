@@ -18,7 +18,7 @@ use crate::miniscript::limits::MAX_PUBKEYS_PER_MULTISIG;
 use crate::miniscript::types::{self, ErrorKind, ExtData, Property, Type};
 use crate::miniscript::ScriptContext;
 use crate::policy::Concrete;
-use crate::{policy, Extension, Miniscript, MiniscriptKey, Terminal};
+use crate::{policy, Extension, Miniscript, MiniscriptKey, Terminal, Threshold};
 
 type PolicyCache<Pk, Ctx> =
     BTreeMap<(Concrete<Pk>, OrdF64, Option<OrdF64>), BTreeMap<CompilationKey, AstElemExt<Pk, Ctx>>>;
@@ -969,8 +969,8 @@ where
             compile_binary!(&mut l_comp[3], &mut r_comp[2], [lw, rw], Terminal::OrI);
             compile_binary!(&mut r_comp[3], &mut l_comp[2], [rw, lw], Terminal::OrI);
         }
-        Concrete::Threshold(k, ref subs) => {
-            let n = subs.len();
+        Concrete::Threshold(ref thresh) => {
+            let (k, n, subs) = (thresh.k(), thresh.n(), thresh.data());
             let k_over_n = k as f64 / n as f64;
 
             let mut sub_ast = Vec::with_capacity(n);
@@ -1005,7 +1005,8 @@ where
                 }
             }
 
-            let ast = Terminal::Thresh(k, sub_ast);
+            let thresh = Threshold::new(k, sub_ast).expect("policy thresholds are valid");
+            let ast = Terminal::Thresh(thresh);
             let ast_ext = AstElemExt {
                 ms: Arc::new(
                     Miniscript::from_ast(ast)
@@ -1029,14 +1030,16 @@ where
 
             match Ctx::sig_type() {
                 SigType::Schnorr if key_vec.len() == subs.len() => {
-                    insert_wrap!(AstElemExt::terminal(Terminal::MultiA(k, key_vec)))
+                    let thresh = Threshold::new(k, key_vec).expect("policy thresholds are valid");
+                    insert_wrap!(AstElemExt::terminal(Terminal::MultiA(thresh)))
                 }
                 SigType::Ecdsa
                     if key_vec.len() == subs.len() && subs.len() <= MAX_PUBKEYS_PER_MULTISIG =>
                 {
-                    insert_wrap!(AstElemExt::terminal(Terminal::Multi(k, key_vec)))
+                    let thresh = Threshold::new(k, key_vec).expect("policy thresholds are valid");
+                    insert_wrap!(AstElemExt::terminal(Terminal::Multi(thresh)))
                 }
-                _ if k == subs.len() => {
+                _ if thresh.is_and() => {
                     let mut it = subs.iter();
                     let mut policy = it.next().expect("No sub policy in thresh() ?").clone();
                     policy = it.fold(policy, |acc, pol| Concrete::And(vec![acc, pol.clone()]));
@@ -1208,7 +1211,7 @@ mod tests {
     use super::*;
     use crate::miniscript::{Legacy, Segwitv0, Tap};
     use crate::policy::Liftable;
-    use crate::{script_num_size, ElementsSig, ToPublicKey};
+    use crate::{script_num_size, ElementsSig, RelLockTime, ToPublicKey};
 
     type SPolicy = Concrete<String>;
     type BPolicy = Concrete<bitcoin::PublicKey>;
@@ -1362,12 +1365,15 @@ mod tests {
 
         // Liquid policy
         let policy: BPolicy = Concrete::Or(vec![
-            (127, Concrete::Threshold(3, key_pol[0..5].to_owned())),
+            (
+                127,
+                Concrete::Threshold(Threshold::new(3, key_pol[0..5].to_owned()).unwrap()),
+            ),
             (
                 1,
                 Concrete::And(vec![
-                    Concrete::Older(Sequence::from_height(10000)),
-                    Concrete::Threshold(2, key_pol[5..8].to_owned()),
+                    Concrete::Older(RelLockTime::from_height(10000)),
+                    Concrete::Threshold(Threshold::new(2, key_pol[5..8].to_owned()).unwrap()),
                 ]),
             ),
         ]);
@@ -1485,7 +1491,7 @@ mod tests {
         for k in &[10, 15, 21] {
             let pubkeys: Vec<Concrete<bitcoin::PublicKey>> =
                 keys.iter().map(|pubkey| Concrete::Key(*pubkey)).collect();
-            let big_thresh = Concrete::Threshold(*k, pubkeys);
+            let big_thresh = Concrete::Threshold(Threshold::new(*k, pubkeys).unwrap());
             let big_thresh_ms: SegwitMiniScript = big_thresh.compile().unwrap();
             if *k == 21 {
                 // N * (PUSH + pubkey + CHECKSIGVERIFY)
@@ -1521,8 +1527,8 @@ mod tests {
             .collect();
 
         let thresh_res: Result<SegwitMiniScript, _> = Concrete::Or(vec![
-            (1, Concrete::Threshold(keys_a.len(), keys_a)),
-            (1, Concrete::Threshold(keys_b.len(), keys_b)),
+            (1, Concrete::Threshold(Threshold::and(keys_a).unwrap())),
+            (1, Concrete::Threshold(Threshold::and(keys_b).unwrap())),
         ])
         .compile();
         let script_size = thresh_res.clone().map(|m| m.script_size());
@@ -1538,7 +1544,7 @@ mod tests {
         let keys: Vec<Concrete<bitcoin::PublicKey>> =
             keys.iter().map(|pubkey| Concrete::Key(*pubkey)).collect();
         let thresh_res: Result<SegwitMiniScript, _> =
-            Concrete::Threshold(keys.len(), keys).compile();
+            Concrete::Threshold(Threshold::and(keys).unwrap()).compile();
         let n_elements = thresh_res
             .clone()
             .map(|m| m.max_satisfaction_witness_elements());
@@ -1557,7 +1563,7 @@ mod tests {
         let keys: Vec<Concrete<bitcoin::PublicKey>> =
             keys.iter().map(|pubkey| Concrete::Key(*pubkey)).collect();
         let thresh_res: Result<SegwitMiniScript, _> =
-            Concrete::Threshold(keys.len() - 1, keys).compile();
+            Concrete::Threshold(Threshold::new(keys.len() - 1, keys).unwrap()).compile();
         let ops_count = thresh_res.clone().map(|m| m.ext.ops.op_count());
         assert_eq!(
             thresh_res,
@@ -1569,7 +1575,8 @@ mod tests {
         let (keys, _) = pubkeys_and_a_sig(68);
         let keys: Vec<Concrete<bitcoin::PublicKey>> =
             keys.iter().map(|pubkey| Concrete::Key(*pubkey)).collect();
-        let thresh_res = Concrete::Threshold(keys.len() - 1, keys).compile::<Legacy>();
+        let thresh_res =
+            Concrete::Threshold(Threshold::new(keys.len() - 1, keys).unwrap()).compile::<Legacy>();
         let ops_count = thresh_res.clone().map(|m| m.ext.ops.op_count());
         assert_eq!(
             thresh_res,
@@ -7,7 +7,7 @@
 use std::collections::HashSet;
 use std::{error, fmt, str};
 
-use elements::{LockTime, Sequence};
+use elements::LockTime;
 #[cfg(feature = "compiler")]
 use {
     crate::descriptor::TapTree,
@@ -25,11 +25,14 @@ use {
 };
 
 use super::ENTAILMENT_MAX_TERMINALS;
+use crate::descriptor::checksum::{self, verify_checksum};
 use crate::expression::{self, FromTree};
 use crate::miniscript::types::extra_props::TimelockInfo;
 #[cfg(all(doc, not(feature = "compiler")))]
 use crate::Descriptor;
-use crate::{errstr, AbsLockTime, Error, ForEachKey, MiniscriptKey, Translator};
+use crate::{
+    errstr, AbsLockTime, Error, ForEachKey, MiniscriptKey, RelLockTime, Threshold, Translator,
+};
 
 /// Maximum TapLeafs allowed in a compiled TapTree
 #[cfg(feature = "compiler")]
@@ -49,7 +52,7 @@ pub enum Policy<Pk: MiniscriptKey> {
     /// An absolute locktime restriction
     After(AbsLockTime),
     /// A relative locktime restriction
-    Older(Sequence),
+    Older(RelLockTime),
     /// A SHA256 whose preimage must be provided to satisfy the descriptor
     Sha256(Pk::Sha256),
     /// A SHA256d whose preimage must be provided to satisfy the descriptor
@@ -64,7 +67,7 @@ pub enum Policy<Pk: MiniscriptKey> {
     /// relative probabilities for each one
     Or(Vec<(usize, Policy<Pk>)>),
     /// A set of descriptors, satisfactions must be provided for `k` of them
-    Threshold(usize, Vec<Policy<Pk>>),
+    Threshold(Threshold<Policy<Pk>>),
 }
 
 impl<Pk> Policy<Pk>
@@ -78,9 +81,9 @@ where
     }
 
     /// Construct a `Policy::Older` from `n`. Helper function equivalent to
-    /// `Policy::Older(Sequence::from_consensus(n))`.
+    /// `Policy::Older(RelLockTime::from_consensus(n))`.
     pub fn older(n: u32) -> Policy<Pk> {
-        Policy::Older(Sequence::from_consensus(n))
+        Policy::Older(RelLockTime::from_consensus(n))
     }
 }
 
@@ -114,7 +117,7 @@ enum PolicyArc<Pk: MiniscriptKey> {
     /// along with relative probabilities for each one
     Or(Vec<(usize, Arc<PolicyArc<Pk>>)>),
     /// A set of descriptors' references, satisfactions must be provided for `k` of them
-    Threshold(usize, Vec<Arc<PolicyArc<Pk>>>),
+    Threshold(Threshold<Arc<PolicyArc<Pk>>>),
 }
 
 #[cfg(feature = "compiler")]
@@ -125,7 +128,7 @@ impl<Pk: MiniscriptKey> From<PolicyArc<Pk>> for Policy<Pk> {
             PolicyArc::Trivial => Policy::Trivial,
             PolicyArc::Key(pk) => Policy::Key(pk),
             PolicyArc::After(t) => Policy::After(AbsLockTime::from(LockTime::from_consensus(t))),
-            PolicyArc::Older(t) => Policy::Older(Sequence::from_consensus(t)),
+            PolicyArc::Older(t) => Policy::Older(RelLockTime::from_consensus(t)),
             PolicyArc::Sha256(hash) => Policy::Sha256(hash),
             PolicyArc::Hash256(hash) => Policy::Hash256(hash),
             PolicyArc::Ripemd160(hash) => Policy::Ripemd160(hash),
@@ -140,12 +143,9 @@ impl<Pk: MiniscriptKey> From<PolicyArc<Pk>> for Policy<Pk> {
                     .map(|(odds, sub)| (odds, Self::from((*sub).clone())))
                     .collect(),
             ),
-            PolicyArc::Threshold(k, subs) => Policy::Threshold(
-                k,
-                subs.into_iter()
-                    .map(|pol| Self::from((*pol).clone()))
-                    .collect(),
-            ),
+            PolicyArc::Threshold(thresh) => {
+                Policy::Threshold(thresh.map(|pol| Self::from((*pol).clone())))
+            }
         }
     }
 }
@@ -158,7 +158,7 @@ impl<Pk: MiniscriptKey> From<Policy<Pk>> for PolicyArc<Pk> {
             Policy::Trivial => PolicyArc::Trivial,
             Policy::Key(pk) => PolicyArc::Key(pk),
             Policy::After(t) => PolicyArc::After(t.to_consensus_u32()),
-            Policy::Older(Sequence(t)) => PolicyArc::Older(t),
+            Policy::Older(t) => PolicyArc::Older(t.to_consensus_u32()),
             Policy::Sha256(hash) => PolicyArc::Sha256(hash),
             Policy::Hash256(hash) => PolicyArc::Hash256(hash),
             Policy::Ripemd160(hash) => PolicyArc::Ripemd160(hash),
@@ -173,12 +173,9 @@ impl<Pk: MiniscriptKey> From<Policy<Pk>> for PolicyArc<Pk> {
                     .map(|(odds, sub)| (*odds, Arc::new(Self::from(sub.clone()))))
                     .collect(),
             ),
-            Policy::Threshold(k, subs) => PolicyArc::Threshold(
-                k,
-                subs.iter()
-                    .map(|sub| Arc::new(Self::from(sub.clone())))
-                    .collect(),
-            ),
+            Policy::Threshold(thresh) => {
+                PolicyArc::Threshold(thresh.map(|sub| Arc::new(Self::from(sub))))
+            }
         }
     }
 }
@@ -310,9 +307,10 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                     })
                     .collect::<Vec<_>>()
             }
-            Policy::Threshold(k, ref subs) if *k == 1 => {
-                let total_odds = subs.len();
-                subs.iter()
+            Policy::Threshold(ref thresh) if thresh.is_or() => {
+                let total_odds = thresh.n();
+                thresh
+                    .iter()
                     .flat_map(|policy| policy.to_tapleaf_prob_vec(prob / total_odds as f64))
                     .collect::<Vec<_>>()
             }
@@ -538,14 +536,15 @@ impl<Pk: MiniscriptKey> PolicyArc<Pk> {
                     .map(|(odds, pol)| (prob * *odds as f64 / total_odds as f64, pol.clone()))
                     .collect::<Vec<_>>()
             }
-            PolicyArc::Threshold(k, subs) if *k == 1 => {
-                let total_odds = subs.len();
-                subs.iter()
+            PolicyArc::Threshold(thresh) if thresh.is_or() => {
+                let total_odds = thresh.n();
+                thresh
+                    .iter()
                     .map(|pol| (prob / total_odds as f64, pol.clone()))
                     .collect::<Vec<_>>()
             }
-            PolicyArc::Threshold(k, subs) if *k != subs.len() => {
-                generate_combination(subs, prob, *k)
+            PolicyArc::Threshold(thresh) if !thresh.is_and() => {
+                generate_combination(thresh.data(), prob, thresh.k())
             }
             pol => vec![(prob, Arc::new(pol.clone()))],
         }
@@ -671,15 +670,33 @@ impl<Pk: MiniscriptKey> ForEachKey<Pk> for Policy<Pk> {
             | Policy::Hash160(..)
             | Policy::After(..)
             | Policy::Older(..) => true,
-            Policy::Threshold(_, ref subs) | Policy::And(ref subs) => {
-                subs.iter().all(|sub| sub.for_each_key(&mut pred))
-            }
+            Policy::Threshold(ref thresh) => thresh.iter().all(|sub| sub.for_each_key(&mut pred)),
+            Policy::And(ref subs) => subs.iter().all(|sub| sub.for_each_key(&mut pred)),
             Policy::Or(ref subs) => subs.iter().all(|(_, sub)| sub.for_each_key(&mut pred)),
         }
     }
 }
 
 impl<Pk: MiniscriptKey> Policy<Pk> {
+    /// Display the policy followed by a descriptor-style checksum
+    ///
+    /// Parsing a policy string with a checksum verifies it, so that policies
+    /// stored e.g. in backups get the same corruption detection as descriptors.
+    pub fn to_string_with_checksum(&self) -> String {
+        struct WithChecksum<'a, Pk: MiniscriptKey>(&'a Policy<Pk>);
+
+        impl<'a, Pk: MiniscriptKey> fmt::Display for WithChecksum<'a, Pk> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                use fmt::Write;
+                let mut wrapped_f = checksum::Formatter::new(f);
+                write!(wrapped_f, "{}", self.0)?;
+                wrapped_f.write_checksum()
+            }
+        }
+
+        WithChecksum(self).to_string()
+    }
+
     /// Convert a policy using one kind of public key to another
     /// type of public key
     ///
@@ -746,11 +763,9 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             Policy::Hash160(ref h) => t.hash160(h).map(Policy::Hash160),
             Policy::Older(n) => Ok(Policy::Older(n)),
             Policy::After(n) => Ok(Policy::After(n)),
-            Policy::Threshold(k, ref subs) => {
-                let new_subs: Result<Vec<Policy<Q>>, _> =
-                    subs.iter().map(|sub| sub._translate_pk(t)).collect();
-                new_subs.map(|ok| Policy::Threshold(k, ok))
-            }
+            Policy::Threshold(ref thresh) => thresh
+                .translate_ref(|sub| sub._translate_pk(t))
+                .map(Policy::Threshold),
             Policy::And(ref subs) => Ok(Policy::And(
                 subs.iter()
                     .map(|sub| sub._translate_pk(t))
@@ -778,12 +793,9 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                     .map(|(k, sub)| (k, sub.translate_unsatisfiable_pk(key)))
                     .collect::<Vec<_>>(),
             ),
-            Policy::Threshold(k, subs) => Policy::Threshold(
-                k,
-                subs.into_iter()
-                    .map(|sub| sub.translate_unsatisfiable_pk(key))
-                    .collect::<Vec<_>>(),
-            ),
+            Policy::Threshold(thresh) => {
+                Policy::Threshold(thresh.map(|sub| sub.translate_unsatisfiable_pk(key)))
+            }
             x => x,
         }
     }
@@ -792,8 +804,8 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     pub fn keys(&self) -> Vec<&Pk> {
         match self {
             Policy::Key(ref pk) => vec![pk],
-            Policy::Threshold(_k, ref subs) => {
-                subs.iter().flat_map(|sub| sub.keys()).collect::<Vec<_>>()
+            Policy::Threshold(ref thresh) => {
+                thresh.iter().flat_map(|sub| sub.keys()).collect::<Vec<_>>()
             }
             Policy::And(subs) => subs.iter().flat_map(|sub| sub.keys()).collect::<Vec<_>>(),
             Policy::Or(ref subs) => subs
@@ -811,8 +823,8 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     fn num_tap_leaves(&self) -> usize {
         match self {
             Policy::Or(subs) => subs.iter().map(|(_prob, pol)| pol.num_tap_leaves()).sum(),
-            Policy::Threshold(k, subs) if *k == 1 => {
-                subs.iter().map(|pol| pol.num_tap_leaves()).sum()
+            Policy::Threshold(thresh) if thresh.is_or() => {
+                thresh.iter().map(|pol| pol.num_tap_leaves()).sum()
             }
             _ => 1,
         }
@@ -879,9 +891,9 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                 cltv_with_time: false,
                 contains_combination: false,
             },
-            Policy::Threshold(k, ref subs) => {
-                let iter = subs.iter().map(|sub| sub.check_timelocks_helper());
-                TimelockInfo::combine_threshold(k, iter)
+            Policy::Threshold(ref thresh) => {
+                let iter = thresh.iter().map(|sub| sub.check_timelocks_helper());
+                TimelockInfo::combine_threshold(thresh.k(), iter)
             }
             Policy::And(ref subs) => {
                 let iter = subs.iter().map(|sub| sub.check_timelocks_helper());
@@ -922,15 +934,12 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                     Ok(())
                 }
             }
-            Policy::Threshold(k, ref subs) => {
-                if k == 0 || k > subs.len() {
-                    Err(PolicyError::IncorrectThresh)
-                } else {
-                    subs.iter()
-                        .map(|sub| sub.is_valid())
-                        .collect::<Result<Vec<()>, PolicyError>>()?;
-                    Ok(())
-                }
+            Policy::Threshold(ref thresh) => {
+                thresh
+                    .iter()
+                    .map(|sub| sub.is_valid())
+                    .collect::<Result<Vec<()>, PolicyError>>()?;
+                Ok(())
             }
             Policy::After(n) => {
                 if n == LockTime::ZERO.into() {
@@ -942,7 +951,7 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                 }
             }
             Policy::Older(n) => {
-                if n == Sequence::ZERO {
+                if n.to_consensus_u32() == 0 {
                     Err(PolicyError::ZeroTime)
                 } else if n.to_consensus_u32() > 2u32.pow(31) {
                     Err(PolicyError::TimeTooFar)
@@ -968,8 +977,9 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             | Policy::Hash160(_)
             | Policy::After(_)
             | Policy::Older(_) => (false, true),
-            Policy::Threshold(k, ref subs) => {
-                let (safe_count, non_mall_count) = subs
+            Policy::Threshold(ref thresh) => {
+                let (k, n) = (thresh.k(), thresh.n());
+                let (safe_count, non_mall_count) = thresh
                     .iter()
                     .map(|sub| sub.is_safe_nonmalleable())
                     .fold((0, 0), |(safe_count, non_mall_count), (safe, non_mall)| {
@@ -979,8 +989,8 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                         )
                     });
                 (
-                    safe_count >= (subs.len() - k + 1),
-                    non_mall_count == subs.len() && safe_count >= (subs.len() - k),
+                    safe_count >= (n - k + 1),
+                    non_mall_count == n && safe_count >= (n - k),
                 )
             }
             Policy::And(ref subs) => {
@@ -1036,9 +1046,9 @@ impl<Pk: MiniscriptKey> fmt::Debug for Policy<Pk> {
                 }
                 f.write_str(")")
             }
-            Policy::Threshold(k, ref subs) => {
-                write!(f, "thresh({}", k)?;
-                for sub in subs {
+            Policy::Threshold(ref thresh) => {
+                write!(f, "thresh({}", thresh.k())?;
+                for sub in thresh {
                     write!(f, ",{:?}", sub)?;
                 }
                 f.write_str(")")
@@ -1079,9 +1089,9 @@ impl<Pk: MiniscriptKey> fmt::Display for Policy<Pk> {
                 }
                 f.write_str(")")
             }
-            Policy::Threshold(k, ref subs) => {
-                write!(f, "thresh({}", k)?;
-                for sub in subs {
+            Policy::Threshold(ref thresh) => {
+                write!(f, "thresh({}", thresh.k())?;
+                for sub in thresh {
                     write!(f, ",{}", sub)?;
                 }
                 f.write_str(")")
@@ -1094,12 +1104,8 @@ impl_from_str!(
     Policy<Pk>,
     type Err = Error;,
     fn from_str(s: &str) -> Result<Policy<Pk>, Error> {
-        for ch in s.as_bytes() {
-            if *ch < 20 || *ch > 127 {
-                return Err(Error::Unprintable(*ch));
-            }
-        }
-
+        // Policies may carry a descriptor-style checksum
+        let s = verify_checksum(s)?;
         let tree = expression::Tree::from_str(s)?;
         let policy: Policy<Pk> = FromTree::from_tree(&tree)?;
         policy.check_timelocks()?;
@@ -1199,16 +1205,15 @@ impl_block_str!(
                     return Err(Error::PolicyError(PolicyError::IncorrectThresh));
                 }
 
-                let thresh = expression::parse_num::<u32>(top.args[0].name)?;
-                if thresh >= nsubs || thresh == 0 {
-                    return Err(Error::PolicyError(PolicyError::IncorrectThresh));
-                }
+                let k = expression::parse_num::<u32>(top.args[0].name)?;
 
-                let mut subs = Vec::with_capacity(top.args.len() - 1);
+                let mut subs = Vec::with_capacity(nsubs - 1);
                 for arg in &top.args[1..] {
                     subs.push(Policy::from_tree(arg)?);
                 }
-                Ok(Policy::Threshold(thresh as usize, subs))
+                Threshold::new(k as usize, subs)
+                    .map(Policy::Threshold)
+                    .map_err(|_| Error::PolicyError(PolicyError::IncorrectThresh))
             }
             _ => Err(errstr(top.name)),
         }
@@ -1263,11 +1268,11 @@ fn with_huffman_tree<Pk: MiniscriptKey>(
 /// any one of the conditions exclusively.
 #[cfg(feature = "compiler")]
 fn generate_combination<Pk: MiniscriptKey>(
-    policy_vec: &Vec<Arc<PolicyArc<Pk>>>,
+    policy_vec: &[Arc<PolicyArc<Pk>>],
     prob: f64,
     k: usize,
 ) -> Vec<(f64, Arc<PolicyArc<Pk>>)> {
-    debug_assert!(k <= policy_vec.len());
+    debug_assert!(k < policy_vec.len());
 
     let mut ret: Vec<(f64, Arc<PolicyArc<Pk>>)> = vec![];
     for i in 0..policy_vec.len() {
@@ -1278,7 +1283,9 @@ fn generate_combination<Pk: MiniscriptKey>(
             .collect();
         ret.push((
             prob / policy_vec.len() as f64,
-            Arc::new(PolicyArc::Threshold(k, policies)),
+            Arc::new(PolicyArc::Threshold(
+                Threshold::new(k, policies).expect("k < n, so k <= n - 1"),
+            )),
         ));
     }
     ret
@@ -1291,6 +1298,7 @@ mod tests {
 
     use super::Concrete;
     use crate::policy::concrete::{generate_combination, PolicyArc};
+    use crate::Threshold;
 
     #[test]
     fn test_gen_comb() {
@@ -1339,7 +1347,10 @@ mod tests {
         .collect();
         let expected_comb = vec![comb_a, comb_b, comb_c, comb_d]
             .into_iter()
-            .map(|sub_pol| (0.25, Arc::new(PolicyArc::Threshold(2, sub_pol))))
+            .map(|sub_pol| {
+                let thresh = Threshold::new(2, sub_pol).unwrap();
+                (0.25, Arc::new(PolicyArc::Threshold(thresh)))
+            })
             .collect::<Vec<_>>();
         assert_eq!(combinations, expected_comb);
     }
@@ -0,0 +1,413 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! Covenant Policies
+//!
+//! The [`Semantic`] policy language only reasons about signatures, hashes and
+//! timelocks, so any descriptor using covenant extensions cannot be lifted into
+//! it. This module provides an extended policy language where covenant fragments
+//! are lifted into [`Constraint`]s on the spending transaction, for example
+//! "output 0 must pay at least 1000 of asset X to script Y".
+//!
+
+use std::cmp;
+
+use super::{Liftable, Semantic};
+use crate::descriptor::{Descriptor, LegacyCSFSCov, TapTree, Tr};
+use crate::extensions::IdxExpr;
+use crate::miniscript::{Miniscript, ScriptContext};
+use crate::{fmt, Error, Extension, MiniscriptKey, Terminal, Threshold};
+
+/// Constraint on a single output of the spending transaction.
+/// Fields which are [`None`] are not constrained.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct OutputConstraint {
+    /// Index of the constrained output
+    pub index: IdxExpr,
+    /// Required script pubkey of the output
+    pub spk: Option<String>,
+    /// Required asset of the output
+    pub asset: Option<String>,
+    /// Required (possibly confidential) value of the output
+    pub value: Option<String>,
+    /// Minimum explicit amount of the output, inclusive
+    pub min_amount: Option<i64>,
+    /// Maximum explicit amount of the output, inclusive
+    pub max_amount: Option<i64>,
+}
+
+impl OutputConstraint {
+    /// Create a new [`OutputConstraint`] on the output at `index` without
+    /// any requirement.
+    pub fn new(index: IdxExpr) -> Self {
+        OutputConstraint {
+            index,
+            spk: None,
+            asset: None,
+            value: None,
+            min_amount: None,
+            max_amount: None,
+        }
+    }
+
+    /// Combine two constraints which must both hold for the same output.
+    /// Returns [`None`] if the constraints are on different outputs or
+    /// require different script pubkeys, assets or values.
+    pub fn merge(&self, other: &OutputConstraint) -> Option<OutputConstraint> {
+        fn merge_field(a: &Option<String>, b: &Option<String>) -> Result<Option<String>, ()> {
+            match (a, b) {
+                (Some(a), Some(b)) if a != b => Err(()),
+                (Some(x), _) | (None, Some(x)) => Ok(Some(x.clone())),
+                (None, None) => Ok(None),
+            }
+        }
+
+        if self.index != other.index {
+            return None;
+        }
+        Some(OutputConstraint {
+            index: self.index.clone(),
+            spk: merge_field(&self.spk, &other.spk).ok()?,
+            asset: merge_field(&self.asset, &other.asset).ok()?,
+            value: merge_field(&self.value, &other.value).ok()?,
+            min_amount: match (self.min_amount, other.min_amount) {
+                (Some(a), Some(b)) => Some(cmp::max(a, b)),
+                (a, b) => a.or(b),
+            },
+            max_amount: match (self.max_amount, other.max_amount) {
+                (Some(a), Some(b)) => Some(cmp::min(a, b)),
+                (a, b) => a.or(b),
+            },
+        })
+    }
+}
+
+impl fmt::Display for OutputConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "outputs_to({}", self.index)?;
+        if let Some(ref spk) = self.spk {
+            write!(f, ",spk={}", spk)?;
+        }
+        if let Some(ref asset) = self.asset {
+            write!(f, ",asset={}", asset)?;
+        }
+        if let Some(ref value) = self.value {
+            write!(f, ",value={}", value)?;
+        }
+        if let Some(min) = self.min_amount {
+            write!(f, ",amount>={}", min)?;
+        }
+        if let Some(max) = self.max_amount {
+            write!(f, ",amount<={}", max)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// A constraint a covenant places on the spending transaction
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Constraint {
+    /// Requirement on an output of the spending transaction
+    OutputsTo(OutputConstraint),
+    /// The spending transaction must have the given version
+    TxVersion(u32),
+    /// Any other covenant, described by its miniscript fragment
+    Other(String),
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Constraint::OutputsTo(ref out) => write!(f, "{}", out),
+            Constraint::TxVersion(n) => write!(f, "tx_version({})", n),
+            Constraint::Other(ref s) => f.write_str(s),
+        }
+    }
+}
+
+/// Abstract policy which, in addition to the [`Semantic`] spending
+/// conditions, keeps track of covenant [`Constraint`]s.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Policy<Pk: MiniscriptKey> {
+    /// A policy without any covenant
+    Semantic(Semantic<Pk>),
+    /// A constraint on the spending transaction
+    Covenant(Constraint),
+    /// A set of descriptors, satisfactions must be provided for `k` of them
+    Threshold(Threshold<Policy<Pk>>),
+}
+
+impl<Pk: MiniscriptKey> Policy<Pk> {
+    /// Flatten out nested `and`s and `or`s, collapse trees of
+    /// [`Policy::Semantic`] into a single semantic policy and merge
+    /// constraints on the same output which must hold together.
+    pub fn normalized(self) -> Policy<Pk> {
+        match self {
+            Policy::Semantic(pol) => Policy::Semantic(pol.normalized()),
+            Policy::Covenant(c) => Policy::Covenant(c),
+            Policy::Threshold(thresh) => {
+                let is_and = thresh.is_and();
+                let is_or = thresh.is_or();
+                let k = thresh.k();
+                let mut subs: Vec<Policy<Pk>> = vec![];
+                for sub in thresh.into_iter().map(Policy::normalized) {
+                    match sub {
+                        Policy::Threshold(child)
+                            if (is_and && child.is_and()) || (is_or && child.is_or()) =>
+                        {
+                            subs.extend(child)
+                        }
+                        x => subs.push(x),
+                    }
+                }
+                let k = if is_and { subs.len() } else { k };
+                let semantic_subs: Vec<_> = subs
+                    .iter()
+                    .filter_map(|sub| match *sub {
+                        Policy::Semantic(ref pol) => Some(pol.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                if semantic_subs.len() == subs.len() {
+                    let thresh = Threshold::new(k, semantic_subs).expect("0 < k <= n");
+                    return Policy::Semantic(Semantic::Threshold(thresh).normalized());
+                }
+
+                let k = if is_and {
+                    subs = merge_outputs(subs);
+                    subs.len()
+                } else {
+                    k
+                };
+                if k == 1 && subs.len() == 1 {
+                    subs.pop().expect("Length checked above")
+                } else {
+                    Policy::Threshold(Threshold::new(k, subs).expect("0 < k <= n"))
+                }
+            }
+        }
+    }
+
+    /// Convert into a [`Semantic`] policy, assuming all covenant constraints
+    /// are met by the spending transaction.
+    pub fn to_semantic(&self) -> Semantic<Pk> {
+        match *self {
+            Policy::Semantic(ref pol) => pol.clone(),
+            Policy::Covenant(..) => Semantic::Trivial,
+            Policy::Threshold(ref thresh) => {
+                Semantic::Threshold(thresh.map_ref(Policy::to_semantic)).normalized()
+            }
+        }
+    }
+
+    /// All the covenant constraints in this policy
+    pub fn constraints(&self) -> Vec<&Constraint> {
+        match *self {
+            Policy::Semantic(..) => vec![],
+            Policy::Covenant(ref c) => vec![c],
+            Policy::Threshold(ref thresh) => thresh.iter().flat_map(Policy::constraints).collect(),
+        }
+    }
+
+    /// Whether the policy contains any covenant constraint
+    pub fn has_covenant(&self) -> bool {
+        match *self {
+            Policy::Semantic(..) => false,
+            Policy::Covenant(..) => true,
+            Policy::Threshold(ref thresh) => thresh.iter().any(Policy::has_covenant),
+        }
+    }
+}
+
+// Merge the output constraints of the children of an `and` which apply to
+// the same output
+fn merge_outputs<Pk: MiniscriptKey>(subs: Vec<Policy<Pk>>) -> Vec<Policy<Pk>> {
+    let mut ret: Vec<Policy<Pk>> = Vec::with_capacity(subs.len());
+    for sub in subs {
+        if let Policy::Covenant(Constraint::OutputsTo(ref out)) = sub {
+            let merged = ret.iter_mut().any(|pol| match *pol {
+                Policy::Covenant(Constraint::OutputsTo(ref mut acc)) => match acc.merge(out) {
+                    Some(new) => {
+                        *acc = new;
+                        true
+                    }
+                    None => false,
+                },
+                _ => false,
+            });
+            if merged {
+                continue;
+            }
+        }
+        ret.push(sub);
+    }
+    ret
+}
+
+impl<Pk: MiniscriptKey> fmt::Display for Policy<Pk> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Policy::Semantic(ref pol) => write!(f, "{}", pol),
+            Policy::Covenant(ref c) => write!(f, "{}", c),
+            Policy::Threshold(ref thresh) => {
+                if thresh.is_and() {
+                    write!(f, "and(")?;
+                } else if thresh.is_or() {
+                    write!(f, "or(")?;
+                } else {
+                    write!(f, "thresh({},", thresh.k())?;
+                }
+                for (i, sub) in thresh.iter().enumerate() {
+                    if i == 0 {
+                        write!(f, "{}", sub)?;
+                    } else {
+                        write!(f, ",{}", sub)?;
+                    }
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Trait describing script representations which can be lifted into
+/// a covenant [`Policy`]. Unlike [`Liftable`], this succeeds on
+/// descriptors that contain covenant extensions.
+pub trait CovLiftable<Pk: MiniscriptKey> {
+    /// Convert the object into a covenant policy
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error>;
+}
+
+impl<Pk: MiniscriptKey, Ctx: ScriptContext, Ext: Extension> CovLiftable<Pk>
+    for Miniscript<Pk, Ctx, Ext>
+{
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error> {
+        self.lift_check()?;
+        self.as_inner().lift_cov()
+    }
+}
+
+impl<Pk, Ctx, Ext> CovLiftable<Pk> for Terminal<Pk, Ctx, Ext>
+where
+    Pk: MiniscriptKey,
+    Ctx: ScriptContext,
+    Ext: Extension,
+{
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error> {
+        let ret = match *self {
+            Terminal::Ext(ref e) => Policy::Covenant(e.cov_constraint()),
+            Terminal::Alt(ref sub)
+            | Terminal::Swap(ref sub)
+            | Terminal::Check(ref sub)
+            | Terminal::DupIf(ref sub)
+            | Terminal::Verify(ref sub)
+            | Terminal::NonZero(ref sub)
+            | Terminal::ZeroNotEqual(ref sub) => sub.node.lift_cov()?,
+            Terminal::AndV(ref left, ref right) | Terminal::AndB(ref left, ref right) => {
+                Policy::Threshold(Threshold::both(
+                    left.node.lift_cov()?,
+                    right.node.lift_cov()?,
+                ))
+            }
+            Terminal::AndOr(ref a, ref b, ref c) => Policy::Threshold(Threshold::either(
+                Policy::Threshold(Threshold::both(a.node.lift_cov()?, b.node.lift_cov()?)),
+                c.node.lift_cov()?,
+            )),
+            Terminal::OrB(ref left, ref right)
+            | Terminal::OrD(ref left, ref right)
+            | Terminal::OrC(ref left, ref right)
+            | Terminal::OrI(ref left, ref right) => Policy::Threshold(Threshold::either(
+                left.node.lift_cov()?,
+                right.node.lift_cov()?,
+            )),
+            Terminal::Thresh(ref thresh) => {
+                Policy::Threshold(thresh.translate_ref(|s| s.node.lift_cov())?)
+            }
+            _ => Policy::Semantic(self.lift()?),
+        }
+        .normalized();
+        Ok(ret)
+    }
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> CovLiftable<Pk> for TapTree<Pk, Ext> {
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error> {
+        let ret = match *self {
+            TapTree::Tree(ref l, ref r) => {
+                Policy::Threshold(Threshold::either(l.lift_cov()?, r.lift_cov()?))
+            }
+            TapTree::Leaf(ref leaf) => leaf.lift_cov()?,
+        };
+        Ok(ret.normalized())
+    }
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> CovLiftable<Pk> for Tr<Pk, Ext> {
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error> {
+        let key = Policy::Semantic(Semantic::Key(self.internal_key().clone()));
+        match *self.taptree() {
+            Some(ref root) => {
+                Ok(Policy::Threshold(Threshold::either(key, root.lift_cov()?)).normalized())
+            }
+            None => Ok(key),
+        }
+    }
+}
+
+impl<Pk: MiniscriptKey, Ext: Extension> CovLiftable<Pk> for LegacyCSFSCov<Pk, Ext> {
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error> {
+        // The covenant key signs the transaction via CHECKSIGFROMSTACK
+        let key = Policy::Semantic(Semantic::Key(self.pk().clone()));
+        Ok(Policy::Threshold(Threshold::both(key, self.to_ms().lift_cov()?)).normalized())
+    }
+}
+
+impl<Pk: MiniscriptKey, T: Extension> CovLiftable<Pk> for Descriptor<Pk, T> {
+    fn lift_cov(&self) -> Result<Policy<Pk>, Error> {
+        match *self {
+            Descriptor::LegacyCSFSCov(ref cov) => cov.lift_cov(),
+            Descriptor::TrExt(ref tr) => tr.lift_cov(),
+            _ => Ok(Policy::Semantic(self.lift()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::extensions::CovenantExt;
+    use crate::Tap;
+
+    type MsExtStr = Miniscript<String, Tap, CovenantExt<String>>;
+
+    #[test]
+    fn lift_covenant() {
+        let ms = MsExtStr::from_str_insane(
+            "and_v(v:pk(A),and_v(v:spk_eq(out_spk(0),Spk),num64_geq(out_v(0),1000)))",
+        )
+        .unwrap();
+        assert!(ms.lift().is_err());
+
+        let pol = ms.lift_cov().unwrap();
+        assert!(pol.has_covenant());
+        assert_eq!(pol.constraints().len(), 1);
+        assert_eq!(
+            pol.to_string(),
+            "and(pk(A),outputs_to(0,spk=Spk,amount>=1000))"
+        );
+        assert_eq!(pol.to_semantic(), Semantic::from_str("pk(A)").unwrap());
+
+        // Conflicting requirements on the same output are not merged
+        let ms = MsExtStr::from_str_insane(
+            "or_i(pk(A),and_v(v:ver_eq(2),and_v(v:spk_eq(out_spk(1),X),spk_eq(Y,out_spk(1)))))",
+        )
+        .unwrap();
+        let pol = ms.lift_cov().unwrap();
+        assert_eq!(pol.constraints().len(), 3);
+        assert_eq!(
+            pol.to_string(),
+            "or(pk(A),and(tx_version(2),outputs_to(1,spk=X),outputs_to(1,spk=Y)))"
+        );
+    }
+}
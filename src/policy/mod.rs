@@ -10,22 +10,27 @@
 //! The format represents EC public keys abstractly to allow wallets to replace
 //! these with BIP32 paths, pay-to-contract instructions, etc.
 //!
-use elements::Sequence;
-
 use crate::{error, fmt};
 
 #[cfg(feature = "compiler")]
 pub mod compiler;
 pub mod concrete;
+pub mod covenant;
+#[cfg(feature = "compiler")]
+mod paths;
 pub mod semantic;
 
 pub use self::concrete::Policy as Concrete;
+#[cfg(feature = "compiler")]
+pub use self::paths::PathWeight;
 /// Semantic policies are "abstract" policies elsewhere; but we
 /// avoid this word because it is a reserved keyword in Rust
 pub use self::semantic::Policy as Semantic;
 use crate::descriptor::{CovError, Descriptor};
 use crate::miniscript::{Miniscript, ScriptContext};
-use crate::{AbsLockTime, BtcPolicy, Error, Extension, MiniscriptKey, Terminal};
+use crate::{
+    AbsLockTime, BtcPolicy, Error, Extension, MiniscriptKey, RelLockTime, Terminal, Threshold,
+};
 
 /// Policy entailment algorithm maximum number of terminals allowed
 const ENTAILMENT_MAX_TERMINALS: usize = 20;
@@ -142,27 +147,23 @@ where
             | Terminal::NonZero(ref sub)
             | Terminal::ZeroNotEqual(ref sub) => sub.node.lift()?,
             Terminal::AndV(ref left, ref right) | Terminal::AndB(ref left, ref right) => {
-                Semantic::Threshold(2, vec![left.node.lift()?, right.node.lift()?])
+                Semantic::Threshold(Threshold::both(left.node.lift()?, right.node.lift()?))
             }
-            Terminal::AndOr(ref a, ref b, ref c) => Semantic::Threshold(
-                1,
-                vec![
-                    Semantic::Threshold(2, vec![a.node.lift()?, b.node.lift()?]),
-                    c.node.lift()?,
-                ],
-            ),
+            Terminal::AndOr(ref a, ref b, ref c) => Semantic::Threshold(Threshold::either(
+                Semantic::Threshold(Threshold::both(a.node.lift()?, b.node.lift()?)),
+                c.node.lift()?,
+            )),
             Terminal::OrB(ref left, ref right)
             | Terminal::OrD(ref left, ref right)
             | Terminal::OrC(ref left, ref right)
             | Terminal::OrI(ref left, ref right) => {
-                Semantic::Threshold(1, vec![left.node.lift()?, right.node.lift()?])
+                Semantic::Threshold(Threshold::either(left.node.lift()?, right.node.lift()?))
             }
-            Terminal::Thresh(k, ref subs) => {
-                let semantic_subs: Result<_, Error> = subs.iter().map(|s| s.node.lift()).collect();
-                Semantic::Threshold(k, semantic_subs?)
+            Terminal::Thresh(ref thresh) => {
+                Semantic::Threshold(thresh.translate_ref(|s| s.node.lift())?)
             }
-            Terminal::Multi(k, ref keys) | Terminal::MultiA(k, ref keys) => {
-                Semantic::Threshold(k, keys.iter().map(|k| Semantic::Key(k.clone())).collect())
+            Terminal::Multi(ref thresh) | Terminal::MultiA(ref thresh) => {
+                Semantic::Threshold(thresh.map_ref(|k| Semantic::Key(k.clone())))
             }
             Terminal::Ext(ref _e) => Err(Error::CovError(CovError::CovenantLift))?,
         }
@@ -179,9 +180,10 @@ impl<Pk: MiniscriptKey, T: Extension> Liftable<Pk> for Descriptor<Pk, T> {
             Descriptor::Wpkh(ref wpkh) => wpkh.lift(),
             Descriptor::Wsh(ref wsh) => wsh.lift(),
             Descriptor::Sh(ref sh) => sh.lift(),
-            Descriptor::LegacyCSFSCov(ref _cov) => Err(Error::CovError(CovError::CovenantLift)),
+            Descriptor::LegacyCSFSCov(ref cov) => cov.lift(),
             Descriptor::Tr(ref tr) => tr.lift(),
             Descriptor::TrExt(ref tr) => tr.lift(),
+            Descriptor::Wit(_) => Err(Error::UnknownWitnessVersion),
         }
     }
 }
@@ -209,16 +211,15 @@ impl<Pk: MiniscriptKey> Liftable<Pk> for Concrete<Pk> {
             Concrete::Hash160(ref h) => Semantic::Hash160(h.clone()),
             Concrete::And(ref subs) => {
                 let semantic_subs: Result<_, Error> = subs.iter().map(Liftable::lift).collect();
-                Semantic::Threshold(2, semantic_subs?)
+                Semantic::Threshold(Threshold::and(semantic_subs?)?)
             }
             Concrete::Or(ref subs) => {
                 let semantic_subs: Result<_, Error> =
                     subs.iter().map(|(_p, sub)| sub.lift()).collect();
-                Semantic::Threshold(1, semantic_subs?)
+                Semantic::Threshold(Threshold::or(semantic_subs?)?)
             }
-            Concrete::Threshold(k, ref subs) => {
-                let semantic_subs: Result<_, Error> = subs.iter().map(Liftable::lift).collect();
-                Semantic::Threshold(k, semantic_subs?)
+            Concrete::Threshold(ref thresh) => {
+                Semantic::Threshold(thresh.translate_ref(Liftable::lift)?)
             }
         }
         .normalized();
@@ -240,11 +241,13 @@ impl<Pk: MiniscriptKey> Liftable<Pk> for BtcPolicy<Pk> {
             BtcPolicy::After(n) => Ok(Semantic::After(AbsLockTime::from_consensus(
                 n.to_consensus_u32(),
             ))),
-            BtcPolicy::Older(n) => Ok(Semantic::Older(Sequence(n.to_consensus_u32()))),
+            BtcPolicy::Older(n) => Ok(Semantic::Older(RelLockTime::from_consensus(
+                n.to_consensus_u32(),
+            ))),
             BtcPolicy::Threshold(k, ref subs) => {
                 let new_subs: Result<Vec<Semantic<Pk>>, _> =
                     subs.iter().map(|sub| Liftable::lift(sub)).collect();
-                Ok(Semantic::Threshold(k, new_subs?))
+                Ok(Semantic::Threshold(Threshold::new(k, new_subs?)?))
             }
         }
     }
@@ -257,7 +260,6 @@ mod tests {
     use std::sync::Arc;
 
     use bitcoin;
-    use elements::Sequence;
 
     use super::super::miniscript::context::Segwitv0;
     use super::super::miniscript::Miniscript;
@@ -266,6 +268,7 @@ mod tests {
     use crate::descriptor::Tr;
     #[cfg(feature = "compiler")]
     use crate::{descriptor::TapTree, Descriptor, Tap};
+    use crate::{RelLockTime, Threshold};
 
     type ConcretePol = Concrete<String>;
     type SemanticPol = Semantic<String>;
@@ -315,6 +318,24 @@ mod tests {
         concrete_policy_rtt("ripemd160()");
     }
 
+    #[test]
+    fn policy_checksum() {
+        let pol = ConcretePol::from_str("and(pk(A),or(99@pk(B),1@older(12960)))").unwrap();
+        let s = pol.to_string_with_checksum();
+        let (pol_str, checksum) = s.split_at(s.len() - 9);
+        assert_eq!(pol_str, pol.to_string());
+        assert_eq!(
+            checksum[1..],
+            crate::descriptor::checksum::desc_checksum(pol_str).unwrap()
+        );
+        assert_eq!(ConcretePol::from_str(&s).unwrap(), pol);
+
+        // A corrupted policy or checksum is rejected
+        let corrupted = s.replace("12960", "12961");
+        assert!(ConcretePol::from_str(&corrupted).is_err());
+        assert!(ConcretePol::from_str(&format!("{}#aaaaaaaa", pol)).is_err());
+    }
+
     #[test]
     fn compile_invalid() {
         // Since the root Error does not support Eq type, we have to
@@ -379,19 +400,13 @@ mod tests {
         .parse()
         .unwrap();
         assert_eq!(
-            Semantic::Threshold(
-                1,
-                vec![
-                    Semantic::Threshold(
-                        2,
-                        vec![
-                            Semantic::Key(key_a),
-                            Semantic::Older(Sequence::from_height(42))
-                        ]
-                    ),
-                    Semantic::Key(key_b)
-                ]
-            ),
+            Semantic::Threshold(Threshold::either(
+                Semantic::Threshold(Threshold::both(
+                    Semantic::Key(key_a),
+                    Semantic::Older(RelLockTime::from_height(42))
+                )),
+                Semantic::Key(key_b)
+            )),
             ms_str.lift().unwrap()
         );
     }
@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Spend Path Weights
+//!
+//! Estimation of the witness weight of each spend path of a concrete policy,
+//! once compiled, e.g. to compare the fees of a recovery path with the ones
+//! of the normal path when designing a policy.
+//!
+
+use std::collections::BTreeMap;
+
+use bitcoin::hashes::{hash160, ripemd160, sha256, Hash};
+use elements::secp256k1_zkp::{self, SecretKey};
+use elements::taproot::TapLeafHash;
+use elements::{EcdsaSigHashType, LockTime, SchnorrSigHashType, Sequence};
+
+use super::semantic::SpendPath;
+use super::{Concrete, Liftable, Semantic};
+use crate::miniscript::context::Context;
+use crate::miniscript::satisfy::{ElementsSig, Preimage32};
+use crate::miniscript::ScriptContext;
+use crate::util::varint_len;
+use crate::{
+    hash256, push_opcode_size, AbsLockTime, Error, MiniscriptKey, RelLockTime, Satisfier,
+    TranslatePk, Translator,
+};
+
+/// A spend path of a concrete policy with the weight of its witness, see
+/// [`Concrete::enumerate_paths`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PathWeight<Pk: MiniscriptKey> {
+    /// The spend path
+    pub path: SpendPath<Pk>,
+    /// The weight of the satisfaction of the compiled miniscript along the
+    /// path, in weight units.
+    ///
+    /// This is the weight of the witness elements, or of the scriptSig
+    /// pushes for legacy contexts, excluding the number of witness elements
+    /// and the witness script or control block. Signatures are assumed to
+    /// have the largest size, 73 bytes for ECDSA and 65 bytes for Schnorr
+    /// signatures, including their length prefix and sighash type.
+    pub witness_weight: usize,
+}
+
+impl<Pk: MiniscriptKey> Concrete<Pk> {
+    /// Compiles the policy for the script context `Ctx` and returns up to
+    /// `max` of its spend paths, see [`Semantic::spend_paths`], with the
+    /// weight of their satisfaction.
+    ///
+    /// The satisfaction of each path is the cheapest one using only the
+    /// keys, preimages and timelocks of the path.
+    pub fn enumerate_paths<Ctx: ScriptContext>(
+        &self,
+        max: usize,
+    ) -> Result<Vec<PathWeight<Pk>>, Error> {
+        let ms = self.compile::<Ctx>()?;
+        let mut dummies = Dummies::new();
+        let ms = ms.translate_pk(&mut dummies)?;
+        self.lift()?
+            .spend_paths()
+            .into_iter()
+            .take(max)
+            .map(|path| {
+                let witness = ms.satisfy(PathSatisfier {
+                    path: &path,
+                    dummies: &dummies,
+                })?;
+                let witness_weight = match Ctx::context() {
+                    Context::BareCtx | Context::Legacy => {
+                        4 * witness
+                            .iter()
+                            .map(|elem| push_opcode_size(elem.len()) + elem.len())
+                            .sum::<usize>()
+                    }
+                    Context::Segwitv0 | Context::Tap | Context::NoChecks => witness
+                        .iter()
+                        .map(|elem| varint_len(elem.len()) + elem.len())
+                        .sum(),
+                };
+                Ok(PathWeight {
+                    path,
+                    witness_weight,
+                })
+            })
+            .collect()
+    }
+}
+
+// Translator replacing keys and hashes by distinct dummy ones, whose
+// preimages are known
+struct Dummies<Pk: MiniscriptKey> {
+    keys: BTreeMap<Pk, bitcoin::PublicKey>,
+    sha256: BTreeMap<Pk::Sha256, (sha256::Hash, Preimage32)>,
+    hash256: BTreeMap<Pk::Hash256, (hash256::Hash, Preimage32)>,
+    ripemd160: BTreeMap<Pk::Ripemd160, (ripemd160::Hash, Preimage32)>,
+    hash160: BTreeMap<Pk::Hash160, (hash160::Hash, Preimage32)>,
+    n_preimages: u64,
+}
+
+impl<Pk: MiniscriptKey> Dummies<Pk> {
+    fn new() -> Self {
+        Dummies {
+            keys: BTreeMap::new(),
+            sha256: BTreeMap::new(),
+            hash256: BTreeMap::new(),
+            ripemd160: BTreeMap::new(),
+            hash160: BTreeMap::new(),
+            n_preimages: 0,
+        }
+    }
+
+    fn preimage(&mut self) -> Preimage32 {
+        self.n_preimages += 1;
+        sha256::Hash::hash(&self.n_preimages.to_be_bytes()).to_byte_array()
+    }
+}
+
+impl<Pk: MiniscriptKey> Translator<Pk, bitcoin::PublicKey, Error> for Dummies<Pk> {
+    fn pk(&mut self, pk: &Pk) -> Result<bitcoin::PublicKey, Error> {
+        if let Some(dummy) = self.keys.get(pk) {
+            return Ok(*dummy);
+        }
+        let mut sk = [0; 32];
+        sk[24..].copy_from_slice(&(self.keys.len() as u64 + 1).to_be_bytes());
+        let sk = SecretKey::from_slice(&sk)?;
+        let dummy = bitcoin::PublicKey {
+            inner: crate::with_secp(|secp| secp256k1_zkp::PublicKey::from_secret_key(secp, &sk)),
+            compressed: !pk.is_uncompressed(),
+        };
+        self.keys.insert(pk.clone(), dummy);
+        Ok(dummy)
+    }
+
+    fn sha256(&mut self, hash: &Pk::Sha256) -> Result<sha256::Hash, Error> {
+        let preimage = self.preimage();
+        let entry = self
+            .sha256
+            .entry(hash.clone())
+            .or_insert((sha256::Hash::hash(&preimage), preimage));
+        Ok(entry.0)
+    }
+
+    fn hash256(&mut self, hash: &Pk::Hash256) -> Result<hash256::Hash, Error> {
+        let preimage = self.preimage();
+        let entry = self
+            .hash256
+            .entry(hash.clone())
+            .or_insert((hash256::Hash::hash(&preimage), preimage));
+        Ok(entry.0)
+    }
+
+    fn ripemd160(&mut self, hash: &Pk::Ripemd160) -> Result<ripemd160::Hash, Error> {
+        let preimage = self.preimage();
+        let entry = self
+            .ripemd160
+            .entry(hash.clone())
+            .or_insert((ripemd160::Hash::hash(&preimage), preimage));
+        Ok(entry.0)
+    }
+
+    fn hash160(&mut self, hash: &Pk::Hash160) -> Result<hash160::Hash, Error> {
+        let preimage = self.preimage();
+        let entry = self
+            .hash160
+            .entry(hash.clone())
+            .or_insert((hash160::Hash::hash(&preimage), preimage));
+        Ok(entry.0)
+    }
+}
+
+// Satisfier with dummy signatures and the preimages of a spend path
+struct PathSatisfier<'a, Pk: MiniscriptKey> {
+    path: &'a SpendPath<Pk>,
+    dummies: &'a Dummies<Pk>,
+}
+
+impl<'a, Pk: MiniscriptKey> PathSatisfier<'a, Pk> {
+    fn has_key(&self, pk: &bitcoin::PublicKey) -> bool {
+        self.path
+            .keys
+            .iter()
+            .any(|key| self.dummies.keys.get(key) == Some(pk))
+    }
+
+    // The preimage of the dummy hash `dummy`, if the path has its hash
+    fn preimage<H, F>(&self, dummy: &H, find: F) -> Option<Preimage32>
+    where
+        H: PartialEq,
+        F: Fn(&Semantic<Pk>) -> Option<&'a (H, Preimage32)>,
+    {
+        self.path
+            .hashes
+            .iter()
+            .filter_map(find)
+            .find(|(hash, _)| hash == dummy)
+            .map(|(_, preimage)| *preimage)
+    }
+}
+
+impl<'a, Pk: MiniscriptKey> Satisfier<bitcoin::PublicKey> for PathSatisfier<'a, Pk> {
+    fn lookup_ecdsa_sig(&self, pk: &bitcoin::PublicKey) -> Option<ElementsSig> {
+        if !self.has_key(pk) {
+            return None;
+        }
+        // The largest DER encoding, with 33 byte r and s values
+        let sig = secp256k1_zkp::ecdsa::Signature::from_compact(&[0x80; 64]).ok()?;
+        Some((sig, EcdsaSigHashType::All))
+    }
+
+    fn lookup_tap_leaf_script_sig(
+        &self,
+        pk: &bitcoin::PublicKey,
+        _: &TapLeafHash,
+    ) -> Option<elements::SchnorrSig> {
+        if !self.has_key(pk) {
+            return None;
+        }
+        Some(elements::SchnorrSig {
+            sig: secp256k1_zkp::schnorr::Signature::from_slice(&[1; 64]).ok()?,
+            hash_ty: SchnorrSigHashType::All,
+        })
+    }
+
+    fn lookup_sha256(&self, h: &sha256::Hash) -> Option<Preimage32> {
+        let dummies = self.dummies;
+        self.preimage(h, |hash| match *hash {
+            Semantic::Sha256(ref hash) => dummies.sha256.get(hash),
+            _ => None,
+        })
+    }
+
+    fn lookup_hash256(&self, h: &hash256::Hash) -> Option<Preimage32> {
+        let dummies = self.dummies;
+        self.preimage(h, |hash| match *hash {
+            Semantic::Hash256(ref hash) => dummies.hash256.get(hash),
+            _ => None,
+        })
+    }
+
+    fn lookup_ripemd160(&self, h: &ripemd160::Hash) -> Option<Preimage32> {
+        let dummies = self.dummies;
+        self.preimage(h, |hash| match *hash {
+            Semantic::Ripemd160(ref hash) => dummies.ripemd160.get(hash),
+            _ => None,
+        })
+    }
+
+    fn lookup_hash160(&self, h: &hash160::Hash) -> Option<Preimage32> {
+        let dummies = self.dummies;
+        self.preimage(h, |hash| match *hash {
+            Semantic::Hash160(ref hash) => dummies.hash160.get(hash),
+            _ => None,
+        })
+    }
+
+    fn check_older(&self, n: Sequence) -> bool {
+        self.path
+            .older
+            .map_or(false, |older| RelLockTime::from(n).is_implied_by(older))
+    }
+
+    fn check_after(&self, n: LockTime) -> bool {
+        self.path
+            .after
+            .map_or(false, |after| AbsLockTime::from(n).is_implied_by(after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{Segwitv0, Tap};
+
+    #[test]
+    fn enumerate_paths() {
+        let policy = Concrete::<String>::from_str(
+            "or(99@pk(A),1@and(thresh(2,pk(B),pk(C),pk(D)),older(4032)))",
+        )
+        .unwrap();
+        let paths = policy.enumerate_paths::<Segwitv0>(10).unwrap();
+        // The normal path and the 3 combinations of recovery keys
+        assert_eq!(paths.len(), 4);
+        let normal = paths
+            .iter()
+            .find(|p| p.path.keys == vec!["A".to_owned()])
+            .unwrap();
+        assert_eq!(normal.path.older, None);
+        let recovery = paths
+            .iter()
+            .find(|p| p.path.keys == vec!["B".to_owned(), "C".to_owned()])
+            .unwrap();
+        assert_eq!(recovery.path.older, Some(RelLockTime::from_height(4032)));
+        // The recovery path has more signatures, and dissatisfies the normal
+        // path
+        assert!(recovery.witness_weight > normal.witness_weight + 73);
+        assert_eq!(policy.enumerate_paths::<Segwitv0>(2).unwrap().len(), 2);
+
+        let policy = Concrete::<String>::from_str("and(pk(A),sha256(H))").unwrap();
+        let paths = policy.enumerate_paths::<Tap>(10).unwrap();
+        // A 65 byte signature and a 32 byte preimage, with their lengths
+        assert_eq!(paths[0].witness_weight, 66 + 33);
+    }
+}
@@ -10,7 +10,10 @@ use elements::{LockTime, Sequence};
 
 use super::concrete::PolicyError;
 use super::ENTAILMENT_MAX_TERMINALS;
-use crate::{errstr, expression, AbsLockTime, Error, ForEachKey, MiniscriptKey, Translator};
+use crate::{
+    errstr, expression, AbsLockTime, Error, ForEachKey, MiniscriptKey, RelLockTime, Threshold,
+    Translator,
+};
 
 /// Abstract policy which corresponds to the semantics of a Miniscript
 /// and which allows complex forms of analysis, e.g. filtering and
@@ -18,7 +21,7 @@ use crate::{errstr, expression, AbsLockTime, Error, ForEachKey, MiniscriptKey, T
 /// Semantic policies store only hashes of keys to ensure that objects
 /// representing the same policy are lifted to the same `Semantic`,
 /// regardless of their choice of `pk` or `pk_h` nodes.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Policy<Pk: MiniscriptKey> {
     /// Unsatisfiable
     Unsatisfiable,
@@ -29,7 +32,7 @@ pub enum Policy<Pk: MiniscriptKey> {
     /// An absolute locktime restriction
     After(AbsLockTime),
     /// A relative locktime restriction
-    Older(Sequence),
+    Older(RelLockTime),
     /// A SHA256 whose preimage must be provided to satisfy the descriptor
     Sha256(Pk::Sha256),
     /// A SHA256d whose preimage must be provided to satisfy the descriptor
@@ -39,7 +42,10 @@ pub enum Policy<Pk: MiniscriptKey> {
     /// A HASH160 whose preimage must be provided to satisfy the descriptor
     Hash160(Pk::Hash160),
     /// A set of descriptors, satisfactions must be provided for `k` of them
-    Threshold(usize, Vec<Policy<Pk>>),
+    Threshold(Threshold<Policy<Pk>>),
+    /// The inner policy, where the spending transaction must additionally
+    /// meet a covenant which cannot be expressed as a semantic policy
+    Covenant(Box<Policy<Pk>>),
 }
 
 impl<Pk> Policy<Pk>
@@ -53,9 +59,9 @@ where
     }
 
     /// Construct a `Policy::Older` from `n`. Helper function equivalent to
-    /// `Policy::Older(Sequence::from_consensus(n))`.
+    /// `Policy::Older(RelLockTime::from_consensus(n))`.
     pub fn older(n: u32) -> Policy<Pk> {
-        Policy::Older(Sequence::from_consensus(n))
+        Policy::Older(RelLockTime::from_consensus(n))
     }
 }
 
@@ -73,7 +79,8 @@ impl<Pk: MiniscriptKey> ForEachKey<Pk> for Policy<Pk> {
             | Policy::Hash160(..)
             | Policy::After(..)
             | Policy::Older(..) => true,
-            Policy::Threshold(_, ref subs) => subs.iter().all(|sub| sub.for_each_key(&mut pred)),
+            Policy::Threshold(ref thresh) => thresh.iter().all(|sub| sub.for_each_key(&mut pred)),
+            Policy::Covenant(ref sub) => sub.for_each_key(pred),
         }
     }
 }
@@ -144,10 +151,11 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             Policy::Hash160(ref h) => t.hash160(h).map(Policy::Hash160),
             Policy::After(n) => Ok(Policy::After(n)),
             Policy::Older(n) => Ok(Policy::Older(n)),
-            Policy::Threshold(k, ref subs) => {
-                let new_subs: Result<Vec<Policy<Q>>, _> =
-                    subs.iter().map(|sub| sub._translate_pk(t)).collect();
-                new_subs.map(|ok| Policy::Threshold(k, ok))
+            Policy::Threshold(ref thresh) => thresh
+                .translate_ref(|sub| sub._translate_pk(t))
+                .map(Policy::Threshold),
+            Policy::Covenant(ref sub) => {
+                sub._translate_pk(t).map(|p| Policy::Covenant(Box::new(p)))
             }
         }
     }
@@ -156,6 +164,10 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     /// A |- B means every satisfaction of A is also a satisfaction of B.
     /// This implementation will run slow for larger policies but should be sufficient for
     /// most practical policies.
+    ///
+    /// Covenants are opaque: a policy with a covenant entails the same policy
+    /// without it, but a policy entails one with a covenant only if it is
+    /// unsatisfiable.
 
     // This algorithm has a naive implementation. It is possible to optimize this
     // by memoizing and maintaining a hashmap.
@@ -163,7 +175,11 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
         if self.n_terminals() > ENTAILMENT_MAX_TERMINALS {
             return Err(PolicyError::EntailmentMaxTerminals);
         }
-        match (self, other) {
+        if other.has_covenant() {
+            return Ok(self.normalized() == Policy::Unsatisfiable);
+        }
+        let this = self.without_covenants();
+        match (this, other) {
             (Policy::Unsatisfiable, _) => Ok(true),
             (Policy::Trivial, Policy::Trivial) => Ok(true),
             (Policy::Trivial, _) => Ok(false),
@@ -184,10 +200,30 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
         }
     }
 
+    // Whether the policy contains a covenant
+    fn has_covenant(&self) -> bool {
+        match *self {
+            Policy::Threshold(ref thresh) => thresh.iter().any(Policy::has_covenant),
+            Policy::Covenant(..) => true,
+            _ => false,
+        }
+    }
+
+    // The policy with all covenant requirements removed, i.e. assuming the
+    // spending transaction meets them
+    fn without_covenants(self) -> Policy<Pk> {
+        match self {
+            Policy::Threshold(thresh) => Policy::Threshold(thresh.map(Policy::without_covenants)),
+            Policy::Covenant(sub) => sub.without_covenants(),
+            x => x,
+        }
+    }
+
     // Helper function to compute the number of constraints in policy.
     fn n_terminals(&self) -> usize {
         match self {
-            &Policy::Threshold(_k, ref subs) => subs.iter().map(|sub| sub.n_terminals()).sum(),
+            &Policy::Threshold(ref thresh) => thresh.iter().map(|sub| sub.n_terminals()).sum(),
+            &Policy::Covenant(ref sub) => sub.n_terminals(),
             &Policy::Trivial | &Policy::Unsatisfiable => 0,
             _leaf => 1,
         }
@@ -199,7 +235,8 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     fn first_constraint(&self) -> Policy<Pk> {
         debug_assert!(self.clone().normalized() == self.clone());
         match self {
-            &Policy::Threshold(_k, ref subs) => subs[0].first_constraint(),
+            &Policy::Threshold(ref thresh) => thresh.data()[0].first_constraint(),
+            &Policy::Covenant(ref sub) => sub.first_constraint(),
             first => first.clone(),
         }
     }
@@ -216,12 +253,11 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             unreachable!()
         }
         let ret = match self {
-            Policy::Threshold(k, subs) => {
-                let mut ret_subs = vec![];
-                for sub in subs {
-                    ret_subs.push(sub.satisfy_constraint(witness, available));
-                }
-                Policy::Threshold(k, ret_subs)
+            Policy::Threshold(thresh) => {
+                Policy::Threshold(thresh.map(|sub| sub.satisfy_constraint(witness, available)))
+            }
+            Policy::Covenant(sub) => {
+                Policy::Covenant(Box::new(sub.satisfy_constraint(witness, available)))
             }
             ref leaf if leaf == witness => {
                 if available {
@@ -248,15 +284,15 @@ impl<Pk: MiniscriptKey> fmt::Debug for Policy<Pk> {
             Policy::Hash256(ref h) => write!(f, "hash256({})", h),
             Policy::Ripemd160(ref h) => write!(f, "ripemd160({})", h),
             Policy::Hash160(ref h) => write!(f, "hash160({})", h),
-            Policy::Threshold(k, ref subs) => {
-                if k == subs.len() {
+            Policy::Threshold(ref thresh) => {
+                if thresh.is_and() {
                     write!(f, "and(")?;
-                } else if k == 1 {
+                } else if thresh.is_or() {
                     write!(f, "or(")?;
                 } else {
-                    write!(f, "thresh({},", k)?;
+                    write!(f, "thresh({},", thresh.k())?;
                 }
-                for (i, sub) in subs.iter().enumerate() {
+                for (i, sub) in thresh.iter().enumerate() {
                     if i == 0 {
                         write!(f, "{}", sub)?;
                     } else {
@@ -265,6 +301,7 @@ impl<Pk: MiniscriptKey> fmt::Debug for Policy<Pk> {
                 }
                 f.write_str(")")
             }
+            Policy::Covenant(ref sub) => write!(f, "covenant({})", sub),
         }
     }
 }
@@ -281,15 +318,15 @@ impl<Pk: MiniscriptKey> fmt::Display for Policy<Pk> {
             Policy::Hash256(ref h) => write!(f, "hash256({})", h),
             Policy::Ripemd160(ref h) => write!(f, "ripemd160({})", h),
             Policy::Hash160(ref h) => write!(f, "hash160({})", h),
-            Policy::Threshold(k, ref subs) => {
-                if k == subs.len() {
+            Policy::Threshold(ref thresh) => {
+                if thresh.is_and() {
                     write!(f, "and(")?;
-                } else if k == 1 {
+                } else if thresh.is_or() {
                     write!(f, "or(")?;
                 } else {
-                    write!(f, "thresh({},", k)?;
+                    write!(f, "thresh({},", thresh.k())?;
                 }
-                for (i, sub) in subs.iter().enumerate() {
+                for (i, sub) in thresh.iter().enumerate() {
                     if i == 0 {
                         write!(f, "{}", sub)?;
                     } else {
@@ -298,6 +335,7 @@ impl<Pk: MiniscriptKey> fmt::Display for Policy<Pk> {
                 }
                 f.write_str(")")
             }
+            Policy::Covenant(ref sub) => write!(f, "covenant({})", sub),
         }
     }
 }
@@ -344,6 +382,7 @@ impl_from_tree!(
             ("hash160", 1) => expression::terminal(&top.args[0], |x| {
                 Pk::Hash160::from_str(x).map(Policy::Hash160)
             }),
+            ("covenant", 1) => Ok(Policy::Covenant(Box::new(Policy::from_tree(&top.args[0])?))),
             ("and", nsubs) => {
                 if nsubs < 2 {
                     return Err(Error::PolicyError(PolicyError::InsufficientArgsforAnd));
@@ -352,7 +391,7 @@ impl_from_tree!(
                 for arg in &top.args {
                     subs.push(Policy::from_tree(arg)?);
                 }
-                Ok(Policy::Threshold(nsubs, subs))
+                Ok(Policy::Threshold(Threshold::and(subs)?))
             }
             ("or", nsubs) => {
                 if nsubs < 2 {
@@ -362,7 +401,7 @@ impl_from_tree!(
                 for arg in &top.args {
                     subs.push(Policy::from_tree(arg)?);
                 }
-                Ok(Policy::Threshold(1, subs))
+                Ok(Policy::Threshold(Threshold::or(subs)?))
             }
             ("thresh", nsubs) => {
                 if nsubs == 0 || nsubs == 1 {
@@ -389,7 +428,7 @@ impl_from_tree!(
                 for arg in &top.args[1..] {
                     subs.push(Policy::from_tree(arg)?);
                 }
-                Ok(Policy::Threshold(thresh as usize, subs))
+                Ok(Policy::Threshold(Threshold::new(thresh as usize, subs)?))
             }
             _ => Err(errstr(top.name)),
         }
@@ -401,10 +440,11 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     /// `Unsatisfiable`s. Does not reorder any branches; use `.sort`.
     pub fn normalized(self) -> Policy<Pk> {
         match self {
-            Policy::Threshold(k, subs) => {
-                let mut ret_subs = Vec::with_capacity(subs.len());
+            Policy::Threshold(thresh) => {
+                let k = thresh.k();
+                let mut ret_subs = Vec::with_capacity(thresh.n());
 
-                let subs: Vec<_> = subs.into_iter().map(|sub| sub.normalized()).collect();
+                let subs: Vec<_> = thresh.into_iter().map(|sub| sub.normalized()).collect();
                 let trivial_count = subs.iter().filter(|&pol| *pol == Policy::Trivial).count();
                 let unsatisfied_count = subs
                     .iter()
@@ -419,15 +459,15 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                 for sub in subs {
                     match sub {
                         Policy::Trivial | Policy::Unsatisfiable => {}
-                        Policy::Threshold(k, subs) => {
+                        Policy::Threshold(thresh) => {
                             match (is_and, is_or) {
                                 (true, true) => {
                                     // means m = n = 1, thresh(1,X) type thing.
-                                    ret_subs.push(Policy::Threshold(k, subs));
+                                    ret_subs.push(Policy::Threshold(thresh));
                                 }
-                                (true, false) if k == subs.len() => ret_subs.extend(subs), // and case
-                                (false, true) if k == 1 => ret_subs.extend(subs), // or case
-                                _ => ret_subs.push(Policy::Threshold(k, subs)),
+                                (true, false) if thresh.is_and() => ret_subs.extend(thresh), // and case
+                                (false, true) if thresh.is_or() => ret_subs.extend(thresh), // or case
+                                _ => ret_subs.push(Policy::Threshold(thresh)),
                             }
                         }
                         x => ret_subs.push(x),
@@ -441,13 +481,17 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                 } else if ret_subs.len() == 1 {
                     ret_subs.pop().unwrap()
                 } else if is_and {
-                    Policy::Threshold(ret_subs.len(), ret_subs)
+                    Policy::Threshold(Threshold::and(ret_subs).expect("at least 2 subs"))
                 } else if is_or {
-                    Policy::Threshold(1, ret_subs)
+                    Policy::Threshold(Threshold::or(ret_subs).expect("at least 2 subs"))
                 } else {
-                    Policy::Threshold(m, ret_subs)
+                    Policy::Threshold(Threshold::new(m, ret_subs).expect("0 < m <= n"))
                 }
             }
+            Policy::Covenant(sub) => match sub.normalized() {
+                Policy::Unsatisfiable => Policy::Unsatisfiable,
+                sub => Policy::Covenant(Box::new(sub)),
+            },
             x => x,
         }
     }
@@ -480,10 +524,11 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             | Policy::Hash160(..) => vec![],
             Policy::After(..) => vec![],
             Policy::Older(t) => vec![t.to_consensus_u32()],
-            Policy::Threshold(_, ref subs) => subs.iter().fold(vec![], |mut acc, x| {
+            Policy::Threshold(ref thresh) => thresh.iter().fold(vec![], |mut acc, x| {
                 acc.extend(x.real_relative_timelocks());
                 acc
             }),
+            Policy::Covenant(ref sub) => sub.real_relative_timelocks(),
         }
     }
 
@@ -508,10 +553,11 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             | Policy::Hash160(..) => vec![],
             Policy::Older(..) => vec![],
             Policy::After(t) => vec![t.to_u32()],
-            Policy::Threshold(_, ref subs) => subs.iter().fold(vec![], |mut acc, x| {
+            Policy::Threshold(ref thresh) => thresh.iter().fold(vec![], |mut acc, x| {
                 acc.extend(x.real_absolute_timelocks());
                 acc
             }),
+            Policy::Covenant(ref sub) => sub.real_absolute_timelocks(),
         }
     }
 
@@ -529,18 +575,14 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     pub fn at_age(mut self, age: Sequence) -> Policy<Pk> {
         self = match self {
             Policy::Older(t) => {
-                if t.is_height_locked() && age.is_time_locked()
-                    || t.is_time_locked() && age.is_height_locked()
-                    || t.to_consensus_u32() > age.to_consensus_u32()
-                {
+                if !t.is_satisfied_by(age) {
                     Policy::Unsatisfiable
                 } else {
                     Policy::Older(t)
                 }
             }
-            Policy::Threshold(k, subs) => {
-                Policy::Threshold(k, subs.into_iter().map(|sub| sub.at_age(age)).collect())
-            }
+            Policy::Threshold(thresh) => Policy::Threshold(thresh.map(|sub| sub.at_age(age))),
+            Policy::Covenant(sub) => Policy::Covenant(Box::new(sub.at_age(age))),
             x => x,
         };
         self.normalized()
@@ -549,25 +591,16 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     /// Filter a policy by eliminating absolute timelock constraints
     /// that are not satisfied at the given `n` (`n OP_CHECKLOCKTIMEVERIFY`).
     pub fn at_lock_time(mut self, n: LockTime) -> Policy<Pk> {
-        use LockTime::*;
-
         self = match self {
             Policy::After(t) => {
-                let t = LockTime::from(t);
-                let is_satisfied_by = match (t, n) {
-                    (Blocks(t), Blocks(n)) => t <= n,
-                    (Seconds(t), Seconds(n)) => t <= n,
-                    _ => false,
-                };
-                if !is_satisfied_by {
+                if !t.is_satisfied_by(n) {
                     Policy::Unsatisfiable
                 } else {
-                    Policy::After(t.into())
+                    Policy::After(t)
                 }
             }
-            Policy::Threshold(k, subs) => {
-                Policy::Threshold(k, subs.into_iter().map(|sub| sub.at_lock_time(n)).collect())
-            }
+            Policy::Threshold(thresh) => Policy::Threshold(thresh.map(|sub| sub.at_lock_time(n))),
+            Policy::Covenant(sub) => Policy::Covenant(Box::new(sub.at_lock_time(n))),
             x => x,
         };
         self.normalized()
@@ -585,7 +618,8 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             | Policy::Hash256(..)
             | Policy::Ripemd160(..)
             | Policy::Hash160(..) => 0,
-            Policy::Threshold(_, ref subs) => subs.iter().map(|sub| sub.n_keys()).sum::<usize>(),
+            Policy::Threshold(ref thresh) => thresh.iter().map(|sub| sub.n_keys()).sum::<usize>(),
+            Policy::Covenant(ref sub) => sub.n_keys(),
         }
     }
 
@@ -603,9 +637,10 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
             | Policy::Hash256(..)
             | Policy::Ripemd160(..)
             | Policy::Hash160(..) => Some(0),
-            Policy::Threshold(k, ref subs) => {
+            Policy::Threshold(ref thresh) => {
+                let k = thresh.k();
                 let mut sublens: Vec<usize> =
-                    subs.iter().filter_map(Policy::minimum_n_keys).collect();
+                    thresh.iter().filter_map(Policy::minimum_n_keys).collect();
                 if sublens.len() < k {
                     // Not enough branches are satisfiable
                     None
@@ -614,10 +649,141 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
                     Some(sublens[0..k].iter().cloned().sum::<usize>())
                 }
             }
+            Policy::Covenant(ref sub) => sub.minimum_n_keys(),
+        }
+    }
+
+    /// Returns the ways of satisfying the policy, with the keys, preimages
+    /// and timelocks they require.
+    ///
+    /// Paths combining timelocks of different units, which cannot be
+    /// satisfied, are omitted. The number of paths is exponential in the
+    /// number of thresholds of the policy.
+    pub fn spend_paths(&self) -> Vec<SpendPath<Pk>> {
+        let mut paths = self.real_spend_paths();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Helper function to do the recursion in `spend_paths`.
+    fn real_spend_paths(&self) -> Vec<SpendPath<Pk>> {
+        let path = SpendPath::empty();
+        match *self {
+            Policy::Unsatisfiable => vec![],
+            Policy::Trivial => vec![path],
+            Policy::Key(ref pk) => vec![SpendPath {
+                keys: vec![pk.clone()],
+                ..path
+            }],
+            Policy::After(t) => vec![SpendPath {
+                after: Some(t),
+                ..path
+            }],
+            Policy::Older(t) => vec![SpendPath {
+                older: Some(t),
+                ..path
+            }],
+            Policy::Sha256(..)
+            | Policy::Hash256(..)
+            | Policy::Ripemd160(..)
+            | Policy::Hash160(..) => vec![SpendPath {
+                hashes: vec![self.clone()],
+                ..path
+            }],
+            Policy::Threshold(ref thresh) => {
+                let subs: Vec<_> = thresh.iter().map(Policy::real_spend_paths).collect();
+                let mut paths = vec![];
+                threshold_spend_paths(thresh.k(), &subs, path, &mut paths);
+                paths
+            }
+            Policy::Covenant(ref sub) => sub
+                .real_spend_paths()
+                .into_iter()
+                .map(|path| SpendPath {
+                    covenant: true,
+                    ..path
+                })
+                .collect(),
         }
     }
 }
 
+// Pushes the combinations of `path` with paths of `k` of the `subs`
+fn threshold_spend_paths<Pk: MiniscriptKey>(
+    k: usize,
+    subs: &[Vec<SpendPath<Pk>>],
+    path: SpendPath<Pk>,
+    paths: &mut Vec<SpendPath<Pk>>,
+) {
+    if k == 0 {
+        paths.push(path);
+    } else if subs.len() >= k {
+        for sub_path in &subs[0] {
+            if let Some(combined) = path.combine(sub_path) {
+                threshold_spend_paths(k - 1, &subs[1..], combined, paths);
+            }
+        }
+        threshold_spend_paths(k, &subs[1..], path, paths);
+    }
+}
+
+/// A way of satisfying a semantic policy, see [`Policy::spend_paths`]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SpendPath<Pk: MiniscriptKey> {
+    /// The keys which must sign, sorted
+    pub keys: Vec<Pk>,
+    /// The hash fragments whose preimages must be revealed, sorted
+    pub hashes: Vec<Policy<Pk>>,
+    /// The earliest block height or time at which the path can be used, if
+    /// it has an absolute timelock
+    pub after: Option<AbsLockTime>,
+    /// The number of blocks or of intervals of 512 seconds after the
+    /// confirmation of the spent output from which the path can be used, if
+    /// it has a relative timelock
+    pub older: Option<RelLockTime>,
+    /// Whether the spending transaction must also meet a covenant
+    pub covenant: bool,
+}
+
+impl<Pk: MiniscriptKey> SpendPath<Pk> {
+    fn empty() -> Self {
+        SpendPath {
+            keys: vec![],
+            hashes: vec![],
+            after: None,
+            older: None,
+            covenant: false,
+        }
+    }
+
+    // The path requiring what both paths require, if its timelocks have the
+    // same units
+    fn combine(&self, other: &Self) -> Option<Self> {
+        let after = match (self.after, other.after) {
+            (Some(a), Some(b)) if !a.is_same_unit(b) => return None,
+            (a, b) => a.max(b),
+        };
+        let older = match (self.older, other.older) {
+            (Some(a), Some(b)) if !a.is_same_unit(b) => return None,
+            (a, b) => a.max(b),
+        };
+        let mut keys: Vec<_> = self.keys.iter().chain(&other.keys).cloned().collect();
+        keys.sort();
+        keys.dedup();
+        let mut hashes: Vec<_> = self.hashes.iter().chain(&other.hashes).cloned().collect();
+        hashes.sort();
+        hashes.dedup();
+        Some(SpendPath {
+            keys,
+            hashes,
+            after,
+            older,
+            covenant: self.covenant || other.covenant,
+        })
+    }
+}
+
 impl<Pk: MiniscriptKey> Policy<Pk> {
     /// "Sort" a policy to bring it into a canonical form to allow comparisons.
     /// Does **not** allow policies to be compared for functional equivalence;
@@ -625,11 +791,13 @@ impl<Pk: MiniscriptKey> Policy<Pk> {
     /// implemented.
     pub fn sorted(self) -> Policy<Pk> {
         match self {
-            Policy::Threshold(k, subs) => {
-                let mut new_subs: Vec<_> = subs.into_iter().map(Policy::sorted).collect();
+            Policy::Threshold(thresh) => {
+                let k = thresh.k();
+                let mut new_subs: Vec<_> = thresh.into_iter().map(Policy::sorted).collect();
                 new_subs.sort();
-                Policy::Threshold(k, new_subs)
+                Policy::Threshold(Threshold::new(k, new_subs).expect("same k and n"))
             }
+            Policy::Covenant(sub) => Policy::Covenant(Box::new(sub.sorted())),
             x => x,
         }
     }
@@ -645,6 +813,48 @@ mod tests {
 
     type StringPolicy = Policy<String>;
 
+    #[test]
+    fn spend_paths() {
+        let path = |keys: &[&str], after: Option<u32>, older: Option<u16>| SpendPath {
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            hashes: vec![],
+            after: after.map(AbsLockTime::from_consensus),
+            older: older.map(RelLockTime::from_height),
+            covenant: false,
+        };
+
+        let policy = StringPolicy::from_str("thresh(2,pk(A),pk(B),after(100))").unwrap();
+        assert_eq!(
+            policy.spend_paths(),
+            vec![
+                path(&["A"], Some(100), None),
+                path(&["A", "B"], None, None),
+                path(&["B"], Some(100), None),
+            ]
+        );
+
+        // A recovery path, and a path which can never be satisfied
+        let policy = StringPolicy::from_str(
+            "or(and(pk(A),pk(B)),or(and(pk(A),and(older(144),older(1000))),\
+             and(pk(C),and(after(1000),after(1700000000)))))",
+        )
+        .unwrap();
+        assert_eq!(
+            policy.spend_paths(),
+            vec![
+                path(&["A"], None, Some(1000)),
+                path(&["A", "B"], None, None)
+            ]
+        );
+
+        let policy = StringPolicy::from_str("and(pk(A),sha256(H))").unwrap();
+        assert_eq!(
+            policy.spend_paths()[0].hashes,
+            vec![StringPolicy::from_str("sha256(H)").unwrap()]
+        );
+        assert!(StringPolicy::Unsatisfiable.spend_paths().is_empty());
+    }
+
     #[test]
     fn parse_policy_err() {
         assert!(StringPolicy::from_str("(").is_err());
@@ -681,7 +891,7 @@ mod tests {
         assert_eq!(policy.minimum_n_keys(), Some(1));
 
         let policy = StringPolicy::from_str("older(1000)").unwrap();
-        assert_eq!(policy, Policy::Older(Sequence::from_height(1000)));
+        assert_eq!(policy, Policy::Older(RelLockTime::from_height(1000)));
         assert!(policy.absolute_timelocks().is_empty());
         assert_eq!(policy.relative_timelocks(), vec![1000]);
         assert_eq!(policy.clone().at_age(Sequence::ZERO), Policy::Unsatisfiable);
@@ -698,11 +908,14 @@ mod tests {
         assert_eq!(
             policy,
             Policy::Threshold(
-                1,
-                vec![
-                    Policy::Key("".to_owned()),
-                    Policy::Older(Sequence::from_height(1000)),
-                ]
+                Threshold::new(
+                    1,
+                    vec![
+                        Policy::Key("".to_owned()),
+                        Policy::Older(RelLockTime::from_height(1000)),
+                    ]
+                )
+                .unwrap()
             )
         );
         assert_eq!(policy.relative_timelocks(), vec![1000]);
@@ -729,7 +942,9 @@ mod tests {
         let policy = StringPolicy::from_str("or(pk(),UNSATISFIABLE)").unwrap();
         assert_eq!(
             policy,
-            Policy::Threshold(1, vec![Policy::Key("".to_owned()), Policy::Unsatisfiable,])
+            Policy::Threshold(
+                Threshold::or(vec![Policy::Key("".to_owned()), Policy::Unsatisfiable]).unwrap()
+            )
         );
         assert_eq!(policy.relative_timelocks().len(), 0);
         assert_eq!(policy.absolute_timelocks().len(), 0);
@@ -739,7 +954,9 @@ mod tests {
         let policy = StringPolicy::from_str("and(pk(),UNSATISFIABLE)").unwrap();
         assert_eq!(
             policy,
-            Policy::Threshold(2, vec![Policy::Key("".to_owned()), Policy::Unsatisfiable,])
+            Policy::Threshold(
+                Threshold::and(vec![Policy::Key("".to_owned()), Policy::Unsatisfiable]).unwrap()
+            )
         );
         assert_eq!(policy.relative_timelocks().len(), 0);
         assert_eq!(policy.absolute_timelocks().len(), 0);
@@ -755,14 +972,17 @@ mod tests {
         assert_eq!(
             policy,
             Policy::Threshold(
-                2,
-                vec![
-                    Policy::Older(Sequence::from_height(1000)),
-                    Policy::Older(Sequence::from_height(10000)),
-                    Policy::Older(Sequence::from_height(1000)),
-                    Policy::Older(Sequence::from_height(2000)),
-                    Policy::Older(Sequence::from_height(2000)),
-                ]
+                Threshold::new(
+                    2,
+                    vec![
+                        Policy::Older(RelLockTime::from_height(1000)),
+                        Policy::Older(RelLockTime::from_height(10000)),
+                        Policy::Older(RelLockTime::from_height(1000)),
+                        Policy::Older(RelLockTime::from_height(2000)),
+                        Policy::Older(RelLockTime::from_height(2000)),
+                    ]
+                )
+                .unwrap()
             )
         );
         assert_eq!(
@@ -779,14 +999,17 @@ mod tests {
         assert_eq!(
             policy,
             Policy::Threshold(
-                2,
-                vec![
-                    Policy::Older(Sequence::from_height(1000)),
-                    Policy::Older(Sequence::from_height(10000)),
-                    Policy::Older(Sequence::from_height(1000)),
-                    Policy::Unsatisfiable,
-                    Policy::Unsatisfiable,
-                ]
+                Threshold::new(
+                    2,
+                    vec![
+                        Policy::Older(RelLockTime::from_height(1000)),
+                        Policy::Older(RelLockTime::from_height(10000)),
+                        Policy::Older(RelLockTime::from_height(1000)),
+                        Policy::Unsatisfiable,
+                        Policy::Unsatisfiable,
+                    ]
+                )
+                .unwrap()
             )
         );
         assert_eq!(
@@ -897,7 +1120,8 @@ mod tests {
             "or(and(older(4096),thresh(2,pk(A),pk(B),pk(C))),thresh(11,pk(F1),pk(F2),pk(F3),pk(F4),pk(F5),pk(F6),pk(F7),pk(F8),pk(F9),pk(F10),pk(F11),pk(F12),pk(F13),pk(F14)))").unwrap();
         // Very bad idea to add master key,pk but let's have it have 50M blocks
         let master_key = StringPolicy::from_str("and(older(50000000),pk(master))").unwrap();
-        let new_liquid_pol = Policy::Threshold(1, vec![liquid_pol.clone(), master_key]);
+        let new_liquid_pol =
+            Policy::Threshold(Threshold::or(vec![liquid_pol.clone(), master_key]).unwrap());
 
         assert!(liquid_pol.clone().entails(new_liquid_pol.clone()).unwrap());
         assert!(!new_liquid_pol.entails(liquid_pol.clone()).unwrap());
@@ -0,0 +1,200 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Blinding Audit
+//!
+//! Checks, before signing a PSET, that its outputs are blinded as required,
+//! so that a wallet does not accidentally reveal the amounts and assets sent
+//! to third parties. See [`blinding_audit`].
+//!
+
+use std::collections::BTreeSet;
+
+use elements::pset::PartiallySignedTransaction as Psbt;
+use elements::{Script, VerificationError};
+
+use super::finalizer;
+
+/// Which outputs of a PSET may be explicit, see [`blinding_audit`]
+///
+/// All the outputs except the fee output must be blinded, unless their
+/// script pubkey is allowed to be explicit.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct BlindingPolicy {
+    /// The script pubkeys of the outputs which may be explicit, e.g. the
+    /// outputs of the wallet itself
+    pub explicit_allowed: BTreeSet<Script>,
+}
+
+/// The blinding of an output of a PSET
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum OutputBlinding {
+    /// The fee output, with an empty script pubkey
+    Fee,
+    /// The output is blinded, with its commitments and proofs
+    Blinded,
+    /// The output has a blinding key but is not blinded yet, or only partly
+    MarkedForBlinding,
+    /// The output is explicit
+    Explicit,
+}
+
+/// The check of the value commitments of a PSET, see [`BlindingAudit`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BalanceCheck {
+    /// No output has a value commitment, so there is nothing to check
+    NoCommitments,
+    /// The commitments of the inputs and outputs balance and the proofs of
+    /// the outputs are valid
+    Balanced,
+    /// The transaction or the utxos of the inputs are missing from the PSET
+    Unavailable,
+    /// The commitments do not balance, or a proof is invalid
+    Failed(VerificationError),
+}
+
+/// An output of a PSET not blinded as required, see [`BlindingAudit`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BlindingViolation {
+    /// The output at the index is not blinded and not allowed to be
+    /// explicit by the policy
+    NotBlinded(usize),
+    /// The fee output at the index is blinded or marked for blinding
+    ConfidentialFee(usize),
+}
+
+/// The result of [`blinding_audit`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlindingAudit {
+    /// The blinding of each output
+    pub outputs: Vec<OutputBlinding>,
+    /// The outputs not blinded as required
+    pub violations: Vec<BlindingViolation>,
+    /// The check of the value commitments
+    pub balance: BalanceCheck,
+}
+
+impl BlindingAudit {
+    /// Whether the outputs are blinded as required and, if there are value
+    /// commitments, they balance
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+            && matches!(
+                self.balance,
+                BalanceCheck::NoCommitments | BalanceCheck::Balanced
+            )
+    }
+}
+
+/// Audits the blinding of the outputs of `pset`.
+///
+/// Every output, except the fee output and the outputs allowed by `policy`,
+/// must be fully blinded, and the fee output must be explicit. When outputs
+/// are blinded, the commitments of the inputs and outputs must balance and
+/// the proofs of the outputs must be valid, which requires the utxos of all
+/// the inputs.
+pub fn blinding_audit(pset: &Psbt, policy: &BlindingPolicy) -> BlindingAudit {
+    let mut outputs = Vec::with_capacity(pset.outputs().len());
+    let mut violations = vec![];
+    for (i, out) in pset.outputs().iter().enumerate() {
+        let blinding = if out.script_pubkey.is_empty() {
+            if out.is_marked_for_blinding() || out.amount_comm.is_some() || out.asset_comm.is_some()
+            {
+                violations.push(BlindingViolation::ConfidentialFee(i));
+            }
+            OutputBlinding::Fee
+        } else if out.is_fully_blinded() {
+            OutputBlinding::Blinded
+        } else if out.is_marked_for_blinding() {
+            OutputBlinding::MarkedForBlinding
+        } else {
+            OutputBlinding::Explicit
+        };
+        if (blinding == OutputBlinding::MarkedForBlinding || blinding == OutputBlinding::Explicit)
+            && !policy.explicit_allowed.contains(&out.script_pubkey)
+        {
+            violations.push(BlindingViolation::NotBlinded(i));
+        }
+        outputs.push(blinding);
+    }
+
+    let balance = if pset.outputs().iter().all(|out| out.amount_comm.is_none()) {
+        BalanceCheck::NoCommitments
+    } else {
+        match (pset.extract_tx(), finalizer::prevouts(pset)) {
+            (Ok(tx), Ok(utxos)) => {
+                match crate::with_secp(|secp| tx.verify_tx_amt_proofs(secp, &utxos)) {
+                    Ok(()) => BalanceCheck::Balanced,
+                    Err(e) => BalanceCheck::Failed(e),
+                }
+            }
+            _ => BalanceCheck::Unavailable,
+        }
+    };
+
+    BlindingAudit {
+        outputs,
+        violations,
+        balance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::pset;
+    use elements::AssetId;
+
+    use super::*;
+
+    #[test]
+    fn blinding_audit_explicit_outputs() {
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+        let blinding_key = bitcoin::PublicKey::from_str(
+            "0331c7d3fc85c148717848033ce276ae2b464a4e2c367ed33886cc428b8af48ff8",
+        )
+        .unwrap();
+        let third_party = Script::from(vec![0x51]);
+        let change = Script::from(vec![0x52]);
+        let output =
+            |spk: &Script, value| pset::Output::new_explicit(spk.clone(), value, asset, None);
+
+        let mut pset = Psbt::new_v2();
+        pset.add_output(output(&third_party, 1_000));
+        pset.add_output(output(&change, 500));
+        pset.add_output(output(&Script::new(), 10));
+        let mut policy = BlindingPolicy::default();
+        policy.explicit_allowed.insert(change.clone());
+
+        let audit = blinding_audit(&pset, &policy);
+        assert_eq!(
+            audit.outputs,
+            vec![
+                OutputBlinding::Explicit,
+                OutputBlinding::Explicit,
+                OutputBlinding::Fee
+            ]
+        );
+        assert_eq!(audit.violations, vec![BlindingViolation::NotBlinded(0)]);
+        assert_eq!(audit.balance, BalanceCheck::NoCommitments);
+        assert!(!audit.is_ok());
+
+        // Marking the outputs for blinding is not enough, and the fee must
+        // stay explicit
+        pset.outputs_mut()[0].blinding_key = Some(blinding_key);
+        pset.outputs_mut()[2].blinding_key = Some(blinding_key);
+        let audit = blinding_audit(&pset, &policy);
+        assert_eq!(audit.outputs[0], OutputBlinding::MarkedForBlinding);
+        assert_eq!(
+            audit.violations,
+            vec![
+                BlindingViolation::NotBlinded(0),
+                BlindingViolation::ConfidentialFee(2)
+            ]
+        );
+
+        policy.explicit_allowed.insert(third_party);
+        pset.outputs_mut()[2].blinding_key = None;
+        assert!(blinding_audit(&pset, &policy).is_ok());
+    }
+}
@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # PSET Combiner
+//!
+//! Merging of PSETs updated, signed or blinded by different parties. The
+//! merge of `elements` keeps the value of one of the PSETs when both have a
+//! different one for the same field, which silently drops data such as a
+//! signature or a rangeproof; [`combine`] reports these conflicts instead.
+//!
+
+use std::collections::BTreeMap;
+use std::{error, fmt};
+
+use elements::pset::{self, PartiallySignedTransaction as Psbt};
+
+/// Error when combining two PSETs
+#[derive(Debug)]
+pub enum CombineError {
+    /// The PSETs are not for the same transaction
+    TxMismatch,
+    /// The PSETs have different values for a field of an input
+    InputConflict {
+        /// Index of the input
+        index: usize,
+        /// Name of the field
+        field: &'static str,
+    },
+    /// The PSETs have different values for a field of an output
+    OutputConflict {
+        /// Index of the output
+        index: usize,
+        /// Name of the field
+        field: &'static str,
+    },
+    /// Upstream error, when computing the transaction of a PSET or merging
+    /// the global fields
+    Pset(pset::Error),
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CombineError::TxMismatch => f.write_str("PSETs are not for the same transaction"),
+            CombineError::InputConflict { index, field } => {
+                write!(f, "Conflicting {} for input {}", field, index)
+            }
+            CombineError::OutputConflict { index, field } => {
+                write!(f, "Conflicting {} for output {}", field, index)
+            }
+            CombineError::Pset(ref e) => write!(f, "Pset Error {}", e),
+        }
+    }
+}
+
+impl error::Error for CombineError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match self {
+            CombineError::TxMismatch
+            | CombineError::InputConflict { .. }
+            | CombineError::OutputConflict { .. } => None,
+            CombineError::Pset(e) => Some(e),
+        }
+    }
+}
+
+#[doc(hidden)]
+impl From<pset::Error> for CombineError {
+    fn from(e: pset::Error) -> Self {
+        CombineError::Pset(e)
+    }
+}
+
+// Whether both options are set to different values
+fn conflicting_opt<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    }
+}
+
+// Whether both maps have different values for the same key
+fn conflicting_map<K: Ord, V: PartialEq>(a: &BTreeMap<K, V>, b: &BTreeMap<K, V>) -> bool {
+    a.iter()
+        .any(|(k, v)| b.get(k).map_or(false, |other| other != v))
+}
+
+macro_rules! check_conflicts {
+    ($a:expr, $b:expr, $err:expr, [$($opt:ident),* $(,)?], [$($map:ident),* $(,)?]) => {
+        $(
+            if conflicting_opt(&$a.$opt, &$b.$opt) {
+                return Err($err(stringify!($opt)));
+            }
+        )*
+        $(
+            if conflicting_map(&$a.$map, &$b.$map) {
+                return Err($err(stringify!($map)));
+            }
+        )*
+    };
+}
+
+/// Checks that `b` can be merged into `a` without any conflict
+pub(super) fn check_combine(a: &Psbt, b: &Psbt) -> Result<(), CombineError> {
+    if a.unique_id()? != b.unique_id()? {
+        return Err(CombineError::TxMismatch);
+    }
+
+    for (index, (a, b)) in a.inputs().iter().zip(b.inputs()).enumerate() {
+        let err = |field| CombineError::InputConflict { index, field };
+        check_conflicts!(
+            a,
+            b,
+            err,
+            [
+                non_witness_utxo,
+                witness_utxo,
+                sighash_type,
+                redeem_script,
+                witness_script,
+                final_script_sig,
+                final_script_witness,
+                tap_key_sig,
+                tap_internal_key,
+                tap_merkle_root,
+                issuance_value_rangeproof,
+                issuance_keys_rangeproof,
+                pegin_tx,
+                pegin_txout_proof,
+                pegin_genesis_hash,
+                pegin_claim_script,
+                pegin_value,
+                pegin_witness,
+                issuance_blinding_nonce,
+                in_utxo_rangeproof,
+                in_issuance_blind_value_proof,
+                in_issuance_blind_inflation_keys_proof,
+            ],
+            [
+                partial_sigs,
+                bip32_derivation,
+                ripemd160_preimages,
+                sha256_preimages,
+                hash160_preimages,
+                hash256_preimages,
+                tap_script_sigs,
+                tap_scripts,
+                tap_key_origins,
+                proprietary,
+                unknown,
+            ]
+        );
+    }
+
+    for (index, (a, b)) in a.outputs().iter().zip(b.outputs()).enumerate() {
+        let err = |field| CombineError::OutputConflict { index, field };
+        check_conflicts!(
+            a,
+            b,
+            err,
+            [
+                redeem_script,
+                witness_script,
+                tap_internal_key,
+                tap_tree,
+                amount,
+                asset,
+                value_rangeproof,
+                asset_surjection_proof,
+                blinding_key,
+                ecdh_pubkey,
+                blinder_index,
+                blind_value_proof,
+                blind_asset_proof,
+            ],
+            [bip32_derivation, tap_key_origins, proprietary, unknown]
+        );
+    }
+    Ok(())
+}
+
+/// Combines two PSETs of the same transaction, e.g. returned by different
+/// cosigners or blinding parties, into a PSET with the data of both.
+///
+/// # Errors
+///
+/// [`CombineError::TxMismatch`] if the PSETs are not for the same
+/// transaction, or a [`CombineError::InputConflict`] or
+/// [`CombineError::OutputConflict`] for the first field of an input or output
+/// which is set to a different value in each PSET, such as the signature of a
+/// key, a script or the blinding data of an output.
+pub fn combine(mut a: Psbt, b: Psbt) -> Result<Psbt, CombineError> {
+    check_combine(&a, &b)?;
+    a.merge(b)?;
+    Ok(a)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::{AssetId, OutPoint};
+
+    use super::*;
+    use crate::psbt::PsbtExt;
+    use crate::{DefiniteDescriptorKey, Descriptor};
+
+    #[test]
+    fn combine_psets() {
+        let desc = Descriptor::<DefiniteDescriptorKey>::from_str(
+            "elwsh(multi(2,\
+             02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13,\
+             022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4))",
+        )
+        .unwrap();
+        let pks = [
+            "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13",
+            "022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4",
+        ]
+        .iter()
+        .map(|s| bitcoin::PublicKey::from_str(s).unwrap())
+        .collect::<Vec<_>>();
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+
+        let mut base = Psbt::new_v2();
+        base.add_descriptor_input(OutPoint::default(), desc.txout(1_000, asset, None), &desc)
+            .unwrap();
+        base.add_output(pset::Output::from_txout(desc.txout(900, asset, None)));
+
+        let mut a = base.clone();
+        a.inputs_mut()[0].partial_sigs.insert(pks[0], vec![1]);
+        let mut b = base.clone();
+        b.inputs_mut()[0].partial_sigs.insert(pks[1], vec![2]);
+        let combined = combine(a.clone(), b.clone()).unwrap();
+        assert_eq!(combined.inputs()[0].partial_sigs.len(), 2);
+
+        // Different signatures for the same key
+        b.inputs_mut()[0].partial_sigs.insert(pks[0], vec![3]);
+        assert!(matches!(
+            combine(a.clone(), b),
+            Err(CombineError::InputConflict {
+                index: 0,
+                field: "partial_sigs"
+            })
+        ));
+
+        // Different blinding keys for the same output
+        let mut b = base.clone();
+        a.outputs_mut()[0].blinding_key = Some(pks[0]);
+        b.outputs_mut()[0].blinding_key = Some(pks[1]);
+        assert!(matches!(
+            combine(a.clone(), b),
+            Err(CombineError::OutputConflict {
+                index: 0,
+                field: "blinding_key"
+            })
+        ));
+
+        // Different transactions
+        let mut b = base;
+        b.add_output(pset::Output::from_txout(desc.txout(100, asset, None)));
+        assert!(matches!(combine(a, b), Err(CombineError::TxMismatch)));
+    }
+}
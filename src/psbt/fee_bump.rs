@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Fee Bumping
+//!
+//! Replacement of a PSET paying a higher fee, taken from its change output.
+//! The fee is computed from an upper bound of the weight of the final
+//! transaction, using the satisfaction weight of every input, as inferred
+//! from its PSET fields, and accounting for the blinding of the change.
+//!
+
+use std::{cmp, error, fmt};
+
+use bitcoin::key::XOnlyPublicKey;
+use elements::pset::PartiallySignedTransaction as Psbt;
+
+use super::finalizer::{get_descriptor, get_scriptpubkey};
+use super::InputError;
+use crate::descriptor::coin_selection::fee;
+use crate::extensions::{CovExtArgs, CovenantExt};
+use crate::util::{self, varint_len};
+use crate::{DefiniteDescriptorKey, Descriptor, ExtParams, Miniscript, Tap};
+
+/// Length of the rangeproof of a blinded output, proving a 52 bits value
+/// with a minimum of 1, as created by `elements`
+const RANGEPROOF_LEN: usize = 4174;
+
+/// Error when bumping the fee of a PSET
+#[derive(Debug)]
+pub enum FeeBumpError {
+    /// The PSET has no explicit fee output
+    MissingFeeOutput,
+    /// The PSET has no output to the change descriptor in the asset of the
+    /// fee
+    MissingChangeOutput,
+    /// The satisfaction weight of an input cannot be computed
+    InputError(InputError, usize),
+    /// The new fee is not higher than the current one
+    FeeNotIncreased {
+        /// The current fee
+        fee: u64,
+        /// The fee at the new feerate
+        new_fee: u64,
+    },
+    /// The change output does not have enough value to pay the new fee
+    InsufficientChange {
+        /// The value of the change output
+        change: u64,
+        /// The fee increase
+        needed: u64,
+    },
+    /// Upstream error, when computing the transaction of the PSET
+    Pset(elements::pset::Error),
+}
+
+impl fmt::Display for FeeBumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FeeBumpError::MissingFeeOutput => f.write_str("PSET has no explicit fee output"),
+            FeeBumpError::MissingChangeOutput => f.write_str("PSET has no change output"),
+            FeeBumpError::InputError(ref e, index) => write!(f, "{} at index {}", e, index),
+            FeeBumpError::FeeNotIncreased { fee, new_fee } => write!(
+                f,
+                "New fee {} is not higher than the current fee {}",
+                new_fee, fee
+            ),
+            FeeBumpError::InsufficientChange { change, needed } => write!(
+                f,
+                "Change of {} cannot pay the fee increase of {}",
+                change, needed
+            ),
+            FeeBumpError::Pset(ref e) => write!(f, "Pset Error {}", e),
+        }
+    }
+}
+
+impl error::Error for FeeBumpError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match self {
+            FeeBumpError::MissingFeeOutput
+            | FeeBumpError::MissingChangeOutput
+            | FeeBumpError::FeeNotIncreased { .. }
+            | FeeBumpError::InsufficientChange { .. } => None,
+            FeeBumpError::InputError(e, _) => Some(e),
+            FeeBumpError::Pset(e) => Some(e),
+        }
+    }
+}
+
+#[doc(hidden)]
+impl From<elements::pset::Error> for FeeBumpError {
+    fn from(e: elements::pset::Error) -> Self {
+        FeeBumpError::Pset(e)
+    }
+}
+
+// Upper bound of the weight added by satisfying the input at `index`
+fn input_weight(psbt: &Psbt, index: usize) -> Result<usize, InputError> {
+    let spk = get_scriptpubkey(psbt, index)?;
+    if !util::is_v1_p2tr(spk) {
+        return Ok(get_descriptor(psbt, index)?.max_weight_to_satisfy()?);
+    }
+    // key spend: varint(sig+sigHash) + <sig(64)+sigHash(1)>
+    let mut weight = 1 + 65;
+    for (control_block, (script, _)) in &psbt.inputs()[index].tap_scripts {
+        let ms = Miniscript::<XOnlyPublicKey, Tap, CovenantExt<CovExtArgs>>::parse_with_ext(
+            script,
+            &ExtParams::allow_all(),
+        )?;
+        let max_sat_elems = ms.max_satisfaction_witness_elements()?;
+        let max_sat_size = ms.max_satisfaction_size()?;
+        let control_block_size = control_block.size();
+        // See `Tr::max_weight_to_satisfy`
+        let leaf_weight = varint_len(max_sat_elems + 1) - varint_len(0)
+            + max_sat_size
+            + varint_len(script.len())
+            + script.len()
+            + varint_len(control_block_size)
+            + control_block_size;
+        weight = cmp::max(weight, leaf_weight);
+    }
+    Ok(weight)
+}
+
+// Weight added to an output by blinding it, with a surjection proof over at
+// most `n_inputs` inputs
+fn blinding_weight(n_inputs: usize) -> usize {
+    // value commitment of 33 bytes instead of 9, nonce of 33 bytes instead
+    // of 1
+    let commitments = 4 * (24 + 32);
+    let surjection_proof_len = 2 + (n_inputs + 7) / 8 + 32 * (1 + n_inputs);
+    commitments
+        + varint_len(RANGEPROOF_LEN)
+        + RANGEPROOF_LEN
+        + varint_len(surjection_proof_len)
+        + surjection_proof_len
+        - 2 * varint_len(0)
+}
+
+/// Replaces the PSET with one paying `new_feerate` satoshis per kvB, taking
+/// the fee increase from the output to `change_descriptor`.
+///
+/// The fee is computed for an upper bound of the weight of the transaction,
+/// with the maximum satisfaction weight of every input, as inferred from its
+/// PSET fields, so the inputs must have been updated with their descriptor.
+///
+/// All the signatures and final witnesses are removed, since they commit to
+/// the outputs. The other outputs keep their blinding data, while the change
+/// output keeps its blinding key and blinder index but loses its
+/// commitments and proofs: if it is confidential, it must be blinded again,
+/// as the last blinded output.
+pub fn bump_fee(
+    psbt: &Psbt,
+    new_feerate: u64,
+    change_descriptor: &Descriptor<DefiniteDescriptorKey>,
+) -> Result<Psbt, FeeBumpError> {
+    let fee_idx = psbt
+        .outputs()
+        .iter()
+        .position(|o| o.script_pubkey.is_empty() && o.amount.is_some() && o.asset.is_some())
+        .ok_or(FeeBumpError::MissingFeeOutput)?;
+    let fee_asset = psbt.outputs()[fee_idx].asset;
+    let change_spk = change_descriptor.script_pubkey();
+    let change_idx = psbt
+        .outputs()
+        .iter()
+        .position(|o| o.script_pubkey == change_spk && o.asset == fee_asset && o.amount.is_some())
+        .ok_or(FeeBumpError::MissingChangeOutput)?;
+
+    let mut psbt = psbt.clone();
+    for input in psbt.inputs_mut() {
+        // The key of pkh and wpkh inputs is looked up among the signatures,
+        // which are removed below anyway
+        let keys = input.bip32_derivation.keys().copied().collect::<Vec<_>>();
+        for pk in keys {
+            input.partial_sigs.entry(pk).or_default();
+        }
+    }
+    let satisfaction_weight = (0..psbt.inputs().len())
+        .map(|index| input_weight(&psbt, index).map_err(|e| FeeBumpError::InputError(e, index)))
+        .sum::<Result<usize, _>>()?;
+
+    for input in psbt.inputs_mut() {
+        input.partial_sigs.clear();
+        input.tap_key_sig = None;
+        input.tap_script_sigs.clear();
+        input.final_script_sig = None;
+        input.final_script_witness = None;
+    }
+    let n_inputs = psbt.inputs().len();
+    let change = &mut psbt.outputs_mut()[change_idx];
+    let blinded_change = change.blinding_key.is_some();
+    change.amount_comm = None;
+    change.asset_comm = None;
+    change.value_rangeproof = None;
+    change.asset_surjection_proof = None;
+    change.ecdh_pubkey = None;
+    change.blind_value_proof = None;
+    change.blind_asset_proof = None;
+
+    let tx = psbt.extract_tx()?;
+    let mut weight = tx.weight() + satisfaction_weight;
+    if !tx.has_witness() {
+        // empty witnesses of the inputs and outputs
+        weight += 4 * tx.input.len() + 2 * tx.output.len();
+    }
+    if blinded_change {
+        weight += blinding_weight(n_inputs);
+    }
+
+    let old_fee = psbt.outputs()[fee_idx]
+        .amount
+        .expect("Fee output is explicit");
+    let new_fee = fee(weight, new_feerate);
+    if new_fee <= old_fee {
+        return Err(FeeBumpError::FeeNotIncreased {
+            fee: old_fee,
+            new_fee,
+        });
+    }
+    let needed = new_fee - old_fee;
+    let change = psbt.outputs()[change_idx]
+        .amount
+        .expect("Change output is explicit");
+    if change <= needed {
+        return Err(FeeBumpError::InsufficientChange { change, needed });
+    }
+    psbt.outputs_mut()[change_idx].amount = Some(change - needed);
+    psbt.outputs_mut()[fee_idx].amount = Some(new_fee);
+    Ok(psbt)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::{pset, AssetId, OutPoint, TxOut};
+
+    use super::*;
+    use crate::psbt::PsbtExt;
+
+    #[test]
+    fn bump_fee_from_change() {
+        let desc = Descriptor::<DefiniteDescriptorKey>::from_str(
+            "elwpkh(02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13)",
+        )
+        .unwrap();
+        let change_desc = Descriptor::<DefiniteDescriptorKey>::from_str(
+            "elwpkh(022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4)",
+        )
+        .unwrap();
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+
+        let mut psbt = Psbt::new_v2();
+        psbt.add_descriptor_input(OutPoint::default(), desc.txout(10_000, asset, None), &desc)
+            .unwrap();
+        psbt.add_output(pset::Output::from_txout(desc.txout(5_000, asset, None)));
+        psbt.add_output(pset::Output::from_txout(
+            change_desc.txout(4_900, asset, None),
+        ));
+        psbt.add_output(pset::Output::from_txout(TxOut::new_fee(100, asset)));
+
+        let bumped = bump_fee(&psbt, 1_000, &change_desc).unwrap();
+        let new_fee = bumped.outputs()[2].amount.unwrap();
+        assert!(new_fee > 100);
+        assert_eq!(bumped.outputs()[0].amount, Some(5_000));
+        assert_eq!(bumped.outputs()[1].amount, Some(5_000 - new_fee));
+        // The fee covers the weight of the signed transaction
+        let weight = bumped.extract_tx().unwrap().weight()
+            + 4
+            + 2 * 3
+            + desc.max_weight_to_satisfy().unwrap();
+        assert_eq!(new_fee, fee(weight, 1_000));
+
+        assert!(matches!(
+            bump_fee(&bumped, 1_000, &change_desc),
+            Err(FeeBumpError::FeeNotIncreased { .. })
+        ));
+        assert!(matches!(
+            bump_fee(&psbt, 1_000_000, &change_desc),
+            Err(FeeBumpError::InsufficientChange { change: 4_900, .. })
+        ));
+        let other_desc = Descriptor::<DefiniteDescriptorKey>::from_str(
+            "elwpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556)",
+        )
+        .unwrap();
+        assert!(matches!(
+            bump_fee(&psbt, 1_000, &other_desc),
+            Err(FeeBumpError::MissingChangeOutput)
+        ));
+    }
+}
@@ -11,15 +11,16 @@
 use bitcoin::key::XOnlyPublicKey;
 use bitcoin::{self, PublicKey};
 use elements::secp256k1_zkp::{self, Secp256k1};
+use elements::sighash::SigHashCache;
 use elements::taproot::LeafVersion;
 use elements::{self, confidential, Script, Sequence, Transaction, TxOut};
 
-use super::{sanity_check, Error, InputError, Psbt, PsbtInputSatisfier};
+use super::{sanity_check, Error, InputError, Psbt, PsbtExt, PsbtInputSatisfier, TimelockPlan};
 use crate::descriptor::{LegacyCSFSCov, LegacyCovSatisfier};
 use crate::extensions::{CovExtArgs, TxEnv};
 use crate::{
-    interpreter, util, BareCtx, CovenantExt, Descriptor, ExtParams, Legacy, Miniscript, Satisfier,
-    Segwitv0, SigType, Tap, ToPublicKey,
+    interpreter, util, AbsLockTime, BareCtx, CovenantExt, Descriptor, ExtParams, Legacy,
+    Miniscript, Satisfier, Segwitv0, SigType, Tap, ToPublicKey,
 };
 
 // Get the amount being spent for the psbt input
@@ -276,6 +277,7 @@ pub(super) fn get_descriptor(
 pub fn _interpreter_inp_check<C: secp256k1_zkp::Verification>(
     psbt: &Psbt,
     tx: &Transaction,
+    cache: &mut SigHashCache<&Transaction>,
     secp: &Secp256k1<C>,
     index: usize,
     genesis_hash: elements::BlockHash,
@@ -307,7 +309,7 @@ pub fn _interpreter_inp_check<C: secp256k1_zkp::Verification>(
     let env = TxEnv::new(tx, &prevouts, index)
         .ok_or(Error::InputError(InputError::MissingUtxo, index))?;
     if let Some(error) = interpreter
-        .iter(secp, &env, genesis_hash)
+        .iter_with_cache(secp, &env, cache, genesis_hash)
         .filter_map(Result::err)
         .next()
     {
@@ -323,8 +325,9 @@ pub fn interpreter_inp_check<C: secp256k1_zkp::Verification>(
     genesis_hash: elements::BlockHash,
 ) -> Result<(), Error> {
     let tx = psbt.extract_tx()?;
+    let mut cache = SigHashCache::new(&tx);
 
-    _interpreter_inp_check(psbt, &tx, secp, index, genesis_hash)
+    _interpreter_inp_check(psbt, &tx, &mut cache, secp, index, genesis_hash)
 }
 /// Interprets all psbt inputs and checks whether the
 /// script is correctly interpreted according to the context
@@ -337,14 +340,16 @@ pub fn interpreter_check<C: secp256k1_zkp::Verification>(
     genesis_hash: elements::BlockHash,
 ) -> Result<(), Error> {
     let tx = psbt.extract_tx()?;
+    // Share the sighash cache between all inputs
+    let mut cache = SigHashCache::new(&tx);
     for index in 0..psbt.inputs().len() {
-        _interpreter_inp_check(psbt, &tx, secp, index, genesis_hash)?;
+        _interpreter_inp_check(psbt, &tx, &mut cache, secp, index, genesis_hash)?;
     }
     Ok(())
 }
 
 // Helper function for input sanity checks and code-dedup
-fn input_sanity_checks(psbt: &Psbt, index: usize) -> Result<(), super::Error> {
+pub(super) fn input_sanity_checks(psbt: &Psbt, index: usize) -> Result<(), super::Error> {
     let input = &psbt.inputs()[index];
     let target = input
         .ecdsa_hash_ty()
@@ -389,66 +394,124 @@ fn input_sanity_checks(psbt: &Psbt, index: usize) -> Result<(), super::Error> {
     Ok(())
 }
 
-// Helper function to finalize a input
-fn _finalize_inp(
-    psbt: &mut Psbt,
+// Whether a signature of the psbt, possibly in a finalized input, commits to
+// the locktime and the sequences of the transaction
+pub(super) fn has_signatures(psbt: &Psbt) -> bool {
+    psbt.inputs().iter().any(|inp| {
+        !inp.partial_sigs.is_empty()
+            || inp.tap_key_sig.is_some()
+            || !inp.tap_script_sigs.is_empty()
+            || inp.final_script_sig.is_some()
+            || inp.final_script_witness.is_some()
+    })
+}
+
+// Enables the locktime for the inputs requiring it, see
+// [`PsbtExt::enable_required_locktimes`]. Once a signature commits to the
+// sequences they cannot be changed anymore, and an input requiring a
+// locktime which its sequence does not enable is an error.
+fn enable_required_locktimes(psbt: &mut Psbt) -> Result<(), super::Error> {
+    if !has_signatures(psbt) {
+        psbt.enable_required_locktimes()?;
+        return Ok(());
+    }
+    let lock_time = psbt
+        .locktime()
+        .map_err(|_| Error::LockTimeCombinationError)?;
+    for (index, input) in psbt.inputs().iter().enumerate() {
+        let required =
+            input.required_time_locktime.is_some() || input.required_height_locktime.is_some();
+        let enabled = input
+            .sequence
+            .map_or(false, |seq| seq.enables_absolute_lock_time());
+        if required && !enabled {
+            let plan = TimelockPlan {
+                lock_time: Some(AbsLockTime::from(lock_time)),
+                sequence: None,
+            };
+            return Err(Error::InputError(
+                InputError::TimelocksCommitted(plan),
+                index,
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The witness and the script sig satisfying the input at `index` with
+// `psbt_sat`, which is combined with the covenant satisfiers of the input
+pub(super) fn satisfy_inp<S>(
+    psbt: &Psbt,
     extracted_tx: &Transaction,
     spent_utxos: &[TxOut],
     index: usize,
     allow_mall: bool,
-) -> Result<(), super::Error> {
-    // rust 1.29 burrowchecker
-    let (witness, script_sig) = {
-        let spk = get_scriptpubkey(psbt, index).map_err(|e| Error::InputError(e, index))?;
-        let psbt_sat = PsbtInputSatisfier::new(psbt, index);
+    psbt_sat: S,
+) -> Result<(Vec<Vec<u8>>, Script), super::Error>
+where
+    S: Satisfier<XOnlyPublicKey> + Satisfier<PublicKey>,
+{
+    let spk = get_scriptpubkey(psbt, index).map_err(|e| Error::InputError(e, index))?;
+    let res = if util::is_v1_p2tr(spk) {
+        let cov_sat = TxEnv::new(extracted_tx, spent_utxos, index)
+            .ok_or(super::Error::InputError(InputError::MissingUtxo, index))?;
+        // Deal with tr case separately, unfortunately we cannot infer the full descriptor for Tr
+        let wit = construct_tap_witness(spk, &(psbt_sat, cov_sat), allow_mall)
+            .map_err(|e| Error::InputError(e, index))?;
+        (wit, Script::new())
+    } else {
+        // Get a descriptor for this input
+        let desc = get_descriptor(psbt, index).map_err(|e| Error::InputError(e, index))?;
 
-        if util::is_v1_p2tr(spk) {
-            let cov_sat = TxEnv::new(extracted_tx, spent_utxos, index)
+        // If the descriptor is covenant one, create a covenant satisfier. Otherwise
+        // use the regular satisfier
+        if let Descriptor::LegacyCSFSCov(cov) = &desc {
+            // For covenant descriptors create satisfier
+            let utxo = psbt.inputs()[index]
+                .witness_utxo
+                .as_ref()
                 .ok_or(super::Error::InputError(InputError::MissingUtxo, index))?;
-            // Deal with tr case separately, unfortunately we cannot infer the full descriptor for Tr
-            let wit = construct_tap_witness(spk, &(psbt_sat, cov_sat), allow_mall)
-                .map_err(|e| Error::InputError(e, index))?;
-            (wit, Script::new())
+            // Codesepartor calculation
+            let script_code = cov.cov_script_code();
+            let cov_sat = LegacyCovSatisfier::new_segwitv0(
+                extracted_tx,
+                index as u32,
+                utxo.value,
+                &script_code,
+                psbt.inputs()[index]
+                    .ecdsa_hash_ty()
+                    .ok_or(Error::InputError(InputError::NonStandardSighashType, index))?,
+            );
+            let sat = if !allow_mall {
+                desc.get_satisfaction((psbt_sat, cov_sat))
+            } else {
+                desc.get_satisfaction_mall((psbt_sat, cov_sat))
+            };
+            sat.map_err(|e| Error::InputError(InputError::MiniscriptError(e), index))?
         } else {
-            // Get a descriptor for this input
-            let desc = get_descriptor(psbt, index).map_err(|e| Error::InputError(e, index))?;
-
-            // If the descriptor is covenant one, create a covenant satisfier. Otherwise
-            // use the regular satisfier
-            if let Descriptor::LegacyCSFSCov(cov) = &desc {
-                // For covenant descriptors create satisfier
-                let utxo = psbt.inputs()[index]
-                    .witness_utxo
-                    .as_ref()
-                    .ok_or(super::Error::InputError(InputError::MissingUtxo, index))?;
-                // Codesepartor calculation
-                let script_code = cov.cov_script_code();
-                let cov_sat = LegacyCovSatisfier::new_segwitv0(
-                    extracted_tx,
-                    index as u32,
-                    utxo.value,
-                    &script_code,
-                    psbt.inputs()[index]
-                        .ecdsa_hash_ty()
-                        .ok_or(Error::InputError(InputError::NonStandardSighashType, index))?,
-                );
-                let sat = if !allow_mall {
-                    desc.get_satisfaction((psbt_sat, cov_sat))
-                } else {
-                    desc.get_satisfaction_mall((psbt_sat, cov_sat))
-                };
-                sat.map_err(|e| Error::InputError(InputError::MiniscriptError(e), index))?
+            //generate the satisfaction witness and scriptsig
+            let sat = if !allow_mall {
+                desc.get_satisfaction(psbt_sat)
             } else {
-                //generate the satisfaction witness and scriptsig
-                let sat = if !allow_mall {
-                    desc.get_satisfaction(psbt_sat)
-                } else {
-                    desc.get_satisfaction_mall(psbt_sat)
-                };
-                sat.map_err(|e| Error::InputError(InputError::MiniscriptError(e), index))?
-            }
+                desc.get_satisfaction_mall(psbt_sat)
+            };
+            sat.map_err(|e| Error::InputError(InputError::MiniscriptError(e), index))?
         }
     };
+    Ok(res)
+}
+
+// Helper function to finalize a input
+fn _finalize_inp(
+    psbt: &mut Psbt,
+    extracted_tx: &Transaction,
+    spent_utxos: &[TxOut],
+    index: usize,
+    allow_mall: bool,
+) -> Result<(), super::Error> {
+    let psbt_sat = PsbtInputSatisfier::new(psbt, index);
+    let (witness, script_sig) =
+        satisfy_inp(psbt, extracted_tx, spent_utxos, index, allow_mall, psbt_sat)?;
     let input = &mut psbt.inputs_mut()[index];
     //Fill in the satisfactions
     input.final_script_sig = if script_sig.is_empty() {
@@ -481,6 +544,7 @@ pub fn finalize_input<C: secp256k1_zkp::Verification>(
     genesis_hash: elements::BlockHash,
 ) -> Result<(), super::Error> {
     input_sanity_checks(psbt, index)?;
+    enable_required_locktimes(psbt)?;
 
     let extracted_tx = psbt.extract_tx()?;
     let spent_utxos = prevouts(psbt)?;
@@ -497,6 +561,11 @@ pub fn finalize_input<C: secp256k1_zkp::Verification>(
 /// For satisfaction of individual inputs, use the satisfy API.
 /// This function also performs a sanity interpreter check on the
 /// finalized psbt which involves checking the signatures/ preimages/timelocks.
+/// When the psbt has no signatures yet, the inputs requiring a locktime get a
+/// sequence enabling it, see [`PsbtExt::enable_required_locktimes`]. Once the
+/// signatures commit to the sequences, an input requiring a locktime which
+/// its sequence does not enable is reported as
+/// [`InputError::TimelocksCommitted`].
 pub fn finalize<C: secp256k1_zkp::Verification>(
     psbt: &mut Psbt,
     secp: &Secp256k1<C>,
@@ -508,6 +577,7 @@ pub fn finalize<C: secp256k1_zkp::Verification>(
     for n in 0..psbt.inputs().len() {
         input_sanity_checks(psbt, n)?;
     }
+    enable_required_locktimes(psbt)?;
 
     // Actually construct the witnesses
     let extracted_tx = psbt.extract_tx()?;
@@ -529,6 +599,57 @@ pub fn finalize<C: secp256k1_zkp::Verification>(
     Ok(())
 }
 
+/// Finalizes the inputs of the psbt which are not finalized yet, extracts
+/// the transaction and checks with the interpreter that the witness of every
+/// input satisfies the script of its prevout, so that the transaction can
+/// safely be broadcast. The psbt itself is left untouched.
+///
+/// All the inputs are checked, even after a failure, and the errors of all
+/// the failing inputs are returned, as [`Error::InputError`]s with the index
+/// of the input. Errors affecting the whole psbt are returned alone.
+pub fn extract_tx_validated<C: secp256k1_zkp::Verification>(
+    psbt: &Psbt,
+    secp: &Secp256k1<C>,
+    genesis_hash: elements::BlockHash,
+) -> Result<Transaction, Vec<super::Error>> {
+    sanity_check(psbt).map_err(|e| vec![e])?;
+
+    let mut psbt = psbt.clone();
+    enable_required_locktimes(&mut psbt).map_err(|e| vec![e])?;
+    let extracted_tx = psbt.extract_tx().map_err(|e| vec![e.into()])?;
+    let spent_utxos = prevouts(&psbt).map_err(|e| vec![e])?;
+    let mut errors = vec![];
+    for index in 0..psbt.inputs().len() {
+        let input = &psbt.inputs()[index];
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            continue;
+        }
+        let res = input_sanity_checks(&psbt, index)
+            .and_then(|()| _finalize_inp(&mut psbt, &extracted_tx, &spent_utxos, index, false));
+        if let Err(e) = res {
+            errors.push((index, e));
+        }
+    }
+
+    let tx = psbt.extract_tx().map_err(|e| vec![e.into()])?;
+    let mut cache = SigHashCache::new(&tx);
+    for index in 0..psbt.inputs().len() {
+        if errors.iter().any(|&(i, _)| i == index) {
+            continue;
+        }
+        if let Err(e) = _interpreter_inp_check(&psbt, &tx, &mut cache, secp, index, genesis_hash) {
+            errors.push((index, e));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tx)
+    } else {
+        errors.sort_by_key(|&(index, _)| index);
+        Err(errors.into_iter().map(|(_, e)| e).collect())
+    }
+}
+
 // TODO: redo this test again
 // Currently, the test fails because of serde-bug in rangeproof.
 // #[cfg(test)]
@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # PSET Roles
+//!
+//! Wrappers around a PSET exposing only the operations allowed to each role
+//! of BIP 174, so that e.g. a signer cannot add an output to the PSET it is
+//! asked to sign. Each wrapper can be created from a PSET and converted back
+//! into it with `into_inner`, to pass it to the next role.
+//!
+
+use std::ops::Deref;
+
+use bitcoin::key::XOnlyPublicKey;
+use elements::pset::{self, PartiallySignedTransaction as Psbt};
+use elements::secp256k1_zkp::{Secp256k1, Verification};
+use elements::sighash::SigHashCache;
+use elements::taproot::TapLeafHash;
+
+use super::combiner::check_combine;
+use super::{
+    finalize, CombineError, Error, OutputUpdateError, PsbtExt, PsbtSigHashMsg, SighashError,
+    UtxoUpdateError,
+};
+use crate::extensions::{CovExtArgs, CovenantExt};
+use crate::{elementssig_to_rawsig, DefiniteDescriptorKey, Descriptor, ElementsSig};
+
+macro_rules! impl_role {
+    ($role:ident) => {
+        impl $role {
+            /// The wrapped PSET
+            pub fn psbt(&self) -> &Psbt {
+                &self.0
+            }
+
+            /// Returns the wrapped PSET, e.g. to pass it to the next role
+            pub fn into_inner(self) -> Psbt {
+                self.0
+            }
+        }
+
+        impl From<Psbt> for $role {
+            fn from(psbt: Psbt) -> Self {
+                $role(psbt)
+            }
+        }
+    };
+}
+
+/// The creator of a PSET, adding its inputs and outputs
+#[derive(Clone, Debug)]
+pub struct Creator(Psbt);
+impl_role!(Creator);
+
+impl Default for Creator {
+    fn default() -> Self {
+        Creator(Psbt::new_v2())
+    }
+}
+
+impl Creator {
+    /// Creates an empty version 2 PSET
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an input to the PSET
+    pub fn add_input(&mut self, input: pset::Input) {
+        self.0.add_input(input)
+    }
+
+    /// Adds an input spending `utxo`, see [`PsbtExt::add_descriptor_input`]
+    pub fn add_descriptor_input(
+        &mut self,
+        prevout: elements::OutPoint,
+        utxo: elements::TxOut,
+        descriptor: &Descriptor<DefiniteDescriptorKey, CovenantExt<CovExtArgs>>,
+    ) -> Result<usize, UtxoUpdateError> {
+        self.0.add_descriptor_input(prevout, utxo, descriptor)
+    }
+
+    /// Adds an output to the PSET
+    pub fn add_output(&mut self, output: pset::Output) {
+        self.0.add_output(output)
+    }
+}
+
+/// The updater of a PSET, adding the information required to sign and
+/// finalize its inputs and outputs
+#[derive(Clone, Debug)]
+pub struct Updater(Psbt);
+impl_role!(Updater);
+
+impl Updater {
+    /// See [`PsbtExt::update_input_with_descriptor`]
+    pub fn update_input_with_descriptor(
+        &mut self,
+        input_index: usize,
+        descriptor: &Descriptor<DefiniteDescriptorKey, CovenantExt<CovExtArgs>>,
+    ) -> Result<(), UtxoUpdateError> {
+        self.0.update_input_with_descriptor(input_index, descriptor)
+    }
+
+    /// See [`PsbtExt::update_output_with_descriptor`]
+    pub fn update_output_with_descriptor(
+        &mut self,
+        output_index: usize,
+        descriptor: &Descriptor<DefiniteDescriptorKey>,
+    ) -> Result<(), OutputUpdateError> {
+        self.0
+            .update_output_with_descriptor(output_index, descriptor)
+    }
+}
+
+/// The signer of a PSET, adding signatures to its inputs
+#[derive(Clone, Debug)]
+pub struct Signer(Psbt);
+impl_role!(Signer);
+
+impl Signer {
+    /// The message to sign for the input at `idx`, see
+    /// [`PsbtExt::sighash_msg`]
+    pub fn sighash_msg<T: Deref<Target = elements::Transaction>>(
+        &self,
+        idx: usize,
+        cache: &mut SigHashCache<T>,
+        tapleaf_hash: Option<TapLeafHash>,
+        genesis_hash: elements::BlockHash,
+    ) -> Result<PsbtSigHashMsg, SighashError> {
+        self.0.sighash_msg(idx, cache, tapleaf_hash, genesis_hash)
+    }
+
+    /// Adds the ecdsa signature of `pk` to the input at `idx`
+    pub fn add_ecdsa_sig(
+        &mut self,
+        idx: usize,
+        pk: bitcoin::PublicKey,
+        sig: &ElementsSig,
+    ) -> Result<(), Error> {
+        self.input_mut(idx)?
+            .partial_sigs
+            .insert(pk, elementssig_to_rawsig(sig));
+        Ok(())
+    }
+
+    /// Adds the taproot key spend signature of the input at `idx`
+    pub fn add_tap_key_sig(&mut self, idx: usize, sig: elements::SchnorrSig) -> Result<(), Error> {
+        self.input_mut(idx)?.tap_key_sig = Some(sig);
+        Ok(())
+    }
+
+    /// Adds the signature of `pk` for the leaf `leaf_hash` to the input at
+    /// `idx`
+    pub fn add_tap_script_sig(
+        &mut self,
+        idx: usize,
+        pk: XOnlyPublicKey,
+        leaf_hash: TapLeafHash,
+        sig: elements::SchnorrSig,
+    ) -> Result<(), Error> {
+        self.input_mut(idx)?
+            .tap_script_sigs
+            .insert((pk, leaf_hash), sig);
+        Ok(())
+    }
+
+    fn input_mut(&mut self, idx: usize) -> Result<&mut pset::Input, Error> {
+        let psbt_inp = self.0.inputs().len();
+        self.0
+            .inputs_mut()
+            .get_mut(idx)
+            .ok_or(Error::InputIdxOutofBounds {
+                psbt_inp,
+                index: idx,
+            })
+    }
+}
+
+/// The combiner of PSETs, merging the PSETs returned by several signers or
+/// updaters
+#[derive(Clone, Debug)]
+pub struct Combiner(Psbt);
+impl_role!(Combiner);
+
+impl Combiner {
+    /// Merges `other` into the PSET, which must have the same unsigned
+    /// transaction. The PSET is left untouched if they conflict, see
+    /// [`super::combine`].
+    pub fn combine(&mut self, other: Psbt) -> Result<(), CombineError> {
+        check_combine(&self.0, &other)?;
+        self.0.merge(other)?;
+        Ok(())
+    }
+}
+
+/// The finalizer of a PSET, building the final witnesses of its inputs, and
+/// extracting the transaction
+#[derive(Clone, Debug)]
+pub struct Finalizer(Psbt);
+impl_role!(Finalizer);
+
+impl Finalizer {
+    /// Finalizes all the inputs, see [`finalize`]
+    pub fn finalize<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        genesis_hash: elements::BlockHash,
+    ) -> Result<(), Error> {
+        finalize(&mut self.0, secp, genesis_hash)
+    }
+
+    /// Extracts the finalized transaction, see [`PsbtExt::extract`]
+    pub fn extract<C: Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        genesis_hash: elements::BlockHash,
+    ) -> Result<elements::Transaction, Error> {
+        self.0.extract(secp, genesis_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::hashes::Hash;
+    use elements::secp256k1_zkp::{self, SecretKey};
+    use elements::{AssetId, EcdsaSigHashType, OutPoint};
+
+    use super::*;
+
+    #[test]
+    fn pset_roles() {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+        let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+        let desc =
+            Descriptor::<DefiniteDescriptorKey>::from_str(&format!("elwpkh({})", pk)).unwrap();
+        let genesis_hash = elements::BlockHash::all_zeros();
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+
+        let mut creator = Creator::new();
+        creator
+            .add_descriptor_input(OutPoint::default(), desc.txout(1_000, asset, None), &desc)
+            .unwrap();
+        creator.add_output(pset::Output::from_txout(desc.txout(900, asset, None)));
+        let mut updater = Updater::from(creator.into_inner());
+        updater.update_output_with_descriptor(0, &desc).unwrap();
+
+        let mut signer = Signer::from(updater.into_inner());
+        let tx = signer.psbt().extract_tx().unwrap();
+        let msg = signer
+            .sighash_msg(0, &mut SigHashCache::new(&tx), None, genesis_hash)
+            .unwrap()
+            .to_secp_msg();
+        let sig = (secp.sign_ecdsa(&msg, &sk), EcdsaSigHashType::All);
+        assert!(signer.add_ecdsa_sig(1, pk, &sig).is_err());
+        signer.add_ecdsa_sig(0, pk, &sig).unwrap();
+
+        let mut combiner = Combiner::from(signer.psbt().clone());
+        combiner.combine(signer.into_inner()).unwrap();
+
+        let mut finalizer = Finalizer::from(combiner.into_inner());
+        finalizer.finalize(&secp, genesis_hash).unwrap();
+        let tx = finalizer.extract(&secp, genesis_hash).unwrap();
+        assert_eq!(tx.input[0].witness.script_witness.len(), 2);
+    }
+}
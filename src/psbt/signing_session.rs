@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Signing Sessions
+//!
+//! Coordination of the cosigners of a PSET. A [`SigningSession`] knows the
+//! descriptor of every input it tracks and, given the current state of the
+//! PSET, reports which cosigners (identified by the master fingerprint of
+//! their keys) have signed each input and which signatures are still required
+//! to satisfy it, along the spending path needing the fewest of them.
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::bip32;
+use elements::pset::PartiallySignedTransaction as Psbt;
+use elements::secp256k1_zkp::{Secp256k1, Verification};
+
+use super::{Error, PsbtInputSatisfier};
+use crate::descriptor::ConversionError;
+use crate::extensions::{CovExtArgs, CovenantExt};
+use crate::{DefiniteDescriptorKey, Descriptor, ForEachKey, ForEachKeyExt};
+
+/// The signatures of a PSET input, as returned by [`SigningSession::status`]
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct InputStatus {
+    /// The cosigners which have signed the input
+    pub signed: BTreeSet<bip32::Fingerprint>,
+    /// The cosigners whose signatures are still required
+    pub required: BTreeSet<bip32::Fingerprint>,
+    /// Whether the input can be satisfied with the signatures of the PSET
+    pub complete: bool,
+}
+
+/// A request for a cosigner to sign some inputs of a PSET, as returned by
+/// [`SigningSession::requests`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SigningRequest {
+    /// The master fingerprint of the cosigner
+    pub fingerprint: bip32::Fingerprint,
+    /// The inputs to sign, each with the key to sign it with and the full
+    /// derivation path of the key
+    pub inputs: Vec<(usize, bitcoin::PublicKey, bip32::DerivationPath)>,
+}
+
+// A tracked input: its descriptor with derived keys, and the origin of the
+// keys
+#[derive(Clone, Debug)]
+struct SessionInput {
+    descriptor: Descriptor<bitcoin::PublicKey, CovenantExt<CovExtArgs>>,
+    keys: BTreeMap<bitcoin::PublicKey, (bip32::Fingerprint, bip32::DerivationPath)>,
+}
+
+/// Tracks the signatures of the cosigners of a PSET
+///
+/// The inputs to track are added with their descriptor, then the session
+/// is queried with the latest version of the PSET, e.g. after combining the
+/// PSETs returned by the cosigners.
+///
+/// Inputs with covenant descriptors, which need the whole transaction to be
+/// satisfied, are never reported as complete.
+#[derive(Clone, Debug, Default)]
+pub struct SigningSession {
+    inputs: BTreeMap<usize, SessionInput>,
+}
+
+impl SigningSession {
+    /// Creates a session without any input
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks the input at `index`, which spends an output of `descriptor`.
+    /// Replaces the descriptor of the input if it is already tracked.
+    ///
+    /// # Errors
+    ///
+    /// If a key of the descriptor cannot be derived, see
+    /// [`DefiniteDescriptorKey::derive_public_key`].
+    pub fn add_input<C: Verification>(
+        &mut self,
+        secp: &Secp256k1<C>,
+        index: usize,
+        descriptor: &Descriptor<DefiniteDescriptorKey, CovenantExt<CovExtArgs>>,
+    ) -> Result<(), ConversionError> {
+        let mut keys = BTreeMap::new();
+        for key in descriptor.filter_keys(|_| true) {
+            let path = key
+                .full_derivation_path()
+                .ok_or(ConversionError::MultiKey)?;
+            keys.insert(
+                key.derive_public_key(secp)?,
+                (key.master_fingerprint(), path),
+            );
+        }
+        let descriptor = descriptor.derived_descriptor(secp)?;
+        self.inputs.insert(index, SessionInput { descriptor, keys });
+        Ok(())
+    }
+
+    /// The indexes of the tracked inputs
+    pub fn inputs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.inputs.keys().copied()
+    }
+
+    /// The signatures of the tracked input at `index` in `pset`, or `None`
+    /// if the input is not tracked.
+    ///
+    /// # Errors
+    ///
+    /// If `pset` does not have an input at `index`.
+    pub fn status(&self, pset: &Psbt, index: usize) -> Result<Option<InputStatus>, Error> {
+        Ok(self.input_state(pset, index)?.map(|(status, _)| status))
+    }
+
+    // The status of the input along with the keys whose signatures are
+    // still required
+    fn input_state(
+        &self,
+        pset: &Psbt,
+        index: usize,
+    ) -> Result<Option<(InputStatus, Vec<bitcoin::PublicKey>)>, Error> {
+        let input = match self.inputs.get(&index) {
+            Some(input) => input,
+            None => return Ok(None),
+        };
+        let psbt_input = pset.inputs().get(index).ok_or(Error::InputIdxOutofBounds {
+            psbt_inp: pset.inputs().len(),
+            index,
+        })?;
+
+        let mut status = InputStatus::default();
+        for (pk, &(fingerprint, _)) in &input.keys {
+            let xonly = pk.inner.x_only_public_key().0;
+            let key_spend = match input.descriptor {
+                Descriptor::Tr(ref tr) => tr.internal_key() == pk,
+                Descriptor::TrExt(ref tr) => tr.internal_key() == pk,
+                _ => false,
+            };
+            if psbt_input.partial_sigs.contains_key(pk)
+                || psbt_input.tap_script_sigs.keys().any(|&(k, _)| k == xonly)
+                || (key_spend && psbt_input.tap_key_sig.is_some())
+            {
+                status.signed.insert(fingerprint);
+            }
+        }
+
+        let satisfier = PsbtInputSatisfier::new(pset, index);
+        let missing = match input.descriptor.get_satisfaction(satisfier) {
+            Ok(..) => {
+                status.complete = true;
+                vec![]
+            }
+            Err(crate::Error::MissingItems(missing)) => missing
+                .sigs
+                .into_iter()
+                .chain(missing.raw_pkh_sigs)
+                .collect(),
+            Err(crate::Error::MissingSig(pk)) => vec![pk],
+            Err(..) => vec![],
+        };
+        let missing = missing
+            .into_iter()
+            .filter(|pk| input.keys.contains_key(pk))
+            .collect::<Vec<_>>();
+        status.required = missing.iter().map(|pk| input.keys[pk].0).collect();
+        Ok(Some((status, missing)))
+    }
+
+    /// Whether all the tracked inputs of `pset` can be satisfied
+    ///
+    /// # Errors
+    ///
+    /// If `pset` does not have one of the tracked inputs.
+    pub fn is_complete(&self, pset: &Psbt) -> Result<bool, Error> {
+        for index in self.inputs() {
+            if !self.status(pset, index)?.map_or(false, |s| s.complete) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The signatures still required for the tracked inputs of `pset`,
+    /// grouped by cosigner
+    ///
+    /// # Errors
+    ///
+    /// If `pset` does not have one of the tracked inputs.
+    pub fn requests(&self, pset: &Psbt) -> Result<Vec<SigningRequest>, Error> {
+        let mut requests = BTreeMap::<bip32::Fingerprint, Vec<_>>::new();
+        for (&index, input) in &self.inputs {
+            let missing = match self.input_state(pset, index)? {
+                Some((_, missing)) => missing,
+                None => continue,
+            };
+            for pk in missing {
+                let (fingerprint, ref path) = input.keys[&pk];
+                requests
+                    .entry(fingerprint)
+                    .or_default()
+                    .push((index, pk, path.clone()));
+            }
+        }
+        Ok(requests
+            .into_iter()
+            .map(|(fingerprint, inputs)| SigningRequest {
+                fingerprint,
+                inputs,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::{AssetId, OutPoint};
+
+    use super::*;
+    use crate::psbt::PsbtExt;
+    use crate::{elementssig_to_rawsig, ElementsSig};
+
+    #[test]
+    fn signing_session() {
+        let secp = Secp256k1::verification_only();
+        let desc = Descriptor::<DefiniteDescriptorKey>::from_str(
+            "elwsh(multi(2,\
+             [aaaaaaaa/48'/1'/0'/2']02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13,\
+             [bbbbbbbb/48'/1'/0'/2']022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4,\
+             [cccccccc/48'/1'/0'/2']03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))",
+        )
+        .unwrap();
+        let fingerprint = |s: &str| bip32::Fingerprint::from_str(s).unwrap();
+        let mut keys = vec![];
+        desc.for_each_key(|key| {
+            keys.push(key.derive_public_key(&secp).unwrap());
+            true
+        });
+
+        let mut pset = Psbt::new_v2();
+        let utxo = desc.txout(1_000, AssetId::default(), None);
+        pset.add_descriptor_input(OutPoint::default(), utxo, &desc)
+            .unwrap();
+        let mut session = SigningSession::new();
+        session.add_input(&secp, 0, &desc).unwrap();
+        assert_eq!(session.status(&pset, 1).unwrap(), None);
+
+        let status = session.status(&pset, 0).unwrap().unwrap();
+        assert!(status.signed.is_empty());
+        assert_eq!(
+            status.required,
+            vec![fingerprint("aaaaaaaa"), fingerprint("bbbbbbbb")]
+                .into_iter()
+                .collect()
+        );
+        assert!(!session.is_complete(&pset).unwrap());
+
+        let sig: ElementsSig = (
+            elements::secp256k1_zkp::ecdsa::Signature::from_compact(&[1; 64]).unwrap(),
+            elements::EcdsaSigHashType::All,
+        );
+        pset.inputs_mut()[0]
+            .partial_sigs
+            .insert(keys[0], elementssig_to_rawsig(&sig));
+        let status = session.status(&pset, 0).unwrap().unwrap();
+        assert_eq!(
+            status.signed,
+            vec![fingerprint("aaaaaaaa")].into_iter().collect()
+        );
+        assert_eq!(
+            status.required,
+            vec![fingerprint("bbbbbbbb")].into_iter().collect()
+        );
+        assert_eq!(
+            session.requests(&pset).unwrap(),
+            vec![SigningRequest {
+                fingerprint: fingerprint("bbbbbbbb"),
+                inputs: vec![(
+                    0,
+                    keys[1],
+                    bip32::DerivationPath::from_str("m/48'/1'/0'/2'").unwrap()
+                )],
+            }]
+        );
+
+        pset.inputs_mut()[0]
+            .partial_sigs
+            .insert(keys[2], elementssig_to_rawsig(&sig));
+        let status = session.status(&pset, 0).unwrap().unwrap();
+        assert!(status.complete);
+        assert!(status.required.is_empty());
+        assert!(session.is_complete(&pset).unwrap());
+        assert!(session.requests(&pset).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Taproot Signing
+//!
+//! Helpers signing the taproot inputs of a PSET with a single key pair: the
+//! Elements taproot sighash of the input is computed from the PSET, with its
+//! sighash type, and signed with BIP-340 auxiliary randomness.
+//!
+//! Key spends are signed by the key pair tweaked with the merkle root of the
+//! input, script spends by the untweaked key pair for the leaf given by its
+//! hash, see [`Tr::iter_scripts`](crate::descriptor::Tr::iter_scripts) to
+//! obtain the leaves of a descriptor.
+//!
+
+use std::{error, fmt};
+
+use bitcoin::key::XOnlyPublicKey;
+use elements::pset::{self, PartiallySignedTransaction as Psbt};
+use elements::schnorr::TapTweak;
+use elements::secp256k1_zkp::{KeyPair, Secp256k1, Signing, Verification};
+use elements::sighash::SigHashCache;
+use elements::taproot::TapLeafHash;
+use elements::SchnorrSig;
+
+use super::{PsbtExt, PsbtSigHashMsg, SighashError};
+
+/// An error signing a taproot input of a PSET
+#[derive(Debug)]
+pub enum TapSignError {
+    /// The transaction of the PSET could not be extracted
+    Pset(pset::Error),
+    /// The sighash of the input could not be computed
+    Sighash(SighashError),
+    /// The input at the index does not spend a taproot output
+    NotTaproot(usize),
+    /// The key pair is not the one of the internal key of the input
+    InternalKeyMismatch(XOnlyPublicKey),
+}
+
+impl fmt::Display for TapSignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TapSignError::Pset(ref e) => write!(f, "Pset error: {}", e),
+            TapSignError::Sighash(ref e) => write!(f, "Sighash error: {}", e),
+            TapSignError::NotTaproot(idx) => {
+                write!(f, "input {} does not spend a taproot output", idx)
+            }
+            TapSignError::InternalKeyMismatch(ref pk) => {
+                write!(f, "key {} is not the internal key of the input", pk)
+            }
+        }
+    }
+}
+
+impl error::Error for TapSignError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            TapSignError::Pset(ref e) => Some(e),
+            TapSignError::Sighash(ref e) => Some(e),
+            TapSignError::NotTaproot(_) | TapSignError::InternalKeyMismatch(_) => None,
+        }
+    }
+}
+
+/// Signs the taproot key spend of the input at `idx` of `pset` with
+/// `keypair`, the untweaked key pair of the internal key of the input.
+///
+/// The key pair is tweaked with the `tap_merkle_root` of the input, if any.
+/// `aux_rand` is the BIP-340 auxiliary randomness, which should be freshly
+/// generated for each signature.
+pub fn sign_taproot_key_spend<C: Signing + Verification>(
+    secp: &Secp256k1<C>,
+    keypair: &KeyPair,
+    pset: &Psbt,
+    idx: usize,
+    genesis_hash: elements::BlockHash,
+    aux_rand: &[u8; 32],
+) -> Result<SchnorrSig, TapSignError> {
+    let msg = tap_sighash_msg(pset, idx, None, genesis_hash)?;
+    let inp = &pset.inputs()[idx];
+    let (internal_key, _parity) = XOnlyPublicKey::from_keypair(keypair);
+    if inp.tap_internal_key.map_or(false, |pk| pk != internal_key) {
+        return Err(TapSignError::InternalKeyMismatch(internal_key));
+    }
+    let tweaked = (*keypair).tap_tweak(secp, inp.tap_merkle_root).to_inner();
+    Ok(SchnorrSig {
+        sig: secp.sign_schnorr_with_aux_rand(&msg.to_secp_msg(), &tweaked, aux_rand),
+        hash_ty: schnorr_hash_ty(pset, idx)?,
+    })
+}
+
+/// Signs the script spend of the input at `idx` of `pset` through the leaf
+/// `leaf_hash` with `keypair`.
+///
+/// `aux_rand` is the BIP-340 auxiliary randomness, which should be freshly
+/// generated for each signature.
+pub fn sign_tap_leaf<C: Signing>(
+    secp: &Secp256k1<C>,
+    keypair: &KeyPair,
+    pset: &Psbt,
+    idx: usize,
+    leaf_hash: TapLeafHash,
+    genesis_hash: elements::BlockHash,
+    aux_rand: &[u8; 32],
+) -> Result<SchnorrSig, TapSignError> {
+    let msg = tap_sighash_msg(pset, idx, Some(leaf_hash), genesis_hash)?;
+    Ok(SchnorrSig {
+        sig: secp.sign_schnorr_with_aux_rand(&msg.to_secp_msg(), keypair, aux_rand),
+        hash_ty: schnorr_hash_ty(pset, idx)?,
+    })
+}
+
+// The taproot sighash of the input at `idx`
+fn tap_sighash_msg(
+    pset: &Psbt,
+    idx: usize,
+    leaf_hash: Option<TapLeafHash>,
+    genesis_hash: elements::BlockHash,
+) -> Result<PsbtSigHashMsg, TapSignError> {
+    let tx = pset.extract_tx().map_err(TapSignError::Pset)?;
+    let mut cache = SigHashCache::new(&tx);
+    match pset
+        .sighash_msg(idx, &mut cache, leaf_hash, genesis_hash)
+        .map_err(TapSignError::Sighash)?
+    {
+        msg @ PsbtSigHashMsg::TapSigHash(_) => Ok(msg),
+        PsbtSigHashMsg::EcdsaSigHash(_) => Err(TapSignError::NotTaproot(idx)),
+    }
+}
+
+// The sighash type of the input at `idx`, which was checked by
+// `tap_sighash_msg`
+fn schnorr_hash_ty(pset: &Psbt, idx: usize) -> Result<elements::SchnorrSigHashType, TapSignError> {
+    pset.inputs()[idx]
+        .schnorr_hash_ty()
+        .ok_or(TapSignError::Sighash(SighashError::InvalidSigHashType))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::hashes::Hash;
+    use elements::secp256k1_zkp::SecretKey;
+    use elements::taproot::LeafVersion;
+    use elements::{AssetId, OutPoint};
+
+    use super::*;
+    use crate::{DefiniteDescriptorKey, Descriptor};
+
+    #[test]
+    fn sign_taproot_inputs() {
+        let secp = Secp256k1::new();
+        let internal = KeyPair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let leaf = KeyPair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+        let leaf_pk = XOnlyPublicKey::from_keypair(&leaf).0;
+        let desc = Descriptor::<DefiniteDescriptorKey>::from_str(&format!(
+            "eltr({},pk({}))",
+            XOnlyPublicKey::from_keypair(&internal).0,
+            leaf_pk,
+        ))
+        .unwrap();
+        let genesis_hash = elements::BlockHash::all_zeros();
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+
+        let mut pset = Psbt::new_v2();
+        pset.add_descriptor_input(OutPoint::default(), desc.txout(1_000, asset, None), &desc)
+            .unwrap();
+        pset.add_output(pset::Output::from_txout(desc.txout(900, asset, None)));
+
+        // The key spend, checked by the interpreter when finalizing
+        let sig =
+            sign_taproot_key_spend(&secp, &internal, &pset, 0, genesis_hash, &[3; 32]).unwrap();
+        let mut key_spend = pset.clone();
+        key_spend.inputs_mut()[0].tap_key_sig = Some(sig);
+        key_spend.finalize_mut(&secp, genesis_hash).unwrap();
+        assert_eq!(
+            key_spend.inputs()[0]
+                .final_script_witness
+                .as_ref()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches!(
+            sign_taproot_key_spend(&secp, &leaf, &pset, 0, genesis_hash, &[3; 32]),
+            Err(TapSignError::InternalKeyMismatch(pk)) if pk == leaf_pk
+        ));
+
+        // The script spend
+        let tr = match desc {
+            Descriptor::Tr(ref tr) => tr,
+            _ => unreachable!(),
+        };
+        let (_, ms) = tr.iter_scripts().next().unwrap();
+        let leaf_hash = TapLeafHash::from_script(&ms.encode(), LeafVersion::default());
+        let sig = sign_tap_leaf(&secp, &leaf, &pset, 0, leaf_hash, genesis_hash, &[3; 32]).unwrap();
+        let mut script_spend = pset.clone();
+        script_spend.inputs_mut()[0]
+            .tap_script_sigs
+            .insert((leaf_pk, leaf_hash), sig);
+        script_spend.finalize_mut(&secp, genesis_hash).unwrap();
+        assert_eq!(
+            script_spend.inputs()[0]
+                .final_script_witness
+                .as_ref()
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
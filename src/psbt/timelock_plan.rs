@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Timelock Planning
+//!
+//! Computes the locktime and the sequence needed by the cheapest satisfaction
+//! of a PSET input, so that they can be set on the transaction before it is
+//! signed, instead of finding out that they are missing when finalizing or
+//! broadcasting it. See [`plan_timelocks`] and [`apply_timelock_plan`].
+//!
+
+use elements::{LockTime, Sequence};
+
+use super::finalizer::{
+    get_scriptpubkey, has_signatures, input_sanity_checks, prevouts, satisfy_inp,
+};
+use super::{Error, InputError, Psbt, PsbtInputSatisfier};
+use crate::extensions::TxEnv;
+use crate::interpreter::{Interpreter, InterpreterOptions, SatisfiedConstraint};
+use crate::{AbsLockTime, MiniscriptKey, RelLockTime, Satisfier, ToPublicKey};
+
+/// The timelocks needed by the cheapest satisfaction of a PSET input, see
+/// [`plan_timelocks`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct TimelockPlan {
+    /// The minimum locktime of the transaction, for the `after` fragments
+    pub lock_time: Option<AbsLockTime>,
+    /// The minimum relative locktime of the input, for the `older` fragments
+    pub sequence: Option<RelLockTime>,
+}
+
+// A satisfier assuming that all the timelocks are met
+struct AssumeTimelocks;
+
+impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for AssumeTimelocks {
+    fn check_after(&self, _: LockTime) -> bool {
+        true
+    }
+
+    fn check_older(&self, _: Sequence) -> bool {
+        true
+    }
+}
+
+/// Computes the timelocks needed by the cheapest satisfaction of the input at
+/// `index` of `psbt`, with its signatures and preimages, assuming that any
+/// locktime and sequence can be set.
+///
+/// # Errors
+///
+/// If the input cannot be satisfied even with its timelocks met, or if the
+/// cheapest satisfaction mixes heights and times.
+pub fn plan_timelocks(psbt: &Psbt, index: usize) -> Result<TimelockPlan, Error> {
+    if index >= psbt.inputs().len() {
+        return Err(Error::InputIdxOutofBounds {
+            psbt_inp: psbt.inputs().len(),
+            index,
+        });
+    }
+    input_sanity_checks(psbt, index)?;
+    let tx = psbt.extract_tx()?;
+    let spent_utxos = prevouts(psbt)?;
+    let sat = (PsbtInputSatisfier::new(psbt, index), AssumeTimelocks);
+    let (witness, script_sig) = satisfy_inp(psbt, &tx, &spent_utxos, index, false, sat)?;
+
+    // The timelocks of the satisfaction are the ones checked by the
+    // interpreter, which is told that they are all met
+    let spk = get_scriptpubkey(psbt, index).map_err(|e| Error::InputError(e, index))?;
+    let env = TxEnv::new(&tx, &spent_utxos, index)
+        .ok_or(Error::InputError(InputError::MissingUtxo, index))?;
+    let options = InterpreterOptions {
+        verify_cltv_csv: false,
+        ..Default::default()
+    };
+    let interpreter = Interpreter::from_txdata_with_options(
+        spk,
+        &script_sig,
+        &witness,
+        Sequence::MAX,
+        LockTime::ZERO,
+        options,
+    )
+    .map_err(|e| Error::InputError(InputError::Interpreter(e), index))?;
+
+    let mut plan = TimelockPlan::default();
+    for constraint in interpreter.iter_custom(Box::new(|_| true), Some(&env)) {
+        match constraint.map_err(|e| Error::InputError(InputError::Interpreter(e), index))? {
+            SatisfiedConstraint::AbsoluteTimelock { n } => {
+                let n = AbsLockTime::from(n);
+                plan.lock_time = match plan.lock_time {
+                    Some(lock_time) if !lock_time.is_same_unit(n) => {
+                        return Err(Error::LockTimeCombinationError)
+                    }
+                    lock_time => lock_time.max(Some(n)),
+                };
+            }
+            SatisfiedConstraint::RelativeTimelock { n } => {
+                let n = RelLockTime::from(n);
+                plan.sequence = match plan.sequence {
+                    Some(sequence) if !sequence.is_same_unit(n) => {
+                        return Err(Error::LockTimeCombinationError)
+                    }
+                    sequence => sequence.max(Some(n)),
+                };
+            }
+            _ => {}
+        }
+    }
+    Ok(plan)
+}
+
+// Whether the timelocks of `plan` are met by the input at `index`
+fn timelocks_met(psbt: &Psbt, index: usize, plan: &TimelockPlan) -> bool {
+    let sat = PsbtInputSatisfier::new(psbt, index);
+    plan.lock_time.map_or(true, |n| {
+        Satisfier::<bitcoin::PublicKey>::check_after(&sat, n.into())
+    }) && plan.sequence.map_or(true, |n| {
+        Satisfier::<bitcoin::PublicKey>::check_older(&sat, n.to_sequence())
+    })
+}
+
+/// Sets the locktime and the sequence needed by `plan` for the input at
+/// `index` of `psbt`, see [`plan_timelocks`].
+///
+/// The locktime is set with the `required_height_locktime` or the
+/// `required_time_locktime` field of the input, and the transaction version
+/// is raised to 2 for relative locktimes. Nothing is changed if the timelocks
+/// are already met.
+///
+/// # Errors
+///
+/// If the timelocks are not met but the signatures of the PSET already commit
+/// to the locktime and the sequences, reported as
+/// [`InputError::TimelocksCommitted`], or if the locktime conflicts with the
+/// ones required by the other inputs. The PSET is not changed on error.
+pub fn apply_timelock_plan(
+    psbt: &mut Psbt,
+    index: usize,
+    plan: &TimelockPlan,
+) -> Result<(), Error> {
+    if index >= psbt.inputs().len() {
+        return Err(Error::InputIdxOutofBounds {
+            psbt_inp: psbt.inputs().len(),
+            index,
+        });
+    }
+    if timelocks_met(psbt, index, plan) {
+        return Ok(());
+    }
+    if has_signatures(psbt) {
+        return Err(Error::InputError(
+            InputError::TimelocksCommitted(*plan),
+            index,
+        ));
+    }
+
+    let mut updated = psbt.clone();
+    let input = &mut updated.inputs_mut()[index];
+    if let Some(n) = plan.lock_time {
+        match LockTime::from(n) {
+            LockTime::Blocks(height) => {
+                input.required_height_locktime = input.required_height_locktime.max(Some(height));
+            }
+            LockTime::Seconds(time) => {
+                input.required_time_locktime = input.required_time_locktime.max(Some(time));
+            }
+        }
+        if input
+            .sequence
+            .map_or(true, |seq| !seq.enables_absolute_lock_time())
+        {
+            input.sequence = Some(Sequence::ENABLE_LOCKTIME_NO_RBF);
+        }
+    }
+    if let Some(n) = plan.sequence {
+        input.sequence = Some(n.to_sequence());
+        if updated.global.tx_data.version < 2 {
+            updated.global.tx_data.version = 2;
+        }
+    }
+
+    if timelocks_met(&updated, index, plan) {
+        *psbt = updated;
+        Ok(())
+    } else {
+        Err(Error::LockTimeCombinationError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use elements::hashes::{sha256, Hash};
+    use elements::{pset, AssetId, OutPoint};
+
+    use super::*;
+    use crate::psbt::PsbtExt;
+    use crate::{DefiniteDescriptorKey, Descriptor};
+
+    #[test]
+    fn plan_and_apply_timelocks() {
+        let secp = elements::secp256k1_zkp::Secp256k1::verification_only();
+        let genesis_hash = elements::BlockHash::all_zeros();
+        let preimage = [1u8; 32];
+        let hash = sha256::Hash::hash(&preimage);
+        let desc = Descriptor::<DefiniteDescriptorKey>::from_str(&format!(
+            "elwsh(or_i(and_v(v:sha256({}),older(144)),and_v(v:sha256({}),after(1000))))",
+            hash,
+            sha256::Hash::hash(&[2; 32]),
+        ))
+        .unwrap();
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+
+        let mut psbt = Psbt::new_v2();
+        psbt.add_descriptor_input(OutPoint::default(), desc.txout(1_000, asset, None), &desc)
+            .unwrap();
+        psbt.add_output(pset::Output::from_txout(desc.txout(900, asset, None)));
+        psbt.inputs_mut()[0]
+            .sha256_preimages
+            .insert(hash, preimage.to_vec());
+        assert!(psbt.clone().finalize(&secp, genesis_hash).is_err());
+
+        // Only the first branch has its preimage
+        let plan = plan_timelocks(&psbt, 0).unwrap();
+        assert_eq!(
+            plan,
+            TimelockPlan {
+                lock_time: None,
+                sequence: Some(RelLockTime::from_height(144)),
+            }
+        );
+        assert!(matches!(
+            apply_timelock_plan(&mut psbt, 1, &plan),
+            Err(Error::InputIdxOutofBounds { index: 1, .. })
+        ));
+        apply_timelock_plan(&mut psbt, 0, &plan).unwrap();
+        assert_eq!(psbt.inputs()[0].sequence, Some(Sequence::from_height(144)));
+        psbt.clone().finalize(&secp, genesis_hash).unwrap();
+
+        // Once an input is finalized, the sequences cannot be changed anymore
+        let stricter = TimelockPlan {
+            lock_time: None,
+            sequence: Some(RelLockTime::from_height(200)),
+        };
+        psbt.inputs_mut()[0].final_script_witness = Some(vec![vec![]]);
+        assert!(matches!(
+            apply_timelock_plan(&mut psbt, 0, &stricter),
+            Err(Error::InputError(InputError::TimelocksCommitted(p), 0)) if p == stricter
+        ));
+    }
+}
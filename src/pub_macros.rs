@@ -116,3 +116,192 @@ macro_rules! translate_hash_clone {
         }
     };
 }
+
+/// Builds a miniscript fragment from its miniscript syntax, returning a
+/// `Result` of a [`crate::MiniscriptBuilder`]
+///
+/// The fragment expands to the constructors of the builder, without any
+/// string parsing. Keys, timelocks, hashes and extension fragments, as
+/// `ext(..)`, are Rust expressions, and wrappers are written one at a time,
+/// e.g. `s:l:n:older(..)` instead of `sln:older(..)`. Unknown fragments and
+/// wrong numbers of arguments are rejected at compile time, while the types
+/// of the fragments are checked when the expression is evaluated.
+///
+/// ```
+/// use elements_miniscript::elements::Sequence;
+/// use elements_miniscript::{fragment, MiniscriptBuilder, Segwitv0};
+///
+/// let ms: MiniscriptBuilder<String, Segwitv0> = fragment!(or_d(
+///     pk("A".to_string()),
+///     and_v(v:pk("B".to_string()), older(Sequence::from_height(144)))
+/// ))?;
+/// assert_eq!(ms.build()?.to_string(), "or_d(pk(A),and_v(v:pk(B),older(144)))");
+/// # Ok::<(), elements_miniscript::Error>(())
+/// ```
+#[macro_export]
+macro_rules! fragment {
+    // Splits the arguments of a fragment on their commas
+    (@args $f:tt [$($done:tt)*] [$($cur:tt)+] , $($rest:tt)+) => {
+        $crate::fragment!(@args $f [$($done)* ($($cur)+)] [] $($rest)+)
+    };
+    (@args $f:tt [$($done:tt)*] [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::fragment!(@args $f [$($done)*] [$($cur)* $next] $($rest)*)
+    };
+    (@args $f:tt [$($done:tt)*] [$($cur:tt)+]) => {
+        $crate::fragment!(@call $f $($done)* ($($cur)+))
+    };
+    (@call (thresh $k:expr) $(($($sub:tt)+))+) => {{
+        let subs: Result<Vec<_>, $crate::Error> =
+            vec![$($crate::fragment!($($sub)+)),+].into_iter().collect();
+        subs.and_then(|subs| $crate::MiniscriptBuilder::thresh($k, subs))
+    }};
+    (@call andor ($($a:tt)+) ($($b:tt)+) ($($c:tt)+)) => {
+        $crate::fragment!($($a)+).and_then(|a| {
+            $crate::fragment!($($b)+)
+                .and_then(|b| $crate::fragment!($($c)+).and_then(|c| a.andor(b, c)))
+        })
+    };
+    (@call $f:ident ($($l:tt)+) ($($r:tt)+)) => {
+        $crate::fragment!($($l)+).and_then(|l| $crate::fragment!($($r)+).and_then(|r| l.$f(r)))
+    };
+    (0) => {
+        $crate::MiniscriptBuilder::terminal($crate::Terminal::False)
+    };
+    (1) => {
+        $crate::MiniscriptBuilder::terminal($crate::Terminal::True)
+    };
+    (pk_k ( $k:expr )) => {
+        $crate::MiniscriptBuilder::pk_k($k)
+    };
+    (pk_h ( $k:expr )) => {
+        $crate::MiniscriptBuilder::pk_h($k)
+    };
+    (pk ( $k:expr )) => {
+        $crate::MiniscriptBuilder::pk($k)
+    };
+    (pkh ( $k:expr )) => {
+        $crate::MiniscriptBuilder::pkh($k)
+    };
+    (after ( $t:expr )) => {
+        $crate::MiniscriptBuilder::after($t)
+    };
+    (older ( $t:expr )) => {
+        $crate::MiniscriptBuilder::older($t)
+    };
+    (sha256 ( $h:expr )) => {
+        $crate::MiniscriptBuilder::terminal($crate::Terminal::Sha256($h))
+    };
+    (hash256 ( $h:expr )) => {
+        $crate::MiniscriptBuilder::terminal($crate::Terminal::Hash256($h))
+    };
+    (ripemd160 ( $h:expr )) => {
+        $crate::MiniscriptBuilder::terminal($crate::Terminal::Ripemd160($h))
+    };
+    (hash160 ( $h:expr )) => {
+        $crate::MiniscriptBuilder::terminal($crate::Terminal::Hash160($h))
+    };
+    (multi ( $k:expr $(, $key:expr)+ )) => {
+        $crate::MiniscriptBuilder::multi($k, vec![$($key),+])
+    };
+    (multi_a ( $k:expr $(, $key:expr)+ )) => {
+        $crate::MiniscriptBuilder::multi_a($k, vec![$($key),+])
+    };
+    (ext ( $e:expr )) => {
+        $crate::MiniscriptBuilder::ext($e)
+    };
+    (thresh ( $k:expr , $($subs:tt)+ )) => {
+        $crate::fragment!(@args (thresh $k) [] [] $($subs)+)
+    };
+    ($f:ident ( $($args:tt)+ )) => {
+        $crate::fragment!(@args $f [] [] $($args)+)
+    };
+    ($w:ident : $($inner:tt)+) => {
+        $crate::fragment!($($inner)+).and_then(|ms| ms.$w())
+    };
+}
+
+/// Builds a descriptor from its descriptor syntax, returning a `Result` of a
+/// [`crate::Descriptor`]
+///
+/// The miniscripts of the descriptor are written as for [`crate::fragment!`],
+/// and keys are Rust expressions. Supported are `elpkh`, `elwpkh`,
+/// `elsh(wpkh)`, `elsh`, `elwsh` and `elsh(wsh)`, with `sortedmulti` or a
+/// miniscript, `elcovwsh` with extension fragments, and `eltr` with a tree of
+/// miniscripts without extensions, in the `{left,right}` syntax.
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// use elements_miniscript::{descriptor, Descriptor};
+///
+/// let desc: Descriptor<String> = descriptor!(eltr(
+///     "A".to_string(),
+///     {pk("B".to_string()), and_v(v:pk("C".to_string()), pk("D".to_string()))}
+/// ))?;
+/// assert_eq!(desc, Descriptor::from_str("eltr(A,{pk(B),and_v(v:pk(C),pk(D))})")?);
+/// # Ok::<(), elements_miniscript::Error>(())
+/// ```
+#[macro_export]
+macro_rules! descriptor {
+    (@ms $($ms:tt)+) => {
+        $crate::fragment!($($ms)+).and_then(|ms| ms.build())
+    };
+    (@tree { $($branches:tt)+ }) => {
+        $crate::descriptor!(@branch [] $($branches)+)
+    };
+    (@tree $($ms:tt)+) => {
+        $crate::descriptor!(@ms $($ms)+)
+            .map(|ms| $crate::descriptor::TapTree::Leaf(::std::sync::Arc::new(ms)))
+    };
+    // Splits the branches of a tree on their comma
+    (@branch [$($left:tt)+] , $($right:tt)+) => {
+        $crate::descriptor!(@tree $($left)+).and_then(|left| {
+            $crate::descriptor!(@tree $($right)+).map(|right| {
+                $crate::descriptor::TapTree::Tree(
+                    ::std::sync::Arc::new(left),
+                    ::std::sync::Arc::new(right),
+                )
+            })
+        })
+    };
+    (@branch [$($left:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::descriptor!(@branch [$($left)* $next] $($rest)*)
+    };
+    (elpkh ( $k:expr )) => {
+        Ok::<_, $crate::Error>($crate::Descriptor::new_pkh($k))
+    };
+    (elwpkh ( $k:expr )) => {
+        $crate::Descriptor::new_wpkh($k)
+    };
+    (elsh ( wpkh ( $k:expr ) )) => {
+        $crate::Descriptor::new_sh_wpkh($k)
+    };
+    (elsh ( wsh ( sortedmulti ( $k:expr $(, $key:expr)+ ) ) )) => {
+        $crate::Descriptor::new_sh_wsh_sortedmulti($k, vec![$($key),+])
+    };
+    (elsh ( wsh ( $($ms:tt)+ ) )) => {
+        $crate::descriptor!(@ms $($ms)+).and_then($crate::Descriptor::new_sh_wsh)
+    };
+    (elsh ( sortedmulti ( $k:expr $(, $key:expr)+ ) )) => {
+        $crate::Descriptor::new_sh_sortedmulti($k, vec![$($key),+])
+    };
+    (elsh ( $($ms:tt)+ )) => {
+        $crate::descriptor!(@ms $($ms)+).and_then($crate::Descriptor::new_sh)
+    };
+    (elwsh ( sortedmulti ( $k:expr $(, $key:expr)+ ) )) => {
+        $crate::Descriptor::new_wsh_sortedmulti($k, vec![$($key),+])
+    };
+    (elwsh ( $($ms:tt)+ )) => {
+        $crate::descriptor!(@ms $($ms)+).and_then($crate::Descriptor::new_wsh)
+    };
+    (elcovwsh ( $k:expr , $($ms:tt)+ )) => {
+        $crate::descriptor!(@ms $($ms)+).and_then(|ms| $crate::Descriptor::new_cov_wsh($k, ms))
+    };
+    (eltr ( $k:expr )) => {
+        $crate::Descriptor::new_tr($k, None)
+    };
+    (eltr ( $k:expr , $($tree:tt)+ )) => {
+        $crate::descriptor!(@tree $($tree)+)
+            .and_then(|tree| $crate::Descriptor::new_tr($k, Some(tree)))
+    };
+}
@@ -0,0 +1,345 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Signature Serialization and Sighashes
+//!
+//! ECDSA signatures as they are placed in witnesses, scriptSigs and the
+//! partial signatures of a PSET, i.e. their DER encoding followed by their
+//! sighash type byte.
+//!
+//! Signatures are parsed either strictly, as policy requires for relay, or
+//! laxly, as found in older transactions, see [`DerMode`].
+//!
+//! Taproot sighashes also commit to the genesis hash of the chain, which
+//! [`TapSighashCache`] takes from a [`Chain`] instead of every signer
+//! hard-coding it.
+//!
+
+use std::borrow::Borrow;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::{error, fmt};
+
+use elements::sighash::{Prevouts, SigHashCache};
+use elements::taproot::{TapLeafHash, TapSighashHash};
+use elements::{
+    secp256k1_zkp, BlockHash, EcdsaSigHashType, SchnorrSigHashType, Transaction, TxOut,
+};
+
+use crate::miniscript::satisfy::ElementsSig;
+
+/// The encodings accepted when parsing a serialized ECDSA signature
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum DerMode {
+    /// Strict DER signatures with a standard sighash type, which are the
+    /// only ones relayed by Elements nodes
+    Strict,
+    /// Any signature accepted by consensus: DER signatures with the parsing
+    /// quirks of OpenSSL, and any sighash byte, interpreted as consensus does
+    Lax,
+}
+
+/// An ECDSA signature with its sighash type
+///
+/// This is the typed version of [`ElementsSig`], which it converts from and
+/// to.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct EcdsaSig {
+    /// The signature
+    pub sig: secp256k1_zkp::ecdsa::Signature,
+    /// The sighash type of the signature
+    pub hash_ty: EcdsaSigHashType,
+}
+
+impl EcdsaSig {
+    /// Creates a signature with the [`EcdsaSigHashType::All`] sighash type
+    pub fn sighash_all(sig: secp256k1_zkp::ecdsa::Signature) -> Self {
+        EcdsaSig {
+            sig,
+            hash_ty: EcdsaSigHashType::All,
+        }
+    }
+
+    /// Serializes the signature as it is placed in a witness
+    pub fn to_vec(&self) -> Vec<u8> {
+        let ser_sig = self.sig.serialize_der();
+        let mut raw_sig = Vec::with_capacity(ser_sig.len() + 1);
+        raw_sig.extend_from_slice(&ser_sig);
+        raw_sig.push(self.hash_ty as u8);
+        raw_sig
+    }
+
+    /// Parses a signature serialized as in a witness, i.e. a DER signature
+    /// followed by its sighash type byte
+    pub fn from_slice(sl: &[u8], mode: DerMode) -> Result<Self, SigError> {
+        let (flag, sig) = sl.split_last().ok_or(SigError::Empty)?;
+        let (sig, hash_ty) = match mode {
+            DerMode::Strict => (
+                secp256k1_zkp::ecdsa::Signature::from_der(sig),
+                EcdsaSigHashType::from_standard(u32::from(*flag))
+                    .map_err(|_| SigError::NonStandardSighash(*flag))?,
+            ),
+            DerMode::Lax => (
+                secp256k1_zkp::ecdsa::Signature::from_der_lax(sig),
+                EcdsaSigHashType::from_u32(u32::from(*flag)),
+            ),
+        };
+        Ok(EcdsaSig {
+            sig: sig.map_err(|e| SigError::Secp(secp256k1_zkp::Error::Upstream(e)))?,
+            hash_ty,
+        })
+    }
+}
+
+impl From<ElementsSig> for EcdsaSig {
+    fn from((sig, hash_ty): ElementsSig) -> Self {
+        EcdsaSig { sig, hash_ty }
+    }
+}
+
+impl From<EcdsaSig> for ElementsSig {
+    fn from(sig: EcdsaSig) -> Self {
+        (sig.sig, sig.hash_ty)
+    }
+}
+
+/// An error parsing a serialized ECDSA signature
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SigError {
+    /// The serialization is empty, with no sighash type byte
+    Empty,
+    /// The sighash type byte is not a standard one
+    NonStandardSighash(u8),
+    /// The signature is not a valid DER signature
+    Secp(secp256k1_zkp::Error),
+}
+
+impl fmt::Display for SigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SigError::Empty => f.write_str("empty signature"),
+            SigError::NonStandardSighash(flag) => {
+                write!(f, "non-standard sighash type {:#04x}", flag)
+            }
+            SigError::Secp(ref e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl error::Error for SigError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            SigError::Empty | SigError::NonStandardSighash(_) => None,
+            SigError::Secp(ref e) => Some(e),
+        }
+    }
+}
+
+/// An Elements chain, identified by its genesis block
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Chain {
+    /// The Liquid network
+    Liquid,
+    /// The Liquid testnet
+    LiquidTestnet,
+    /// The `elementsregtest` chain, with the default chain parameters of
+    /// `elementsd`
+    ElementsRegtest,
+    /// Any other chain, with its genesis hash
+    Custom(BlockHash),
+}
+
+impl Chain {
+    /// The hash of the genesis block of the chain
+    pub fn genesis_hash(&self) -> BlockHash {
+        let hex = match *self {
+            Chain::Liquid => "1466275836220db2944ca059a3a10ef6fd2ea684b0688d2c379296888a206003",
+            Chain::LiquidTestnet => {
+                "a771da8e52ee6ad581ed1e9a99825e5b3b7992225534eaa2ae23244fe26ab1c1"
+            }
+            Chain::ElementsRegtest => {
+                "209577bda6bf4b5804bd46f8621580dd6d4e8bfa2d190e1c50e932492baca07d"
+            }
+            Chain::Custom(hash) => return hash,
+        };
+        BlockHash::from_str(hex).expect("valid genesis hash")
+    }
+}
+
+/// A [`SigHashCache`] computing the taproot sighashes of a given chain
+///
+/// The segwit v0 and legacy sighashes, which do not commit to the chain, are
+/// computed by the wrapped cache, see [`TapSighashCache::inner_mut`].
+#[derive(Debug)]
+pub struct TapSighashCache<T: Deref<Target = Transaction>> {
+    cache: SigHashCache<T>,
+    genesis_hash: BlockHash,
+}
+
+impl<T: Deref<Target = Transaction>> TapSighashCache<T> {
+    /// Creates a cache for the sighashes of `tx` on `chain`
+    pub fn new(tx: T, chain: Chain) -> Self {
+        TapSighashCache {
+            cache: SigHashCache::new(tx),
+            genesis_hash: chain.genesis_hash(),
+        }
+    }
+
+    /// The genesis hash committed to by the taproot sighashes
+    pub fn genesis_hash(&self) -> BlockHash {
+        self.genesis_hash
+    }
+
+    /// The sighash of a key spend of the input at `input_index`
+    pub fn taproot_key_spend_signature_hash<O: Borrow<TxOut>>(
+        &mut self,
+        input_index: usize,
+        prevouts: &Prevouts<O>,
+        sighash_type: SchnorrSigHashType,
+    ) -> Result<TapSighashHash, elements::sighash::Error> {
+        self.cache.taproot_key_spend_signature_hash(
+            input_index,
+            prevouts,
+            sighash_type,
+            self.genesis_hash,
+        )
+    }
+
+    /// The sighash of a script spend of the input at `input_index`, through
+    /// the leaf `leaf_hash`
+    pub fn taproot_script_spend_signature_hash<O: Borrow<TxOut>>(
+        &mut self,
+        input_index: usize,
+        prevouts: &Prevouts<O>,
+        leaf_hash: TapLeafHash,
+        sighash_type: SchnorrSigHashType,
+    ) -> Result<TapSighashHash, elements::sighash::Error> {
+        self.cache.taproot_script_spend_signature_hash(
+            input_index,
+            prevouts,
+            leaf_hash,
+            sighash_type,
+            self.genesis_hash,
+        )
+    }
+
+    /// The wrapped cache
+    pub fn inner_mut(&mut self) -> &mut SigHashCache<T> {
+        &mut self.cache
+    }
+
+    /// Returns the wrapped cache
+    pub fn into_inner(self) -> SigHashCache<T> {
+        self.cache
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ecdsa_sig_serialization() {
+        let sig = secp256k1_zkp::ecdsa::Signature::from_compact(&[0x42; 64]).unwrap();
+        let ecdsa_sig = EcdsaSig {
+            sig,
+            hash_ty: EcdsaSigHashType::SinglePlusAnyoneCanPay,
+        };
+        let ser = ecdsa_sig.to_vec();
+        assert_eq!(*ser.last().unwrap(), 0x83);
+        for mode in [DerMode::Strict, DerMode::Lax].iter() {
+            assert_eq!(EcdsaSig::from_slice(&ser, *mode), Ok(ecdsa_sig));
+        }
+        let elements_sig: ElementsSig = ecdsa_sig.into();
+        assert_eq!(crate::elementssig_to_rawsig(&elements_sig), ser);
+        assert_eq!(crate::elementssig_from_rawsig(&ser).unwrap(), elements_sig);
+
+        assert_eq!(
+            EcdsaSig::from_slice(&[], DerMode::Lax),
+            Err(SigError::Empty)
+        );
+        assert!(crate::elementssig_from_rawsig(&[]).is_err());
+
+        // A non-standard sighash byte is only accepted by lax parsing
+        let mut non_standard = ser.clone();
+        *non_standard.last_mut().unwrap() = 0x84;
+        assert_eq!(
+            EcdsaSig::from_slice(&non_standard, DerMode::Strict),
+            Err(SigError::NonStandardSighash(0x84))
+        );
+        assert_eq!(
+            EcdsaSig::from_slice(&non_standard, DerMode::Lax)
+                .unwrap()
+                .hash_ty,
+            EcdsaSigHashType::AllPlusAnyoneCanPay
+        );
+
+        // A DER signature with a padded r value is only accepted by lax
+        // parsing
+        let mut padded = ser[..ser.len() - 1].to_vec();
+        padded[1] += 1;
+        padded[3] += 1;
+        padded.insert(4, 0);
+        padded.push(0x01);
+        assert!(EcdsaSig::from_slice(&padded, DerMode::Strict).is_err());
+        assert_eq!(
+            EcdsaSig::from_slice(&padded, DerMode::Lax),
+            Ok(EcdsaSig::sighash_all(sig))
+        );
+    }
+    #[test]
+    fn tap_sighash_cache() {
+        let tx = Transaction {
+            version: 2,
+            lock_time: elements::LockTime::ZERO,
+            input: vec![elements::TxIn::default()],
+            output: vec![TxOut::default()],
+        };
+        let utxos = [TxOut::default()];
+        let prevouts = Prevouts::All(&utxos);
+        let leaf_hash = TapLeafHash::from_script(
+            &elements::Script::new(),
+            elements::taproot::LeafVersion::default(),
+        );
+
+        let mut sighashes = vec![];
+        for chain in [Chain::Liquid, Chain::LiquidTestnet, Chain::ElementsRegtest].iter() {
+            let mut cache = TapSighashCache::new(&tx, *chain);
+            let mut expected = SigHashCache::new(&tx);
+            let key_spend = cache
+                .taproot_key_spend_signature_hash(0, &prevouts, SchnorrSigHashType::Default)
+                .unwrap();
+            assert_eq!(
+                key_spend,
+                expected
+                    .taproot_key_spend_signature_hash(
+                        0,
+                        &prevouts,
+                        SchnorrSigHashType::Default,
+                        chain.genesis_hash(),
+                    )
+                    .unwrap()
+            );
+            let script_spend = cache
+                .taproot_script_spend_signature_hash(
+                    0,
+                    &prevouts,
+                    leaf_hash,
+                    SchnorrSigHashType::All,
+                )
+                .unwrap();
+            assert_ne!(key_spend, script_spend);
+            sighashes.push(key_spend);
+        }
+        // The sighashes of each chain differ
+        sighashes.sort();
+        sighashes.dedup();
+        assert_eq!(sighashes.len(), 3);
+
+        let genesis_hash = Chain::Liquid.genesis_hash();
+        assert_eq!(Chain::Custom(genesis_hash).genesis_hash(), genesis_hash);
+        assert_eq!(
+            TapSighashCache::new(&tx, Chain::Custom(genesis_hash)).genesis_hash(),
+            genesis_hash
+        );
+    }
+}
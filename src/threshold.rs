@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: CC0-1.0
+
+//! # Thresholds
+//!
+//! A threshold `k` over `n` items, as used by the `thresh`, `multi` and
+//! `multi_a` fragments, with `0 < k <= n` checked at construction, so that
+//! an invalid threshold cannot be built, e.g. by constructing a
+//! [`crate::Terminal`] directly.
+//!
+
+use std::{error, fmt, slice, vec};
+
+/// A threshold of `k` out of the `n` items it contains, with `0 < k <= n`
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Threshold<T> {
+    k: usize,
+    inner: Vec<T>,
+}
+
+/// Error when creating a [`Threshold`] with `k = 0` or `k > n`
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ThresholdError {
+    /// The threshold
+    pub k: usize,
+    /// The number of items
+    pub n: usize,
+}
+
+impl fmt::Display for ThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.k == 0 {
+            f.write_str("threshold k must be greater than 0")
+        } else {
+            write!(
+                f,
+                "threshold k={} is higher than the number of items n={}",
+                self.k, self.n
+            )
+        }
+    }
+}
+
+impl error::Error for ThresholdError {}
+
+impl<T> Threshold<T> {
+    /// Creates a threshold of `k` out of `inner`
+    ///
+    /// # Errors
+    ///
+    /// If `k` is 0 or greater than the number of items.
+    pub fn new(k: usize, inner: Vec<T>) -> Result<Self, ThresholdError> {
+        if k == 0 || k > inner.len() {
+            Err(ThresholdError { k, n: inner.len() })
+        } else {
+            Ok(Threshold { k, inner })
+        }
+    }
+
+    /// Creates a `1` out of `n` threshold, i.e. a disjunction
+    pub fn or(inner: Vec<T>) -> Result<Self, ThresholdError> {
+        Threshold::new(1, inner)
+    }
+
+    /// Creates a `n` out of `n` threshold, i.e. a conjunction
+    pub fn and(inner: Vec<T>) -> Result<Self, ThresholdError> {
+        Threshold::new(inner.len(), inner)
+    }
+
+    /// Creates the conjunction of two items, i.e. a `2` out of `2` threshold
+    pub fn both(left: T, right: T) -> Self {
+        Threshold {
+            k: 2,
+            inner: vec![left, right],
+        }
+    }
+
+    /// Creates the disjunction of two items, i.e. a `1` out of `2` threshold
+    pub fn either(left: T, right: T) -> Self {
+        Threshold {
+            k: 1,
+            inner: vec![left, right],
+        }
+    }
+
+    /// The threshold `k`
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The number of items `n`
+    pub fn n(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Whether the threshold is a disjunction, i.e. `k = 1`
+    pub fn is_or(&self) -> bool {
+        self.k == 1
+    }
+
+    /// Whether the threshold is a conjunction, i.e. `k = n`
+    pub fn is_and(&self) -> bool {
+        self.k == self.inner.len()
+    }
+
+    /// The items of the threshold
+    pub fn data(&self) -> &[T] {
+        &self.inner
+    }
+
+    /// Iterates over the items of the threshold
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.inner.iter()
+    }
+
+    /// Returns the items of the threshold
+    pub fn into_data(self) -> Vec<T> {
+        self.inner
+    }
+
+    /// Maps every item of the threshold, keeping `k`
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Threshold<U> {
+        Threshold {
+            k: self.k,
+            inner: self.inner.into_iter().map(f).collect(),
+        }
+    }
+
+    /// Maps every item of the threshold by reference, keeping `k`
+    pub fn map_ref<U, F: FnMut(&T) -> U>(&self, f: F) -> Threshold<U> {
+        Threshold {
+            k: self.k,
+            inner: self.inner.iter().map(f).collect(),
+        }
+    }
+
+    /// Maps every item of the threshold by reference with a fallible
+    /// function, keeping `k`
+    pub fn translate_ref<U, E, F: FnMut(&T) -> Result<U, E>>(
+        &self,
+        f: F,
+    ) -> Result<Threshold<U>, E> {
+        Ok(Threshold {
+            k: self.k,
+            inner: self.inner.iter().map(f).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Threshold<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
+impl<T> IntoIterator for Threshold<T> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold() {
+        let thresh = Threshold::new(2, vec![1, 2, 3]).unwrap();
+        assert_eq!((thresh.k(), thresh.n()), (2, 3));
+        assert!(!thresh.is_or() && !thresh.is_and());
+        assert_eq!(thresh.map_ref(|x| x * 2).data(), &[2, 4, 6]);
+        assert_eq!(
+            thresh.translate_ref(|&x| if x < 3 { Ok(x) } else { Err(x) }),
+            Err(3)
+        );
+        assert!(Threshold::and(vec![1, 2]).unwrap().is_and());
+        assert!(Threshold::or(vec![1, 2]).unwrap().is_or());
+        assert_eq!(Threshold::both(1, 2), Threshold::and(vec![1, 2]).unwrap());
+        assert_eq!(Threshold::either(1, 2), Threshold::or(vec![1, 2]).unwrap());
+
+        assert_eq!(
+            Threshold::new(0, vec![1]),
+            Err(ThresholdError { k: 0, n: 1 })
+        );
+        assert_eq!(
+            Threshold::new(3, vec![1, 2]),
+            Err(ThresholdError { k: 3, n: 2 })
+        );
+        assert_eq!(
+            Threshold::<u32>::or(vec![]),
+            Err(ThresholdError { k: 1, n: 0 })
+        );
+    }
+}